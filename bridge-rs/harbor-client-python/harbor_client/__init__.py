@@ -0,0 +1,27 @@
+"""Python client for the Harbor bridge's local control API."""
+
+from .client import (
+    DEFAULT_URL,
+    BridgeRejected,
+    BridgeUnavailable,
+    Client,
+    Event,
+    HarborError,
+    OAuthStatus,
+    ServerInfo,
+    Tool,
+    ToolResult,
+)
+
+__all__ = [
+    "DEFAULT_URL",
+    "BridgeRejected",
+    "BridgeUnavailable",
+    "Client",
+    "Event",
+    "HarborError",
+    "OAuthStatus",
+    "ServerInfo",
+    "Tool",
+    "ToolResult",
+]
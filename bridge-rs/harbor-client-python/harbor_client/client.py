@@ -0,0 +1,237 @@
+"""Client for the Harbor bridge's local control API.
+
+The bridge serves JSON-RPC on ``http://127.0.0.1:8766`` (``POST /rpc``) and
+pushes events over a WebSocket (``/ws``). Requests carry the bridge token
+from ``~/.harbor/bridge-token`` and count as a local program's rather than
+the extension's, so methods only the extension may call (installing
+servers, granting access, changing settings) are refused.
+"""
+
+import itertools
+import json
+import os
+import threading
+import urllib.error
+import urllib.request
+from dataclasses import dataclass, field
+from typing import Any, Dict, Iterator, List, Optional
+
+DEFAULT_URL = "http://127.0.0.1:8766"
+TOKEN_PATH = os.path.join("~", ".harbor", "bridge-token")
+
+
+def read_token() -> Optional[str]:
+    """The token of the bridge running as the current user, if it has made one."""
+    try:
+        with open(os.path.expanduser(TOKEN_PATH)) as f:
+            return f.read().strip() or None
+    except OSError:
+        return None
+
+
+class HarborError(Exception):
+    """An error answered by the bridge, with its JSON-RPC code."""
+
+    def __init__(self, code: int, message: str):
+        super().__init__(f"{message} ({code})")
+        self.code = code
+        self.message = message
+
+
+class BridgeUnavailable(Exception):
+    """The bridge couldn't be reached."""
+
+
+class BridgeRejected(Exception):
+    """The bridge answered with an HTTP error, e.g. 401 for a missing or
+    wrong token."""
+
+    def __init__(self, status: int, message: str):
+        super().__init__(f"{message} (HTTP {status})")
+        self.status = status
+        self.message = message
+
+
+@dataclass
+class Tool:
+    server_id: str
+    name: str
+    description: Optional[str] = None
+    input_schema: Optional[Dict[str, Any]] = None
+
+    @classmethod
+    def from_json(cls, data: Dict[str, Any]) -> "Tool":
+        return cls(
+            server_id=data.get("serverId") or data.get("server_id", ""),
+            name=data["name"],
+            description=data.get("description"),
+            input_schema=data.get("inputSchema") or data.get("input_schema"),
+        )
+
+
+@dataclass
+class ToolResult:
+    """The result of a tool call: its first text block, or the raw content."""
+
+    result: Any
+    meta: Dict[str, Any] = field(default_factory=dict)
+
+    @property
+    def text(self) -> Optional[str]:
+        return self.result if isinstance(self.result, str) else None
+
+    def json(self) -> Any:
+        """The result, parsed first if the tool returned JSON as text."""
+        if isinstance(self.result, str):
+            return json.loads(self.result)
+        return self.result
+
+
+@dataclass
+class ServerInfo:
+    id: str
+    running: bool
+    paused: bool = False
+
+
+@dataclass
+class OAuthStatus:
+    authenticated: bool
+    server_id: Optional[str] = None
+    provider: Optional[str] = None
+    scopes: List[str] = field(default_factory=list)
+    is_expired: bool = False
+    expires_at: Optional[int] = None
+    has_refresh_token: bool = False
+
+    @classmethod
+    def from_json(cls, data: Dict[str, Any]) -> "OAuthStatus":
+        return cls(
+            authenticated=data.get("authenticated", False),
+            server_id=data.get("server_id"),
+            provider=data.get("provider"),
+            scopes=data.get("scopes") or [],
+            is_expired=data.get("is_expired", False),
+            expires_at=data.get("expires_at"),
+            has_refresh_token=data.get("has_refresh_token", False),
+        )
+
+
+@dataclass
+class Event:
+    """An event pushed by the bridge, e.g. ``outbox/delivered``."""
+
+    event: str
+    payload: Any = None
+
+
+class Client:
+    """A connection to a running bridge. Safe to share between threads."""
+
+    def __init__(self, url: str = DEFAULT_URL, timeout: float = 60.0, token: Optional[str] = None):
+        self.url = url.rstrip("/")
+        self.timeout = timeout
+        self.token = token or read_token()
+        self._ids = itertools.count(1)
+        self._lock = threading.Lock()
+
+    def _request(self, path: str, body: Optional[bytes] = None) -> Any:
+        headers = {"Content-Type": "application/json"} if body is not None else {}
+        if self.token:
+            headers["Authorization"] = f"Bearer {self.token}"
+        request = urllib.request.Request(self.url + path, data=body, headers=headers)
+        try:
+            with urllib.request.urlopen(request, timeout=self.timeout) as response:
+                return json.load(response)
+        except urllib.error.HTTPError as e:
+            # The bridge is up and refused the request; HTTPError is also a URLError
+            if e.code == 401:
+                raise BridgeRejected(e.code, f"The bridge refused the token; check {TOKEN_PATH}") from e
+            raise BridgeRejected(e.code, f"Bridge request to {self.url} failed: {e.reason}") from e
+        except urllib.error.URLError as e:
+            raise BridgeUnavailable(f"Bridge request to {self.url} failed: {e}") from e
+
+    def call(self, method: str, params: Optional[Dict[str, Any]] = None) -> Any:
+        """Call any RPC method, e.g. ``client.call("fs.list", {"path": ""})``."""
+        with self._lock:
+            request_id = next(self._ids)
+        body = json.dumps({"id": request_id, "method": method, "params": params or {}}).encode()
+        response = self._request("/rpc", body)
+        error = response.get("error")
+        if error:
+            raise HarborError(error.get("code", -32603), error.get("message", "Unknown error"))
+        return response.get("result")
+
+    def health(self) -> bool:
+        """Whether the bridge is up."""
+        try:
+            return self._request("/health").get("status") == "ok"
+        except BridgeUnavailable:
+            return False
+
+    # Tools
+
+    def list_tools(self) -> List[Tool]:
+        """Every tool the bridge knows of, across servers."""
+        return [Tool.from_json(t) for t in self.call("mcp.list_tools")["tools"]]
+
+    def call_tool(self, server_id: str, tool: str, args: Optional[Dict[str, Any]] = None) -> ToolResult:
+        """Call a tool on a server."""
+        result = self.call("mcp.call_tool", {"serverId": server_id, "toolName": tool, "args": args or {}})
+        return ToolResult(result=result.get("result"), meta=result.get("_meta") or {})
+
+    # Servers
+
+    def list_servers(self) -> List[ServerInfo]:
+        """The JS MCP servers running in the bridge."""
+        return [
+            ServerInfo(id=s["id"], running=s["running"], paused=s.get("paused", False))
+            for s in self.call("js.list_servers")["servers"]
+        ]
+
+    def stop_server(self, server_id: str) -> None:
+        """Stop a running JS server."""
+        self.call("js.stop_server", {"id": server_id})
+
+    # OAuth
+
+    def oauth_status(self, server_id: str) -> OAuthStatus:
+        """A server's OAuth token status."""
+        status = OAuthStatus.from_json(self.call("oauth.status", {"server_id": server_id}))
+        status.server_id = server_id
+        return status
+
+    def list_authenticated(self) -> List[OAuthStatus]:
+        """Every server with stored OAuth tokens."""
+        return [OAuthStatus.from_json(s) for s in self.call("oauth.list_authenticated")["servers"]]
+
+    # Events
+
+    def events(self) -> Iterator[Event]:
+        """Yield the bridge's events as they arrive, until the connection
+        closes. Needs the ``events`` extra (``websocket-client``)."""
+        try:
+            import websocket
+        except ImportError as e:
+            raise ImportError("Client.events() needs websocket-client: pip install 'harbor-client[events]'") from e
+
+        ws_url = "ws" + self.url[len("http"):] + "/ws"
+        if self.token:
+            ws_url += "?token=" + self.token
+        try:
+            socket = websocket.create_connection(ws_url, timeout=None)
+        except (OSError, websocket.WebSocketException) as e:
+            raise BridgeUnavailable(f"Bridge event stream at {ws_url} failed: {e}") from e
+        try:
+            while True:
+                try:
+                    text = socket.recv()
+                except websocket.WebSocketConnectionClosedException:
+                    return
+                if not text:
+                    return
+                message = json.loads(text)
+                if message.get("type") == "event":
+                    yield Event(event=message["event"], payload=message.get("payload"))
+        finally:
+            socket.close()
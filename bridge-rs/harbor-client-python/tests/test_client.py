@@ -0,0 +1,74 @@
+import json
+import threading
+import unittest
+from http.server import BaseHTTPRequestHandler, ThreadingHTTPServer
+
+from harbor_client import BridgeRejected, BridgeUnavailable, Client, HarborError
+
+TOKEN = "0123abcd"
+
+
+class FakeBridge(BaseHTTPRequestHandler):
+    """Answers /rpc like the bridge: `echo` returns its params, anything else
+    is an unknown method, and a wrong token gets 401."""
+
+    def log_message(self, *args):
+        pass
+
+    def _send(self, status, body):
+        data = json.dumps(body).encode()
+        self.send_response(status)
+        self.send_header("Content-Type", "application/json")
+        self.send_header("Content-Length", str(len(data)))
+        self.end_headers()
+        self.wfile.write(data)
+
+    def do_GET(self):
+        self._send(200, {"status": "ok"})
+
+    def do_POST(self):
+        request = json.loads(self.rfile.read(int(self.headers["Content-Length"])))
+        if self.headers.get("Authorization") != f"Bearer {TOKEN}":
+            self._send(401, {})
+        elif request["method"] == "echo":
+            self._send(200, {"id": request["id"], "result": request["params"]})
+        else:
+            self._send(200, {"id": request["id"], "error": {"code": -32601, "message": "Method not found"}})
+
+
+class ClientTest(unittest.TestCase):
+    @classmethod
+    def setUpClass(cls):
+        cls.server = ThreadingHTTPServer(("127.0.0.1", 0), FakeBridge)
+        cls.url = f"http://127.0.0.1:{cls.server.server_address[1]}"
+        threading.Thread(target=cls.server.serve_forever, daemon=True).start()
+
+    @classmethod
+    def tearDownClass(cls):
+        cls.server.shutdown()
+        cls.server.server_close()
+
+    def test_call_sends_token(self):
+        client = Client(self.url, token=TOKEN)
+        self.assertEqual(client.call("echo", {"a": 1}), {"a": 1})
+        self.assertTrue(client.health())
+
+    def test_rpc_error(self):
+        with self.assertRaises(HarborError) as raised:
+            Client(self.url, token=TOKEN).call("nope")
+        self.assertEqual(raised.exception.code, -32601)
+
+    def test_wrong_token_is_rejected_not_unavailable(self):
+        with self.assertRaises(BridgeRejected) as raised:
+            Client(self.url, token="wrong").call("echo")
+        self.assertEqual(raised.exception.status, 401)
+
+    def test_unreachable_bridge(self):
+        client = Client("http://127.0.0.1:1", token=TOKEN, timeout=2)
+        with self.assertRaises(BridgeUnavailable):
+            client.call("echo")
+        self.assertFalse(client.health())
+
+
+if __name__ == "__main__":
+    unittest.main()
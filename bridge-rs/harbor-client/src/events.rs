@@ -0,0 +1,50 @@
+//! The bridge's event stream, read over its WebSocket.
+
+use futures_util::StreamExt;
+use serde::Deserialize;
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::{Error, Event};
+
+type Socket = tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>;
+
+/// Messages on the WebSocket; only events are of interest here.
+#[derive(Deserialize)]
+#[serde(tag = "type")]
+enum WsMessage {
+    #[serde(rename = "event")]
+    Event(Event),
+    #[serde(other)]
+    Other,
+}
+
+/// Events from the bridge, in the order they were emitted. Events emitted
+/// while the stream is not being read may be dropped if it falls far behind.
+pub struct EventStream {
+    socket: Socket,
+}
+
+impl EventStream {
+    pub(crate) async fn connect(url: &str) -> Result<Self, Error> {
+        let (socket, _) = tokio_tungstenite::connect_async(url).await.map_err(Error::WebSocket)?;
+        Ok(Self { socket })
+    }
+
+    /// The next event, or `None` once the bridge closes the connection.
+    pub async fn next(&mut self) -> Option<Result<Event, Error>> {
+        while let Some(message) = self.socket.next().await {
+            let text = match message {
+                Ok(Message::Text(text)) => text,
+                Ok(Message::Close(_)) => return None,
+                Ok(_) => continue,
+                Err(e) => return Some(Err(Error::WebSocket(e))),
+            };
+            match serde_json::from_str::<WsMessage>(&text) {
+                Ok(WsMessage::Event(event)) => return Some(Ok(event)),
+                Ok(WsMessage::Other) => continue,
+                Err(e) => return Some(Err(Error::Decode(e.to_string()))),
+            }
+        }
+        None
+    }
+}
@@ -0,0 +1,299 @@
+//! Typed client for the Harbor bridge's local control API.
+//!
+//! The bridge serves JSON-RPC on `http://127.0.0.1:8766` (`POST /rpc`) and
+//! pushes events over a WebSocket (`/ws`). `Client` wraps both, with typed
+//! methods for the common calls and `call` for everything else:
+//!
+//! ```no_run
+//! # async fn demo() -> Result<(), harbor_client::Error> {
+//! let client = harbor_client::Client::new();
+//! for tool in client.list_tools().await? {
+//!     println!("{}/{}", tool.server_id, tool.name);
+//! }
+//! let result = client
+//!     .call_tool("github", "search_issues", serde_json::json!({ "q": "is:open" }))
+//!     .await?;
+//! println!("{}", result.text().unwrap_or_default());
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Requests carry the bridge token from `~/.harbor/bridge-token`, which
+//! `Client::new` reads. The bridge treats them as a local program's rather
+//! than the extension's, so methods only the extension may call (installing
+//! servers, granting access, changing settings) are refused.
+
+mod events;
+mod types;
+
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+pub use events::EventStream;
+pub use types::{Event, OAuthStatus, ServerInfo, Tool, ToolResult};
+
+/// Where the bridge listens by default.
+pub const DEFAULT_URL: &str = "http://127.0.0.1:8766";
+
+/// The bridge token's file in `~/.harbor`.
+pub const TOKEN_FILE_NAME: &str = "bridge-token";
+
+/// The token of the bridge running as the current user, if it has made one.
+pub fn read_token() -> Option<String> {
+    let path = dirs::home_dir()?.join(".harbor").join(TOKEN_FILE_NAME);
+    let token = std::fs::read_to_string(path).ok()?;
+    Some(token.trim().to_string()).filter(|token| !token.is_empty())
+}
+
+#[derive(Debug)]
+pub enum Error {
+    /// The bridge couldn't be reached, or the HTTP request failed
+    Http(reqwest::Error),
+    /// The event WebSocket failed
+    WebSocket(tokio_tungstenite::tungstenite::Error),
+    /// The bridge answered with a JSON-RPC error
+    Rpc { code: i64, message: String },
+    /// A response didn't have the expected shape
+    Decode(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Http(e) => write!(f, "Bridge request failed: {}", e),
+            Error::WebSocket(e) => write!(f, "Bridge event stream failed: {}", e),
+            Error::Rpc { code, message } => write!(f, "{} ({})", message, code),
+            Error::Decode(e) => write!(f, "Unexpected response from the bridge: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        Error::Http(e)
+    }
+}
+
+#[derive(Serialize)]
+struct Request<'a, P> {
+    id: u64,
+    method: &'a str,
+    params: P,
+}
+
+#[derive(Deserialize)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct Response {
+    #[serde(default)]
+    result: Option<serde_json::Value>,
+    #[serde(default)]
+    error: Option<RpcError>,
+}
+
+impl Response {
+    fn decode<T: DeserializeOwned>(self) -> Result<T, Error> {
+        if let Some(error) = self.error {
+            return Err(Error::Rpc {
+                code: error.code,
+                message: error.message,
+            });
+        }
+        serde_json::from_value(self.result.unwrap_or(serde_json::Value::Null)).map_err(|e| Error::Decode(e.to_string()))
+    }
+}
+
+/// Pull one field out of a result object, e.g. `{ "tools": [...] }`.
+#[derive(Deserialize)]
+struct Field<T> {
+    #[serde(alias = "tools", alias = "servers")]
+    items: T,
+}
+
+/// A connection to a running bridge. Cheap to clone.
+#[derive(Clone)]
+pub struct Client {
+    base_url: String,
+    http: reqwest::Client,
+    token: Option<String>,
+    next_id: Arc<AtomicU64>,
+}
+
+impl Default for Client {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Client {
+    /// A client for the bridge at its default address.
+    pub fn new() -> Self {
+        Self::with_url(DEFAULT_URL)
+    }
+
+    /// A client for the bridge at `url`, e.g. `http://127.0.0.1:9000`.
+    pub fn with_url(url: impl Into<String>) -> Self {
+        Self::with_http_client(url, reqwest::Client::new())
+    }
+
+    /// Like `with_url`, using a configured `reqwest::Client` (for timeouts
+    /// and the like).
+    pub fn with_http_client(url: impl Into<String>, http: reqwest::Client) -> Self {
+        Self {
+            base_url: url.into().trim_end_matches('/').to_string(),
+            http,
+            token: read_token(),
+            next_id: Arc::new(AtomicU64::new(1)),
+        }
+    }
+
+    /// Use `token` rather than the one in `~/.harbor/bridge-token`, e.g.
+    /// for a bridge run as another user.
+    pub fn with_token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    /// Call any RPC method, e.g. `client.call("fs.list", json!({ "path": "" }))`.
+    pub async fn call<T: DeserializeOwned>(&self, method: &str, params: impl Serialize) -> Result<T, Error> {
+        let request = Request {
+            id: self.next_id.fetch_add(1, Ordering::Relaxed),
+            method,
+            params,
+        };
+        let mut post = self.http.post(format!("{}/rpc", self.base_url)).json(&request);
+        if let Some(token) = &self.token {
+            post = post.bearer_auth(token);
+        }
+        let response: Response = post
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        response.decode()
+    }
+
+    /// Whether the bridge is up.
+    pub async fn health(&self) -> Result<bool, Error> {
+        let status: serde_json::Value = self
+            .http
+            .get(format!("{}/health", self.base_url))
+            .send()
+            .await?
+            .json()
+            .await?;
+        Ok(status.get("status").and_then(|s| s.as_str()) == Some("ok"))
+    }
+
+    // ========================================================================
+    // Tools
+    // ========================================================================
+
+    /// Every tool the bridge knows of, across servers.
+    pub async fn list_tools(&self) -> Result<Vec<Tool>, Error> {
+        let tools: Field<Vec<Tool>> = self.call("mcp.list_tools", serde_json::json!({})).await?;
+        Ok(tools.items)
+    }
+
+    /// Call a tool on a server.
+    pub async fn call_tool(&self, server_id: &str, tool: &str, args: impl Serialize) -> Result<ToolResult, Error> {
+        let args = serde_json::to_value(args).map_err(|e| Error::Decode(e.to_string()))?;
+        self.call(
+            "mcp.call_tool",
+            serde_json::json!({ "serverId": server_id, "toolName": tool, "args": args }),
+        )
+        .await
+    }
+
+    // ========================================================================
+    // Servers
+    // ========================================================================
+
+    /// The JS MCP servers running in the bridge.
+    pub async fn list_servers(&self) -> Result<Vec<ServerInfo>, Error> {
+        let servers: Field<Vec<ServerInfo>> = self.call("js.list_servers", serde_json::json!({})).await?;
+        Ok(servers.items)
+    }
+
+    /// Stop a running JS server.
+    pub async fn stop_server(&self, server_id: &str) -> Result<(), Error> {
+        self.call::<serde_json::Value>("js.stop_server", serde_json::json!({ "id": server_id }))
+            .await
+            .map(|_| ())
+    }
+
+    // ========================================================================
+    // OAuth
+    // ========================================================================
+
+    /// A server's OAuth token status.
+    pub async fn oauth_status(&self, server_id: &str) -> Result<OAuthStatus, Error> {
+        self.call("oauth.status", serde_json::json!({ "server_id": server_id }))
+            .await
+    }
+
+    /// Every server with stored OAuth tokens.
+    pub async fn list_authenticated(&self) -> Result<Vec<OAuthStatus>, Error> {
+        let servers: Field<Vec<OAuthStatus>> = self.call("oauth.list_authenticated", serde_json::json!({})).await?;
+        Ok(servers.items)
+    }
+
+    // ========================================================================
+    // Events
+    // ========================================================================
+
+    /// Subscribe to the bridge's events.
+    pub async fn events(&self) -> Result<EventStream, Error> {
+        let mut url = match self.base_url.strip_prefix("https://") {
+            Some(rest) => format!("wss://{}/ws", rest),
+            None => format!("ws://{}/ws", self.base_url.trim_start_matches("http://")),
+        };
+        // The token is hex, so it needs no escaping
+        if let Some(token) = &self.token {
+            url = format!("{}?token={}", url, token);
+        }
+        EventStream::connect(&url).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_responses() {
+        let ok: Response = serde_json::from_value(serde_json::json!({
+            "id": 1,
+            "result": { "tools": [{ "serverId": "github", "name": "search_issues" }] },
+        }))
+        .unwrap();
+        let tools: Field<Vec<Tool>> = ok.decode().unwrap();
+        assert_eq!(tools.items[0].server_id, "github");
+
+        let failed: Response = serde_json::from_value(serde_json::json!({
+            "id": 2,
+            "error": { "code": -32010, "message": "Budget exceeded" },
+        }))
+        .unwrap();
+        match failed.decode::<serde_json::Value>() {
+            Err(Error::Rpc { code, .. }) => assert_eq!(code, -32010),
+            other => panic!("expected an RPC error, got {:?}", other),
+        }
+
+        let result = ToolResult {
+            result: serde_json::json!("{\"count\": 3}"),
+            meta: serde_json::Value::Null,
+        };
+        assert_eq!(result.json::<serde_json::Value>().unwrap()["count"], 3);
+    }
+}
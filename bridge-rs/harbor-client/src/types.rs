@@ -0,0 +1,85 @@
+//! Response types for the typed methods on `Client`.
+
+use serde::{de::DeserializeOwned, Deserialize};
+
+use crate::Error;
+
+/// A tool published by an MCP server (or a composite tool).
+#[derive(Debug, Clone, Deserialize)]
+pub struct Tool {
+    #[serde(rename = "serverId", alias = "server_id")]
+    pub server_id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default, rename = "inputSchema", alias = "input_schema")]
+    pub input_schema: Option<serde_json::Value>,
+}
+
+/// The result of `mcp.call_tool`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ToolResult {
+    /// The tool's content: its first text block, or the raw content
+    #[serde(default)]
+    pub result: serde_json::Value,
+    /// Timings and other details reported by the bridge
+    #[serde(default, rename = "_meta")]
+    pub meta: serde_json::Value,
+}
+
+impl ToolResult {
+    /// The result as `T`. Tools usually return JSON as text, which is
+    /// parsed first.
+    pub fn json<T: DeserializeOwned>(&self) -> Result<T, Error> {
+        let value = match &self.result {
+            serde_json::Value::String(text) => serde_json::from_str(text).map_err(|e| Error::Decode(e.to_string()))?,
+            other => other.clone(),
+        };
+        serde_json::from_value(value).map_err(|e| Error::Decode(e.to_string()))
+    }
+
+    /// The result as text, if it is a string.
+    pub fn text(&self) -> Option<&str> {
+        self.result.as_str()
+    }
+}
+
+/// A JS MCP server running in the bridge.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ServerInfo {
+    pub id: String,
+    pub running: bool,
+    /// Running, but not taking tool calls
+    #[serde(default)]
+    pub paused: bool,
+}
+
+/// A server's OAuth token status.
+#[derive(Debug, Clone, Deserialize)]
+pub struct OAuthStatus {
+    /// Set when listing every authenticated server
+    #[serde(default)]
+    pub server_id: Option<String>,
+    pub authenticated: bool,
+    #[serde(default)]
+    pub provider: Option<String>,
+    #[serde(default)]
+    pub scopes: Vec<String>,
+    /// Expired, or expiring within a minute
+    #[serde(default)]
+    pub is_expired: bool,
+    /// Milliseconds since the epoch
+    #[serde(default)]
+    pub expires_at: Option<i64>,
+    #[serde(default)]
+    pub has_refresh_token: bool,
+}
+
+/// An event pushed by the bridge, e.g. `outbox/delivered`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Event {
+    /// Event name, `<subsystem>/<event>`
+    pub event: String,
+    #[serde(default)]
+    pub payload: serde_json::Value,
+}
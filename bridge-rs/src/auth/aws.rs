@@ -0,0 +1,508 @@
+//! AWS credentials from shared profiles, with STS role assumption.
+//!
+//! Profiles are read from `~/.aws/config` and `~/.aws/credentials` (or
+//! `$AWS_CONFIG_FILE` / `$AWS_SHARED_CREDENTIALS_FILE`). A profile with a
+//! `role_arn` is resolved by calling STS AssumeRole with the credentials of
+//! its `source_profile`. If the profile has an `mfa_serial`, the bridge asks
+//! the extension's permission broker for a code by emitting
+//! `aws/mfa_required` and waits for `aws.submit_mfa`. Session credentials
+//! are cached in memory until shortly before they expire.
+
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::Serialize;
+use tokio::sync::{oneshot, Mutex, RwLock};
+
+use super::sigv4::{self, AwsCredentials};
+use super::HttpRequest;
+use crate::events;
+use crate::redact::redact;
+use crate::rpc::RpcError;
+
+const STS_VERSION: &str = "2011-06-15";
+
+/// Refresh sessions this long before they expire.
+const REFRESH_MARGIN_MS: i64 = 5 * 60 * 1000;
+
+/// How long to wait for the user to enter an MFA code.
+const MFA_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Limit on `source_profile` chains, which may also be cyclic.
+const MAX_SOURCE_DEPTH: usize = 4;
+
+/// A named profile from the shared AWS files.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Profile {
+    pub name: String,
+    #[serde(skip)]
+    access_key_id: Option<String>,
+    #[serde(skip)]
+    secret_access_key: Option<String>,
+    #[serde(skip)]
+    session_token: Option<String>,
+    pub region: Option<String>,
+    pub role_arn: Option<String>,
+    pub source_profile: Option<String>,
+    pub mfa_serial: Option<String>,
+    pub external_id: Option<String>,
+    pub role_session_name: Option<String>,
+    pub duration_seconds: Option<u32>,
+}
+
+impl Profile {
+    fn set(&mut self, key: &str, value: String) {
+        match key {
+            "aws_access_key_id" => self.access_key_id = Some(value),
+            "aws_secret_access_key" => self.secret_access_key = Some(value),
+            "aws_session_token" => self.session_token = Some(value),
+            "region" => self.region = Some(value),
+            "role_arn" => self.role_arn = Some(value),
+            "source_profile" => self.source_profile = Some(value),
+            "mfa_serial" => self.mfa_serial = Some(value),
+            "external_id" => self.external_id = Some(value),
+            "role_session_name" => self.role_session_name = Some(value),
+            "duration_seconds" => self.duration_seconds = value.parse().ok(),
+            _ => {}
+        }
+    }
+
+    /// Long-term (or pre-issued session) keys stored in the profile itself.
+    fn static_credentials(&self) -> Option<AwsCredentials> {
+        Some(AwsCredentials {
+            access_key_id: self.access_key_id.clone()?,
+            secret_access_key: self.secret_access_key.clone()?,
+            session_token: self.session_token.clone(),
+        })
+    }
+}
+
+/// Parse an INI file into `(section, key/value)` pairs, in order.
+fn parse_ini(contents: &str) -> Vec<(String, Vec<(String, String)>)> {
+    let mut sections: Vec<(String, Vec<(String, String)>)> = Vec::new();
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            sections.push((name.trim().to_string(), Vec::new()));
+        } else if let (Some((key, value)), Some(section)) = (line.split_once('='), sections.last_mut()) {
+            section.1.push((key.trim().to_lowercase(), value.trim().to_string()));
+        }
+    }
+    sections
+}
+
+/// Build profiles from the contents of the config and credentials files.
+/// Values in the credentials file take precedence.
+fn parse_profiles(config: &str, credentials: &str) -> BTreeMap<String, Profile> {
+    let mut profiles: BTreeMap<String, Profile> = BTreeMap::new();
+
+    // The config file names sections `[default]` and `[profile <name>]`;
+    // other sections (`sso-session`, `services`) aren't profiles
+    let config_sections = parse_ini(config).into_iter().filter_map(|(section, values)| {
+        let name = if section == "default" {
+            section
+        } else {
+            section.strip_prefix("profile ")?.trim().to_string()
+        };
+        Some((name, values))
+    });
+
+    for (name, values) in config_sections.chain(parse_ini(credentials)) {
+        let profile = profiles.entry(name.clone()).or_insert_with(|| Profile {
+            name,
+            ..Default::default()
+        });
+        for (key, value) in values {
+            profile.set(&key, value);
+        }
+    }
+    profiles
+}
+
+fn aws_file(env_var: &str, file_name: &str) -> Option<PathBuf> {
+    std::env::var_os(env_var)
+        .map(PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|h| h.join(".aws").join(file_name)))
+}
+
+/// Load all profiles from the shared AWS files.
+pub fn load_profiles() -> BTreeMap<String, Profile> {
+    let read = |path: Option<PathBuf>| path.and_then(|p| std::fs::read_to_string(p).ok()).unwrap_or_default();
+    parse_profiles(
+        &read(aws_file("AWS_CONFIG_FILE", "config")),
+        &read(aws_file("AWS_SHARED_CREDENTIALS_FILE", "credentials")),
+    )
+}
+
+/// Temporary credentials from STS.
+#[derive(Clone)]
+struct Session {
+    credentials: AwsCredentials,
+    /// Unix timestamp ms
+    expires_at: i64,
+}
+
+impl Session {
+    fn is_fresh(&self) -> bool {
+        self.expires_at - chrono::Utc::now().timestamp_millis() > REFRESH_MARGIN_MS
+    }
+}
+
+/// AWS credential state.
+#[derive(Default)]
+pub struct AwsState {
+    /// Assumed-role sessions keyed by profile name
+    sessions: RwLock<HashMap<String, Session>>,
+    /// Per-profile locks so a role is only assumed (and MFA prompted) once
+    locks: std::sync::Mutex<HashMap<String, Arc<Mutex<()>>>>,
+    /// MFA prompts waiting for the permission broker, keyed by request ID
+    pending_mfa: std::sync::Mutex<HashMap<String, oneshot::Sender<Option<String>>>>,
+}
+
+impl AwsState {
+    fn lock_for(&self, profile: &str) -> Arc<Mutex<()>> {
+        self.locks
+            .lock()
+            .unwrap()
+            .entry(profile.to_string())
+            .or_default()
+            .clone()
+    }
+
+    async fn cached(&self, profile: &str) -> Option<AwsCredentials> {
+        self.sessions
+            .read()
+            .await
+            .get(profile)
+            .filter(|s| s.is_fresh())
+            .map(|s| s.credentials.clone())
+    }
+}
+
+fn state() -> &'static AwsState {
+    &crate::state::get().auth.aws
+}
+
+/// Get credentials for a profile, assuming its role if it has one.
+///
+/// `requester` (usually a server ID) is shown in the MFA prompt. Pass
+/// `token_code` to supply an MFA code up front instead of prompting.
+pub async fn credentials(
+    profile: &str,
+    requester: &str,
+    token_code: Option<String>,
+) -> Result<AwsCredentials, String> {
+    let profiles = load_profiles();
+    resolve(&profiles, profile, requester, token_code, 0).await
+}
+
+fn resolve<'a>(
+    profiles: &'a BTreeMap<String, Profile>,
+    name: &'a str,
+    requester: &'a str,
+    token_code: Option<String>,
+    depth: usize,
+) -> Pin<Box<dyn Future<Output = Result<AwsCredentials, String>> + Send + 'a>> {
+    Box::pin(async move {
+        if depth > MAX_SOURCE_DEPTH {
+            return Err(format!("Too many nested source profiles resolving '{}'", name));
+        }
+        let profile = profiles
+            .get(name)
+            .ok_or_else(|| format!("AWS profile '{}' not found", name))?;
+
+        let Some(role_arn) = profile.role_arn.as_deref() else {
+            return profile
+                .static_credentials()
+                .ok_or_else(|| format!("AWS profile '{}' has no credentials", name));
+        };
+
+        if let Some(credentials) = state().cached(name).await {
+            return Ok(credentials);
+        }
+        let lock = state().lock_for(name);
+        let _guard = lock.lock().await;
+        // Another caller may have assumed the role while we waited
+        if let Some(credentials) = state().cached(name).await {
+            return Ok(credentials);
+        }
+
+        let source_name = profile
+            .source_profile
+            .as_deref()
+            .ok_or_else(|| format!("AWS profile '{}' has a role_arn but no source_profile", name))?;
+        let source = resolve(profiles, source_name, requester, None, depth + 1).await?;
+
+        let mfa = match profile.mfa_serial.as_deref() {
+            Some(serial) => {
+                let code = match token_code {
+                    Some(code) => code,
+                    None => request_mfa(name, serial, requester).await?,
+                };
+                Some((serial, code))
+            }
+            None => None,
+        };
+
+        let session = assume_role(profile, role_arn, &source, mfa).await?;
+        tracing::info!("Assumed role for AWS profile {} (requested by {})", name, requester);
+        let credentials = session.credentials.clone();
+        state().sessions.write().await.insert(name.to_string(), session);
+        Ok(credentials)
+    })
+}
+
+/// Ask the permission broker for an MFA code and wait for the answer.
+async fn request_mfa(profile: &str, mfa_serial: &str, requester: &str) -> Result<String, String> {
+    let request_id = format!("{:016x}", rand::random::<u64>());
+    let (tx, rx) = oneshot::channel();
+    state().pending_mfa.lock().unwrap().insert(request_id.clone(), tx);
+
+    events::emit(
+        "aws/mfa_required",
+        serde_json::json!({
+            "request_id": request_id,
+            "profile": profile,
+            "mfa_serial": mfa_serial,
+            "requester": requester,
+        }),
+    );
+
+    let answer = tokio::time::timeout(MFA_TIMEOUT, rx).await;
+    state().pending_mfa.lock().unwrap().remove(&request_id);
+    match answer {
+        Ok(Ok(Some(code))) => Ok(code),
+        Ok(Ok(None)) => Err("MFA prompt was denied".to_string()),
+        Ok(Err(_)) => Err("MFA prompt was cancelled".to_string()),
+        Err(_) => Err("Timed out waiting for an MFA code".to_string()),
+    }
+}
+
+/// Extract the text of the first `<tag>` element in an STS XML response.
+fn xml_field(body: &str, tag: &str) -> Option<String> {
+    let start = body.find(&format!("<{}>", tag))? + tag.len() + 2;
+    let end = start + body[start..].find(&format!("</{}>", tag))?;
+    Some(body[start..end].to_string())
+}
+
+/// Parse the credentials out of an AssumeRole response.
+fn parse_assume_role_response(body: &str) -> Result<Session, String> {
+    let field = |tag: &str| xml_field(body, tag).ok_or_else(|| format!("AssumeRole response has no {}", tag));
+    let expiration = field("Expiration")?;
+    let expires_at = chrono::DateTime::parse_from_rfc3339(&expiration)
+        .map_err(|e| format!("Invalid expiration '{}': {}", expiration, e))?
+        .timestamp_millis();
+    Ok(Session {
+        credentials: AwsCredentials {
+            access_key_id: field("AccessKeyId")?,
+            secret_access_key: field("SecretAccessKey")?,
+            session_token: Some(field("SessionToken")?),
+        },
+        expires_at,
+    })
+}
+
+/// Call STS AssumeRole for a profile.
+async fn assume_role(
+    profile: &Profile,
+    role_arn: &str,
+    source: &AwsCredentials,
+    mfa: Option<(&str, String)>,
+) -> Result<Session, String> {
+    let region = profile.region.as_deref().unwrap_or("us-east-1");
+    let session_name = profile.role_session_name.clone().unwrap_or_else(|| "harbor".to_string());
+    let duration = profile.duration_seconds.unwrap_or(3600).to_string();
+
+    let mut form = vec![
+        ("Action", "AssumeRole"),
+        ("Version", STS_VERSION),
+        ("RoleArn", role_arn),
+        ("RoleSessionName", session_name.as_str()),
+        ("DurationSeconds", duration.as_str()),
+    ];
+    if let Some(ref external_id) = profile.external_id {
+        form.push(("ExternalId", external_id.as_str()));
+    }
+    if let Some((serial, ref code)) = mfa {
+        form.push(("SerialNumber", serial));
+        form.push(("TokenCode", code.as_str()));
+    }
+    let body = url::form_urlencoded::Serializer::new(String::new())
+        .extend_pairs(&form)
+        .finish();
+
+    let url = url::Url::parse(&format!("https://sts.{}.amazonaws.com/", region))
+        .map_err(|e| format!("Invalid STS endpoint: {}", e))?;
+    let mut request = HttpRequest {
+        method: "POST".to_string(),
+        url,
+        headers: HashMap::from([(
+            "Content-Type".to_string(),
+            "application/x-www-form-urlencoded; charset=utf-8".to_string(),
+        )]),
+        body: body.into_bytes(),
+    };
+    sigv4::sign(&mut request, source, region, "sts", chrono::Utc::now())?;
+
+    let client = reqwest::Client::new();
+    let mut builder = client.post(request.url).body(request.body);
+    for (key, value) in &request.headers {
+        builder = builder.header(key.as_str(), value.as_str());
+    }
+    let response = builder
+        .send()
+        .await
+        .map_err(|e| format!("AssumeRole request failed: {}", e))?;
+
+    let status = response.status();
+    let text = response.text().await.unwrap_or_default();
+    if !status.is_success() {
+        let message = xml_field(&text, "Message").unwrap_or(text);
+        return Err(format!("AssumeRole failed: {} - {}", status, redact(&message)));
+    }
+    parse_assume_role_response(&text)
+}
+
+// ============================================================================
+// RPC Handlers
+// ============================================================================
+
+fn profile_param(params: &serde_json::Value) -> Result<&str, RpcError> {
+    params
+        .get("profile")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| RpcError::invalid_params("Missing 'profile' parameter"))
+}
+
+/// List profiles from the shared AWS files. Secrets are never returned.
+pub async fn rpc_list_profiles(_params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    let sessions = state().sessions.read().await;
+    let profiles: Vec<serde_json::Value> = load_profiles()
+        .into_values()
+        .map(|profile| {
+            let mut entry = serde_json::to_value(&profile).unwrap_or_default();
+            entry["has_credentials"] = serde_json::json!(profile.static_credentials().is_some());
+            entry["session_expires_at"] = serde_json::json!(sessions.get(&profile.name).map(|s| s.expires_at));
+            entry
+        })
+        .collect();
+    Ok(serde_json::json!({ "profiles": profiles }))
+}
+
+/// Assume a profile's role now (e.g. when a server is enabled), optionally
+/// with an MFA `token_code`, so later requests don't have to prompt.
+pub async fn rpc_assume_role(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    crate::rpc::require_extension("assume AWS roles")?;
+    let profile = profile_param(&params)?;
+    let token_code = params.get("token_code").and_then(|v| v.as_str()).map(String::from);
+
+    credentials(profile, "user", token_code)
+        .await
+        .map_err(|e| RpcError::new(-32000, e))?;
+
+    let expires_at = state().sessions.read().await.get(profile).map(|s| s.expires_at);
+    Ok(serde_json::json!({
+        "profile": profile,
+        "expires_at": expires_at,
+    }))
+}
+
+/// Answer an `aws/mfa_required` prompt. Omit `token_code` to deny.
+pub async fn rpc_submit_mfa(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    crate::rpc::require_extension("answer MFA prompts")?;
+    let request_id = params
+        .get("request_id")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| RpcError::invalid_params("Missing 'request_id' parameter"))?;
+    let token_code = params.get("token_code").and_then(|v| v.as_str()).map(String::from);
+
+    let sender = state()
+        .pending_mfa
+        .lock()
+        .unwrap()
+        .remove(request_id)
+        .ok_or_else(|| RpcError::new(-32000, "Unknown or expired MFA request"))?;
+    let _ = sender.send(token_code);
+
+    Ok(serde_json::json!({ "success": true }))
+}
+
+/// Drop cached sessions that have expired. Returns how many were removed.
+pub async fn prune_sessions() -> usize {
+    let now = chrono::Utc::now().timestamp_millis();
+    let mut sessions = state().sessions.write().await;
+    let before = sessions.len();
+    sessions.retain(|_, s| s.expires_at > now);
+    before - sessions.len()
+}
+
+/// Drop cached sessions, for one `profile` or all of them.
+pub async fn rpc_clear_sessions(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    crate::rpc::require_extension("clear AWS sessions")?;
+    let mut sessions = state().sessions.write().await;
+    match params.get("profile").and_then(|v| v.as_str()) {
+        Some(profile) => {
+            sessions.remove(profile);
+        }
+        None => sessions.clear(),
+    }
+    Ok(serde_json::json!({ "success": true }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_profiles() {
+        let config = "\
+[default]
+region = eu-west-1
+
+[profile admin]
+role_arn = arn:aws:iam::123456789012:role/Admin
+source_profile = default
+mfa_serial = arn:aws:iam::123456789012:mfa/alice
+
+[sso-session corp]
+sso_region = us-east-1
+";
+        let credentials = "\
+[default]
+aws_access_key_id = AKIDEXAMPLE
+aws_secret_access_key = secret
+";
+        let profiles = parse_profiles(config, credentials);
+        assert_eq!(profiles.len(), 2);
+
+        let default = &profiles["default"];
+        assert_eq!(default.region.as_deref(), Some("eu-west-1"));
+        assert_eq!(default.static_credentials().unwrap().access_key_id, "AKIDEXAMPLE");
+
+        let admin = &profiles["admin"];
+        assert_eq!(admin.source_profile.as_deref(), Some("default"));
+        assert!(admin.mfa_serial.is_some());
+        assert!(admin.static_credentials().is_none());
+    }
+
+    #[test]
+    fn test_parse_assume_role_response() {
+        let body = "<AssumeRoleResponse><AssumeRoleResult><Credentials>\
+            <AccessKeyId>ASIAEXAMPLE</AccessKeyId>\
+            <SecretAccessKey>secret</SecretAccessKey>\
+            <SessionToken>token</SessionToken>\
+            <Expiration>2030-01-01T00:00:00Z</Expiration>\
+            </Credentials></AssumeRoleResult></AssumeRoleResponse>";
+        let session = parse_assume_role_response(body).unwrap();
+        assert_eq!(session.credentials.access_key_id, "ASIAEXAMPLE");
+        assert_eq!(session.credentials.session_token.as_deref(), Some("token"));
+        assert_eq!(session.expires_at, 1_893_456_000_000);
+    }
+}
@@ -0,0 +1,312 @@
+//! Managed credentials for the fetch capability.
+//!
+//! A server's manifest can declare how its API authenticates:
+//!
+//! ```json
+//! { "auth": { "type": "api_key", "header": "X-API-Key", "hosts": ["api.example.com"] } }
+//! ```
+//!
+//! Supported types are `api_key`, `basic`, `oauth` and `aws_sigv4`. The
+//! secret material (key, password, AWS keys) is stored in the OS keychain
+//! under the server ID; OAuth uses the tokens from the OAuth subsystem.
+//! `aws_sigv4` can instead reference a shared AWS profile (see `aws`).
+//! Fetch requests to the declared hosts are then signed by the bridge, so
+//! the server's code never sees the credentials.
+
+pub mod aws;
+pub mod sigv4;
+
+use std::collections::HashMap;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+
+use crate::rpc::RpcError;
+
+const KEYCHAIN_SERVICE: &str = "harbor-auth";
+
+/// Authentication declared by a server's manifest.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthConfig {
+    #[serde(flatten)]
+    pub scheme: AuthScheme,
+    /// Hosts credentials may be sent to. Defaults to the server's network
+    /// allowlist.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hosts: Vec<String>,
+}
+
+/// How requests are authenticated.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AuthScheme {
+    /// A static key sent in a header or query parameter
+    ApiKey {
+        /// Header to send the key in (default `X-API-Key`)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        header: Option<String>,
+        /// Query parameter to send the key in, instead of a header
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        query: Option<String>,
+        /// Prefix for the header value (e.g. `"Bearer "`)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        prefix: Option<String>,
+    },
+    /// HTTP Basic authentication
+    Basic,
+    /// Bearer tokens from the OAuth subsystem
+    #[serde(rename = "oauth")]
+    OAuth,
+    /// AWS Signature Version 4
+    AwsSigv4 { region: String, service: String },
+}
+
+impl AuthScheme {
+    pub fn name(&self) -> &'static str {
+        match self {
+            AuthScheme::ApiKey { .. } => "api_key",
+            AuthScheme::Basic => "basic",
+            AuthScheme::OAuth => "oauth",
+            AuthScheme::AwsSigv4 { .. } => "aws_sigv4",
+        }
+    }
+}
+
+/// Secret material for a server, stored in the keychain.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AuthMaterial {
+    ApiKey {
+        key: String,
+    },
+    Basic {
+        username: String,
+        password: String,
+    },
+    AwsSigv4 {
+        access_key_id: String,
+        secret_access_key: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        session_token: Option<String>,
+    },
+    /// A profile in the shared AWS files, for `aws_sigv4`
+    AwsProfile {
+        profile: String,
+    },
+}
+
+impl AuthMaterial {
+    fn name(&self) -> &'static str {
+        match self {
+            AuthMaterial::ApiKey { .. } => "api_key",
+            AuthMaterial::Basic { .. } => "basic",
+            AuthMaterial::AwsSigv4 { .. } => "aws_sigv4",
+            AuthMaterial::AwsProfile { .. } => "aws_profile",
+        }
+    }
+}
+
+/// An outgoing request, before it is handed to the HTTP client.
+#[derive(Debug, Clone)]
+pub struct HttpRequest {
+    pub method: String,
+    pub url: url::Url,
+    pub headers: HashMap<String, String>,
+    pub body: Vec<u8>,
+}
+
+/// Auth subsystem state.
+#[derive(Default)]
+pub struct AuthState {
+    /// Material read from the keychain, keyed by server ID
+    material: RwLock<HashMap<String, AuthMaterial>>,
+    /// AWS sessions and pending MFA prompts
+    pub aws: aws::AwsState,
+}
+
+fn state() -> &'static AuthState {
+    &crate::state::get().auth
+}
+
+fn entry(server_id: &str) -> Result<keyring::Entry, String> {
+    keyring::Entry::new(KEYCHAIN_SERVICE, server_id)
+        .map_err(|e| format!("Failed to open keychain entry for {}: {}", server_id, e))
+}
+
+/// Get the stored material for a server.
+async fn get_material(server_id: &str) -> Option<AuthMaterial> {
+    if let Some(material) = state().material.read().await.get(server_id) {
+        return Some(material.clone());
+    }
+
+    let json = match entry(server_id).and_then(|e| e.get_password().map_err(|e| e.to_string())) {
+        Ok(json) => json,
+        Err(e) => {
+            tracing::debug!("No auth material for {}: {}", server_id, e);
+            return None;
+        }
+    };
+    match serde_json::from_str::<AuthMaterial>(&json) {
+        Ok(material) => {
+            state().material.write().await.insert(server_id.to_string(), material.clone());
+            Some(material)
+        }
+        Err(e) => {
+            tracing::warn!("Failed to parse auth material for {}: {}", server_id, e);
+            None
+        }
+    }
+}
+
+/// Whether credentials are stored for a server.
+pub async fn has_credentials(server_id: &str) -> bool {
+    get_material(server_id).await.is_some()
+}
+
+/// Store material for a server.
+pub async fn set_material(server_id: &str, material: AuthMaterial) -> Result<(), String> {
+    let json = serde_json::to_string(&material)
+        .map_err(|e| format!("Failed to serialize auth material: {}", e))?;
+    entry(server_id)?
+        .set_password(&json)
+        .map_err(|e| format!("Failed to store auth material in keychain: {}", e))?;
+    state().material.write().await.insert(server_id.to_string(), material);
+    Ok(())
+}
+
+/// Remove the material for a server. Missing entries are not an error.
+pub async fn remove_material(server_id: &str) -> Result<(), String> {
+    state().material.write().await.remove(server_id);
+    match entry(server_id)?.delete_password() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(format!("Failed to delete auth material from keychain: {}", e)),
+    }
+}
+
+/// Authenticate a request for a server according to its declared scheme.
+pub async fn apply(server_id: &str, scheme: &AuthScheme, request: &mut HttpRequest) -> Result<(), String> {
+    if let AuthScheme::OAuth = scheme {
+        let token = crate::oauth::get_access_token(server_id).await?;
+        request.headers.insert("Authorization".to_string(), format!("Bearer {}", token));
+        return Ok(());
+    }
+
+    let material = get_material(server_id)
+        .await
+        .ok_or_else(|| format!("No {} credentials configured for server '{}'", scheme.name(), server_id))?;
+
+    match (scheme, &material) {
+        (AuthScheme::ApiKey { header, query, prefix }, AuthMaterial::ApiKey { key }) => {
+            if let Some(param) = query {
+                request.url.query_pairs_mut().append_pair(param, key);
+            } else {
+                let header = header.as_deref().unwrap_or("X-API-Key");
+                let value = format!("{}{}", prefix.as_deref().unwrap_or(""), key);
+                request.headers.insert(header.to_string(), value);
+            }
+        }
+        (AuthScheme::Basic, AuthMaterial::Basic { username, password }) => {
+            let encoded = STANDARD.encode(format!("{}:{}", username, password));
+            request.headers.insert("Authorization".to_string(), format!("Basic {}", encoded));
+        }
+        (
+            AuthScheme::AwsSigv4 { region, service },
+            AuthMaterial::AwsSigv4 { access_key_id, secret_access_key, session_token },
+        ) => {
+            let credentials = sigv4::AwsCredentials {
+                access_key_id: access_key_id.clone(),
+                secret_access_key: secret_access_key.clone(),
+                session_token: session_token.clone(),
+            };
+            sigv4::sign(request, &credentials, region, service, chrono::Utc::now())?;
+        }
+        (AuthScheme::AwsSigv4 { region, service }, AuthMaterial::AwsProfile { profile }) => {
+            let credentials = aws::credentials(profile, server_id, None).await?;
+            sigv4::sign(request, &credentials, region, service, chrono::Utc::now())?;
+        }
+        _ => {
+            return Err(format!(
+                "Server '{}' declares {} auth but has {} credentials stored",
+                server_id,
+                scheme.name(),
+                material.name()
+            ));
+        }
+    }
+    Ok(())
+}
+
+// ============================================================================
+// RPC Handlers
+// ============================================================================
+
+fn server_id_param(params: &serde_json::Value) -> Result<&str, RpcError> {
+    params
+        .get("server_id")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| RpcError::invalid_params("Missing 'server_id' parameter"))
+}
+
+/// Store credentials for a server: `{ server_id, material: { type, ... } }`.
+pub async fn rpc_set_credentials(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    crate::rpc::require_extension("change server credentials")?;
+    let server_id = server_id_param(&params)?;
+    let material: AuthMaterial = params
+        .get("material")
+        .cloned()
+        .ok_or_else(|| RpcError::invalid_params("Missing 'material' parameter"))
+        .and_then(|m| {
+            serde_json::from_value(m).map_err(|e| RpcError::invalid_params(format!("Invalid material: {}", e)))
+        })?;
+
+    let kind = material.name();
+    set_material(server_id, material).await.map_err(RpcError::internal)?;
+    tracing::info!("Stored {} credentials for server {}", kind, server_id);
+
+    Ok(serde_json::json!({ "success": true }))
+}
+
+/// Remove stored credentials for a server.
+pub async fn rpc_remove_credentials(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    crate::rpc::require_extension("change server credentials")?;
+    let server_id = server_id_param(&params)?;
+    remove_material(server_id).await.map_err(RpcError::internal)?;
+    Ok(serde_json::json!({ "success": true }))
+}
+
+/// Report whether credentials are stored for a server, and of which type.
+pub async fn rpc_status(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    let server_id = server_id_param(&params)?;
+    Ok(match get_material(server_id).await {
+        Some(material) => serde_json::json!({ "configured": true, "type": material.name() }),
+        None => serde_json::json!({ "configured": false }),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_manifest_declaration() {
+        let config: AuthConfig = serde_json::from_value(serde_json::json!({
+            "type": "api_key",
+            "header": "Authorization",
+            "prefix": "Token ",
+            "hosts": ["api.example.com"],
+        }))
+        .unwrap();
+        assert_eq!(config.hosts, vec!["api.example.com"]);
+        assert!(matches!(config.scheme, AuthScheme::ApiKey { ref header, .. } if header.as_deref() == Some("Authorization")));
+
+        let config: AuthConfig = serde_json::from_value(serde_json::json!({
+            "type": "aws_sigv4",
+            "region": "us-east-1",
+            "service": "s3",
+        }))
+        .unwrap();
+        assert_eq!(config.scheme.name(), "aws_sigv4");
+        assert!(config.hosts.is_empty());
+    }
+}
@@ -0,0 +1,148 @@
+//! Five-field cron expressions (`minute hour day-of-month month day-of-week`).
+//!
+//! Fields accept `*`, numbers, ranges (`1-5`), lists (`1,15`) and steps
+//! (`*/15`, `0-30/10`). Day-of-week is 0-7 with both 0 and 7 meaning Sunday.
+//! As in standard cron, when both day fields are restricted a time matches if
+//! either does. `@hourly`, `@daily`, `@weekly` and `@monthly` are accepted.
+//! Times are evaluated in the timezone of the starting time passed in.
+
+use chrono::{DateTime, Datelike, Duration, NaiveDateTime, TimeZone, Timelike};
+
+/// A parsed cron schedule.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Schedule {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    /// Whether the day-of-month / day-of-week fields were `*`
+    any_day: bool,
+    any_weekday: bool,
+}
+
+/// Parse one field into a bitmask of allowed values.
+fn parse_field(field: &str, min: u32, max: u32) -> Result<u64, String> {
+    let mut mask = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (
+                range,
+                step.parse::<u32>()
+                    .ok()
+                    .filter(|s| *s > 0)
+                    .ok_or_else(|| format!("Invalid step in '{}'", part))?,
+            ),
+            None => (part, 1),
+        };
+        let number = |s: &str| {
+            s.parse::<u32>()
+                .ok()
+                .filter(|n| (min..=max).contains(n))
+                .ok_or_else(|| format!("'{}' is not between {} and {}", s, min, max))
+        };
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((start, end)) = range.split_once('-') {
+            (number(start)?, number(end)?)
+        } else {
+            let n = number(range)?;
+            // `5/15` means from 5 to the end, every 15
+            (n, if part.contains('/') { max } else { n })
+        };
+        if start > end {
+            return Err(format!("Invalid range '{}'", range));
+        }
+        for value in (start..=end).step_by(step as usize) {
+            mask |= 1 << value;
+        }
+    }
+    Ok(mask)
+}
+
+impl Schedule {
+    pub fn parse(expression: &str) -> Result<Self, String> {
+        let expression = match expression.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            other => other,
+        };
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err(format!("Expected 5 fields, got {}", fields.len()));
+        };
+
+        let mut weekdays = parse_field(weekday, 0, 7)?;
+        // Sunday is both 0 and 7
+        if weekdays & (1 << 7) != 0 {
+            weekdays |= 1;
+        }
+        Ok(Self {
+            minutes: parse_field(minute, 0, 59)?,
+            hours: parse_field(hour, 0, 23)?,
+            days: parse_field(day, 1, 31)?,
+            months: parse_field(month, 1, 12)?,
+            weekdays,
+            any_day: day == "*",
+            any_weekday: weekday == "*",
+        })
+    }
+
+    fn matches(&self, t: &NaiveDateTime) -> bool {
+        let bit = |mask: u64, value: u32| mask & (1 << value) != 0;
+        let day = bit(self.days, t.day());
+        let weekday = bit(self.weekdays, t.weekday().num_days_from_sunday());
+        let day_ok = match (self.any_day, self.any_weekday) {
+            (false, false) => day || weekday,
+            _ => day && weekday,
+        };
+        bit(self.minutes, t.minute()) && bit(self.hours, t.hour()) && bit(self.months, t.month()) && day_ok
+    }
+
+    /// The next `count` run times after `from`, looking at most `horizon`
+    /// ahead.
+    pub fn upcoming<Z: TimeZone>(&self, from: DateTime<Z>, count: usize, horizon: Duration) -> Vec<DateTime<Z>> {
+        let tz = from.timezone();
+        let start = from.naive_local().with_second(0).and_then(|t| t.with_nanosecond(0)).unwrap_or_default()
+            + Duration::minutes(1);
+        let end = start + horizon;
+
+        let mut runs = Vec::new();
+        let mut t = start;
+        while t < end && runs.len() < count {
+            if self.matches(&t) {
+                // Skip times that don't exist locally (DST gaps)
+                if let Some(local) = tz.from_local_datetime(&t).earliest() {
+                    runs.push(local);
+                }
+            }
+            t += Duration::minutes(1);
+        }
+        runs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_match() {
+        let weekday_mornings = Schedule::parse("30 9 * * 1-5").unwrap();
+        let monday = NaiveDateTime::parse_from_str("2024-06-03 09:30", "%Y-%m-%d %H:%M").unwrap();
+        let sunday = NaiveDateTime::parse_from_str("2024-06-02 09:30", "%Y-%m-%d %H:%M").unwrap();
+        assert!(weekday_mornings.matches(&monday));
+        assert!(!weekday_mornings.matches(&sunday));
+
+        let quarter_hours = Schedule::parse("*/15 * * * *").unwrap();
+        assert_eq!(quarter_hours.minutes.count_ones(), 4);
+        assert_eq!(Schedule::parse("@daily").unwrap(), Schedule::parse("0 0 * * *").unwrap());
+        assert_eq!(Schedule::parse("0 0 * * 7").unwrap(), Schedule::parse("0 0 * * 0,7").unwrap());
+
+        assert!(Schedule::parse("61 * * * *").is_err());
+        assert!(Schedule::parse("* * *").is_err());
+        assert!(Schedule::parse("*/0 * * * *").is_err());
+    }
+}
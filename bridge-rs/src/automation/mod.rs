@@ -0,0 +1,385 @@
+//! Scheduled automations.
+//!
+//! The extension turns a natural-language request ("every weekday at 9,
+//! summarize my unread mail") into an automation definition: a cron schedule
+//! (or a recurrence phrase like "weekdays at 9am", see `recurrence`) in an
+//! optional timezone, and a list of tool calls. `automation.dry_run`
+//! validates the definition
+//! and simulates it without calling anything, returning a plan the user can
+//! review before enabling it: which tools would run, with what permissions,
+//! and what it would cost.
+
+pub mod cron;
+pub mod recurrence;
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Duration, FixedOffset, TimeZone};
+use serde::{Deserialize, Serialize};
+
+use crate::budget::{self, Estimate};
+use crate::rpc::RpcError;
+use cron::Schedule;
+use recurrence::Recurrence;
+
+/// Most steps an automation may have.
+const MAX_STEPS: usize = 50;
+
+/// Upcoming runs shown in a plan.
+const PREVIEW_RUNS: usize = 5;
+
+/// An automation definition.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Automation {
+    pub name: String,
+    /// The natural-language request the definition was generated from
+    #[serde(default)]
+    pub spec: Option<String>,
+    /// Cron expression (see `cron`) or recurrence phrase (see `recurrence`)
+    pub schedule: String,
+    /// IANA timezone the schedule is in, e.g. `Europe/Paris`; defaults to
+    /// the system's
+    #[serde(default)]
+    pub timezone: Option<String>,
+    pub steps: Vec<Step>,
+}
+
+/// One tool call in an automation.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Step {
+    pub server_id: String,
+    pub tool: String,
+    #[serde(default)]
+    pub args: serde_json::Value,
+}
+
+/// When an automation runs.
+#[derive(Debug, Clone)]
+pub enum Timing {
+    Cron(Schedule),
+    Recurrence(Recurrence),
+}
+
+impl Timing {
+    /// Parse a cron expression, or failing that a recurrence phrase.
+    pub fn parse(schedule: &str) -> Result<Self, String> {
+        let cron_error = match Schedule::parse(schedule) {
+            Ok(cron) => return Ok(Self::Cron(cron)),
+            Err(e) => e,
+        };
+        Recurrence::parse(schedule).map(Self::Recurrence).map_err(|e| {
+            // Report the cron error for things that look like cron
+            let cron_like = schedule.trim_start().starts_with(|c: char| c.is_ascii_digit() || c == '*' || c == '@')
+                && !schedule.contains(|c: char| c.is_ascii_alphabetic() && c != '@');
+            if cron_like {
+                cron_error
+            } else {
+                e
+            }
+        })
+    }
+
+    /// The next `count` run times after `from`, looking at most `horizon`
+    /// ahead.
+    pub fn upcoming<Z: TimeZone>(&self, from: DateTime<Z>, count: usize, horizon: Duration) -> Vec<DateTime<Z>> {
+        match self {
+            Self::Cron(cron) => cron.upcoming(from, count, horizon),
+            Self::Recurrence(recurrence) => recurrence.upcoming(from, count, horizon),
+        }
+    }
+
+    /// Average runs per day over the four weeks after `from`.
+    pub fn runs_per_day<Z: TimeZone>(&self, from: DateTime<Z>) -> f64 {
+        let days = 28;
+        self.upcoming(from, usize::MAX, Duration::days(days)).len() as f64 / days as f64
+    }
+}
+
+/// Upcoming runs and runs per day, from now in `tz`.
+fn preview<Z: TimeZone>(timing: &Timing, tz: &Z) -> (Vec<DateTime<FixedOffset>>, f64) {
+    let now = chrono::Utc::now().with_timezone(tz);
+    let runs = timing
+        .upcoming(now.clone(), PREVIEW_RUNS, Duration::days(366))
+        .into_iter()
+        .map(|t| t.fixed_offset())
+        .collect();
+    (runs, timing.runs_per_day(now))
+}
+
+/// What a step would be allowed to do.
+#[derive(Debug, Default, Serialize)]
+pub struct StepPermissions {
+    pub network_hosts: Vec<String>,
+    pub fs_read: Vec<String>,
+    pub fs_write: Vec<String>,
+    /// Directories granted through `fs.request_access`
+    pub fs_grants: Vec<crate::fs::permissions::Grant>,
+    /// Declared auth scheme, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auth: Option<String>,
+    /// Whether credentials for the auth scheme are stored
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub credentials_configured: Option<bool>,
+}
+
+/// The simulated outcome of one step.
+#[derive(Debug, Serialize)]
+pub struct StepPlan {
+    pub index: usize,
+    pub server_id: String,
+    pub tool: String,
+    /// `js` (running in the bridge), `registered` (synced from the
+    /// extension) or `unknown`
+    pub runtime: &'static str,
+    /// Whether the tool exists, if the server's tool list is known
+    pub tool_found: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Required arguments missing from `args`
+    pub missing_args: Vec<String>,
+    pub permissions: StepPermissions,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cost: Option<Estimate>,
+}
+
+/// The result of a dry run.
+#[derive(Debug, Serialize)]
+pub struct Plan {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub spec: Option<String>,
+    /// Whether the automation could be enabled as is
+    pub valid: bool,
+    pub errors: Vec<String>,
+    pub warnings: Vec<String>,
+    pub schedule: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<String>,
+    pub next_runs: Vec<DateTime<FixedOffset>>,
+    pub runs_per_day: f64,
+    pub steps: Vec<StepPlan>,
+    /// Estimated cost per run and per day, by unit
+    pub cost_per_run: BTreeMap<String, f64>,
+    pub cost_per_day: BTreeMap<String, f64>,
+}
+
+/// Required properties of a JSON schema missing from `args`.
+fn missing_required(schema: &serde_json::Value, args: &serde_json::Value) -> Vec<String> {
+    schema
+        .get("required")
+        .and_then(|r| r.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|key| key.as_str())
+        .filter(|key| args.get(key).is_none())
+        .map(String::from)
+        .collect()
+}
+
+async fn plan_step(index: usize, step: &Step, errors: &mut Vec<String>, warnings: &mut Vec<String>) -> StepPlan {
+    let label = format!("Step {} ({}/{})", index + 1, step.server_id, step.tool);
+    let definition = crate::js::definition(&step.server_id).await;
+    let registered = crate::mcp::find_tool(&step.server_id, &step.tool).await;
+    let server_registered = registered.is_some() || crate::mcp::has_server(&step.server_id).await;
+
+    let runtime = if definition.is_some() {
+        "js"
+    } else if server_registered {
+        "registered"
+    } else {
+        "unknown"
+    };
+    let tool_found = if server_registered {
+        Some(registered.is_some())
+    } else {
+        None
+    };
+    match (runtime, tool_found) {
+        ("unknown", _) => errors.push(format!("{}: server '{}' is not running", label, step.server_id)),
+        (_, Some(false)) => errors.push(format!("{}: server has no tool '{}'", label, step.tool)),
+        (_, None) => warnings.push(format!("{}: tool list not available; the tool was not checked", label)),
+        _ => {}
+    }
+
+    let missing_args = registered
+        .as_ref()
+        .and_then(|t| t.input_schema.as_ref())
+        .map(|schema| missing_required(schema, &step.args))
+        .unwrap_or_default();
+    if !missing_args.is_empty() {
+        errors.push(format!("{}: missing required arguments: {}", label, missing_args.join(", ")));
+    }
+
+    let mut permissions = StepPermissions {
+        fs_grants: crate::fs::grants_for(&step.server_id).await,
+        ..Default::default()
+    };
+    if let Some(definition) = &definition {
+        permissions.network_hosts = definition.capabilities.network.allowed_hosts.clone();
+        permissions.fs_read = definition.capabilities.filesystem.read_paths.clone();
+        permissions.fs_write = definition.capabilities.filesystem.write_paths.clone();
+        if let Some(auth) = &definition.auth {
+            let configured = match auth.scheme {
+                crate::auth::AuthScheme::OAuth => crate::oauth::has_tokens(&step.server_id).await,
+                _ => crate::auth::has_credentials(&step.server_id).await,
+            };
+            if !configured {
+                warnings.push(format!("{}: no {} credentials are stored", label, auth.scheme.name()));
+            }
+            permissions.auth = Some(auth.scheme.name().to_string());
+            permissions.credentials_configured = Some(configured);
+        }
+    }
+
+    StepPlan {
+        index,
+        server_id: step.server_id.clone(),
+        tool: step.tool.clone(),
+        runtime,
+        tool_found,
+        description: registered.and_then(|t| t.description),
+        missing_args,
+        permissions,
+        cost: budget::estimate(&step.server_id, &step.tool).await,
+    }
+}
+
+/// Validate and simulate an automation.
+pub async fn dry_run(automation: &Automation) -> Plan {
+    let mut errors = Vec::new();
+    let mut warnings = Vec::new();
+
+    let timezone = match &automation.timezone {
+        Some(name) => match name.parse::<chrono_tz::Tz>() {
+            Ok(tz) => Some(tz),
+            Err(_) => {
+                errors.push(format!("Unknown timezone '{}'", name));
+                None
+            }
+        },
+        None => None,
+    };
+    let (next_runs, runs_per_day) = match Timing::parse(&automation.schedule) {
+        Ok(timing) => match timezone {
+            Some(tz) => preview(&timing, &tz),
+            None => preview(&timing, &chrono::Local),
+        },
+        Err(e) => {
+            errors.push(format!("Invalid schedule '{}': {}", automation.schedule, e));
+            (Vec::new(), 0.0)
+        }
+    };
+    if errors.is_empty() && next_runs.is_empty() {
+        errors.push("The schedule never runs".to_string());
+    }
+
+    if automation.steps.is_empty() {
+        errors.push("The automation has no steps".to_string());
+    } else if automation.steps.len() > MAX_STEPS {
+        errors.push(format!("Too many steps ({}, limit {})", automation.steps.len(), MAX_STEPS));
+    }
+
+    let mut steps = Vec::new();
+    for (index, step) in automation.steps.iter().take(MAX_STEPS).enumerate() {
+        steps.push(plan_step(index, step, &mut errors, &mut warnings).await);
+    }
+
+    // Total costs, and check each server's daily spend against its budget
+    let mut cost_per_run: BTreeMap<String, f64> = BTreeMap::new();
+    let mut per_server: BTreeMap<&str, (f64, &Estimate)> = BTreeMap::new();
+    for step in &steps {
+        if let Some(cost) = &step.cost {
+            *cost_per_run.entry(cost.unit.clone()).or_default() += cost.cost;
+            per_server.entry(step.server_id.as_str()).or_insert((0.0, cost)).0 += cost.cost;
+        }
+    }
+    let cost_per_day = cost_per_run
+        .iter()
+        .map(|(unit, cost)| (unit.clone(), cost * runs_per_day))
+        .collect();
+    for (server_id, (per_run, estimate)) in per_server {
+        if let Some(limit) = estimate.daily_limit {
+            let per_day = per_run * runs_per_day;
+            if per_day > limit {
+                warnings.push(format!(
+                    "'{}' would spend about {:.2} {} a day, over its {} {} daily budget",
+                    server_id, per_day, estimate.unit, limit, estimate.unit
+                ));
+            }
+        }
+    }
+
+    Plan {
+        name: automation.name.clone(),
+        spec: automation.spec.clone(),
+        valid: errors.is_empty(),
+        errors,
+        warnings,
+        schedule: automation.schedule.clone(),
+        timezone: automation.timezone.clone(),
+        next_runs,
+        runs_per_day,
+        steps,
+        cost_per_run,
+        cost_per_day,
+    }
+}
+
+// ============================================================================
+// RPC Handlers
+// ============================================================================
+
+/// Validate and simulate an automation without running it:
+/// `{ name, spec?, schedule, timezone?, steps: [{ server_id, tool, args }] }`.
+pub async fn rpc_dry_run(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    let automation: Automation = serde_json::from_value(params)
+        .map_err(|e| RpcError::invalid_params(format!("Invalid automation: {}", e)))?;
+    let plan = dry_run(&automation).await;
+    serde_json::to_value(plan).map_err(|e| RpcError::internal(e.to_string()))
+}
+
+/// The next firing times of a schedule, for the extension's scheduler:
+/// `{ schedule, timezone?, count? }`.
+pub async fn rpc_next_runs(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    let schedule = params
+        .get("schedule")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| RpcError::invalid_params("Missing 'schedule' parameter"))?;
+    let count = params.get("count").and_then(|v| v.as_u64()).map_or(1, |n| (n as usize).clamp(1, 100));
+    let timing = Timing::parse(schedule)
+        .map_err(|e| RpcError::invalid_params(format!("Invalid schedule '{}': {}", schedule, e)))?;
+
+    let from = chrono::Utc::now();
+    let horizon = Duration::days(366);
+    let runs: Vec<DateTime<FixedOffset>> = match params.get("timezone").and_then(|v| v.as_str()) {
+        Some(name) => {
+            let tz: chrono_tz::Tz = name
+                .parse()
+                .map_err(|_| RpcError::invalid_params(format!("Unknown timezone '{}'", name)))?;
+            timing.upcoming(from.with_timezone(&tz), count, horizon).iter().map(|t| t.fixed_offset()).collect()
+        }
+        None => timing
+            .upcoming(from.with_timezone(&chrono::Local), count, horizon)
+            .iter()
+            .map(|t| t.fixed_offset())
+            .collect(),
+    };
+    Ok(serde_json::json!({ "next_runs": runs }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_required_args() {
+        let schema = serde_json::json!({
+            "type": "object",
+            "properties": { "query": {}, "limit": {} },
+            "required": ["query", "limit"],
+        });
+        let args = serde_json::json!({ "query": "rust" });
+        assert_eq!(missing_required(&schema, &args), vec!["limit"]);
+        assert!(missing_required(&serde_json::json!({}), &args).is_empty());
+    }
+}
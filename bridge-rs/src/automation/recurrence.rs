@@ -0,0 +1,299 @@
+//! Human-friendly recurrence phrases.
+//!
+//! Schedules can be written the way people say them instead of as cron:
+//!
+//! - `every day at 9am`, `daily at 18:30`
+//! - `weekdays at 9am and 5pm`, `weekends at noon`
+//! - `every monday and thursday at 7:15 pm`, `tues, thurs at 08:00`
+//! - `first monday of the month at 9am`, `last friday of every month at 4pm`
+//! - `on the 1st and 15th of each month at midnight`
+//! - `every 15 minutes`, `every 2 hours`
+//!
+//! Times are 12-hour (`9am`, `9:30 pm`) or 24-hour (`21:00`). A phrase
+//! parses into a `Recurrence`, which is evaluated in whatever timezone the
+//! automation is attached to. Around DST changes each occurrence still fires
+//! once: a time skipped by the clock going forward fires when the gap ends,
+//! and a time repeated by the clock going back fires the first time.
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Weekday};
+
+/// Weekday names, indexed by days from Sunday.
+const WEEKDAY_NAMES: [&str; 7] = ["sunday", "monday", "tuesday", "wednesday", "thursday", "friday", "saturday"];
+
+/// Which days a recurrence fires on.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Days {
+    Every,
+    /// Bit per weekday, counted from Sunday
+    Weekdays(u8),
+    /// Days of the month; -1 is the last day
+    MonthDays(Vec<i8>),
+    /// The nth (1-5, or -1 for last) given weekday of the month
+    NthWeekday(i8, Weekday),
+}
+
+/// A parsed recurrence: days, and the local times on each of them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recurrence {
+    pub days: Days,
+    pub times: Vec<NaiveTime>,
+}
+
+fn weekday(word: &str) -> Option<u32> {
+    let word = if WEEKDAY_NAMES.contains(&word) {
+        word
+    } else {
+        word.strip_suffix('s').unwrap_or(word)
+    };
+    if word.len() < 3 {
+        return None;
+    }
+    WEEKDAY_NAMES.iter().position(|name| name.starts_with(word)).map(|i| i as u32)
+}
+
+/// `first`..`fifth`, `last`, or `1st`..`31st`.
+fn ordinal(word: &str) -> Option<i8> {
+    let named = ["first", "second", "third", "fourth", "fifth"];
+    if let Some(i) = named.iter().position(|n| *n == word) {
+        return Some(i as i8 + 1);
+    }
+    if word == "last" {
+        return Some(-1);
+    }
+    let digits = word
+        .strip_suffix("st")
+        .or_else(|| word.strip_suffix("nd"))
+        .or_else(|| word.strip_suffix("rd"))
+        .or_else(|| word.strip_suffix("th"))
+        .unwrap_or(word);
+    digits.parse::<i8>().ok().filter(|n| (1..=31).contains(n))
+}
+
+/// `9am`, `9:30pm`, `21:00`, `noon` or `midnight`.
+fn time(text: &str) -> Result<NaiveTime, String> {
+    let invalid = || format!("'{}' is not a time", text);
+    match text {
+        "noon" => return Ok(NaiveTime::from_hms_opt(12, 0, 0).unwrap()),
+        "midnight" => return Ok(NaiveTime::MIN),
+        _ => {}
+    }
+    let (clock, meridiem) = match text.strip_suffix("am") {
+        Some(clock) => (clock, Some(0)),
+        None => match text.strip_suffix("pm") {
+            Some(clock) => (clock, Some(12)),
+            None => (text, None),
+        },
+    };
+    let (hour, minute) = clock.split_once(':').unwrap_or((clock, "0"));
+    let mut hour: u32 = hour.parse().map_err(|_| invalid())?;
+    let minute: u32 = minute.parse().map_err(|_| invalid())?;
+    if let Some(offset) = meridiem {
+        if !(1..=12).contains(&hour) {
+            return Err(invalid());
+        }
+        hour = hour % 12 + offset;
+    }
+    NaiveTime::from_hms_opt(hour, minute, 0).ok_or_else(invalid)
+}
+
+/// `every N minutes` / `every N hours`, as times of day from midnight.
+fn interval(words: &[&str]) -> Option<Result<Vec<NaiveTime>, String>> {
+    let (count, unit) = match words {
+        ["every", unit] => (1, *unit),
+        ["every", count, unit] => (count.parse::<u32>().ok()?, *unit),
+        _ => return None,
+    };
+    let minutes = match unit.trim_end_matches('s') {
+        "minute" | "min" => Some(count),
+        "hour" => count.checked_mul(60),
+        _ => return None,
+    };
+    let minutes = match minutes {
+        Some(minutes) if minutes > 0 && 24 * 60 % minutes == 0 => minutes,
+        _ => return Some(Err(format!("An interval of {} {} doesn't divide a day evenly", count, unit))),
+    };
+    Some(Ok((0..24 * 60 / minutes)
+        .filter_map(|i| NaiveTime::from_hms_opt(i * minutes / 60, i * minutes % 60, 0))
+        .collect()))
+}
+
+fn days(words: &[&str]) -> Result<Days, String> {
+    let unknown = || format!("Can't tell which days '{}' means", words.join(" "));
+    let monthly = words.iter().any(|w| matches!(*w, "month" | "monthly"));
+    let words: Vec<&str> = words
+        .iter()
+        .copied()
+        .filter(|w| !matches!(*w, "every" | "each" | "on" | "the" | "of" | "and" | "month" | "monthly"))
+        .collect();
+
+    if monthly {
+        // `last day of the month` is just `last`
+        let words: Vec<&str> = words.into_iter().filter(|w| *w != "day").collect();
+        return match words[..] {
+            [] => Ok(Days::MonthDays(vec![1])),
+            [nth, day] if weekday(day).is_some() => {
+                let nth = ordinal(nth).filter(|n| *n <= 5).ok_or_else(unknown)?;
+                let day = Weekday::try_from(((weekday(day).unwrap() + 6) % 7) as u8).map_err(|_| unknown())?;
+                Ok(Days::NthWeekday(nth, day))
+            }
+            [_, ..] => words
+                .iter()
+                .copied()
+                .map(ordinal)
+                .collect::<Option<Vec<_>>>()
+                .map(Days::MonthDays)
+                .ok_or_else(unknown),
+        };
+    }
+
+    match words[..] {
+        [] | ["day"] | ["daily"] | ["everyday"] => Ok(Days::Every),
+        ["weekday"] | ["weekdays"] => Ok(Days::Weekdays(0b0111110)),
+        ["weekend"] | ["weekends"] => Ok(Days::Weekdays(0b1000001)),
+        _ => words
+            .iter()
+            .map(|w| weekday(w).map(|d| 1u8 << d))
+            .collect::<Option<Vec<_>>>()
+            .map(|bits| Days::Weekdays(bits.into_iter().fold(0, |mask, bit| mask | bit)))
+            .ok_or_else(unknown),
+    }
+}
+
+impl Recurrence {
+    pub fn parse(phrase: &str) -> Result<Self, String> {
+        let phrase = phrase.to_lowercase().replace(',', " ");
+        let words: Vec<&str> = phrase.split_whitespace().collect();
+        if let Some(times) = interval(&words) {
+            return Ok(Self {
+                days: Days::Every,
+                times: times?,
+            });
+        }
+
+        let at = words
+            .iter()
+            .position(|w| *w == "at")
+            .ok_or_else(|| "Say when in the day it runs, e.g. 'at 9am'".to_string())?;
+        let mut times = words[at + 1..]
+            .join("")
+            .split("and")
+            .map(time)
+            .collect::<Result<Vec<_>, _>>()?;
+        times.sort();
+        times.dedup();
+        Ok(Self {
+            days: days(&words[..at])?,
+            times,
+        })
+    }
+
+    fn on(&self, date: NaiveDate) -> bool {
+        let last_day = date
+            .with_day(1)
+            .and_then(|d| d.checked_add_months(chrono::Months::new(1)))
+            .and_then(|d| d.pred_opt())
+            .map_or(31, |d| d.day());
+        match &self.days {
+            Days::Every => true,
+            Days::Weekdays(mask) => mask & (1 << date.weekday().num_days_from_sunday()) != 0,
+            Days::MonthDays(days) => days
+                .iter()
+                .any(|&d| if d < 0 { date.day() == last_day } else { date.day() == d as u32 }),
+            Days::NthWeekday(nth, weekday) => {
+                date.weekday() == *weekday
+                    && if *nth < 0 {
+                        date.day() + 7 > last_day
+                    } else {
+                        (date.day() - 1) / 7 + 1 == *nth as u32
+                    }
+            }
+        }
+    }
+
+    /// The instant a local time fires at in `tz`, shifting times in a DST
+    /// gap to the end of the gap.
+    fn resolve<Z: TimeZone>(tz: &Z, local: NaiveDateTime) -> Option<DateTime<Z>> {
+        (0..=180).find_map(|shift| tz.from_local_datetime(&(local + Duration::minutes(shift))).earliest())
+    }
+
+    /// The next `count` run times after `from`, looking at most `horizon`
+    /// ahead.
+    pub fn upcoming<Z: TimeZone>(&self, from: DateTime<Z>, count: usize, horizon: Duration) -> Vec<DateTime<Z>> {
+        let tz = from.timezone();
+        let end = from.clone() + horizon;
+        let mut runs = Vec::new();
+        let mut date = from.naive_local().date();
+        while runs.len() < count && date.and_time(NaiveTime::MIN) <= end.naive_local() {
+            if self.on(date) {
+                for time in &self.times {
+                    let Some(run) = Self::resolve(&tz, date.and_time(*time)) else {
+                        continue;
+                    };
+                    // A DST shift can land two times on the same instant
+                    if run > from && run <= end && runs.last() != Some(&run) && runs.len() < count {
+                        runs.push(run);
+                    }
+                }
+            }
+            let Some(next) = date.succ_opt() else {
+                break;
+            };
+            date = next;
+        }
+        runs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_phrases() {
+        let at = |h, m| NaiveTime::from_hms_opt(h, m, 0).unwrap();
+        let weekdays = Recurrence::parse("Weekdays at 9am and 5:30 PM").unwrap();
+        assert_eq!(weekdays.days, Days::Weekdays(0b0111110));
+        assert_eq!(weekdays.times, vec![at(9, 0), at(17, 30)]);
+
+        assert_eq!(
+            Recurrence::parse("first Monday of the month at 09:00").unwrap().days,
+            Days::NthWeekday(1, Weekday::Mon)
+        );
+        assert_eq!(
+            Recurrence::parse("on the 1st and 15th of each month at noon").unwrap().days,
+            Days::MonthDays(vec![1, 15])
+        );
+        assert_eq!(
+            Recurrence::parse("tues, thurs at 7pm").unwrap().days,
+            Days::Weekdays((1 << 2) | (1 << 4))
+        );
+        assert_eq!(
+            Recurrence::parse("last day of the month at 23:00").unwrap().days,
+            Days::MonthDays(vec![-1])
+        );
+        assert_eq!(Recurrence::parse("every 15 minutes").unwrap().times.len(), 96);
+
+        assert!(Recurrence::parse("every monday").is_err());
+        assert!(Recurrence::parse("every day at 13pm").is_err());
+        assert!(Recurrence::parse("every blursday at 9am").is_err());
+        assert!(Recurrence::parse("every 7 minutes").is_err());
+        assert!(Recurrence::parse("every 4294967295 hours").is_err());
+    }
+
+    #[test]
+    fn test_upcoming_across_dst() {
+        let tz = chrono_tz::America::New_York;
+        let daily = Recurrence::parse("every day at 2:30am").unwrap();
+        // Clocks went from 2:00 to 3:00 on 2024-03-10
+        let from = tz.with_ymd_and_hms(2024, 3, 9, 12, 0, 0).unwrap();
+        let runs = daily.upcoming(from, 3, Duration::days(7));
+        let shown: Vec<String> = runs.iter().map(|t| t.format("%m-%d %H:%M").to_string()).collect();
+        assert_eq!(shown, ["03-10 03:00", "03-11 02:30", "03-12 02:30"]);
+
+        let first_monday = Recurrence::parse("first monday of the month at 9am").unwrap();
+        let from = tz.with_ymd_and_hms(2024, 6, 1, 0, 0, 0).unwrap();
+        let runs = first_monday.upcoming(from, 2, Duration::days(62));
+        let shown: Vec<String> = runs.iter().map(|t| t.format("%Y-%m-%d").to_string()).collect();
+        assert_eq!(shown, ["2024-06-03", "2024-07-01"]);
+    }
+}
@@ -0,0 +1,126 @@
+//! Browser context from the extension: open tabs, page text, bookmarks and
+//! history.
+//!
+//! The bridge can't see the browser itself, so it asks the extension. A
+//! request is emitted as a `browser/request` event carrying an ID, a method
+//! and its params, to the extension's native messaging connection alone,
+//! and only that connection may answer it with `browser.respond`. Servers
+//! reach this through the `harbor:mcp/browser` import, if granted
+//! `browser` (see `crate::wasm`).
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use tokio::sync::oneshot;
+
+use crate::events;
+use crate::rpc::RpcError;
+
+/// What the extension can be asked.
+pub const METHODS: [&str; 4] = ["tabs.list", "tab.readContent", "bookmarks.search", "history.search"];
+
+/// How long to wait for the extension's answer. Reading a page injects a
+/// script into it, which a busy tab can be slow to run.
+const TIMEOUT: Duration = Duration::from_secs(15);
+
+type Answer = Result<serde_json::Value, String>;
+
+lazy_static::lazy_static! {
+    /// Requests waiting for the extension, keyed by request ID
+    static ref PENDING: Mutex<HashMap<String, oneshot::Sender<Answer>>> = Mutex::new(HashMap::new());
+}
+
+/// Ask the extension `method` on behalf of `server_id` and wait for the
+/// answer.
+pub async fn request(server_id: &str, method: &str, params: serde_json::Value) -> Answer {
+    if !METHODS.contains(&method) {
+        return Err(format!("Unknown browser method '{}'; use one of {}", method, METHODS.join(", ")));
+    }
+    if !events::extension_connected() {
+        return Err("The browser extension isn't connected".to_string());
+    }
+    let request_id = format!("{:016x}", rand::random::<u64>());
+    let (tx, rx) = oneshot::channel();
+    PENDING.lock().unwrap().insert(request_id.clone(), tx);
+
+    events::emit_to_extension(
+        "browser/request",
+        serde_json::json!({
+            "request_id": request_id,
+            "server_id": server_id,
+            "method": method,
+            "params": params,
+        }),
+    );
+
+    let answer = tokio::time::timeout(TIMEOUT, rx).await;
+    PENDING.lock().unwrap().remove(&request_id);
+    match answer {
+        Ok(Ok(answer)) => answer,
+        Ok(Err(_)) => Err("The browser request was cancelled".to_string()),
+        Err(_) => Err(format!("Timed out waiting for the browser to answer {}", method)),
+    }
+}
+
+// ============================================================================
+// RPC Handlers
+// ============================================================================
+
+/// Answer a `browser/request`: `{ request_id, result }` or
+/// `{ request_id, error }`.
+pub async fn rpc_respond(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    crate::rpc::require_native_messaging("answer browser requests")?;
+    let request_id = params
+        .get("request_id")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| RpcError::invalid_params("Missing 'request_id' parameter"))?;
+    let answer = match params.get("error") {
+        Some(error) => Err(error.as_str().map_or_else(|| error.to_string(), str::to_string)),
+        None => Ok(params.get("result").cloned().unwrap_or(serde_json::Value::Null)),
+    };
+
+    let sender = PENDING
+        .lock()
+        .unwrap()
+        .remove(request_id)
+        .ok_or_else(|| RpcError::new(-32000, "Unknown or expired browser request"))?;
+    let _ = sender.send(answer);
+
+    Ok(serde_json::json!({ "success": true }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_request_checks_method() {
+        let error = request("mcp-browser", "cookies.list", serde_json::json!({})).await.unwrap_err();
+        assert!(error.contains("Unknown browser method"));
+    }
+
+    #[tokio::test]
+    async fn test_request_round_trip() {
+        let mut events = events::subscribe_extension();
+        let pending = tokio::spawn(request("mcp-browser", "tabs.list", serde_json::json!({})));
+        let event = loop {
+            let event = events.recv().await.unwrap();
+            if event.event == "browser/request" {
+                break event;
+            }
+        };
+        assert_eq!(event.payload["method"], "tabs.list");
+        assert_eq!(event.payload["server_id"], "mcp-browser");
+
+        let request_id = event.payload["request_id"].clone();
+        let tabs = serde_json::json!([{ "id": 1, "title": "Harbor" }]);
+        let answer = serde_json::json!({ "request_id": request_id, "result": tabs.clone() });
+        crate::rpc::as_extension(rpc_respond(answer)).await.unwrap();
+        assert_eq!(pending.await.unwrap().unwrap(), tabs);
+
+        let answer = serde_json::json!({ "request_id": request_id, "result": [] });
+        let expired = crate::rpc::as_extension(rpc_respond(answer)).await;
+        assert!(expired.is_err());
+    }
+}
@@ -0,0 +1,393 @@
+//! Per-server cost tracking and daily budgets.
+//!
+//! Servers declare an approximate cost per tool call (from their manifest's
+//! `costs`), in a unit such as `usd` or `credits`. The bridge adds up the
+//! cost of each call per server per day (UTC) and checks it against the
+//! budget the user set for that server. Crossing the warning threshold emits
+//! `budget/warning`. A call that would exceed the budget is blocked, or
+//! allowed only once the caller confirms it, depending on the budget's
+//! `on_exceed` setting.
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+
+use crate::events;
+use crate::rpc::RpcError;
+use crate::store::{JsonStore, Stored};
+
+/// Days of usage kept per server.
+const USAGE_RETENTION_DAYS: i64 = 30;
+
+/// Error code for calls refused by a budget.
+pub const BUDGET_EXCEEDED: i64 = -32010;
+
+/// Declared cost of a server's tool calls.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CostConfig {
+    /// Unit costs are expressed in (e.g. `usd`, `credits`)
+    #[serde(default = "default_unit")]
+    pub unit: String,
+    /// Cost of a call to a tool not listed in `tools`
+    #[serde(default)]
+    pub default: f64,
+    /// Cost per call, by tool name
+    #[serde(default)]
+    pub tools: HashMap<String, f64>,
+}
+
+fn default_unit() -> String {
+    "usd".to_string()
+}
+
+impl CostConfig {
+    fn cost_of(&self, tool: &str) -> f64 {
+        self.tools.get(tool).copied().unwrap_or(self.default)
+    }
+}
+
+/// What to do with a call that would exceed the budget.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OnExceed {
+    /// Require the caller to confirm the call
+    #[default]
+    Confirm,
+    /// Refuse the call
+    Block,
+    /// Allow the call (warnings only)
+    Allow,
+}
+
+/// A user's daily budget for a server.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Budget {
+    /// Daily limit, in the server's cost unit
+    pub daily_limit: f64,
+    /// Fraction of the limit at which to warn
+    #[serde(default = "default_warn_at")]
+    pub warn_at: f64,
+    #[serde(default)]
+    pub on_exceed: OnExceed,
+}
+
+fn default_warn_at() -> f64 {
+    0.8
+}
+
+/// Cost and call count for one server on one day.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct DayUsage {
+    pub cost: f64,
+    pub calls: u64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct BudgetFile {
+    /// Budgets keyed by server ID
+    #[serde(default)]
+    budgets: HashMap<String, Budget>,
+    /// Usage keyed by server ID, then date (`YYYY-MM-DD`, UTC)
+    #[serde(default)]
+    usage: HashMap<String, BTreeMap<String, DayUsage>>,
+}
+
+impl Stored for BudgetFile {
+    const FILE_NAME: &'static str = "budgets.json";
+}
+
+/// Budget subsystem state.
+#[derive(Default)]
+pub struct BudgetState {
+    /// Declared costs keyed by server ID
+    costs: RwLock<HashMap<String, CostConfig>>,
+    /// Budgets and usage
+    file: JsonStore<BudgetFile>,
+}
+
+fn state() -> &'static BudgetState {
+    &crate::state::get().budget
+}
+
+fn today() -> String {
+    chrono::Utc::now().format("%Y-%m-%d").to_string()
+}
+
+/// Record a server's declared costs.
+pub async fn declare_costs(server_id: &str, costs: CostConfig) {
+    state().costs.write().await.insert(server_id.to_string(), costs);
+}
+
+/// Forget a server's declared costs.
+pub async fn remove_costs(server_id: &str) {
+    state().costs.write().await.remove(server_id);
+}
+
+/// Estimated cost of a tool call, against the server's budget.
+#[derive(Debug, Clone, Serialize)]
+pub struct Estimate {
+    pub cost: f64,
+    pub unit: String,
+    /// The server's daily limit, if it has a budget
+    pub daily_limit: Option<f64>,
+    pub spent_today: f64,
+}
+
+/// Estimate a tool call's cost, if the server declared costs.
+pub async fn estimate(server_id: &str, tool: &str) -> Option<Estimate> {
+    let costs = state().costs.read().await.get(server_id).cloned()?;
+    let date = today();
+    let (daily_limit, spent_today) = state()
+        .file
+        .read(|file| {
+            let spent = file
+                .usage
+                .get(server_id)
+                .and_then(|days| days.get(&date))
+                .map_or(0.0, |u| u.cost);
+            (file.budgets.get(server_id).map(|b| b.daily_limit), spent)
+        })
+        .await;
+    Some(Estimate {
+        cost: costs.cost_of(tool),
+        unit: costs.unit,
+        daily_limit,
+        spent_today,
+    })
+}
+
+/// A call that has passed the budget check, with its cost held against the
+/// day's usage until it is settled.
+pub struct Charge {
+    server_id: String,
+    cost: f64,
+    date: String,
+}
+
+/// A threshold a server's spending crossed: the event, the limit and what
+/// is now spent.
+type Crossing = (&'static str, f64, f64);
+
+/// Add `cost` to a server's usage on `date`, dropping usage older than the
+/// retention window. Returns the threshold crossed, if any.
+fn add_usage(file: &mut BudgetFile, server_id: &str, date: &str, cost: f64) -> Option<Crossing> {
+    let days = file.usage.entry(server_id.to_string()).or_default();
+    let usage = days.entry(date.to_string()).or_default();
+    let before = usage.cost;
+    usage.cost += cost;
+    let after = usage.cost;
+
+    let cutoff = (chrono::Utc::now() - chrono::Duration::days(USAGE_RETENTION_DAYS))
+        .format("%Y-%m-%d")
+        .to_string();
+    days.retain(|day, _| *day >= cutoff);
+
+    let budget = file.budgets.get(server_id)?;
+    let threshold = budget.daily_limit * budget.warn_at;
+    if before < budget.daily_limit && after >= budget.daily_limit {
+        Some(("budget/exceeded", budget.daily_limit, after))
+    } else if before < threshold && after >= threshold {
+        Some(("budget/warning", budget.daily_limit, after))
+    } else {
+        None
+    }
+}
+
+/// Check whether a tool call fits in the server's budget, and hold its cost
+/// against today's usage.
+///
+/// The check and the hold happen under one lock, so calls made at the same
+/// time can't all fit in what is left of the budget. Settle the returned
+/// charge once the call has run. `confirmed` is the caller's confirmation
+/// for calls over a `confirm` budget.
+pub async fn check(server_id: &str, tool: &str, confirmed: bool) -> Result<Option<Charge>, RpcError> {
+    let Some(costs) = state().costs.read().await.get(server_id).cloned() else {
+        return Ok(None);
+    };
+    let cost = costs.cost_of(tool);
+    if cost <= 0.0 {
+        return Ok(None);
+    }
+
+    let date = today();
+    let crossed = state()
+        .file
+        .update(|file| {
+            let spent = file
+                .usage
+                .get(server_id)
+                .and_then(|days| days.get(&date))
+                .map_or(0.0, |u| u.cost);
+            if let Some(budget) = file.budgets.get(server_id).filter(|b| spent + cost > b.daily_limit) {
+                let detail = format!(
+                    "'{}' costs {} {}; {} of the {} {} daily budget for '{}' is already spent",
+                    tool, cost, costs.unit, spent, budget.daily_limit, costs.unit, server_id
+                );
+                match budget.on_exceed {
+                    OnExceed::Block => {
+                        return Err(RpcError::new(BUDGET_EXCEEDED, format!("Budget exceeded: {}", detail)));
+                    }
+                    OnExceed::Confirm if !confirmed => {
+                        return Err(RpcError::new(
+                            BUDGET_EXCEEDED,
+                            format!("Budget exceeded, confirmation required: {}", detail),
+                        ));
+                    }
+                    _ => {}
+                }
+            }
+            Ok(add_usage(file, server_id, &date, cost))
+        })
+        .await?;
+
+    if let Some((event, limit, spent)) = crossed {
+        tracing::warn!("Server {} has spent {} of its {} {} budget", server_id, spent, limit, costs.unit);
+        events::emit(
+            event,
+            serde_json::json!({
+                "server_id": server_id,
+                "spent": spent,
+                "limit": limit,
+                "unit": costs.unit,
+            }),
+        );
+    }
+
+    Ok(Some(Charge {
+        server_id: server_id.to_string(),
+        cost,
+        date,
+    }))
+}
+
+/// Settle a charge once its call has run: a call that succeeded is counted,
+/// and one that failed gets its cost back.
+pub async fn settle(charge: Charge, succeeded: bool) {
+    state()
+        .file
+        .update(|file| {
+            let days = file.usage.entry(charge.server_id.clone()).or_default();
+            let usage = days.entry(charge.date.clone()).or_default();
+            if succeeded {
+                usage.calls += 1;
+            } else {
+                usage.cost = (usage.cost - charge.cost).max(0.0);
+            }
+        })
+        .await;
+}
+
+// ============================================================================
+// RPC Handlers
+// ============================================================================
+
+fn server_id_param(params: &serde_json::Value) -> Result<&str, RpcError> {
+    params
+        .get("server_id")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| RpcError::invalid_params("Missing 'server_id' parameter"))
+}
+
+/// Declare costs for a server not started through `js.start_server`:
+/// `{ server_id, costs: { unit, default, tools } }`.
+pub async fn rpc_declare_costs(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    crate::rpc::require_extension("change budgets")?;
+    let server_id = server_id_param(&params)?;
+    let costs: CostConfig = serde_json::from_value(params.get("costs").cloned().unwrap_or_default())
+        .map_err(|e| RpcError::invalid_params(format!("Invalid costs: {}", e)))?;
+    declare_costs(server_id, costs).await;
+    Ok(serde_json::json!({ "success": true }))
+}
+
+/// Set a server's daily budget: `{ server_id, daily_limit, warn_at?, on_exceed? }`.
+pub async fn rpc_set_budget(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    crate::rpc::require_extension("change budgets")?;
+    let server_id = server_id_param(&params)?.to_string();
+    let budget: Budget = serde_json::from_value(params)
+        .map_err(|e| RpcError::invalid_params(format!("Invalid budget: {}", e)))?;
+    if budget.daily_limit < 0.0 || !(0.0..=1.0).contains(&budget.warn_at) {
+        return Err(RpcError::invalid_params("daily_limit must be >= 0 and warn_at between 0 and 1"));
+    }
+
+    state()
+        .file
+        .update(|file| {
+            file.budgets.insert(server_id, budget);
+        })
+        .await;
+    Ok(serde_json::json!({ "success": true }))
+}
+
+/// Remove a server's budget.
+pub async fn rpc_remove_budget(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    crate::rpc::require_extension("change budgets")?;
+    let server_id = server_id_param(&params)?;
+    state()
+        .file
+        .update(|file| {
+            file.budgets.remove(server_id);
+        })
+        .await;
+    Ok(serde_json::json!({ "success": true }))
+}
+
+/// Today's spend, budget and declared costs for every known server.
+pub async fn rpc_status(_params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    let costs = state().costs.read().await.clone();
+    let date = today();
+
+    let servers = state()
+        .file
+        .read(|file| {
+            let mut ids: Vec<&String> = costs.keys().chain(file.budgets.keys()).collect();
+            ids.sort();
+            ids.dedup();
+            ids.into_iter()
+                .map(|id| {
+                    let today = file
+                        .usage
+                        .get(id)
+                        .and_then(|days| days.get(&date))
+                        .copied()
+                        .unwrap_or_default();
+                    serde_json::json!({
+                        "server_id": id,
+                        "costs": costs.get(id),
+                        "budget": file.budgets.get(id),
+                        "today": today,
+                    })
+                })
+                .collect::<Vec<_>>()
+        })
+        .await;
+
+    Ok(serde_json::json!({
+        "date": date,
+        "servers": servers,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cost_of_tool() {
+        let costs: CostConfig = serde_json::from_value(serde_json::json!({
+            "unit": "credits",
+            "default": 1.0,
+            "tools": { "search": 5.0 },
+        }))
+        .unwrap();
+        assert_eq!(costs.cost_of("search"), 5.0);
+        assert_eq!(costs.cost_of("lookup"), 1.0);
+    }
+
+    #[test]
+    fn test_budget_defaults() {
+        let budget: Budget = serde_json::from_value(serde_json::json!({ "daily_limit": 2.5 })).unwrap();
+        assert_eq!(budget.warn_at, 0.8);
+        assert_eq!(budget.on_exceed, OnExceed::Confirm);
+    }
+}
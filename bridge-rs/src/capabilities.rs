@@ -0,0 +1,144 @@
+//! Machine-readable changelog of what the bridge offers.
+//!
+//! `capabilities.json` records, for each bridge version, the RPC methods,
+//! events and capabilities it added, deprecated or removed. It is embedded
+//! at build time, so the extension and other clients can ask any bridge
+//! what it supports instead of probing it, and adapt across mixed-version
+//! installs. New RPC methods go in the entry for the current version; a
+//! test checks the file against the handler registry.
+
+use std::collections::BTreeSet;
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+
+use crate::rpc::RpcError;
+
+const CHANGELOG: &str = include_str!("../capabilities.json");
+
+/// Names added or removed in a release.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Changes {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub methods: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub events: Vec<String>,
+    /// Behaviors not visible as a method or event, e.g. `fs.write.mode`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub capabilities: Vec<String>,
+}
+
+/// Something still supported but on its way out.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Deprecation {
+    /// A method, event or capability; `method:param` for a parameter
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replacement: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Release {
+    pub version: String,
+    #[serde(default)]
+    pub added: Changes,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub deprecated: Vec<Deprecation>,
+    #[serde(default)]
+    pub removed: Changes,
+}
+
+#[derive(Debug, Deserialize)]
+struct Changelog {
+    /// Newest first
+    releases: Vec<Release>,
+}
+
+fn releases() -> &'static [Release] {
+    static RELEASES: OnceLock<Vec<Release>> = OnceLock::new();
+    RELEASES.get_or_init(|| match serde_json::from_str::<Changelog>(CHANGELOG) {
+        Ok(changelog) => changelog.releases,
+        Err(e) => {
+            tracing::error!("Embedded capabilities changelog is invalid: {}", e);
+            Vec::new()
+        }
+    })
+}
+
+/// `1.10.2` as `[1, 10, 2]`, for ordering versions.
+fn version_key(version: &str) -> Vec<u64> {
+    version
+        .split(['.', '-'])
+        .map_while(|part| part.parse().ok())
+        .collect()
+}
+
+/// What this bridge supports now: everything added and not since removed.
+fn current() -> Changes {
+    let mut methods = BTreeSet::new();
+    let mut events = BTreeSet::new();
+    let mut capabilities = BTreeSet::new();
+    // Oldest first, so a later removal wins over an earlier addition
+    for release in releases().iter().rev() {
+        methods.extend(release.added.methods.iter().cloned());
+        events.extend(release.added.events.iter().cloned());
+        capabilities.extend(release.added.capabilities.iter().cloned());
+        release.removed.methods.iter().for_each(|m| {
+            methods.remove(m);
+        });
+        release.removed.events.iter().for_each(|e| {
+            events.remove(e);
+        });
+        release.removed.capabilities.iter().for_each(|c| {
+            capabilities.remove(c);
+        });
+    }
+    Changes {
+        methods: methods.into_iter().collect(),
+        events: events.into_iter().collect(),
+        capabilities: capabilities.into_iter().collect(),
+    }
+}
+
+/// The capabilities changelog: `{ since? }`.
+/// With `since`, only releases newer than that version are returned.
+pub async fn rpc_changelog(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    let since = params.get("since").and_then(|v| v.as_str()).map(version_key);
+    let releases: Vec<&Release> = releases()
+        .iter()
+        .filter(|release| since.is_none() || Some(version_key(&release.version)) > since)
+        .collect();
+    Ok(serde_json::json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "current": current(),
+        "releases": releases,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_changelog_matches_registry() {
+        assert_eq!(releases()[0].version, env!("CARGO_PKG_VERSION"));
+        assert!(releases()
+            .windows(2)
+            .all(|pair| version_key(&pair[0].version) > version_key(&pair[1].version)));
+
+        let listed: BTreeSet<String> = current().methods.into_iter().collect();
+        let registered: BTreeSet<String> = crate::rpc::method_names().into_iter().map(String::from).collect();
+        let missing: Vec<_> = registered.difference(&listed).collect();
+        let stale: Vec<_> = listed.difference(&registered).collect();
+        assert!(missing.is_empty(), "Methods missing from capabilities.json: {:?}", missing);
+        assert!(stale.is_empty(), "Methods in capabilities.json but not registered: {:?}", stale);
+    }
+
+    #[test]
+    fn test_version_key() {
+        assert!(version_key("0.10.0") > version_key("0.9.3"));
+        assert_eq!(version_key("1.2.0-beta"), vec![1, 2, 0]);
+    }
+}
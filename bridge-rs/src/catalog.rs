@@ -0,0 +1,450 @@
+//! Tool catalog: the last known tools and input schemas of each server.
+//!
+//! Whenever a server reports its tools (`mcp.register_tools`, or a
+//! `tools/list` response from a JS server), the list is compared with the
+//! previous one. Removed tools and changed input schemas are recorded and
+//! announced as `catalog/schema_changed`.
+//!
+//! The extension tells the catalog which tools its scheduled jobs and
+//! pipelines use (`catalog.set_references`). With `hold_breaking_changes`
+//! on, a registered tool list that breaks a referenced tool is held back
+//! until the user confirms it (`catalog.confirm_change`).
+//!
+//! Everything is kept in `~/.harbor/tool_catalog.json`.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use serde::{Deserialize, Serialize};
+
+use crate::events;
+use crate::rpc::RpcError;
+use crate::store::{self, JsonStore, Stored};
+
+/// Schema changes kept in the log.
+const MAX_CHANGES: usize = 100;
+
+/// Tools and schemas last seen for a server.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ServerCatalog {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// Input schema by tool name
+    pub tools: BTreeMap<String, serde_json::Value>,
+    /// When the list was last updated (Unix timestamp ms)
+    pub updated_at: i64,
+}
+
+/// How one tool's input schema changed.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SchemaDiff {
+    pub tool: String,
+    pub added_properties: Vec<String>,
+    pub removed_properties: Vec<String>,
+    /// Properties whose `type` changed
+    pub retyped_properties: Vec<String>,
+    /// Properties that became required
+    pub newly_required: Vec<String>,
+}
+
+/// Difference between two tool lists.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ToolDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<SchemaDiff>,
+}
+
+impl ToolDiff {
+    /// Whether existing callers may break.
+    pub fn is_breaking(&self) -> bool {
+        !self.removed.is_empty() || !self.changed.is_empty()
+    }
+
+    /// Tools that were removed or changed.
+    fn affected(&self) -> impl Iterator<Item = &str> {
+        self.removed
+            .iter()
+            .map(String::as_str)
+            .chain(self.changed.iter().map(|c| c.tool.as_str()))
+    }
+}
+
+/// A recorded schema change.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SchemaChange {
+    pub server_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from_version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub to_version: Option<String>,
+    /// When the change was seen (Unix timestamp ms)
+    pub detected_at: i64,
+    pub diff: ToolDiff,
+    /// Owners (jobs, pipelines) referencing an affected tool
+    pub referenced_by: Vec<String>,
+    /// Whether the new tool list is waiting for confirmation
+    pub held: bool,
+}
+
+/// A tool used by a job or pipeline.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolRef {
+    pub server_id: String,
+    pub tool: String,
+}
+
+/// A held tool list, waiting for confirmation.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PendingChange {
+    catalog: ServerCatalog,
+    /// The tool list as registered, re-applied on confirmation
+    tools: serde_json::Value,
+    change: SchemaChange,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CatalogFile {
+    #[serde(default)]
+    servers: HashMap<String, ServerCatalog>,
+    #[serde(default)]
+    changes: Vec<SchemaChange>,
+    /// Tools used, keyed by owner (a job or pipeline ID)
+    #[serde(default)]
+    references: HashMap<String, Vec<ToolRef>>,
+    #[serde(default)]
+    pending: HashMap<String, PendingChange>,
+    /// Hold registered tool lists that break a referenced tool
+    #[serde(default)]
+    hold_breaking_changes: bool,
+}
+
+impl Stored for CatalogFile {
+    const FILE_NAME: &'static str = "tool_catalog.json";
+}
+
+/// Catalog subsystem state.
+#[derive(Default)]
+pub struct CatalogState {
+    /// Catalog, loaded on first use
+    file: JsonStore<CatalogFile>,
+}
+
+fn state() -> &'static CatalogState {
+    &crate::state::get().catalog
+}
+
+/// Number of servers in the catalog, read from disk.
+pub fn server_count() -> usize {
+    store::read_now::<CatalogFile>().servers.len()
+}
+
+fn property_names(schema: &serde_json::Value, key: &str) -> BTreeSet<String> {
+    match schema.get(key) {
+        Some(serde_json::Value::Object(map)) => map.keys().cloned().collect(),
+        Some(serde_json::Value::Array(items)) => items.iter().filter_map(|v| v.as_str()).map(String::from).collect(),
+        _ => BTreeSet::new(),
+    }
+}
+
+/// Compare one tool's old and new input schemas.
+fn diff_schema(tool: &str, old: &serde_json::Value, new: &serde_json::Value) -> Option<SchemaDiff> {
+    if old == new {
+        return None;
+    }
+    let old_props = property_names(old, "properties");
+    let new_props = property_names(new, "properties");
+    let property_type = |schema: &serde_json::Value, name: &str| schema.get("properties")?.get(name)?.get("type").cloned();
+
+    let diff = SchemaDiff {
+        tool: tool.to_string(),
+        added_properties: new_props.difference(&old_props).cloned().collect(),
+        removed_properties: old_props.difference(&new_props).cloned().collect(),
+        retyped_properties: old_props
+            .intersection(&new_props)
+            .filter(|name| property_type(old, name) != property_type(new, name))
+            .cloned()
+            .collect(),
+        newly_required: property_names(new, "required")
+            .difference(&property_names(old, "required"))
+            .cloned()
+            .collect(),
+    };
+    // Descriptions and other cosmetic edits don't count
+    let unchanged = diff.added_properties.is_empty()
+        && diff.removed_properties.is_empty()
+        && diff.retyped_properties.is_empty()
+        && diff.newly_required.is_empty();
+    (!unchanged).then_some(diff)
+}
+
+/// Compare two tool lists (input schema by tool name).
+pub fn diff_tools(old: &BTreeMap<String, serde_json::Value>, new: &BTreeMap<String, serde_json::Value>) -> ToolDiff {
+    ToolDiff {
+        added: new.keys().filter(|t| !old.contains_key(*t)).cloned().collect(),
+        removed: old.keys().filter(|t| !new.contains_key(*t)).cloned().collect(),
+        changed: old
+            .iter()
+            .filter_map(|(tool, schema)| diff_schema(tool, schema, new.get(tool)?))
+            .collect(),
+    }
+}
+
+/// Whether a new tool list was applied or held for confirmation.
+pub enum Observed {
+    Applied,
+    Held(SchemaChange),
+}
+
+/// Record a server's current tools (input schema by tool name), announcing
+/// breaking changes. `tools` is the list as registered, kept so a held list
+/// can be re-applied. Pass `can_hold` where the caller can defer applying
+/// the list.
+pub async fn observe(
+    server_id: &str,
+    version: Option<String>,
+    schemas: BTreeMap<String, serde_json::Value>,
+    tools: serde_json::Value,
+    can_hold: bool,
+) -> Observed {
+    let now = chrono::Utc::now().timestamp_millis();
+    let catalog = ServerCatalog {
+        version,
+        tools: schemas,
+        updated_at: now,
+    };
+
+    let change = state()
+        .file
+        .update(|file| {
+            let Some(previous) = file.servers.get(server_id) else {
+                // First sighting: nothing to compare with
+                file.servers.insert(server_id.to_string(), catalog);
+                return None;
+            };
+            let diff = diff_tools(&previous.tools, &catalog.tools);
+            if !diff.is_breaking() {
+                file.servers.insert(server_id.to_string(), catalog);
+                return None;
+            }
+
+            let affected: BTreeSet<&str> = diff.affected().collect();
+            let mut referenced_by: Vec<String> = file
+                .references
+                .iter()
+                .filter(|(_, refs)| {
+                    refs.iter()
+                        .any(|r| r.server_id == server_id && affected.contains(r.tool.as_str()))
+                })
+                .map(|(owner, _)| owner.clone())
+                .collect();
+            referenced_by.sort();
+
+            let held = can_hold && file.hold_breaking_changes && !referenced_by.is_empty();
+            let change = SchemaChange {
+                server_id: server_id.to_string(),
+                from_version: previous.version.clone(),
+                to_version: catalog.version.clone(),
+                detected_at: now,
+                diff,
+                referenced_by,
+                held,
+            };
+
+            file.changes.push(change.clone());
+            if file.changes.len() > MAX_CHANGES {
+                let excess = file.changes.len() - MAX_CHANGES;
+                file.changes.drain(..excess);
+            }
+            if held {
+                file.pending.insert(
+                    server_id.to_string(),
+                    PendingChange {
+                        catalog,
+                        tools,
+                        change: change.clone(),
+                    },
+                );
+            } else {
+                file.servers.insert(server_id.to_string(), catalog);
+            }
+            Some(change)
+        })
+        .await;
+
+    let Some(change) = change else {
+        return Observed::Applied;
+    };
+    tracing::warn!(
+        "Tool schemas changed for {}: {} removed, {} changed{}",
+        server_id,
+        change.diff.removed.len(),
+        change.diff.changed.len(),
+        if change.held { " (held for confirmation)" } else { "" }
+    );
+    events::emit(
+        "catalog/schema_changed",
+        serde_json::to_value(&change).unwrap_or_default(),
+    );
+    if change.held {
+        Observed::Held(change)
+    } else {
+        Observed::Applied
+    }
+}
+
+// ============================================================================
+// RPC Handlers
+// ============================================================================
+
+fn server_id_param(params: &serde_json::Value) -> Result<&str, RpcError> {
+    params
+        .get("server_id")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| RpcError::invalid_params("Missing 'server_id' parameter"))
+}
+
+/// Known tools, for one `server_id` or every server.
+pub async fn rpc_get(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    let server_id = params.get("server_id").and_then(|v| v.as_str());
+    state()
+        .file
+        .read(|file| {
+            let servers: BTreeMap<&String, &ServerCatalog> = file
+                .servers
+                .iter()
+                .filter(|(id, _)| server_id.is_none() || server_id == Some(id.as_str()))
+                .collect();
+            Ok(serde_json::json!({ "servers": servers }))
+        })
+        .await
+}
+
+/// Recorded schema changes, newest first, optionally for one `server_id`,
+/// plus changes waiting for confirmation.
+pub async fn rpc_changes(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    let server_id = params.get("server_id").and_then(|v| v.as_str());
+    state()
+        .file
+        .read(|file| {
+            let changes: Vec<&SchemaChange> = file
+                .changes
+                .iter()
+                .rev()
+                .filter(|c| server_id.is_none() || server_id == Some(c.server_id.as_str()))
+                .collect();
+            let pending: Vec<&SchemaChange> = file.pending.values().map(|p| &p.change).collect();
+            Ok(serde_json::json!({ "changes": changes, "pending": pending }))
+        })
+        .await
+}
+
+/// Record the tools a job or pipeline uses, replacing earlier ones:
+/// `{ owner, tools: [{ server_id, tool }] }`. An empty list removes the owner.
+pub async fn rpc_set_references(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    crate::rpc::require_extension("change the tool catalog")?;
+    let owner = params
+        .get("owner")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| RpcError::invalid_params("Missing 'owner' parameter"))?
+        .to_string();
+    let tools: Vec<ToolRef> = serde_json::from_value(params.get("tools").cloned().unwrap_or_default())
+        .map_err(|e| RpcError::invalid_params(format!("Invalid tools: {}", e)))?;
+
+    state()
+        .file
+        .update(|file| {
+            if tools.is_empty() {
+                file.references.remove(&owner);
+            } else {
+                file.references.insert(owner, tools);
+            }
+        })
+        .await;
+    Ok(serde_json::json!({ "success": true }))
+}
+
+/// Turn holding of breaking changes on or off: `{ hold_breaking_changes }`.
+pub async fn rpc_configure(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    crate::rpc::require_extension("change the tool catalog")?;
+    let hold = params
+        .get("hold_breaking_changes")
+        .and_then(|v| v.as_bool())
+        .ok_or_else(|| RpcError::invalid_params("Missing 'hold_breaking_changes' parameter"))?;
+    state()
+        .file
+        .update(|file| {
+            file.hold_breaking_changes = hold;
+        })
+        .await;
+    Ok(serde_json::json!({ "hold_breaking_changes": hold }))
+}
+
+/// Apply a held tool list: `{ server_id }`.
+pub async fn rpc_confirm_change(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    crate::rpc::require_extension("change the tool catalog")?;
+    let server_id = server_id_param(&params)?;
+    let pending = state()
+        .file
+        .update(|file| {
+            let pending = file.pending.remove(server_id)?;
+            file.servers.insert(server_id.to_string(), pending.catalog.clone());
+            Some(pending)
+        })
+        .await
+        .ok_or_else(|| RpcError::new(-32000, format!("No held change for '{}'", server_id)))?;
+
+    crate::mcp::replace_tools(server_id, pending.tools)
+        .await
+        .map_err(RpcError::invalid_params)?;
+    Ok(serde_json::json!({ "applied": true }))
+}
+
+/// Discard a held tool list, keeping the previous tools: `{ server_id }`.
+pub async fn rpc_reject_change(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    crate::rpc::require_extension("change the tool catalog")?;
+    let server_id = server_id_param(&params)?;
+    let removed = state().file.update(|file| file.pending.remove(server_id).is_some()).await;
+    Ok(serde_json::json!({ "removed": removed }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_tools() {
+        let old: BTreeMap<String, serde_json::Value> = serde_json::from_value(serde_json::json!({
+            "search": {
+                "properties": { "query": { "type": "string" }, "limit": { "type": "number" } },
+                "required": ["query"],
+            },
+            "fetch": { "properties": { "url": { "type": "string" } } },
+            "ping": {},
+        }))
+        .unwrap();
+        let new: BTreeMap<String, serde_json::Value> = serde_json::from_value(serde_json::json!({
+            "search": {
+                "properties": { "query": { "type": "string" }, "limit": { "type": "string" }, "lang": {} },
+                "required": ["query", "lang"],
+            },
+            "fetch": { "description": "Fetch a URL", "properties": { "url": { "type": "string" } } },
+            "echo": {},
+        }))
+        .unwrap();
+
+        let diff = diff_tools(&old, &new);
+        assert_eq!(diff.added, vec!["echo"]);
+        assert_eq!(diff.removed, vec!["ping"]);
+        assert_eq!(
+            diff.changed,
+            vec![SchemaDiff {
+                tool: "search".to_string(),
+                added_properties: vec!["lang".to_string()],
+                removed_properties: vec![],
+                retyped_properties: vec!["limit".to_string()],
+                newly_required: vec!["lang".to_string()],
+            }]
+        );
+        assert!(diff.is_breaking());
+    }
+}
@@ -0,0 +1,73 @@
+//! Debounced file changes, for the loops that rebuild or reload something
+//! when files change (`dev`, hot reload, dev server builds).
+//!
+//! Changes come in batches: everything that changed until a quiet period
+//! passed without another change, so a build that writes a file in several
+//! steps, or an editor saving several files, is acted on once.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use notify::{EventKind, RecursiveMode, Watcher};
+use tokio::sync::mpsc;
+
+/// Quiet period after the last change that ends a batch.
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Watched paths, and the changes to them that matter.
+pub struct Changes {
+    watcher: notify::RecommendedWatcher,
+    rx: mpsc::UnboundedReceiver<PathBuf>,
+}
+
+impl Changes {
+    /// Start a watcher that reports a changed path when `wanted` says the
+    /// kind of change to it matters. Nothing is watched until `watch`.
+    pub fn new(wanted: impl Fn(&EventKind, &Path) -> bool + Send + 'static) -> Result<Self, String> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let watcher = notify::recommended_watcher(move |result: notify::Result<notify::Event>| match result {
+            Ok(event) => {
+                for path in event.paths.into_iter().filter(|path| wanted(&event.kind, path)) {
+                    let _ = tx.send(path);
+                }
+            }
+            Err(e) => tracing::warn!("File watch error: {}", e),
+        })
+        .map_err(|e| format!("Failed to create watcher: {}", e))?;
+        Ok(Self { watcher, rx })
+    }
+
+    pub fn watch(&mut self, path: &Path, mode: RecursiveMode) -> Result<(), String> {
+        self.watcher
+            .watch(path, mode)
+            .map_err(|e| format!("Failed to watch {:?}: {}", path, e))
+    }
+
+    /// The next batch of changed paths.
+    pub async fn next(&mut self) -> Option<HashSet<PathBuf>> {
+        let first = self.rx.recv().await?;
+        Some(self.batch(first).await)
+    }
+
+    /// Like `next`, but an empty batch if nothing changes within `wait`.
+    pub async fn next_within(&mut self, wait: Duration) -> Option<HashSet<PathBuf>> {
+        match tokio::time::timeout(wait, self.rx.recv()).await {
+            Ok(first) => Some(self.batch(first?).await),
+            Err(_) => Some(HashSet::new()),
+        }
+    }
+
+    async fn batch(&mut self, first: PathBuf) -> HashSet<PathBuf> {
+        let mut changed = HashSet::from([first]);
+        while let Ok(Some(path)) = tokio::time::timeout(DEBOUNCE, self.rx.recv()).await {
+            changed.insert(path);
+        }
+        changed
+    }
+}
+
+/// Whether an event is a file being written or replaced.
+pub fn is_write(kind: &EventKind) -> bool {
+    matches!(kind, EventKind::Create(_) | EventKind::Modify(_))
+}
@@ -0,0 +1,300 @@
+//! Fault injection for resilience testing.
+//!
+//! A developer mode, off by default and never persisted, in which the bridge
+//! misbehaves on purpose: tool calls are delayed, RPC responses to the
+//! extension are dropped, OAuth token refreshes fail and JS servers crash.
+//! Each fault fires with its own probability, so extension and server
+//! authors can watch their error handling against failures that look like
+//! the real thing. Give a `seed` to make a run repeatable.
+//!
+//! Every injected fault is logged and emitted as a `chaos/injected` event.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::rpc::RpcError;
+
+/// Longest latency that can be injected into a single call.
+const MAX_LATENCY_MS: u64 = 60_000;
+
+/// A kind of fault the bridge can inject.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Fault {
+    /// A tool call is delayed before it reaches the server
+    ToolLatency,
+    /// An RPC response to the extension is never sent
+    DroppedMessage,
+    /// An OAuth token refresh fails without contacting the provider
+    FailedRefresh,
+    /// A JS server is stopped in the middle of a tool call
+    ServerCrash,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LatencyConfig {
+    pub probability: f64,
+    pub min_ms: u64,
+    pub max_ms: u64,
+}
+
+impl Default for LatencyConfig {
+    fn default() -> Self {
+        Self {
+            probability: 0.0,
+            min_ms: 500,
+            max_ms: 5_000,
+        }
+    }
+}
+
+/// Probabilities are in `0.0..=1.0`; zero disables that fault.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChaosConfig {
+    pub enabled: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    pub tool_latency: LatencyConfig,
+    pub dropped_messages: f64,
+    pub failed_refreshes: f64,
+    pub server_crashes: f64,
+    /// Only inject faults into these servers; empty means all of them.
+    /// Dropped messages aren't tied to a server and ignore this.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub servers: Vec<String>,
+}
+
+impl ChaosConfig {
+    fn validate(&self) -> Result<(), String> {
+        let probabilities = [
+            ("tool_latency.probability", self.tool_latency.probability),
+            ("dropped_messages", self.dropped_messages),
+            ("failed_refreshes", self.failed_refreshes),
+            ("server_crashes", self.server_crashes),
+        ];
+        for (name, p) in probabilities {
+            if !(0.0..=1.0).contains(&p) {
+                return Err(format!("'{}' must be between 0 and 1", name));
+            }
+        }
+        if self.tool_latency.min_ms > self.tool_latency.max_ms {
+            return Err("'tool_latency.min_ms' is above 'tool_latency.max_ms'".to_string());
+        }
+        if self.tool_latency.max_ms > MAX_LATENCY_MS {
+            return Err(format!("'tool_latency.max_ms' may be at most {}", MAX_LATENCY_MS));
+        }
+        Ok(())
+    }
+
+    fn probability(&self, fault: Fault) -> f64 {
+        match fault {
+            Fault::ToolLatency => self.tool_latency.probability,
+            Fault::DroppedMessage => self.dropped_messages,
+            Fault::FailedRefresh => self.failed_refreshes,
+            Fault::ServerCrash => self.server_crashes,
+        }
+    }
+
+    fn applies_to(&self, server_id: Option<&str>) -> bool {
+        match server_id {
+            Some(id) if !self.servers.is_empty() => self.servers.iter().any(|s| s == id),
+            _ => true,
+        }
+    }
+}
+
+struct Inner {
+    config: ChaosConfig,
+    rng: StdRng,
+    injected: HashMap<Fault, u64>,
+}
+
+/// Fault injection state.
+pub struct ChaosState {
+    inner: Mutex<Inner>,
+}
+
+impl Default for ChaosState {
+    fn default() -> Self {
+        Self {
+            inner: Mutex::new(Inner {
+                config: ChaosConfig::default(),
+                rng: StdRng::from_entropy(),
+                injected: HashMap::new(),
+            }),
+        }
+    }
+}
+
+impl ChaosState {
+    /// Replace the configuration and reset the counters.
+    fn configure(&self, config: ChaosConfig) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.rng = match config.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        inner.injected.clear();
+        inner.config = config;
+    }
+
+    /// Roll for `fault`, counting it if it fires. With a latency fault, the
+    /// delay to inject is returned too.
+    fn roll(&self, fault: Fault, server_id: Option<&str>) -> Option<Duration> {
+        let mut inner = self.inner.lock().unwrap();
+        let config = &inner.config;
+        if !config.enabled || !config.applies_to(server_id) {
+            return None;
+        }
+        let p = config.probability(fault);
+        let (min_ms, max_ms) = (config.tool_latency.min_ms, config.tool_latency.max_ms);
+        if p <= 0.0 || !inner.rng.gen_bool(p) {
+            return None;
+        }
+        let delay = match fault {
+            Fault::ToolLatency => Duration::from_millis(inner.rng.gen_range(min_ms..=max_ms)),
+            _ => Duration::ZERO,
+        };
+        *inner.injected.entry(fault).or_insert(0) += 1;
+        Some(delay)
+    }
+}
+
+fn state() -> &'static ChaosState {
+    &crate::state::get().chaos
+}
+
+fn report(fault: Fault, server_id: Option<&str>, detail: serde_json::Value) {
+    tracing::warn!("[chaos] Injected {:?} (server: {})", fault, server_id.unwrap_or("-"));
+    crate::events::emit(
+        "chaos/injected",
+        serde_json::json!({ "fault": fault, "server_id": server_id, "detail": detail }),
+    );
+}
+
+/// Whether a fault should be injected now. Reports it if so.
+pub fn inject(fault: Fault, server_id: Option<&str>) -> bool {
+    let fired = state().roll(fault, server_id).is_some();
+    if fired {
+        report(fault, server_id, serde_json::Value::Null);
+    }
+    fired
+}
+
+/// Delay a tool call on `server_id`, if latency is due.
+pub async fn tool_latency(server_id: &str) {
+    if let Some(delay) = state().roll(Fault::ToolLatency, Some(server_id)) {
+        report(
+            Fault::ToolLatency,
+            Some(server_id),
+            serde_json::json!({ "delay_ms": delay.as_millis() as u64 }),
+        );
+        tokio::time::sleep(delay).await;
+    }
+}
+
+fn status() -> serde_json::Value {
+    let inner = state().inner.lock().unwrap();
+    serde_json::json!({
+        "config": inner.config,
+        "injected": inner.injected,
+    })
+}
+
+// ============================================================================
+// RPC Handlers
+// ============================================================================
+
+/// Set the fault injection config, e.g.
+/// `{ enabled: true, seed?, tool_latency: { probability, min_ms, max_ms },
+/// dropped_messages, failed_refreshes, server_crashes, servers? }`.
+/// Omitted faults are off. `{ enabled: false }` turns everything off.
+pub async fn rpc_configure(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    crate::rpc::require_extension("configure fault injection")?;
+    let config: ChaosConfig =
+        serde_json::from_value(params).map_err(|e| RpcError::invalid_params(format!("Invalid params: {}", e)))?;
+    config.validate().map_err(RpcError::invalid_params)?;
+    if config.enabled {
+        tracing::warn!("[chaos] Fault injection enabled: {:?}", config);
+    } else {
+        tracing::info!("[chaos] Fault injection disabled");
+    }
+    state().configure(config);
+    Ok(status())
+}
+
+/// The current config and how many faults of each kind were injected.
+pub async fn rpc_status(_params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    Ok(status())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seeded(config: ChaosConfig) -> ChaosState {
+        let state = ChaosState::default();
+        state.configure(ChaosConfig {
+            seed: Some(7),
+            ..config
+        });
+        state
+    }
+
+    #[test]
+    fn test_disabled_injects_nothing() {
+        let state = seeded(ChaosConfig {
+            enabled: false,
+            server_crashes: 1.0,
+            ..Default::default()
+        });
+        assert!(state.roll(Fault::ServerCrash, Some("a")).is_none());
+    }
+
+    #[test]
+    fn test_probabilities_and_servers() {
+        let state = seeded(ChaosConfig {
+            enabled: true,
+            dropped_messages: 1.0,
+            tool_latency: LatencyConfig {
+                probability: 1.0,
+                min_ms: 10,
+                max_ms: 20,
+            },
+            servers: vec!["flaky".to_string()],
+            ..Default::default()
+        });
+        assert!(state.roll(Fault::DroppedMessage, None).is_some());
+        assert!(state.roll(Fault::FailedRefresh, None).is_none());
+        assert!(state.roll(Fault::ToolLatency, Some("steady")).is_none());
+        let delay = state.roll(Fault::ToolLatency, Some("flaky")).unwrap();
+        assert!((10..=20).contains(&(delay.as_millis() as u64)));
+        assert_eq!(state.inner.lock().unwrap().injected[&Fault::ToolLatency], 1);
+    }
+
+    #[test]
+    fn test_validate() {
+        let config = ChaosConfig {
+            failed_refreshes: 1.5,
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+        let config = ChaosConfig {
+            tool_latency: LatencyConfig {
+                probability: 0.5,
+                min_ms: 100,
+                max_ms: 10,
+            },
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+        assert!(ChaosConfig::default().validate().is_ok());
+    }
+}
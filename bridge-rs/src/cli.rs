@@ -0,0 +1,110 @@
+//! Subcommands for use from a terminal, which run and exit instead of
+//! starting the bridge:
+//!
+//! ```text
+//! harbor-bridge dev [<dir>]
+//! harbor-bridge install <source> [--sha256 <hex>] [--replace]
+//! harbor-bridge new <name> --sdk <path> [--dir <path>] [--register]
+//! ```
+
+use std::path::PathBuf;
+
+/// Run the subcommand in `args` (without the program name). Returns the
+/// exit code, or `None` if `args` name no subcommand.
+pub async fn run(args: &[String]) -> Option<i32> {
+    let (command, rest) = args.split_first()?;
+    let result = match command.as_str() {
+        "dev" => dev(rest).await,
+        "install" => install(rest).await,
+        "new" => new(rest).await,
+        _ => return None,
+    };
+    Some(match result {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            1
+        }
+    })
+}
+
+/// The value after `--name`, if given.
+fn option<'a>(args: &'a [String], name: &str) -> Result<Option<&'a str>, String> {
+    match args.iter().position(|arg| arg == name) {
+        Some(i) => args
+            .get(i + 1)
+            .map(|value| Some(value.as_str()))
+            .ok_or_else(|| format!("{} needs a value", name)),
+        None => Ok(None),
+    }
+}
+
+async fn dev(args: &[String]) -> Result<(), String> {
+    let dir = match args.first() {
+        Some(arg) if arg.starts_with("--") => return Err("usage: harbor-bridge dev [<dir>]".to_string()),
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from("."),
+    };
+    crate::dev::run(&dir).await
+}
+
+async fn install(args: &[String]) -> Result<(), String> {
+    let source = args
+        .first()
+        .filter(|arg| !arg.starts_with("--"))
+        .ok_or("usage: harbor-bridge install <url | oci://registry/repository:tag> [--sha256 <hex>] [--replace]")?;
+    let sha256 = option(args, "--sha256")?;
+    let replace = args.iter().any(|arg| arg == "--replace");
+    let installed = crate::servers::install(source, sha256, replace).await.map_err(|e| e.message)?;
+    if let Some(warning) = &installed.signature_warning {
+        eprintln!("warning: {}", warning);
+    }
+    let server = &installed.server;
+    println!(
+        "{} {} {} ({})",
+        if installed.replaced { "Reinstalled" } else { "Installed" },
+        server.id,
+        server.version,
+        server.dir.display()
+    );
+    Ok(())
+}
+
+async fn new(args: &[String]) -> Result<(), String> {
+    const USAGE: &str = "usage: harbor-bridge new <name> --sdk <path to mcp-servers/sdk> [--dir <path>] [--register]";
+    let name = args.first().filter(|arg| !arg.starts_with("--")).ok_or(USAGE)?;
+    let dir = PathBuf::from(option(args, "--dir")?.unwrap_or(name));
+    let sdk = option(args, "--sdk")?.map(PathBuf::from).ok_or(USAGE)?;
+    crate::scaffold::create(name, &dir, &sdk)?;
+    println!("Created {} in {}", name, dir.display());
+
+    if args.iter().any(|arg| arg == "--register") {
+        let server = crate::servers::register_dev(&dir).await?;
+        println!("Registered {} as a dev server; Harbor adds it when it next connects, once built", server.id);
+    }
+    println!("Build it with: cd {} && cargo build --release --target wasm32-wasip1", dir.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_option() {
+        let given = args(&["https://example.com/m.json", "--sha256", "abc"]);
+        assert_eq!(option(&given, "--sha256").unwrap(), Some("abc"));
+        assert_eq!(option(&given, "--id").unwrap(), None);
+        assert!(option(&args(&["x", "--sha256"]), "--sha256").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_not_a_subcommand() {
+        assert_eq!(run(&args(&["--native-messaging"])).await, None);
+        assert_eq!(run(&[]).await, None);
+    }
+}
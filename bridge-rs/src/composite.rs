@@ -0,0 +1,439 @@
+//! Declarative composite tools.
+//!
+//! A composite tool is a named pipeline of tool calls defined in config. It
+//! is published in `mcp.list_tools` under the `composite` server like any
+//! native tool, so a common multi-step action ("search the issue tracker,
+//! then post a summary") appears to the agent as one tool.
+//!
+//! Step arguments are templates. A string that is exactly `{{path}}` is
+//! replaced by the value at `path`; `{{path}}` inside a longer string is
+//! replaced by its text. Paths start at `input` (the composite tool's
+//! arguments) or `steps.<id>` (an earlier step's result), e.g.
+//! `{{steps.search.items.0.url}}`. A step result that is a JSON string is
+//! parsed before looking inside it.
+//!
+//! `{{= expression}}` evaluates a sandboxed JavaScript expression instead
+//! (see `js::expr`), with `input` and `steps` as globals, for reshaping data
+//! between steps: `{{= steps.search.items.filter(i => i.open).map(i => i.url)}}`.
+//! An expression can't contain `}}`.
+//!
+//! Definitions are kept in `~/.harbor/composite_tools.json`.
+
+use std::collections::{BTreeMap, HashSet};
+use std::time::Instant;
+
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+
+use crate::mcp::RegisteredTool;
+use crate::rpc::RpcError;
+use crate::store::{JsonStore, Stored};
+
+/// Server ID composite tools are published under.
+pub const SERVER_ID: &str = "composite";
+
+/// Most steps a composite tool may have.
+const MAX_STEPS: usize = 20;
+
+/// One tool call in a composite tool.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Step {
+    /// Name later steps refer to this step's result by; defaults to its index
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub server_id: String,
+    pub tool: String,
+    /// Argument template
+    #[serde(default)]
+    pub args: serde_json::Value,
+}
+
+/// A composite tool definition.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompositeTool {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default = "default_schema", rename = "inputSchema", alias = "input_schema")]
+    pub input_schema: serde_json::Value,
+    pub steps: Vec<Step>,
+    /// Result template; defaults to the last step's result
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<serde_json::Value>,
+}
+
+fn default_schema() -> serde_json::Value {
+    serde_json::json!({ "type": "object", "properties": {} })
+}
+
+impl Step {
+    fn key(&self, index: usize) -> String {
+        self.id.clone().unwrap_or_else(|| index.to_string())
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CompositeFile {
+    #[serde(default)]
+    tools: BTreeMap<String, CompositeTool>,
+}
+
+impl Stored for CompositeFile {
+    const FILE_NAME: &'static str = "composite_tools.json";
+}
+
+/// Composite tool subsystem state.
+#[derive(Default)]
+pub struct CompositeState {
+    /// Definitions, loaded on first use
+    file: JsonStore<CompositeFile>,
+}
+
+fn state() -> &'static CompositeState {
+    &crate::state::get().composite
+}
+
+// ============================================================================
+// Templates
+// ============================================================================
+
+/// The `{{path}}` references in a template string, as (start, end, path).
+fn references(text: &str) -> Vec<(usize, usize, &str)> {
+    let mut found = Vec::new();
+    let mut offset = 0;
+    while let Some(start) = text[offset..].find("{{").map(|i| offset + i) {
+        let Some(end) = text[start..].find("}}").map(|i| start + i + 2) else {
+            break;
+        };
+        found.push((start, end, text[start + 2..end - 2].trim()));
+        offset = end;
+    }
+    found
+}
+
+/// Every path referenced anywhere in a template.
+fn template_paths(template: &serde_json::Value, out: &mut Vec<String>) {
+    match template {
+        serde_json::Value::String(text) => out.extend(
+            references(text)
+                .into_iter()
+                .filter(|(_, _, p)| !p.starts_with('='))
+                .map(|(_, _, p)| p.to_string()),
+        ),
+        serde_json::Value::Array(items) => items.iter().for_each(|v| template_paths(v, out)),
+        serde_json::Value::Object(map) => map.values().for_each(|v| template_paths(v, out)),
+        _ => {}
+    }
+}
+
+/// Look up a dotted path in the template scope.
+fn lookup(scope: &serde_json::Value, path: &str) -> Option<serde_json::Value> {
+    let mut current = scope.clone();
+    for part in path.split('.') {
+        // Tool results are often JSON serialized as text
+        if let serde_json::Value::String(text) = &current {
+            current = serde_json::from_str(text).ok()?;
+        }
+        current = match &current {
+            serde_json::Value::Object(map) => map.get(part)?.clone(),
+            serde_json::Value::Array(items) => items.get(part.parse::<usize>().ok()?)?.clone(),
+            _ => return None,
+        };
+    }
+    Some(current)
+}
+
+fn render(template: &serde_json::Value, scope: &serde_json::Value) -> Result<serde_json::Value, String> {
+    let value = |path: &str| match path.strip_prefix('=') {
+        Some(expression) => crate::js::evaluate(expression, scope, &crate::js::ExprLimits::default())
+            .map_err(|e| format!("'{{{{{}}}}}': {}", path, e)),
+        None => lookup(scope, path).ok_or_else(|| format!("'{{{{{}}}}}' has no value", path)),
+    };
+    Ok(match template {
+        serde_json::Value::String(text) => {
+            let refs = references(text);
+            match refs[..] {
+                [(0, end, path)] if end == text.len() => value(path)?,
+                _ => {
+                    let mut rendered = String::new();
+                    let mut last = 0;
+                    for (start, end, path) in refs {
+                        rendered.push_str(&text[last..start]);
+                        match value(path)? {
+                            serde_json::Value::String(s) => rendered.push_str(&s),
+                            other => rendered.push_str(&other.to_string()),
+                        }
+                        last = end;
+                    }
+                    rendered.push_str(&text[last..]);
+                    serde_json::Value::String(rendered)
+                }
+            }
+        }
+        serde_json::Value::Array(items) => {
+            serde_json::Value::Array(items.iter().map(|v| render(v, scope)).collect::<Result<_, _>>()?)
+        }
+        serde_json::Value::Object(map) => serde_json::Value::Object(
+            map.iter()
+                .map(|(k, v)| Ok((k.clone(), render(v, scope)?)))
+                .collect::<Result<_, String>>()?,
+        ),
+        other => other.clone(),
+    })
+}
+
+/// `render` off the async runtime, since expressions can take a while.
+async fn render_blocking(template: &serde_json::Value, scope: &serde_json::Value) -> Result<serde_json::Value, String> {
+    let (template, scope) = (template.clone(), scope.clone());
+    tokio::task::spawn_blocking(move || render(&template, &scope))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Check a definition: step count and IDs, and that templates only refer
+/// to the input and earlier steps.
+fn validate(tool: &CompositeTool) -> Result<(), String> {
+    if tool.name.trim().is_empty() || tool.name.contains('/') {
+        return Err("Composite tool names must be non-empty and contain no '/'".to_string());
+    }
+    if tool.steps.is_empty() || tool.steps.len() > MAX_STEPS {
+        return Err(format!("A composite tool needs 1 to {} steps", MAX_STEPS));
+    }
+
+    let mut known = HashSet::new();
+    let check = |template: &serde_json::Value, known: &HashSet<String>, what: &str| {
+        let mut paths = Vec::new();
+        template_paths(template, &mut paths);
+        for path in paths {
+            let mut parts = path.split('.');
+            let ok = match (parts.next(), parts.next()) {
+                (Some("input"), _) => true,
+                (Some("steps"), Some(step)) => known.contains(step),
+                _ => false,
+            };
+            if !ok {
+                return Err(format!("{} refers to '{}', which is not the input or an earlier step", what, path));
+            }
+        }
+        Ok(())
+    };
+    for (index, step) in tool.steps.iter().enumerate() {
+        if step.server_id == SERVER_ID {
+            return Err("Steps cannot call other composite tools".to_string());
+        }
+        check(&step.args, &known, &format!("Step {}", index))?;
+        if !known.insert(step.key(index)) {
+            return Err(format!("Duplicate step ID '{}'", step.key(index)));
+        }
+    }
+    if let Some(output) = &tool.output {
+        check(output, &known, "The output")?;
+    }
+    Ok(())
+}
+
+/// Check that the steps' servers don't span profiles. Runs are checked
+/// again step by step, since assignments can change after definition.
+fn check_profiles(tool: &CompositeTool, profiles: &crate::profiles::Profiles) -> Result<(), String> {
+    profiles
+        .check_servers(tool.steps.iter().map(|step| step.server_id.as_str()))
+        .map_err(|e| format!("{}: {}", tool.name, e))
+}
+
+// ============================================================================
+// Running
+// ============================================================================
+
+/// The composite tools, in registration format.
+pub async fn registered_tools() -> Vec<RegisteredTool> {
+    state()
+        .file
+        .read(|file| {
+            file.tools
+                .values()
+                .map(|tool| RegisteredTool {
+                    server_id: SERVER_ID.to_string(),
+                    name: tool.name.clone(),
+                    description: tool.description.clone(),
+                    input_schema: Some(tool.input_schema.clone()),
+                })
+                .collect()
+        })
+        .await
+}
+
+/// Run a composite tool. Returns `mcp.call_tool`'s result format.
+/// The steps run as one profile flow, so they can't span profiles.
+pub fn run(name: String, input: serde_json::Value) -> BoxFuture<'static, Result<serde_json::Value, RpcError>> {
+    Box::pin(crate::profiles::scope(None, async move {
+        let tool = state()
+            .file
+            .read(|file| file.tools.get(&name).cloned())
+            .await
+            .ok_or_else(|| RpcError::new(-32000, format!("Unknown composite tool '{}'", name)))?;
+
+        let mut scope = serde_json::json!({ "input": input, "steps": {} });
+        let mut trace = Vec::new();
+        let mut last = serde_json::Value::Null;
+        for (index, step) in tool.steps.iter().enumerate() {
+            let args = render_blocking(&step.args, &scope)
+                .await
+                .map_err(|e| RpcError::invalid_params(format!("{} step {}: {}", name, index, e)))?;
+            let started = Instant::now();
+            let failed = |code: i64, message: &str| {
+                RpcError::new(
+                    code,
+                    format!("{} step {} ({}/{}) failed: {}", name, index, step.server_id, step.tool, message),
+                )
+            };
+            let response = crate::mcp::call_tool(serde_json::json!({
+                "serverId": step.server_id,
+                "toolName": step.tool,
+                "args": args,
+            }))
+            .await
+            .map_err(|e| failed(e.code, &e.message))?;
+            // A tool that reports an error fails the run like one that can't be called
+            if crate::mcp::call_failed(&response) {
+                let message = crate::server_logs::message_text(&response["result"]);
+                return Err(failed(-32000, &message));
+            }
+            trace.push(serde_json::json!({
+                "server_id": step.server_id,
+                "tool": step.tool,
+                "ms": started.elapsed().as_millis() as u64,
+            }));
+
+            last = response.get("result").cloned().unwrap_or(serde_json::Value::Null);
+            scope["steps"][step.key(index)] = last.clone();
+        }
+
+        let result = match &tool.output {
+            Some(output) => render_blocking(output, &scope)
+                .await
+                .map_err(|e| RpcError::internal(format!("{} output: {}", name, e)))?,
+            None => last,
+        };
+        Ok(serde_json::json!({ "result": result, "_meta": { "steps": trace } }))
+    }))
+}
+
+// ============================================================================
+// RPC Handlers
+// ============================================================================
+
+/// List composite tool definitions.
+pub async fn rpc_list(_params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    let tools: Vec<CompositeTool> = state().file.read(|file| file.tools.values().cloned().collect()).await;
+    Ok(serde_json::json!({ "tools": tools }))
+}
+
+/// Add or replace a composite tool: `{ name, description?, inputSchema?, steps, output? }`.
+pub async fn rpc_define(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    crate::rpc::require_extension("change composite tools")?;
+    let tool: CompositeTool = serde_json::from_value(params)
+        .map_err(|e| RpcError::invalid_params(format!("Invalid composite tool: {}", e)))?;
+    validate(&tool).map_err(RpcError::invalid_params)?;
+    check_profiles(&tool, &crate::profiles::current().await)
+        .map_err(|e| RpcError::new(crate::profiles::PROFILE_DENIED, e))?;
+
+    let result = serde_json::json!({ "name": tool.name });
+    state()
+        .file
+        .update(|file| {
+            file.tools.insert(tool.name.clone(), tool);
+        })
+        .await;
+    Ok(result)
+}
+
+/// Remove a composite tool: `{ name }`.
+pub async fn rpc_remove(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    crate::rpc::require_extension("change composite tools")?;
+    let name = params
+        .get("name")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| RpcError::invalid_params("Missing 'name' parameter"))?;
+    let removed = state().file.update(|file| file.tools.remove(name).is_some()).await;
+    Ok(serde_json::json!({ "removed": removed }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_templates() {
+        let scope = serde_json::json!({
+            "input": { "query": "rust", "limit": 5 },
+            "steps": { "search": "{\"items\":[{\"url\":\"https://a\"}]}" },
+        });
+        let template = serde_json::json!({
+            "q": "{{input.query}}",
+            "n": "{{ input.limit }}",
+            "text": "Top hit for {{input.query}}: {{steps.search.items.0.url}}",
+        });
+        assert_eq!(
+            render(&template, &scope).unwrap(),
+            serde_json::json!({ "q": "rust", "n": 5, "text": "Top hit for rust: https://a" })
+        );
+        assert!(render(&serde_json::json!("{{input.missing}}"), &scope).is_err());
+
+        let mapped = serde_json::json!({
+            "urls": "{{= JSON.parse(steps.search).items.map(i => i.url)}}",
+            "title": "Results for {{= input.query.toUpperCase()}}",
+        });
+        assert_eq!(
+            render(&mapped, &scope).unwrap(),
+            serde_json::json!({ "urls": ["https://a"], "title": "Results for RUST" })
+        );
+    }
+
+    #[test]
+    fn test_validate_references() {
+        let tool = |args: serde_json::Value| CompositeTool {
+            name: "digest".into(),
+            description: None,
+            input_schema: default_schema(),
+            steps: vec![
+                Step {
+                    id: Some("search".into()),
+                    server_id: "gmail".into(),
+                    tool: "search".into(),
+                    args: serde_json::json!({ "q": "{{input.q}}" }),
+                },
+                Step {
+                    id: None,
+                    server_id: "llm".into(),
+                    tool: "summarize".into(),
+                    args,
+                },
+            ],
+            output: None,
+        };
+        assert!(validate(&tool(serde_json::json!({ "text": "{{steps.search}}" }))).is_ok());
+        assert!(validate(&tool(serde_json::json!({ "text": "{{steps.1}}" }))).is_err());
+        assert!(validate(&tool(serde_json::json!({ "text": "{{env.HOME}}" }))).is_err());
+    }
+
+    #[test]
+    fn test_steps_stay_in_one_profile() {
+        let step = |server_id: &str| Step {
+            id: None,
+            server_id: server_id.into(),
+            tool: "run".into(),
+            args: serde_json::json!({}),
+        };
+        let tool = |servers: &[&str]| CompositeTool {
+            name: "digest".into(),
+            description: None,
+            input_schema: default_schema(),
+            steps: servers.iter().map(|s| step(s)).collect(),
+            output: None,
+        };
+        let profiles = crate::profiles::tests::work_and_personal();
+        assert!(check_profiles(&tool(&["gmail-work", "llm", "jira"]), &profiles).is_ok());
+        let error = check_profiles(&tool(&["notes", "llm", "gmail-work"]), &profiles).unwrap_err();
+        assert!(error.starts_with("digest: 'gmail-work' belongs to profile 'work'"));
+    }
+}
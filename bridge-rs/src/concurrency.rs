@@ -0,0 +1,222 @@
+//! Adaptive per-server concurrency limits.
+//!
+//! Each server gets an AIMD (additive increase, multiplicative decrease)
+//! limiter. The limit grows by about one per round of successful calls while
+//! latency stays near its baseline, and halves when a call fails or latency
+//! spikes. Callers beyond the limit wait for a slot, so a struggling server
+//! sheds load without anyone tuning numbers per server.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tokio::sync::Notify;
+
+use crate::rpc::RpcError;
+
+const INITIAL_LIMIT: f64 = 4.0;
+const MIN_LIMIT: f64 = 1.0;
+const MAX_LIMIT: f64 = 64.0;
+
+/// Multiplier applied to the limit on failure or a latency spike.
+const BACKOFF: f64 = 0.5;
+
+/// Latency above `baseline * SPIKE_FACTOR` counts as a spike.
+const SPIKE_FACTOR: f64 = 2.0;
+
+/// Latency below this never counts as a spike (jitter on fast calls).
+const MIN_SPIKE_MS: f64 = 50.0;
+
+/// Weight of a new sample in the baseline latency moving average.
+const BASELINE_ALPHA: f64 = 0.1;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LimiterStats {
+    pub limit: f64,
+    pub in_flight: usize,
+    pub baseline_ms: Option<f64>,
+}
+
+struct Inner {
+    limit: f64,
+    in_flight: usize,
+    /// Moving average of healthy call latency
+    baseline_ms: Option<f64>,
+    last_decrease: Option<Instant>,
+}
+
+/// AIMD concurrency limiter for one server.
+pub struct Limiter {
+    inner: Mutex<Inner>,
+    available: Notify,
+}
+
+impl Default for Limiter {
+    fn default() -> Self {
+        Self {
+            inner: Mutex::new(Inner {
+                limit: INITIAL_LIMIT,
+                in_flight: 0,
+                baseline_ms: None,
+                last_decrease: None,
+            }),
+            available: Notify::new(),
+        }
+    }
+}
+
+impl Limiter {
+    /// Wait for a slot under the current limit.
+    pub async fn acquire(self: &Arc<Self>) -> Permit {
+        loop {
+            // Register for wakeups before checking, so a release in between isn't missed
+            let notified = self.available.notified();
+            {
+                let mut inner = self.inner.lock().unwrap();
+                if (inner.in_flight as f64) < inner.limit.floor() {
+                    inner.in_flight += 1;
+                    return Permit {
+                        limiter: self.clone(),
+                        started: Instant::now(),
+                        finished: false,
+                    };
+                }
+            }
+            notified.await;
+        }
+    }
+
+    pub fn stats(&self) -> LimiterStats {
+        let inner = self.inner.lock().unwrap();
+        LimiterStats {
+            limit: inner.limit,
+            in_flight: inner.in_flight,
+            baseline_ms: inner.baseline_ms,
+        }
+    }
+
+    /// Adjust the limit after a call completes and free its slot.
+    fn complete(&self, latency: Duration, ok: bool) {
+        {
+            let mut inner = self.inner.lock().unwrap();
+            let ms = latency.as_secs_f64() * 1000.0;
+            let baseline = *inner.baseline_ms.get_or_insert(ms);
+
+            if !ok || ms > (baseline * SPIKE_FACTOR).max(MIN_SPIKE_MS) {
+                // Back off at most once per baseline interval so a single burst
+                // of slow calls doesn't collapse the limit to the minimum
+                let backed_off_recently = inner
+                    .last_decrease
+                    .map(|t| t.elapsed().as_secs_f64() * 1000.0 < baseline)
+                    .unwrap_or(false);
+                if !backed_off_recently {
+                    inner.limit = (inner.limit * BACKOFF).max(MIN_LIMIT);
+                    inner.last_decrease = Some(Instant::now());
+                }
+            } else {
+                inner.limit = (inner.limit + 1.0 / inner.limit).min(MAX_LIMIT);
+                inner.baseline_ms = Some(baseline + BASELINE_ALPHA * (ms - baseline));
+            }
+        }
+        self.release();
+    }
+
+    fn release(&self) {
+        self.inner.lock().unwrap().in_flight -= 1;
+        self.available.notify_waiters();
+    }
+}
+
+/// A slot held for the duration of one call.
+pub struct Permit {
+    limiter: Arc<Limiter>,
+    started: Instant,
+    finished: bool,
+}
+
+impl Permit {
+    /// Report the call's outcome, adjusting the server's limit.
+    pub fn finish(mut self, ok: bool) {
+        self.finished = true;
+        self.limiter.complete(self.started.elapsed(), ok);
+    }
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        // Cancelled calls free their slot without affecting the limit
+        if !self.finished {
+            self.limiter.release();
+        }
+    }
+}
+
+/// Limiters for every server, created on first use.
+#[derive(Default)]
+pub struct Limiters {
+    servers: Mutex<HashMap<String, Arc<Limiter>>>,
+}
+
+impl Limiters {
+    pub fn get(&self, server_id: &str) -> Arc<Limiter> {
+        self.servers
+            .lock()
+            .unwrap()
+            .entry(server_id.to_string())
+            .or_default()
+            .clone()
+    }
+
+    pub fn stats(&self) -> HashMap<String, LimiterStats> {
+        self.servers
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(id, limiter)| (id.clone(), limiter.stats()))
+            .collect()
+    }
+}
+
+/// The limiter for a server.
+pub fn limiter(server_id: &str) -> Arc<Limiter> {
+    crate::state::get().concurrency.get(server_id)
+}
+
+/// Current limits and load per server.
+pub async fn rpc_stats(_params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    Ok(serde_json::json!({
+        "servers": crate::state::get().concurrency.stats(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_limit_grows_then_backs_off() {
+        let limiter = Arc::new(Limiter::default());
+
+        for _ in 0..8 {
+            limiter.acquire().await.finish(true);
+        }
+        let grown = limiter.stats().limit;
+        assert!(grown > INITIAL_LIMIT);
+
+        limiter.acquire().await.finish(false);
+        let stats = limiter.stats();
+        assert_eq!(stats.limit, grown * BACKOFF);
+        assert_eq!(stats.in_flight, 0);
+    }
+
+    #[tokio::test]
+    async fn test_dropped_permit_frees_slot() {
+        let limiter = Arc::new(Limiter::default());
+        let permit = limiter.acquire().await;
+        assert_eq!(limiter.stats().in_flight, 1);
+        drop(permit);
+        assert_eq!(limiter.stats().in_flight, 0);
+        assert_eq!(limiter.stats().limit, INITIAL_LIMIT);
+    }
+}
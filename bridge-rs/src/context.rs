@@ -0,0 +1,204 @@
+//! Shared conversation context (opt-in).
+//!
+//! The extension can publish a structured summary of the current
+//! conversation (`context.set`) so tools can tailor their behavior, e.g. a
+//! search server adding the conversation's keywords to a query. Nothing is
+//! shared unless the user has turned the feature on, and only servers the
+//! user granted `context.read` may read it.
+//!
+//! The policy is kept in `~/.harbor/context_policy.json`. The context itself
+//! is only held in memory and expires after its TTL.
+
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+
+use crate::rpc::RpcError;
+use crate::store::{JsonStore, Stored};
+
+/// Error code for reads the policy doesn't allow.
+const CONTEXT_DENIED: i64 = -32020;
+
+/// Longest summary accepted.
+const MAX_SUMMARY_CHARS: usize = 8000;
+
+/// Most keywords and entities accepted.
+const MAX_TERMS: usize = 50;
+
+/// Default time a published context stays readable.
+const DEFAULT_TTL_SECS: i64 = 30 * 60;
+
+/// Who may read the shared context.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ContextPolicy {
+    /// Whether the extension may publish context at all
+    pub enabled: bool,
+    /// Servers granted `context.read`
+    pub readers: BTreeSet<String>,
+}
+
+impl Stored for ContextPolicy {
+    const FILE_NAME: &'static str = "context_policy.json";
+}
+
+/// A summary of the current conversation.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConversationContext {
+    pub summary: String,
+    #[serde(default)]
+    pub keywords: Vec<String>,
+    /// People, places, projects and the like mentioned in the conversation
+    #[serde(default)]
+    pub entities: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    #[serde(default)]
+    pub updated_at: i64,
+    #[serde(default)]
+    pub expires_at: i64,
+}
+
+/// Context subsystem state.
+#[derive(Default)]
+pub struct ContextState {
+    /// Policy, loaded on first use
+    policy: JsonStore<ContextPolicy>,
+    /// The published context, if any
+    current: RwLock<Option<ConversationContext>>,
+}
+
+fn state() -> &'static ContextState {
+    &crate::state::get().context
+}
+
+/// Trim a list of terms to unique, non-empty entries, keeping order.
+fn clean_terms(terms: Vec<String>) -> Vec<String> {
+    let mut seen = BTreeSet::new();
+    terms
+        .into_iter()
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty() && seen.insert(t.to_lowercase()))
+        .take(MAX_TERMS)
+        .collect()
+}
+
+/// Read the context on behalf of a server, if the policy allows it.
+pub async fn read_for(server_id: Option<&str>) -> Result<Option<ConversationContext>, RpcError> {
+    let policy = state().policy.read(|policy| policy.clone()).await;
+    if !policy.enabled {
+        return Err(RpcError::new(CONTEXT_DENIED, "Context sharing is turned off"));
+    }
+    if let Some(id) = server_id {
+        if !policy.readers.contains(id) {
+            return Err(RpcError::new(
+                CONTEXT_DENIED,
+                format!("Server '{}' does not have the context.read permission", id),
+            ));
+        }
+    }
+
+    let now = chrono::Utc::now().timestamp_millis();
+    Ok(state().current.read().await.clone().filter(|c| c.expires_at > now))
+}
+
+// ============================================================================
+// RPC Handlers
+// ============================================================================
+
+/// Publish the current conversation's context:
+/// `{ summary, keywords?, entities?, language?, ttl_secs? }`.
+pub async fn rpc_set(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    crate::rpc::require_extension("change the shared context")
+        .map_err(|e| RpcError::new(CONTEXT_DENIED, e.message))?;
+    if !state().policy.read(|policy| policy.enabled).await {
+        return Err(RpcError::new(CONTEXT_DENIED, "Context sharing is turned off"));
+    }
+
+    let ttl_secs = params.get("ttl_secs").and_then(|v| v.as_i64()).unwrap_or(DEFAULT_TTL_SECS);
+    let mut context: ConversationContext = serde_json::from_value(params)
+        .map_err(|e| RpcError::invalid_params(format!("Invalid context: {}", e)))?;
+    if context.summary.chars().count() > MAX_SUMMARY_CHARS {
+        return Err(RpcError::invalid_params(format!(
+            "Summary is longer than {} characters",
+            MAX_SUMMARY_CHARS
+        )));
+    }
+    context.keywords = clean_terms(context.keywords);
+    context.entities = clean_terms(context.entities);
+    context.updated_at = chrono::Utc::now().timestamp_millis();
+    context.expires_at = context.updated_at.saturating_add(ttl_secs.max(1).saturating_mul(1000));
+
+    let result = serde_json::json!({ "updated_at": context.updated_at, "expires_at": context.expires_at });
+    *state().current.write().await = Some(context);
+    Ok(result)
+}
+
+/// Forget the published context, e.g. when the conversation ends.
+pub async fn rpc_clear(_params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    crate::rpc::require_extension("change the shared context")
+        .map_err(|e| RpcError::new(CONTEXT_DENIED, e.message))?;
+    let cleared = state().current.write().await.take().is_some();
+    Ok(serde_json::json!({ "cleared": cleared }))
+}
+
+/// Read the shared context. Servers need `context.read`; the context is
+/// `null` if none is published or it has expired.
+pub async fn rpc_get(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    let server_id = crate::rpc::caller()
+        .or_else(|| params.get("server_id").and_then(|v| v.as_str()).map(String::from));
+    let context = read_for(server_id.as_deref()).await?;
+    Ok(serde_json::json!({ "context": context }))
+}
+
+/// Get the sharing policy.
+pub async fn rpc_get_policy(_params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    let policy = state().policy.read(|policy| policy.clone()).await;
+    Ok(serde_json::json!(policy))
+}
+
+/// Update the sharing policy: `{ enabled?, grant?: [server_id], revoke?: [server_id] }`.
+/// Turning sharing off also forgets the published context.
+pub async fn rpc_set_policy(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    crate::rpc::require_extension("change the shared context")
+        .map_err(|e| RpcError::new(CONTEXT_DENIED, e.message))?;
+    let ids = |key: &str| -> Result<Vec<String>, RpcError> {
+        match params.get(key) {
+            Some(value) => serde_json::from_value(value.clone())
+                .map_err(|_| RpcError::invalid_params(format!("'{}' must be a list of server IDs", key))),
+            None => Ok(Vec::new()),
+        }
+    };
+    let (grant, revoke) = (ids("grant")?, ids("revoke")?);
+    let enabled = params.get("enabled").and_then(|v| v.as_bool());
+
+    let policy = state()
+        .policy
+        .update(|policy| {
+            if let Some(enabled) = enabled {
+                policy.enabled = enabled;
+            }
+            policy.readers.extend(grant);
+            for id in &revoke {
+                policy.readers.remove(id);
+            }
+            policy.clone()
+        })
+        .await;
+    if !policy.enabled {
+        state().current.write().await.take();
+    }
+    Ok(serde_json::json!(policy))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clean_terms() {
+        let terms = vec![" Rust ".to_string(), "rust".to_string(), "".to_string(), "tokio".to_string()];
+        assert_eq!(clean_terms(terms), vec!["Rust", "tokio"]);
+    }
+}
@@ -0,0 +1,133 @@
+//! `harbor-bridge dev`: the edit-build-reload loop for a server project.
+//!
+//! The project is registered as a dev server (see `servers::register_dev`)
+//! and built for `wasm32-wasip1`, then rebuilt whenever a source file
+//! changes. The running bridge notices each new build and has the extension
+//! reload it (`servers/changed`); meanwhile the server's lines from the
+//! bridge log are printed here, so its logs show up in the terminal the
+//! build runs in.
+
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use std::time::Duration;
+
+use notify::{EventKind, RecursiveMode};
+
+use crate::changes::{self, Changes};
+
+/// How often the bridge log is checked for new lines.
+const LOG_POLL: Duration = Duration::from_millis(500);
+
+/// Whether a change to `path` calls for a rebuild: anything in the project
+/// but build output and version control.
+fn is_source(project: &Path, path: &Path) -> bool {
+    let Ok(relative) = path.strip_prefix(project) else {
+        return false;
+    };
+    !relative
+        .components()
+        .next()
+        .is_some_and(|first| matches!(first.as_os_str().to_str(), Some("target" | ".git")))
+}
+
+/// Build the project; returns whether the build succeeded. Cargo's output
+/// goes straight to the terminal.
+async fn build(project: &Path) -> bool {
+    let cargo = std::env::var_os("CARGO").unwrap_or_else(|| "cargo".into());
+    let status = tokio::process::Command::new(cargo)
+        .args(["build", "--release", "--target", "wasm32-wasip1"])
+        .current_dir(project)
+        .status()
+        .await;
+    match status {
+        Ok(status) if status.success() => true,
+        Ok(status) => {
+            eprintln!("Build failed ({}); waiting for changes", status);
+            false
+        }
+        Err(e) => {
+            eprintln!("Failed to run cargo: {}", e);
+            false
+        }
+    }
+}
+
+/// Print the lines the bridge logs for `server_id` from now on.
+async fn tail_logs(server_id: String) {
+    let path = crate::maintenance::log_path();
+    let tag = format!("[WASM:{}]", server_id);
+    let mut position = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+    let mut partial = String::new();
+    loop {
+        tokio::time::sleep(LOG_POLL).await;
+        let Ok(mut file) = std::fs::File::open(&path) else {
+            continue;
+        };
+        let len = file.metadata().map(|m| m.len()).unwrap_or(0);
+        if len < position {
+            // Rotated or truncated
+            position = 0;
+            partial.clear();
+        }
+        let mut new = Vec::new();
+        if file.seek(SeekFrom::Start(position)).is_err() || file.read_to_end(&mut new).is_err() {
+            continue;
+        }
+        position += new.len() as u64;
+        partial.push_str(&String::from_utf8_lossy(&new));
+        let Some(end) = partial.rfind('\n') else {
+            continue;
+        };
+        for line in partial[..end].lines().filter(|line| line.contains(&tag)) {
+            println!("{}", line);
+        }
+        partial.drain(..=end);
+    }
+}
+
+/// Watch, build and tail logs for the project in `dir` until interrupted.
+pub async fn run(dir: &Path) -> Result<(), String> {
+    let server = crate::servers::register_dev(dir).await?;
+    let project = server.dir.clone();
+    println!("Registered {} as a dev server ({})", server.id, project.display());
+
+    let watched = project.clone();
+    let mut changes = Changes::new(move |kind, path| {
+        (changes::is_write(kind) || matches!(kind, EventKind::Remove(_))) && is_source(&watched, path)
+    })?;
+    changes.watch(&project, RecursiveMode::Recursive)?;
+
+    let tail = tokio::spawn(tail_logs(server.id.clone()));
+    let rebuild = async {
+        if build(&project).await {
+            println!("Built {}; Harbor reloads it if running. Watching for changes (Ctrl-C to stop)", server.id);
+        }
+        while changes.next().await.is_some() {
+            println!("Change detected; rebuilding {}", server.id);
+            if build(&project).await {
+                println!("Rebuilt {}", server.id);
+            }
+        }
+    };
+    tokio::select! {
+        _ = rebuild => {}
+        _ = tokio::signal::ctrl_c() => {}
+    }
+    tail.abort();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_source() {
+        let project = Path::new("/work/weather");
+        assert!(is_source(project, Path::new("/work/weather/src/main.rs")));
+        assert!(is_source(project, Path::new("/work/weather/manifest.json")));
+        assert!(!is_source(project, Path::new("/work/weather/target/wasm32-wasip1/release/weather.wasm")));
+        assert!(!is_source(project, Path::new("/work/weather/.git/index")));
+        assert!(!is_source(project, Path::new("/work/other/src/main.rs")));
+    }
+}
@@ -0,0 +1,239 @@
+//! Embedding providers for the local semantic index.
+//!
+//! Each index names the provider that embeds its documents and queries, so
+//! vectors in one index always come from the same model. Providers are:
+//!
+//! - `onnx`: a local sentence-embedding model (needs the `onnx` feature)
+//! - `ollama`: an Ollama endpoint's `/api/embed`
+//! - `openai`: any OpenAI-compatible `/embeddings` API. The API key is kept
+//!   in the OS keychain, set with `embeddings.set_api_key`.
+//!
+//! Index settings are kept in `~/.harbor/embeddings.json`.
+
+mod ollama;
+#[cfg(feature = "onnx")]
+mod onnx;
+mod openai;
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+
+use crate::rpc::RpcError;
+use crate::store::{JsonStore, Stored};
+
+/// Keychain service for provider API keys, keyed by index name.
+const KEYCHAIN_SERVICE: &str = "harbor-embeddings";
+
+/// Most texts embedded in one request.
+const MAX_BATCH: usize = 256;
+
+/// Turns text into vectors.
+pub trait EmbeddingProvider: Send + Sync {
+    /// Embed each text, returning one vector per text in the same order.
+    fn embed<'a>(&'a self, texts: &'a [String]) -> BoxFuture<'a, Result<Vec<Vec<f32>>, String>>;
+}
+
+/// Which provider an index uses, and how to reach it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ProviderConfig {
+    Onnx {
+        /// The model file (`model.onnx`)
+        model_path: PathBuf,
+        /// The Hugging Face `tokenizer.json` for the model
+        tokenizer_path: PathBuf,
+        /// Longer texts are truncated
+        #[serde(default = "default_max_tokens")]
+        max_tokens: usize,
+    },
+    Ollama {
+        #[serde(default = "ollama::default_base_url")]
+        base_url: String,
+        model: String,
+    },
+    #[serde(rename = "openai")]
+    OpenAi {
+        #[serde(default = "openai::default_base_url")]
+        base_url: String,
+        model: String,
+        /// Requested vector size, for models that support shortening
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        dimensions: Option<u32>,
+    },
+}
+
+fn default_max_tokens() -> usize {
+    512
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct EmbeddingsFile {
+    #[serde(default)]
+    indexes: BTreeMap<String, ProviderConfig>,
+}
+
+impl Stored for EmbeddingsFile {
+    const FILE_NAME: &'static str = "embeddings.json";
+}
+
+/// Embeddings subsystem state.
+#[derive(Default)]
+pub struct EmbeddingsState {
+    /// Index settings, loaded on first use
+    file: JsonStore<EmbeddingsFile>,
+}
+
+fn state() -> &'static EmbeddingsState {
+    &crate::state::get().embeddings
+}
+
+fn keychain_entry(index: &str) -> Result<keyring::Entry, String> {
+    keyring::Entry::new(KEYCHAIN_SERVICE, index)
+        .map_err(|e| format!("Failed to open keychain entry for {}: {}", index, e))
+}
+
+/// The API key stored for an index, if any.
+fn api_key(index: &str) -> Option<String> {
+    keychain_entry(index).ok()?.get_password().ok()
+}
+
+/// Build the provider configured for an index.
+fn provider(index: &str, config: ProviderConfig) -> Result<Box<dyn EmbeddingProvider>, String> {
+    match config {
+        #[cfg(feature = "onnx")]
+        ProviderConfig::Onnx {
+            model_path,
+            tokenizer_path,
+            max_tokens,
+        } => Ok(Box::new(onnx::OnnxProvider::load(&model_path, &tokenizer_path, max_tokens)?)),
+        #[cfg(not(feature = "onnx"))]
+        ProviderConfig::Onnx { .. } => Err("This bridge was built without ONNX support".to_string()),
+        ProviderConfig::Ollama { base_url, model } => Ok(Box::new(ollama::OllamaProvider { base_url, model })),
+        ProviderConfig::OpenAi {
+            base_url,
+            model,
+            dimensions,
+        } => Ok(Box::new(openai::OpenAiProvider {
+            base_url,
+            model,
+            dimensions,
+            // Local OpenAI-compatible servers often need no key
+            api_key: api_key(index),
+        })),
+    }
+}
+
+/// Embed texts with the provider configured for `index`.
+pub async fn embed(index: &str, texts: &[String]) -> Result<Vec<Vec<f32>>, String> {
+    let config = state()
+        .file
+        .read(|file| file.indexes.get(index).cloned())
+        .await
+        .ok_or_else(|| format!("No embedding provider is configured for index '{}'", index))?;
+    let vectors = provider(index, config)?.embed(texts).await?;
+    if vectors.len() != texts.len() {
+        return Err(format!("Provider returned {} vectors for {} texts", vectors.len(), texts.len()));
+    }
+    Ok(vectors)
+}
+
+// ============================================================================
+// RPC Handlers
+// ============================================================================
+
+fn index_param(params: &serde_json::Value) -> Result<String, RpcError> {
+    params
+        .get("index")
+        .and_then(|v| v.as_str())
+        .map(String::from)
+        .ok_or_else(|| RpcError::invalid_params("Missing 'index' parameter"))
+}
+
+/// List indexes and their providers, with whether an API key is stored.
+pub async fn rpc_list_indexes(_params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    let indexes = state().file.read(|file| file.indexes.clone()).await;
+    let list: Vec<serde_json::Value> = indexes
+        .into_iter()
+        .map(|(index, provider)| {
+            let has_api_key = api_key(&index).is_some();
+            serde_json::json!({ "index": index, "provider": provider, "has_api_key": has_api_key })
+        })
+        .collect();
+    Ok(serde_json::json!({ "indexes": list }))
+}
+
+/// Set an index's provider: `{ index, provider: { type, ... } }`.
+pub async fn rpc_configure_index(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    crate::rpc::require_extension("configure embeddings")?;
+    let index = index_param(&params)?;
+    let provider: ProviderConfig = params
+        .get("provider")
+        .cloned()
+        .ok_or_else(|| RpcError::invalid_params("Missing 'provider' parameter"))
+        .and_then(|p| {
+            serde_json::from_value(p).map_err(|e| RpcError::invalid_params(format!("Invalid provider: {}", e)))
+        })?;
+
+    state()
+        .file
+        .update(|file| {
+            file.indexes.insert(index.clone(), provider.clone());
+        })
+        .await;
+    Ok(serde_json::json!({ "index": index, "provider": provider }))
+}
+
+/// Remove an index's provider settings and API key.
+pub async fn rpc_remove_index(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    crate::rpc::require_extension("configure embeddings")?;
+    let index = index_param(&params)?;
+    let removed = state().file.update(|file| file.indexes.remove(&index).is_some()).await;
+    match keychain_entry(&index).map_err(RpcError::internal)?.delete_password() {
+        Ok(()) | Err(keyring::Error::NoEntry) => {}
+        Err(e) => return Err(RpcError::internal(format!("Failed to delete API key from keychain: {}", e))),
+    }
+    Ok(serde_json::json!({ "removed": removed }))
+}
+
+/// Store the API key an index's provider sends: `{ index, api_key }`.
+pub async fn rpc_set_api_key(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    crate::rpc::require_extension("configure embeddings")?;
+    let index = index_param(&params)?;
+    let key = params
+        .get("api_key")
+        .and_then(|v| v.as_str())
+        .filter(|k| !k.is_empty())
+        .ok_or_else(|| RpcError::invalid_params("Missing 'api_key' parameter"))?;
+    keychain_entry(&index)
+        .and_then(|entry| {
+            entry
+                .set_password(key)
+                .map_err(|e| format!("Failed to store API key in keychain: {}", e))
+        })
+        .map_err(RpcError::internal)?;
+    Ok(serde_json::json!({ "ok": true }))
+}
+
+/// Embed texts with an index's provider: `{ index, texts: [...] }`.
+pub async fn rpc_embed(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    let index = index_param(&params)?;
+    let texts: Vec<String> = params
+        .get("texts")
+        .cloned()
+        .and_then(|t| serde_json::from_value(t).ok())
+        .ok_or_else(|| RpcError::invalid_params("Missing 'texts' parameter"))?;
+    if texts.len() > MAX_BATCH {
+        return Err(RpcError::invalid_params(format!(
+            "Too many texts ({}, limit {})",
+            texts.len(),
+            MAX_BATCH
+        )));
+    }
+
+    let vectors = embed(&index, &texts).await.map_err(|e| RpcError::new(-32000, e))?;
+    let dimensions = vectors.first().map(Vec::len);
+    Ok(serde_json::json!({ "index": index, "dimensions": dimensions, "vectors": vectors }))
+}
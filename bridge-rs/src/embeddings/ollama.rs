@@ -0,0 +1,46 @@
+//! Embeddings from an Ollama endpoint.
+
+use futures::future::BoxFuture;
+use serde::Deserialize;
+
+use super::EmbeddingProvider;
+
+pub fn default_base_url() -> String {
+    "http://localhost:11434".to_string()
+}
+
+pub struct OllamaProvider {
+    pub base_url: String,
+    pub model: String,
+}
+
+#[derive(Deserialize)]
+struct EmbedResponse {
+    embeddings: Vec<Vec<f32>>,
+}
+
+impl EmbeddingProvider for OllamaProvider {
+    fn embed<'a>(&'a self, texts: &'a [String]) -> BoxFuture<'a, Result<Vec<Vec<f32>>, String>> {
+        Box::pin(async move {
+            let url = format!("{}/api/embed", self.base_url.trim_end_matches('/'));
+            let response = reqwest::Client::new()
+                .post(&url)
+                .json(&serde_json::json!({ "model": self.model, "input": texts }))
+                .send()
+                .await
+                .map_err(|e| format!("Request to {} failed: {}", url, e))?;
+
+            if !response.status().is_success() {
+                let status = response.status();
+                let body = response.text().await.unwrap_or_default();
+                return Err(format!("Ollama returned {}: {}", status, body));
+            }
+
+            let response: EmbedResponse = response
+                .json()
+                .await
+                .map_err(|e| format!("Invalid response from Ollama: {}", e))?;
+            Ok(response.embeddings)
+        })
+    }
+}
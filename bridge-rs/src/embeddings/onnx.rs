@@ -0,0 +1,125 @@
+//! Embeddings from a local ONNX sentence-embedding model.
+//!
+//! Works with BERT-style models exported for feature extraction (e.g.
+//! all-MiniLM-L6-v2): the last hidden state is mean-pooled over the
+//! attention mask and normalized. Loaded models are cached by model and
+//! tokenizer path and token limit.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use futures::future::BoxFuture;
+use ort::session::Session;
+use ort::value::Tensor;
+use tokenizers::{PaddingParams, Tokenizer, TruncationParams};
+
+use super::EmbeddingProvider;
+
+struct Model {
+    session: Session,
+    tokenizer: Tokenizer,
+}
+
+/// Model path, tokenizer path, token limit
+type ModelKey = (PathBuf, PathBuf, usize);
+
+static MODELS: OnceLock<Mutex<HashMap<ModelKey, Arc<Model>>>> = OnceLock::new();
+
+pub struct OnnxProvider {
+    model: Arc<Model>,
+}
+
+impl OnnxProvider {
+    pub fn load(model_path: &Path, tokenizer_path: &Path, max_tokens: usize) -> Result<Self, String> {
+        let key = (model_path.to_path_buf(), tokenizer_path.to_path_buf(), max_tokens);
+        let mut models = MODELS.get_or_init(Default::default).lock().unwrap();
+        if let Some(model) = models.get(&key) {
+            return Ok(Self { model: model.clone() });
+        }
+
+        let session = Session::builder()
+            .and_then(|builder| builder.commit_from_file(model_path))
+            .map_err(|e| format!("Failed to load ONNX model {:?}: {}", model_path, e))?;
+        let mut tokenizer = Tokenizer::from_file(tokenizer_path)
+            .map_err(|e| format!("Failed to load tokenizer {:?}: {}", tokenizer_path, e))?;
+        tokenizer.with_padding(Some(PaddingParams::default()));
+        tokenizer
+            .with_truncation(Some(TruncationParams {
+                max_length: max_tokens,
+                ..Default::default()
+            }))
+            .map_err(|e| format!("Invalid truncation settings: {}", e))?;
+
+        let model = Arc::new(Model { session, tokenizer });
+        models.insert(key, model.clone());
+        Ok(Self { model })
+    }
+}
+
+impl Model {
+    fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, String> {
+        let encodings = self
+            .tokenizer
+            .encode_batch(texts.to_vec(), true)
+            .map_err(|e| format!("Tokenization failed: {}", e))?;
+        let batch = encodings.len();
+        let length = encodings.first().map_or(0, |e| e.len());
+        let column = |f: fn(&tokenizers::Encoding) -> &[u32]| -> Vec<i64> {
+            encodings.iter().flat_map(|e| f(e).iter().map(|&v| v as i64)).collect()
+        };
+        let mask = column(tokenizers::Encoding::get_attention_mask);
+
+        let mut inputs = Vec::new();
+        for input in &self.session.inputs {
+            let values = match input.name.as_str() {
+                "input_ids" => column(tokenizers::Encoding::get_ids),
+                "attention_mask" => mask.clone(),
+                "token_type_ids" => column(tokenizers::Encoding::get_type_ids),
+                other => return Err(format!("Unsupported model input '{}'", other)),
+            };
+            let tensor = Tensor::from_array(([batch, length], values)).map_err(|e| e.to_string())?;
+            inputs.push((input.name.clone(), tensor));
+        }
+
+        let outputs = self.session.run(inputs).map_err(|e| format!("Inference failed: {}", e))?;
+        let (shape, hidden) = outputs[0]
+            .try_extract_raw_tensor::<f32>()
+            .map_err(|e| format!("Unexpected model output: {}", e))?;
+        let width = match shape[..] {
+            [_, _, width] => width as usize,
+            _ => return Err(format!("Unexpected model output shape {:?}", shape)),
+        };
+
+        // Mean of token vectors, ignoring padding, then L2-normalized. The
+        // normalization makes dividing by the token count unnecessary.
+        Ok((0..batch)
+            .map(|b| {
+                let mut vector = vec![0.0f32; width];
+                for t in 0..length {
+                    if mask[b * length + t] == 0 {
+                        continue;
+                    }
+                    let start = (b * length + t) * width;
+                    for (v, h) in vector.iter_mut().zip(&hidden[start..start + width]) {
+                        *v += h;
+                    }
+                }
+                let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt().max(f32::EPSILON);
+                vector.iter().map(|v| v / norm).collect()
+            })
+            .collect())
+    }
+}
+
+impl EmbeddingProvider for OnnxProvider {
+    fn embed<'a>(&'a self, texts: &'a [String]) -> BoxFuture<'a, Result<Vec<Vec<f32>>, String>> {
+        let model = self.model.clone();
+        let texts = texts.to_vec();
+        Box::pin(async move {
+            tokio::task::spawn_blocking(move || model.embed(&texts))
+                .await
+                .map_err(|e| format!("Embedding task failed: {}", e))?
+        })
+    }
+}
@@ -0,0 +1,87 @@
+//! Embeddings from an OpenAI-compatible `/embeddings` API.
+
+use futures::future::BoxFuture;
+use serde::Deserialize;
+
+use super::EmbeddingProvider;
+
+pub fn default_base_url() -> String {
+    "https://api.openai.com/v1".to_string()
+}
+
+pub struct OpenAiProvider {
+    pub base_url: String,
+    pub model: String,
+    pub dimensions: Option<u32>,
+    pub api_key: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingData {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingsResponse {
+    data: Vec<EmbeddingData>,
+}
+
+/// Vectors in input order. The API returns an index with each vector and
+/// doesn't promise to keep them in order.
+fn into_vectors(response: EmbeddingsResponse) -> Vec<Vec<f32>> {
+    let mut data = response.data;
+    data.sort_by_key(|d| d.index);
+    data.into_iter().map(|d| d.embedding).collect()
+}
+
+impl EmbeddingProvider for OpenAiProvider {
+    fn embed<'a>(&'a self, texts: &'a [String]) -> BoxFuture<'a, Result<Vec<Vec<f32>>, String>> {
+        Box::pin(async move {
+            let url = format!("{}/embeddings", self.base_url.trim_end_matches('/'));
+            let mut body = serde_json::json!({ "model": self.model, "input": texts });
+            if let Some(dimensions) = self.dimensions {
+                body["dimensions"] = dimensions.into();
+            }
+
+            let mut request = reqwest::Client::new().post(&url).json(&body);
+            if let Some(key) = &self.api_key {
+                request = request.bearer_auth(key);
+            }
+            let response = request
+                .send()
+                .await
+                .map_err(|e| format!("Request to {} failed: {}", url, e))?;
+
+            if !response.status().is_success() {
+                let status = response.status();
+                let body = response.text().await.unwrap_or_default();
+                return Err(format!("Embeddings API returned {}: {}", status, body));
+            }
+
+            let response: EmbeddingsResponse = response
+                .json()
+                .await
+                .map_err(|e| format!("Invalid response from embeddings API: {}", e))?;
+            Ok(into_vectors(response))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vectors_in_input_order() {
+        let response: EmbeddingsResponse = serde_json::from_value(serde_json::json!({
+            "object": "list",
+            "data": [
+                { "object": "embedding", "index": 1, "embedding": [0.5] },
+                { "object": "embedding", "index": 0, "embedding": [0.25] },
+            ],
+        }))
+        .unwrap();
+        assert_eq!(into_vectors(response), vec![vec![0.25], vec![0.5]]);
+    }
+}
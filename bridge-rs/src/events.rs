@@ -0,0 +1,61 @@
+//! Bridge-initiated events for the extension.
+//!
+//! Subsystems emit named events (e.g. `aws/mfa_required`) without knowing
+//! how the extension is connected; the native messaging loop forwards every
+//! event as an `event` message. Events for the extension alone, such as
+//! requests only it may answer, go on a separate channel that only the
+//! native messaging loop reads.
+
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+/// An event pushed to the extension.
+#[derive(Debug, Clone, Serialize)]
+pub struct Event {
+    /// Event name, `<subsystem>/<event>`
+    pub event: String,
+    pub payload: serde_json::Value,
+}
+
+lazy_static::lazy_static! {
+    static ref EVENT_TX: broadcast::Sender<Event> = {
+        let (tx, _) = broadcast::channel(100);
+        tx
+    };
+    static ref EXTENSION_TX: broadcast::Sender<Event> = {
+        let (tx, _) = broadcast::channel(100);
+        tx
+    };
+}
+
+/// Emit an event. Dropped if nothing is listening.
+pub fn emit(event: &str, payload: serde_json::Value) {
+    let _ = EVENT_TX.send(Event {
+        event: event.to_string(),
+        payload,
+    });
+}
+
+/// Subscribe to all events.
+pub fn subscribe() -> broadcast::Receiver<Event> {
+    EVENT_TX.subscribe()
+}
+
+/// Emit an event to the extension's native messaging connection only.
+/// Dropped if the extension isn't connected.
+pub fn emit_to_extension(event: &str, payload: serde_json::Value) {
+    let _ = EXTENSION_TX.send(Event {
+        event: event.to_string(),
+        payload,
+    });
+}
+
+/// Subscribe to the events for the extension alone.
+pub fn subscribe_extension() -> broadcast::Receiver<Event> {
+    EXTENSION_TX.subscribe()
+}
+
+/// Whether the extension is connected over native messaging.
+pub fn extension_connected() -> bool {
+    EXTENSION_TX.receiver_count() > 0
+}
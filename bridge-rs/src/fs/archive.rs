@@ -0,0 +1,424 @@
+//! Zip and tar.gz archives inside the sandbox.
+//!
+//! Creating an archive skips symlinks, like `ops::copy`. Extracting never
+//! trusts entry names: absolute paths and `..` components are rejected, as
+//! are entries that would be written through a symlink already in the
+//! destination. Links, devices and other special entries are skipped.
+//! Sizes are counted as data is decompressed rather than taken from the
+//! archive's headers, so a zip bomb stops at the limit. If extraction fails
+//! part-way, the files it created are removed again.
+
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::{Component, Path, PathBuf};
+
+use serde::Serialize;
+
+use super::ops::Overwrite;
+
+/// Most entries written to or read from one archive.
+pub const MAX_ENTRIES: usize = 20_000;
+
+/// Most bytes put into or extracted from one archive.
+pub const MAX_BYTES: u64 = 1024 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Zip,
+    TarGz,
+}
+
+impl Format {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "zip" => Ok(Self::Zip),
+            "tar.gz" | "tgz" => Ok(Self::TarGz),
+            other => Err(format!("Unknown archive format '{}'; use \"zip\" or \"tar.gz\"", other)),
+        }
+    }
+
+    /// The format an archive's name suggests.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_string_lossy().to_lowercase();
+        if name.ends_with(".zip") {
+            Some(Self::Zip)
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(Self::TarGz)
+        } else {
+            None
+        }
+    }
+}
+
+/// What creating or extracting an archive did.
+#[derive(Debug, Default, Serialize)]
+pub struct ArchiveStats {
+    pub files: usize,
+    pub directories: usize,
+    /// Uncompressed bytes
+    pub bytes: u64,
+    /// Existing files left in place by `skip`
+    pub skipped: usize,
+    /// Symlinks and special entries, which are not archived or extracted
+    pub ignored: usize,
+}
+
+fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message.into())
+}
+
+// ============================================================================
+// Creating
+// ============================================================================
+
+/// Files and directories to archive, with their names in the archive.
+fn collect(sources: &[PathBuf], stats: &mut ArchiveStats) -> io::Result<Vec<(PathBuf, String, bool)>> {
+    let mut entries = Vec::new();
+    let mut total = 0;
+    let mut stack: Vec<(PathBuf, String)> = sources
+        .iter()
+        .rev()
+        .map(|source| {
+            let name = source.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+            (source.clone(), name)
+        })
+        .collect();
+
+    while let Some((path, name)) = stack.pop() {
+        let metadata = std::fs::symlink_metadata(&path)?;
+        if metadata.is_dir() {
+            let mut children: Vec<_> = std::fs::read_dir(&path)?.collect::<io::Result<_>>()?;
+            children.sort_by_key(|e| std::cmp::Reverse(e.file_name()));
+            for child in children {
+                let child_name = format!("{}/{}", name, child.file_name().to_string_lossy());
+                stack.push((child.path(), child_name));
+            }
+            entries.push((path, name, true));
+        } else if metadata.is_file() {
+            total += metadata.len();
+            if total > MAX_BYTES {
+                return Err(invalid(format!("More than {} bytes to archive", MAX_BYTES)));
+            }
+            entries.push((path, name, false));
+        } else {
+            stats.ignored += 1;
+        }
+        if entries.len() > MAX_ENTRIES {
+            return Err(invalid(format!("More than {} entries to archive", MAX_ENTRIES)));
+        }
+    }
+    Ok(entries)
+}
+
+/// Write `sources` (files or directories, each stored under its own name)
+/// to a new archive at `destination`.
+pub fn create(sources: &[PathBuf], destination: &Path, format: Format) -> io::Result<ArchiveStats> {
+    let mut stats = ArchiveStats::default();
+    let entries = collect(sources, &mut stats)?;
+    if let Some(parent) = destination.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let file = File::create(destination)?;
+
+    let result = match format {
+        Format::Zip => write_zip(file, &entries, &mut stats),
+        Format::TarGz => write_tar_gz(file, &entries, &mut stats),
+    };
+    if result.is_err() {
+        let _ = std::fs::remove_file(destination);
+    }
+    result.map(|_| stats)
+}
+
+fn write_zip(file: File, entries: &[(PathBuf, String, bool)], stats: &mut ArchiveStats) -> io::Result<()> {
+    let mut zip = zip::ZipWriter::new(file);
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated)
+        .large_file(true);
+    for (path, name, is_dir) in entries {
+        if *is_dir {
+            zip.add_directory(name.as_str(), options).map_err(io::Error::other)?;
+            stats.directories += 1;
+        } else {
+            zip.start_file(name.as_str(), options).map_err(io::Error::other)?;
+            stats.bytes += io::copy(&mut File::open(path)?, &mut zip)?;
+            stats.files += 1;
+        }
+    }
+    zip.finish().map_err(io::Error::other)?.flush()
+}
+
+fn write_tar_gz(file: File, entries: &[(PathBuf, String, bool)], stats: &mut ArchiveStats) -> io::Result<()> {
+    let encoder = flate2::write::GzEncoder::new(file, flate2::Compression::default());
+    let mut tar = tar::Builder::new(encoder);
+    tar.follow_symlinks(false);
+    for (path, name, is_dir) in entries {
+        if *is_dir {
+            tar.append_dir(name, path)?;
+            stats.directories += 1;
+        } else {
+            tar.append_path_with_name(path, name)?;
+            stats.bytes += std::fs::metadata(path)?.len();
+            stats.files += 1;
+        }
+    }
+    tar.into_inner()?.finish()?.flush()
+}
+
+// ============================================================================
+// Extracting
+// ============================================================================
+
+/// An entry name as a relative path, or `None` if it could escape.
+fn safe_name(name: &Path) -> Option<PathBuf> {
+    let mut clean = PathBuf::new();
+    for component in name.components() {
+        match component {
+            Component::Normal(part) => clean.push(part),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+    (!clean.as_os_str().is_empty()).then_some(clean)
+}
+
+/// Writes entries under a destination directory, keeping track of what it
+/// created so a failed extraction can be undone.
+struct Extractor {
+    destination: PathBuf,
+    overwrite: Overwrite,
+    max_bytes: u64,
+    stats: ArchiveStats,
+    created: Vec<PathBuf>,
+}
+
+impl Extractor {
+    /// The path for an entry, after checking it stays in the destination.
+    fn target(&self, name: &Path) -> io::Result<PathBuf> {
+        let relative =
+            safe_name(name).ok_or_else(|| invalid(format!("Entry {:?} would be extracted outside the destination", name)))?;
+        let mut partial = self.destination.clone();
+        for part in relative.components() {
+            partial.push(part);
+            match std::fs::symlink_metadata(&partial) {
+                Ok(metadata) if metadata.is_symlink() => {
+                    return Err(invalid(format!("Entry {:?} would be written through a symlink", name)));
+                }
+                Ok(_) => {}
+                Err(_) => break,
+            }
+        }
+        Ok(self.destination.join(relative))
+    }
+
+    fn create_dirs(&mut self, dir: &Path) -> io::Result<()> {
+        let mut missing = Vec::new();
+        let mut current = dir;
+        while !current.exists() {
+            missing.push(current.to_path_buf());
+            match current.parent() {
+                Some(parent) => current = parent,
+                None => break,
+            }
+        }
+        for dir in missing.into_iter().rev() {
+            std::fs::create_dir(&dir)?;
+            self.stats.directories += 1;
+            self.created.push(dir);
+        }
+        Ok(())
+    }
+
+    fn directory(&mut self, name: &Path) -> io::Result<()> {
+        let target = self.target(name)?;
+        self.create_dirs(&target)
+    }
+
+    fn file(&mut self, name: &Path, data: &mut dyn Read) -> io::Result<()> {
+        let target = self.target(name)?;
+        let mut replacing = false;
+        if std::fs::symlink_metadata(&target).is_ok() {
+            match self.overwrite {
+                Overwrite::Error => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::AlreadyExists,
+                        format!("{:?} already exists", name),
+                    ))
+                }
+                Overwrite::Skip => {
+                    self.stats.skipped += 1;
+                    return Ok(());
+                }
+                Overwrite::Replace => replacing = true,
+            }
+        }
+        if let Some(parent) = target.parent() {
+            self.create_dirs(parent)?;
+        }
+
+        // A file being replaced stays whole until the new one is: it's
+        // written beside it, and renamed over it once complete
+        let path = if replacing {
+            let file_name = target.file_name().and_then(|n| n.to_str()).unwrap_or("file");
+            target.with_file_name(format!(".{}.{:016x}.tmp", file_name, rand::random::<u64>()))
+        } else {
+            target.clone()
+        };
+        let remaining = self.max_bytes - self.stats.bytes;
+        let mut out = File::create(&path)?;
+        if !replacing {
+            self.created.push(target.clone());
+        }
+        // One byte past the limit tells a full file from one that was cut off
+        let written = io::copy(&mut data.take(remaining.saturating_add(1)), &mut out).and_then(|written| {
+            if written > remaining {
+                return Err(invalid(format!("The archive holds more than {} bytes", self.max_bytes)));
+            }
+            Ok(written)
+        });
+        drop(out);
+        let written = match (written, replacing) {
+            (Ok(written), true) => std::fs::rename(&path, &target).map(|_| written),
+            (written, _) => written,
+        };
+        if written.is_err() && replacing {
+            let _ = std::fs::remove_file(&path);
+        }
+        self.stats.bytes += written?;
+        self.stats.files += 1;
+        Ok(())
+    }
+
+    fn count(&self, entries: usize) -> io::Result<()> {
+        if entries > MAX_ENTRIES {
+            return Err(invalid(format!("The archive has more than {} entries", MAX_ENTRIES)));
+        }
+        Ok(())
+    }
+
+    /// Remove everything this extraction created, newest first.
+    fn undo(&self) {
+        for path in self.created.iter().rev() {
+            let _ = if path.is_dir() {
+                std::fs::remove_dir(path)
+            } else {
+                std::fs::remove_file(path)
+            };
+        }
+    }
+}
+
+fn read_zip(archive: File, extractor: &mut Extractor) -> io::Result<()> {
+    let mut zip = zip::ZipArchive::new(archive).map_err(io::Error::other)?;
+    extractor.count(zip.len())?;
+    for index in 0..zip.len() {
+        let mut entry = zip.by_index(index).map_err(io::Error::other)?;
+        let name = PathBuf::from(entry.name());
+        let is_link = entry.unix_mode().is_some_and(|mode| mode & 0o170000 == 0o120000);
+        if is_link {
+            extractor.stats.ignored += 1;
+        } else if entry.is_dir() {
+            extractor.directory(&name)?;
+        } else {
+            extractor.file(&name, &mut entry)?;
+        }
+    }
+    Ok(())
+}
+
+fn read_tar_gz(archive: File, extractor: &mut Extractor) -> io::Result<()> {
+    let mut tar = tar::Archive::new(flate2::read::GzDecoder::new(archive));
+    for (index, entry) in tar.entries()?.enumerate() {
+        extractor.count(index + 1)?;
+        let mut entry = entry?;
+        let name = entry.path()?.into_owned();
+        let kind = entry.header().entry_type();
+        if kind.is_dir() {
+            extractor.directory(&name)?;
+        } else if kind.is_file() {
+            extractor.file(&name, &mut entry)?;
+        } else {
+            extractor.stats.ignored += 1;
+        }
+    }
+    Ok(())
+}
+
+/// Extract `archive` into `destination`, writing at most `max_bytes`.
+pub fn extract(
+    archive: &Path,
+    destination: &Path,
+    format: Format,
+    overwrite: Overwrite,
+    max_bytes: u64,
+) -> io::Result<ArchiveStats> {
+    let file = File::open(archive)?;
+    let mut extractor = Extractor {
+        destination: destination.to_path_buf(),
+        overwrite,
+        max_bytes,
+        stats: ArchiveStats::default(),
+        created: Vec::new(),
+    };
+    extractor.create_dirs(destination)?;
+
+    let result = match format {
+        Format::Zip => read_zip(file, &mut extractor),
+        Format::TarGz => read_tar_gz(file, &mut extractor),
+    };
+    match result {
+        Ok(()) => Ok(extractor.stats),
+        Err(e) => {
+            extractor.undo();
+            Err(e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_safe_names() {
+        assert_eq!(safe_name(Path::new("a/./b.txt")), Some(PathBuf::from("a/b.txt")));
+        assert_eq!(safe_name(Path::new("../evil")), None);
+        assert_eq!(safe_name(Path::new("a/../../evil")), None);
+        assert_eq!(safe_name(Path::new("/etc/passwd")), None);
+        assert_eq!(safe_name(Path::new(".")), None);
+    }
+
+    #[test]
+    fn test_round_trip_and_limits() {
+        let root = std::env::temp_dir().join(format!("harbor-fs-archive-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("docs/sub")).unwrap();
+        std::fs::write(root.join("docs/a.txt"), "hello").unwrap();
+        std::fs::write(root.join("docs/sub/b.txt"), "world!").unwrap();
+
+        for (format, name) in [(Format::Zip, "out.zip"), (Format::TarGz, "out.tar.gz")] {
+            let archive = root.join(name);
+            let stats = create(&[root.join("docs")], &archive, format).unwrap();
+            assert_eq!((stats.files, stats.bytes), (2, 11));
+            assert_eq!(Format::from_path(&archive), Some(format));
+
+            let out = root.join(format!("{}-out", name));
+            let stats = extract(&archive, &out, format, Overwrite::Error, u64::MAX).unwrap();
+            assert_eq!(stats.files, 2);
+            assert_eq!(std::fs::read_to_string(out.join("docs/sub/b.txt")).unwrap(), "world!");
+
+            // Replacing stops short of the limit without losing the files it was replacing
+            assert!(extract(&archive, &out, format, Overwrite::Replace, 8).is_err());
+            assert_eq!(std::fs::read_to_string(out.join("docs/a.txt")).unwrap(), "hello");
+            assert_eq!(std::fs::read_to_string(out.join("docs/sub/b.txt")).unwrap(), "world!");
+            assert_eq!(std::fs::read_dir(out.join("docs/sub")).unwrap().count(), 1);
+
+            // Too small a limit fails and leaves nothing behind
+            let out = root.join(format!("{}-small", name));
+            assert!(extract(&archive, &out, format, Overwrite::Error, 8).is_err());
+            assert!(!out.exists());
+        }
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
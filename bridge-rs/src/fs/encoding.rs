@@ -0,0 +1,195 @@
+//! Content encodings and MIME sniffing for file transfers.
+//!
+//! File contents cross the bridge as JSON strings, so binary files are sent
+//! as base64 or hex. Callers choose the encoding per call; `auto` (reads
+//! only) picks `utf8` for text and `base64` otherwise.
+
+use std::path::Path;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+/// How file content is represented in a JSON string.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Utf8,
+    Base64,
+    Hex,
+    /// `utf8` if the content is text, `base64` otherwise
+    Auto,
+}
+
+impl Encoding {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "utf8" | "utf-8" => Ok(Self::Utf8),
+            "base64" => Ok(Self::Base64),
+            "hex" => Ok(Self::Hex),
+            "auto" => Ok(Self::Auto),
+            other => Err(format!("Unknown encoding '{}'", other)),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Utf8 => "utf8",
+            Self::Base64 => "base64",
+            Self::Hex => "hex",
+            Self::Auto => "auto",
+        }
+    }
+
+    /// Encoded size per byte of content, at most.
+    pub fn expansion(self) -> f64 {
+        match self {
+            Self::Hex => 2.0,
+            Self::Base64 | Self::Auto => 4.0 / 3.0,
+            Self::Utf8 => 1.0,
+        }
+    }
+
+    /// Encode content. Returns the encoding actually used, which differs
+    /// from `self` only for `auto`.
+    pub fn encode(self, bytes: Vec<u8>) -> Result<(String, Encoding), String> {
+        match self {
+            Self::Utf8 => String::from_utf8(bytes)
+                .map(|s| (s, Self::Utf8))
+                .map_err(|_| "Content is not valid UTF-8; use encoding 'base64'".to_string()),
+            Self::Base64 => Ok((STANDARD.encode(bytes), Self::Base64)),
+            Self::Hex => Ok((bytes.iter().map(|b| format!("{:02x}", b)).collect(), Self::Hex)),
+            Self::Auto => match String::from_utf8(bytes) {
+                Ok(text) if !text.contains('\0') => Ok((text, Self::Utf8)),
+                Ok(text) => Self::Base64.encode(text.into_bytes()),
+                Err(e) => Self::Base64.encode(e.into_bytes()),
+            },
+        }
+    }
+
+    /// Decode content sent by a caller.
+    pub fn decode(self, content: &str) -> Result<Vec<u8>, String> {
+        match self {
+            Self::Utf8 => Ok(content.as_bytes().to_vec()),
+            Self::Base64 => STANDARD
+                .decode(content)
+                .map_err(|e| format!("Invalid base64 content: {}", e)),
+            Self::Hex => {
+                if content.len() % 2 != 0 {
+                    return Err("Invalid hex content: odd length".to_string());
+                }
+                (0..content.len())
+                    .step_by(2)
+                    .map(|i| {
+                        content
+                            .get(i..i + 2)
+                            .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                            .ok_or_else(|| format!("Invalid hex content at offset {}", i))
+                    })
+                    .collect()
+            }
+            Self::Auto => Err("Encoding 'auto' is only supported for reads".to_string()),
+        }
+    }
+}
+
+/// Magic numbers for common binary formats.
+const SIGNATURES: &[(&[u8], &str)] = &[
+    (b"\x89PNG\r\n\x1a\n", "image/png"),
+    (b"\xff\xd8\xff", "image/jpeg"),
+    (b"GIF87a", "image/gif"),
+    (b"GIF89a", "image/gif"),
+    (b"%PDF-", "application/pdf"),
+    (b"PK\x03\x04", "application/zip"),
+    (b"\x1f\x8b", "application/gzip"),
+    (b"\x00\x00\x01\x00", "image/x-icon"),
+    (b"OggS", "audio/ogg"),
+    (b"fLaC", "audio/flac"),
+    (b"ID3", "audio/mpeg"),
+    (b"\x1aE\xdf\xa3", "video/webm"),
+    (b"\0asm", "application/wasm"),
+    (b"SQLite format 3\0", "application/vnd.sqlite3"),
+];
+
+/// Guess a MIME type from content, then from the file extension.
+pub fn sniff_mime(bytes: &[u8], path: &Path) -> &'static str {
+    if let Some((_, mime)) = SIGNATURES.iter().find(|(magic, _)| bytes.starts_with(magic)) {
+        return mime;
+    }
+    // RIFF containers: WebP and WAV
+    if bytes.len() >= 12 && bytes.starts_with(b"RIFF") {
+        match &bytes[8..12] {
+            b"WEBP" => return "image/webp",
+            b"WAVE" => return "audio/wav",
+            _ => {}
+        }
+    }
+    // ISO base media: MP4 and friends
+    if bytes.len() >= 12 && &bytes[4..8] == b"ftyp" {
+        return match &bytes[8..12] {
+            b"avif" => "image/avif",
+            b"heic" | b"heix" => "image/heic",
+            b"qt  " => "video/quicktime",
+            _ => "video/mp4",
+        };
+    }
+
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("").to_ascii_lowercase();
+    let by_extension = match extension.as_str() {
+        "json" => Some("application/json"),
+        "md" | "markdown" => Some("text/markdown"),
+        "html" | "htm" => Some("text/html"),
+        "css" => Some("text/css"),
+        "js" | "mjs" => Some("text/javascript"),
+        "csv" => Some("text/csv"),
+        "xml" => Some("application/xml"),
+        "svg" => Some("image/svg+xml"),
+        "yaml" | "yml" => Some("application/yaml"),
+        "toml" => Some("application/toml"),
+        _ => None,
+    };
+    if let Some(mime) = by_extension {
+        return mime;
+    }
+
+    let head = &bytes[..bytes.len().min(8192)];
+    let is_text = !head.contains(&0)
+        && match std::str::from_utf8(head) {
+            Ok(_) => true,
+            // A multi-byte character may be cut off at the end of the sample
+            Err(e) => e.error_len().is_none(),
+        };
+    if is_text {
+        "text/plain"
+    } else {
+        "application/octet-stream"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hex_round_trip() {
+        let bytes = vec![0x00, 0x7f, 0xff, 0x10];
+        let (hex, _) = Encoding::Hex.encode(bytes.clone()).unwrap();
+        assert_eq!(hex, "007fff10");
+        assert_eq!(Encoding::Hex.decode("007FFF10").unwrap(), bytes);
+        assert!(Encoding::Hex.decode("abc").is_err());
+        assert!(Encoding::Hex.decode("zz").is_err());
+    }
+
+    #[test]
+    fn test_auto_encoding() {
+        assert_eq!(Encoding::Auto.encode(b"hello".to_vec()).unwrap(), ("hello".to_string(), Encoding::Utf8));
+        assert_eq!(Encoding::Auto.encode(vec![0xff, 0x00]).unwrap().1, Encoding::Base64);
+    }
+
+    #[test]
+    fn test_sniff_mime() {
+        assert_eq!(sniff_mime(b"\x89PNG\r\n\x1a\n....", Path::new("a.bin")), "image/png");
+        assert_eq!(sniff_mime(b"%PDF-1.7", Path::new("doc")), "application/pdf");
+        assert_eq!(sniff_mime(b"RIFF\0\0\0\0WEBPVP8 ", Path::new("x")), "image/webp");
+        assert_eq!(sniff_mime(b"{\"a\": 1}", Path::new("data.json")), "application/json");
+        assert_eq!(sniff_mime(b"plain words", Path::new("notes")), "text/plain");
+        assert_eq!(sniff_mime(&[0x01, 0x00, 0x02], Path::new("blob")), "application/octet-stream");
+    }
+}
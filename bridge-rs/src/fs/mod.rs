@@ -1,22 +1,1300 @@
+//! Filesystem access rooted at a sandbox directory.
+//!
+//! Every path is resolved relative to the sandbox root (`~/.harbor/files`,
+//! or `$HARBOR_FS_ROOT`). `..` components that would leave the root are
+//! rejected. Symlinks are handled per the configured `SymlinkPolicy`: by
+//! default they may be followed only if they stay inside the root.
+//!
+//! Requests made on behalf of an MCP server identify it, either through the
+//! dispatcher (`rpc::caller`) or a `server_id` parameter, and are limited to
+//! the directories it has been granted (see `permissions`). A server with a
+//! jail resolves paths against its jail root instead of the sandbox root.
+//! Requests without a server come from the extension itself.
+
+pub mod archive;
+pub mod encoding;
+pub mod ops;
+pub mod patch;
+pub mod permissions;
+pub mod quota;
+pub mod search;
+pub mod watch;
+
+use std::path::{Component, Path, PathBuf};
+use std::time::SystemTime;
+
+use serde::Serialize;
+use tokio::sync::Mutex;
+
 use crate::rpc::RpcError;
+use encoding::Encoding;
+use permissions::{Access, Grant, Grants, Jail, SymlinkPolicy};
+
+/// Error code for filesystem failures (missing files, I/O errors).
+const FS_ERROR: i64 = -32002;
+
+/// Error code for paths a server has not been granted.
+const FS_PERMISSION_DENIED: i64 = -32003;
+
+/// Error code for writes that would take a server over its storage quota.
+const FS_QUOTA_EXCEEDED: i64 = -32004;
+
+/// Error code for patches that don't match the file.
+const FS_PATCH_CONFLICT: i64 = -32005;
+
+/// Largest file `fs.read` returns. Native messaging caps messages to the
+/// extension at 1 MB, and base64 grows content by a third.
+const MAX_READ_BYTES: u64 = 512 * 1024;
+
+/// Largest file `fs.read` returns in an encoding, keeping the encoded
+/// content within what base64 of `MAX_READ_BYTES` would take.
+fn read_limit(encoding: Encoding) -> u64 {
+  let budget = MAX_READ_BYTES as f64 * Encoding::Base64.expansion();
+  ((budget / encoding.expansion()) as u64).min(MAX_READ_BYTES)
+}
+
+/// Largest content `fs.write` accepts.
+const MAX_WRITE_BYTES: usize = 8 * 1024 * 1024;
 
-pub async fn read(_params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
-  Err(RpcError {
-    code: -32002,
-    message: "Filesystem access not implemented".to_string(),
+/// Most entries `fs.list` returns.
+const MAX_LIST_ENTRIES: usize = 10_000;
+
+/// Filesystem subsystem state.
+#[derive(Default)]
+pub struct FsState {
+  /// Directory grants, loaded on first use
+  grants: Mutex<Option<Grants>>,
+  /// Open `fs.watch` subscriptions
+  watches: watch::Watches,
+  /// Measured storage per server, for quotas
+  usage: quota::Usage,
+}
+
+fn state() -> &'static FsState {
+  &crate::state::get().fs
+}
+
+/// Run `f` with the loaded grants.
+async fn with_grants<T>(f: impl FnOnce(&mut Grants) -> T) -> T {
+  let mut grants = state().grants.lock().await;
+  f(grants.get_or_insert_with(permissions::load))
+}
+
+/// Drop expired grants from the permissions file. Returns how many were
+/// removed.
+pub async fn prune_expired_grants() -> Result<usize, String> {
+  with_grants(|grants| {
+    let removed = grants.prune_expired();
+    if removed > 0 {
+      permissions::save(grants)?;
+    }
+    Ok(removed)
   })
+  .await
+}
+
+/// The directories a server may read: its jail, or each directory it
+/// holds an unexpired grant for. Each comes with its path under the
+/// sandbox root (`""` for a jail).
+pub async fn readable_dirs(server_id: &str) -> Result<Vec<(PathBuf, String)>, RpcError> {
+  let jail = with_grants(|grants| grants.jails.get(server_id).cloned()).await;
+  if let Some(jail) = jail {
+    let root = jail.resolve().map_err(|e| RpcError::new(FS_ERROR, e))?;
+    return Ok(vec![(root, String::new())]);
+  }
+  let root = sandbox_root()?;
+  let now = chrono::Utc::now().timestamp_millis();
+  Ok(
+    grants_for(server_id)
+      .await
+      .into_iter()
+      .filter(|grant| !grant.is_expired(now))
+      .map(|grant| (root.join(&grant.path), grant.path))
+      .collect(),
+  )
 }
 
-pub async fn write(_params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
-  Err(RpcError {
-    code: -32002,
-    message: "Filesystem access not implemented".to_string(),
+/// A server's current grants.
+pub async fn grants_for(server_id: &str) -> Vec<Grant> {
+  with_grants(|grants| grants.servers.get(server_id).cloned().unwrap_or_default()).await
+}
+
+/// The directories a server's storage use is measured over.
+async fn usage_roots(server_id: &str) -> Result<Vec<PathBuf>, RpcError> {
+  let root = sandbox_root()?;
+  let roots = with_grants(|grants| grants.writable_roots(server_id, &root))
+    .await
+    .map_err(|e| RpcError::new(FS_ERROR, e))?;
+  Ok(quota::outermost(roots))
+}
+
+/// A server's storage use, measured over the directories it can write to.
+/// Returns the bytes used and those directories.
+async fn server_usage(server_id: &str) -> Result<(u64, Vec<PathBuf>), RpcError> {
+  let roots = usage_roots(server_id).await?;
+  if let Some(bytes) = state().usage.cached(server_id) {
+    return Ok((bytes, roots));
+  }
+
+  let walk = roots.clone();
+  let bytes = tokio::task::spawn_blocking(move || quota::measure(&walk))
+    .await
+    .map_err(|e| RpcError::internal(format!("Failed to measure usage: {}", e)))?;
+  state().usage.store(server_id, bytes);
+  Ok((bytes, roots))
+}
+
+/// The sandbox root, created if needed.
+fn sandbox_root() -> Result<PathBuf, RpcError> {
+  let root = std::env::var_os("HARBOR_FS_ROOT")
+    .map(PathBuf::from)
+    .unwrap_or_else(|| {
+      let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
+      home.join(".harbor").join("files")
+    });
+  std::fs::create_dir_all(&root)
+    .and_then(|_| root.canonicalize())
+    .map_err(|e| RpcError::new(FS_ERROR, format!("Failed to prepare sandbox root {:?}: {}", root, e)))
+}
+
+/// Resolve a requested path inside a (canonical) root.
+///
+/// Absolute paths are accepted only if they already point inside the root.
+/// Symlinks in the part of the path that exists are rejected (`deny`),
+/// resolved and checked against the root (`within_sandbox`), or followed
+/// anywhere (`allow`).
+fn resolve_in(root: &Path, requested: &str, symlinks: SymlinkPolicy) -> Result<PathBuf, String> {
+  let escape = || format!("Path '{}' is outside the sandbox", requested);
+
+  let requested_path = Path::new(requested);
+  let relative = requested_path.strip_prefix(root).unwrap_or(requested_path);
+
+  let mut resolved = root.to_path_buf();
+  for component in relative.components() {
+    match component {
+      Component::Normal(part) => resolved.push(part),
+      Component::CurDir => {}
+      Component::ParentDir => {
+        if resolved == root {
+          return Err(escape());
+        }
+        resolved.pop();
+      }
+      Component::RootDir | Component::Prefix(_) => return Err(escape()),
+    }
+  }
+
+  match symlinks {
+    SymlinkPolicy::Allow => {}
+    SymlinkPolicy::Deny => {
+      let mut partial = root.to_path_buf();
+      for part in resolved.strip_prefix(root).unwrap_or(Path::new("")).components() {
+        partial.push(part);
+        match std::fs::symlink_metadata(&partial) {
+          Ok(metadata) if metadata.is_symlink() => {
+            return Err(format!("Path '{}' goes through a symlink", requested));
+          }
+          Ok(_) => {}
+          Err(_) => break,
+        }
+      }
+    }
+    SymlinkPolicy::WithinSandbox => {
+      // A symlink counts as existing even if its target doesn't, so a
+      // dangling one is resolved (and refused) rather than skipped
+      let mut existing = resolved.as_path();
+      while std::fs::symlink_metadata(existing).is_err() {
+        existing = existing.parent().ok_or_else(escape)?;
+      }
+      let canonical = existing.canonicalize().map_err(|e| {
+        if existing.is_symlink() {
+          format!("Path '{}' goes through a symlink to nowhere", requested)
+        } else {
+          format!("Failed to resolve '{}': {}", requested, e)
+        }
+      })?;
+      if !canonical.starts_with(root) {
+        return Err(escape());
+      }
+    }
+  }
+
+  Ok(resolved)
+}
+
+/// Resolve the `path` parameter (default: the root itself) and check that
+/// the calling server, if any, has been granted `access` to it.
+async fn resolve_param(
+  params: &serde_json::Value,
+  required: bool,
+  access: Access,
+) -> Result<(PathBuf, PathBuf), RpcError> {
+  resolve_key(params, "path", required, access).await
+}
+
+/// The server a request is made for: the dispatcher's caller, or the
+/// `server_id` parameter. A server can't name another server, and only the
+/// extension may name one or use the sandbox without one; programs on the
+/// HTTP API get no filesystem access.
+fn caller_id(params: &serde_json::Value) -> Result<Option<String>, RpcError> {
+  let param = params.get("server_id").and_then(|v| v.as_str());
+  match crate::rpc::caller() {
+    Some(caller) if param.is_some_and(|id| id != caller) => Err(RpcError::new(
+      FS_PERMISSION_DENIED,
+      format!("Server '{}' cannot act as '{}'", caller, param.unwrap_or_default()),
+    )),
+    Some(caller) => Ok(Some(caller)),
+    None => {
+      crate::rpc::require_extension("use the filesystem")
+        .map_err(|e| RpcError::new(FS_PERMISSION_DENIED, e.message))?;
+      Ok(param.map(String::from))
+    }
+  }
+}
+
+fn denied(server_id: &str, access: Access, shown: &str) -> RpcError {
+  RpcError::new(
+    FS_PERMISSION_DENIED,
+    format!(
+      "Server '{}' has no {} access to '{}'",
+      server_id,
+      if access == Access::Write { "write" } else { "read" },
+      shown
+    ),
+  )
+}
+
+/// Like `resolve_param`, for a path parameter named `key`. Returns the root
+/// the path was resolved against: the caller's jail, or the sandbox root.
+async fn resolve_key(
+  params: &serde_json::Value,
+  key: &str,
+  required: bool,
+  access: Access,
+) -> Result<(PathBuf, PathBuf), RpcError> {
+  let requested = match params.get(key).and_then(|v| v.as_str()) {
+    Some(path) => path,
+    None if required => return Err(RpcError::invalid_params(format!("Missing '{}' parameter", key))),
+    None => "",
+  };
+  resolve_path(params, requested, access).await
+}
+
+/// Resolve a requested path for the caller and check its access.
+async fn resolve_path(
+  params: &serde_json::Value,
+  requested: &str,
+  access: Access,
+) -> Result<(PathBuf, PathBuf), RpcError> {
+  let caller = caller_id(params)?;
+  let (jail, symlinks) = with_grants(|grants| {
+    let jail = caller.as_ref().and_then(|id| grants.jails.get(id).cloned());
+    (jail, grants.symlinks)
   })
+  .await;
+  let root = match &jail {
+    Some(jail) => jail.resolve().map_err(|e| RpcError::new(FS_ERROR, e))?,
+    None => sandbox_root()?,
+  };
+
+  let path = resolve_in(&root, requested, symlinks).map_err(RpcError::invalid_params)?;
+
+  if let Some(id) = caller {
+    let allowed = match jail {
+      Some(jail) => jail.access >= access,
+      None => with_grants(|grants| grants.allows(&id, &root, &path, access)).await,
+    };
+    if !allowed {
+      return Err(denied(&id, access, &display_path(&root, &path)));
+    }
+  }
+  Ok((root, path))
+}
+
+/// Path relative to the root, as reported back to callers.
+fn display_path(root: &Path, path: &Path) -> String {
+  path.strip_prefix(root).unwrap_or(path).to_string_lossy().replace('\\', "/")
+}
+
+fn mtime_ms(time: std::io::Result<SystemTime>) -> Option<i64> {
+  time.ok().map(|t| chrono::DateTime::<chrono::Utc>::from(t).timestamp_millis())
+}
+
+fn io_error(action: &str, path: &str, e: std::io::Error) -> RpcError {
+  RpcError::new(FS_ERROR, format!("Failed to {} '{}': {}", action, path, e))
+}
+
+/// Read a file: `{ path, encoding?: "utf8" | "base64" | "hex" | "auto" }`.
+/// The response includes the encoding used and a sniffed MIME type.
+pub async fn read(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+  let (root, path) = resolve_param(&params, true, Access::Read).await?;
+  let shown = display_path(&root, &path);
+  let encoding = Encoding::parse(params.get("encoding").and_then(|v| v.as_str()).unwrap_or("utf8"))
+    .map_err(RpcError::invalid_params)?;
+  let limit = read_limit(encoding);
+
+  let metadata = tokio::fs::metadata(&path).await.map_err(|e| io_error("read", &shown, e))?;
+  if !metadata.is_file() {
+    return Err(RpcError::new(FS_ERROR, format!("'{}' is not a file", shown)));
+  }
+  if metadata.len() > limit {
+    return Err(RpcError::new(
+      FS_ERROR,
+      format!(
+        "'{}' is {} bytes; the limit with encoding '{}' is {}",
+        shown,
+        metadata.len(),
+        encoding.name(),
+        limit
+      ),
+    ));
+  }
+
+  let bytes = tokio::fs::read(&path).await.map_err(|e| io_error("read", &shown, e))?;
+  let mime = encoding::sniff_mime(&bytes, &path);
+  let (content, used) = encoding
+    .encode(bytes)
+    .map_err(|e| RpcError::new(FS_ERROR, format!("'{}': {}", shown, e)))?;
+
+  Ok(serde_json::json!({
+    "path": shown,
+    "content": content,
+    "encoding": used.name(),
+    "mime": mime,
+    "size": metadata.len(),
+    "mtime": mtime_ms(metadata.modified()),
+  }))
+}
+
+/// Write a file: `{ path, content, encoding?: "utf8" | "base64" | "hex",
+/// mode?: "overwrite" | "append", atomic?, fsync? }`.
+///
+/// Parent directories are created. Overwrites are atomic unless `atomic` is
+/// false: the content goes to a temp file that is renamed over the target,
+/// so a crash leaves either the old or the new file. With `fsync`, data is
+/// flushed to disk before the call returns. `append: true` is accepted as
+/// the older spelling of `mode: "append"`.
+pub async fn write(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+  let (root, path) = resolve_param(&params, true, Access::Write).await?;
+  let shown = display_path(&root, &path);
+  if path == root {
+    return Err(RpcError::invalid_params("Cannot write to the sandbox root"));
+  }
+
+  let content = params
+    .get("content")
+    .and_then(|v| v.as_str())
+    .ok_or_else(|| RpcError::invalid_params("Missing 'content' parameter"))?;
+  let bytes = Encoding::parse(params.get("encoding").and_then(|v| v.as_str()).unwrap_or("utf8"))
+    .and_then(|encoding| encoding.decode(content))
+    .map_err(RpcError::invalid_params)?;
+  if bytes.len() > MAX_WRITE_BYTES {
+    return Err(RpcError::invalid_params(format!(
+      "Content is {} bytes; the limit is {}",
+      bytes.len(),
+      MAX_WRITE_BYTES
+    )));
+  }
+  let append = match params.get("mode").and_then(|v| v.as_str()) {
+    Some("append") => true,
+    Some("overwrite") => false,
+    Some(other) => {
+      return Err(RpcError::invalid_params(format!(
+        "Unknown mode '{}'; use \"overwrite\" or \"append\"",
+        other
+      )))
+    }
+    None => params.get("append").and_then(|v| v.as_bool()).unwrap_or(false),
+  };
+  let atomic = params.get("atomic").and_then(|v| v.as_bool());
+  if append && atomic == Some(true) {
+    return Err(RpcError::invalid_params("Appends cannot be atomic; use mode \"overwrite\""));
+  }
+  let atomic = atomic.unwrap_or(!append);
+  let fsync = params.get("fsync").and_then(|v| v.as_bool()).unwrap_or(false);
+
+  // How much the write grows the calling server's usage, if the path counts
+  // toward it. The growth is reserved against the server's quota before
+  // writing, so concurrent writes can't both fit in the same room, and
+  // given back if the write fails.
+  let mut reserved = None;
+  if let Some(id) = caller_id(&params)? {
+    if quota::counted(&usage_roots(&id).await?, &path) {
+      let existing = if append {
+        0
+      } else {
+        tokio::fs::metadata(&path).await.map(|m| m.len()).unwrap_or(0)
+      };
+      let growth = bytes.len() as i64 - existing as i64;
+      match with_grants(|grants| grants.quotas.get(&id).copied()).await {
+        Some(limit) => {
+          let (used, _) = server_usage(&id).await?;
+          state().usage.reserve(&id, used, growth, limit).map_err(|total| {
+            RpcError::new(
+              FS_QUOTA_EXCEEDED,
+              format!("Writing '{}' would take '{}' to {} bytes, over its quota of {}", shown, id, total, limit),
+            )
+          })?;
+        }
+        None => state().usage.adjust(&id, growth),
+      }
+      reserved = Some((id, growth));
+    }
+  }
+
+  if let Some(parent) = path.parent() {
+    tokio::fs::create_dir_all(parent).await.map_err(|e| io_error("create directory for", &shown, e))?;
+  }
+
+  let written = if atomic {
+    write_atomic(&path, &bytes, fsync).await
+  } else {
+    write_in_place(&path, &bytes, append, fsync).await
+  };
+  if let (Err(_), Some((id, growth))) = (&written, &reserved) {
+    state().usage.adjust(id, -growth);
+  }
+  written.map_err(|e| io_error("write", &shown, e))?;
+
+  let size = tokio::fs::metadata(&path).await.map(|m| m.len()).unwrap_or(bytes.len() as u64);
+  Ok(serde_json::json!({
+    "path": shown,
+    "size": size,
+  }))
+}
+
+async fn write_in_place(path: &Path, bytes: &[u8], append: bool, fsync: bool) -> std::io::Result<()> {
+  use tokio::io::AsyncWriteExt;
+  let mut file = tokio::fs::OpenOptions::new()
+    .create(true)
+    .write(true)
+    .append(append)
+    .truncate(!append)
+    .open(path)
+    .await?;
+  file.write_all(bytes).await?;
+  if fsync {
+    file.sync_all().await?;
+  }
+  Ok(())
+}
+
+/// Write to a sibling temp file and rename it over `path`, so readers never
+/// see a partial file and a crash can't leave one behind.
+async fn write_atomic(path: &Path, bytes: &[u8], fsync: bool) -> std::io::Result<()> {
+  use tokio::io::AsyncWriteExt;
+  let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+  let tmp = path.with_file_name(format!(".{}.{:016x}.harbor-tmp", name, rand::random::<u64>()));
+
+  let result = async {
+    let mut file = tokio::fs::File::create(&tmp).await?;
+    file.write_all(bytes).await?;
+    if fsync {
+      file.sync_all().await?;
+    }
+    drop(file);
+    tokio::fs::rename(&tmp, path).await
+  }
+  .await;
+  if result.is_err() {
+    let _ = tokio::fs::remove_file(&tmp).await;
+    return result;
+  }
+
+  // The rename itself is only durable once the directory entry is flushed
+  #[cfg(unix)]
+  if fsync {
+    if let Some(parent) = path.parent() {
+      tokio::fs::File::open(parent).await?.sync_all().await?;
+    }
+  }
+  Ok(())
 }
 
-pub async fn list(_params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
-  Err(RpcError {
-    code: -32002,
-    message: "Filesystem access not implemented".to_string(),
+/// A file's text for diffing and patching, or `None` if it doesn't exist.
+/// A file over the size limit is refused without being read.
+async fn read_text(path: &Path, shown: &str) -> Result<Option<String>, RpcError> {
+  use tokio::io::AsyncReadExt;
+  let too_large = |size: u64| {
+    RpcError::new(
+      FS_ERROR,
+      format!("'{}' is {} bytes; the limit is {}", shown, size, MAX_WRITE_BYTES),
+    )
+  };
+  let file = match tokio::fs::File::open(path).await {
+    Ok(file) => file,
+    Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+    Err(e) => return Err(io_error("read", shown, e)),
+  };
+  let size = file.metadata().await.map_err(|e| io_error("read", shown, e))?.len();
+  if size > MAX_WRITE_BYTES as u64 {
+    return Err(too_large(size));
+  }
+  // The file may grow after the check; one byte past the limit shows it did
+  let mut bytes = Vec::with_capacity(size as usize);
+  file
+    .take(MAX_WRITE_BYTES as u64 + 1)
+    .read_to_end(&mut bytes)
+    .await
+    .map_err(|e| io_error("read", shown, e))?;
+  if bytes.len() > MAX_WRITE_BYTES {
+    return Err(too_large(bytes.len() as u64));
+  }
+  String::from_utf8(bytes)
+    .map(Some)
+    .map_err(|_| RpcError::new(FS_ERROR, format!("'{}' is not UTF-8 text", shown)))
+}
+
+/// Unified diff between a file and proposed content: `{ path, content, context? }`.
+/// A missing file diffs as empty.
+pub async fn diff(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+  let (root, path) = resolve_param(&params, true, Access::Read).await?;
+  let shown = display_path(&root, &path);
+  let content = params
+    .get("content")
+    .and_then(|v| v.as_str())
+    .ok_or_else(|| RpcError::invalid_params("Missing 'content' parameter"))?;
+  let context = params.get("context").and_then(|v| v.as_u64()).map_or(3, |n| n.min(100) as usize);
+
+  let existing = read_text(&path, &shown).await?;
+  let old_name = match existing {
+    Some(_) => format!("a/{}", shown),
+    None => "/dev/null".to_string(),
+  };
+  let old = existing.as_deref().unwrap_or("");
+  let diff = patch::diff(old, content, &old_name, &format!("b/{}", shown), context);
+
+  Ok(serde_json::json!({
+    "path": shown,
+    "exists": existing.is_some(),
+    "changed": old != content,
+    "diff": diff,
+  }))
+}
+
+/// Apply a unified diff to a file: `{ path, patch, dry_run? }`.
+///
+/// Hunks may have moved by a few lines since the diff was made; the
+/// response says where each one went. If any hunk doesn't match, nothing is
+/// written and the error names the hunk. The result replaces the file as an
+/// `fs.write` overwrite would, so quotas apply; `atomic` and `fsync` are
+/// passed on.
+pub async fn apply_patch(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+  let (root, path) = resolve_param(&params, true, Access::Write).await?;
+  let shown = display_path(&root, &path);
+  let hunks = params
+    .get("patch")
+    .and_then(|v| v.as_str())
+    .ok_or_else(|| RpcError::invalid_params("Missing 'patch' parameter"))
+    .and_then(|text| patch::parse(text).map_err(RpcError::invalid_params))?;
+  let dry_run = params.get("dry_run").and_then(|v| v.as_bool()).unwrap_or(false);
+
+  let original = read_text(&path, &shown).await?.unwrap_or_default();
+  let (patched, placed) = patch::apply(&original, &hunks)
+    .map_err(|conflict| RpcError::new(FS_PATCH_CONFLICT, format!("'{}': {}", shown, conflict.message)))?;
+
+  if !dry_run {
+    let mut write_params = serde_json::json!({
+      "path": params.get("path"),
+      "content": patched.clone(),
+      "encoding": "utf8",
+      "mode": "overwrite",
+    });
+    for key in ["server_id", "atomic", "fsync"] {
+      if let Some(value) = params.get(key) {
+        write_params[key] = value.clone();
+      }
+    }
+    write(write_params).await?;
+  }
+  Ok(serde_json::json!({
+    "path": shown,
+    "applied": !dry_run,
+    "hunks": placed,
+    "size": patched.len(),
+  }))
+}
+
+/// Entry type as reported to callers. Expects `symlink_metadata`.
+fn file_kind(metadata: &std::fs::Metadata) -> &'static str {
+  if metadata.is_symlink() {
+    "symlink"
+  } else if metadata.is_dir() {
+    "directory"
+  } else if metadata.is_file() {
+    "file"
+  } else {
+    "other"
+  }
+}
+
+/// Last status change on Unix; creation time elsewhere.
+#[cfg(unix)]
+fn ctime_ms(metadata: &std::fs::Metadata) -> Option<i64> {
+  use std::os::unix::fs::MetadataExt;
+  Some(metadata.ctime() * 1000 + metadata.ctime_nsec() / 1_000_000)
+}
+
+/// Last status change on Unix; creation time elsewhere.
+#[cfg(not(unix))]
+fn ctime_ms(metadata: &std::fs::Metadata) -> Option<i64> {
+  mtime_ms(metadata.created())
+}
+
+/// Permission bits, as an octal string (e.g. `"644"`) on Unix.
+#[cfg(unix)]
+fn mode_string(metadata: &std::fs::Metadata) -> Option<String> {
+  use std::os::unix::fs::PermissionsExt;
+  Some(format!("{:o}", metadata.permissions().mode() & 0o7777))
+}
+
+#[cfg(not(unix))]
+fn mode_string(_metadata: &std::fs::Metadata) -> Option<String> {
+  None
+}
+
+/// MIME type of a file, sniffed from its first few KB.
+async fn sniff_file(path: &Path) -> Option<&'static str> {
+  use tokio::io::AsyncReadExt;
+  let mut head = Vec::with_capacity(8192);
+  let file = tokio::fs::File::open(path).await.ok()?;
+  file.take(8192).read_to_end(&mut head).await.ok()?;
+  Some(encoding::sniff_mime(&head, path))
+}
+
+/// Describe a path without returning its contents: `{ path }`. Missing
+/// paths are not an error; they are reported with `exists: false`.
+pub async fn stat(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+  let (root, path) = resolve_param(&params, true, Access::Read).await?;
+  let shown = display_path(&root, &path);
+
+  let metadata = match tokio::fs::symlink_metadata(&path).await {
+    Ok(metadata) => metadata,
+    Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+      return Ok(serde_json::json!({ "path": shown, "exists": false }));
+    }
+    Err(e) => return Err(io_error("stat", &shown, e)),
+  };
+
+  // Shown relative to the root, unless the `allow` policy let a link
+  // point elsewhere
+  let target = if metadata.is_symlink() {
+    tokio::fs::canonicalize(&path).await.ok().map(|t| display_path(&root, &t))
+  } else {
+    None
+  };
+  let mime = if metadata.is_file() { sniff_file(&path).await } else { None };
+
+  Ok(serde_json::json!({
+    "path": shown,
+    "exists": true,
+    "type": file_kind(&metadata),
+    "size": metadata.len(),
+    "mtime": mtime_ms(metadata.modified()),
+    "atime": mtime_ms(metadata.accessed()),
+    "ctime": ctime_ms(&metadata),
+    "mode": mode_string(&metadata),
+    "readonly": metadata.permissions().readonly(),
+    "target": target,
+    "mime": mime,
+  }))
+}
+
+/// Run a blocking filesystem operation off the async runtime.
+async fn blocking<T: Send + 'static>(
+  f: impl FnOnce() -> std::io::Result<T> + Send + 'static,
+) -> Result<std::io::Result<T>, RpcError> {
+  tokio::task::spawn_blocking(f)
+    .await
+    .map_err(|e| RpcError::internal(format!("Filesystem task failed: {}", e)))
+}
+
+fn overwrite_param(params: &serde_json::Value) -> Result<ops::Overwrite, RpcError> {
+  ops::Overwrite::parse(params.get("overwrite").and_then(|v| v.as_str()).unwrap_or("error"))
+    .map_err(RpcError::invalid_params)
+}
+
+/// Drop the calling server's measured usage after an operation whose effect
+/// on it isn't tracked.
+fn forget_usage(params: &serde_json::Value) {
+  if let Ok(Some(id)) = caller_id(params) {
+    state().usage.forget(&id);
+  }
+}
+
+/// Delete a file or directory: `{ path, recursive?, trash? }`.
+/// Non-empty directories need `recursive`. With `trash`, the entry is moved
+/// to the OS trash instead of being unlinked.
+pub async fn delete(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+  let (root, path) = resolve_param(&params, true, Access::Write).await?;
+  let shown = display_path(&root, &path);
+  if path == root {
+    return Err(RpcError::invalid_params("Cannot delete the sandbox root"));
+  }
+  let recursive = params.get("recursive").and_then(|v| v.as_bool()).unwrap_or(false);
+  let to_trash = params.get("trash").and_then(|v| v.as_bool()).unwrap_or(false);
+
+  let metadata = tokio::fs::symlink_metadata(&path).await.map_err(|e| io_error("delete", &shown, e))?;
+  let kind = file_kind(&metadata);
+  let target = path.clone();
+  let result = if to_trash {
+    blocking(move || {
+      // Match `fs.delete` without `trash`, which can't remove a non-empty directory either
+      if !recursive && std::fs::read_dir(&target).is_ok_and(|mut d| d.next().is_some()) {
+        return Err(std::io::Error::other("directory is not empty; set 'recursive' to delete it"));
+      }
+      trash::delete(&target).map_err(|e| std::io::Error::other(format!("could not move it to the trash: {}", e)))
+    })
+    .await?
+  } else {
+    blocking(move || ops::remove(&target, recursive)).await?
+  };
+  result.map_err(|e| io_error("delete", &shown, e))?;
+  forget_usage(&params);
+
+  Ok(serde_json::json!({
+    "path": shown,
+    "type": kind,
+    "trashed": to_trash,
+  }))
+}
+
+/// Move or rename: `{ source, destination, overwrite?: "error" | "replace" | "skip", recursive? }`.
+/// Replacing a directory at the destination needs `recursive`.
+pub async fn move_path(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+  let (root, source) = resolve_key(&params, "source", true, Access::Write).await?;
+  let (_, destination) = resolve_key(&params, "destination", true, Access::Write).await?;
+  if source == root || destination == root {
+    return Err(RpcError::invalid_params("Cannot move the sandbox root"));
+  }
+  if destination.starts_with(&source) && destination != source {
+    return Err(RpcError::invalid_params("Cannot move a directory into itself"));
+  }
+  let overwrite = overwrite_param(&params)?;
+  let recursive = params.get("recursive").and_then(|v| v.as_bool()).unwrap_or(false);
+  let shown = display_path(&root, &source);
+
+  let (from, to) = (source.clone(), destination.clone());
+  let moved = blocking(move || ops::rename(&from, &to, overwrite, recursive))
+    .await?
+    .map_err(|e| io_error("move", &shown, e))?;
+  forget_usage(&params);
+
+  Ok(serde_json::json!({
+    "source": shown,
+    "destination": display_path(&root, &destination),
+    "moved": moved,
+  }))
+}
+
+/// Copy a file or directory:
+/// `{ source, destination, recursive?, overwrite?: "error" | "replace" | "skip" }`.
+/// Directories need `recursive` and are merged into an existing destination.
+pub async fn copy(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+  let (root, source) = resolve_key(&params, "source", true, Access::Read).await?;
+  let (_, destination) = resolve_key(&params, "destination", true, Access::Write).await?;
+  if destination == root {
+    return Err(RpcError::invalid_params("Cannot copy onto the sandbox root"));
+  }
+  let overwrite = overwrite_param(&params)?;
+  let recursive = params.get("recursive").and_then(|v| v.as_bool()).unwrap_or(false);
+  let shown = display_path(&root, &source);
+
+  let (from, to) = (source.clone(), destination.clone());
+  let stats = blocking(move || ops::copy(&from, &to, overwrite, recursive))
+    .await?
+    .map_err(|e| io_error("copy", &shown, e))?;
+  forget_usage(&params);
+
+  Ok(serde_json::json!({
+    "source": shown,
+    "destination": display_path(&root, &destination),
+    "copied": stats,
+  }))
+}
+
+/// Bytes the calling server may still store at `path`, if a quota applies.
+async fn quota_room(params: &serde_json::Value, path: &Path) -> Result<Option<u64>, RpcError> {
+  let Some(id) = caller_id(params)? else {
+    return Ok(None);
+  };
+  let Some(limit) = with_grants(|grants| grants.quotas.get(&id).copied()).await else {
+    return Ok(None);
+  };
+  let (used, roots) = server_usage(&id).await?;
+  Ok(quota::counted(&roots, path).then(|| limit.saturating_sub(used)))
+}
+
+fn archive_format(params: &serde_json::Value, archive: &Path) -> Result<archive::Format, RpcError> {
+  match params.get("format").and_then(|v| v.as_str()) {
+    Some(name) => archive::Format::parse(name).map_err(RpcError::invalid_params),
+    None => archive::Format::from_path(archive)
+      .ok_or_else(|| RpcError::invalid_params("Can't tell the archive format from its name; pass 'format'")),
+  }
+}
+
+/// Create an archive of files and directories:
+/// `{ paths: [path], destination, format?: "zip" | "tar.gz", overwrite?: "error" | "replace" }`.
+/// Each path is stored under its own name. Symlinks are left out.
+pub async fn zip(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+  let (root, destination) = resolve_key(&params, "destination", true, Access::Write).await?;
+  let shown = display_path(&root, &destination);
+  let format = archive_format(&params, &destination)?;
+  let requested: Vec<String> = params
+    .get("paths")
+    .and_then(|v| serde_json::from_value(v.clone()).ok())
+    .filter(|paths: &Vec<String>| !paths.is_empty())
+    .ok_or_else(|| RpcError::invalid_params("'paths' must be a non-empty list of paths"))?;
+  let mut sources = Vec::new();
+  for path in &requested {
+    let (_, source) = resolve_path(&params, path, Access::Read).await?;
+    if destination.starts_with(&source) {
+      return Err(RpcError::invalid_params("Cannot write an archive inside what it archives"));
+    }
+    sources.push(source);
+  }
+  if tokio::fs::symlink_metadata(&destination).await.is_ok() {
+    match overwrite_param(&params)? {
+      ops::Overwrite::Replace => {}
+      _ => return Err(RpcError::new(FS_ERROR, format!("'{}' already exists", shown))),
+    }
+  }
+
+  // The archive's size is only known once it's written
+  let room = quota_room(&params, &destination).await?;
+  let target = destination.clone();
+  let stats = blocking(move || archive::create(&sources, &target, format))
+    .await?
+    .map_err(|e| io_error("create archive", &shown, e))?;
+  forget_usage(&params);
+
+  let size = tokio::fs::metadata(&destination).await.map(|m| m.len()).unwrap_or(0);
+  if room.is_some_and(|room| size > room) {
+    let _ = tokio::fs::remove_file(&destination).await;
+    return Err(RpcError::new(
+      FS_QUOTA_EXCEEDED,
+      format!("The archive '{}' would take the server over its storage quota", shown),
+    ));
+  }
+
+  Ok(serde_json::json!({
+    "path": shown,
+    "size": size,
+    "archived": stats,
+  }))
+}
+
+/// Extract an archive:
+/// `{ path, destination?, format?, overwrite?: "error" | "replace" | "skip" }`.
+/// The destination defaults to a directory named after the archive, next to
+/// it. Extraction stops at the size limit or the server's storage quota,
+/// and is undone if it fails.
+pub async fn unzip(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+  let (root, archive_path) = resolve_param(&params, true, Access::Read).await?;
+  let shown = display_path(&root, &archive_path);
+  let format = archive_format(&params, &archive_path)?;
+  let (_, destination) = match params.get("destination").and_then(|v| v.as_str()) {
+    Some(_) => resolve_key(&params, "destination", true, Access::Write).await?,
+    None => {
+      let name = archive_path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+      let lower = name.to_lowercase();
+      let stem = [".tar.gz", ".tgz", ".zip"]
+        .iter()
+        .find(|ext| lower.ends_with(*ext))
+        .map_or(name.as_str(), |ext| &name[..name.len() - ext.len()]);
+      let requested = match archive_path.parent().map(|p| display_path(&root, p)) {
+        Some(parent) if !parent.is_empty() => format!("{}/{}", parent, stem),
+        _ => stem.to_string(),
+      };
+      resolve_path(&params, &requested, Access::Write).await?
+    }
+  };
+  if destination == root && params.get("destination").is_none() {
+    return Err(RpcError::invalid_params("Pass a 'destination' for this archive"));
+  }
+  let overwrite = overwrite_param(&params)?;
+  let max_bytes = match quota_room(&params, &destination).await? {
+    Some(room) => room.min(archive::MAX_BYTES),
+    None => archive::MAX_BYTES,
+  };
+
+  let (from, to) = (archive_path.clone(), destination.clone());
+  let stats = blocking(move || archive::extract(&from, &to, format, overwrite, max_bytes))
+    .await?
+    .map_err(|e| io_error("extract", &shown, e))?;
+  forget_usage(&params);
+
+  Ok(serde_json::json!({
+    "path": shown,
+    "destination": display_path(&root, &destination),
+    "extracted": stats,
+  }))
+}
+
+#[derive(Debug, Serialize)]
+struct Entry {
+  name: String,
+  #[serde(rename = "type")]
+  kind: &'static str,
+  size: u64,
+  mtime: Option<i64>,
+}
+
+/// List a directory: `{ path? }` (default: the sandbox root).
+pub async fn list(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+  let (root, path) = resolve_param(&params, false, Access::Read).await?;
+  let shown = display_path(&root, &path);
+  let hide_links = with_grants(|grants| grants.symlinks == SymlinkPolicy::Deny).await;
+
+  let mut dir = tokio::fs::read_dir(&path).await.map_err(|e| io_error("list", &shown, e))?;
+  let mut entries = Vec::new();
+  let mut truncated = false;
+  while let Some(entry) = dir.next_entry().await.map_err(|e| io_error("list", &shown, e))? {
+    if entries.len() == MAX_LIST_ENTRIES {
+      truncated = true;
+      break;
+    }
+    // symlink_metadata, so links are reported as links rather than followed
+    let Ok(metadata) = tokio::fs::symlink_metadata(entry.path()).await else {
+      continue;
+    };
+    if hide_links && metadata.is_symlink() {
+      continue;
+    }
+    entries.push(Entry {
+      name: entry.file_name().to_string_lossy().into_owned(),
+      kind: file_kind(&metadata),
+      size: if metadata.is_file() { metadata.len() } else { 0 },
+      mtime: mtime_ms(metadata.modified()),
+    });
+  }
+  entries.sort_by(|a, b| a.name.cmp(&b.name));
+
+  Ok(serde_json::json!({
+    "path": shown,
+    "entries": entries,
+    "truncated": truncated,
+  }))
+}
+
+/// Find files by glob and/or line content:
+/// `{ path?, glob?, pattern?, case_sensitive?, max_results? }`.
+/// The glob is matched against paths relative to `path`; `pattern` is a
+/// regex matched against each line.
+pub async fn search(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+  let (root, path) = resolve_param(&params, false, Access::Read).await?;
+  let query = search::Query::from_params(&params).map_err(RpcError::invalid_params)?;
+  let symlinks = with_grants(|grants| grants.symlinks).await;
+
+  let results = tokio::task::spawn_blocking(move || search::search(&root, &path, &query, symlinks))
+    .await
+    .map_err(|e| RpcError::internal(format!("Search failed: {}", e)))?;
+  Ok(serde_json::json!(results))
+}
+
+fn server_id_param(params: &serde_json::Value) -> Result<&str, RpcError> {
+  params
+    .get("server_id")
+    .and_then(|v| v.as_str())
+    .ok_or_else(|| RpcError::invalid_params("Missing 'server_id' parameter"))
+}
+
+/// Record a directory grant for a server, once the user has approved it:
+/// `{ server_id, path, access: "read" | "write", duration_secs? }`.
+pub async fn request_access(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+  crate::rpc::require_extension("change filesystem permissions")
+    .map_err(|e| RpcError::new(FS_PERMISSION_DENIED, e.message))?;
+  let server_id = server_id_param(&params)?;
+  let access: Access = serde_json::from_value(params.get("access").cloned().unwrap_or_default())
+    .map_err(|_| RpcError::invalid_params("'access' must be \"read\" or \"write\""))?;
+
+  let root = sandbox_root()?;
+  let requested = params.get("path").and_then(|v| v.as_str()).unwrap_or("");
+  let symlinks = with_grants(|grants| grants.symlinks).await;
+  let path = resolve_in(&root, requested, symlinks).map_err(RpcError::invalid_params)?;
+
+  let now = chrono::Utc::now().timestamp_millis();
+  let grant = Grant {
+    path: display_path(&root, &path),
+    access,
+    granted_at: now,
+    expires_at: params
+      .get("duration_secs")
+      .and_then(|v| v.as_i64())
+      .map(|secs| now.saturating_add(secs.saturating_mul(1000))),
+  };
+
+  tracing::info!("Granting {} {:?} access to '{}'", server_id, grant.access, grant.path);
+  state().usage.forget(server_id);
+  let result = serde_json::json!({ "grant": grant });
+  with_grants(|grants| {
+    grants.grant(server_id, grant);
+    permissions::save(grants)
+  })
+  .await
+  .map_err(RpcError::internal)?;
+
+  Ok(result)
+}
+
+/// Revoke a server's grant for one directory (`path`), or all of them.
+pub async fn revoke_access(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+  crate::rpc::require_extension("change filesystem permissions")
+    .map_err(|e| RpcError::new(FS_PERMISSION_DENIED, e.message))?;
+  let server_id = server_id_param(&params)?;
+  let path = params.get("path").and_then(|v| v.as_str());
+  state().usage.forget(server_id);
+
+  let root = sandbox_root()?;
+  let (removed, unwatched) = with_grants(|grants| {
+    let removed = grants.revoke(server_id, path);
+    permissions::save(grants)?;
+
+    // Stop watches the server no longer has access to. A jailed server's
+    // watches don't depend on its grants.
+    let unwatched = state().watches.retain(|w| {
+      w.server_id.as_deref() != Some(server_id)
+        || grants.jails.contains_key(server_id)
+        || grants.allows(server_id, &root, &root.join(&w.path), Access::Read)
+    });
+    Ok::<_, String>((removed, unwatched))
+  })
+  .await
+  .map_err(RpcError::internal)?;
+
+  Ok(serde_json::json!({ "removed": removed, "unwatched": unwatched }))
+}
+
+/// List grants and jails, for one `server_id` or every server, and the
+/// symlink policy.
+pub async fn list_access(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+  let server_id = params.get("server_id").and_then(|v| v.as_str());
+  let (servers, jails, symlinks) = with_grants(|grants| match server_id {
+    Some(id) => (
+      serde_json::json!({ id: grants.servers.get(id).cloned().unwrap_or_default() }),
+      match grants.jails.get(id) {
+        Some(jail) => serde_json::json!({ id: jail }),
+        None => serde_json::json!({}),
+      },
+      grants.symlinks,
+    ),
+    None => (
+      serde_json::json!(grants.servers),
+      serde_json::json!(grants.jails),
+      grants.symlinks,
+    ),
+  })
+  .await;
+  Ok(serde_json::json!({ "servers": servers, "jails": jails, "symlinks": symlinks }))
+}
+
+/// Set how paths through symlinks are treated:
+/// `{ policy: "deny" | "within_sandbox" | "allow" }`.
+pub async fn set_symlink_policy(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+  crate::rpc::require_extension("change filesystem permissions")
+    .map_err(|e| RpcError::new(FS_PERMISSION_DENIED, e.message))?;
+  let policy: SymlinkPolicy = serde_json::from_value(params.get("policy").cloned().unwrap_or_default())
+    .map_err(|_| RpcError::invalid_params("'policy' must be \"deny\", \"within_sandbox\" or \"allow\""))?;
+
+  tracing::info!("Setting filesystem symlink policy: {:?}", policy);
+  with_grants(|grants| {
+    grants.symlinks = policy;
+    permissions::save(grants)
+  })
+  .await
+  .map_err(RpcError::internal)?;
+  Ok(serde_json::json!({ "symlinks": policy }))
+}
+
+/// Confine a server to its own root directory, or release it:
+/// `{ server_id, root?, access?: "read" | "write" }`. Without `root`, the
+/// server's jail is removed and it is back to the sandbox and its grants.
+pub async fn set_jail(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+  crate::rpc::require_extension("change filesystem permissions")
+    .map_err(|e| RpcError::new(FS_PERMISSION_DENIED, e.message))?;
+  let server_id = server_id_param(&params)?;
+  let jail = match params.get("root").and_then(|v| v.as_str()) {
+    Some(root) => {
+      let access = match params.get("access") {
+        Some(access) => serde_json::from_value(access.clone())
+          .map_err(|_| RpcError::invalid_params("'access' must be \"read\" or \"write\""))?,
+        None => Access::Write,
+      };
+      let jail = Jail { root: root.to_string(), access };
+      jail.resolve().map_err(RpcError::invalid_params)?;
+      Some(jail)
+    }
+    None => None,
+  };
+
+  tracing::info!("Setting filesystem jail for {}: {:?}", server_id, jail);
+  let result = serde_json::json!({ "server_id": server_id, "jail": jail });
+  with_grants(|grants| {
+    match jail {
+      Some(jail) => grants.jails.insert(server_id.to_string(), jail),
+      None => grants.jails.remove(server_id),
+    };
+    permissions::save(grants)
+  })
+  .await
+  .map_err(RpcError::internal)?;
+
+  // Watches and usage were resolved against the old root
+  stop_watches(server_id);
+  state().usage.forget(server_id);
+  Ok(result)
+}
+
+/// Set or clear a server's storage quota: `{ server_id, bytes? }`.
+pub async fn set_quota(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+  crate::rpc::require_extension("change filesystem permissions")
+    .map_err(|e| RpcError::new(FS_PERMISSION_DENIED, e.message))?;
+  let server_id = server_id_param(&params)?;
+  let bytes = params.get("bytes").and_then(|v| v.as_u64());
+
+  with_grants(|grants| {
+    match bytes {
+      Some(bytes) => grants.quotas.insert(server_id.to_string(), bytes),
+      None => grants.quotas.remove(server_id),
+    };
+    permissions::save(grants)
   })
+  .await
+  .map_err(RpcError::internal)?;
+  Ok(serde_json::json!({ "server_id": server_id, "quota_bytes": bytes }))
+}
+
+/// Report storage use against quotas, for one server (`server_id`, or the
+/// caller) or every server with grants, a jail or a quota.
+pub async fn usage(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+  let server_ids: Vec<String> = match caller_id(&params)? {
+    Some(id) => vec![id],
+    None => with_grants(|grants| {
+      let mut ids: Vec<String> = grants
+        .servers
+        .keys()
+        .chain(grants.jails.keys())
+        .chain(grants.quotas.keys())
+        .cloned()
+        .collect();
+      ids.sort();
+      ids.dedup();
+      ids
+    })
+    .await,
+  };
+
+  let root = sandbox_root()?;
+  let mut servers = Vec::new();
+  for id in server_ids {
+    let (used, roots) = server_usage(&id).await?;
+    let limit = with_grants(|grants| grants.quotas.get(&id).copied()).await;
+    servers.push(serde_json::json!({
+      "server_id": id,
+      "used_bytes": used,
+      "quota_bytes": limit,
+      "directories": roots.iter().map(|r| display_path(&root, r)).collect::<Vec<_>>(),
+    }));
+  }
+  Ok(serde_json::json!({ "servers": servers }))
+}
+
+/// Watch a path for changes:
+/// `{ path?, server_id?, recursive? (default true), debounce_ms? }`.
+/// Changes are reported as `fs/changed` events.
+pub async fn watch(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+  let (root, path) = resolve_param(&params, false, Access::Read).await?;
+  let server_id = caller_id(&params)?;
+  let recursive = params.get("recursive").and_then(|v| v.as_bool()).unwrap_or(true);
+  let debounce_ms = params.get("debounce_ms").and_then(|v| v.as_u64());
+
+  let info = state()
+    .watches
+    .add(root, &path, server_id, recursive, debounce_ms)
+    .map_err(|e| RpcError::new(FS_ERROR, e))?;
+  Ok(serde_json::json!(info))
+}
+
+/// Stop a watch subscription: `{ subscription_id }`.
+pub async fn unwatch(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+  let subscription_id = params
+    .get("subscription_id")
+    .and_then(|v| v.as_str())
+    .ok_or_else(|| RpcError::invalid_params("Missing 'subscription_id' parameter"))?;
+  let removed = state().watches.remove(subscription_id).is_some();
+  Ok(serde_json::json!({ "removed": removed }))
+}
+
+/// List watch subscriptions, for one `server_id` or all of them.
+pub async fn list_watches(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+  let server_id = params.get("server_id").and_then(|v| v.as_str());
+  Ok(serde_json::json!({ "watches": state().watches.list(server_id) }))
+}
+
+/// Recreate every watch's OS watcher. Returns how many are active.
+pub fn rearm_watches() -> usize {
+  state().watches.rearm()
+}
+
+/// Stop every watch belonging to a server, e.g. when it is stopped.
+pub fn stop_watches(server_id: &str) {
+  state().watches.retain(|w| w.server_id.as_deref() != Some(server_id));
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn temp_root(name: &str) -> PathBuf {
+    let root = std::env::temp_dir().join(format!("harbor-fs-test-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(root.join("docs")).unwrap();
+    root.canonicalize().unwrap()
+  }
+
+  #[test]
+  fn test_resolve_inside_root() {
+    let root = temp_root("inside");
+    assert_eq!(resolve_in(&root, "docs/a.txt", SymlinkPolicy::WithinSandbox).unwrap(), root.join("docs/a.txt"));
+    assert_eq!(resolve_in(&root, "docs/../b.txt", SymlinkPolicy::WithinSandbox).unwrap(), root.join("b.txt"));
+    assert_eq!(resolve_in(&root, "", SymlinkPolicy::WithinSandbox).unwrap(), root);
+    let absolute = root.join("docs").to_string_lossy().into_owned();
+    assert_eq!(resolve_in(&root, &absolute, SymlinkPolicy::WithinSandbox).unwrap(), root.join("docs"));
+  }
+
+  #[test]
+  fn test_resolve_rejects_escapes() {
+    let root = temp_root("escape");
+    assert!(resolve_in(&root, "..", SymlinkPolicy::WithinSandbox).is_err());
+    assert!(resolve_in(&root, "docs/../../etc/passwd", SymlinkPolicy::WithinSandbox).is_err());
+    assert!(resolve_in(&root, "/etc/passwd", SymlinkPolicy::WithinSandbox).is_err());
+  }
+
+  #[cfg(unix)]
+  #[test]
+  fn test_resolve_rejects_symlink_escape() {
+    let root = temp_root("symlink");
+    let link = root.join("outside");
+    let _ = std::fs::remove_file(&link);
+    std::os::unix::fs::symlink("/", &link).unwrap();
+    assert!(resolve_in(&root, "outside/etc/passwd", SymlinkPolicy::WithinSandbox).is_err());
+    assert!(resolve_in(&root, "outside/etc/passwd", SymlinkPolicy::Allow).is_ok());
+
+    // A link to a file that doesn't exist yet, which writing would create
+    let dangling = root.join("dangling");
+    let _ = std::fs::remove_file(&dangling);
+    std::os::unix::fs::symlink("/tmp/harbor-fs-test-nowhere/new.txt", &dangling).unwrap();
+    assert!(resolve_in(&root, "dangling", SymlinkPolicy::WithinSandbox).is_err());
+  }
+
+  #[cfg(unix)]
+  #[test]
+  fn test_resolve_denies_symlinks() {
+    let root = temp_root("deny-links");
+    let link = root.join("alias");
+    let _ = std::fs::remove_file(&link);
+    std::os::unix::fs::symlink(root.join("docs"), &link).unwrap();
+    assert!(resolve_in(&root, "alias/a.txt", SymlinkPolicy::WithinSandbox).is_ok());
+    assert!(resolve_in(&root, "alias/a.txt", SymlinkPolicy::Deny).is_err());
+    assert!(resolve_in(&root, "docs/new/a.txt", SymlinkPolicy::Deny).is_ok());
+  }
 }
@@ -0,0 +1,184 @@
+//! Delete, move and copy inside the sandbox.
+//!
+//! Symlinks are never followed: deleting a link removes the link, and
+//! recursive copies skip links rather than copying what they point to,
+//! which may be outside the sandbox.
+
+use std::io;
+use std::path::Path;
+
+use serde::Serialize;
+
+/// What to do when the destination of a move or copy exists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Overwrite {
+    /// Fail the operation
+    Error,
+    /// Replace the existing file
+    Replace,
+    /// Leave the existing file and carry on
+    Skip,
+}
+
+impl Overwrite {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "error" => Ok(Self::Error),
+            "replace" => Ok(Self::Replace),
+            "skip" => Ok(Self::Skip),
+            other => Err(format!(
+                "Unknown overwrite policy '{}'; use \"error\", \"replace\" or \"skip\"",
+                other
+            )),
+        }
+    }
+}
+
+/// What a copy did.
+#[derive(Debug, Default, Serialize)]
+pub struct CopyStats {
+    pub files: usize,
+    pub directories: usize,
+    pub bytes: u64,
+    /// Existing files left in place by `skip`
+    pub skipped: usize,
+    /// Symlinks and special files, which are not copied
+    pub ignored: usize,
+}
+
+fn exists_error(path: &Path) -> io::Error {
+    io::Error::new(io::ErrorKind::AlreadyExists, format!("{:?} already exists", path))
+}
+
+/// Remove a file, link or (with `recursive`) directory.
+pub fn remove(path: &Path, recursive: bool) -> io::Result<()> {
+    let metadata = std::fs::symlink_metadata(path)?;
+    if !metadata.is_dir() {
+        std::fs::remove_file(path)
+    } else if recursive {
+        std::fs::remove_dir_all(path)
+    } else {
+        // Fails unless the directory is empty
+        std::fs::remove_dir(path)
+    }
+}
+
+/// Move `source` to `destination`. A directory in the way is only replaced
+/// with `recursive`.
+pub fn rename(source: &Path, destination: &Path, overwrite: Overwrite, recursive: bool) -> io::Result<bool> {
+    if std::fs::symlink_metadata(destination).is_ok() {
+        match overwrite {
+            Overwrite::Error => return Err(exists_error(destination)),
+            Overwrite::Skip => return Ok(false),
+            Overwrite::Replace => remove(destination, recursive)?,
+        }
+    }
+    if let Some(parent) = destination.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::rename(source, destination)?;
+    Ok(true)
+}
+
+/// Copy a file, or (with `recursive`) a directory tree. Existing files are
+/// handled per `overwrite`; existing directories are merged into.
+pub fn copy(source: &Path, destination: &Path, overwrite: Overwrite, recursive: bool) -> io::Result<CopyStats> {
+    let mut stats = CopyStats::default();
+    let metadata = std::fs::symlink_metadata(source)?;
+    if metadata.is_dir() && !recursive {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "source is a directory; set 'recursive' to copy it",
+        ));
+    }
+    if destination.starts_with(source) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "cannot copy a directory into itself",
+        ));
+    }
+    if let Some(parent) = destination.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    copy_entry(source, destination, &metadata, overwrite, &mut stats)?;
+    Ok(stats)
+}
+
+fn copy_entry(
+    source: &Path,
+    destination: &Path,
+    metadata: &std::fs::Metadata,
+    overwrite: Overwrite,
+    stats: &mut CopyStats,
+) -> io::Result<()> {
+    if metadata.is_dir() {
+        match std::fs::symlink_metadata(destination) {
+            Ok(existing) if existing.is_dir() => {}
+            Ok(_) => return Err(exists_error(destination)),
+            Err(_) => {
+                std::fs::create_dir(destination)?;
+                stats.directories += 1;
+            }
+        }
+        let mut entries: Vec<_> = std::fs::read_dir(source)?.collect::<io::Result<_>>()?;
+        entries.sort_by_key(|e| e.file_name());
+        for entry in entries {
+            let metadata = std::fs::symlink_metadata(entry.path())?;
+            copy_entry(&entry.path(), &destination.join(entry.file_name()), &metadata, overwrite, stats)?;
+        }
+        return Ok(());
+    }
+    if !metadata.is_file() {
+        stats.ignored += 1;
+        return Ok(());
+    }
+
+    if let Ok(existing) = std::fs::symlink_metadata(destination) {
+        match overwrite {
+            Overwrite::Error => return Err(exists_error(destination)),
+            Overwrite::Skip => {
+                stats.skipped += 1;
+                return Ok(());
+            }
+            // A file doesn't replace a directory
+            Overwrite::Replace if existing.is_dir() => return Err(exists_error(destination)),
+            Overwrite::Replace => std::fs::remove_file(destination)?,
+        }
+    }
+    stats.bytes += std::fs::copy(source, destination)?;
+    stats.files += 1;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_copy_tree_with_policies() {
+        let root = std::env::temp_dir().join(format!("harbor-fs-ops-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("src/sub")).unwrap();
+        std::fs::write(root.join("src/a.txt"), "new").unwrap();
+        std::fs::write(root.join("src/sub/b.txt"), "b").unwrap();
+        std::fs::create_dir_all(root.join("dst")).unwrap();
+        std::fs::write(root.join("dst/a.txt"), "old").unwrap();
+
+        assert!(copy(&root.join("src"), &root.join("dst"), Overwrite::Skip, false).is_err());
+        assert!(copy(&root.join("src"), &root.join("src/sub/x"), Overwrite::Skip, true).is_err());
+
+        let stats = copy(&root.join("src"), &root.join("dst"), Overwrite::Skip, true).unwrap();
+        assert_eq!((stats.files, stats.skipped, stats.directories), (1, 1, 1));
+        assert_eq!(std::fs::read_to_string(root.join("dst/a.txt")).unwrap(), "old");
+
+        assert!(copy(&root.join("src"), &root.join("dst"), Overwrite::Error, true).is_err());
+        copy(&root.join("src"), &root.join("dst"), Overwrite::Replace, true).unwrap();
+        assert_eq!(std::fs::read_to_string(root.join("dst/a.txt")).unwrap(), "new");
+
+        assert!(remove(&root.join("dst"), false).is_err());
+        remove(&root.join("dst"), true).unwrap();
+        assert!(!root.join("dst").exists());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
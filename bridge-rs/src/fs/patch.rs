@@ -0,0 +1,226 @@
+//! Unified diffs: producing them, and applying them to a file's text.
+//!
+//! Hunks are applied in order. A hunk whose lines aren't at the position it
+//! names is looked for a little above and below, as `patch` does, since
+//! earlier edits may have shifted the file. If any hunk can't be placed the
+//! patch is rejected as a whole, so a file is never left half-patched.
+
+use serde::Serialize;
+
+/// How far from its stated position a hunk is looked for.
+const MAX_OFFSET: usize = 100;
+
+/// Unified diff from `old` to `new`, with `context` lines around changes.
+pub fn diff(old: &str, new: &str, old_name: &str, new_name: &str, context: usize) -> String {
+    similar::TextDiff::from_lines(old, new)
+        .unified_diff()
+        .context_radius(context)
+        .header(old_name, new_name)
+        .to_string()
+}
+
+/// One hunk of a unified diff.
+#[derive(Debug, Default)]
+pub struct Hunk {
+    /// 1-based line the hunk starts at in the old file (0 for an empty file)
+    old_start: usize,
+    old_lines: Vec<String>,
+    new_lines: Vec<String>,
+    /// Whether the new side ends with "\ No newline at end of file"
+    new_no_eol: bool,
+}
+
+/// Where a hunk was applied.
+#[derive(Debug, Serialize)]
+pub struct Placed {
+    /// 1-based line in the patched file
+    pub line: usize,
+    /// Lines from where the hunk said it would be
+    pub offset: isize,
+}
+
+/// Why a patch didn't apply.
+#[derive(Debug)]
+pub struct Conflict {
+    /// 1-based hunk number
+    pub hunk: usize,
+    pub message: String,
+}
+
+fn hunk_header(line: &str) -> Option<(usize, usize, usize)> {
+    // @@ -old_start[,old_count] +new_start[,new_count] @@ ...
+    let mut parts = line.strip_prefix("@@ ")?.split_whitespace();
+    let range = |part: Option<&str>, sign: char| -> Option<(usize, usize)> {
+        let part = part?.strip_prefix(sign)?;
+        match part.split_once(',') {
+            Some((start, count)) => Some((start.parse().ok()?, count.parse().ok()?)),
+            None => Some((part.parse().ok()?, 1)),
+        }
+    };
+    let (old_start, old_count) = range(parts.next(), '-')?;
+    let (_, new_count) = range(parts.next(), '+')?;
+    Some((old_start, old_count, new_count))
+}
+
+/// Parse the hunks of a single-file unified diff.
+pub fn parse(patch: &str) -> Result<Vec<Hunk>, String> {
+    let mut hunks: Vec<Hunk> = Vec::new();
+    let mut lines = patch.split('\n').peekable();
+    let mut files = 0;
+
+    while let Some(line) = lines.next() {
+        if line.starts_with("+++ ") {
+            files += 1;
+            if files > 1 {
+                return Err("The patch changes more than one file".to_string());
+            }
+            continue;
+        }
+        if !line.starts_with("@@") {
+            // File headers, `diff` lines and other preamble
+            continue;
+        }
+        let (old_start, mut old_left, mut new_left) =
+            hunk_header(line).ok_or_else(|| format!("Invalid hunk header '{}'", line))?;
+        let mut hunk = Hunk {
+            old_start,
+            ..Default::default()
+        };
+        // "\ No newline at end of file" applies to the line before it
+        let mut last = ' ';
+
+        while old_left > 0 || new_left > 0 {
+            let Some(line) = lines.next() else {
+                return Err(format!("Hunk {} ends early", hunks.len() + 1));
+            };
+            // Some tools drop the space from blank context lines
+            let (marker, text) = match line.chars().next() {
+                Some(marker) => (marker, &line[marker.len_utf8()..]),
+                None => (' ', ""),
+            };
+            match marker {
+                ' ' if old_left > 0 && new_left > 0 => {
+                    hunk.old_lines.push(text.to_string());
+                    hunk.new_lines.push(text.to_string());
+                    old_left -= 1;
+                    new_left -= 1;
+                }
+                '-' if old_left > 0 => {
+                    hunk.old_lines.push(text.to_string());
+                    old_left -= 1;
+                }
+                '+' if new_left > 0 => {
+                    hunk.new_lines.push(text.to_string());
+                    new_left -= 1;
+                }
+                '\\' => {
+                    hunk.new_no_eol |= last != '-';
+                    continue;
+                }
+                _ => return Err(format!("Unexpected line in hunk {}: '{}'", hunks.len() + 1, line)),
+            }
+            last = marker;
+        }
+        if lines.next_if(|l| l.starts_with('\\')).is_some() {
+            hunk.new_no_eol |= last != '-';
+        }
+        hunks.push(hunk);
+    }
+
+    if hunks.is_empty() {
+        return Err("The patch has no hunks".to_string());
+    }
+    Ok(hunks)
+}
+
+/// Apply parsed hunks to `original`, returning the new text and where each
+/// hunk went.
+pub fn apply(original: &str, hunks: &[Hunk]) -> Result<(String, Vec<Placed>), Conflict> {
+    // Split on '\n' only, so CRLF files keep their '\r's
+    let mut lines: Vec<&str> = match original.strip_suffix('\n').unwrap_or(original) {
+        "" if original.is_empty() => Vec::new(),
+        body => body.split('\n').collect(),
+    };
+    let mut trailing_newline = original.is_empty() || original.ends_with('\n');
+    let mut placed = Vec::new();
+    // Net lines added by earlier hunks, and the first line they didn't touch
+    let mut shift: isize = 0;
+    let mut floor = 0;
+
+    for (index, hunk) in hunks.iter().enumerate() {
+        // A hunk for an insertion names the line before it
+        let stated = if hunk.old_lines.is_empty() {
+            hunk.old_start
+        } else {
+            hunk.old_start.saturating_sub(1)
+        };
+        let expected = (stated as isize + shift).max(0) as usize;
+        let fits = |at: usize| {
+            at >= floor
+                && at + hunk.old_lines.len() <= lines.len()
+                && lines[at..at + hunk.old_lines.len()].iter().zip(&hunk.old_lines).all(|(a, b)| a == b)
+        };
+        let at = (0..=MAX_OFFSET)
+            .flat_map(|d| [expected.checked_add(d), expected.checked_sub(d)])
+            .flatten()
+            .find(|at| fits(*at))
+            .ok_or_else(|| Conflict {
+                hunk: index + 1,
+                message: format!(
+                    "Hunk {} does not match the file near line {}",
+                    index + 1,
+                    expected + 1
+                ),
+            })?;
+
+        let reaches_end = at + hunk.old_lines.len() == lines.len();
+        lines.splice(at..at + hunk.old_lines.len(), hunk.new_lines.iter().map(String::as_str));
+        if reaches_end {
+            trailing_newline = !hunk.new_no_eol;
+        }
+        placed.push(Placed {
+            line: at + 1,
+            offset: at as isize - expected as isize,
+        });
+        shift += hunk.new_lines.len() as isize - hunk.old_lines.len() as isize + (at as isize - expected as isize);
+        floor = at + hunk.new_lines.len();
+    }
+
+    let mut text = lines.join("\n");
+    if trailing_newline && !lines.is_empty() {
+        text.push('\n');
+    }
+    Ok((text, placed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_round_trip() {
+        let old = "one\ntwo\nthree\nfour\nfive\n";
+        let patch = diff(old, "one\ntwo\nthree\nfour\n", "a/f.txt", "b/f.txt", 1);
+        assert_eq!(apply(old, &parse(&patch).unwrap()).unwrap().0, "one\ntwo\nthree\nfour\n");
+
+        let new = "one\n2\nthree\nfour\nfive\nsix";
+        let patch = diff(old, new, "a/f.txt", "b/f.txt", 1);
+        let (patched, placed) = apply(old, &parse(&patch).unwrap()).unwrap();
+        assert_eq!(patched, new);
+        assert!(placed.iter().all(|p| p.offset == 0));
+    }
+
+    #[test]
+    fn test_apply_with_offset_and_conflict() {
+        let patch = "--- a/f.txt\n+++ b/f.txt\n@@ -2,2 +2,2 @@\n b\n-c\n+C\n";
+        let hunks = parse(patch).unwrap();
+        // Two lines were added above since the diff was made
+        let (patched, placed) = apply("x\ny\na\nb\nc\n", &hunks).unwrap();
+        assert_eq!(patched, "x\ny\na\nb\nC\n");
+        assert_eq!(placed[0].offset, 2);
+
+        let conflict = apply("a\nb\nd\n", &hunks).unwrap_err();
+        assert_eq!(conflict.hunk, 1);
+        assert!(parse("not a patch").is_err());
+    }
+}
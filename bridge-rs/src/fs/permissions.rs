@@ -0,0 +1,284 @@
+//! Per-server directory grants for filesystem access.
+//!
+//! A server may only touch paths under a directory it has been granted,
+//! with `read` or `write` access (write implies read). Grants are recorded
+//! by `fs.request_access` once the user approves them, and persisted in
+//! `~/.harbor/fs_permissions.json`. Paths are stored relative to the sandbox
+//! root.
+//!
+//! A server may instead be jailed to its own root directory (e.g. a notes
+//! server to `~/Notes`), declared in the same file under `jails`. A jailed
+//! server sees only that directory, with the jail's access level, and its
+//! grants don't apply.
+//!
+//! Byte quotas on what a server may store are kept here too, under `quotas`,
+//! as is the symlink policy applied to every path.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+const PERMISSIONS_FILE_NAME: &str = "fs_permissions.json";
+
+/// Level of access to a directory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Access {
+    Read,
+    Write,
+}
+
+/// How paths through symlinks are treated.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SymlinkPolicy {
+    /// Reject any path that goes through a symlink
+    Deny,
+    /// Follow symlinks whose target stays inside the root
+    #[default]
+    WithinSandbox,
+    /// Follow symlinks wherever they point
+    Allow,
+}
+
+/// A directory a server may access.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Grant {
+    /// Directory, relative to the sandbox root (`""` is the root itself)
+    pub path: String,
+    pub access: Access,
+    /// When the grant was made (Unix timestamp ms)
+    pub granted_at: i64,
+    /// When the grant lapses (Unix timestamp ms), if ever
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<i64>,
+}
+
+impl Grant {
+    pub fn is_expired(&self, now: i64) -> bool {
+        self.expires_at.is_some_and(|t| t <= now)
+    }
+
+    /// Whether this grant allows `access` to `path` (resolved, under `root`).
+    fn allows(&self, root: &Path, path: &Path, access: Access, now: i64) -> bool {
+        !self.is_expired(now) && self.access >= access && path.starts_with(root.join(&self.path))
+    }
+}
+
+/// A directory a server is confined to in place of the sandbox root.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Jail {
+    /// Absolute path; a leading `~/` is the user's home directory
+    pub root: String,
+    #[serde(default = "default_jail_access")]
+    pub access: Access,
+}
+
+fn default_jail_access() -> Access {
+    Access::Write
+}
+
+/// Directories in the home directory a jail may not be, be in, or contain:
+/// Harbor's own state, and keys and credentials.
+const PROTECTED_DIRS: [&str; 4] = [".harbor", ".ssh", ".gnupg", ".aws"];
+
+impl Jail {
+    /// The jail's root, canonicalized. It must exist, be inside the home
+    /// directory, and stay clear of `PROTECTED_DIRS`.
+    pub fn resolve(&self) -> Result<PathBuf, String> {
+        let home = dirs::home_dir()
+            .and_then(|home| home.canonicalize().ok())
+            .ok_or("Failed to find the home directory")?;
+        let root = match self.root.strip_prefix("~/") {
+            Some(rest) => home.join(rest),
+            None => PathBuf::from(&self.root),
+        };
+        if !root.is_absolute() {
+            return Err(format!("Jail root '{}' is not an absolute path", self.root));
+        }
+        let root = root
+            .canonicalize()
+            .map_err(|e| format!("Failed to resolve jail root '{}': {}", self.root, e))?;
+        check_jail_root(&root, &home).map_err(|e| format!("Jail root '{}' {}", self.root, e))?;
+        Ok(root)
+    }
+}
+
+fn check_jail_root(root: &Path, home: &Path) -> Result<(), String> {
+    if root == home || !root.starts_with(home) {
+        return Err("must be a directory inside the home directory".to_string());
+    }
+    for dir in PROTECTED_DIRS {
+        let protected = home.join(dir);
+        if root.starts_with(&protected) || protected.starts_with(root) {
+            return Err(format!("may not be in or contain ~/{}", dir));
+        }
+    }
+    Ok(())
+}
+
+/// Grants and jails keyed by server ID.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Grants {
+    #[serde(default)]
+    pub servers: HashMap<String, Vec<Grant>>,
+    #[serde(default)]
+    pub jails: HashMap<String, Jail>,
+    /// Storage quota in bytes, by server ID
+    #[serde(default)]
+    pub quotas: HashMap<String, u64>,
+    #[serde(default)]
+    pub symlinks: SymlinkPolicy,
+}
+
+impl Grants {
+    /// Whether a server may access a resolved path.
+    pub fn allows(&self, server_id: &str, root: &Path, path: &Path, access: Access) -> bool {
+        let now = chrono::Utc::now().timestamp_millis();
+        self.servers
+            .get(server_id)
+            .is_some_and(|grants| grants.iter().any(|g| g.allows(root, path, access, now)))
+    }
+
+    /// Directories a server can write to, under the (canonical) sandbox
+    /// root unless the server is jailed.
+    pub fn writable_roots(&self, server_id: &str, sandbox_root: &Path) -> Result<Vec<PathBuf>, String> {
+        if let Some(jail) = self.jails.get(server_id) {
+            return Ok(match jail.access {
+                Access::Write => vec![jail.resolve()?],
+                Access::Read => Vec::new(),
+            });
+        }
+        let now = chrono::Utc::now().timestamp_millis();
+        Ok(self
+            .servers
+            .get(server_id)
+            .into_iter()
+            .flatten()
+            .filter(|g| g.access == Access::Write && !g.is_expired(now))
+            .map(|g| sandbox_root.join(&g.path))
+            .collect())
+    }
+
+    /// Add a grant, replacing any existing grant for the same directory.
+    pub fn grant(&mut self, server_id: &str, grant: Grant) {
+        let grants = self.servers.entry(server_id.to_string()).or_default();
+        grants.retain(|g| g.path != grant.path);
+        grants.push(grant);
+    }
+
+    /// Remove a server's grant for one directory, or all of its grants.
+    /// Returns how many were removed.
+    pub fn revoke(&mut self, server_id: &str, path: Option<&str>) -> usize {
+        let Some(grants) = self.servers.get_mut(server_id) else {
+            return 0;
+        };
+        let before = grants.len();
+        match path {
+            Some(path) => grants.retain(|g| g.path != path),
+            None => grants.clear(),
+        }
+        let removed = before - grants.len();
+        if grants.is_empty() {
+            self.servers.remove(server_id);
+        }
+        removed
+    }
+
+    /// Drop expired grants. Returns how many were removed.
+    pub fn prune_expired(&mut self) -> usize {
+        let now = chrono::Utc::now().timestamp_millis();
+        let mut removed = 0;
+        for grants in self.servers.values_mut() {
+            let before = grants.len();
+            grants.retain(|g| !g.is_expired(now));
+            removed += before - grants.len();
+        }
+        self.servers.retain(|_, grants| !grants.is_empty());
+        removed
+    }
+}
+
+fn permissions_file_path() -> PathBuf {
+    let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
+    home.join(".harbor").join(PERMISSIONS_FILE_NAME)
+}
+
+/// Load grants from disk.
+pub fn load() -> Grants {
+    let path = permissions_file_path();
+    match std::fs::read_to_string(&path) {
+        Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+            tracing::warn!("Failed to parse {:?}: {}", path, e);
+            Grants::default()
+        }),
+        Err(_) => Grants::default(),
+    }
+}
+
+/// Persist grants.
+pub fn save(grants: &Grants) -> Result<(), String> {
+    let path = permissions_file_path();
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create directory: {}", e))?;
+    }
+    let json = serde_json::to_string_pretty(grants)
+        .map_err(|e| format!("Failed to serialize fs permissions: {}", e))?;
+    std::fs::write(&path, json)
+        .map_err(|e| format!("Failed to write fs permissions file: {}", e))?;
+    crate::oauth::permissions::restrict_to_owner(&path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grant(path: &str, access: Access) -> Grant {
+        Grant {
+            path: path.to_string(),
+            access,
+            granted_at: 0,
+            expires_at: None,
+        }
+    }
+
+    #[test]
+    fn test_grant_covers_subdirectories() {
+        let root = Path::new("/sandbox");
+        let mut grants = Grants::default();
+        grants.grant("notes", grant("notes", Access::Read));
+
+        assert!(grants.allows("notes", root, Path::new("/sandbox/notes/a/b.md"), Access::Read));
+        assert!(!grants.allows("notes", root, Path::new("/sandbox/notes/a/b.md"), Access::Write));
+        assert!(!grants.allows("notes", root, Path::new("/sandbox/notes-old/b.md"), Access::Read));
+        assert!(!grants.allows("other", root, Path::new("/sandbox/notes/b.md"), Access::Read));
+    }
+
+    #[test]
+    fn test_expired_grants_are_ignored_and_pruned() {
+        let root = Path::new("/sandbox");
+        let mut grants = Grants::default();
+        grants.grant("notes", Grant {
+            expires_at: Some(1),
+            ..grant("", Access::Write)
+        });
+
+        assert!(!grants.allows("notes", root, Path::new("/sandbox/a.md"), Access::Read));
+        assert_eq!(grants.prune_expired(), 1);
+        assert!(grants.servers.is_empty());
+    }
+
+    #[test]
+    fn test_jail_roots_stay_inside_home_and_clear_of_harbor() {
+        let home = Path::new("/home/me");
+        assert!(check_jail_root(Path::new("/home/me/projects/site"), home).is_ok());
+        assert!(check_jail_root(Path::new("/home/me"), home).is_err());
+        assert!(check_jail_root(Path::new("/etc"), home).is_err());
+        assert!(check_jail_root(Path::new("/home/me/.harbor"), home).is_err());
+        assert!(check_jail_root(Path::new("/home/me/.harbor/files"), home).is_err());
+        assert!(check_jail_root(Path::new("/home/me/.ssh"), home).is_err());
+        assert!(check_jail_root(Path::new("/home/me/.harbor-notes"), home).is_ok());
+    }
+}
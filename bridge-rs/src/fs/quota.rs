@@ -0,0 +1,146 @@
+//! Per-server storage quotas.
+//!
+//! A server's usage is the size of everything under the directories it can
+//! write to: its jail, or its write grants. Measuring means walking those
+//! directories, so the result is cached for a while and adjusted by each
+//! write the bridge makes in between.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How long a measured usage is trusted before walking the directories again.
+const MEASURE_TTL: Duration = Duration::from_secs(60);
+
+struct Measured {
+    bytes: u64,
+    at: Instant,
+}
+
+/// Cached usage per server.
+#[derive(Default)]
+pub struct Usage {
+    servers: Mutex<HashMap<String, Measured>>,
+}
+
+impl Usage {
+    /// A server's cached usage, if still fresh.
+    pub fn cached(&self, server_id: &str) -> Option<u64> {
+        let servers = self.servers.lock().unwrap();
+        servers
+            .get(server_id)
+            .filter(|m| m.at.elapsed() < MEASURE_TTL)
+            .map(|m| m.bytes)
+    }
+
+    pub fn store(&self, server_id: &str, bytes: u64) {
+        self.servers.lock().unwrap().insert(
+            server_id.to_string(),
+            Measured {
+                bytes,
+                at: Instant::now(),
+            },
+        );
+    }
+
+    /// Take `delta` bytes of room for a write, unless that would take the
+    /// server over `limit`; returns the usage it would reach if so. `measured`
+    /// is used if the cached usage has since been dropped.
+    pub fn reserve(&self, server_id: &str, measured: u64, delta: i64, limit: u64) -> Result<(), u64> {
+        let mut servers = self.servers.lock().unwrap();
+        let usage = servers.entry(server_id.to_string()).or_insert_with(|| Measured {
+            bytes: measured,
+            at: Instant::now(),
+        });
+        let after = usage.bytes.saturating_add_signed(delta);
+        if delta > 0 && after > limit {
+            return Err(after);
+        }
+        usage.bytes = after;
+        Ok(())
+    }
+
+    /// Account for a write that changed a server's usage by `delta` bytes.
+    pub fn adjust(&self, server_id: &str, delta: i64) {
+        if let Some(measured) = self.servers.lock().unwrap().get_mut(server_id) {
+            measured.bytes = measured.bytes.saturating_add_signed(delta);
+        }
+    }
+
+    /// Forget a server's usage, e.g. after its grants change.
+    pub fn forget(&self, server_id: &str) {
+        self.servers.lock().unwrap().remove(server_id);
+    }
+}
+
+/// Drop roots nested inside other roots, so nothing is counted twice.
+pub fn outermost(mut roots: Vec<PathBuf>) -> Vec<PathBuf> {
+    roots.sort();
+    let mut kept: Vec<PathBuf> = Vec::new();
+    for root in roots {
+        if !kept.iter().any(|k| root.starts_with(k)) {
+            kept.push(root);
+        }
+    }
+    kept
+}
+
+/// Total size of the files under `roots`. Symlinks are not followed.
+pub fn measure(roots: &[PathBuf]) -> u64 {
+    let mut total = 0;
+    let mut stack: Vec<PathBuf> = roots.to_vec();
+    while let Some(path) = stack.pop() {
+        let Ok(metadata) = std::fs::symlink_metadata(&path) else {
+            continue;
+        };
+        if metadata.is_file() {
+            total += metadata.len();
+        } else if metadata.is_dir() {
+            if let Ok(entries) = std::fs::read_dir(&path) {
+                stack.extend(entries.filter_map(Result::ok).map(|e| e.path()));
+            }
+        }
+    }
+    total
+}
+
+/// Whether `path` counts toward usage measured over `roots`.
+pub fn counted(roots: &[PathBuf], path: &Path) -> bool {
+    roots.iter().any(|root| path.starts_with(root))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_measure_nested_roots() {
+        let root = std::env::temp_dir().join(format!("harbor-fs-quota-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("a/b")).unwrap();
+        std::fs::write(root.join("a/one.txt"), [0u8; 10]).unwrap();
+        std::fs::write(root.join("a/b/two.txt"), [0u8; 5]).unwrap();
+
+        let roots = outermost(vec![root.join("a/b"), root.join("a")]);
+        assert_eq!(roots, vec![root.join("a")]);
+        assert_eq!(measure(&roots), 15);
+        assert!(counted(&roots, &root.join("a/b/new.txt")));
+        assert!(!counted(&roots, &root.join("elsewhere.txt")));
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_reserve() {
+        let usage = Usage::default();
+        usage.store("notes", 90);
+        assert!(usage.reserve("notes", 0, 10, 100).is_ok());
+        // The first write took the room, so the next doesn't fit
+        assert_eq!(usage.reserve("notes", 0, 1, 100), Err(101));
+        // Shrinking is always allowed, and a failed write gives its room back
+        assert!(usage.reserve("notes", 0, -20, 100).is_ok());
+        usage.adjust("notes", -10);
+        assert_eq!(usage.cached("notes"), Some(70));
+        assert!(usage.reserve("drafts", 50, 60, 100).is_err());
+    }
+}
@@ -0,0 +1,294 @@
+//! Glob and content search under a sandbox directory.
+//!
+//! Symlinks are followed per the `SymlinkPolicy`: never, only when their
+//! target is inside the root, or always. Each directory is visited once, so
+//! a link cycle doesn't loop. Files that look binary (a NUL byte near the
+//! start) or are too large are skipped for content matching.
+
+use std::collections::HashSet;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use globset::{Glob, GlobMatcher};
+use regex::Regex;
+use serde::Serialize;
+
+use super::permissions::SymlinkPolicy;
+
+/// Default and largest number of results returned.
+const DEFAULT_MAX_RESULTS: usize = 200;
+const MAX_RESULTS: usize = 1000;
+
+/// Most files looked at in one search.
+const MAX_SCANNED_FILES: usize = 50_000;
+
+/// Largest file searched for content.
+const MAX_CONTENT_BYTES: u64 = 1024 * 1024;
+
+/// Bytes checked for a NUL when deciding whether a file is binary.
+const BINARY_SNIFF_BYTES: usize = 8192;
+
+/// Longest line excerpt returned per match.
+const MAX_LINE_CHARS: usize = 200;
+
+/// What to search for.
+pub struct Query {
+    /// Matched against paths relative to the search directory
+    pub glob: Option<GlobMatcher>,
+    /// Matched against each line of text files
+    pub pattern: Option<Regex>,
+    pub max_results: usize,
+}
+
+impl Query {
+    /// Build a query from `glob`, `pattern`, `case_sensitive` and
+    /// `max_results` parameters.
+    pub fn from_params(params: &serde_json::Value) -> Result<Self, String> {
+        let glob = match params.get("glob").and_then(|v| v.as_str()) {
+            Some(glob) => Some(
+                Glob::new(glob)
+                    .map_err(|e| format!("Invalid glob: {}", e))?
+                    .compile_matcher(),
+            ),
+            None => None,
+        };
+        let case_sensitive = params.get("case_sensitive").and_then(|v| v.as_bool()).unwrap_or(true);
+        let pattern = match params.get("pattern").and_then(|v| v.as_str()) {
+            Some(pattern) => Some(
+                regex::RegexBuilder::new(pattern)
+                    .case_insensitive(!case_sensitive)
+                    .build()
+                    .map_err(|e| format!("Invalid pattern: {}", e))?,
+            ),
+            None => None,
+        };
+        if glob.is_none() && pattern.is_none() {
+            return Err("Provide 'glob', 'pattern', or both".to_string());
+        }
+        let max_results = params
+            .get("max_results")
+            .and_then(|v| v.as_u64())
+            .map_or(DEFAULT_MAX_RESULTS, |n| (n as usize).clamp(1, MAX_RESULTS));
+
+        Ok(Self {
+            glob,
+            pattern,
+            max_results,
+        })
+    }
+}
+
+/// A matching line.
+#[derive(Debug, Serialize)]
+pub struct LineMatch {
+    /// 1-based line number
+    pub line: usize,
+    pub text: String,
+}
+
+/// A matching file.
+#[derive(Debug, Serialize)]
+pub struct FileResult {
+    /// Path relative to the sandbox root
+    pub path: String,
+    pub size: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub matches: Option<Vec<LineMatch>>,
+}
+
+/// Search results. `truncated` is set when a limit stopped the search early.
+#[derive(Debug, Default, Serialize)]
+pub struct SearchResults {
+    pub results: Vec<FileResult>,
+    pub files_scanned: usize,
+    pub binary_skipped: usize,
+    pub truncated: bool,
+}
+
+/// The metadata of a directory entry, following a symlink if the policy
+/// allows it. `None` means the entry is skipped.
+fn entry_metadata(root: &Path, path: &Path, symlinks: SymlinkPolicy) -> Option<std::fs::Metadata> {
+    let metadata = std::fs::symlink_metadata(path).ok()?;
+    if !metadata.is_symlink() {
+        return Some(metadata);
+    }
+    match symlinks {
+        SymlinkPolicy::Deny => None,
+        SymlinkPolicy::WithinSandbox if !path.canonicalize().ok()?.starts_with(root) => None,
+        _ => std::fs::metadata(path).ok(),
+    }
+}
+
+/// Search files under `base` (resolved, under `root`).
+pub fn search(root: &Path, base: &Path, query: &Query, symlinks: SymlinkPolicy) -> SearchResults {
+    let mut out = SearchResults::default();
+    // Results count files for glob-only searches and lines otherwise
+    let mut found = 0;
+    let mut stack: Vec<PathBuf> = vec![base.to_path_buf()];
+    let mut visited = HashSet::new();
+
+    while let Some(dir) = stack.pop() {
+        if !visited.insert(dir.canonicalize().unwrap_or_else(|_| dir.clone())) {
+            continue;
+        }
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        let mut entries: Vec<_> = entries.filter_map(Result::ok).collect();
+        entries.sort_by_key(|e| e.file_name());
+        let mut subdirs = Vec::new();
+        for entry in entries {
+            let path = entry.path();
+            let Some(metadata) = entry_metadata(root, &path, symlinks) else {
+                continue;
+            };
+            if metadata.is_dir() {
+                subdirs.push(path);
+                continue;
+            }
+            if !metadata.is_file() {
+                continue;
+            }
+
+            if out.files_scanned == MAX_SCANNED_FILES {
+                out.truncated = true;
+                return out;
+            }
+            out.files_scanned += 1;
+
+            let relative = path.strip_prefix(base).unwrap_or(&path);
+            if query.glob.as_ref().is_some_and(|g| !g.is_match(relative)) {
+                continue;
+            }
+            let size = metadata.len();
+
+            let matches = match &query.pattern {
+                Some(pattern) => {
+                    if size > MAX_CONTENT_BYTES {
+                        continue;
+                    }
+                    let Some(text) = read_text(&path) else {
+                        out.binary_skipped += 1;
+                        continue;
+                    };
+                    let remaining = query.max_results - found;
+                    let mut lines = text
+                        .lines()
+                        .enumerate()
+                        .filter(|(_, line)| pattern.is_match(line))
+                        .map(|(i, line)| LineMatch {
+                            line: i + 1,
+                            text: excerpt(line),
+                        })
+                        .take(remaining + 1)
+                        .collect::<Vec<_>>();
+                    if lines.is_empty() {
+                        continue;
+                    }
+                    if lines.len() > remaining {
+                        lines.truncate(remaining);
+                        out.truncated = true;
+                    }
+                    found += lines.len();
+                    Some(lines)
+                }
+                None => {
+                    found += 1;
+                    None
+                }
+            };
+
+            out.results.push(FileResult {
+                path: super::display_path(root, &path),
+                size,
+                matches,
+            });
+            if found >= query.max_results {
+                out.truncated = true;
+                return out;
+            }
+        }
+        // Pushed in reverse so subdirectories are visited in name order
+        stack.extend(subdirs.into_iter().rev());
+    }
+    out
+}
+
+/// A file's contents, or `None` if it looks binary or isn't UTF-8.
+fn read_text(path: &Path) -> Option<String> {
+    let mut bytes = Vec::new();
+    std::fs::File::open(path).ok()?.read_to_end(&mut bytes).ok()?;
+    if bytes[..bytes.len().min(BINARY_SNIFF_BYTES)].contains(&0) {
+        return None;
+    }
+    String::from_utf8(bytes).ok()
+}
+
+fn excerpt(line: &str) -> String {
+    match line.char_indices().nth(MAX_LINE_CHARS) {
+        Some((end, _)) => format!("{}…", &line[..end]),
+        None => line.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_glob_and_content() {
+        let root = std::env::temp_dir().join(format!("harbor-fs-search-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("src/nested")).unwrap();
+        std::fs::write(root.join("src/main.rs"), "fn main() {\n    todo!()\n}\n").unwrap();
+        std::fs::write(root.join("src/nested/lib.rs"), "// TODO: tests\n").unwrap();
+        std::fs::write(root.join("notes.md"), "todo list\n").unwrap();
+        std::fs::write(root.join("src/blob.rs"), b"todo\0binary").unwrap();
+
+        let query = Query::from_params(&serde_json::json!({ "glob": "**/*.rs" })).unwrap();
+        let found = search(&root, &root, &query, SymlinkPolicy::WithinSandbox);
+        let paths: Vec<&str> = found.results.iter().map(|r| r.path.as_str()).collect();
+        assert_eq!(paths, ["src/blob.rs", "src/main.rs", "src/nested/lib.rs"]);
+
+        let query = Query::from_params(&serde_json::json!({
+            "glob": "**/*.rs",
+            "pattern": "todo",
+            "case_sensitive": false,
+        }))
+        .unwrap();
+        let found = search(&root, &root, &query, SymlinkPolicy::WithinSandbox);
+        let paths: Vec<&str> = found.results.iter().map(|r| r.path.as_str()).collect();
+        assert_eq!(paths, ["src/main.rs", "src/nested/lib.rs"]);
+        assert_eq!(found.results[0].matches.as_ref().unwrap()[0].line, 2);
+        assert_eq!(found.binary_skipped, 1);
+
+        let query = Query::from_params(&serde_json::json!({ "pattern": "todo", "max_results": 1 })).unwrap();
+        let found = search(&root, &root, &query, SymlinkPolicy::WithinSandbox);
+        assert_eq!(found.results.len(), 1);
+        assert!(found.truncated);
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_search_symlink_policy() {
+        let root = std::env::temp_dir().join(format!("harbor-fs-search-links-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("docs")).unwrap();
+        std::fs::write(root.join("docs/a.md"), "a").unwrap();
+        std::os::unix::fs::symlink(root.join("docs"), root.join("alias")).unwrap();
+        std::os::unix::fs::symlink(&root, root.join("docs/loop")).unwrap();
+        let root = root.canonicalize().unwrap();
+
+        let query = Query::from_params(&serde_json::json!({ "glob": "**/*.md" })).unwrap();
+        let paths = |symlinks| {
+            let found = search(&root, &root, &query, symlinks);
+            found.results.into_iter().map(|r| r.path).collect::<Vec<_>>()
+        };
+        assert_eq!(paths(SymlinkPolicy::Deny), ["docs/a.md"]);
+        // `alias` and `docs` are the same directory, so only one is walked
+        assert_eq!(paths(SymlinkPolicy::WithinSandbox), ["alias/a.md"]);
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,255 @@
+//! File watching for the sandbox.
+//!
+//! `fs.watch` subscribes to a path and reports changes under it as
+//! `fs/changed` events. Changes are collected for a short debounce window
+//! and reported together, one entry per path, so a burst of writes to a
+//! file becomes a single `modified` change.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use notify::{EventKind, RecursiveMode, Watcher};
+use serde::Serialize;
+use tokio::sync::mpsc;
+
+use crate::events;
+
+/// Debounce window used when a subscription doesn't ask for one.
+const DEFAULT_DEBOUNCE_MS: u64 = 200;
+
+/// Longest debounce window a subscription may ask for.
+const MAX_DEBOUNCE_MS: u64 = 10_000;
+
+/// Most subscriptions open at once.
+const MAX_WATCHES: usize = 256;
+
+/// What happened to a path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    Created,
+    Modified,
+    Removed,
+}
+
+impl ChangeKind {
+    fn from_event(kind: &EventKind) -> Option<Self> {
+        match kind {
+            EventKind::Create(_) => Some(Self::Created),
+            EventKind::Modify(_) => Some(Self::Modified),
+            EventKind::Remove(_) => Some(Self::Removed),
+            _ => None,
+        }
+    }
+
+    /// Combine a change seen earlier in the window with a later one.
+    fn merge(earlier: Self, later: Self) -> Self {
+        match (earlier, later) {
+            // A file created and then written is still new
+            (Self::Created, Self::Modified) => Self::Created,
+            // Removed and recreated: the old contents are gone
+            (Self::Removed, Self::Created) => Self::Modified,
+            (_, later) => later,
+        }
+    }
+}
+
+/// A watch subscription, as reported to callers.
+#[derive(Debug, Clone, Serialize)]
+pub struct WatchInfo {
+    pub subscription_id: String,
+    /// Server the subscription belongs to (`None` for the extension)
+    pub server_id: Option<String>,
+    /// Watched path, relative to the sandbox root
+    pub path: String,
+    pub recursive: bool,
+    pub debounce_ms: u64,
+}
+
+struct Subscription {
+    info: WatchInfo,
+    root: PathBuf,
+    /// Resolved watched path
+    watched: PathBuf,
+    /// Dropping the watcher stops the subscription's task
+    _watcher: notify::RecommendedWatcher,
+}
+
+/// Start an OS watcher for a subscription, with a task forwarding its
+/// changes.
+fn start_watcher(info: &WatchInfo, root: &Path, path: &Path) -> Result<notify::RecommendedWatcher, String> {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let mut watcher = notify::recommended_watcher(move |result: notify::Result<notify::Event>| match result {
+        Ok(event) => {
+            let _ = tx.send(event);
+        }
+        Err(e) => tracing::warn!("File watch error: {}", e),
+    })
+    .map_err(|e| format!("Failed to create watcher: {}", e))?;
+    let mode = if info.recursive {
+        RecursiveMode::Recursive
+    } else {
+        RecursiveMode::NonRecursive
+    };
+    watcher
+        .watch(path, mode)
+        .map_err(|e| format!("Failed to watch '{}': {}", info.path, e))?;
+
+    tokio::spawn(forward_changes(info.clone(), root.to_path_buf(), rx));
+    Ok(watcher)
+}
+
+/// Open watch subscriptions keyed by subscription ID.
+#[derive(Default)]
+pub struct Watches {
+    subscriptions: std::sync::Mutex<HashMap<String, Subscription>>,
+}
+
+impl Watches {
+    /// Start watching `path` (resolved, under `root`).
+    pub fn add(
+        &self,
+        root: PathBuf,
+        path: &Path,
+        server_id: Option<String>,
+        recursive: bool,
+        debounce_ms: Option<u64>,
+    ) -> Result<WatchInfo, String> {
+        if self.subscriptions.lock().unwrap().len() >= MAX_WATCHES {
+            return Err(format!("Too many watch subscriptions (limit {})", MAX_WATCHES));
+        }
+        if !path.exists() {
+            return Err(format!("'{}' does not exist", super::display_path(&root, path)));
+        }
+
+        let info = WatchInfo {
+            subscription_id: format!("watch-{:016x}", rand::random::<u64>()),
+            server_id,
+            path: super::display_path(&root, path),
+            recursive,
+            debounce_ms: debounce_ms.unwrap_or(DEFAULT_DEBOUNCE_MS).min(MAX_DEBOUNCE_MS),
+        };
+
+        let watcher = start_watcher(&info, &root, path)?;
+        self.subscriptions.lock().unwrap().insert(
+            info.subscription_id.clone(),
+            Subscription {
+                info: info.clone(),
+                root,
+                watched: path.to_path_buf(),
+                _watcher: watcher,
+            },
+        );
+        Ok(info)
+    }
+
+    /// Replace every subscription's OS watcher with a fresh one, e.g. after
+    /// the machine resumes from sleep. Subscriptions whose path is gone are
+    /// dropped. Returns how many were rearmed.
+    pub fn rearm(&self) -> usize {
+        let mut subscriptions = self.subscriptions.lock().unwrap();
+        subscriptions.retain(|_, s| match start_watcher(&s.info, &s.root, &s.watched) {
+            Ok(watcher) => {
+                s._watcher = watcher;
+                true
+            }
+            Err(e) => {
+                tracing::warn!("Dropping watch {}: {}", s.info.subscription_id, e);
+                false
+            }
+        });
+        subscriptions.len()
+    }
+
+    /// Stop a subscription. Returns its info if it existed.
+    pub fn remove(&self, subscription_id: &str) -> Option<WatchInfo> {
+        self.subscriptions
+            .lock()
+            .unwrap()
+            .remove(subscription_id)
+            .map(|s| s.info)
+    }
+
+    /// Stop every subscription for which `keep` returns false. Returns how
+    /// many were stopped.
+    pub fn retain(&self, keep: impl Fn(&WatchInfo) -> bool) -> usize {
+        let mut subscriptions = self.subscriptions.lock().unwrap();
+        let before = subscriptions.len();
+        subscriptions.retain(|_, s| keep(&s.info));
+        before - subscriptions.len()
+    }
+
+    /// Open subscriptions, optionally for one server only.
+    pub fn list(&self, server_id: Option<&str>) -> Vec<WatchInfo> {
+        let mut watches: Vec<WatchInfo> = self
+            .subscriptions
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|s| server_id.is_none() || s.info.server_id.as_deref() == server_id)
+            .map(|s| s.info.clone())
+            .collect();
+        watches.sort_by(|a, b| a.path.cmp(&b.path));
+        watches
+    }
+}
+
+/// Collect raw watcher events into debounced `fs/changed` events until the
+/// subscription's watcher is dropped.
+async fn forward_changes(info: WatchInfo, root: PathBuf, mut rx: mpsc::UnboundedReceiver<notify::Event>) {
+    let debounce = Duration::from_millis(info.debounce_ms);
+
+    while let Some(first) = rx.recv().await {
+        let mut changes: BTreeMap<String, ChangeKind> = BTreeMap::new();
+        let mut add = |event: notify::Event| {
+            let Some(kind) = ChangeKind::from_event(&event.kind) else {
+                return;
+            };
+            for path in event.paths {
+                let shown = super::display_path(&root, &path);
+                let merged = match changes.get(&shown) {
+                    Some(earlier) => ChangeKind::merge(*earlier, kind),
+                    None => kind,
+                };
+                changes.insert(shown, merged);
+            }
+        };
+
+        add(first);
+        let deadline = tokio::time::Instant::now() + debounce;
+        while let Ok(Some(event)) = tokio::time::timeout_at(deadline, rx.recv()).await {
+            add(event);
+        }
+
+        if changes.is_empty() {
+            continue;
+        }
+        let changes: Vec<_> = changes
+            .into_iter()
+            .map(|(path, kind)| serde_json::json!({ "path": path, "kind": kind }))
+            .collect();
+        events::emit(
+            "fs/changed",
+            serde_json::json!({
+                "subscription_id": info.subscription_id,
+                "server_id": info.server_id,
+                "changes": changes,
+            }),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_changes() {
+        use ChangeKind::*;
+        assert_eq!(ChangeKind::merge(Created, Modified), Created);
+        assert_eq!(ChangeKind::merge(Removed, Created), Modified);
+        assert_eq!(ChangeKind::merge(Modified, Removed), Removed);
+        assert_eq!(ChangeKind::merge(Created, Removed), Removed);
+    }
+}
@@ -0,0 +1,173 @@
+//! Tool call history.
+//!
+//! Every tool call routed through the bridge is appended to a local JSONL log
+//! (`~/.harbor/history.jsonl`). Nothing leaves the machine; the log backs
+//! usage statistics and exports, including transcripts (`transcript`).
+
+pub mod transcript;
+
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+const HISTORY_FILE_NAME: &str = "history.jsonl";
+
+/// Held while the log is appended to or rewritten, so an entry recorded
+/// during a compaction isn't lost when the rewritten log replaces the old.
+static WRITING: Mutex<()> = Mutex::new(());
+
+/// Maximum stored size of arguments/results per entry (characters of JSON).
+const MAX_PAYLOAD_CHARS: usize = 4096;
+
+/// A single recorded tool call.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// When the call started (Unix timestamp ms)
+    pub timestamp: i64,
+    pub server_id: String,
+    pub tool: String,
+    pub duration_ms: u64,
+    pub ok: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub args: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<serde_json::Value>,
+}
+
+impl HistoryEntry {
+    /// Create an entry, truncating large payloads.
+    pub fn new(
+        server_id: &str,
+        tool: &str,
+        started_at: i64,
+        duration_ms: u64,
+        outcome: Result<&serde_json::Value, &str>,
+        args: &serde_json::Value,
+    ) -> Self {
+        let (ok, error, result) = match outcome {
+            Ok(value) => (true, None, Some(truncate(value))),
+            Err(e) => (false, Some(e.to_string()), None),
+        };
+        Self {
+            timestamp: started_at,
+            server_id: server_id.to_string(),
+            tool: tool.to_string(),
+            duration_ms,
+            ok,
+            error,
+            args: Some(truncate(args)),
+            result,
+        }
+    }
+}
+
+/// Replace payloads that are too large with a truncated string preview.
+fn truncate(value: &serde_json::Value) -> serde_json::Value {
+    let text = value.to_string();
+    if text.len() <= MAX_PAYLOAD_CHARS {
+        return value.clone();
+    }
+    let mut end = MAX_PAYLOAD_CHARS;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    serde_json::json!({ "truncated": true, "preview": &text[..end] })
+}
+
+/// Path to the history log.
+pub fn history_path() -> PathBuf {
+    let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
+    home.join(".harbor").join(HISTORY_FILE_NAME)
+}
+
+/// Append an entry to the history log (in the background).
+pub fn record(entry: HistoryEntry) {
+    tokio::task::spawn_blocking(move || {
+        if let Err(e) = append(&history_path(), &entry) {
+            tracing::warn!("Failed to record tool call history: {}", e);
+        }
+    });
+}
+
+fn append(path: &PathBuf, entry: &HistoryEntry) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
+    }
+    let line = serde_json::to_string(entry).map_err(|e| format!("Failed to serialize entry: {}", e))?;
+    let _writing = WRITING.lock().unwrap();
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| format!("Failed to open history file: {}", e))?;
+    writeln!(file, "{}", line).map_err(|e| format!("Failed to write history file: {}", e))
+}
+
+/// Load entries within `[since, until)` (Unix timestamps ms).
+/// Malformed lines are skipped.
+pub fn load(since: Option<i64>, until: Option<i64>) -> Vec<HistoryEntry> {
+    let file = match std::fs::File::open(history_path()) {
+        Ok(f) => f,
+        Err(_) => return Vec::new(),
+    };
+
+    BufReader::new(file)
+        .lines()
+        .map_while(Result::ok)
+        .filter_map(|line| serde_json::from_str::<HistoryEntry>(&line).ok())
+        .filter(|e| since.map(|s| e.timestamp >= s).unwrap_or(true))
+        .filter(|e| until.map(|u| e.timestamp < u).unwrap_or(true))
+        .collect()
+}
+
+/// Rewrite the log without entries older than `retain_days` or lines that
+/// no longer parse. Returns how many lines were dropped.
+pub fn compact(retain_days: i64) -> Result<usize, String> {
+    let _writing = WRITING.lock().unwrap();
+    let path = history_path();
+    let file = match std::fs::File::open(&path) {
+        Ok(f) => f,
+        Err(_) => return Ok(0),
+    };
+    let cutoff = (chrono::Utc::now() - chrono::Duration::days(retain_days)).timestamp_millis();
+
+    let mut kept = Vec::new();
+    let mut dropped = 0;
+    for line in BufReader::new(file).lines().map_while(Result::ok) {
+        match serde_json::from_str::<HistoryEntry>(&line) {
+            Ok(entry) if entry.timestamp >= cutoff => kept.push(line),
+            _ => dropped += 1,
+        }
+    }
+    if dropped == 0 {
+        return Ok(0);
+    }
+
+    let tmp = path.with_extension("jsonl.tmp");
+    let mut contents = kept.join("\n");
+    if !contents.is_empty() {
+        contents.push('\n');
+    }
+    std::fs::write(&tmp, contents)
+        .and_then(|_| std::fs::rename(&tmp, &path))
+        .map_err(|e| format!("Failed to rewrite history file: {}", e))?;
+    Ok(dropped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truncate_large_payload() {
+        let big = serde_json::json!({ "text": "x".repeat(MAX_PAYLOAD_CHARS * 2) });
+        let truncated = truncate(&big);
+        assert_eq!(truncated["truncated"], true);
+
+        let small = serde_json::json!({ "a": 1 });
+        assert_eq!(truncate(&small), small);
+    }
+}
@@ -0,0 +1,329 @@
+//! Session transcripts: a window of tool call history as a document.
+//!
+//! Calls are rendered in order with their arguments and results (or
+//! errors), as markdown or a standalone HTML page, so "what the agent did"
+//! for a task can be archived or shared. Secrets are masked on the way out:
+//! everything passes through `redact`, and values of any extra field names
+//! the caller lists are replaced entirely.
+
+use chrono::{DateTime, Utc};
+
+use super::HistoryEntry;
+use crate::redact::redact;
+use crate::rpc::RpcError;
+
+/// Most calls included in one transcript; the most recent are kept.
+const DEFAULT_LIMIT: usize = 500;
+const MAX_LIMIT: usize = 5000;
+
+const MASK: &str = "[REDACTED]";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Markdown,
+    Html,
+}
+
+impl Format {
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "markdown" | "md" => Some(Self::Markdown),
+            "html" => Some(Self::Html),
+            _ => None,
+        }
+    }
+}
+
+/// One call, ready to render.
+struct Step {
+    title: String,
+    time: String,
+    duration_ms: u64,
+    args: Option<String>,
+    outcome: Result<Option<String>, String>,
+}
+
+fn format_time(timestamp: i64) -> String {
+    DateTime::<Utc>::from_timestamp_millis(timestamp)
+        .map(|t| t.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+        .unwrap_or_else(|| timestamp.to_string())
+}
+
+/// Replace the values of `fields` (case-insensitive) anywhere in `value`.
+fn mask_fields(value: &mut serde_json::Value, fields: &[String]) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (key, child) in map.iter_mut() {
+                if fields.iter().any(|f| f.eq_ignore_ascii_case(key)) {
+                    *child = serde_json::Value::String(MASK.to_string());
+                } else {
+                    mask_fields(child, fields);
+                }
+            }
+        }
+        serde_json::Value::Array(items) => items.iter_mut().for_each(|item| mask_fields(item, fields)),
+        _ => {}
+    }
+}
+
+/// A payload as redacted text: strings as-is, anything else as pretty JSON.
+fn payload(value: &serde_json::Value, fields: &[String]) -> String {
+    let mut value = value.clone();
+    mask_fields(&mut value, fields);
+    let text = match &value {
+        serde_json::Value::String(s) => s.clone(),
+        other => serde_json::to_string_pretty(other).unwrap_or_default(),
+    };
+    redact(&text).into_owned()
+}
+
+fn steps(entries: &[HistoryEntry], fields: &[String]) -> Vec<Step> {
+    entries
+        .iter()
+        .map(|entry| Step {
+            title: format!("{}/{}", entry.server_id, entry.tool),
+            time: format_time(entry.timestamp),
+            duration_ms: entry.duration_ms,
+            args: entry
+                .args
+                .as_ref()
+                .filter(|args| !args.is_null() && args.as_object().map(|o| !o.is_empty()).unwrap_or(true))
+                .map(|args| payload(args, fields)),
+            outcome: if entry.ok {
+                Ok(entry.result.as_ref().map(|result| payload(result, fields)))
+            } else {
+                Err(redact(entry.error.as_deref().unwrap_or("Unknown error")).into_owned())
+            },
+        })
+        .collect()
+}
+
+fn summary(entries: &[HistoryEntry]) -> String {
+    let failed = entries.iter().filter(|e| !e.ok).count();
+    match (entries.first(), entries.last()) {
+        (Some(first), Some(last)) => format!(
+            "{} tool call{} from {} to {}, {} failed.",
+            entries.len(),
+            if entries.len() == 1 { "" } else { "s" },
+            format_time(first.timestamp),
+            format_time(last.timestamp),
+            failed
+        ),
+        _ => "No tool calls in this window.".to_string(),
+    }
+}
+
+/// A code fence longer than any run of backticks in `text`.
+fn fence(text: &str) -> String {
+    let mut longest = 0;
+    let mut run = 0;
+    for c in text.chars() {
+        run = if c == '`' { run + 1 } else { 0 };
+        longest = longest.max(run);
+    }
+    "`".repeat((longest + 1).max(3))
+}
+
+fn code_block(out: &mut String, text: &str) {
+    let lang = if text.starts_with('{') || text.starts_with('[') { "json" } else { "" };
+    let fence = fence(text);
+    out.push_str(&format!("{}{}\n{}\n{}\n\n", fence, lang, text, fence));
+}
+
+fn render_markdown(title: &str, entries: &[HistoryEntry], steps: &[Step]) -> String {
+    let mut out = format!("# {}\n\n{}\n\n", title, summary(entries));
+    for (i, step) in steps.iter().enumerate() {
+        out.push_str(&format!(
+            "## {}. `{}`\n\n{} · {} ms\n\n",
+            i + 1,
+            step.title,
+            step.time,
+            step.duration_ms
+        ));
+        if let Some(args) = &step.args {
+            out.push_str("**Arguments**\n\n");
+            code_block(&mut out, args);
+        }
+        match &step.outcome {
+            Ok(Some(result)) => {
+                out.push_str("**Result**\n\n");
+                code_block(&mut out, result);
+            }
+            Ok(None) => out.push_str("**Result:** none\n\n"),
+            Err(error) => {
+                out.push_str("**Error**\n\n");
+                code_block(&mut out, error);
+            }
+        }
+    }
+    out
+}
+
+fn escape_html(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+const STYLE: &str = "body{font-family:system-ui,sans-serif;max-width:60rem;margin:2rem auto;padding:0 1rem;color:#1f2328}\
+section{border-top:1px solid #d0d7de;padding:.5rem 0}\
+.meta{color:#656d76;font-size:.9em}\
+pre{background:#f6f8fa;padding:.75rem;overflow-x:auto;white-space:pre-wrap}\
+.error pre{background:#ffebe9}";
+
+fn render_html(title: &str, entries: &[HistoryEntry], steps: &[Step]) -> String {
+    let title = escape_html(title);
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>{}</style>\n</head>\n<body>\n<h1>{}</h1>\n<p>{}</p>\n",
+        title,
+        STYLE,
+        title,
+        escape_html(&summary(entries))
+    );
+    for (i, step) in steps.iter().enumerate() {
+        let class = if step.outcome.is_err() { " class=\"error\"" } else { "" };
+        out.push_str(&format!(
+            "<section{}>\n<h2>{}. <code>{}</code></h2>\n<p class=\"meta\">{} · {} ms</p>\n",
+            class,
+            i + 1,
+            escape_html(&step.title),
+            step.time,
+            step.duration_ms
+        ));
+        if let Some(args) = &step.args {
+            out.push_str(&format!("<h3>Arguments</h3>\n<pre><code>{}</code></pre>\n", escape_html(args)));
+        }
+        match &step.outcome {
+            Ok(Some(result)) => {
+                out.push_str(&format!("<h3>Result</h3>\n<pre><code>{}</code></pre>\n", escape_html(result)))
+            }
+            Ok(None) => out.push_str("<h3>Result</h3>\n<p>None</p>\n"),
+            Err(error) => {
+                out.push_str(&format!("<h3>Error</h3>\n<pre><code>{}</code></pre>\n", escape_html(error)))
+            }
+        }
+        out.push_str("</section>\n");
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+/// Render `entries` (oldest first) as a transcript.
+pub fn render(entries: &[HistoryEntry], format: Format, title: &str, redact_fields: &[String]) -> String {
+    let steps = steps(entries, redact_fields);
+    match format {
+        Format::Markdown => render_markdown(title, entries, &steps),
+        Format::Html => render_html(title, entries, &steps),
+    }
+}
+
+/// Export a window of history as a transcript.
+///
+/// Params: optional `since`/`until` (Unix ms), `server_id`, `format`
+/// ("markdown" | "html", default "markdown"), `title`, `limit` (most recent
+/// calls kept, default 500) and `redact_fields` (extra field names whose
+/// values are masked).
+pub async fn rpc_transcript(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    crate::rpc::require_extension("export transcripts")?;
+    let format_name = params.get("format").and_then(|v| v.as_str()).unwrap_or("markdown");
+    let format = Format::parse(format_name)
+        .ok_or_else(|| RpcError::invalid_params(format!("Unknown format '{}'", format_name)))?;
+    let since = params.get("since").and_then(|v| v.as_i64());
+    let until = params.get("until").and_then(|v| v.as_i64());
+    let server_id = params.get("server_id").and_then(|v| v.as_str()).map(String::from);
+    let title = params
+        .get("title")
+        .and_then(|v| v.as_str())
+        .unwrap_or("Harbor session transcript")
+        .to_string();
+    let limit = params
+        .get("limit")
+        .and_then(|v| v.as_u64())
+        .map(|n| (n as usize).min(MAX_LIMIT))
+        .unwrap_or(DEFAULT_LIMIT);
+    let redact_fields: Vec<String> = params
+        .get("redact_fields")
+        .and_then(|v| v.as_array())
+        .map(|fields| fields.iter().filter_map(|f| f.as_str().map(String::from)).collect())
+        .unwrap_or_default();
+
+    let (entries, content) = tokio::task::spawn_blocking(move || {
+        let mut entries: Vec<HistoryEntry> = super::load(since, until)
+            .into_iter()
+            .filter(|e| server_id.as_ref().map(|id| &e.server_id == id).unwrap_or(true))
+            .collect();
+        entries.sort_by_key(|e| e.timestamp);
+        let skip = entries.len().saturating_sub(limit);
+        entries.drain(..skip);
+        let content = render(&entries, format, &title, &redact_fields);
+        (entries.len(), content)
+    })
+    .await
+    .map_err(|e| RpcError::internal(e.to_string()))?;
+
+    Ok(serde_json::json!({
+        "format": format_name,
+        "entries": entries,
+        "content": content,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries() -> Vec<HistoryEntry> {
+        vec![
+            HistoryEntry {
+                timestamp: 0,
+                server_id: "github".to_string(),
+                tool: "search_issues".to_string(),
+                duration_ms: 120,
+                ok: true,
+                error: None,
+                args: Some(serde_json::json!({ "q": "is:open", "api_key": "k-123", "session": "s-456" })),
+                result: Some(serde_json::json!("Found ```3``` issues")),
+            },
+            HistoryEntry {
+                timestamp: 1_000,
+                server_id: "github".to_string(),
+                tool: "create_issue".to_string(),
+                duration_ms: 80,
+                ok: false,
+                error: Some("401 <Unauthorized>: Bearer ghp_abcdefghijklmnopqrstuvwx".to_string()),
+                args: Some(serde_json::json!({})),
+                result: None,
+            },
+        ]
+    }
+
+    #[test]
+    fn test_markdown_redacts_and_fences() {
+        let out = render(&entries(), Format::Markdown, "Triage", &["session".to_string()]);
+        assert!(out.starts_with("# Triage\n\n2 tool calls"));
+        assert!(out.contains("## 1. `github/search_issues`"));
+        assert!(!out.contains("k-123"));
+        assert!(!out.contains("s-456"));
+        assert!(!out.contains("ghp_abcdefghijklmnopqrstuvwx"));
+        assert!(out.contains("````\nFound ```3``` issues\n````"));
+        // Empty arguments are left out
+        assert_eq!(out.matches("**Arguments**").count(), 1);
+    }
+
+    #[test]
+    fn test_html_escapes() {
+        let out = render(&entries(), Format::Html, "<Triage>", &[]);
+        assert!(out.contains("<title>&lt;Triage&gt;</title>"));
+        assert!(out.contains("401 &lt;Unauthorized&gt;"));
+        assert!(out.contains("<section class=\"error\">"));
+    }
+}
@@ -4,14 +4,27 @@
 //! This server provides alternative communication channels:
 //! - HTTP POST /rpc for request/response
 //! - WebSocket /ws for persistent bidirectional communication (preferred)
+//!
+//! The same endpoints are the bridge's local control API for other
+//! programs; `harbor-client` wraps them for Rust.
+//!
+//! `/rpc` and `/ws` need the bridge token, kept in `~/.harbor/bridge-token`
+//! where only the current user can read it: as `Authorization: Bearer
+//! <token>`, or for `/ws`, which browsers can't give headers, a `token`
+//! query parameter. Requests from web pages are refused outright.
+//!
+//! Safari's extension has a token of its own, in `~/.harbor/extension-token`,
+//! which it gets from Harbor.app. Only requests carrying that token count as
+//! the extension's; the Origin header, which any local program can set,
+//! doesn't decide it.
 
 use axum::{
     extract::{
         ws::{Message, WebSocket, WebSocketUpgrade},
-        State,
+        Query, State,
     },
-    http::{header, Method, StatusCode},
-    response::IntoResponse,
+    http::{header, HeaderMap, HeaderValue, Method, StatusCode},
+    response::{IntoResponse, Response},
     routing::{get, post},
     Json, Router,
 };
@@ -19,13 +32,20 @@ use futures_util::{SinkExt, StreamExt};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use tokio::sync::{broadcast, RwLock};
-use tower_http::cors::{Any, CorsLayer};
+use tower_http::cors::{AllowOrigin, CorsLayer};
 
 use crate::rpc;
 
 /// Default port for the HTTP server
 pub const DEFAULT_PORT: u16 = 8766;
 
+/// The file in `~/.harbor` holding the token `/rpc` and `/ws` need
+pub const TOKEN_FILE_NAME: &str = "bridge-token";
+
+/// The file in `~/.harbor` holding the token that marks requests as the
+/// extension's
+pub const EXTENSION_TOKEN_FILE_NAME: &str = "extension-token";
+
 /// RPC request from extension
 #[derive(Debug, Deserialize)]
 pub struct HttpRpcRequest {
@@ -88,6 +108,12 @@ pub enum WsMessage {
         level: String,
         message: String,
     },
+    /// Bridge-initiated event (see `events`)
+    #[serde(rename = "event")]
+    Event {
+        event: String,
+        payload: serde_json::Value,
+    },
     /// Ping/pong for keepalive
     #[serde(rename = "ping")]
     Ping,
@@ -110,28 +136,59 @@ pub struct StreamEvent {
     pub error: Option<HttpRpcErrorResponse>,
 }
 
+/// The tokens requests must carry, one telling how they reached the bridge.
+#[derive(Debug, Clone)]
+struct Tokens {
+    /// Safari's extension's, from Harbor.app
+    extension: String,
+    /// Other local programs', from `~/.harbor/bridge-token`
+    client: String,
+}
+
 /// Server state shared across handlers
 struct ServerState {
     /// Broadcast channel for server-initiated messages (logs, status updates)
     broadcast_tx: broadcast::Sender<WsMessage>,
+    tokens: Tokens,
 }
 
 impl ServerState {
-    fn new() -> Self {
+    fn new(tokens: Tokens) -> Self {
         let (broadcast_tx, _) = broadcast::channel(100);
-        Self { broadcast_tx }
+        Self { broadcast_tx, tokens }
+    }
+}
+
+/// The token in `~/.harbor/<file_name>`, made on first run.
+fn load_or_create_token(file_name: &str) -> Result<String, String> {
+    let home = dirs::home_dir().unwrap_or_else(|| std::path::PathBuf::from("."));
+    let path = home.join(".harbor").join(file_name);
+    if let Ok(token) = std::fs::read_to_string(&path) {
+        let token = token.trim();
+        if !token.is_empty() {
+            return Ok(token.to_string());
+        }
     }
+    let token: String = (0..32).map(|_| format!("{:02x}", rand::random::<u8>())).collect();
+    crate::store::write_private(&path, token.as_bytes())?;
+    Ok(token)
 }
 
 /// Run the HTTP/WebSocket server for Safari extension communication
 pub async fn run_http_server(port: u16) -> Result<(), String> {
-    let state = Arc::new(RwLock::new(ServerState::new()));
+    let tokens = Tokens {
+        extension: load_or_create_token(EXTENSION_TOKEN_FILE_NAME)?,
+        client: load_or_create_token(TOKEN_FILE_NAME)?,
+    };
+    let state = Arc::new(RwLock::new(ServerState::new(tokens)));
 
-    // CORS layer to allow Safari extension to make requests
+    // CORS layer to allow the Safari extension, and no web page, to make requests
     let cors = CorsLayer::new()
-        .allow_origin(Any)
+        .allow_origin(AllowOrigin::predicate(|origin: &HeaderValue, _| {
+            origin.to_str().is_ok_and(|origin| !is_web_origin(origin))
+        }))
         .allow_methods([Method::GET, Method::POST, Method::OPTIONS])
-        .allow_headers([header::CONTENT_TYPE, header::ACCEPT]);
+        .allow_headers([header::CONTENT_TYPE, header::ACCEPT, header::AUTHORIZATION]);
 
     let app = Router::new()
         .route("/health", get(health_handler))
@@ -155,6 +212,38 @@ pub async fn run_http_server(port: u16) -> Result<(), String> {
         .map_err(|e| format!("HTTP server error: {}", e))
 }
 
+/// Whether a request's origin is a web page's (`null` for pages opened
+/// from files). Pages never get a token, but their requests are refused
+/// before it's looked at. Other origins say nothing about the sender.
+fn is_web_origin(origin: &str) -> bool {
+    origin == "null" || origin.starts_with("http://") || origin.starts_with("https://")
+}
+
+/// Compare tokens in time that doesn't depend on where they differ.
+fn token_matches(given: &str, token: &str) -> bool {
+    given.len() == token.len() && given.bytes().zip(token.bytes()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+/// Refuse web pages, check a request's token, and tell by the token how it
+/// reached the bridge. `query_token` is the WebSocket's `token` parameter.
+fn authorize(headers: &HeaderMap, query_token: Option<&str>, tokens: &Tokens) -> Result<rpc::Transport, StatusCode> {
+    let origin = headers.get(header::ORIGIN).map(|v| v.to_str().unwrap_or_default());
+    if origin.is_some_and(is_web_origin) {
+        tracing::warn!("Refused a bridge request from {:?}", origin);
+        return Err(StatusCode::FORBIDDEN);
+    }
+    let given = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .or(query_token);
+    match given.map(str::trim) {
+        Some(given) if token_matches(given, &tokens.extension) => Ok(rpc::Transport::Extension),
+        Some(given) if token_matches(given, &tokens.client) => Ok(rpc::Transport::Client),
+        _ => Err(StatusCode::UNAUTHORIZED),
+    }
+}
+
 /// Health check endpoint
 async fn health_handler() -> Json<serde_json::Value> {
     Json(serde_json::json!({
@@ -167,9 +256,14 @@ async fn health_handler() -> Json<serde_json::Value> {
 
 /// HTTP RPC endpoint - handles the same RPC calls as native messaging
 async fn rpc_handler(
-    State(_state): State<Arc<RwLock<ServerState>>>,
+    State(state): State<Arc<RwLock<ServerState>>>,
+    headers: HeaderMap,
     Json(request): Json<HttpRpcRequest>,
-) -> (StatusCode, Json<HttpRpcResponse>) {
+) -> Response {
+    let transport = match authorize(&headers, None, &state.read().await.tokens) {
+        Ok(transport) => transport,
+        Err(status) => return status.into_response(),
+    };
     tracing::info!(
         "HTTP RPC request: {} (id: {:?})",
         request.method,
@@ -181,10 +275,11 @@ async fn rpc_handler(
         id: request.id.clone(),
         method: request.method,
         params: request.params,
+        caller: None,
     };
 
     // Handle the request using the same RPC handler as native messaging
-    let result = rpc::handle(internal_request).await;
+    let result = rpc::handle(internal_request, transport).await;
 
     let response = HttpRpcResponse {
         id: request.id,
@@ -195,20 +290,31 @@ async fn rpc_handler(
         }),
     };
 
-    (StatusCode::OK, Json(response))
+    (StatusCode::OK, Json(response)).into_response()
+}
+
+#[derive(Debug, Deserialize)]
+struct WsQuery {
+    token: Option<String>,
 }
 
 /// WebSocket upgrade handler
 async fn ws_handler(
     ws: WebSocketUpgrade,
     State(state): State<Arc<RwLock<ServerState>>>,
-) -> impl IntoResponse {
+    headers: HeaderMap,
+    Query(query): Query<WsQuery>,
+) -> Response {
     tracing::info!("WebSocket connection request");
-    ws.on_upgrade(move |socket| handle_websocket(socket, state))
+    let transport = match authorize(&headers, query.token.as_deref(), &state.read().await.tokens) {
+        Ok(transport) => transport,
+        Err(status) => return status.into_response(),
+    };
+    ws.on_upgrade(move |socket| handle_websocket(socket, state, transport))
 }
 
 /// Handle a WebSocket connection
-async fn handle_websocket(socket: WebSocket, state: Arc<RwLock<ServerState>>) {
+async fn handle_websocket(socket: WebSocket, state: Arc<RwLock<ServerState>>, transport: rpc::Transport) {
     tracing::info!("WebSocket client connected");
 
     let (mut sender, mut receiver) = socket.split();
@@ -218,6 +324,7 @@ async fn handle_websocket(socket: WebSocket, state: Arc<RwLock<ServerState>>) {
         let state_read = state.read().await;
         state_read.broadcast_tx.subscribe()
     };
+    let mut event_rx = crate::events::subscribe();
 
     // Send initial status message
     let welcome = WsMessage::Status {
@@ -246,6 +353,24 @@ async fn handle_websocket(socket: WebSocket, state: Arc<RwLock<ServerState>>) {
                         Err(broadcast::error::RecvError::Lagged(_)) => continue,
                     }
                 }
+                // Forward bridge events
+                result = event_rx.recv() => {
+                    match result {
+                        Ok(event) => {
+                            let msg = WsMessage::Event {
+                                event: event.event,
+                                payload: event.payload,
+                            };
+                            if let Ok(json) = serde_json::to_string(&msg) {
+                                if sender.send(Message::Text(json)).await.is_err() {
+                                    break;
+                                }
+                            }
+                        }
+                        Err(broadcast::error::RecvError::Closed) => break,
+                        Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    }
+                }
             }
         }
         sender
@@ -259,7 +384,7 @@ async fn handle_websocket(socket: WebSocket, state: Arc<RwLock<ServerState>>) {
                 Ok(Message::Text(text)) => {
                     match serde_json::from_str::<WsMessage>(&text) {
                         Ok(msg) => {
-                            handle_ws_message(msg, &state_clone).await;
+                            handle_ws_message(msg, &state_clone, transport).await;
                         }
                         Err(e) => {
                             tracing::warn!("Invalid WebSocket message: {}", e);
@@ -298,7 +423,7 @@ async fn handle_websocket(socket: WebSocket, state: Arc<RwLock<ServerState>>) {
 }
 
 /// Handle an incoming WebSocket message
-async fn handle_ws_message(msg: WsMessage, state: &Arc<RwLock<ServerState>>) {
+async fn handle_ws_message(msg: WsMessage, state: &Arc<RwLock<ServerState>>, transport: rpc::Transport) {
     match msg {
         WsMessage::Rpc { id, method, params } => {
             tracing::info!("WebSocket RPC request: {} (id: {:?})", method, id);
@@ -308,16 +433,17 @@ async fn handle_ws_message(msg: WsMessage, state: &Arc<RwLock<ServerState>>) {
 
             if is_stream {
                 // For streaming, we'll send multiple messages
-                handle_streaming_rpc(id, method, params, state).await;
+                handle_streaming_rpc(id, method, params, state, transport).await;
             } else {
                 // Standard request/response
                 let internal_request = rpc::RpcRequest {
                     id: id.clone(),
                     method,
                     params,
+                    caller: None,
                 };
 
-                let result = rpc::handle(internal_request).await;
+                let result = rpc::handle(internal_request, transport).await;
 
                 let response = WsMessage::RpcResponse {
                     id,
@@ -349,6 +475,7 @@ async fn handle_streaming_rpc(
     method: String,
     params: serde_json::Value,
     state: &Arc<RwLock<ServerState>>,
+    transport: rpc::Transport,
 ) {
     let request_id = id.as_str().unwrap_or("unknown").to_string();
     
@@ -360,10 +487,11 @@ async fn handle_streaming_rpc(
         id: id.clone(),
         method: method.clone(),
         params: params.clone(),
+        caller: None,
     };
 
     // Get the result (for now, this is non-streaming, we'll enhance later)
-    let result = rpc::handle(internal_request).await;
+    let result = rpc::handle(internal_request, transport).await;
 
     // If it's an LLM response with content, simulate streaming by sending the content
     if let Some(ref result_value) = result.result {
@@ -422,3 +550,89 @@ async fn broadcast_message(_state: &Arc<RwLock<ServerState>>, _msg: WsMessage) {
     // This function can be used by other modules to push messages
     // For now, it's a placeholder for future use (e.g., console log forwarding)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(pairs: &[(header::HeaderName, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.insert(name.clone(), HeaderValue::from_str(value).unwrap());
+        }
+        headers
+    }
+
+    fn tokens() -> Tokens {
+        Tokens {
+            extension: "ext456".to_string(),
+            client: "abc123".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_authorize() {
+        let tokens = tokens();
+        let bearer = (header::AUTHORIZATION, "Bearer abc123");
+
+        assert_eq!(
+            authorize(&headers(&[bearer.clone()]), None, &tokens),
+            Ok(rpc::Transport::Client)
+        );
+        assert_eq!(
+            authorize(&headers(&[]), Some("abc123"), &tokens),
+            Ok(rpc::Transport::Client)
+        );
+        let safari = headers(&[
+            (header::AUTHORIZATION, "Bearer ext456"),
+            (header::ORIGIN, "safari-web-extension://ABC"),
+        ]);
+        assert_eq!(authorize(&safari, None, &tokens), Ok(rpc::Transport::Extension));
+        assert_eq!(
+            authorize(&headers(&[]), Some("ext456"), &tokens),
+            Ok(rpc::Transport::Extension)
+        );
+        // The client token stays the client's whatever origin it claims
+        let spoofed = headers(&[bearer.clone(), (header::ORIGIN, "safari-web-extension://ABC")]);
+        assert_eq!(authorize(&spoofed, None, &tokens), Ok(rpc::Transport::Client));
+
+        assert_eq!(authorize(&headers(&[]), None, &tokens), Err(StatusCode::UNAUTHORIZED));
+        assert_eq!(
+            authorize(&headers(&[]), Some("abc124"), &tokens),
+            Err(StatusCode::UNAUTHORIZED)
+        );
+        let page = headers(&[bearer.clone(), (header::ORIGIN, "https://example.com")]);
+        assert_eq!(authorize(&page, None, &tokens), Err(StatusCode::FORBIDDEN));
+        let file = headers(&[bearer, (header::ORIGIN, "null")]);
+        assert_eq!(authorize(&file, None, &tokens), Err(StatusCode::FORBIDDEN));
+    }
+
+    /// `secrets.set` with no params, as a request carrying `token` and an
+    /// extension's origin, and the error it gets.
+    async fn set_secret(token: &str) -> serde_json::Value {
+        let state = Arc::new(RwLock::new(ServerState::new(tokens())));
+        let bearer = format!("Bearer {}", token);
+        let headers = headers(&[
+            (header::AUTHORIZATION, bearer.as_str()),
+            (header::ORIGIN, "safari-web-extension://ABC"),
+        ]);
+        let request = HttpRpcRequest {
+            id: serde_json::json!(1),
+            method: "secrets.set".to_string(),
+            params: serde_json::json!({}),
+        };
+        let response = rpc_handler(State(state), headers, Json(request)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        body["error"]["message"].clone()
+    }
+
+    #[tokio::test]
+    async fn test_spoofed_origin_is_not_the_extension() {
+        let refused = "Only the Harbor extension can manage secrets";
+        assert_eq!(set_secret("abc123").await, refused);
+        // The extension's token gets past the check, to the missing params
+        assert_ne!(set_secret("ext456").await, refused);
+    }
+}
@@ -0,0 +1,358 @@
+//! Canary upgrades for JS servers.
+//!
+//! `js.upgrade_server` normally swaps a running server for its new version
+//! at once. With a `canary` config, the old version keeps serving every call
+//! and the new version runs alongside it: a fraction of read-only tool calls
+//! are mirrored to it in the background and the two results and latencies
+//! are compared. The caller reviews `js.canary_status` and then promotes the
+//! new version or rolls it back.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use super::{prepare, state, JsServer, ServerDefinition, ServerHandle, StartServerParams};
+use crate::budget;
+use crate::rpc::RpcError;
+
+/// Default fraction of read-only calls mirrored to the new version.
+const DEFAULT_FRACTION: f64 = 0.1;
+
+/// Longest a mirrored call may take before it counts as a canary error.
+const MIRROR_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Mismatches kept for review.
+const MAX_MISMATCHES: usize = 20;
+
+/// Mirrored calls without a difference before a canary is reported ready.
+const READY_SAMPLES: u64 = 20;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CanaryConfig {
+    /// Fraction of read-only calls to mirror, 0.0-1.0
+    #[serde(default = "default_fraction")]
+    pub fraction: f64,
+    /// Tools safe to call twice. Defaults to the tools the new version
+    /// annotates with `readOnlyHint`.
+    #[serde(default)]
+    pub read_only_tools: Option<Vec<String>>,
+}
+
+fn default_fraction() -> f64 {
+    DEFAULT_FRACTION
+}
+
+/// A call whose result differed between versions.
+#[derive(Debug, Clone, Serialize)]
+pub struct Mismatch {
+    pub tool: String,
+    pub at: i64,
+    pub primary: serde_json::Value,
+    pub canary: serde_json::Value,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct CanaryStats {
+    pub mirrored: u64,
+    pub matched: u64,
+    pub mismatched: u64,
+    /// Mirrored calls the new version failed or timed out on
+    pub canary_errors: u64,
+    primary_ms_total: u64,
+    canary_ms_total: u64,
+    pub recent_mismatches: VecDeque<Mismatch>,
+}
+
+/// A new server version running alongside the current one.
+pub struct Canary {
+    handle: ServerHandle,
+    definition: ServerDefinition,
+    env: HashMap<String, String>,
+    fraction: f64,
+    read_only: HashSet<String>,
+    started_at: i64,
+    stats: Arc<Mutex<CanaryStats>>,
+}
+
+/// The comparable part of a response: the result without `_meta`, or the
+/// error code.
+fn outcome(response: &serde_json::Value) -> serde_json::Value {
+    if let Some(error) = response.get("error") {
+        return serde_json::json!({ "error": error.get("code").cloned().unwrap_or_default() });
+    }
+    let mut result = response.get("result").cloned().unwrap_or_default();
+    if let Some(obj) = result.as_object_mut() {
+        obj.remove("_meta");
+    }
+    result
+}
+
+/// Tools the server annotates as read-only in its `tools/list` result.
+async fn annotated_read_only(handle: &ServerHandle) -> HashSet<String> {
+    let request = serde_json::json!({ "jsonrpc": "2.0", "id": "bridge-canary", "method": "tools/list" });
+    let (response, _) = handle.call_timed(request).await;
+    response
+        .ok()
+        .and_then(|r| r.pointer("/result/tools").cloned())
+        .and_then(|tools| tools.as_array().cloned())
+        .into_iter()
+        .flatten()
+        .filter(|tool| tool.pointer("/annotations/readOnlyHint").and_then(|h| h.as_bool()) == Some(true))
+        .filter_map(|tool| tool.get("name")?.as_str().map(String::from))
+        .collect()
+}
+
+/// Whether a call to `tool` on `server_id` should be mirrored.
+pub(super) async fn should_mirror(server_id: &str, tool: &str) -> bool {
+    match state().canaries.read().await.get(server_id) {
+        Some(canary) => canary.read_only.contains(tool) && rand::random::<f64>() < canary.fraction,
+        None => false,
+    }
+}
+
+/// Send a call the current version has answered to the canary and record
+/// how the two compare. Runs in the background.
+pub(super) fn mirror(
+    server_id: String,
+    tool: String,
+    request: serde_json::Value,
+    primary: Result<serde_json::Value, String>,
+    primary_time: Duration,
+) {
+    tokio::spawn(async move {
+        // Not holding the lock while the canary works, which would hold up
+        // promoting or rolling it back
+        let canary = state()
+            .canaries
+            .read()
+            .await
+            .get(&server_id)
+            .map(|canary| (canary.handle.requests_only(), canary.stats.clone()));
+        let Some((handle, stats)) = canary else {
+            return;
+        };
+        let started = Instant::now();
+        let response = tokio::time::timeout(MIRROR_TIMEOUT, handle.call_timed(request)).await;
+        let canary_time = started.elapsed();
+
+        let mut stats = stats.lock().unwrap();
+        stats.mirrored += 1;
+        stats.primary_ms_total += primary_time.as_millis() as u64;
+        stats.canary_ms_total += canary_time.as_millis() as u64;
+        let canary_response = match response {
+            Ok((Ok(response), _)) => response,
+            _ => {
+                stats.canary_errors += 1;
+                return;
+            }
+        };
+        // Nothing to compare against if the current version failed
+        let Ok(primary) = primary else {
+            return;
+        };
+
+        let (primary, canary_outcome) = (outcome(&primary), outcome(&canary_response));
+        if primary == canary_outcome {
+            stats.matched += 1;
+        } else {
+            stats.mismatched += 1;
+            if stats.recent_mismatches.len() == MAX_MISMATCHES {
+                stats.recent_mismatches.pop_front();
+            }
+            stats.recent_mismatches.push_back(Mismatch {
+                tool,
+                at: chrono::Utc::now().timestamp_millis(),
+                primary,
+                canary: canary_outcome,
+            });
+        }
+    });
+}
+
+/// Stop a server's canary, if it has one.
+pub(super) async fn discard(server_id: &str) {
+    if let Some(canary) = state().canaries.write().await.remove(server_id) {
+        canary.handle.stop().await;
+    }
+}
+
+fn status_json(server_id: &str, canary: &Canary) -> serde_json::Value {
+    let stats = canary.stats.lock().unwrap();
+    let average = |total: u64| (stats.mirrored > 0).then(|| total as f64 / stats.mirrored as f64);
+    let mut read_only: Vec<&String> = canary.read_only.iter().collect();
+    read_only.sort();
+    serde_json::json!({
+        "id": server_id,
+        "version": canary.definition.version,
+        "started_at": canary.started_at,
+        "fraction": canary.fraction,
+        "read_only_tools": read_only,
+        "mirrored": stats.mirrored,
+        "matched": stats.matched,
+        "mismatched": stats.mismatched,
+        "canary_errors": stats.canary_errors,
+        "avg_primary_ms": average(stats.primary_ms_total),
+        "avg_canary_ms": average(stats.canary_ms_total),
+        "recent_mismatches": stats.recent_mismatches,
+        "ready": stats.mirrored >= READY_SAMPLES && stats.mismatched == 0 && stats.canary_errors == 0,
+    })
+}
+
+// ============================================================================
+// RPC Handlers
+// ============================================================================
+
+#[derive(Debug, Deserialize)]
+struct UpgradeServerParams {
+    #[serde(flatten)]
+    server: StartServerParams,
+    /// Run the new version as a canary instead of switching at once
+    #[serde(default)]
+    canary: Option<CanaryConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CanaryParams {
+    id: String,
+}
+
+/// Upgrade a running JS server to a new version. Takes the same params as
+/// `js.start_server`, plus an optional `canary: { fraction, read_only_tools }`.
+pub async fn upgrade_server(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    crate::rpc::require_extension("upgrade JS servers")?;
+    let params: UpgradeServerParams = serde_json::from_value(params)
+        .map_err(|e| RpcError::invalid_params(format!("Invalid params: {}", e)))?;
+    let id = params.server.id.clone();
+
+    if !state().servers.read().await.contains_key(&id) {
+        return Err(RpcError::new(-32000, format!("Server '{}' not found", id)));
+    }
+    if state().canaries.read().await.contains_key(&id) {
+        return Err(RpcError::new(
+            -32000,
+            format!("Server '{}' already has a canary; promote or roll it back first", id),
+        ));
+    }
+
+    let (definition, config) = prepare(params.server);
+    let env = config.env.clone();
+    let handle = JsServer::start(config)
+        .await
+        .map_err(|e| RpcError::new(-32000, format!("Failed to start server: {}", e)))?;
+
+    let Some(canary) = params.canary else {
+        install(&id, handle, definition, env).await;
+        tracing::info!("Upgraded JS MCP server: {}", id);
+        return Ok(serde_json::json!({ "id": id, "status": "running" }));
+    };
+
+    let read_only = match canary.read_only_tools {
+        Some(tools) => tools.into_iter().collect(),
+        None => annotated_read_only(&handle).await,
+    };
+    let canary = Canary {
+        handle,
+        definition,
+        env,
+        fraction: canary.fraction.clamp(0.0, 1.0),
+        read_only,
+        started_at: chrono::Utc::now().timestamp_millis(),
+        stats: Arc::new(Mutex::new(CanaryStats::default())),
+    };
+    let status = status_json(&id, &canary);
+    if canary.read_only.is_empty() {
+        tracing::warn!("Canary for {} has no read-only tools; no calls will be mirrored", id);
+    }
+    state().canaries.write().await.insert(id.clone(), canary);
+    tracing::info!("Started canary for JS MCP server: {}", id);
+
+    Ok(serde_json::json!({ "id": id, "status": "canary", "canary": status }))
+}
+
+/// Replace a running server with a new version's handle.
+async fn install(id: &str, handle: ServerHandle, definition: ServerDefinition, env: HashMap<String, String>) {
+    let costs = definition.costs.clone();
+    let old = state().servers.write().await.insert(id.to_string(), handle);
+    if let Some(old) = old {
+        old.stop().await;
+    }
+    state().definitions.write().await.insert(id.to_string(), definition);
+    state().envs.write().await.insert(id.to_string(), env);
+    budget::remove_costs(id).await;
+    if let Some(costs) = costs {
+        budget::declare_costs(id, costs).await;
+    }
+}
+
+/// Compare results and latencies of a server's canary so far.
+pub async fn canary_status(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    let canaries = state().canaries.read().await;
+    match params.get("id").and_then(|v| v.as_str()) {
+        Some(id) => canaries
+            .get(id)
+            .map(|canary| status_json(id, canary))
+            .ok_or_else(|| RpcError::new(-32000, format!("Server '{}' has no canary", id))),
+        None => {
+            let mut ids: Vec<&String> = canaries.keys().collect();
+            ids.sort();
+            let list: Vec<serde_json::Value> = ids.into_iter().map(|id| status_json(id, &canaries[id])).collect();
+            Ok(serde_json::json!({ "canaries": list }))
+        }
+    }
+}
+
+/// Switch a server over to its canary and stop the old version.
+pub async fn promote_canary(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    crate::rpc::require_extension("promote or roll back canaries")?;
+    let params: CanaryParams = serde_json::from_value(params)
+        .map_err(|e| RpcError::invalid_params(format!("Invalid params: {}", e)))?;
+    let canary = state()
+        .canaries
+        .write()
+        .await
+        .remove(&params.id)
+        .ok_or_else(|| RpcError::new(-32000, format!("Server '{}' has no canary", params.id)))?;
+    let status = status_json(&params.id, &canary);
+    install(&params.id, canary.handle, canary.definition, canary.env).await;
+    tracing::info!("Promoted canary for JS MCP server: {}", params.id);
+
+    Ok(serde_json::json!({ "id": params.id, "status": "running", "canary": status }))
+}
+
+/// Stop a server's canary and keep the current version.
+pub async fn rollback_canary(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    crate::rpc::require_extension("promote or roll back canaries")?;
+    let params: CanaryParams = serde_json::from_value(params)
+        .map_err(|e| RpcError::invalid_params(format!("Invalid params: {}", e)))?;
+    let canary = state()
+        .canaries
+        .write()
+        .await
+        .remove(&params.id)
+        .ok_or_else(|| RpcError::new(-32000, format!("Server '{}' has no canary", params.id)))?;
+    let status = status_json(&params.id, &canary);
+    canary.handle.stop().await;
+    tracing::info!("Rolled back canary for JS MCP server: {}", params.id);
+
+    Ok(serde_json::json!({ "id": params.id, "status": "running", "canary": status }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_outcome_ignores_meta() {
+        let a = serde_json::json!({ "id": 1, "result": { "content": [1], "_meta": { "timing": {} } } });
+        let b = serde_json::json!({ "id": 2, "result": { "content": [1] } });
+        assert_eq!(outcome(&a), outcome(&b));
+
+        let c = serde_json::json!({ "id": 1, "result": { "content": [2] } });
+        assert_ne!(outcome(&a), outcome(&c));
+
+        let error = serde_json::json!({ "id": 1, "error": { "code": -32601, "message": "x" } });
+        assert_eq!(outcome(&error), serde_json::json!({ "error": -32601 }));
+    }
+}
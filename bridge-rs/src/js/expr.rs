@@ -0,0 +1,177 @@
+//! Sandboxed expressions for mapping data between pipeline steps.
+//!
+//! The glue between tool calls (pick a few fields out of a result, reformat
+//! a date, filter an array) is written as a JavaScript expression instead of
+//! another LLM round-trip. Each evaluation gets a fresh QuickJS context with
+//! no host APIs: no fetch, filesystem, timers or console. Memory and running
+//! time are capped, and data goes in and comes out as JSON, so an expression
+//! can only compute a value from what it was given.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use rquickjs::{CatchResultExt, Context, Runtime, Value};
+
+use crate::rpc::RpcError;
+
+/// Longest expression accepted.
+const MAX_EXPRESSION_BYTES: usize = 16 * 1024;
+
+/// Largest result, as JSON.
+const MAX_RESULT_BYTES: usize = 1024 * 1024;
+
+/// Stack for nested calls; deep recursion fails instead of crashing.
+const MAX_STACK_BYTES: usize = 256 * 1024;
+
+/// Upper bounds for limits a caller asks for.
+const MAX_TIMEOUT: Duration = Duration::from_secs(1);
+const MAX_MEMORY_BYTES: usize = 64 * 1024 * 1024;
+
+/// Resources one evaluation may use.
+#[derive(Debug, Clone, Copy)]
+pub struct Limits {
+    /// Heap for the evaluation, including the data passed in
+    pub memory_bytes: usize,
+    pub timeout: Duration,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            memory_bytes: 16 * 1024 * 1024,
+            timeout: Duration::from_millis(100),
+        }
+    }
+}
+
+/// Evaluate `expression` with each top-level field of `data` as a global,
+/// e.g. `steps.search.items.filter(i => i.open).map(i => i.url)` with
+/// `data = { steps: ... }`. `undefined` and functions come back as null.
+pub fn evaluate(expression: &str, data: &serde_json::Value, limits: &Limits) -> Result<serde_json::Value, String> {
+    if expression.len() > MAX_EXPRESSION_BYTES {
+        return Err(format!("Expressions are limited to {} bytes", MAX_EXPRESSION_BYTES));
+    }
+    let globals = match data {
+        serde_json::Value::Object(map) => map
+            .iter()
+            .map(|(name, value)| Ok((name.clone(), serde_json::to_string(value)?)))
+            .collect::<Result<Vec<_>, serde_json::Error>>()
+            .map_err(|e| e.to_string())?,
+        serde_json::Value::Null => Vec::new(),
+        _ => return Err("Expression data must be an object".to_string()),
+    };
+
+    let runtime = Runtime::new().map_err(|e| format!("Failed to create runtime: {}", e))?;
+    runtime.set_memory_limit(limits.memory_bytes);
+    runtime.set_max_stack_size(MAX_STACK_BYTES);
+    let deadline = Instant::now() + limits.timeout;
+    let timed_out = Arc::new(AtomicBool::new(false));
+    let flag = timed_out.clone();
+    runtime.set_interrupt_handler(Some(Box::new(move || {
+        let over = Instant::now() >= deadline;
+        flag.fetch_or(over, Ordering::Relaxed);
+        over
+    })));
+    let context = Context::full(&runtime).map_err(|e| format!("Failed to create context: {}", e))?;
+
+    let result = context.with(|ctx| -> Result<Option<String>, String> {
+        for (name, json) in globals {
+            let value = ctx.json_parse(json).catch(&ctx).map_err(|e| e.to_string())?;
+            ctx.globals().set(name.as_str(), value).map_err(|e| e.to_string())?;
+        }
+        // The newlines keep a trailing `//` comment from swallowing the paren
+        let value: Value = ctx
+            .eval(format!("(\n{}\n)", expression))
+            .catch(&ctx)
+            .map_err(|e| e.to_string())?;
+        let json = ctx.json_stringify(value).catch(&ctx).map_err(|e| e.to_string())?;
+        json.map(|s| s.to_string()).transpose().map_err(|e| e.to_string())
+    });
+
+    let json = match result {
+        Ok(json) => json,
+        Err(_) if timed_out.load(Ordering::Relaxed) => {
+            return Err(format!("Expression ran longer than {} ms", limits.timeout.as_millis()));
+        }
+        Err(e) if e.contains("out of memory") => {
+            return Err(format!("Expression used more than {} bytes of memory", limits.memory_bytes));
+        }
+        Err(e) => return Err(e),
+    };
+    match json {
+        Some(json) if json.len() > MAX_RESULT_BYTES => {
+            Err(format!("Expression result is over {} bytes", MAX_RESULT_BYTES))
+        }
+        Some(json) => serde_json::from_str(&json).map_err(|e| e.to_string()),
+        None => Ok(serde_json::Value::Null),
+    }
+}
+
+/// Evaluate an expression: `{ expression, data?, timeout_ms?, memory_mb? }`.
+/// Fields of `data` are globals in the expression. Limits are capped at
+/// 1000 ms and 64 MB.
+pub async fn eval_expression(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    let expression = params
+        .get("expression")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| RpcError::invalid_params("Missing 'expression' parameter"))?
+        .to_string();
+    let data = params.get("data").cloned().unwrap_or(serde_json::Value::Null);
+    let mut limits = Limits::default();
+    if let Some(ms) = params.get("timeout_ms").and_then(|v| v.as_u64()) {
+        limits.timeout = Duration::from_millis(ms).min(MAX_TIMEOUT);
+    }
+    if let Some(mb) = params.get("memory_mb").and_then(|v| v.as_u64()) {
+        limits.memory_bytes = (mb as usize).saturating_mul(1024 * 1024).min(MAX_MEMORY_BYTES);
+    }
+
+    let started = Instant::now();
+    let value = tokio::task::spawn_blocking(move || evaluate(&expression, &data, &limits))
+        .await
+        .map_err(|e| RpcError::internal(format!("Expression task failed: {}", e)))?
+        .map_err(|e| RpcError::new(-32000, e))?;
+    Ok(serde_json::json!({
+        "value": value,
+        "ms": started.elapsed().as_millis() as u64,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evaluate_mapping() {
+        let data = serde_json::json!({
+            "steps": { "search": { "items": [
+                { "url": "https://a", "open": true },
+                { "url": "https://b", "open": false },
+            ] } },
+        });
+        let limits = Limits::default();
+        assert_eq!(
+            evaluate("steps.search.items.filter(i => i.open).map(i => i.url)", &data, &limits).unwrap(),
+            serde_json::json!(["https://a"])
+        );
+        assert_eq!(
+            evaluate("{ count: steps.search.items.length } // comment", &data, &limits).unwrap(),
+            serde_json::json!({ "count": 2 })
+        );
+        assert_eq!(evaluate("undefined", &data, &limits).unwrap(), serde_json::Value::Null);
+        assert!(evaluate("steps.missing.items", &data, &limits).is_err());
+        assert!(evaluate("typeof fetch === 'undefined' ? null : fetch('x')", &data, &limits).unwrap().is_null());
+    }
+
+    #[test]
+    fn test_evaluate_limits() {
+        let limits = Limits {
+            memory_bytes: 4 * 1024 * 1024,
+            timeout: Duration::from_millis(50),
+        };
+        let slow = evaluate("(() => { while (true) {} })()", &serde_json::Value::Null, &limits).unwrap_err();
+        assert!(slow.contains("longer than"), "{}", slow);
+        let big = evaluate("'x'.repeat(1 << 20).split('')", &serde_json::Value::Null, &limits).unwrap_err();
+        assert!(big.contains("memory"), "{}", big);
+    }
+}
@@ -5,22 +5,145 @@
 //! - Filesystem with path allowlists  
 //! - Environment variables
 //! - MCP stdio interface
+//!
+//! `expr` uses the same engine, with no host APIs at all, for the small
+//! data-mapping expressions in pipelines and composite tools.
 
+mod canary;
+mod expr;
 mod runtime;
 mod sandbox;
 
+pub use canary::{canary_status, promote_canary, rollback_canary, upgrade_server};
+pub use expr::{eval_expression, evaluate, Limits as ExprLimits};
 pub use runtime::{JsServer, JsServerConfig, ServerHandle};
-pub use sandbox::Capabilities;
+pub use sandbox::{Capabilities, NetworkCapabilities};
 
+use crate::auth::AuthConfig;
+use crate::budget::{self, CostConfig};
+use crate::catalog;
+use crate::chaos::{self, Fault};
+use crate::concurrency;
+use crate::history::{self, HistoryEntry};
+use crate::mcp::{self, pause, timeout};
+use crate::metrics::{self, CallTimings};
 use crate::rpc::RpcError;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
 
-// Global registry of running JS servers
-lazy_static::lazy_static! {
-    static ref SERVERS: Arc<RwLock<HashMap<String, ServerHandle>>> = Arc::new(RwLock::new(HashMap::new()));
+/// How long past a call's deadline a server has to answer it before it is
+/// taken to be stuck and restarted.
+const STUCK_GRACE: Duration = Duration::from_secs(2);
+
+/// JS runtime subsystem state.
+#[derive(Default)]
+pub struct JsState {
+    /// Running servers
+    servers: RwLock<HashMap<String, ServerHandle>>,
+    /// Definitions of running servers
+    definitions: RwLock<HashMap<String, ServerDefinition>>,
+    /// Environment each server was started with, kept for restarts
+    envs: RwLock<HashMap<String, HashMap<String, String>>>,
+    /// New versions running alongside the current one (see `canary`)
+    canaries: RwLock<HashMap<String, canary::Canary>>,
+}
+
+impl JsState {
+    /// Stop all running servers. Definitions are kept so they can be restarted.
+    pub async fn stop(&self) {
+        let canaries: Vec<String> = self.canaries.read().await.keys().cloned().collect();
+        for id in canaries {
+            canary::discard(&id).await;
+        }
+        let servers: Vec<(String, ServerHandle)> = self.servers.write().await.drain().collect();
+        for (id, handle) in servers {
+            handle.stop().await;
+            tracing::info!("Stopped JS MCP server: {}", id);
+        }
+    }
+
+    /// Send every running server an MCP `ping`. Returns the IDs of servers
+    /// that didn't answer within `timeout`.
+    pub async fn ping_all(&self, timeout: std::time::Duration) -> Vec<String> {
+        let servers = self.servers.read().await;
+        let pings = servers.iter().map(|(id, handle)| async move {
+            let ping = serde_json::json!({ "jsonrpc": "2.0", "id": "bridge-ping", "method": "ping" });
+            match tokio::time::timeout(timeout, handle.call_timed(ping)).await {
+                Ok((Ok(_), _)) => None,
+                _ => Some(id.clone()),
+            }
+        });
+        let mut unresponsive: Vec<String> = futures::future::join_all(pings).await.into_iter().flatten().collect();
+        unresponsive.sort();
+        unresponsive
+    }
+
+    /// Stop all servers and start them again from their definitions.
+    /// Returns the IDs of servers that failed to start, with the error.
+    pub async fn restart(&self) -> Vec<(String, String)> {
+        self.stop().await;
+
+        let definitions: Vec<ServerDefinition> = self.definitions.read().await.values().cloned().collect();
+        let mut failed = Vec::new();
+        for definition in definitions {
+            let id = definition.id.clone();
+            match self.start_again(definition).await {
+                Ok(handle) => {
+                    self.servers.write().await.insert(id.clone(), handle);
+                    tracing::info!("Restarted JS MCP server: {}", id);
+                }
+                Err(e) => {
+                    tracing::error!("Failed to restart JS MCP server {}: {}", id, e);
+                    self.definitions.write().await.remove(&id);
+                    self.envs.write().await.remove(&id);
+                    failed.push((id, e));
+                }
+            }
+        }
+        failed
+    }
+
+    /// Start a new instance of a server from its definition and the
+    /// environment it was last started with.
+    async fn start_again(&self, definition: ServerDefinition) -> Result<ServerHandle, String> {
+        let env = self.envs.read().await.get(&definition.id).cloned().unwrap_or_default();
+        JsServer::start(JsServerConfig {
+            id: definition.id,
+            code: definition.code,
+            env,
+            capabilities: definition.capabilities,
+            auth: definition.auth,
+        })
+        .await
+    }
+}
+
+/// The bridge's JS runtime state.
+fn state() -> &'static JsState {
+    &crate::state::get().js
+}
+
+/// The non-secret definition a server was started with.
+/// Kept so running servers can be exported and re-created elsewhere.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServerDefinition {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    pub code: String,
+    /// Names of injected environment variables (values are never exported)
+    #[serde(default)]
+    pub env_keys: Vec<String>,
+    #[serde(default)]
+    pub capabilities: Capabilities,
+    /// Declared authentication for fetch requests
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth: Option<AuthConfig>,
+    /// Declared cost per tool call
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub costs: Option<CostConfig>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -35,6 +158,15 @@ pub struct StartServerParams {
     /// Capabilities/permissions
     #[serde(default)]
     pub capabilities: Capabilities,
+    /// Server version (informational, used for export)
+    #[serde(default)]
+    pub version: Option<String>,
+    /// Authentication the bridge applies to fetch requests (from the manifest)
+    #[serde(default)]
+    pub auth: Option<AuthConfig>,
+    /// Approximate cost per tool call (from the manifest)
+    #[serde(default)]
+    pub costs: Option<CostConfig>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -46,12 +178,44 @@ pub struct StopServerParams {
 pub struct CallServerParams {
     pub id: String,
     pub request: serde_json::Value,
+    /// The user confirmed a call that exceeds the server's budget
+    #[serde(default)]
+    pub confirm_over_budget: bool,
+    /// Timeout for a `tools/call`; defaults to the bridge-wide tool timeout
+    #[serde(default, alias = "timeoutMs")]
+    pub timeout_ms: Option<u64>,
 }
 
 #[derive(Debug, Serialize)]
 pub struct ServerInfo {
     pub id: String,
     pub running: bool,
+    /// Running, but not taking tool calls (see `mcp::pause`)
+    pub paused: bool,
+}
+
+/// Split start params into the server's definition and runtime config.
+fn prepare(params: StartServerParams) -> (ServerDefinition, JsServerConfig) {
+    let mut env_keys: Vec<String> = params.env.keys().cloned().collect();
+    env_keys.sort();
+    let definition = ServerDefinition {
+        id: params.id.clone(),
+        version: params.version,
+        code: params.code.clone(),
+        env_keys,
+        capabilities: params.capabilities.clone(),
+        auth: params.auth.clone(),
+        costs: params.costs,
+    };
+
+    let config = JsServerConfig {
+        id: params.id,
+        code: params.code,
+        env: params.env,
+        capabilities: params.capabilities,
+        auth: params.auth,
+    };
+    (definition, config)
 }
 
 /// Start a new JS MCP server
@@ -61,7 +225,7 @@ pub async fn start_server(params: serde_json::Value) -> Result<serde_json::Value
         message: format!("Invalid params: {}", e),
     })?;
 
-    let mut servers = SERVERS.write().await;
+    let mut servers = state().servers.write().await;
     
     if servers.contains_key(&params.id) {
         return Err(RpcError {
@@ -70,24 +234,27 @@ pub async fn start_server(params: serde_json::Value) -> Result<serde_json::Value
         });
     }
 
-    let config = JsServerConfig {
-        id: params.id.clone(),
-        code: params.code,
-        env: params.env,
-        capabilities: params.capabilities,
-    };
+    let (definition, config) = prepare(params);
+    let id = config.id.clone();
+    let env = config.env.clone();
+    let costs = definition.costs.clone();
 
     let handle = JsServer::start(config).await.map_err(|e| RpcError {
         code: -32000,
         message: format!("Failed to start server: {}", e),
     })?;
 
-    servers.insert(params.id.clone(), handle);
+    if let Some(costs) = costs {
+        budget::declare_costs(&id, costs).await;
+    }
+    servers.insert(id.clone(), handle);
+    state().definitions.write().await.insert(id.clone(), definition);
+    state().envs.write().await.insert(id.clone(), env);
 
-    tracing::info!("Started JS MCP server: {}", params.id);
+    tracing::info!("Started JS MCP server: {}", id);
 
     Ok(serde_json::json!({
-        "id": params.id,
+        "id": id,
         "status": "running"
     }))
 }
@@ -99,10 +266,15 @@ pub async fn stop_server(params: serde_json::Value) -> Result<serde_json::Value,
         message: format!("Invalid params: {}", e),
     })?;
 
-    let mut servers = SERVERS.write().await;
+    let mut servers = state().servers.write().await;
     
     if let Some(handle) = servers.remove(&params.id) {
         handle.stop().await;
+        canary::discard(&params.id).await;
+        state().definitions.write().await.remove(&params.id);
+        state().envs.write().await.remove(&params.id);
+        budget::remove_costs(&params.id).await;
+        crate::fs::stop_watches(&params.id);
         tracing::info!("Stopped JS MCP server: {}", params.id);
         Ok(serde_json::json!({
             "id": params.id,
@@ -118,35 +290,243 @@ pub async fn stop_server(params: serde_json::Value) -> Result<serde_json::Value,
 
 /// Send an MCP request to a running JS server
 pub async fn call_server(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    let received = Instant::now();
     let params: CallServerParams = serde_json::from_value(params).map_err(|e| RpcError {
         code: -32602,
         message: format!("Invalid params: {}", e),
     })?;
 
-    let servers = SERVERS.read().await;
+    // A paused server stays running but takes no tool calls and lists no tools
+    match params.request.get("method").and_then(|m| m.as_str()) {
+        Some("tools/call") => pause::check(&params.id)?,
+        Some("tools/list") if pause::is_paused(&params.id) => return Ok(pause::empty_tools_list(&params.request)),
+        _ => {}
+    }
+
+    let servers = state().servers.read().await;
     
     let handle = servers.get(&params.id).ok_or_else(|| RpcError {
         code: -32000,
         message: format!("Server '{}' not found", params.id),
     })?;
 
-    handle.call(params.request).await.map_err(|e| RpcError {
+    let is_tools_list = params.request.get("method").and_then(|m| m.as_str()) == Some("tools/list");
+
+    // Record tool calls in the usage history
+    let tool_call = if params.request.get("method").and_then(|m| m.as_str()) == Some("tools/call") {
+        let call_params = params.request.get("params");
+        Some((
+            call_params
+                .and_then(|p| p.get("name"))
+                .and_then(|n| n.as_str())
+                .unwrap_or_default()
+                .to_string(),
+            call_params
+                .and_then(|p| p.get("arguments"))
+                .cloned()
+                .unwrap_or(serde_json::Value::Null),
+        ))
+    } else {
+        None
+    };
+
+    let call_timeout = match &tool_call {
+        Some(_) => Some(timeout::resolve(params.timeout_ms)?),
+        None => None,
+    };
+
+    // In fault injection mode, a tool call may find the server crashed
+    if tool_call.is_some() && chaos::inject(Fault::ServerCrash, Some(&params.id)) {
+        drop(servers);
+        let _ = stop_server(serde_json::json!({ "id": params.id })).await;
+        return Err(RpcError {
+            code: -32000,
+            message: format!("Server call failed: server '{}' crashed", params.id),
+        });
+    }
+
+    // Check the call against the server's budget
+    let charge = match &tool_call {
+        Some((tool, _)) => budget::check(&params.id, tool, params.confirm_over_budget).await?,
+        None => None,
+    };
+
+    // Wait for a slot under the server's adaptive concurrency limit
+    let routed = Instant::now();
+    let permit = concurrency::limiter(&params.id).acquire().await;
+
+    let started_at = chrono::Utc::now().timestamp_millis();
+    let dispatched = Instant::now();
+
+    // Mirror some read-only calls to a canary, if the server has one
+    let mirrored = match &tool_call {
+        Some((tool, _)) if canary::should_mirror(&params.id, tool).await => {
+            Some((tool.clone(), params.request.clone()))
+        }
+        _ => None,
+    };
+
+    if tool_call.is_some() {
+        chaos::tool_latency(&params.id).await;
+    }
+    // The server answers a call that passes its deadline itself, whether it
+    // was still queued or running; one that doesn't answer at all is stuck
+    let mut stuck = false;
+    let (mut result, server_timing) = match call_timeout {
+        Some(limit) => {
+            let call = handle.call_until(params.request, Some(dispatched + limit));
+            tokio::time::timeout(limit + STUCK_GRACE, call).await.unwrap_or_else(|_| {
+                stuck = true;
+                (Err(runtime::TIMED_OUT.to_string()), Default::default())
+            })
+        }
+        None => handle.call_timed(params.request).await,
+    };
+    let round_trip = dispatched.elapsed();
+    if let Some((tool, request)) = mirrored {
+        canary::mirror(params.id.clone(), tool, request, result.clone(), round_trip);
+    }
+    let ok = matches!(&result, Ok(response) if response.get("error").is_none());
+    permit.finish(ok);
+    if let Some(charge) = charge {
+        let succeeded = matches!(&result, Ok(response) if !mcp::call_failed(response));
+        budget::settle(charge, succeeded).await;
+    }
+
+    if let Some((tool, args)) = tool_call {
+        let post_start = Instant::now();
+        let duration_ms = round_trip.as_millis() as u64;
+        let outcome = match &result {
+            Ok(response) => match response.get("error") {
+                Some(error) => Err(error
+                    .get("message")
+                    .and_then(|m| m.as_str())
+                    .unwrap_or("Tool call failed")),
+                None => Ok(response.get("result").unwrap_or(response)),
+            },
+            Err(e) => Err(e.as_str()),
+        };
+        history::record(HistoryEntry::new(&params.id, &tool, started_at, duration_ms, outcome, &args));
+
+        let server_time = server_timing.queue_wait + server_timing.execution;
+        let mut timings = CallTimings {
+            policy_ms: metrics::ms(routed - received),
+            queue_wait_ms: metrics::ms(dispatched - routed + server_timing.queue_wait),
+            server_ms: metrics::ms(server_timing.execution),
+            transport_ms: metrics::ms(round_trip.saturating_sub(server_time)),
+            ..Default::default()
+        };
+        timings.post_processing_ms = metrics::ms(post_start.elapsed());
+        timings.total_ms = metrics::ms(received.elapsed());
+        metrics::record(&params.id, &tool, &timings);
+        if let Some(response) = result.as_mut().ok().and_then(|r| r.get_mut("result")) {
+            metrics::attach(response, &timings);
+        }
+    }
+
+    if is_tools_list {
+        if let Some(tools) = result.as_ref().ok().and_then(|r| r.pointer("/result/tools")) {
+            observe_tools(&params.id, tools).await;
+        }
+    }
+
+    if let Some(limit) = call_timeout.filter(|_| matches!(&result, Err(e) if e == runtime::TIMED_OUT)) {
+        if stuck {
+            let stale = handle.requests_only();
+            drop(servers);
+            recycle(&params.id, &stale).await;
+        }
+        return Err(timeout::error(&params.id, limit));
+    }
+
+    result.map_err(|e| RpcError {
         code: -32000,
         message: format!("Server call failed: {}", e),
     })
 }
 
+/// Replace a server that didn't answer a call past its deadline with a
+/// fresh instance of its definition, so the next call isn't stuck behind
+/// whatever it was doing. Calls that timed out on the same stuck instance
+/// restart it once: `stale` is that instance, and a server that has
+/// already been replaced is left alone.
+async fn recycle(id: &str, stale: &ServerHandle) {
+    let Some(definition) = definition(id).await else {
+        return;
+    };
+    let handle = match state().start_again(definition).await {
+        Ok(handle) => handle,
+        Err(e) => {
+            tracing::error!("Failed to restart JS MCP server {}: {}", id, e);
+            return;
+        }
+    };
+    let mut servers = state().servers.write().await;
+    if !servers.get(id).is_some_and(|current| current.same_server(stale)) {
+        drop(servers);
+        handle.stop().await;
+        return;
+    }
+    if let Some(old) = servers.insert(id.to_string(), handle) {
+        old.stop().await;
+    }
+    tracing::info!("Restarted JS MCP server after a timeout: {}", id);
+}
+
+/// Record a `tools/list` result in the tool catalog.
+async fn observe_tools(server_id: &str, tools: &serde_json::Value) {
+    let schemas = tools
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|tool| {
+            let name = tool.get("name")?.as_str()?;
+            Some((name.to_string(), tool.get("inputSchema").cloned().unwrap_or_default()))
+        })
+        .collect();
+    let version = definition(server_id).await.and_then(|d| d.version);
+    // JS servers are already running, so there is nothing to hold
+    catalog::observe(server_id, version, schemas, tools.clone(), false).await;
+}
+
 /// List all running JS servers
 pub async fn list_servers() -> Result<serde_json::Value, RpcError> {
-    let servers = SERVERS.read().await;
+    let servers = state().servers.read().await;
     
     let list: Vec<ServerInfo> = servers
         .keys()
         .map(|id| ServerInfo {
             id: id.clone(),
             running: true,
+            paused: pause::is_paused(id),
         })
         .collect();
 
     Ok(serde_json::json!({ "servers": list }))
 }
+
+/// Restart the JS runtime: stop every server and start it again.
+pub async fn restart() -> Result<serde_json::Value, RpcError> {
+    let failed = state().restart().await;
+    let restarted: Vec<String> = state().servers.read().await.keys().cloned().collect();
+
+    Ok(serde_json::json!({
+        "restarted": restarted,
+        "failed": failed
+            .into_iter()
+            .map(|(id, error)| serde_json::json!({ "id": id, "error": error }))
+            .collect::<Vec<_>>(),
+    }))
+}
+
+/// Get the definition of a running JS server.
+pub async fn definition(id: &str) -> Option<ServerDefinition> {
+    state().definitions.read().await.get(id).cloned()
+}
+
+/// Get the definitions of all running JS servers.
+pub async fn list_definitions() -> Vec<ServerDefinition> {
+    let mut definitions: Vec<ServerDefinition> = state().definitions.read().await.values().cloned().collect();
+    definitions.sort_by(|a, b| a.id.cmp(&b.id));
+    definitions
+}
@@ -1,6 +1,7 @@
 //! QuickJS runtime for executing JS MCP servers.
 
-use super::sandbox::Capabilities;
+use super::sandbox::{Capabilities, NetworkCapabilities};
+use crate::auth::{self, AuthConfig, AuthScheme, HttpRequest};
 use crate::native_messaging::{get_console_log_sender, ConsoleLogMessage};
 use rquickjs::{Context, Object, Runtime};
 use serde::{Deserialize, Serialize};
@@ -22,6 +23,34 @@ struct FetchOptions {
     body: Option<String>,
 }
 
+/// A pending `peer.call` from JS
+#[derive(Debug, Deserialize)]
+struct PeerRequest {
+    id: u64,
+    params: serde_json::Value,
+}
+
+/// Managed credentials applied to a server's fetch requests
+struct FetchAuth {
+    scheme: AuthScheme,
+    /// Hosts the credentials may be sent to
+    hosts: NetworkCapabilities,
+}
+
+impl FetchAuth {
+    fn new(config: &AuthConfig, capabilities: &Capabilities) -> Self {
+        let allowed_hosts = if config.hosts.is_empty() {
+            capabilities.network.allowed_hosts.clone()
+        } else {
+            config.hosts.clone()
+        };
+        Self {
+            scheme: config.scheme.clone(),
+            hosts: NetworkCapabilities { allowed_hosts },
+        }
+    }
+}
+
 /// Response to inject back into JS
 #[derive(Debug, Serialize)]
 struct FetchResponse {
@@ -33,12 +62,25 @@ struct FetchResponse {
     error: Option<String>,
 }
 
+impl FetchResponse {
+    fn error(message: String) -> Self {
+        FetchResponse {
+            status: 0,
+            status_text: String::new(),
+            headers: HashMap::new(),
+            body: String::new(),
+            error: Some(message),
+        }
+    }
+}
+
 /// Configuration for starting a JS server
 pub struct JsServerConfig {
     pub id: String,
     pub code: String,
     pub env: HashMap<String, String>,
     pub capabilities: Capabilities,
+    pub auth: Option<AuthConfig>,
 }
 
 /// Handle to a running JS server
@@ -47,33 +89,77 @@ pub struct ServerHandle {
     shutdown_tx: Option<oneshot::Sender<()>>,
 }
 
+/// The error a request gets when it runs past its deadline.
+pub const TIMED_OUT: &str = "Request passed its deadline";
+
 struct ServerRequest {
     payload: serde_json::Value,
-    response_tx: oneshot::Sender<Result<serde_json::Value, String>>,
+    enqueued_at: std::time::Instant,
+    /// When to give up on the request, interrupting any running JS
+    deadline: Option<std::time::Instant>,
+    response_tx: oneshot::Sender<(Result<serde_json::Value, String>, ServerTiming)>,
+}
+
+/// Time a request spent inside the server thread.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ServerTiming {
+    /// Waiting in the server's request channel
+    pub queue_wait: std::time::Duration,
+    /// Executing in QuickJS
+    pub execution: std::time::Duration,
 }
 
 /// Represents a running JS MCP server
 pub struct JsServer;
 
 impl ServerHandle {
-    /// Send an MCP request to the server and wait for response
-    pub async fn call(&self, request: serde_json::Value) -> Result<serde_json::Value, String> {
+    /// Send an MCP request to the server and wait for response.
+    /// Also reports where the server spent its time.
+    pub async fn call_timed(&self, request: serde_json::Value) -> (Result<serde_json::Value, String>, ServerTiming) {
+        self.call_until(request, None).await
+    }
+
+    /// Like `call_timed`, but the server stops working on the request at
+    /// `deadline` and answers `TIMED_OUT`.
+    pub async fn call_until(
+        &self,
+        request: serde_json::Value,
+        deadline: Option<std::time::Instant>,
+    ) -> (Result<serde_json::Value, String>, ServerTiming) {
         let (response_tx, response_rx) = oneshot::channel();
         
-        self.request_tx
+        let sent = self.request_tx
             .send(ServerRequest {
                 payload: request,
+                enqueued_at: std::time::Instant::now(),
+                deadline,
                 response_tx,
             })
-            .await
-            .map_err(|_| "Server channel closed".to_string())?;
+            .await;
+        if sent.is_err() {
+            return (Err("Server channel closed".to_string()), ServerTiming::default());
+        }
 
         response_rx
             .await
-            .map_err(|_| "Response channel closed".to_string())?
+            .unwrap_or_else(|_| (Err("Response channel closed".to_string()), ServerTiming::default()))
     }
 
     /// Stop the server
+    /// A handle that sends requests to the same server but can't stop it,
+    /// for a caller that shouldn't hold a lock while it waits.
+    pub fn requests_only(&self) -> ServerHandle {
+        ServerHandle {
+            request_tx: self.request_tx.clone(),
+            shutdown_tx: None,
+        }
+    }
+
+    /// Whether two handles reach the same server instance.
+    pub fn same_server(&self, other: &ServerHandle) -> bool {
+        self.request_tx.same_channel(&other.request_tx)
+    }
+
     pub async fn stop(mut self) {
         if let Some(tx) = self.shutdown_tx.take() {
             let _ = tx.send(());
@@ -110,6 +196,15 @@ impl JsServer {
     ) -> Result<(), String> {
         // Create QuickJS runtime
         let runtime = Runtime::new().map_err(|e| format!("Failed to create runtime: {}", e))?;
+        // Running JS is interrupted once the current request's deadline passes
+        let deadline = std::sync::Arc::new(std::sync::Mutex::new(None::<std::time::Instant>));
+        let current = deadline.clone();
+        runtime.set_interrupt_handler(Some(Box::new(move || {
+            current
+                .lock()
+                .map(|d| d.map(|d| std::time::Instant::now() >= d).unwrap_or(false))
+                .unwrap_or(false)
+        })));
         let context = Context::full(&runtime).map_err(|e| format!("Failed to create context: {}", e))?;
 
         context.with(|ctx| {
@@ -147,6 +242,8 @@ impl JsServer {
             Self::flush_console_logs(&ctx, &config.id);
         });
 
+        let fetch_auth = config.auth.as_ref().map(|a| FetchAuth::new(a, &config.capabilities));
+
         // Message processing loop
         let rt = tokio::runtime::Handle::current();
         loop {
@@ -167,10 +264,23 @@ impl JsServer {
                 }
             }) {
                 Some(request) => {
-                    let response = Self::handle_mcp_request_with_jobs(
-                        &context, &runtime, &rt, request.payload, &config.id
-                    );
-                    let _ = request.response_tx.send(response);
+                    let started = std::time::Instant::now();
+                    let response = if request.deadline.map(|d| started >= d).unwrap_or(false) {
+                        // Expired while queued
+                        Err(TIMED_OUT.to_string())
+                    } else {
+                        *deadline.lock().unwrap() = request.deadline;
+                        let response = Self::handle_mcp_request_with_jobs(
+                            &context, &runtime, &rt, request.payload, &config.id, fetch_auth.as_ref(), request.deadline
+                        );
+                        *deadline.lock().unwrap() = None;
+                        response
+                    };
+                    let timing = ServerTiming {
+                        queue_wait: started.duration_since(request.enqueued_at),
+                        execution: started.elapsed(),
+                    };
+                    let _ = request.response_tx.send((response, timing));
                 }
                 None => {
                     // No request, continue loop
@@ -367,6 +477,42 @@ impl JsServer {
             "#).map_err(|e| e.to_string())?;
         }
 
+        // peer.call(target, tool, arguments, token) - the bridge makes the
+        // call as this server; see `crate::peer`
+        ctx.eval::<(), _>(r#"
+            globalThis.__peer_requests = [];
+            globalThis.__peer_responses = {};
+            globalThis.__peer_id = 0;
+
+            globalThis.peer = {
+                call: async function(target, tool, args, token) {
+                    const id = ++globalThis.__peer_id;
+                    globalThis.__peer_requests.push({
+                        id: id,
+                        params: { target: target, tool: tool, arguments: args || {}, token: token || null }
+                    });
+
+                    // Wait for the result (will be filled by Rust)
+                    return new Promise((resolve, reject) => {
+                        const check = () => {
+                            const resp = globalThis.__peer_responses[id];
+                            if (resp) {
+                                delete globalThis.__peer_responses[id];
+                                if (resp.error) {
+                                    reject(new Error(resp.error));
+                                } else {
+                                    resolve(resp.result);
+                                }
+                            } else {
+                                setTimeout(check, 1);
+                            }
+                        };
+                        check();
+                    });
+                },
+            };
+        "#).map_err(|e| e.to_string())?;
+
         Ok(())
     }
 
@@ -418,6 +564,8 @@ impl JsServer {
         rt: &tokio::runtime::Handle,
         request: serde_json::Value,
         server_id: &str,
+        fetch_auth: Option<&FetchAuth>,
+        deadline: Option<std::time::Instant>,
     ) -> Result<serde_json::Value, String> {
         tracing::info!("[JS:{}] Handling MCP request", server_id);
         
@@ -494,7 +642,10 @@ impl JsServer {
             });
             
             // Process pending fetch requests
-            Self::process_fetch_requests(&context, &rt, server_id);
+            Self::process_fetch_requests(&context, &rt, server_id, fetch_auth);
+
+            // Process pending peer calls
+            Self::process_peer_calls(&context, &rt, server_id);
             
             // Check for response INSIDE context lock
             let response_result: Result<Option<String>, String> = context.with(|ctx| {
@@ -529,6 +680,11 @@ impl JsServer {
                 }
             }
 
+            if deadline.map(|d| std::time::Instant::now() >= d).unwrap_or(false) {
+                tracing::warn!("[JS:{}] Request passed its deadline after {} jobs", server_id, total_jobs);
+                return Err(TIMED_OUT.to_string());
+            }
+
             // Small delay before checking again
             std::thread::sleep(std::time::Duration::from_millis(1));
         }
@@ -548,7 +704,12 @@ impl JsServer {
     }
 
     /// Process any pending fetch requests from JS
-    fn process_fetch_requests(context: &Context, rt: &tokio::runtime::Handle, server_id: &str) {
+    fn process_fetch_requests(
+        context: &Context,
+        rt: &tokio::runtime::Handle,
+        server_id: &str,
+        fetch_auth: Option<&FetchAuth>,
+    ) {
         // Extract pending fetch requests from JS
         let requests_json: Option<String> = context.with(|ctx| {
             ctx.eval(r#"
@@ -575,7 +736,7 @@ impl JsServer {
         // Process each request
         for request in requests {
             let response = rt.block_on(async {
-                Self::execute_fetch(&request).await
+                Self::execute_fetch(&request, server_id, fetch_auth).await
             });
 
             // Inject response back into JS
@@ -594,8 +755,35 @@ impl JsServer {
         }
     }
 
+    /// Process any pending `peer.call`s from JS, made as `server_id`
+    fn process_peer_calls(context: &Context, rt: &tokio::runtime::Handle, server_id: &str) {
+        let requests: Vec<PeerRequest> = context
+            .with(|ctx| {
+                ctx.eval::<String, _>(r#"
+                    JSON.stringify(globalThis.__peer_requests ? globalThis.__peer_requests.splice(0) : [])
+                "#).ok()
+            })
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default();
+
+        for request in requests {
+            let response = match rt.block_on(crate::peer::call(server_id, request.params)) {
+                Ok(result) => serde_json::json!({ "result": result }),
+                Err(e) => serde_json::json!({ "error": e.message }),
+            };
+            context.with(|ctx| {
+                let code = format!("globalThis.__peer_responses[{}] = {};", request.id, response);
+                let _: Result<(), _> = ctx.eval(code.as_str());
+            });
+        }
+    }
+
     /// Execute a single fetch request
-    async fn execute_fetch(request: &FetchRequest) -> FetchResponse {
+    async fn execute_fetch(
+        request: &FetchRequest,
+        server_id: &str,
+        fetch_auth: Option<&FetchAuth>,
+    ) -> FetchResponse {
         let client = reqwest::Client::new();
         
         let method = request.options.method
@@ -605,34 +793,48 @@ impl JsServer {
 
         tracing::info!("[Fetch] {} {}", method, request.url);
 
+        let url = match url::Url::parse(&request.url) {
+            Ok(url) => url,
+            Err(e) => return FetchResponse::error(format!("Invalid URL: {}", e)),
+        };
+
+        let mut outgoing = HttpRequest {
+            method: method.clone(),
+            url,
+            headers: request.options.headers.clone().unwrap_or_default(),
+            body: request.options.body.clone().unwrap_or_default().into_bytes(),
+        };
+
+        // Attach managed credentials for the declared hosts only
+        if let Some(fetch_auth) = fetch_auth {
+            if fetch_auth.hosts.is_host_allowed(outgoing.url.as_str()) {
+                if let Err(e) = auth::apply(server_id, &fetch_auth.scheme, &mut outgoing).await {
+                    tracing::warn!("[Fetch] Failed to authenticate request: {}", e);
+                    return FetchResponse::error(e);
+                }
+            }
+        }
+
         let mut req_builder = match method.as_str() {
-            "GET" => client.get(&request.url),
-            "POST" => client.post(&request.url),
-            "PUT" => client.put(&request.url),
-            "DELETE" => client.delete(&request.url),
-            "PATCH" => client.patch(&request.url),
-            "HEAD" => client.head(&request.url),
+            "GET" => client.get(outgoing.url),
+            "POST" => client.post(outgoing.url),
+            "PUT" => client.put(outgoing.url),
+            "DELETE" => client.delete(outgoing.url),
+            "PATCH" => client.patch(outgoing.url),
+            "HEAD" => client.head(outgoing.url),
             _ => {
-                return FetchResponse {
-                    status: 0,
-                    status_text: String::new(),
-                    headers: HashMap::new(),
-                    body: String::new(),
-                    error: Some(format!("Unsupported method: {}", method)),
-                };
+                return FetchResponse::error(format!("Unsupported method: {}", method));
             }
         };
 
         // Add headers
-        if let Some(headers) = &request.options.headers {
-            for (key, value) in headers {
-                req_builder = req_builder.header(key.as_str(), value.as_str());
-            }
+        for (key, value) in &outgoing.headers {
+            req_builder = req_builder.header(key.as_str(), value.as_str());
         }
 
         // Add body
-        if let Some(body) = &request.options.body {
-            req_builder = req_builder.body(body.clone());
+        if request.options.body.is_some() {
+            req_builder = req_builder.body(outgoing.body);
         }
 
         // Execute request
@@ -664,13 +866,7 @@ impl JsServer {
             }
             Err(e) => {
                 tracing::error!("[Fetch] Error: {}", e);
-                FetchResponse {
-                    status: 0,
-                    status_text: String::new(),
-                    headers: HashMap::new(),
-                    body: String::new(),
-                    error: Some(e.to_string()),
-                }
+                FetchResponse::error(e.to_string())
             }
         }
     }
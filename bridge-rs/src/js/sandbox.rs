@@ -37,7 +37,6 @@ pub struct FilesystemCapabilities {
 
 impl NetworkCapabilities {
     /// Check if a URL's host is allowed
-    #[allow(dead_code)]
     pub fn is_host_allowed(&self, url: &str) -> bool {
         if self.allowed_hosts.is_empty() {
             return false;
@@ -48,7 +48,6 @@ impl ProviderInstance {
     }
 
     /// Create a new provider instance with a specific ID
-    #[allow(dead_code)]
     pub fn with_id(id: &str, provider_type: &str, name: &str) -> Self {
         Self {
             id: id.to_string(),
@@ -380,7 +379,6 @@ impl LlmConfig {
     }
 
     /// Get a model by name.
-    #[allow(dead_code)]
     pub fn get_model(&self, name: &str) -> Option<&ModelAlias> {
         self.models.iter().find(|m| m.name == name)
     }
@@ -2,7 +2,7 @@
 
 mod config;
 
-pub use config::{LlmConfig, ProviderInstance};
+pub use config::{LlmConfig, ModelAlias, ProviderInstance};
 
 use crate::rpc::RpcError;
 use any_llm::{
@@ -1,11 +1,46 @@
+mod auth;
+mod automation;
+mod browser;
+mod budget;
+mod capabilities;
+mod catalog;
+mod changes;
+mod chaos;
+mod cli;
+mod composite;
+mod concurrency;
+mod context;
+mod dev;
+mod embeddings;
+mod events;
 mod fs;
+mod history;
 mod http_server;
 mod js;
 mod llm;
+mod maintenance;
 mod mcp;
+mod metrics;
 mod native_messaging;
 mod oauth;
+mod outbox;
+mod peer;
+mod power;
+mod profiles;
+mod redact;
+mod report;
 mod rpc;
+mod scaffold;
+mod scheduler;
+mod secrets;
+mod server_logs;
+mod servers;
+mod signing;
+mod state;
+mod store;
+mod wasm;
+mod watchdog;
+mod workspace;
 
 use std::env;
 
@@ -22,12 +57,23 @@ async fn main() {
     .nth(1)
     .and_then(|p| p.parse().ok())
     .unwrap_or(http_server::DEFAULT_PORT);
+  let mode = if http_mode {
+    report::Mode::HttpServer
+  } else if native_mode {
+    report::Mode::NativeMessaging
+  } else {
+    report::Mode::Standalone
+  };
+
+  // Print the environment report and exit, for bug reports
+  if env::args().any(|arg| arg == "--print-env-report") {
+    report::emit(&report::collect(mode, http_port));
+    return;
+  }
   
   // Set up logging - in native mode, log to file (stderr is used for protocol in some cases)
   if native_mode {
-    let log_path = dirs::cache_dir()
-      .unwrap_or_else(|| std::path::PathBuf::from("/tmp"))
-      .join("harbor-bridge.log");
+    let log_path = maintenance::log_path();
     
     if let Ok(file) = std::fs::OpenOptions::new()
       .create(true)
@@ -35,14 +81,25 @@ async fn main() {
       .open(&log_path)
     {
       tracing_subscriber::fmt()
-        .with_writer(std::sync::Mutex::new(file))
+        .with_writer(redact::Redacting::new(std::sync::Mutex::new(file)))
         .with_ansi(false)
         .init();
     }
   } else {
-    tracing_subscriber::fmt::init();
+    tracing_subscriber::fmt()
+      .with_writer(redact::Redacting::new(std::io::stderr))
+      .init();
   }
 
+  // Subcommands run and exit without starting the bridge
+  let args: Vec<String> = env::args().skip(1).collect();
+  if let Some(code) = cli::run(&args).await {
+    std::process::exit(code);
+  }
+
+  // Restart the bridge if the runtime or a critical task stops making progress
+  watchdog::start();
+
   // Load LLM configuration from disk
   match llm::LlmConfig::load() {
     Ok(config) => {
@@ -54,8 +111,18 @@ async fn main() {
     }
   }
 
-  // Initialize OAuth module (loads credentials and stored tokens)
+  // One line of context for triaging from logs
+  report::emit(&report::collect(mode, http_port));
+
+  // Install subsystem state, then initialize OAuth (loads credentials and stored tokens)
+  state::install(state::AppState::default());
   oauth::init().await;
+  maintenance::start();
+  outbox::start();
+  scheduler::start();
+  power::start();
+  wasm::start();
+  servers::start();
 
   if http_mode {
     // HTTP server mode for Safari
@@ -0,0 +1,325 @@
+//! Housekeeping scheduler.
+//!
+//! Once per interval, inside a configurable window of local hours, the
+//! bridge compacts the tool call history, rotates its log file, prunes
+//! expired AWS sessions and filesystem grants, and verifies the OAuth token
+//! file. Each run reports a `maintenance/completed` event with a summary.
+//!
+//! Settings and the last run are kept in `~/.harbor/maintenance.json`.
+
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use chrono::Timelike;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::events;
+use crate::rpc::RpcError;
+use crate::store::{JsonStore, Stored};
+
+/// How often the scheduler checks whether a run is due.
+const CHECK_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// Scheduler settings.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MaintenanceConfig {
+    pub enabled: bool,
+    /// Minimum hours between runs
+    pub interval_hours: u32,
+    /// First local hour (0-23) in which a run may start
+    pub window_start_hour: u32,
+    /// Local hour at which the window closes; may wrap past midnight
+    pub window_end_hour: u32,
+    /// Days of tool call history to keep
+    pub history_retention_days: i64,
+    /// Rotate the log once it grows past this size
+    pub max_log_bytes: u64,
+    /// Rotated log files to keep
+    pub log_files_kept: u32,
+}
+
+impl Default for MaintenanceConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval_hours: 24,
+            window_start_hour: 3,
+            window_end_hour: 6,
+            history_retention_days: 90,
+            max_log_bytes: 10 * 1024 * 1024,
+            log_files_kept: 3,
+        }
+    }
+}
+
+impl MaintenanceConfig {
+    /// Whether `hour` (local) is inside the run window.
+    fn in_window(&self, hour: u32) -> bool {
+        let (start, end) = (self.window_start_hour, self.window_end_hour);
+        if start == end {
+            true
+        } else if start < end {
+            (start..end).contains(&hour)
+        } else {
+            hour >= start || hour < end
+        }
+    }
+}
+
+/// Outcome of one maintenance task.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskResult {
+    pub task: String,
+    pub ok: bool,
+    pub detail: String,
+}
+
+/// Summary of a maintenance run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunSummary {
+    /// When the run started (Unix timestamp ms)
+    pub started_at: i64,
+    pub duration_ms: u64,
+    /// `schedule`, `resume` or `manual`
+    pub trigger: String,
+    pub tasks: Vec<TaskResult>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct MaintenanceFile {
+    #[serde(default)]
+    config: MaintenanceConfig,
+    #[serde(default)]
+    last_run: Option<RunSummary>,
+}
+
+impl Stored for MaintenanceFile {
+    const FILE_NAME: &'static str = "maintenance.json";
+}
+
+/// Maintenance subsystem state.
+#[derive(Default)]
+pub struct MaintenanceState {
+    /// Settings and last run, loaded on first use
+    file: JsonStore<MaintenanceFile>,
+    /// Held while a run is in progress
+    running: Mutex<()>,
+}
+
+fn state() -> &'static MaintenanceState {
+    &crate::state::get().maintenance
+}
+
+/// Path to the bridge's log file in native messaging mode.
+pub fn log_path() -> PathBuf {
+    dirs::cache_dir()
+        .unwrap_or_else(|| PathBuf::from("/tmp"))
+        .join("harbor-bridge.log")
+}
+
+/// Start the scheduler. Must be called from within the tokio runtime.
+pub fn start() {
+    tokio::spawn(async {
+        let mut interval = tokio::time::interval(CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            run_if_due("schedule").await;
+        }
+    });
+}
+
+/// Run maintenance if a run is due and the window is open. Returns whether
+/// it ran.
+pub async fn run_if_due(trigger: &str) -> bool {
+    if !is_due().await {
+        return false;
+    }
+    run(trigger).await;
+    true
+}
+
+async fn is_due() -> bool {
+    let hour = chrono::Local::now().hour();
+    let now = chrono::Utc::now().timestamp_millis();
+    state()
+        .file
+        .read(|file| {
+            let config = &file.config;
+            let interval_ms = i64::from(config.interval_hours) * 3600 * 1000;
+            let elapsed = file.last_run.as_ref().map_or(i64::MAX, |r| now - r.started_at);
+            config.enabled && config.in_window(hour) && elapsed >= interval_ms
+        })
+        .await
+}
+
+/// Run every maintenance task, record the summary and emit it.
+async fn run(trigger: &str) -> RunSummary {
+    let _running = state().running.lock().await;
+    let config = state().file.read(|file| file.config.clone()).await;
+
+    let started_at = chrono::Utc::now().timestamp_millis();
+    let started = Instant::now();
+    tracing::info!("Starting maintenance run ({})", trigger);
+
+    let mut tasks = Vec::new();
+
+    let retain_days = config.history_retention_days;
+    let compacted = tokio::task::spawn_blocking(move || crate::history::compact(retain_days))
+        .await
+        .unwrap_or_else(|e| Err(e.to_string()));
+    tasks.push(task_result("compact_history", compacted.map(|n| format!("Dropped {} entries", n))));
+
+    let rotated = tokio::task::spawn_blocking(move || rotate_log(config.max_log_bytes, config.log_files_kept))
+        .await
+        .unwrap_or_else(|e| Err(e.to_string()));
+    tasks.push(task_result(
+        "rotate_log",
+        rotated.map(|rotated| if rotated { "Rotated" } else { "Below size limit" }.to_string()),
+    ));
+
+    let pruned = crate::auth::aws::prune_sessions().await;
+    tasks.push(task_result("prune_aws_sessions", Ok(format!("Removed {} sessions", pruned))));
+
+    let grants = crate::fs::prune_expired_grants().await;
+    tasks.push(task_result("prune_fs_grants", grants.map(|n| format!("Removed {} grants", n))));
+
+    let tokens = crate::oauth::storage::TokenStore::verify();
+    tasks.push(task_result("verify_token_file", tokens.map(|n| format!("{} tokens ok", n))));
+
+    let summary = RunSummary {
+        started_at,
+        duration_ms: started.elapsed().as_millis() as u64,
+        trigger: trigger.to_string(),
+        tasks,
+    };
+
+    for task in summary.tasks.iter().filter(|t| !t.ok) {
+        tracing::warn!("Maintenance task {} failed: {}", task.task, task.detail);
+    }
+    state()
+        .file
+        .update(|file| {
+            file.last_run = Some(summary.clone());
+        })
+        .await;
+    events::emit(
+        "maintenance/completed",
+        serde_json::to_value(&summary).unwrap_or_default(),
+    );
+
+    summary
+}
+
+fn task_result(task: &str, result: Result<String, String>) -> TaskResult {
+    let ok = result.is_ok();
+    TaskResult {
+        task: task.to_string(),
+        ok,
+        detail: result.unwrap_or_else(|e| e),
+    }
+}
+
+/// Rotate the log if it has grown past `max_bytes`: `harbor-bridge.log` is
+/// copied to `.1` (shifting older files up to `kept`) and truncated in place,
+/// since the logger keeps the file open for appending.
+fn rotate_log(max_bytes: u64, kept: u32) -> Result<bool, String> {
+    let path = log_path();
+    let size = match std::fs::metadata(&path) {
+        Ok(metadata) => metadata.len(),
+        Err(_) => return Ok(false),
+    };
+    if size <= max_bytes || kept == 0 {
+        return Ok(false);
+    }
+
+    let rotated = |n: u32| path.with_extension(format!("log.{}", n));
+    let _ = std::fs::remove_file(rotated(kept));
+    for n in (1..kept).rev() {
+        let _ = std::fs::rename(rotated(n), rotated(n + 1));
+    }
+    std::fs::copy(&path, rotated(1)).map_err(|e| format!("Failed to copy log: {}", e))?;
+    std::fs::OpenOptions::new()
+        .write(true)
+        .open(&path)
+        .and_then(|file| file.set_len(0))
+        .map_err(|e| format!("Failed to truncate log: {}", e))?;
+    Ok(true)
+}
+
+// ============================================================================
+// RPC Handlers
+// ============================================================================
+
+/// Settings and the last run's summary.
+pub async fn rpc_status(_params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    let running = state().running.try_lock().is_err();
+    state()
+        .file
+        .read(|file| {
+            Ok(serde_json::json!({
+                "config": file.config,
+                "last_run": file.last_run,
+                "running": running,
+            }))
+        })
+        .await
+}
+
+/// Update settings; fields not given keep their current values.
+pub async fn rpc_configure(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    crate::rpc::require_extension("configure maintenance")?;
+    state()
+        .file
+        .update(|file| {
+            let mut merged = serde_json::to_value(&file.config).map_err(|e| RpcError::internal(e.to_string()))?;
+            if let (Some(merged), Some(updates)) = (merged.as_object_mut(), params.as_object()) {
+                for (key, value) in updates {
+                    merged.insert(key.clone(), value.clone());
+                }
+            }
+            let config: MaintenanceConfig = serde_json::from_value(merged)
+                .map_err(|e| RpcError::invalid_params(format!("Invalid maintenance settings: {}", e)))?;
+            if config.window_start_hour > 23 || config.window_end_hour > 23 {
+                return Err(RpcError::invalid_params("Window hours must be between 0 and 23"));
+            }
+
+            file.config = config;
+            Ok(serde_json::json!({ "config": file.config }))
+        })
+        .await
+}
+
+/// Run maintenance now, regardless of the window.
+pub async fn rpc_run(_params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    crate::rpc::require_extension("run maintenance")?;
+    let summary = run("manual").await;
+    serde_json::to_value(summary).map_err(|e| RpcError::internal(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_window_wraps_past_midnight() {
+        let config = MaintenanceConfig {
+            window_start_hour: 22,
+            window_end_hour: 2,
+            ..Default::default()
+        };
+        assert!(config.in_window(23));
+        assert!(config.in_window(1));
+        assert!(!config.in_window(2));
+        assert!(!config.in_window(12));
+
+        let daytime = MaintenanceConfig {
+            window_start_hour: 9,
+            window_end_hour: 17,
+            ..Default::default()
+        };
+        assert!(daytime.in_window(9));
+        assert!(!daytime.in_window(17));
+    }
+}
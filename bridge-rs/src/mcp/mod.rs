@@ -5,11 +5,17 @@
 //! This module maintains a registry of tools that Harbor syncs to the bridge,
 //! allowing Web Agents to query available tools.
 
+pub mod pause;
+pub mod timeout;
+
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::OnceLock;
 use tokio::sync::RwLock;
 
+use crate::catalog;
+use crate::history::{self, HistoryEntry};
+use crate::metrics::{self, CallTimings};
 use crate::rpc::RpcError;
 
 /// A registered MCP tool
@@ -24,6 +30,22 @@ pub struct RegisteredTool {
     pub input_schema: Option<serde_json::Value>,
 }
 
+/// Whether a tool call's JSON-RPC response, or `call_tool`'s output,
+/// reports a failure: an error, or a result marked `isError`.
+pub fn call_failed(response: &serde_json::Value) -> bool {
+    let marked = |pointer| response.pointer(pointer).and_then(|v| v.as_bool()) == Some(true);
+    response.get("error").is_some() || marked("/result/isError") || marked("/isError")
+}
+
+/// `call_tool`'s output, marked `isError` if the tool's result was.
+fn tool_output(result: serde_json::Value, meta: serde_json::Value, is_error: bool) -> serde_json::Value {
+    let mut output = serde_json::json!({ "result": result, "_meta": meta });
+    if is_error {
+        output["isError"] = serde_json::json!(true);
+    }
+    output
+}
+
 /// Global tool registry
 fn tool_registry() -> &'static RwLock<HashMap<String, RegisteredTool>> {
     static REGISTRY: OnceLock<RwLock<HashMap<String, RegisteredTool>>> = OnceLock::new();
@@ -34,7 +56,10 @@ fn tool_registry() -> &'static RwLock<HashMap<String, RegisteredTool>> {
 #[derive(Debug, Deserialize)]
 pub struct RegisterToolsParams {
     pub server_id: String,
-    pub tools: Vec<ToolInfo>,
+    /// Server version, recorded in the tool catalog
+    #[serde(default)]
+    pub version: Option<String>,
+    pub tools: serde_json::Value,
 }
 
 #[derive(Debug, Deserialize)]
@@ -46,25 +71,54 @@ pub struct ToolInfo {
     pub input_schema: Option<serde_json::Value>,
 }
 
+/// Register a server's full tool list, replacing its previous tools.
+///
+/// A list that breaks tools used by scheduled jobs may be held by the tool
+/// catalog until the user confirms it; the previous tools stay registered
+/// meanwhile.
 pub async fn register_tools(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
     let params: RegisterToolsParams = serde_json::from_value(params).map_err(|e| RpcError {
         code: -32602,
         message: format!("Invalid params: {}", e),
     })?;
+    let tools: Vec<ToolInfo> = serde_json::from_value(params.tools.clone()).map_err(|e| RpcError {
+        code: -32602,
+        message: format!("Invalid params: {}", e),
+    })?;
+    
+    let schemas = tools
+        .iter()
+        .map(|t| (t.name.clone(), t.input_schema.clone().unwrap_or_default()))
+        .collect();
+    let observed = catalog::observe(&params.server_id, params.version, schemas, params.tools, true).await;
+    if let catalog::Observed::Held(change) = observed {
+        return Ok(serde_json::json!({ "ok": false, "held": true, "change": change }));
+    }
     
+    insert_tools(&params.server_id, tools).await;
+    Ok(serde_json::json!({ "ok": true }))
+}
+
+/// Replace a server's registered tools with a list in registration format.
+pub async fn replace_tools(server_id: &str, tools: serde_json::Value) -> Result<(), String> {
+    let tools: Vec<ToolInfo> = serde_json::from_value(tools).map_err(|e| format!("Invalid tools: {}", e))?;
+    insert_tools(server_id, tools).await;
+    Ok(())
+}
+
+async fn insert_tools(server_id: &str, tools: Vec<ToolInfo>) {
     let mut registry = tool_registry().write().await;
     
-    for tool in params.tools {
-        let full_name = format!("{}/{}", params.server_id, tool.name);
-        registry.insert(full_name.clone(), RegisteredTool {
-            server_id: params.server_id.clone(),
+    registry.retain(|_, tool| tool.server_id != server_id);
+    for tool in tools {
+        let full_name = format!("{}/{}", server_id, tool.name);
+        registry.insert(full_name, RegisteredTool {
+            server_id: server_id.to_string(),
             name: tool.name,
             description: tool.description,
             input_schema: tool.input_schema,
         });
     }
-    
-    Ok(serde_json::json!({ "ok": true }))
 }
 
 /// Unregister tools from a server
@@ -87,11 +141,22 @@ pub async fn unregister_tools(params: serde_json::Value) -> Result<serde_json::V
     Ok(serde_json::json!({ "ok": true }))
 }
 
-/// List all registered tools
+/// Look up a registered tool.
+pub async fn find_tool(server_id: &str, name: &str) -> Option<RegisteredTool> {
+    tool_registry().read().await.get(&format!("{}/{}", server_id, name)).cloned()
+}
+
+/// Whether any tools are registered for a server.
+pub async fn has_server(server_id: &str) -> bool {
+    tool_registry().read().await.values().any(|t| t.server_id == server_id)
+}
+
+/// List all registered tools, and the composite tools defined in config,
+/// leaving out paused servers
 pub async fn list_tools() -> Result<serde_json::Value, RpcError> {
-    let registry = tool_registry().read().await;
-    
-    let tools: Vec<RegisteredTool> = registry.values().cloned().collect();
+    let mut tools: Vec<RegisteredTool> = tool_registry().read().await.values().cloned().collect();
+    tools.extend(crate::composite::registered_tools().await);
+    tools.retain(|tool| !pause::is_paused(&tool.server_id));
     
     Ok(serde_json::json!({ "tools": tools }))
 }
@@ -116,6 +181,9 @@ pub struct PendingToolCall {
     #[serde(skip)]
     #[allow(dead_code)]
     pub created_at: Instant,
+    /// When Harbor first picked the call up
+    #[serde(skip)]
+    pub polled_at: Option<Instant>,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -123,6 +191,8 @@ pub struct ToolCallResult {
     pub call_id: String,
     pub result: Option<serde_json::Value>,
     pub error: Option<String>,
+    #[serde(skip)]
+    pub submitted_at: Option<Instant>,
 }
 
 fn pending_calls() -> &'static RwLock<HashMap<String, PendingToolCall>> {
@@ -144,42 +214,136 @@ pub struct CallToolParams {
     pub tool_name: String,
     #[serde(default)]
     pub args: serde_json::Value,
+    /// Profile the call is made on behalf of; see `profiles`. A call made
+    /// by a server acts for the server's own profile.
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// Overrides the default timeout; see `timeout`
+    #[serde(default, alias = "timeoutMs")]
+    pub timeout_ms: Option<u64>,
+}
+
+/// Call a tool on a WASM component server running in the bridge.
+async fn call_component_tool(params: CallToolParams, timeout: Duration) -> Result<serde_json::Value, RpcError> {
+    let started_at = chrono::Utc::now().timestamp_millis();
+    let start = Instant::now();
+    let token = crate::peer::open(&params.server_id).await;
+    let request = serde_json::json!({
+        "method": "tools/call",
+        "params": {
+            "name": params.tool_name,
+            "arguments": params.args,
+            "_meta": { "harbor/peerToken": token, "harbor/peerChain": [params.server_id] },
+        },
+    });
+    crate::chaos::tool_latency(&params.server_id).await;
+    let called = timeout::limit(&params.server_id, timeout, crate::wasm::call(&params.server_id, &request)).await;
+    crate::peer::close(&token).await;
+    let outcome = called.and_then(|response| match response.get("error") {
+            Some(error) => Err(RpcError::new(
+                -32000,
+                error
+                    .get("message")
+                    .and_then(|m| m.as_str())
+                    .unwrap_or("Tool call failed"),
+            )),
+            None => Ok(response.get("result").cloned().unwrap_or(serde_json::Value::Null)),
+        });
+    history::record(HistoryEntry::new(
+        &params.server_id,
+        &params.tool_name,
+        started_at,
+        start.elapsed().as_millis() as u64,
+        outcome.as_ref().map_err(|e| e.message.as_str()),
+        &params.args,
+    ));
+    let result = outcome?;
+
+    // Same shape as JS servers: the first text block, or the raw content
+    let meta = result.get("_meta").cloned().unwrap_or(serde_json::Value::Null);
+    let is_error = result.get("isError").and_then(|v| v.as_bool()) == Some(true);
+    let content = result.get("content").cloned().unwrap_or(result);
+    let text = content
+        .as_array()
+        .and_then(|blocks| blocks.first())
+        .and_then(|first| first.get("text"))
+        .cloned();
+    Ok(tool_output(text.unwrap_or(content), meta, is_error))
 }
 
 pub async fn call_tool(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
-    let params: CallToolParams = serde_json::from_value(params).map_err(|e| RpcError {
+    let received = Instant::now();
+    let mut params: CallToolParams = serde_json::from_value(params).map_err(|e| RpcError {
         code: -32602,
         message: format!("Invalid params: {}", e),
     })?;
+    pause::check(&params.server_id)?;
+    let timeout = timeout::resolve(params.timeout_ms)?;
+    let acting = crate::profiles::acting(params.profile.take()).await?;
     
-    // First, try calling via JS runtime (works for JS servers)
+    if params.server_id == crate::composite::SERVER_ID {
+        let run = crate::composite::run(params.tool_name, params.args);
+        return timeout::limit(&params.server_id, timeout, crate::profiles::scope(acting, run)).await;
+    }
+    crate::profiles::scope(acting, async move {
+        crate::profiles::enter(&params.server_id).await?;
+        call_server_tool(params, timeout, received).await
+    })
+    .await
+}
+
+/// Call a tool on a server, within the caller's profile flow.
+async fn call_server_tool(
+    params: CallToolParams,
+    timeout: Duration,
+    received: Instant,
+) -> Result<serde_json::Value, RpcError> {
+    if crate::wasm::is_running(&params.server_id).await {
+        return call_component_tool(params, timeout).await;
+    }
+    
+    // First, try calling via JS runtime (works for JS servers). The peer
+    // token carries the flow into any peer calls the server makes meanwhile.
+    let token = crate::peer::open(&params.server_id).await;
     let js_request = serde_json::json!({
         "id": params.server_id,
         "request": {
             "method": "tools/call",
             "params": {
                 "name": params.tool_name,
-                "arguments": params.args
+                "arguments": params.args,
+                "_meta": { "harbor/peerToken": token, "harbor/peerChain": [params.server_id] },
             }
-        }
+        },
+        "timeout_ms": timeout.as_millis() as u64,
     });
+    let called = crate::js::call_server(js_request).await;
+    crate::peer::close(&token).await;
     
-    match crate::js::call_server(js_request).await {
+    match called {
         Ok(result) => {
             // JS server call succeeded
-            // Extract the result from the MCP response
+            // Extract the result from the MCP response, keeping its _meta (timing)
+            let meta = result
+                .get("result")
+                .and_then(|r| r.get("_meta"))
+                .cloned()
+                .unwrap_or(serde_json::Value::Null);
+            let is_error = call_failed(&result);
             if let Some(content) = result.get("result").and_then(|r| r.get("content")) {
                 if let Some(arr) = content.as_array() {
                     if let Some(first) = arr.first() {
                         if let Some(text) = first.get("text") {
-                            return Ok(serde_json::json!({ "result": text }));
+                            return Ok(tool_output(text.clone(), meta, is_error));
                         }
                     }
                 }
-                return Ok(serde_json::json!({ "result": content }));
+                return Ok(tool_output(content.clone(), meta, is_error));
             }
-            Ok(serde_json::json!({ "result": result }))
+            Ok(tool_output(result, meta, is_error))
         }
+        // The server was found but didn't answer in time
+        Err(e) if e.code == timeout::TOOL_TIMEOUT => Err(e),
         Err(_) => {
             // JS call failed - queue for Harbor to handle (WASM servers)
             let call_id = format!("call-{}", CALL_COUNTER.fetch_add(1, Ordering::SeqCst));
@@ -190,37 +354,81 @@ pub async fn call_tool(params: serde_json::Value) -> Result<serde_json::Value, R
                 tool_name: params.tool_name,
                 args: params.args,
                 created_at: Instant::now(),
+                polled_at: None,
             };
             
+            let server_id = pending.server_id.clone();
+            let tool_name = pending.tool_name.clone();
+            let args = pending.args.clone();
             pending_calls().write().await.insert(call_id.clone(), pending);
             
             // Wait for result with timeout
-            let timeout = Duration::from_secs(30);
+            let started_at = chrono::Utc::now().timestamp_millis();
             let start = Instant::now();
+            let mut polled_at = None;
+            let mut submitted_at = None;
+            let mut timed_out = false;
             
-            loop {
+            let outcome = loop {
                 tokio::time::sleep(Duration::from_millis(100)).await;
                 
                 if let Some(result) = call_results().write().await.remove(&call_id) {
                     // Clean up pending
-                    pending_calls().write().await.remove(&call_id);
+                    if let Some(pending) = pending_calls().write().await.remove(&call_id) {
+                        polled_at = pending.polled_at;
+                    }
+                    submitted_at = result.submitted_at;
                     
-                    if let Some(err) = result.error {
-                        return Err(RpcError {
-                            code: -32000,
-                            message: err,
-                        });
+                    match result.error {
+                        Some(err) => break Err(err),
+                        None => break Ok(result.result.unwrap_or(serde_json::Value::Null)),
                     }
-                    return Ok(serde_json::json!({ "result": result.result }));
                 }
                 
                 if start.elapsed() > timeout {
                     pending_calls().write().await.remove(&call_id);
-                    return Err(RpcError {
-                        code: -32000,
-                        message: "Tool call timed out waiting for Harbor".to_string(),
-                    });
+                    timed_out = true;
+                    break Err(timeout::error(&server_id, timeout).message);
                 }
+            };
+            let round_trip = start.elapsed();
+            let post_start = Instant::now();
+            
+            history::record(HistoryEntry::new(
+                &server_id,
+                &tool_name,
+                started_at,
+                round_trip.as_millis() as u64,
+                outcome.as_ref().map_err(|e| e.as_str()),
+                &args,
+            ));
+            
+            // Queue wait lasts until Harbor polls the call; execution until it submits
+            let queue_wait = polled_at.map(|t| t.saturating_duration_since(start)).unwrap_or(round_trip);
+            let server_time = match (polled_at, submitted_at) {
+                (Some(polled), Some(submitted)) => submitted.saturating_duration_since(polled),
+                _ => Duration::ZERO,
+            };
+            let mut timings = CallTimings {
+                policy_ms: metrics::ms(start - received),
+                queue_wait_ms: metrics::ms(queue_wait),
+                server_ms: metrics::ms(server_time),
+                transport_ms: metrics::ms(round_trip.saturating_sub(queue_wait + server_time)),
+                ..Default::default()
+            };
+            timings.post_processing_ms = metrics::ms(post_start.elapsed());
+            timings.total_ms = metrics::ms(received.elapsed());
+            metrics::record(&server_id, &tool_name, &timings);
+            
+            match outcome {
+                Ok(result) => Ok(serde_json::json!({
+                    "result": result,
+                    "_meta": { "timing": timings },
+                })),
+                Err(message) => Err(RpcError {
+                    code: if timed_out { timeout::TOOL_TIMEOUT } else { -32000 },
+                    message,
+                }),
             }
         }
     }
@@ -228,7 +436,11 @@ pub async fn call_tool(params: serde_json::Value) -> Result<serde_json::Value, R
 
 /// Get pending tool calls (called by Harbor to execute WASM tools)
 pub async fn poll_pending_calls() -> Result<serde_json::Value, RpcError> {
-    let pending = pending_calls().read().await;
+    let mut pending = pending_calls().write().await;
+    let now = Instant::now();
+    for call in pending.values_mut() {
+        call.polled_at.get_or_insert(now);
+    }
     let calls: Vec<&PendingToolCall> = pending.values().collect();
     Ok(serde_json::json!({ "calls": calls }))
 }
@@ -251,6 +463,7 @@ pub async fn submit_call_result(params: serde_json::Value) -> Result<serde_json:
         call_id: params.call_id.clone(),
         result: params.result,
         error: params.error,
+        submitted_at: Some(Instant::now()),
     };
     
     call_results().write().await.insert(params.call_id, result);
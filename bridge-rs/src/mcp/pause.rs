@@ -0,0 +1,105 @@
+//! Paused servers.
+//!
+//! Pausing silences a server without stopping it: it stays resident, with
+//! its warm state and file watches, but tool calls to it are rejected with
+//! `SERVER_PAUSED` and its tools are left out of tool lists until it is
+//! resumed. Other requests (`initialize`, `ping`) still reach it. Pauses
+//! aren't persisted; a restarted bridge starts with every server active.
+
+use std::collections::HashSet;
+use std::sync::{OnceLock, RwLock};
+
+use crate::rpc::RpcError;
+
+/// Error code for tool calls to a paused server.
+pub const SERVER_PAUSED: i64 = -32040;
+
+fn paused() -> &'static RwLock<HashSet<String>> {
+    static PAUSED: OnceLock<RwLock<HashSet<String>>> = OnceLock::new();
+    PAUSED.get_or_init(|| RwLock::new(HashSet::new()))
+}
+
+/// Whether a server is paused.
+pub fn is_paused(server_id: &str) -> bool {
+    paused().read().unwrap().contains(server_id)
+}
+
+/// Fail with `SERVER_PAUSED` if the server is paused.
+pub fn check(server_id: &str) -> Result<(), RpcError> {
+    if is_paused(server_id) {
+        return Err(RpcError::new(
+            SERVER_PAUSED,
+            format!("server_paused: Server '{}' is paused; resume it to call its tools", server_id),
+        ));
+    }
+    Ok(())
+}
+
+/// The JSON-RPC answer to `tools/list` for a paused server: no tools.
+pub fn empty_tools_list(request: &serde_json::Value) -> serde_json::Value {
+    serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": request.get("id").cloned().unwrap_or(serde_json::Value::Null),
+        "result": { "tools": [] },
+    })
+}
+
+fn server_id_param(params: &serde_json::Value) -> Result<String, RpcError> {
+    params
+        .get("server_id")
+        .or_else(|| params.get("serverId"))
+        .and_then(|v| v.as_str())
+        .map(String::from)
+        .ok_or_else(|| RpcError::invalid_params("Missing 'server_id' parameter"))
+}
+
+// ============================================================================
+// RPC Handlers
+// ============================================================================
+
+/// Pause a server: `{ server_id }`.
+pub async fn rpc_pause(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    crate::rpc::require_extension("pause or resume servers")?;
+    let server_id = server_id_param(&params)?;
+    if paused().write().unwrap().insert(server_id.clone()) {
+        tracing::info!("Paused server: {}", server_id);
+        crate::events::emit("server/paused", serde_json::json!({ "server_id": server_id }));
+    }
+    Ok(serde_json::json!({ "server_id": server_id, "status": "paused" }))
+}
+
+/// Resume a paused server: `{ server_id }`.
+pub async fn rpc_resume(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    crate::rpc::require_extension("pause or resume servers")?;
+    let server_id = server_id_param(&params)?;
+    if paused().write().unwrap().remove(&server_id) {
+        tracing::info!("Resumed server: {}", server_id);
+        crate::events::emit("server/resumed", serde_json::json!({ "server_id": server_id }));
+    }
+    Ok(serde_json::json!({ "server_id": server_id, "status": "active" }))
+}
+
+/// List paused servers.
+pub async fn rpc_list(_params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    let mut servers: Vec<String> = paused().read().unwrap().iter().cloned().collect();
+    servers.sort();
+    Ok(serde_json::json!({ "servers": servers }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_pause_and_resume() {
+        crate::rpc::as_extension(rpc_pause(serde_json::json!({ "server_id": "noisy" }))).await.unwrap();
+        assert_eq!(check("noisy").unwrap_err().code, SERVER_PAUSED);
+        assert!(check("quiet").is_ok());
+
+        let listed = rpc_list(serde_json::json!({})).await.unwrap();
+        assert!(listed["servers"].as_array().unwrap().contains(&serde_json::json!("noisy")));
+
+        crate::rpc::as_extension(rpc_resume(serde_json::json!({ "serverId": "noisy" }))).await.unwrap();
+        assert!(check("noisy").is_ok());
+    }
+}
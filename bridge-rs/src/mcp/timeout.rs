@@ -0,0 +1,106 @@
+//! Per-call timeouts for tool calls.
+//!
+//! Every `tools/call` runs against a wall-clock deadline: the bridge-wide
+//! default (30 s unless set with `mcp.set_tool_timeout`), or `timeout_ms`
+//! passed with the call. A call that misses it fails with `TOOL_TIMEOUT`,
+//! and the server is left able to take the next call: WASM components yield
+//! to the runtime on every epoch tick, so the call is dropped mid-run and
+//! the instance recreated; JS servers are interrupted at the deadline, and
+//! restarted from their definition only if they still don't answer; calls
+//! forwarded to the extension are abandoned. The default isn't persisted.
+
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use crate::rpc::RpcError;
+
+/// Error code for a tool call that didn't finish in time.
+pub const TOOL_TIMEOUT: i64 = -32001;
+
+const DEFAULT_TIMEOUT_MS: u64 = 30_000;
+
+/// Longest timeout a call or the default may be set to.
+const MAX_TIMEOUT_MS: u64 = 600_000;
+
+static DEFAULT_MS: AtomicU64 = AtomicU64::new(DEFAULT_TIMEOUT_MS);
+
+/// The timeout for a call: `timeout_ms` if given, else the default.
+pub fn resolve(timeout_ms: Option<u64>) -> Result<Duration, RpcError> {
+    match timeout_ms {
+        Some(0) => Err(RpcError::invalid_params("'timeout_ms' must be positive")),
+        Some(ms) if ms > MAX_TIMEOUT_MS => Err(RpcError::invalid_params(format!(
+            "'timeout_ms' may be at most {}",
+            MAX_TIMEOUT_MS
+        ))),
+        Some(ms) => Ok(Duration::from_millis(ms)),
+        None => Ok(Duration::from_millis(DEFAULT_MS.load(Ordering::Relaxed))),
+    }
+}
+
+/// The `TOOL_TIMEOUT` error for a call to `server_id`.
+pub fn error(server_id: &str, timeout: Duration) -> RpcError {
+    tracing::warn!("Tool call to {} timed out after {}ms", server_id, timeout.as_millis());
+    RpcError::new(
+        TOOL_TIMEOUT,
+        format!(
+            "tool timeout: '{}' did not answer within {}ms",
+            server_id,
+            timeout.as_millis()
+        ),
+    )
+}
+
+/// Run a call to `server_id`, failing with `TOOL_TIMEOUT` if it takes
+/// longer than `timeout`. The call is dropped at the deadline.
+pub async fn limit<T>(
+    server_id: &str,
+    timeout: Duration,
+    call: impl Future<Output = Result<T, RpcError>>,
+) -> Result<T, RpcError> {
+    tokio::time::timeout(timeout, call)
+        .await
+        .unwrap_or_else(|_| Err(error(server_id, timeout)))
+}
+
+// ============================================================================
+// RPC Handlers
+// ============================================================================
+
+/// Set the default tool call timeout: `{ timeout_ms }`.
+pub async fn rpc_set_default(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    crate::rpc::require_extension("change the tool timeout")?;
+    let timeout_ms = params
+        .get("timeout_ms")
+        .and_then(|v| v.as_u64())
+        .ok_or_else(|| RpcError::invalid_params("Missing 'timeout_ms' parameter"))?;
+    let timeout = resolve(Some(timeout_ms))?;
+    DEFAULT_MS.store(timeout_ms, Ordering::Relaxed);
+    Ok(serde_json::json!({ "timeout_ms": timeout.as_millis() as u64 }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve() {
+        assert_eq!(resolve(Some(1500)).unwrap(), Duration::from_millis(1500));
+        assert!(resolve(Some(0)).is_err());
+        assert!(resolve(Some(MAX_TIMEOUT_MS + 1)).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_limit() {
+        let slow = async {
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            Ok(())
+        };
+        let error = limit("slow", Duration::from_millis(10), slow).await.unwrap_err();
+        assert_eq!(error.code, TOOL_TIMEOUT);
+        assert!(error.message.starts_with("tool timeout"));
+
+        let fast = limit("fast", Duration::from_secs(1), async { Ok(7) }).await;
+        assert_eq!(fast.unwrap(), 7);
+    }
+}
@@ -0,0 +1,117 @@
+//! Tool call latency metrics.
+//!
+//! Each tool call is broken into phases so slow calls can be attributed:
+//!
+//! - `policy`: validating and routing the call before dispatch
+//! - `queue_wait`: waiting for the server (JS request channel, or the
+//!   extension polling the WASM call queue)
+//! - `server`: executing in the server
+//! - `transport`: moving the request and result between the bridge and the
+//!   server, beyond the phases above
+//! - `post_processing`: recording and shaping the result
+//!
+//! The breakdown is attached to the result's `_meta.timing` and aggregated
+//! per tool for `metrics.latency`.
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::rpc::RpcError;
+
+/// Where a single tool call spent its time, in milliseconds.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct CallTimings {
+    pub queue_wait_ms: f64,
+    pub policy_ms: f64,
+    pub transport_ms: f64,
+    pub server_ms: f64,
+    pub post_processing_ms: f64,
+    pub total_ms: f64,
+}
+
+/// Convert a duration to fractional milliseconds.
+pub fn ms(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+/// Aggregated timings for one tool.
+#[derive(Debug, Clone, Default, Serialize)]
+struct ToolLatency {
+    calls: u64,
+    /// Sum of each phase across calls
+    total: CallTimings,
+    max_total_ms: f64,
+}
+
+lazy_static::lazy_static! {
+    /// Aggregates keyed by "server_id/tool"
+    static ref LATENCY: Mutex<BTreeMap<String, ToolLatency>> = Mutex::new(BTreeMap::new());
+}
+
+/// Add a call's timings to the aggregates.
+pub fn record(server_id: &str, tool: &str, timings: &CallTimings) {
+    let mut latency = LATENCY.lock().unwrap();
+    let entry = latency.entry(format!("{}/{}", server_id, tool)).or_default();
+    entry.calls += 1;
+    entry.total.queue_wait_ms += timings.queue_wait_ms;
+    entry.total.policy_ms += timings.policy_ms;
+    entry.total.transport_ms += timings.transport_ms;
+    entry.total.server_ms += timings.server_ms;
+    entry.total.post_processing_ms += timings.post_processing_ms;
+    entry.total.total_ms += timings.total_ms;
+    entry.max_total_ms = entry.max_total_ms.max(timings.total_ms);
+}
+
+/// Attach timings to a tool result under `_meta.timing`.
+/// Non-object results are left untouched.
+pub fn attach(result: &mut serde_json::Value, timings: &CallTimings) {
+    if let Some(obj) = result.as_object_mut() {
+        let meta = obj
+            .entry("_meta")
+            .or_insert_with(|| serde_json::json!({}));
+        if let Some(meta) = meta.as_object_mut() {
+            meta.insert("timing".to_string(), serde_json::json!(timings));
+        }
+    }
+}
+
+/// Average phase timings per tool since the bridge started.
+pub async fn rpc_latency(_params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    let latency = LATENCY.lock().unwrap();
+    let tools: BTreeMap<&String, serde_json::Value> = latency
+        .iter()
+        .map(|(key, stats)| {
+            let n = stats.calls as f64;
+            (key, serde_json::json!({
+                "calls": stats.calls,
+                "max_total_ms": stats.max_total_ms,
+                "avg": CallTimings {
+                    queue_wait_ms: stats.total.queue_wait_ms / n,
+                    policy_ms: stats.total.policy_ms / n,
+                    transport_ms: stats.total.transport_ms / n,
+                    server_ms: stats.total.server_ms / n,
+                    post_processing_ms: stats.total.post_processing_ms / n,
+                    total_ms: stats.total.total_ms / n,
+                },
+            }))
+        })
+        .collect();
+
+    Ok(serde_json::json!({ "tools": tools }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_attach_preserves_existing_meta() {
+        let mut result = serde_json::json!({ "content": [], "_meta": { "source": "test" } });
+        attach(&mut result, &CallTimings { total_ms: 5.0, ..Default::default() });
+        assert_eq!(result["_meta"]["source"], "test");
+        assert_eq!(result["_meta"]["timing"]["total_ms"], 5.0);
+    }
+}
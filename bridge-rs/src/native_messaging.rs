@@ -8,13 +8,26 @@
 //! - `rpc_stream`: Streaming RPC request, sends multiple `stream` messages
 //! - `ping`: Health check, responds with `status`
 //! - `shutdown`: Graceful shutdown request
+//!
+//! The bridge also pushes `console` (JS server logs) and `event` (see
+//! `crate::events`) messages unprompted.
 
 use std::io::{self, Read, Write};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::{broadcast, mpsc};
 
+use crate::chaos::{self, Fault};
+use crate::events::{self, Event};
 use crate::llm;
 use crate::rpc::{self, RpcRequest};
+use crate::watchdog;
+
+/// How long a single stdout write may block before the watchdog steps in.
+const WRITER_STALL_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long the router may leave an incoming message undelivered.
+const ROUTER_STALL_TIMEOUT: Duration = Duration::from_secs(30);
 
 /// Message from the browser extension
 #[derive(Debug, serde::Deserialize)]
@@ -27,6 +40,9 @@ struct IncomingMessage {
     method: Option<String>,
     #[serde(default)]
     params: serde_json::Value,
+    /// MCP server the extension is forwarding the request for
+    #[serde(default)]
+    caller: Option<String>,
 }
 
 /// Message to the browser extension
@@ -142,6 +158,13 @@ impl MessageWriter {
         })).await;
     }
 
+    async fn send_event(&self, event: &Event) {
+        self.send("event", serde_json::json!({
+            "event": event.event,
+            "payload": event.payload,
+        })).await;
+    }
+
     async fn send_console_log(&self, log: &ConsoleLogMessage) {
         self.send("console", serde_json::json!({
             "server_id": log.server_id,
@@ -159,14 +182,21 @@ pub async fn run_native_messaging() {
     let (writer, mut write_rx) = MessageWriter::new();
     let writer = Arc::new(writer);
     
-    // Subscribe to console logs
+    // Subscribe to console logs and bridge events, including those for the
+    // extension alone
     let mut console_rx = CONSOLE_LOG_TX.subscribe();
+    let mut event_rx = events::subscribe();
+    let mut extension_rx = events::subscribe_extension();
     
     // Spawn stdout writer task
+    let writer_watch = watchdog::register("native_messaging.writer", WRITER_STALL_TIMEOUT);
     let write_handle = tokio::task::spawn_blocking(move || {
         let mut stdout = io::stdout().lock();
         while let Some(msg) = write_rx.blocking_recv() {
-            if let Err(e) = write_message(&mut stdout, &msg) {
+            writer_watch.begin();
+            let result = write_message(&mut stdout, &msg);
+            writer_watch.end();
+            if let Err(e) = result {
                 tracing::error!("Failed to write message: {}", e);
                 break;
             }
@@ -187,16 +217,39 @@ pub async fn run_native_messaging() {
         }
     });
 
+    // Spawn event forwarder
+    let event_writer = writer.clone();
+    tokio::spawn(async move {
+        loop {
+            let received = tokio::select! {
+                received = event_rx.recv() => received,
+                received = extension_rx.recv() => received,
+            };
+            match received {
+                Ok(event) => event_writer.send_event(&event).await,
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    tracing::warn!("Dropped {} events for a slow extension", n);
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    });
+
     // Create channel for incoming messages
     let (msg_tx, mut msg_rx) = mpsc::channel::<IncomingMessage>(32);
     
-    // Spawn stdin reader task
+    // Spawn stdin reader task. The router watch covers the hand-off to the
+    // dispatch loop, which blocks if the loop stops draining the channel.
+    let router_watch = watchdog::register("native_messaging.router", ROUTER_STALL_TIMEOUT);
     tokio::task::spawn_blocking(move || {
         let mut stdin = io::stdin().lock();
         loop {
             match read_message(&mut stdin) {
                 Ok(Some(msg)) => {
-                    if msg_tx.blocking_send(msg).is_err() {
+                    router_watch.begin();
+                    let sent = msg_tx.blocking_send(msg);
+                    router_watch.end();
+                    if sent.is_err() {
                         break;
                     }
                 }
@@ -258,7 +311,7 @@ async fn handle_message(msg: IncomingMessage, writer: Arc<MessageWriter>) {
             if rpc::is_streaming_method(&method) {
                 handle_streaming_rpc(id, method, msg.params, writer).await;
             } else {
-                handle_rpc(id, method, msg.params, writer).await;
+                handle_rpc(id, method, msg.params, msg.caller, writer).await;
             }
         }
         
@@ -273,10 +326,16 @@ async fn handle_rpc(
     id: serde_json::Value,
     method: String,
     params: serde_json::Value,
+    caller: Option<String>,
     writer: Arc<MessageWriter>,
 ) {
-    let request = RpcRequest { id: id.clone(), method, params };
-    let response = rpc::handle(request).await;
+    // Fault injection never drops its own responses, so it can be turned off
+    let droppable = !method.starts_with("chaos.");
+    let request = RpcRequest { id: id.clone(), method, params, caller };
+    let response = rpc::handle(request, rpc::Transport::NativeMessaging).await;
+    if droppable && chaos::inject(Fault::DroppedMessage, None) {
+        return;
+    }
     
     writer.send_rpc_response(
         id,
@@ -5,11 +5,13 @@ use rand::Rng;
 use sha2::{Digest, Sha256};
 use url::Url;
 
+use crate::redact::redact;
+
 use super::{
-    providers::get_provider_config, OAuthCredentials, OAuthFlowState, OAuthTokens,
+    providers::get_server_config, OAuthCredentials, OAuthFlowState, OAuthTokens,
 };
 
-const CALLBACK_URL: &str = "http://127.0.0.1:8765/oauth/callback";
+pub const CALLBACK_URL: &str = "http://127.0.0.1:8765/oauth/callback";
 
 /// Generate a random state string for CSRF protection.
 fn generate_state() -> String {
@@ -36,8 +38,9 @@ pub fn start_flow(
     server_id: &str,
     scopes: &[String],
     credentials: &OAuthCredentials,
+    redirect_uri: &str,
 ) -> Result<(String, OAuthFlowState), String> {
-    let config = get_provider_config(provider_id)
+    let config = get_server_config(provider_id, server_id)
         .ok_or_else(|| format!("Unknown provider: {}", provider_id))?;
     
     let state = generate_state();
@@ -55,7 +58,7 @@ pub fn start_flow(
     {
         let mut query = url.query_pairs_mut();
         query.append_pair("client_id", &credentials.client_id);
-        query.append_pair("redirect_uri", CALLBACK_URL);
+        query.append_pair("redirect_uri", redirect_uri);
         query.append_pair("response_type", "code");
         query.append_pair("state", &state);
         
@@ -69,6 +72,11 @@ pub fn start_flow(
             query.append_pair("code_challenge_method", "S256");
         }
         
+        // Resource indicator for remote MCP servers (RFC 8707)
+        if let Some(ref resource) = config.resource {
+            query.append_pair("resource", resource);
+        }
+        
         // Google-specific: request offline access for refresh token
         if provider_id == "google" {
             query.append_pair("access_type", "offline");
@@ -82,6 +90,7 @@ pub fn start_flow(
         provider_id: provider_id.to_string(),
         server_id: server_id.to_string(),
         scopes: scopes.to_vec(),
+        redirect_uri: redirect_uri.to_string(),
         started_at: chrono::Utc::now().timestamp_millis(),
     };
     
@@ -101,18 +110,26 @@ pub async fn exchange_code(
     flow: &OAuthFlowState,
     credentials: &OAuthCredentials,
 ) -> Result<OAuthTokens, String> {
-    let config = get_provider_config(&flow.provider_id)
+    let config = get_server_config(&flow.provider_id, &flow.server_id)
         .ok_or_else(|| format!("Unknown provider: {}", flow.provider_id))?;
     
     // Build token request
     let mut params = vec![
         ("client_id", credentials.client_id.as_str()),
-        ("client_secret", credentials.client_secret.as_str()),
         ("code", code),
-        ("redirect_uri", CALLBACK_URL),
+        ("redirect_uri", flow.redirect_uri.as_str()),
         ("grant_type", "authorization_code"),
     ];
     
+    // Public clients (dynamically registered) have no secret
+    if !credentials.client_secret.is_empty() {
+        params.push(("client_secret", credentials.client_secret.as_str()));
+    }
+    
+    if let Some(ref resource) = config.resource {
+        params.push(("resource", resource.as_str()));
+    }
+    
     // Add PKCE verifier if we used it
     let verifier_str;
     if let Some(ref verifier) = flow.code_verifier {
@@ -134,6 +151,7 @@ pub async fn exchange_code(
     if !response.status().is_success() {
         let status = response.status();
         let body = response.text().await.unwrap_or_default();
+        let body = redact(&body);
         tracing::error!("Token exchange failed: {} - {}", status, body);
         return Err(format!("Token exchange failed: {} - {}", status, body));
     }
@@ -168,18 +186,26 @@ pub async fn exchange_code(
 pub async fn refresh_tokens(
     refresh_token: &str,
     provider_id: &str,
+    server_id: &str,
     credentials: &OAuthCredentials,
 ) -> Result<OAuthTokens, String> {
-    let config = get_provider_config(provider_id)
+    let config = get_server_config(provider_id, server_id)
         .ok_or_else(|| format!("Unknown provider: {}", provider_id))?;
     
-    let params = [
+    let mut params = vec![
         ("client_id", credentials.client_id.as_str()),
-        ("client_secret", credentials.client_secret.as_str()),
         ("refresh_token", refresh_token),
         ("grant_type", "refresh_token"),
     ];
     
+    if !credentials.client_secret.is_empty() {
+        params.push(("client_secret", credentials.client_secret.as_str()));
+    }
+    
+    if let Some(ref resource) = config.resource {
+        params.push(("resource", resource.as_str()));
+    }
+    
     tracing::info!("Refreshing token (provider: {})", provider_id);
     
     let client = reqwest::Client::new();
@@ -194,7 +220,7 @@ pub async fn refresh_tokens(
     if !response.status().is_success() {
         let status = response.status();
         let body = response.text().await.unwrap_or_default();
-        return Err(format!("Token refresh failed: {} - {}", status, body));
+        return Err(format!("Token refresh failed: {} - {}", status, redact(&body)));
     }
     
     let token_response: TokenResponse = response
@@ -220,6 +246,51 @@ pub async fn refresh_tokens(
     Ok(tokens)
 }
 
+/// An authorization code pasted by the user.
+#[derive(Debug, PartialEq)]
+pub struct PastedCode {
+    pub code: String,
+    /// Flow state, if the user pasted the whole redirect URL
+    pub state: Option<String>,
+}
+
+/// Parse what the user pasted in manual mode.
+///
+/// Accepts either the bare code shown by the provider or the full URL the
+/// browser was redirected to (which fails to load when the bridge runs in a
+/// container or over SSH, but still carries the code and state).
+pub fn parse_pasted_code(input: &str) -> Result<PastedCode, String> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Err("Empty authorization code".to_string());
+    }
+
+    let url = match Url::parse(input) {
+        Ok(url) if matches!(url.scheme(), "http" | "https") => url,
+        _ => {
+            return Ok(PastedCode {
+                code: input.to_string(),
+                state: None,
+            });
+        }
+    };
+
+    let param = |name: &str| {
+        url.query_pairs()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.into_owned())
+    };
+    if let Some(error) = param("error") {
+        let description = param("error_description").unwrap_or(error);
+        return Err(format!("Provider returned error: {}", description));
+    }
+    let code = param("code").ok_or_else(|| "URL does not contain an authorization code".to_string())?;
+    Ok(PastedCode {
+        code,
+        state: param("state"),
+    })
+}
+
 /// Token response from OAuth provider.
 #[derive(Debug, serde::Deserialize)]
 struct TokenResponse {
@@ -248,4 +319,17 @@ mod tests {
         assert!(!challenge.is_empty());
         assert_ne!(verifier, challenge);
     }
+    
+    #[test]
+    fn test_parse_pasted_code() {
+        let bare = parse_pasted_code("  4/0AbCd  ").unwrap();
+        assert_eq!(bare.code, "4/0AbCd");
+        assert_eq!(bare.state, None);
+        
+        let url = parse_pasted_code("http://127.0.0.1:8765/oauth/callback?code=abc&state=xyz").unwrap();
+        assert_eq!(url.code, "abc");
+        assert_eq!(url.state.as_deref(), Some("xyz"));
+        
+        assert!(parse_pasted_code("http://127.0.0.1:8765/oauth/callback?error=access_denied").is_err());
+    }
 }
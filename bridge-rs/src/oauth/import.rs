@@ -0,0 +1,230 @@
+//! Import OAuth state from other MCP hosts.
+//!
+//! Supported sources:
+//! - `mcp-remote`: token caches in `~/.mcp-auth/mcp-remote-<version>/`
+//!   (or `$MCP_REMOTE_CONFIG_DIR`), keyed by the MD5 of the server URL.
+//!   Server URLs come from the caller or from the `mcp-remote` entries in
+//!   Claude Desktop's `claude_desktop_config.json`.
+//!
+//! Claude Desktop's own keychain entries use an undocumented format and are
+//! not imported.
+
+use std::path::{Path, PathBuf};
+
+use md5::{Digest, Md5};
+use serde::{Deserialize, Serialize};
+
+use super::{remote, OAuthCredentials, OAuthTokens, StoredTokens};
+
+/// A remote server whose credentials should be imported.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportTarget {
+    pub server_id: String,
+    pub url: String,
+}
+
+/// Outcome for one server.
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportResult {
+    pub server_id: String,
+    pub url: String,
+    pub imported: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// `<hash>_tokens.json` as written by mcp-remote.
+#[derive(Debug, Deserialize)]
+struct McpRemoteTokens {
+    access_token: String,
+    #[serde(default)]
+    token_type: Option<String>,
+    #[serde(default)]
+    expires_in: Option<i64>,
+    #[serde(default)]
+    refresh_token: Option<String>,
+    #[serde(default)]
+    scope: Option<String>,
+}
+
+/// `<hash>_client_info.json` as written by mcp-remote.
+#[derive(Debug, Deserialize)]
+struct McpRemoteClientInfo {
+    client_id: String,
+    #[serde(default)]
+    client_secret: Option<String>,
+}
+
+/// Cached credentials found for a server URL.
+struct McpRemoteEntry {
+    tokens: McpRemoteTokens,
+    client: McpRemoteClientInfo,
+    /// When the token file was written (Unix timestamp ms)
+    saved_at: i64,
+}
+
+/// Hash mcp-remote uses to name its cache files.
+fn server_url_hash(url: &str) -> String {
+    Md5::digest(url.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// mcp-remote cache directories, newest first.
+fn mcp_remote_dirs() -> Vec<PathBuf> {
+    let base = std::env::var_os("MCP_REMOTE_CONFIG_DIR")
+        .map(PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|h| h.join(".mcp-auth")));
+    let Some(base) = base else {
+        return Vec::new();
+    };
+    let Ok(entries) = std::fs::read_dir(&base) else {
+        return Vec::new();
+    };
+
+    let mut dirs: Vec<(std::time::SystemTime, PathBuf)> = entries
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_name().to_string_lossy().starts_with("mcp-remote-"))
+        .filter_map(|e| {
+            let modified = e.metadata().and_then(|m| m.modified()).ok()?;
+            Some((modified, e.path()))
+        })
+        .collect();
+    dirs.sort_by(|a, b| b.0.cmp(&a.0));
+    dirs.into_iter().map(|(_, path)| path).collect()
+}
+
+fn read_json<T: for<'de> Deserialize<'de>>(path: &Path) -> Option<T> {
+    let contents = std::fs::read_to_string(path).ok()?;
+    serde_json::from_str(&contents)
+        .map_err(|e| tracing::warn!("Failed to parse {:?}: {}", path, e))
+        .ok()
+}
+
+/// Find cached mcp-remote credentials for a server URL.
+fn find_mcp_remote(url: &str) -> Option<McpRemoteEntry> {
+    let hash = server_url_hash(url);
+    for dir in mcp_remote_dirs() {
+        let tokens_path = dir.join(format!("{}_tokens.json", hash));
+        let client_path = dir.join(format!("{}_client_info.json", hash));
+        let (Some(tokens), Some(client)) = (read_json(&tokens_path), read_json(&client_path)) else {
+            continue;
+        };
+        let saved_at = std::fs::metadata(&tokens_path)
+            .and_then(|m| m.modified())
+            .map(|t| chrono::DateTime::<chrono::Utc>::from(t).timestamp_millis())
+            .unwrap_or_else(|_| chrono::Utc::now().timestamp_millis());
+        return Some(McpRemoteEntry { tokens, client, saved_at });
+    }
+    None
+}
+
+/// Servers launched through `mcp-remote` in Claude Desktop's config.
+pub fn claude_desktop_targets() -> Vec<ImportTarget> {
+    let Some(path) = dirs::config_dir().map(|d| d.join("Claude").join("claude_desktop_config.json")) else {
+        return Vec::new();
+    };
+    let Some(config) = read_json::<serde_json::Value>(&path) else {
+        return Vec::new();
+    };
+    let Some(servers) = config.get("mcpServers").and_then(|s| s.as_object()) else {
+        return Vec::new();
+    };
+
+    servers
+        .iter()
+        .filter_map(|(name, server)| {
+            let args: Vec<&str> = server
+                .get("args")?
+                .as_array()?
+                .iter()
+                .filter_map(|a| a.as_str())
+                .collect();
+            let pos = args.iter().position(|a| a.starts_with("mcp-remote"))?;
+            let url = args[pos + 1..].iter().find(|a| a.starts_with("http"))?;
+            Some(ImportTarget {
+                server_id: name.clone(),
+                url: url.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Convert an mcp-remote token cache into Harbor's stored format.
+fn to_stored(server_id: &str, provider_id: &str, entry: &McpRemoteEntry) -> StoredTokens {
+    let now = chrono::Utc::now().timestamp_millis();
+    StoredTokens {
+        server_id: server_id.to_string(),
+        provider: provider_id.to_string(),
+        tokens: OAuthTokens {
+            access_token: entry.tokens.access_token.clone(),
+            refresh_token: entry.tokens.refresh_token.clone(),
+            expires_at: entry.tokens.expires_in.map(|s| entry.saved_at + s * 1000),
+            token_type: entry.tokens.token_type.clone().unwrap_or_else(|| "Bearer".to_string()),
+            scope: entry.tokens.scope.clone(),
+        },
+        scopes: entry
+            .tokens
+            .scope
+            .as_deref()
+            .map(|s| s.split_whitespace().map(String::from).collect())
+            .unwrap_or_default(),
+        created_at: now,
+        updated_at: now,
+    }
+}
+
+/// Import mcp-remote credentials for one server.
+///
+/// Discovers the server's authorization server so the imported client is
+/// registered under the same provider ID Harbor would use, which keeps
+/// refresh working. Returns the tokens to store.
+pub async fn import_mcp_remote(target: &ImportTarget, dry_run: bool) -> Result<StoredTokens, String> {
+    let entry = find_mcp_remote(&target.url)
+        .ok_or_else(|| "No mcp-remote credentials found for this URL".to_string())?;
+
+    let challenge = remote::probe(&target.url)
+        .await?
+        .ok_or_else(|| "Server does not require authorization".to_string())?;
+
+    let client = OAuthCredentials {
+        client_id: entry.client.client_id.clone(),
+        client_secret: entry.client.client_secret.clone().unwrap_or_default(),
+    };
+    let provider_id = remote::adopt_client(&target.server_id, &target.url, &challenge, client, dry_run).await?;
+
+    Ok(to_stored(&target.server_id, &provider_id, &entry))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_server_url_hash() {
+        assert_eq!(server_url_hash(""), "d41d8cd98f00b204e9800998ecf8427e");
+    }
+
+    #[test]
+    fn test_to_stored_computes_expiry() {
+        let entry = McpRemoteEntry {
+            tokens: McpRemoteTokens {
+                access_token: "at".to_string(),
+                token_type: None,
+                expires_in: Some(3600),
+                refresh_token: Some("rt".to_string()),
+                scope: Some("read write".to_string()),
+            },
+            client: McpRemoteClientInfo {
+                client_id: "client".to_string(),
+                client_secret: None,
+            },
+            saved_at: 1_000,
+        };
+        let stored = to_stored("linear", "remote:mcp.linear.app", &entry);
+        assert_eq!(stored.tokens.expires_at, Some(3_601_000));
+        assert_eq!(stored.tokens.token_type, "Bearer");
+        assert_eq!(stored.scopes, vec!["read", "write"]);
+    }
+}
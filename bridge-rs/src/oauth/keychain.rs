@@ -0,0 +1,38 @@
+//! OS keychain storage for OAuth client secrets.
+//!
+//! Client secrets are kept in the platform credential store (macOS Keychain,
+//! Windows Credential Manager, Secret Service on Linux) keyed by provider ID.
+//! Only non-secret data such as client IDs lives in `oauth_credentials.json`.
+
+const SERVICE: &str = "harbor-oauth";
+
+fn entry(provider_id: &str) -> Result<keyring::Entry, String> {
+    keyring::Entry::new(SERVICE, provider_id)
+        .map_err(|e| format!("Failed to open keychain entry for {}: {}", provider_id, e))
+}
+
+/// Store a client secret for a provider.
+pub fn set_secret(provider_id: &str, secret: &str) -> Result<(), String> {
+    entry(provider_id)?
+        .set_password(secret)
+        .map_err(|e| format!("Failed to store secret in keychain: {}", e))
+}
+
+/// Get the client secret for a provider, if one is stored.
+pub fn get_secret(provider_id: &str) -> Option<String> {
+    match entry(provider_id).and_then(|e| e.get_password().map_err(|e| e.to_string())) {
+        Ok(secret) => Some(secret),
+        Err(e) => {
+            tracing::debug!("No keychain secret for {}: {}", provider_id, e);
+            None
+        }
+    }
+}
+
+/// Delete the client secret for a provider. Missing entries are not an error.
+pub fn delete_secret(provider_id: &str) -> Result<(), String> {
+    match entry(provider_id)?.delete_password() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(format!("Failed to delete secret from keychain: {}", e)),
+    }
+}
@@ -4,21 +4,28 @@
 //! API access (Gmail, Google Drive, GitHub, etc.).
 
 pub mod flow;
+pub mod import;
+pub mod keychain;
+pub mod permissions;
+pub mod policy;
 pub mod providers;
+pub mod remote;
 pub mod server;
 pub mod storage;
+pub mod versioning;
 
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{Mutex, RwLock};
 
 use crate::rpc::RpcError;
 
 pub use flow::{start_flow, exchange_code};
+pub use policy::ScopePolicy;
 pub use storage::{TokenStore, StoredTokens};
 
-// Re-export for internal use by storage module
+// Re-export for token refresh in get_access_token
 pub(crate) use flow::refresh_tokens;
 
 // ============================================================================
@@ -40,6 +47,13 @@ pub struct OAuthProviderConfig {
     pub revocation_url: Option<String>,
     /// Whether to use PKCE (Proof Key for Code Exchange)
     pub pkce_enabled: bool,
+    /// Resource indicator (RFC 8707) sent with authorization and token requests
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resource: Option<String>,
+    /// Redirect URI that shows the code to the user instead of calling back
+    /// to localhost (a provider-hosted page, or `urn:ietf:wg:oauth:2.0:oob`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub manual_redirect_uri: Option<String>,
 }
 
 /// OAuth tokens returned from token exchange.
@@ -70,6 +84,8 @@ pub struct OAuthFlowState {
     pub server_id: String,
     /// Requested scopes
     pub scopes: Vec<String>,
+    /// Redirect URI sent with the authorization request
+    pub redirect_uri: String,
     /// When this flow was started (for timeout detection)
     #[allow(dead_code)]
     pub started_at: i64,
@@ -82,29 +98,75 @@ pub struct OAuthCredentials {
     pub client_secret: String,
 }
 
+/// Current version of the credentials file format.
+const CREDENTIALS_FILE_VERSION: u32 = 1;
+
+/// Credentials file migrations, indexed by the version they upgrade from.
+const CREDENTIALS_MIGRATIONS: &[versioning::Migration] = &[
+    // v0 -> v1: unversioned files. Same shape; `client_secret` became
+    // optional when secrets moved to the keychain.
+    |_| Ok(()),
+];
+
 /// Stored credentials file format.
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 struct CredentialsFile {
+    /// Format version (see `CREDENTIALS_FILE_VERSION`)
+    #[serde(default)]
+    version: u32,
     /// Map of provider_id -> credentials
-    providers: HashMap<String, OAuthCredentials>,
+    providers: HashMap<String, StoredCredentials>,
+}
+
+impl Default for CredentialsFile {
+    fn default() -> Self {
+        Self {
+            version: CREDENTIALS_FILE_VERSION,
+            providers: HashMap::new(),
+        }
+    }
+}
+
+/// Credentials as stored on disk.
+///
+/// Client secrets live in the OS keychain. `client_secret` is only present in
+/// files written by older versions, or when the keychain is unavailable.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct StoredCredentials {
+    client_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    client_secret: Option<String>,
 }
 
 // ============================================================================
-// Global State
+// Subsystem State
 // ============================================================================
 
-lazy_static::lazy_static! {
+/// OAuth subsystem state.
+#[derive(Default)]
+pub struct OAuthState {
     /// Active OAuth flows waiting for callback
-    static ref PENDING_FLOWS: Arc<RwLock<HashMap<String, OAuthFlowState>>> = 
-        Arc::new(RwLock::new(HashMap::new()));
+    pending_flows: RwLock<HashMap<String, OAuthFlowState>>,
     
-    /// OAuth credentials loaded from environment
-    static ref OAUTH_CREDENTIALS: Arc<RwLock<HashMap<String, OAuthCredentials>>> = 
-        Arc::new(RwLock::new(HashMap::new()));
+    /// OAuth credentials loaded from file, keychain and environment
+    credentials: RwLock<HashMap<String, OAuthCredentials>>,
     
     /// Token store for persisted tokens
-    static ref TOKEN_STORE: Arc<RwLock<Option<TokenStore>>> = 
-        Arc::new(RwLock::new(None));
+    token_store: RwLock<Option<TokenStore>>,
+    
+    /// Declared scope policies, keyed by server ID
+    policies: RwLock<HashMap<String, ScopePolicy>>,
+    
+    /// Per-server locks held while refreshing tokens
+    refresh_locks: std::sync::Mutex<HashMap<String, Arc<Mutex<()>>>>,
+    
+    /// Callback server, while running
+    callback_server: Mutex<Option<server::CallbackServer>>,
+}
+
+/// The bridge's OAuth state.
+fn state() -> &'static OAuthState {
+    &crate::state::get().oauth
 }
 
 /// Get the path to the credentials file.
@@ -116,25 +178,25 @@ fn credentials_file_path() -> std::path::PathBuf {
 /// Load credentials from the credentials file.
 fn load_credentials_file() -> CredentialsFile {
     let path = credentials_file_path();
-    if path.exists() {
-        match std::fs::read_to_string(&path) {
-            Ok(contents) => {
-                match serde_json::from_str(&contents) {
-                    Ok(creds) => {
-                        tracing::info!("Loaded credentials from {:?}", path);
-                        return creds;
-                    }
-                    Err(e) => {
-                        tracing::warn!("Failed to parse credentials file: {}", e);
-                    }
-                }
-            }
-            Err(e) => {
-                tracing::warn!("Failed to read credentials file: {}", e);
-            }
+    let raw = match versioning::load(&path, CREDENTIALS_FILE_VERSION, CREDENTIALS_MIGRATIONS) {
+        Ok(Some(raw)) => raw,
+        Ok(None) => return CredentialsFile::default(),
+        Err(e) => {
+            tracing::warn!("Failed to load credentials file: {}", e);
+            return CredentialsFile::default();
+        }
+    };
+    
+    match serde_json::from_value(raw) {
+        Ok(creds) => {
+            tracing::info!("Loaded credentials from {:?}", path);
+            creds
+        }
+        Err(e) => {
+            tracing::warn!("Failed to parse credentials file: {}", e);
+            CredentialsFile::default()
         }
     }
-    CredentialsFile::default()
 }
 
 /// Save credentials to the credentials file.
@@ -152,70 +214,152 @@ fn save_credentials_file(creds: &CredentialsFile) -> Result<(), String> {
     
     std::fs::write(&path, json)
         .map_err(|e| format!("Failed to write credentials file: {}", e))?;
+    permissions::restrict_to_owner(&path)?;
     
     tracing::info!("Saved credentials to {:?}", path);
     Ok(())
 }
 
-/// Initialize OAuth module - load credentials and stored tokens.
-pub async fn init() {
-    let mut creds = OAUTH_CREDENTIALS.write().await;
+/// Move plaintext client secrets from the credentials file into the keychain.
+/// Secrets that can't be stored (no keychain available) are left in place.
+fn migrate_secrets_to_keychain(file_creds: &mut CredentialsFile) {
+    let mut migrated = false;
     
-    // First, load from credentials file
-    let file_creds = load_credentials_file();
-    for (provider_id, credentials) in file_creds.providers {
-        tracing::info!("Loaded {} OAuth credentials from file", provider_id);
-        creds.insert(provider_id, credentials);
+    for (provider_id, stored) in file_creds.providers.iter_mut() {
+        if let Some(ref secret) = stored.client_secret {
+            match keychain::set_secret(provider_id, secret) {
+                Ok(()) => {
+                    tracing::info!("Migrated {} client secret to the keychain", provider_id);
+                    stored.client_secret = None;
+                    migrated = true;
+                }
+                Err(e) => {
+                    tracing::warn!("Keeping {} client secret in file: {}", provider_id, e);
+                }
+            }
+        }
     }
     
-    // Then, override with environment variables (env vars take precedence)
-    // Google
-    if let (Ok(client_id), Ok(client_secret)) = (
-        std::env::var("HARBOR_GOOGLE_CLIENT_ID"),
-        std::env::var("HARBOR_GOOGLE_CLIENT_SECRET"),
-    ) {
-        if !client_id.is_empty() && !client_secret.is_empty() {
-            tracing::info!("Loaded Google OAuth credentials from environment");
-            creds.insert("google".to_string(), OAuthCredentials {
-                client_id,
+    if migrated {
+        if let Err(e) = save_credentials_file(file_creds) {
+            tracing::warn!("Failed to rewrite credentials file after migration: {}", e);
+        }
+    }
+}
+
+/// Initialize OAuth module - load credentials and stored tokens.
+pub async fn init() {
+    state().start().await;
+}
+
+impl OAuthState {
+    /// Load credentials and stored tokens.
+    pub async fn start(&self) {
+        let mut creds = self.credentials.write().await;
+        
+        // First, load from credentials file (secrets come from the keychain)
+        let mut file_creds = load_credentials_file();
+        migrate_secrets_to_keychain(&mut file_creds);
+        for (provider_id, stored) in file_creds.providers {
+            let client_secret = match stored.client_secret.or_else(|| keychain::get_secret(&provider_id)) {
+                Some(secret) => secret,
+                None => {
+                    tracing::warn!("No client secret found for {}; re-enter its credentials", provider_id);
+                    continue;
+                }
+            };
+            tracing::info!("Loaded {} OAuth credentials", provider_id);
+            creds.insert(provider_id, OAuthCredentials {
+                client_id: stored.client_id,
                 client_secret,
             });
         }
+        
+        // Then, override with environment variables (env vars take precedence)
+        // Google
+        if let (Ok(client_id), Ok(client_secret)) = (
+            std::env::var("HARBOR_GOOGLE_CLIENT_ID"),
+            std::env::var("HARBOR_GOOGLE_CLIENT_SECRET"),
+        ) {
+            if !client_id.is_empty() && !client_secret.is_empty() {
+                tracing::info!("Loaded Google OAuth credentials from environment");
+                creds.insert("google".to_string(), OAuthCredentials {
+                    client_id,
+                    client_secret,
+                });
+            }
+        }
+        
+        // GitHub
+        if let (Ok(client_id), Ok(client_secret)) = (
+            std::env::var("HARBOR_GITHUB_CLIENT_ID"),
+            std::env::var("HARBOR_GITHUB_CLIENT_SECRET"),
+        ) {
+            if !client_id.is_empty() && !client_secret.is_empty() {
+                tracing::info!("Loaded GitHub OAuth credentials from environment");
+                creds.insert("github".to_string(), OAuthCredentials {
+                    client_id,
+                    client_secret,
+                });
+            }
+        }
+        
+        // Remote MCP servers registered through dynamic client registration
+        for (provider_id, credentials) in remote::load() {
+            tracing::info!("Loaded remote OAuth registration for {}", provider_id);
+            creds.insert(provider_id, credentials);
+        }
+        
+        drop(creds);
+        
+        // Load declared scope policies
+        *self.policies.write().await = policy::load();
+        
+        // Load token store
+        match TokenStore::load() {
+            Ok(store) => {
+                let count = store.tokens.len();
+                *self.token_store.write().await = Some(store);
+                if count > 0 {
+                    tracing::info!("Loaded {} stored OAuth tokens", count);
+                }
+            }
+            Err(e) => {
+                tracing::warn!("Failed to load token store: {}", e);
+                *self.token_store.write().await = Some(TokenStore::new());
+            }
+        }
     }
     
-    // GitHub
-    if let (Ok(client_id), Ok(client_secret)) = (
-        std::env::var("HARBOR_GITHUB_CLIENT_ID"),
-        std::env::var("HARBOR_GITHUB_CLIENT_SECRET"),
-    ) {
-        if !client_id.is_empty() && !client_secret.is_empty() {
-            tracing::info!("Loaded GitHub OAuth credentials from environment");
-            creds.insert("github".to_string(), OAuthCredentials {
-                client_id,
-                client_secret,
-            });
+    /// Stop the callback server and drop in-memory flows, credentials and tokens.
+    pub async fn stop(&self) {
+        if let Some(server) = self.callback_server.lock().await.take() {
+            server.shutdown().await;
         }
+        self.pending_flows.write().await.clear();
+        self.credentials.write().await.clear();
+        self.policies.write().await.clear();
+        *self.token_store.write().await = None;
     }
     
-    drop(creds);
+    /// Lock serializing token refreshes for a server.
+    fn refresh_lock(&self, server_id: &str) -> Arc<Mutex<()>> {
+        self.refresh_locks
+            .lock()
+            .unwrap()
+            .entry(server_id.to_string())
+            .or_default()
+            .clone()
+    }
     
-    // Load token store
-    match TokenStore::load() {
-        Ok(store) => {
-            let count = store.tokens.len();
-            *TOKEN_STORE.write().await = Some(store);
-            if count > 0 {
-                tracing::info!("Loaded {} stored OAuth tokens", count);
-            }
-        }
-        Err(e) => {
-            tracing::warn!("Failed to load token store: {}", e);
-            *TOKEN_STORE.write().await = Some(TokenStore::new());
-        }
+    /// Stop and start again, reloading everything from disk.
+    pub async fn restart(&self) {
+        self.stop().await;
+        self.start().await;
     }
 }
 
-/// Set credentials for a provider (and save to file).
+/// Set credentials for a provider (secret to the keychain, client ID to file).
 pub async fn set_credentials(provider_id: &str, client_id: &str, client_secret: &str) -> Result<(), String> {
     let credentials = OAuthCredentials {
         client_id: client_id.to_string(),
@@ -223,11 +367,23 @@ pub async fn set_credentials(provider_id: &str, client_id: &str, client_secret:
     };
     
     // Update in-memory credentials
-    OAUTH_CREDENTIALS.write().await.insert(provider_id.to_string(), credentials.clone());
+    state().credentials.write().await.insert(provider_id.to_string(), credentials);
+    
+    // Store the secret in the keychain, falling back to the file if unavailable
+    let file_secret = match keychain::set_secret(provider_id, client_secret) {
+        Ok(()) => None,
+        Err(e) => {
+            tracing::warn!("Keychain unavailable, storing {} client secret in file: {}", provider_id, e);
+            Some(client_secret.to_string())
+        }
+    };
     
     // Save to file
     let mut file_creds = load_credentials_file();
-    file_creds.providers.insert(provider_id.to_string(), credentials);
+    file_creds.providers.insert(provider_id.to_string(), StoredCredentials {
+        client_id: client_id.to_string(),
+        client_secret: file_secret,
+    });
     save_credentials_file(&file_creds)?;
     
     Ok(())
@@ -236,56 +392,198 @@ pub async fn set_credentials(provider_id: &str, client_id: &str, client_secret:
 /// Remove credentials for a provider.
 pub async fn remove_credentials(provider_id: &str) -> Result<(), String> {
     // Remove from in-memory
-    OAUTH_CREDENTIALS.write().await.remove(provider_id);
+    state().credentials.write().await.remove(provider_id);
     
-    // Save to file
+    // Remove from both the keychain and the file, even if one of them fails
+    let keychain = keychain::delete_secret(provider_id);
     let mut file_creds = load_credentials_file();
     file_creds.providers.remove(provider_id);
-    save_credentials_file(&file_creds)?;
+    let file = save_credentials_file(&file_creds);
     
-    Ok(())
+    match (keychain, file) {
+        (Ok(()), Ok(())) => Ok(()),
+        (Err(e), Ok(())) | (Ok(()), Err(e)) => Err(e),
+        (Err(keychain), Err(file)) => Err(format!("{}; {}", keychain, file)),
+    }
 }
 
 /// Get credentials for a provider.
 pub async fn get_credentials(provider_id: &str) -> Option<OAuthCredentials> {
-    OAUTH_CREDENTIALS.read().await.get(provider_id).cloned()
+    state().credentials.read().await.get(provider_id).cloned()
 }
 
 /// Check if a provider is configured.
 #[allow(dead_code)]
 pub async fn is_provider_configured(provider_id: &str) -> bool {
-    OAUTH_CREDENTIALS.read().await.contains_key(provider_id)
+    state().credentials.read().await.contains_key(provider_id)
 }
 
 /// List configured providers.
 pub async fn list_configured_providers() -> Vec<String> {
-    OAUTH_CREDENTIALS.read().await.keys().cloned().collect()
+    state().credentials.read().await.keys().cloned().collect()
 }
 
 /// Store a pending flow.
 pub async fn store_pending_flow(flow: OAuthFlowState) {
-    PENDING_FLOWS.write().await.insert(flow.state.clone(), flow);
+    state().pending_flows.write().await.insert(flow.state.clone(), flow);
 }
 
 /// Get and remove a pending flow by state.
-pub async fn take_pending_flow(state: &str) -> Option<OAuthFlowState> {
-    PENDING_FLOWS.write().await.remove(state)
+pub async fn take_pending_flow(flow_state: &str) -> Option<OAuthFlowState> {
+    state().pending_flows.write().await.remove(flow_state)
 }
 
 /// Get the token store.
 pub async fn get_token_store() -> tokio::sync::RwLockReadGuard<'static, Option<TokenStore>> {
-    TOKEN_STORE.read().await
+    state().token_store.read().await
 }
 
 /// Get mutable token store.
 pub async fn get_token_store_mut() -> tokio::sync::RwLockWriteGuard<'static, Option<TokenStore>> {
-    TOKEN_STORE.write().await
+    state().token_store.write().await
+}
+
+/// Store tokens from a completed flow and persist the token store.
+pub async fn save_tokens(server_id: &str, provider: &str, scopes: &[String], tokens: OAuthTokens) {
+    let mut store = get_token_store_mut().await;
+    if let Some(ref mut s) = *store {
+        let now = chrono::Utc::now().timestamp_millis();
+        let stored = StoredTokens {
+            server_id: server_id.to_string(),
+            provider: provider.to_string(),
+            tokens,
+            scopes: scopes.to_vec(),
+            created_at: now,
+            updated_at: now,
+        };
+        s.set_tokens(server_id, stored);
+        if let Err(e) = s.save() {
+            tracing::error!("Failed to save tokens: {}", e);
+        }
+    }
+    tracing::info!("OAuth tokens stored for server: {}", server_id);
+}
+
+/// Whether tokens are stored for a server.
+pub async fn has_tokens(server_id: &str) -> bool {
+    get_token_store().await.as_ref().is_some_and(|s| s.has_tokens(server_id))
+}
+
+/// Get a valid access token for a server, refreshing it if needed.
+///
+/// Refreshes are serialized per server: concurrent callers wait for the
+/// in-flight refresh and reuse its result, so a rotating refresh token is
+/// only spent once. The token store lock is not held during the request.
+pub async fn get_access_token(server_id: &str) -> Result<String, String> {
+    if let Some(token) = current_access_token(server_id).await? {
+        return Ok(token);
+    }
+    
+    let lock = state().refresh_lock(server_id);
+    let _guard = lock.lock().await;
+    
+    // Another caller may have refreshed while we waited
+    if let Some(token) = current_access_token(server_id).await? {
+        return Ok(token);
+    }
+    
+    let stored = get_token_store()
+        .await
+        .as_ref()
+        .and_then(|s| s.get_tokens(server_id).cloned())
+        .ok_or_else(|| format!("No tokens found for server: {}", server_id))?;
+    let refresh_token = stored.tokens.refresh_token.clone()
+        .ok_or("Token expired and no refresh token available")?;
+    let credentials = get_credentials(&stored.provider).await
+        .ok_or_else(|| format!("No credentials for provider: {}", stored.provider))?;
+    
+    if crate::chaos::inject(crate::chaos::Fault::FailedRefresh, Some(server_id)) {
+        return Err(format!("Token refresh failed for {}: injected fault", server_id));
+    }
+    let new_tokens = refresh_tokens(&refresh_token, &stored.provider, server_id, &credentials).await?;
+    let access_token = new_tokens.access_token.clone();
+    
+    let mut store = get_token_store_mut().await;
+    let s = store.as_mut().ok_or("Token store not loaded")?;
+    let mut updated = stored;
+    updated.tokens = new_tokens;
+    updated.updated_at = chrono::Utc::now().timestamp_millis();
+    s.set_tokens(server_id, updated);
+    s.save()?;
+    
+    Ok(access_token)
+}
+
+/// Refresh every expired token that has a refresh token, e.g. after the
+/// machine resumes from sleep. Returns the servers refreshed and the
+/// servers whose refresh failed.
+pub async fn revalidate_tokens() -> (Vec<String>, Vec<String>) {
+    let expired: Vec<String> = get_token_store()
+        .await
+        .as_ref()
+        .map(|s| {
+            s.tokens
+                .iter()
+                .filter(|(id, stored)| stored.tokens.refresh_token.is_some() && s.is_expired(id))
+                .map(|(id, _)| id.clone())
+                .collect()
+        })
+        .unwrap_or_default();
+    
+    let mut refreshed = Vec::new();
+    let mut failed = Vec::new();
+    for server_id in expired {
+        match get_access_token(&server_id).await {
+            Ok(_) => refreshed.push(server_id),
+            Err(e) => {
+                tracing::warn!("Failed to refresh tokens for {}: {}", server_id, e);
+                failed.push(server_id);
+            }
+        }
+    }
+    (refreshed, failed)
+}
+
+/// The stored access token for a server, if it hasn't expired.
+async fn current_access_token(server_id: &str) -> Result<Option<String>, String> {
+    let store = get_token_store().await;
+    let s = store.as_ref()
+        .ok_or_else(|| format!("No tokens found for server: {}", server_id))?;
+    let stored = s.get_tokens(server_id)
+        .ok_or_else(|| format!("No tokens found for server: {}", server_id))?;
+    
+    Ok((!s.is_expired(server_id)).then(|| stored.tokens.access_token.clone()))
+}
+
+/// Get the declared scope policy for a server, if any.
+pub async fn get_scope_policy(server_id: &str) -> Option<ScopePolicy> {
+    state().policies.read().await.get(server_id).cloned()
+}
+
+/// Refuse a flow whose provider or scopes fall outside the server's policy.
+async fn check_flow_policy(server_id: &str, provider_id: &str, scopes: &[String]) -> Result<(), RpcError> {
+    match get_scope_policy(server_id).await {
+        Some(policy) => policy.check_request(provider_id, scopes).map_err(|e| RpcError {
+            code: -32000,
+            message: format!("Scope policy for '{}' rejected flow: {}", server_id, e),
+        }),
+        None => Ok(()),
+    }
 }
 
 // ============================================================================
 // RPC Handlers
 // ============================================================================
 
+/// Restart the OAuth subsystem: stop the callback server, drop pending flows
+/// and reload credentials and tokens from disk.
+pub async fn rpc_restart(_params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    crate::rpc::require_extension("restart OAuth")?;
+    state().restart().await;
+    tracing::info!("OAuth subsystem restarted");
+    Ok(serde_json::json!({ "status": "restarted" }))
+}
+
 /// Start an OAuth flow for a server.
 /// Returns the authorization URL to open in browser.
 pub async fn rpc_start_flow(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
@@ -315,14 +613,28 @@ pub async fn rpc_start_flow(params: serde_json::Value) -> Result<serde_json::Val
         });
     }
     
+    check_flow_policy(server_id, provider_id, &scopes).await?;
+    
     // Check if provider is configured
     let credentials = get_credentials(provider_id).await.ok_or_else(|| RpcError {
         code: -32000,
         message: format!("OAuth provider '{}' is not configured", provider_id),
     })?;
     
+    // In manual mode, prefer a redirect that shows the user the code
+    let manual = is_manual_mode(&params)?;
+    let redirect_uri = if manual {
+        params.get("redirect_uri")
+            .and_then(|v| v.as_str())
+            .map(String::from)
+            .or_else(|| providers::get_provider_config(provider_id).and_then(|c| c.manual_redirect_uri))
+            .unwrap_or_else(|| flow::CALLBACK_URL.to_string())
+    } else {
+        flow::CALLBACK_URL.to_string()
+    };
+    
     // Start the flow
-    let (auth_url, flow_state) = start_flow(provider_id, server_id, &scopes, &credentials)
+    let (auth_url, flow_state) = start_flow(provider_id, server_id, &scopes, &credentials, &redirect_uri)
         .map_err(|e| RpcError {
             code: -32000,
             message: format!("Failed to start OAuth flow: {}", e),
@@ -333,14 +645,186 @@ pub async fn rpc_start_flow(params: serde_json::Value) -> Result<serde_json::Val
     store_pending_flow(flow_state).await;
     
     // Start the callback server if not running
-    server::ensure_server_running().await.map_err(|e| RpcError {
+    if !manual {
+        server::ensure_server_running().await.map_err(|e| RpcError {
+            code: -32000,
+            message: format!("Failed to start OAuth callback server: {}", e),
+        })?;
+    }
+    
+    Ok(serde_json::json!({
+        "auth_url": auth_url,
+        "state": state,
+        "mode": if manual { "manual" } else { "callback" },
+        "redirect_uri": redirect_uri,
+    }))
+}
+
+/// Read the flow `mode` parameter.
+///
+/// `callback` (the default) receives the code on the localhost callback
+/// server. `manual` skips the callback server; the user pastes the code, or
+/// the URL they were redirected to, into `oauth.submit_code`. Use it when the
+/// browser can't reach the bridge, e.g. in a container or over SSH.
+fn is_manual_mode(params: &serde_json::Value) -> Result<bool, RpcError> {
+    match params.get("mode").and_then(|v| v.as_str()) {
+        None | Some("callback") => Ok(false),
+        Some("manual") => Ok(true),
+        Some(other) => Err(RpcError {
+            code: -32602,
+            message: format!("Unknown flow mode '{}'", other),
+        }),
+    }
+}
+
+/// Complete a manual-mode flow with a code pasted by the user.
+///
+/// `code` may be the bare authorization code or the full redirect URL. The
+/// `state` parameter can be omitted when the URL carries it.
+pub async fn rpc_submit_code(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    let input = params.get("code")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| RpcError {
+            code: -32602,
+            message: "Missing 'code' parameter".to_string(),
+        })?;
+    
+    let pasted = flow::parse_pasted_code(input).map_err(|e| RpcError {
+        code: -32602,
+        message: e,
+    })?;
+    
+    let param_state = params.get("state").and_then(|v| v.as_str());
+    let flow_state = match (param_state, pasted.state.as_deref()) {
+        (Some(given), Some(pasted)) if given != pasted => {
+            return Err(RpcError {
+                code: -32602,
+                message: "Pasted URL belongs to a different authorization flow".to_string(),
+            });
+        }
+        (Some(s), _) | (None, Some(s)) => s.to_string(),
+        (None, None) => {
+            return Err(RpcError {
+                code: -32602,
+                message: "Missing 'state' parameter".to_string(),
+            });
+        }
+    };
+    
+    let flow = take_pending_flow(&flow_state).await.ok_or_else(|| RpcError {
         code: -32000,
-        message: format!("Failed to start OAuth callback server: {}", e),
+        message: "Unknown or expired authorization flow".to_string(),
     })?;
     
+    let credentials = get_credentials(&flow.provider_id).await.ok_or_else(|| RpcError {
+        code: -32000,
+        message: format!("OAuth provider '{}' is not configured", flow.provider_id),
+    })?;
+    
+    let tokens = exchange_code(&pasted.code, &flow, &credentials)
+        .await
+        .map_err(|e| RpcError {
+            code: -32000,
+            message: e,
+        })?;
+    
+    save_tokens(&flow.server_id, &flow.provider_id, &flow.scopes, tokens).await;
+    
     Ok(serde_json::json!({
+        "success": true,
+        "server_id": flow.server_id,
+        "provider": flow.provider_id,
+        "scopes": flow.scopes,
+    }))
+}
+
+/// Authorize against a remote MCP server.
+///
+/// Probes the server (or uses the supplied `www_authenticate` header from a
+/// 401 the caller already received), discovers its authorization server,
+/// registers a client if needed, and starts a PKCE flow.
+/// Returns the authorization URL to open in browser.
+pub async fn rpc_remote_authorize(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    crate::rpc::require_extension("authorize remote servers")?;
+    let server_id = params.get("server_id")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| RpcError {
+            code: -32602,
+            message: "Missing 'server_id' parameter".to_string(),
+        })?;
+    
+    let server_url = params.get("url")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| RpcError {
+            code: -32602,
+            message: "Missing 'url' parameter".to_string(),
+        })?;
+    
+    let challenge = match params.get("www_authenticate").and_then(|v| v.as_str()) {
+        Some(header) => remote::parse_www_authenticate(header).unwrap_or_default(),
+        None => match remote::probe(server_url).await {
+            Ok(Some(challenge)) => challenge,
+            Ok(None) => {
+                return Ok(serde_json::json!({
+                    "requires_auth": false,
+                }));
+            }
+            Err(e) => {
+                return Err(RpcError {
+                    code: -32000,
+                    message: e,
+                });
+            }
+        },
+    };
+    
+    let authorization = remote::prepare(server_id, server_url, &challenge, flow::CALLBACK_URL)
+        .await
+        .map_err(|e| RpcError {
+            code: -32000,
+            message: format!("Remote authorization discovery failed: {}", e),
+        })?;
+    
+    check_flow_policy(server_id, &authorization.provider_id, &authorization.scopes).await?;
+    
+    state().credentials.write().await.insert(
+        authorization.provider_id.clone(),
+        authorization.credentials.clone(),
+    );
+    
+    let manual = is_manual_mode(&params)?;
+    
+    let (auth_url, flow_state) = start_flow(
+        &authorization.provider_id,
+        server_id,
+        &authorization.scopes,
+        &authorization.credentials,
+        flow::CALLBACK_URL,
+    )
+    .map_err(|e| RpcError {
+        code: -32000,
+        message: format!("Failed to start OAuth flow: {}", e),
+    })?;
+    
+    let state = flow_state.state.clone();
+    store_pending_flow(flow_state).await;
+    
+    // Registered clients only allow the localhost callback, so in manual mode
+    // the user pastes the URL the browser failed to load
+    if !manual {
+        server::ensure_server_running().await.map_err(|e| RpcError {
+            code: -32000,
+            message: format!("Failed to start OAuth callback server: {}", e),
+        })?;
+    }
+    
+    Ok(serde_json::json!({
+        "requires_auth": true,
+        "mode": if manual { "manual" } else { "callback" },
         "auth_url": auth_url,
         "state": state,
+        "provider": authorization.provider_id,
+        "scopes": authorization.scopes,
     }))
 }
 
@@ -353,44 +837,186 @@ pub async fn rpc_get_tokens(params: serde_json::Value) -> Result<serde_json::Val
             message: "Missing 'server_id' parameter".to_string(),
         })?;
     
-    // Use get_access_token which handles refresh automatically
-    let mut store = get_token_store_mut().await;
+    // Check if we have tokens at all
+    let stored = get_token_store()
+        .await
+        .as_ref()
+        .and_then(|s| s.get_tokens(server_id).cloned());
+    let stored = match stored {
+        Some(stored) => stored,
+        None => {
+            return Ok(serde_json::json!({
+                "has_tokens": false,
+            }));
+        }
+    };
     
-    match store.as_mut() {
-        Some(s) => {
-            // Check if we have tokens at all
-            if !s.has_tokens(server_id) {
-                return Ok(serde_json::json!({
-                    "has_tokens": false,
-                }));
-            }
-            
-            // Get access token (this will refresh if needed)
-            match s.get_access_token(server_id).await {
-                Ok(access_token) => {
-                    // Get the stored data for additional info
-                    let stored = s.get_tokens(server_id);
-                    Ok(serde_json::json!({
-                        "has_tokens": true,
-                        "access_token": access_token,
-                        "expires_at": stored.map(|t| t.tokens.expires_at).flatten(),
-                        "provider": stored.map(|t| &t.provider),
-                        "scopes": stored.map(|t| &t.scopes),
-                    }))
-                }
-                Err(e) => {
-                    tracing::error!("Failed to get/refresh access token: {}", e);
-                    Err(RpcError {
-                        code: -32000,
-                        message: format!("Failed to get access token: {}", e),
-                    })
+    // Refuse tokens broader than the server declared
+    if let Some(policy) = get_scope_policy(server_id).await {
+        if let Err(e) = policy.check_tokens(&stored) {
+            tracing::warn!("Refusing tokens for {}: {}", server_id, e);
+            return Err(RpcError {
+                code: -32000,
+                message: format!("Scope policy for '{}' rejected stored tokens: {}", server_id, e),
+            });
+        }
+    }
+    
+    // Get access token (this will refresh if needed)
+    match get_access_token(server_id).await {
+        Ok(access_token) => {
+            // Get the stored data for additional info
+            let store = get_token_store().await;
+            let stored = store.as_ref().and_then(|s| s.get_tokens(server_id));
+            Ok(serde_json::json!({
+                "has_tokens": true,
+                "access_token": access_token,
+                "expires_at": stored.and_then(|t| t.tokens.expires_at),
+                "provider": stored.map(|t| &t.provider),
+                "scopes": stored.map(|t| &t.scopes),
+            }))
+        }
+        Err(e) => {
+            tracing::error!("Failed to get/refresh access token: {}", e);
+            Err(RpcError {
+                code: -32000,
+                message: format!("Failed to get access token: {}", e),
+            })
+        }
+    }
+}
+
+/// Declare the OAuth provider and scopes a server is allowed to use.
+pub async fn rpc_set_scope_policy(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    crate::rpc::require_extension("change OAuth scope policies")?;
+    let server_id = params.get("server_id")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| RpcError {
+            code: -32602,
+            message: "Missing 'server_id' parameter".to_string(),
+        })?;
+    
+    let policy: ScopePolicy = serde_json::from_value(params.clone()).map_err(|e| RpcError {
+        code: -32602,
+        message: format!("Invalid params: {}", e),
+    })?;
+    
+    let mut policies = state().policies.write().await;
+    policies.insert(server_id.to_string(), policy);
+    policy::save(&policies).map_err(|e| RpcError {
+        code: -32000,
+        message: format!("Failed to save scope policy: {}", e),
+    })?;
+    
+    tracing::info!("Set OAuth scope policy for {}", server_id);
+    
+    Ok(serde_json::json!({
+        "success": true,
+    }))
+}
+
+/// Remove a server's scope policy.
+pub async fn rpc_remove_scope_policy(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    crate::rpc::require_extension("change OAuth scope policies")?;
+    let server_id = params.get("server_id")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| RpcError {
+            code: -32602,
+            message: "Missing 'server_id' parameter".to_string(),
+        })?;
+    
+    let mut policies = state().policies.write().await;
+    if policies.remove(server_id).is_some() {
+        policy::save(&policies).map_err(|e| RpcError {
+            code: -32000,
+            message: format!("Failed to save scope policy: {}", e),
+        })?;
+    }
+    
+    Ok(serde_json::json!({
+        "success": true,
+    }))
+}
+
+/// Import credentials stored by other MCP hosts.
+///
+/// Params: `source` (currently only "mcp-remote"), optional `servers`
+/// (`[{server_id, url}]`, defaults to the mcp-remote servers configured in
+/// Claude Desktop) and `dry_run`. Servers that already have Harbor tokens
+/// are skipped.
+pub async fn rpc_import_external(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    crate::rpc::require_extension("import OAuth tokens")?;
+    let source = params.get("source").and_then(|v| v.as_str()).unwrap_or("mcp-remote");
+    if source != "mcp-remote" {
+        return Err(RpcError {
+            code: -32602,
+            message: format!("Unsupported import source: {}", source),
+        });
+    }
+    let dry_run = params.get("dry_run").and_then(|v| v.as_bool()).unwrap_or(false);
+    
+    let targets: Vec<import::ImportTarget> = match params.get("servers") {
+        Some(servers) => serde_json::from_value(servers.clone()).map_err(|e| RpcError {
+            code: -32602,
+            message: format!("Invalid 'servers' parameter: {}", e),
+        })?,
+        None => import::claude_desktop_targets(),
+    };
+    
+    let mut results = Vec::new();
+    for target in targets {
+        let existing = get_token_store()
+            .await
+            .as_ref()
+            .map(|s| s.has_tokens(&target.server_id))
+            .unwrap_or(false);
+        if existing {
+            results.push(import::ImportResult {
+                server_id: target.server_id,
+                url: target.url,
+                imported: false,
+                reason: Some("Server already has Harbor tokens".to_string()),
+            });
+            continue;
+        }
+        
+        let outcome = match import::import_mcp_remote(&target, dry_run).await {
+            Ok(stored) if dry_run => Ok(stored),
+            Ok(stored) => {
+                if let Some(provider) = remote::get_registered(&stored.provider) {
+                    state().credentials.write().await.insert(stored.provider.clone(), provider.credentials);
                 }
+                let mut store = get_token_store_mut().await;
+                let s = store.get_or_insert_with(TokenStore::new);
+                s.set_tokens(&target.server_id, stored.clone());
+                s.save().map(|_| stored)
             }
+            Err(e) => Err(e),
+        };
+        
+        match outcome {
+            Ok(_) => {
+                tracing::info!("Imported mcp-remote credentials for {}", target.server_id);
+                results.push(import::ImportResult {
+                    server_id: target.server_id,
+                    url: target.url,
+                    imported: true,
+                    reason: None,
+                });
+            }
+            Err(e) => results.push(import::ImportResult {
+                server_id: target.server_id,
+                url: target.url,
+                imported: false,
+                reason: Some(e),
+            }),
         }
-        None => Ok(serde_json::json!({
-            "has_tokens": false,
-        })),
     }
+    
+    Ok(serde_json::json!({
+        "dry_run": dry_run,
+        "results": results,
+    }))
 }
 
 /// Check OAuth status for a server.
@@ -406,27 +1032,54 @@ pub async fn rpc_status(params: serde_json::Value) -> Result<serde_json::Value,
     let stored = store.as_ref().and_then(|s| s.get_tokens(server_id));
     
     match stored {
-        Some(tokens) => {
-            let now = chrono::Utc::now().timestamp_millis();
-            let is_expired = tokens.tokens.expires_at
-                .map(|exp| exp < now + 60_000) // Consider expired if < 1 min remaining
-                .unwrap_or(false);
-            
-            Ok(serde_json::json!({
-                "authenticated": true,
-                "provider": tokens.provider,
-                "scopes": tokens.scopes,
-                "is_expired": is_expired,
-                "expires_at": tokens.tokens.expires_at,
-                "has_refresh_token": tokens.tokens.refresh_token.is_some(),
-            }))
-        }
+        Some(tokens) => Ok(token_status(tokens)),
         None => Ok(serde_json::json!({
             "authenticated": false,
         })),
     }
 }
 
+/// List every server with stored tokens and its status.
+pub async fn rpc_list_authenticated(_params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    let store = get_token_store().await;
+
+    let mut servers: Vec<serde_json::Value> = store
+        .as_ref()
+        .map(|s| {
+            s.tokens
+                .iter()
+                .map(|(server_id, tokens)| {
+                    let mut status = token_status(tokens);
+                    status["server_id"] = serde_json::json!(server_id);
+                    status
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    servers.sort_by(|a, b| a["server_id"].as_str().cmp(&b["server_id"].as_str()));
+
+    Ok(serde_json::json!({
+        "servers": servers,
+    }))
+}
+
+/// Status fields reported for a server's stored tokens.
+fn token_status(tokens: &StoredTokens) -> serde_json::Value {
+    let now = chrono::Utc::now().timestamp_millis();
+    let is_expired = tokens.tokens.expires_at
+        .map(|exp| exp < now + 60_000) // Consider expired if < 1 min remaining
+        .unwrap_or(false);
+
+    serde_json::json!({
+        "authenticated": true,
+        "provider": tokens.provider,
+        "scopes": tokens.scopes,
+        "is_expired": is_expired,
+        "expires_at": tokens.tokens.expires_at,
+        "has_refresh_token": tokens.tokens.refresh_token.is_some(),
+    })
+}
+
 /// Revoke OAuth tokens for a server.
 pub async fn rpc_revoke(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
     let server_id = params.get("server_id")
@@ -482,7 +1135,7 @@ pub async fn rpc_list_providers(_params: serde_json::Value) -> Result<serde_json
 
 /// Get OAuth credentials configuration status.
 pub async fn rpc_get_credentials_status(_params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
-    let creds = OAUTH_CREDENTIALS.read().await;
+    let creds = state().credentials.read().await;
     
     let mut providers: HashMap<String, serde_json::Value> = HashMap::new();
     
@@ -599,3 +1252,30 @@ pub async fn rpc_remove_credentials(params: serde_json::Value) -> Result<serde_j
         "provider": provider_id,
     }))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_stop_clears_state() {
+        let state = OAuthState::default();
+        state.credentials.write().await.insert("test".to_string(), OAuthCredentials {
+            client_id: "id".to_string(),
+            client_secret: "secret".to_string(),
+        });
+        *state.token_store.write().await = Some(TokenStore::new());
+
+        state.stop().await;
+
+        assert!(state.credentials.read().await.is_empty());
+        assert!(state.token_store.read().await.is_none());
+    }
+
+    #[test]
+    fn test_refresh_lock_is_per_server() {
+        let state = OAuthState::default();
+        assert!(Arc::ptr_eq(&state.refresh_lock("a"), &state.refresh_lock("a")));
+        assert!(!Arc::ptr_eq(&state.refresh_lock("a"), &state.refresh_lock("b")));
+    }
+}
@@ -0,0 +1,53 @@
+//! Owner-only file permissions for files holding secrets.
+//!
+//! On Unix this is mode 0o600. On Windows the file's DACL is replaced with a
+//! single full-control entry for the current user, with inheritance removed.
+
+use std::path::Path;
+
+/// Restrict a file so only the current user can read or write it.
+#[cfg(unix)]
+pub fn restrict_to_owner(path: &Path) -> Result<(), String> {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))
+        .map_err(|e| format!("Failed to set permissions on {:?}: {}", path, e))
+}
+
+/// Restrict a file so only the current user can read or write it.
+#[cfg(windows)]
+pub fn restrict_to_owner(path: &Path) -> Result<(), String> {
+    use std::os::windows::process::CommandExt;
+
+    // Don't flash a console window when launched by the browser
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+    let user = match (std::env::var("USERDOMAIN"), std::env::var("USERNAME")) {
+        (Ok(domain), Ok(name)) => format!("{}\\{}", domain, name),
+        (Err(_), Ok(name)) => name,
+        _ => return Err("Could not determine current user".to_string()),
+    };
+
+    let output = std::process::Command::new("icacls")
+        .arg(path)
+        .arg("/inheritance:r")
+        .arg("/grant:r")
+        .arg(format!("{}:F", user))
+        .creation_flags(CREATE_NO_WINDOW)
+        .output()
+        .map_err(|e| format!("Failed to run icacls: {}", e))?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "icacls failed on {:?}: {}",
+            path,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+/// Restrict a file so only the current user can read or write it.
+#[cfg(not(any(unix, windows)))]
+pub fn restrict_to_owner(_path: &Path) -> Result<(), String> {
+    Ok(())
+}
@@ -0,0 +1,127 @@
+//! Per-server OAuth scope policies.
+//!
+//! A server can declare the provider and scopes it needs (normally from its
+//! manifest, when it is installed). Once declared, the bridge refuses to
+//! start flows for, or hand out, tokens that use a different provider or
+//! carry scopes beyond the declaration. Servers without a policy are not
+//! restricted.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use super::StoredTokens;
+
+const POLICY_FILE_NAME: &str = "oauth_policy.json";
+
+/// Declared OAuth requirements for a server.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScopePolicy {
+    pub provider: String,
+    pub scopes: Vec<String>,
+}
+
+impl ScopePolicy {
+    /// Check that a flow request stays within the declaration.
+    pub fn check_request(&self, provider: &str, scopes: &[String]) -> Result<(), String> {
+        self.check(provider, scopes)
+    }
+
+    /// Check that stored tokens stay within the declaration.
+    pub fn check_tokens(&self, stored: &StoredTokens) -> Result<(), String> {
+        self.check(&stored.provider, &stored.scopes)
+    }
+
+    fn check(&self, provider: &str, scopes: &[String]) -> Result<(), String> {
+        if provider != self.provider {
+            return Err(format!(
+                "provider '{}' does not match declared provider '{}'",
+                provider, self.provider
+            ));
+        }
+        let undeclared: Vec<&str> = scopes
+            .iter()
+            .filter(|s| !self.scopes.contains(s))
+            .map(|s| s.as_str())
+            .collect();
+        if !undeclared.is_empty() {
+            return Err(format!("scopes not declared by server: {}", undeclared.join(", ")));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct PolicyFile {
+    /// Policies keyed by server ID
+    servers: HashMap<String, ScopePolicy>,
+}
+
+fn policy_file_path() -> PathBuf {
+    let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
+    home.join(".harbor").join(POLICY_FILE_NAME)
+}
+
+/// Load all declared policies.
+pub fn load() -> HashMap<String, ScopePolicy> {
+    let path = policy_file_path();
+    match std::fs::read_to_string(&path) {
+        Ok(contents) => match serde_json::from_str::<PolicyFile>(&contents) {
+            Ok(file) => file.servers,
+            Err(e) => {
+                tracing::warn!("Failed to parse {:?}: {}", path, e);
+                HashMap::new()
+            }
+        },
+        Err(_) => HashMap::new(),
+    }
+}
+
+/// Persist all declared policies.
+pub fn save(policies: &HashMap<String, ScopePolicy>) -> Result<(), String> {
+    let path = policy_file_path();
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create directory: {}", e))?;
+    }
+
+    let file = PolicyFile {
+        servers: policies.clone(),
+    };
+    let json = serde_json::to_string_pretty(&file)
+        .map_err(|e| format!("Failed to serialize scope policies: {}", e))?;
+    std::fs::write(&path, json)
+        .map_err(|e| format!("Failed to write scope policy file: {}", e))?;
+    super::permissions::restrict_to_owner(&path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> ScopePolicy {
+        ScopePolicy {
+            provider: "google".to_string(),
+            scopes: vec!["gmail.readonly".to_string(), "gmail.send".to_string()],
+        }
+    }
+
+    #[test]
+    fn test_subset_allowed() {
+        assert!(policy().check_request("google", &["gmail.readonly".to_string()]).is_ok());
+    }
+
+    #[test]
+    fn test_broader_scopes_rejected() {
+        let err = policy()
+            .check_request("google", &["gmail.readonly".to_string(), "drive".to_string()])
+            .unwrap_err();
+        assert!(err.contains("drive"));
+    }
+
+    #[test]
+    fn test_provider_mismatch_rejected() {
+        assert!(policy().check_request("github", &["gmail.readonly".to_string()]).is_err());
+    }
+}
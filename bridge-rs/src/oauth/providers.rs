@@ -13,6 +13,8 @@ pub fn google_config() -> OAuthProviderConfig {
         token_url: "https://oauth2.googleapis.com/token".to_string(),
         revocation_url: Some("https://oauth2.googleapis.com/revoke".to_string()),
         pkce_enabled: true,
+        resource: None,
+        manual_redirect_uri: None,
     }
 }
 
@@ -25,6 +27,8 @@ pub fn github_config() -> OAuthProviderConfig {
         token_url: "https://github.com/login/oauth/access_token".to_string(),
         revocation_url: None,
         pkce_enabled: false, // GitHub doesn't support PKCE yet
+        resource: None,
+        manual_redirect_uri: None,
     }
 }
 
@@ -33,10 +37,20 @@ pub fn get_provider_config(provider_id: &str) -> Option<OAuthProviderConfig> {
     match provider_id {
         "google" => Some(google_config()),
         "github" => Some(github_config()),
-        _ => None,
+        other => super::remote::get_registered_config(other),
     }
 }
 
+/// A provider's config as used for `server_id`: remote servers sharing an
+/// authorization server each have their own resource indicator.
+pub fn get_server_config(provider_id: &str, server_id: &str) -> Option<OAuthProviderConfig> {
+    let mut config = get_provider_config(provider_id)?;
+    if let Some(resource) = super::remote::resource_for(provider_id, server_id) {
+        config.resource = Some(resource);
+    }
+    Some(config)
+}
+
 /// Common Google OAuth scopes.
 #[allow(dead_code)]
 pub mod google_scopes {
@@ -68,3 +82,71 @@ pub mod github_scopes {
     pub const USER_EMAIL: &str = "user:email";
     pub const GIST: &str = "gist";
 }
+
+#[cfg(test)]
+mod tests {
+    use super::google_scopes::*;
+
+    /// The built-in Google servers, with their manifests and harbor.toml.
+    const GOOGLE_SERVERS: [(&str, &str, &str); 3] = [
+        (
+            "gmail-wasm",
+            include_str!("../../../mcp-servers/builtin/gmail-wasm/manifest.json"),
+            include_str!("../../../mcp-servers/builtin/gmail-wasm/harbor.toml"),
+        ),
+        (
+            "drive-wasm",
+            include_str!("../../../mcp-servers/builtin/drive-wasm/manifest.json"),
+            include_str!("../../../mcp-servers/builtin/drive-wasm/harbor.toml"),
+        ),
+        (
+            "calendar-wasm",
+            include_str!("../../../mcp-servers/builtin/calendar-wasm/manifest.json"),
+            include_str!("../../../mcp-servers/builtin/calendar-wasm/harbor.toml"),
+        ),
+    ];
+
+    #[test]
+    fn test_builtin_servers_use_known_scopes() {
+        let known = [
+            GMAIL_READONLY,
+            GMAIL_SEND,
+            GMAIL_MODIFY,
+            DRIVE_READONLY,
+            DRIVE_FILE,
+            DRIVE_FULL,
+            CALENDAR_READONLY,
+            CALENDAR_EVENTS,
+        ];
+        for (server, manifest, harbor_toml) in GOOGLE_SERVERS {
+            let manifest: serde_json::Value = serde_json::from_str(manifest).unwrap();
+            let declared: toml::Value = toml::from_str(harbor_toml).unwrap();
+            let in_manifest: Vec<&str> = manifest["oauth"]["scopes"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .filter_map(|s| s.as_str())
+                .collect();
+            let in_toml: Vec<&str> = declared["capabilities"]["oauth"]["scopes"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .filter_map(|s| s.as_str())
+                .collect();
+            assert_eq!(in_manifest, in_toml, "{}", server);
+            for scope in in_manifest {
+                assert!(known.contains(&scope), "{} asks for unknown scope {}", server, scope);
+            }
+        }
+
+        // What the calendar server asks the bridge for
+        let calendar = include_str!("../../../mcp-servers/builtin/calendar-wasm/src/lib.rs");
+        for (name, scope) in [("READONLY_SCOPE", CALENDAR_READONLY), ("EVENTS_SCOPE", CALENDAR_EVENTS)] {
+            assert!(
+                calendar.contains(&format!("const {}: &str = \"{}\";", name, scope)),
+                "{}",
+                name
+            );
+        }
+    }
+}
@@ -0,0 +1,690 @@
+//! OAuth 2.1 client for remote MCP servers.
+//!
+//! Remote MCP servers advertise their authorization requirements by answering
+//! unauthenticated requests with `401 Unauthorized` and a `WWW-Authenticate`
+//! challenge. This module follows the MCP authorization spec:
+//!
+//! 1. Parse the challenge and fetch the protected resource metadata (RFC 9728)
+//! 2. Discover the authorization server metadata (RFC 8414 / OpenID discovery)
+//! 3. Register a client dynamically if we don't have one yet (RFC 7591)
+//! 4. Run the regular PKCE flow with the `resource` indicator (RFC 8707)
+//! 5. Attach the resulting bearer token to subsequent MCP requests
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::RwLock as StdRwLock;
+
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use super::{OAuthCredentials, OAuthProviderConfig};
+use crate::redact::redact;
+
+const REMOTE_FILE_NAME: &str = "oauth_remote.json";
+const CLIENT_NAME: &str = "Harbor";
+
+/// A parsed `WWW-Authenticate: Bearer ...` challenge.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BearerChallenge {
+    pub realm: Option<String>,
+    /// URL of the protected resource metadata document (RFC 9728)
+    pub resource_metadata: Option<String>,
+    /// Scopes the resource asks for
+    pub scope: Option<String>,
+    pub error: Option<String>,
+    pub error_description: Option<String>,
+}
+
+/// Protected resource metadata (RFC 9728).
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProtectedResourceMetadata {
+    #[serde(default)]
+    pub resource: Option<String>,
+    #[serde(default)]
+    pub authorization_servers: Vec<String>,
+    #[serde(default)]
+    pub scopes_supported: Vec<String>,
+}
+
+/// Authorization server metadata (RFC 8414).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthorizationServerMetadata {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    #[serde(default)]
+    pub registration_endpoint: Option<String>,
+    #[serde(default)]
+    pub revocation_endpoint: Option<String>,
+    #[serde(default)]
+    pub scopes_supported: Vec<String>,
+    #[serde(default)]
+    pub code_challenge_methods_supported: Vec<String>,
+}
+
+/// A remote authorization server we have registered with.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemoteProvider {
+    /// Provider config used by the generic flow code
+    pub config: OAuthProviderConfig,
+    /// Client obtained through dynamic registration (or supplied by the user)
+    pub credentials: OAuthCredentials,
+    /// Discovered metadata, kept for diagnostics
+    pub metadata: AuthorizationServerMetadata,
+    /// Resource indicator (RFC 8707) of each server authorized through this
+    /// authorization server, by server ID
+    #[serde(default)]
+    pub resources: HashMap<String, String>,
+}
+
+/// Persisted remote provider registrations.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct RemoteProvidersFile {
+    /// Map of provider_id -> registration
+    providers: HashMap<String, RemoteProvider>,
+}
+
+lazy_static::lazy_static! {
+    /// Remote providers, keyed by provider ID (e.g. "remote:mcp.example.com").
+    ///
+    /// Uses a std lock because `providers::get_provider_config` is synchronous.
+    static ref REMOTE_PROVIDERS: StdRwLock<HashMap<String, RemoteProvider>> =
+        StdRwLock::new(HashMap::new());
+}
+
+// ============================================================================
+// Challenge Parsing
+// ============================================================================
+
+/// Parse a `WWW-Authenticate` header value into a Bearer challenge.
+///
+/// Returns `None` if the header doesn't contain a Bearer challenge.
+pub fn parse_www_authenticate(header: &str) -> Option<BearerChallenge> {
+    let trimmed = header.trim();
+    let scheme_end = trimmed.find(char::is_whitespace).unwrap_or(trimmed.len());
+    if !trimmed[..scheme_end].eq_ignore_ascii_case("bearer") {
+        return None;
+    }
+
+    let mut challenge = BearerChallenge::default();
+    for (key, value) in parse_auth_params(&trimmed[scheme_end..]) {
+        match key.to_ascii_lowercase().as_str() {
+            "realm" => challenge.realm = Some(value),
+            "resource_metadata" => challenge.resource_metadata = Some(value),
+            "scope" => challenge.scope = Some(value),
+            "error" => challenge.error = Some(value),
+            "error_description" => challenge.error_description = Some(value),
+            _ => {}
+        }
+    }
+    Some(challenge)
+}
+
+/// Parse `key=value, key="quoted value"` auth-params.
+fn parse_auth_params(input: &str) -> Vec<(String, String)> {
+    let mut params = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    loop {
+        // Skip separators
+        while matches!(chars.peek(), Some(c) if c.is_whitespace() || *c == ',') {
+            chars.next();
+        }
+        let mut key = String::new();
+        while let Some(&c) = chars.peek() {
+            if c == '=' || c == ',' || c.is_whitespace() {
+                break;
+            }
+            key.push(c);
+            chars.next();
+        }
+        if key.is_empty() {
+            break;
+        }
+        while matches!(chars.peek(), Some(c) if c.is_whitespace()) {
+            chars.next();
+        }
+        if chars.peek() != Some(&'=') {
+            continue;
+        }
+        chars.next();
+        while matches!(chars.peek(), Some(c) if c.is_whitespace()) {
+            chars.next();
+        }
+
+        let mut value = String::new();
+        if chars.peek() == Some(&'"') {
+            chars.next();
+            while let Some(c) = chars.next() {
+                match c {
+                    '\\' => {
+                        if let Some(escaped) = chars.next() {
+                            value.push(escaped);
+                        }
+                    }
+                    '"' => break,
+                    _ => value.push(c),
+                }
+            }
+        } else {
+            while let Some(&c) = chars.peek() {
+                if c == ',' {
+                    break;
+                }
+                value.push(c);
+                chars.next();
+            }
+            value = value.trim().to_string();
+        }
+        params.push((key, value));
+    }
+
+    params
+}
+
+// ============================================================================
+// Discovery
+// ============================================================================
+
+/// Build a well-known URL per RFC 8414 section 3.1 (path is appended after the
+/// well-known segment, e.g. `https://host/.well-known/x/tenant`).
+fn well_known_url(base: &str, suffix: &str) -> Result<String, String> {
+    let url = Url::parse(base).map_err(|e| format!("Invalid URL '{}': {}", base, e))?;
+    let origin = url.origin().ascii_serialization();
+    let path = url.path().trim_end_matches('/');
+    Ok(format!("{}/.well-known/{}{}", origin, suffix, path))
+}
+
+/// Fetch the protected resource metadata for a server.
+pub async fn fetch_resource_metadata(
+    server_url: &str,
+    challenge: &BearerChallenge,
+) -> Result<ProtectedResourceMetadata, String> {
+    let metadata_url = match &challenge.resource_metadata {
+        Some(url) => url.clone(),
+        None => well_known_url(server_url, "oauth-protected-resource")?,
+    };
+
+    tracing::info!("Fetching protected resource metadata from {}", metadata_url);
+    fetch_json(&metadata_url).await
+}
+
+/// Discover authorization server metadata, falling back to OpenID discovery.
+pub async fn discover_authorization_server(issuer: &str) -> Result<AuthorizationServerMetadata, String> {
+    let candidates = [
+        well_known_url(issuer, "oauth-authorization-server")?,
+        well_known_url(issuer, "openid-configuration")?,
+    ];
+
+    let mut last_error = String::new();
+    for url in &candidates {
+        match fetch_json::<AuthorizationServerMetadata>(url).await {
+            Ok(metadata) => {
+                tracing::info!("Discovered authorization server {} via {}", metadata.issuer, url);
+                return Ok(metadata);
+            }
+            Err(e) => last_error = e,
+        }
+    }
+
+    Err(format!("Authorization server discovery failed for {}: {}", issuer, last_error))
+}
+
+async fn fetch_json<T: serde::de::DeserializeOwned>(url: &str) -> Result<T, String> {
+    let response = reqwest::Client::new()
+        .get(url)
+        .header("Accept", "application/json")
+        .send()
+        .await
+        .map_err(|e| format!("Request to {} failed: {}", url, e))?;
+
+    if !response.status().is_success() {
+        return Err(format!("{} returned {}", url, response.status()));
+    }
+
+    response
+        .json()
+        .await
+        .map_err(|e| format!("Invalid metadata from {}: {}", url, e))
+}
+
+/// Register Harbor as a public client (RFC 7591).
+pub async fn register_client(
+    metadata: &AuthorizationServerMetadata,
+    redirect_uri: &str,
+) -> Result<OAuthCredentials, String> {
+    let endpoint = metadata.registration_endpoint.as_ref().ok_or_else(|| {
+        format!(
+            "Authorization server {} does not support dynamic client registration; configure a client manually",
+            metadata.issuer
+        )
+    })?;
+
+    let request = serde_json::json!({
+        "client_name": CLIENT_NAME,
+        "redirect_uris": [redirect_uri],
+        "grant_types": ["authorization_code", "refresh_token"],
+        "response_types": ["code"],
+        "token_endpoint_auth_method": "none",
+    });
+
+    let response = reqwest::Client::new()
+        .post(endpoint)
+        .header("Accept", "application/json")
+        .json(&request)
+        .send()
+        .await
+        .map_err(|e| format!("Client registration failed: {}", e))?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        return Err(format!("Client registration failed: {} - {}", status, redact(&body)));
+    }
+
+    #[derive(Deserialize)]
+    struct RegistrationResponse {
+        client_id: String,
+        #[serde(default)]
+        client_secret: Option<String>,
+    }
+
+    let registration: RegistrationResponse = response
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse registration response: {}", e))?;
+
+    tracing::info!("Registered OAuth client with {}", metadata.issuer);
+
+    Ok(OAuthCredentials {
+        client_id: registration.client_id,
+        client_secret: registration.client_secret.unwrap_or_default(),
+    })
+}
+
+// ============================================================================
+// Registry
+// ============================================================================
+
+/// Provider ID used for a remote authorization server: its host, port and
+/// path, since issuers on one host (e.g. per-tenant) are separate servers.
+pub fn provider_id_for_issuer(issuer: &str) -> String {
+    let id = Url::parse(issuer)
+        .ok()
+        .and_then(|u| {
+            let host = u.host_str()?;
+            let port = u.port().map(|p| format!(":{}", p)).unwrap_or_default();
+            Some(format!("{}{}{}", host, port, u.path().trim_end_matches('/')))
+        })
+        .unwrap_or_else(|| issuer.to_string());
+    format!("remote:{}", id)
+}
+
+/// Look up a registered remote provider config.
+pub fn get_registered_config(provider_id: &str) -> Option<OAuthProviderConfig> {
+    REMOTE_PROVIDERS
+        .read()
+        .ok()?
+        .get(provider_id)
+        .map(|p| p.config.clone())
+}
+
+/// Get a registered remote provider.
+pub fn get_registered(provider_id: &str) -> Option<RemoteProvider> {
+    REMOTE_PROVIDERS.read().ok()?.get(provider_id).cloned()
+}
+
+/// The resource indicator to send when authorizing `server_id` through a
+/// registered remote provider.
+pub fn resource_for(provider_id: &str, server_id: &str) -> Option<String> {
+    let providers = REMOTE_PROVIDERS.read().ok()?;
+    let provider = providers.get(provider_id)?;
+    // Registrations from before resources were kept per server
+    provider
+        .resources
+        .get(server_id)
+        .or(provider.config.resource.as_ref())
+        .cloned()
+}
+
+fn remote_file_path() -> PathBuf {
+    let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
+    home.join(".harbor").join(REMOTE_FILE_NAME)
+}
+
+/// Load persisted remote registrations. Returns the loaded providers so the
+/// caller can register their client credentials.
+pub fn load() -> Vec<(String, OAuthCredentials)> {
+    let path = remote_file_path();
+    let file: RemoteProvidersFile = match std::fs::read_to_string(&path) {
+        Ok(contents) => match serde_json::from_str(&contents) {
+            Ok(f) => f,
+            Err(e) => {
+                tracing::warn!("Failed to parse {:?}: {}", path, e);
+                return Vec::new();
+            }
+        },
+        Err(_) => return Vec::new(),
+    };
+
+    let loaded: Vec<(String, OAuthCredentials)> = file
+        .providers
+        .iter()
+        .map(|(id, p)| (id.clone(), p.credentials.clone()))
+        .collect();
+
+    if let Ok(mut registry) = REMOTE_PROVIDERS.write() {
+        *registry = file.providers;
+    }
+
+    loaded
+}
+
+fn save() -> Result<(), String> {
+    let file = RemoteProvidersFile {
+        providers: REMOTE_PROVIDERS
+            .read()
+            .map_err(|_| "Remote provider registry poisoned".to_string())?
+            .clone(),
+    };
+
+    let path = remote_file_path();
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create directory: {}", e))?;
+    }
+
+    let json = serde_json::to_string_pretty(&file)
+        .map_err(|e| format!("Failed to serialize remote providers: {}", e))?;
+    std::fs::write(&path, json)
+        .map_err(|e| format!("Failed to write remote providers file: {}", e))?;
+
+    super::permissions::restrict_to_owner(&path)
+}
+
+fn register(provider_id: &str, provider: RemoteProvider) -> Result<(), String> {
+    REMOTE_PROVIDERS
+        .write()
+        .map_err(|_| "Remote provider registry poisoned".to_string())?
+        .insert(provider_id.to_string(), provider);
+    save()
+}
+
+// ============================================================================
+// Authorization
+// ============================================================================
+
+/// Everything needed to start a flow against a remote server.
+#[derive(Debug, Clone)]
+pub struct RemoteAuthorization {
+    pub provider_id: String,
+    pub credentials: OAuthCredentials,
+    pub scopes: Vec<String>,
+}
+
+/// Probe a remote MCP server and return its challenge, if it requires auth.
+pub async fn probe(server_url: &str) -> Result<Option<BearerChallenge>, String> {
+    let response = reqwest::Client::new()
+        .post(server_url)
+        .header("Accept", "application/json, text/event-stream")
+        .json(&serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 0,
+            "method": "ping",
+        }))
+        .send()
+        .await
+        .map_err(|e| format!("Failed to reach {}: {}", server_url, e))?;
+
+    if response.status() != reqwest::StatusCode::UNAUTHORIZED {
+        return Ok(None);
+    }
+
+    let header = response
+        .headers()
+        .get(reqwest::header::WWW_AUTHENTICATE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("Bearer");
+
+    Ok(Some(parse_www_authenticate(header).unwrap_or_default()))
+}
+
+/// Authorization server details discovered for a remote server.
+struct Discovery {
+    provider_id: String,
+    metadata: AuthorizationServerMetadata,
+    resource: Option<ProtectedResourceMetadata>,
+    /// Resource indicator to send with requests
+    resource_id: String,
+}
+
+/// Find the authorization server for a remote MCP server.
+async fn discover(server_url: &str, challenge: &BearerChallenge) -> Result<Discovery, String> {
+    let resource = fetch_resource_metadata(server_url, challenge).await.ok();
+
+    let issuer = resource
+        .as_ref()
+        .and_then(|r| r.authorization_servers.first().cloned())
+        .unwrap_or_else(|| {
+            // Legacy servers act as their own authorization server
+            Url::parse(server_url)
+                .map(|u| u.origin().ascii_serialization())
+                .unwrap_or_else(|_| server_url.to_string())
+        });
+
+    let metadata = discover_authorization_server(&issuer).await?;
+
+    if !metadata.code_challenge_methods_supported.is_empty()
+        && !metadata.code_challenge_methods_supported.iter().any(|m| m == "S256")
+    {
+        return Err(format!("Authorization server {} does not support PKCE S256", metadata.issuer));
+    }
+
+    let provider_id = provider_id_for_issuer(&metadata.issuer);
+    let resource_id = resource
+        .as_ref()
+        .and_then(|r| r.resource.clone())
+        .unwrap_or_else(|| server_url.to_string());
+
+    Ok(Discovery {
+        provider_id,
+        metadata,
+        resource,
+        resource_id,
+    })
+}
+
+/// Register a discovered provider with the given client, and the resource
+/// indicator `server_id` is authorized for. Other servers authorized through
+/// the same provider keep theirs.
+fn register_discovered(discovery: &Discovery, server_id: &str, credentials: OAuthCredentials) -> Result<(), String> {
+    let metadata = &discovery.metadata;
+    let mut resources = get_registered(&discovery.provider_id)
+        .map(|existing| existing.resources)
+        .unwrap_or_default();
+    resources.insert(server_id.to_string(), discovery.resource_id.clone());
+    let config = OAuthProviderConfig {
+        provider_id: discovery.provider_id.clone(),
+        display_name: metadata.issuer.clone(),
+        authorization_url: metadata.authorization_endpoint.clone(),
+        token_url: metadata.token_endpoint.clone(),
+        revocation_url: metadata.revocation_endpoint.clone(),
+        // OAuth 2.1 requires PKCE for every client
+        pkce_enabled: true,
+        // Kept per server instead; see `resource_for`
+        resource: None,
+        // Registered clients only allow the localhost callback
+        manual_redirect_uri: None,
+    };
+
+    register(&discovery.provider_id, RemoteProvider {
+        config,
+        credentials,
+        metadata: metadata.clone(),
+        resources,
+    })
+}
+
+/// Resolve a challenge into a registered provider and requested scopes.
+///
+/// Reuses an existing registration for the same authorization server.
+pub async fn prepare(
+    server_id: &str,
+    server_url: &str,
+    challenge: &BearerChallenge,
+    redirect_uri: &str,
+) -> Result<RemoteAuthorization, String> {
+    let discovery = discover(server_url, challenge).await?;
+
+    let credentials = match get_registered(&discovery.provider_id) {
+        Some(existing) => existing.credentials,
+        None => register_client(&discovery.metadata, redirect_uri).await?,
+    };
+    register_discovered(&discovery, server_id, credentials.clone())?;
+
+    let scopes: Vec<String> = challenge
+        .scope
+        .as_ref()
+        .map(|s| s.split_whitespace().map(String::from).collect())
+        .or_else(|| discovery.resource.as_ref().map(|r| r.scopes_supported.clone()))
+        .filter(|s: &Vec<String>| !s.is_empty())
+        .unwrap_or_else(|| discovery.metadata.scopes_supported.clone());
+
+    Ok(RemoteAuthorization {
+        provider_id: discovery.provider_id,
+        credentials,
+        scopes,
+    })
+}
+
+/// Register a client obtained elsewhere (e.g. imported from another host)
+/// for a remote server's authorization server. Returns the provider ID.
+///
+/// Fails if Harbor already has a different client for that server, since
+/// tokens can only be refreshed by the client they were issued to.
+pub async fn adopt_client(
+    server_id: &str,
+    server_url: &str,
+    challenge: &BearerChallenge,
+    credentials: OAuthCredentials,
+    dry_run: bool,
+) -> Result<String, String> {
+    let discovery = discover(server_url, challenge).await?;
+
+    if let Some(existing) = get_registered(&discovery.provider_id) {
+        if existing.credentials.client_id != credentials.client_id {
+            return Err(format!(
+                "Harbor already has a different client for {}; authorize this server again instead",
+                discovery.metadata.issuer
+            ));
+        }
+    }
+
+    if !dry_run {
+        register_discovered(&discovery, server_id, credentials)?;
+    }
+    Ok(discovery.provider_id)
+}
+
+/// Send an authorized JSON-RPC request to a remote MCP server.
+///
+/// Attaches the stored bearer token (refreshing it if needed). A `401` is
+/// reported with the parsed challenge so the caller can re-run authorization.
+#[allow(dead_code)]
+pub async fn send(
+    server_id: &str,
+    server_url: &str,
+    body: &serde_json::Value,
+) -> Result<reqwest::Response, String> {
+    let has_tokens = super::get_token_store()
+        .await
+        .as_ref()
+        .map(|s| s.has_tokens(server_id))
+        .unwrap_or(false);
+    let access_token = if has_tokens {
+        Some(super::get_access_token(server_id).await?)
+    } else {
+        None
+    };
+
+    let mut request = reqwest::Client::new()
+        .post(server_url)
+        .header("Accept", "application/json, text/event-stream")
+        .json(body);
+    if let Some(token) = access_token {
+        request = request.bearer_auth(token);
+    }
+
+    let response = request
+        .send()
+        .await
+        .map_err(|e| format!("Request to {} failed: {}", server_url, e))?;
+
+    if response.status() == reqwest::StatusCode::UNAUTHORIZED {
+        let challenge = response
+            .headers()
+            .get(reqwest::header::WWW_AUTHENTICATE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(parse_www_authenticate)
+            .unwrap_or_default();
+        return Err(format!(
+            "Remote server requires authorization{}",
+            challenge
+                .error_description
+                .map(|d| format!(": {}", d))
+                .unwrap_or_default()
+        ));
+    }
+
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_www_authenticate() {
+        let challenge = parse_www_authenticate(
+            r#"Bearer realm="mcp", resource_metadata="https://mcp.example.com/.well-known/oauth-protected-resource", scope="read write""#,
+        )
+        .unwrap();
+        assert_eq!(challenge.realm.as_deref(), Some("mcp"));
+        assert_eq!(
+            challenge.resource_metadata.as_deref(),
+            Some("https://mcp.example.com/.well-known/oauth-protected-resource")
+        );
+        assert_eq!(challenge.scope.as_deref(), Some("read write"));
+    }
+
+    #[test]
+    fn test_parse_www_authenticate_non_bearer() {
+        assert!(parse_www_authenticate(r#"Basic realm="x""#).is_none());
+        assert_eq!(parse_www_authenticate("Bearer"), Some(BearerChallenge::default()));
+    }
+
+    #[test]
+    fn test_well_known_url() {
+        assert_eq!(
+            well_known_url("https://auth.example.com", "oauth-authorization-server").unwrap(),
+            "https://auth.example.com/.well-known/oauth-authorization-server"
+        );
+        assert_eq!(
+            well_known_url("https://auth.example.com/tenant1/", "oauth-authorization-server").unwrap(),
+            "https://auth.example.com/.well-known/oauth-authorization-server/tenant1"
+        );
+    }
+
+    #[test]
+    fn test_provider_id_for_issuer() {
+        assert_eq!(provider_id_for_issuer("https://auth.example.com/"), "remote:auth.example.com");
+        assert_eq!(
+            provider_id_for_issuer("https://auth.example.com:8443/tenant1/"),
+            "remote:auth.example.com:8443/tenant1"
+        );
+        assert_ne!(
+            provider_id_for_issuer("https://auth.example.com/tenant1"),
+            provider_id_for_issuer("https://auth.example.com/tenant2")
+        );
+    }
+}
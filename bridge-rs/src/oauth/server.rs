@@ -12,27 +12,30 @@ use axum::{
     routing::get,
     Router,
 };
-use tokio::sync::{mpsc, oneshot, RwLock};
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
 
-use super::{
-    exchange_code, get_credentials, get_token_store_mut, take_pending_flow, OAuthTokens,
-    StoredTokens,
-};
+use super::{exchange_code, get_credentials, save_tokens, take_pending_flow, OAuthTokens};
 
 const OAUTH_PORT: u16 = 8765;
 const CALLBACK_PATH: &str = "/oauth/callback";
 
-/// OAuth callback server state.
-#[allow(dead_code)]
-pub struct OAuthCallbackServer {
+/// Handle to the running OAuth callback server.
+pub struct CallbackServer {
     /// Channel to signal server shutdown
-    shutdown_tx: Option<oneshot::Sender<()>>,
+    shutdown_tx: oneshot::Sender<()>,
+    server_task: JoinHandle<()>,
+    token_task: JoinHandle<()>,
 }
 
-#[allow(dead_code)]
-impl OAuthCallbackServer {
-    pub fn new() -> Self {
-        Self { shutdown_tx: None }
+impl CallbackServer {
+    /// Stop accepting callbacks and wait for in-flight exchanges to finish.
+    pub async fn shutdown(self) {
+        let _ = self.shutdown_tx.send(());
+        let _ = self.server_task.await;
+        // The token channel closes once the server (and its state) is dropped
+        let _ = self.token_task.await;
+        tracing::info!("OAuth callback server stopped");
     }
 }
 
@@ -50,23 +53,15 @@ struct TokenResult {
     scopes: Vec<String>,
 }
 
-// Global server state
-lazy_static::lazy_static! {
-    static ref SERVER_RUNNING: Arc<RwLock<bool>> = Arc::new(RwLock::new(false));
-    static ref TOKEN_CHANNEL: Arc<RwLock<Option<mpsc::Sender<TokenResult>>>> = 
-        Arc::new(RwLock::new(None));
-}
-
 /// Ensure the OAuth callback server is running.
 pub async fn ensure_server_running() -> Result<(), String> {
-    let mut running = SERVER_RUNNING.write().await;
-    if *running {
+    let mut server = super::state().callback_server.lock().await;
+    if server.is_some() {
         return Ok(());
     }
     
     // Create channel for token results
     let (tx, mut rx) = mpsc::channel::<TokenResult>(10);
-    *TOKEN_CHANNEL.write().await = Some(tx.clone());
     
     // Start the server
     let state = Arc::new(ServerState { token_sender: tx });
@@ -90,22 +85,31 @@ pub async fn ensure_server_running() -> Result<(), String> {
         })?;
     
     tracing::info!("OAuth callback server listening on http://127.0.0.1:{}", OAUTH_PORT);
-    *running = true;
     
     // Spawn server task
-    tokio::spawn(async move {
-        if let Err(e) = axum::serve(listener, app).await {
+    let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+    let server_task = tokio::spawn(async move {
+        let shutdown = async {
+            let _ = shutdown_rx.await;
+        };
+        if let Err(e) = axum::serve(listener, app).with_graceful_shutdown(shutdown).await {
             tracing::error!("OAuth server error: {}", e);
         }
     });
     
     // Spawn token handler task
-    tokio::spawn(async move {
+    let token_task = tokio::spawn(async move {
         while let Some(result) = rx.recv().await {
             handle_token_result(result).await;
         }
     });
     
+    *server = Some(CallbackServer {
+        shutdown_tx,
+        server_task,
+        token_task,
+    });
+    
     Ok(())
 }
 
@@ -113,22 +117,7 @@ pub async fn ensure_server_running() -> Result<(), String> {
 async fn handle_token_result(result: TokenResult) {
     match result.tokens {
         Ok(tokens) => {
-            let mut store = get_token_store_mut().await;
-            if let Some(ref mut s) = *store {
-                let stored = StoredTokens {
-                    server_id: result.server_id.clone(),
-                    provider: result.provider,
-                    tokens,
-                    scopes: result.scopes,
-                    created_at: chrono::Utc::now().timestamp_millis(),
-                    updated_at: chrono::Utc::now().timestamp_millis(),
-                };
-                s.set_tokens(&result.server_id, stored);
-                if let Err(e) = s.save() {
-                    tracing::error!("Failed to save tokens: {}", e);
-                }
-            }
-            tracing::info!("OAuth tokens stored for server: {}", result.server_id);
+            save_tokens(&result.server_id, &result.provider, &result.scopes, tokens).await;
         }
         Err(e) => {
             tracing::error!("OAuth token exchange failed: {}", e);
@@ -138,38 +127,12 @@ async fn handle_token_result(result: TokenResult) {
 
 /// Handle root path - just show a simple page.
 async fn handle_root() -> impl IntoResponse {
-    Html(
-        r#"<!DOCTYPE html>
-<html>
-<head>
-    <title>Harbor OAuth</title>
-    <style>
-        body {
-            font-family: -apple-system, BlinkMacSystemFont, "Segoe UI", Roboto, sans-serif;
-            display: flex;
-            align-items: center;
-            justify-content: center;
-            min-height: 100vh;
-            margin: 0;
-            background: linear-gradient(135deg, #1a1a2e 0%, #16213e 100%);
-            color: #fff;
-        }
-        .container {
-            text-align: center;
-            padding: 40px;
-            background: rgba(255, 255, 255, 0.05);
-            border-radius: 16px;
-        }
-    </style>
-</head>
-<body>
-    <div class="container">
-        <h1>Harbor OAuth Server</h1>
-        <p>This server handles OAuth callbacks for Harbor.</p>
-    </div>
-</body>
-</html>"#,
-    )
+    Html(render_page(
+        PageKind::Info,
+        "Harbor OAuth Server",
+        "This server handles OAuth callbacks for Harbor.",
+        None,
+    ))
 }
 
 /// Handle OAuth callback.
@@ -244,72 +207,122 @@ async fn handle_callback(
         Ok(_) => Html(success_page(
             "Authorization Successful",
             "You can close this window and return to Harbor.",
+            &GrantDetails {
+                provider: &flow.provider_id,
+                server_id: &flow.server_id,
+                scopes: &flow.scopes,
+            },
         )),
         Err(e) => Html(error_page("Authorization Failed", &e)),
     }
 }
 
+/// What was granted, shown on the success page.
+struct GrantDetails<'a> {
+    provider: &'a str,
+    server_id: &'a str,
+    scopes: &'a [String],
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum PageKind {
+    Success,
+    Error,
+    Info,
+}
+
+impl PageKind {
+    fn status(self) -> &'static str {
+        match self {
+            PageKind::Success => "success",
+            PageKind::Error => "error",
+            PageKind::Info => "info",
+        }
+    }
+}
+
+/// Escape text for inclusion in HTML.
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
 /// Generate success HTML page.
-fn success_page(title: &str, message: &str) -> String {
-    format!(
-        r#"<!DOCTYPE html>
-<html>
-<head>
-    <meta charset="utf-8">
-    <title>{title} - Harbor</title>
-    <style>
-        * {{ margin: 0; padding: 0; box-sizing: border-box; }}
-        body {{
-            font-family: -apple-system, BlinkMacSystemFont, "Segoe UI", Roboto, sans-serif;
-            display: flex;
-            align-items: center;
-            justify-content: center;
-            min-height: 100vh;
-            background: linear-gradient(135deg, #1a1a2e 0%, #16213e 100%);
-            color: #fff;
-        }}
-        .container {{
-            text-align: center;
-            padding: 40px;
-            background: rgba(255, 255, 255, 0.05);
-            border-radius: 16px;
-            backdrop-filter: blur(10px);
-            border: 1px solid rgba(255, 255, 255, 0.1);
-            max-width: 400px;
-        }}
-        .icon {{ font-size: 48px; margin-bottom: 20px; }}
-        h1 {{ font-size: 24px; margin-bottom: 12px; color: #16a34a; }}
-        p {{ font-size: 16px; color: rgba(255, 255, 255, 0.7); line-height: 1.5; }}
-        .close-hint {{ margin-top: 20px; font-size: 14px; color: rgba(255, 255, 255, 0.4); }}
-    </style>
-</head>
-<body>
-    <div class="container">
-        <div class="icon">✅</div>
-        <h1>{title}</h1>
-        <p>{message}</p>
-        <p class="close-hint">This window will close automatically.</p>
-    </div>
-    <script>
-        setTimeout(() => {{ try {{ window.close(); }} catch (e) {{}} }}, 2000);
-    </script>
-</body>
-</html>"#
-    )
+fn success_page(title: &str, message: &str, grant: &GrantDetails) -> String {
+    render_page(PageKind::Success, title, message, Some(grant))
 }
 
 /// Generate error HTML page.
 fn error_page(title: &str, message: &str) -> String {
-    let escaped_message = message
-        .replace('&', "&amp;")
-        .replace('<', "&lt;")
-        .replace('>', "&gt;");
-    
+    render_page(PageKind::Error, title, message, None)
+}
+
+/// Render a callback page.
+///
+/// The status is conveyed in text (not only by the icon, which is hidden
+/// from assistive technology), announced through a live region, and
+/// repeated in a machine-readable JSON block (`#harbor-oauth-result`) for
+/// automation and tests.
+fn render_page(kind: PageKind, title: &str, message: &str, grant: Option<&GrantDetails>) -> String {
+    let (icon, status_label, heading_color, role) = match kind {
+        PageKind::Success => ("✅", "Success", "#16a34a", "status"),
+        PageKind::Error => ("❌", "Error", "#dc2626", "alert"),
+        PageKind::Info => ("⚓", "Information", "#ffffff", "status"),
+    };
+
+    let mut metadata = serde_json::json!({
+        "status": kind.status(),
+        "title": title,
+        "message": message,
+    });
+    let mut details = String::new();
+    if let Some(grant) = grant {
+        metadata["provider"] = serde_json::json!(grant.provider);
+        metadata["server_id"] = serde_json::json!(grant.server_id);
+        metadata["scopes"] = serde_json::json!(grant.scopes);
+
+        let scopes: String = grant
+            .scopes
+            .iter()
+            .map(|s| format!("<li>{}</li>", escape_html(s)))
+            .collect();
+        details = format!(
+            r#"
+        <section aria-labelledby="grant-heading">
+            <h2 id="grant-heading">Permissions granted</h2>
+            <dl>
+                <dt>Provider</dt><dd>{provider}</dd>
+                <dt>Server</dt><dd>{server}</dd>
+            </dl>
+            <ul aria-label="Scopes">{scopes}</ul>
+        </section>"#,
+            provider = escape_html(grant.provider),
+            server = escape_html(grant.server_id),
+        );
+    }
+    // Keep the JSON from terminating the script element early
+    let metadata = metadata.to_string().replace("</", "<\\/");
+
+    let close_hint = if kind == PageKind::Success {
+        r#"
+        <p class="close-hint">This window will close automatically.</p>
+    <script>
+        setTimeout(() => { try { window.close(); } catch (e) {} }, 2000);
+    </script>"#
+    } else {
+        ""
+    };
+
     format!(
         r#"<!DOCTYPE html>
-<html>
+<html lang="en">
 <head>
     <meta charset="utf-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1">
+    <meta name="harbor-oauth-status" content="{status}">
     <title>{title} - Harbor</title>
     <style>
         * {{ margin: 0; padding: 0; box-sizing: border-box; }}
@@ -322,7 +335,7 @@ fn error_page(title: &str, message: &str) -> String {
             background: linear-gradient(135deg, #1a1a2e 0%, #16213e 100%);
             color: #fff;
         }}
-        .container {{
+        main {{
             text-align: center;
             padding: 40px;
             background: rgba(255, 255, 255, 0.05);
@@ -332,17 +345,54 @@ fn error_page(title: &str, message: &str) -> String {
             max-width: 400px;
         }}
         .icon {{ font-size: 48px; margin-bottom: 20px; }}
-        h1 {{ font-size: 24px; margin-bottom: 12px; color: #dc2626; }}
-        p {{ font-size: 16px; color: rgba(255, 255, 255, 0.7); line-height: 1.5; }}
+        h1 {{ font-size: 24px; margin-bottom: 12px; color: {heading_color}; }}
+        h2 {{ font-size: 16px; margin: 20px 0 8px; }}
+        p, dl, ul {{ font-size: 16px; color: rgba(255, 255, 255, 0.85); line-height: 1.5; }}
+        dt {{ font-weight: 600; }}
+        ul {{ list-style: none; }}
+        .close-hint {{ margin-top: 20px; font-size: 14px; color: rgba(255, 255, 255, 0.7); }}
+        .visually-hidden {{
+            position: absolute; width: 1px; height: 1px; overflow: hidden;
+            clip: rect(0 0 0 0); white-space: nowrap;
+        }}
     </style>
 </head>
 <body>
-    <div class="container">
-        <div class="icon">❌</div>
-        <h1>{title}</h1>
-        <p>{escaped_message}</p>
-    </div>
+    <main>
+        <div class="icon" aria-hidden="true">{icon}</div>
+        <h1><span class="visually-hidden">{status_label}: </span>{title}</h1>
+        <p role="{role}" aria-live="polite">{message}</p>{details}{close_hint}
+    </main>
+    <script type="application/json" id="harbor-oauth-result">{metadata}</script>
 </body>
-</html>"#
+</html>"#,
+        status = kind.status(),
+        title = escape_html(title),
+        message = escape_html(message),
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_page_escapes_and_announces() {
+        let page = error_page("Authorization Failed", "<script>alert(1)</script>");
+        assert!(page.contains("&lt;script&gt;"));
+        assert!(page.contains(r#"role="alert""#));
+        assert!(page.contains(r#"<meta name="harbor-oauth-status" content="error">"#));
+    }
+
+    #[test]
+    fn test_success_page_lists_scopes() {
+        let scopes = vec!["gmail.readonly".to_string()];
+        let page = success_page("Done", "Close this window.", &GrantDetails {
+            provider: "google",
+            server_id: "gmail",
+            scopes: &scopes,
+        });
+        assert!(page.contains("<li>gmail.readonly</li>"));
+        assert!(page.contains(r#""scopes":["gmail.readonly"]"#));
+    }
+}
@@ -8,10 +8,19 @@ use std::path::PathBuf;
 
 use serde::{Deserialize, Serialize};
 
-use super::OAuthTokens;
+use super::{versioning, OAuthTokens};
 
 const TOKEN_FILE_NAME: &str = "oauth_tokens.json";
 
+/// Current version of the token file format.
+const TOKEN_FILE_VERSION: u32 = 1;
+
+/// Token file migrations, indexed by the version they upgrade from.
+const TOKEN_MIGRATIONS: &[versioning::Migration] = &[
+    // v0 -> v1: unversioned files. Same shape, version field added.
+    |_| Ok(()),
+];
+
 /// Stored tokens for a server.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StoredTokens {
@@ -32,6 +41,9 @@ pub struct StoredTokens {
 /// Token store - manages persisted OAuth tokens.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TokenStore {
+    /// Format version (see `TOKEN_FILE_VERSION`)
+    #[serde(default)]
+    pub version: u32,
     /// Tokens keyed by server ID
     pub tokens: HashMap<String, StoredTokens>,
 }
@@ -40,6 +52,7 @@ impl TokenStore {
     /// Create a new empty token store.
     pub fn new() -> Self {
         Self {
+            version: TOKEN_FILE_VERSION,
             tokens: HashMap::new(),
         }
     }
@@ -62,14 +75,12 @@ impl TokenStore {
     pub fn load() -> Result<Self, String> {
         let path = Self::get_token_path()?;
         
-        if !path.exists() {
-            return Ok(Self::new());
-        }
-        
-        let contents = fs::read_to_string(&path)
-            .map_err(|e| format!("Failed to read token file: {}", e))?;
+        let raw = match versioning::load(&path, TOKEN_FILE_VERSION, TOKEN_MIGRATIONS)? {
+            Some(raw) => raw,
+            None => return Ok(Self::new()),
+        };
         
-        let store: TokenStore = serde_json::from_str(&contents)
+        let store: TokenStore = serde_json::from_value(raw)
             .map_err(|e| format!("Failed to parse token file: {}", e))?;
         
         Ok(store)
@@ -85,18 +96,25 @@ impl TokenStore {
         fs::write(&path, contents)
             .map_err(|e| format!("Failed to write token file: {}", e))?;
         
-        // Set restrictive permissions on Unix
-        #[cfg(unix)]
-        {
-            use std::os::unix::fs::PermissionsExt;
-            let perms = fs::Permissions::from_mode(0o600);
-            fs::set_permissions(&path, perms)
-                .map_err(|e| format!("Failed to set token file permissions: {}", e))?;
-        }
+        // Restrict to the current user (mode 0o600 / owner-only DACL)
+        super::permissions::restrict_to_owner(&path)?;
         
         Ok(())
     }
     
+    /// Check that the token file on disk parses and is restricted to the
+    /// current user, re-applying the restriction. Returns the number of
+    /// stored tokens.
+    pub fn verify() -> Result<usize, String> {
+        let path = Self::get_token_path()?;
+        if !path.exists() {
+            return Ok(0);
+        }
+        let store = Self::load()?;
+        super::permissions::restrict_to_owner(&path)?;
+        Ok(store.tokens.len())
+    }
+    
     /// Get tokens for a server.
     pub fn get_tokens(&self, server_id: &str) -> Option<&StoredTokens> {
         self.tokens.get(server_id)
@@ -113,7 +131,6 @@ impl TokenStore {
     }
     
     /// Check if tokens exist for a server.
-    #[allow(dead_code)]
     pub fn has_tokens(&self, server_id: &str) -> bool {
         self.tokens.contains_key(server_id)
     }
@@ -134,42 +151,6 @@ impl TokenStore {
             None => true, // No tokens means "expired" (needs auth)
         }
     }
-    
-    /// Get access token for a server, refreshing if needed.
-    #[allow(dead_code)]
-    pub async fn get_access_token(
-        &mut self,
-        server_id: &str,
-    ) -> Result<String, String> {
-        let stored = self.tokens.get(server_id)
-            .ok_or_else(|| format!("No tokens found for server: {}", server_id))?;
-        
-        // Check if refresh is needed
-        if self.is_expired(server_id) {
-            // Try to refresh
-            if let Some(ref refresh_token) = stored.tokens.refresh_token {
-                let credentials = super::get_credentials(&stored.provider).await
-                    .ok_or_else(|| format!("No credentials for provider: {}", stored.provider))?;
-                
-                let new_tokens = super::refresh_tokens(refresh_token, &stored.provider, &credentials).await?;
-                
-                // Update stored tokens
-                let mut updated = stored.clone();
-                updated.tokens = new_tokens;
-                updated.updated_at = chrono::Utc::now().timestamp_millis();
-                self.tokens.insert(server_id.to_string(), updated);
-                
-                // Save to disk
-                self.save()?;
-                
-                return Ok(self.tokens.get(server_id).unwrap().tokens.access_token.clone());
-            } else {
-                return Err("Token expired and no refresh token available".to_string());
-            }
-        }
-        
-        Ok(stored.tokens.access_token.clone())
-    }
 }
 
 impl Default for TokenStore {
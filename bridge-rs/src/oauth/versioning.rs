@@ -0,0 +1,110 @@
+//! Versioned on-disk formats for OAuth files.
+//!
+//! Each file carries a `version` field (missing means version 0). On load,
+//! the raw JSON is passed through the migrations for every version between
+//! the stored one and the current one, so older files keep working after a
+//! schema change. Files written by a newer Harbor are backed up rather than
+//! overwritten.
+
+use std::path::Path;
+
+/// Upgrades a raw file from version `i` to `i + 1` (indexed by `i`).
+pub type Migration = fn(&mut serde_json::Value) -> Result<(), String>;
+
+/// Run the migrations needed to bring `raw` up to `current`.
+pub fn migrate(
+    mut raw: serde_json::Value,
+    current: u32,
+    migrations: &[Migration],
+) -> Result<serde_json::Value, String> {
+    debug_assert_eq!(migrations.len(), current as usize);
+
+    if !raw.is_object() {
+        return Err("expected a JSON object".to_string());
+    }
+    let version = raw.get("version").and_then(|v| v.as_u64()).unwrap_or(0) as u32;
+    if version > current {
+        return Err(format!(
+            "file version {} is newer than supported version {}",
+            version, current
+        ));
+    }
+
+    for (from, migration) in migrations.iter().enumerate().skip(version as usize) {
+        migration(&mut raw).map_err(|e| format!("migration from version {} failed: {}", from, e))?;
+        raw.as_object_mut()
+            .ok_or_else(|| format!("migration from version {} left no JSON object", from))?
+            .insert("version".to_string(), serde_json::json!(from as u32 + 1));
+    }
+
+    Ok(raw)
+}
+
+/// Read and migrate a versioned JSON file.
+///
+/// Returns `Ok(None)` if the file doesn't exist. If the file can't be
+/// migrated it is copied to `<name>.bak` before the error is returned, so a
+/// later save can't destroy it.
+pub fn load(path: &Path, current: u32, migrations: &[Migration]) -> Result<Option<serde_json::Value>, String> {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(format!("Failed to read {:?}: {}", path, e)),
+    };
+
+    let result = serde_json::from_str(&contents)
+        .map_err(|e| format!("Failed to parse {:?}: {}", path, e))
+        .and_then(|raw| migrate(raw, current, migrations));
+
+    if let Err(ref e) = result {
+        let mut backup = path.as_os_str().to_owned();
+        backup.push(".bak");
+        match std::fs::copy(path, &backup) {
+            Ok(_) => tracing::warn!("Backed up {:?} to {:?}: {}", path, backup, e),
+            Err(copy_err) => tracing::error!("Failed to back up {:?}: {}", path, copy_err),
+        }
+    }
+
+    result.map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unchanged(_raw: &mut serde_json::Value) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn add_field(raw: &mut serde_json::Value) -> Result<(), String> {
+        raw["added"] = serde_json::json!(true);
+        Ok(())
+    }
+
+    #[test]
+    fn test_migrate_unversioned() {
+        let raw = serde_json::json!({ "providers": {} });
+        let migrated = migrate(raw, 2, &[unchanged, add_field]).unwrap();
+        assert_eq!(migrated["version"], 2);
+        assert_eq!(migrated["added"], true);
+    }
+
+    #[test]
+    fn test_migrate_skips_applied() {
+        let raw = serde_json::json!({ "version": 2 });
+        let migrated = migrate(raw, 2, &[unchanged, add_field]).unwrap();
+        assert!(migrated.get("added").is_none());
+    }
+
+    #[test]
+    fn test_non_object_rejected() {
+        assert!(migrate(serde_json::json!([1, 2]), 1, &[unchanged]).is_err());
+        assert!(migrate(serde_json::json!("v1"), 0, &[]).is_err());
+    }
+
+    #[test]
+    fn test_newer_version_rejected() {
+        let raw = serde_json::json!({ "version": 9 });
+        assert!(migrate(raw, 1, &[unchanged]).is_err());
+    }
+}
@@ -0,0 +1,428 @@
+//! Persistent outbox for tool calls blocked by being offline or needing
+//! re-authentication.
+//!
+//! When a scheduled job or pipeline step can't run for one of those
+//! reasons, the extension queues the call here (`outbox.enqueue`) instead
+//! of dropping the run. The bridge retries due calls with exponential
+//! backoff, and each call stays in the user-visible list (`outbox.list`)
+//! until it is delivered, cancelled, or has failed for good. The extension
+//! can ask for an early retry once it's back online or the user has signed
+//! in again (`outbox.retry`).
+//!
+//! A call is delivered once the tool answers without an error; a result
+//! marked `isError` is a failed attempt. A call fails for good when it runs
+//! out of attempts or time, or at once on an error retrying can't fix, such
+//! as invalid arguments or a call across profiles.
+//!
+//! Results are announced as `outbox/delivered` and `outbox/failed` events.
+//! Everything is kept in `~/.harbor/outbox.json`.
+
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::events;
+use crate::rpc::RpcError;
+use crate::store::{JsonStore, Stored};
+
+/// How often due calls are looked for.
+const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Most calls kept, pending or failed.
+const MAX_ITEMS: usize = 500;
+
+/// Why a call was queued.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Blocked {
+    Offline,
+    Auth,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    Pending,
+    /// Out of attempts or past its deadline; kept for the user to see
+    Failed,
+}
+
+/// How queued calls are retried.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub initial_delay_secs: u64,
+    pub max_delay_secs: u64,
+    /// Give up on a call this long after it was queued
+    pub max_age_hours: u64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 10,
+            initial_delay_secs: 60,
+            max_delay_secs: 3600,
+            max_age_hours: 72,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before the attempt after `attempts` failed ones.
+    fn delay_ms(&self, attempts: u32) -> i64 {
+        let secs = self
+            .initial_delay_secs
+            .saturating_mul(1 << attempts.saturating_sub(1).min(20))
+            .min(self.max_delay_secs);
+        secs as i64 * 1000
+    }
+}
+
+/// A queued tool call.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutboxItem {
+    pub id: String,
+    /// What queued it, e.g. `automation:morning-digest`
+    pub source: String,
+    pub server_id: String,
+    pub tool: String,
+    #[serde(default)]
+    pub args: serde_json::Value,
+    pub blocked: Blocked,
+    pub status: Status,
+    pub attempts: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    pub created_at: i64,
+    pub next_attempt_at: i64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct OutboxFile {
+    #[serde(default)]
+    policy: RetryPolicy,
+    #[serde(default)]
+    items: Vec<OutboxItem>,
+}
+
+impl Stored for OutboxFile {
+    const FILE_NAME: &'static str = "outbox.json";
+}
+
+/// Outbox subsystem state.
+#[derive(Default)]
+pub struct OutboxState {
+    /// Policy and queued calls, loaded on first use
+    file: JsonStore<OutboxFile>,
+    /// Held while due calls are being retried
+    running: Mutex<()>,
+}
+
+fn state() -> &'static OutboxState {
+    &crate::state::get().outbox
+}
+
+/// Start retrying due calls. Must be called from within the tokio runtime.
+pub fn start() {
+    tokio::spawn(async {
+        let mut interval = tokio::time::interval(CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            run_due().await;
+        }
+    });
+}
+
+/// Retry every pending call that is due.
+async fn run_due() {
+    let _running = state().running.lock().await;
+    let now = chrono::Utc::now().timestamp_millis();
+    let due: Vec<OutboxItem> = state()
+        .file
+        .read(|file| {
+            file.items
+                .iter()
+                .filter(|item| item.status == Status::Pending && item.next_attempt_at <= now)
+                .cloned()
+                .collect()
+        })
+        .await;
+
+    for item in due {
+        let outcome = crate::mcp::call_tool(serde_json::json!({
+            "serverId": item.server_id,
+            "toolName": item.tool,
+            "args": item.args,
+        }))
+        .await;
+        record(&item.id, outcome).await;
+    }
+}
+
+/// Whether a call that failed with `code` would fail the same way however
+/// often it were retried.
+fn permanent(code: i64) -> bool {
+    matches!(code, -32601 | -32602 | crate::profiles::PROFILE_DENIED)
+}
+
+/// Record an attempt's outcome (`call_tool`'s) and announce deliveries and
+/// failures.
+async fn record(id: &str, outcome: Result<serde_json::Value, RpcError>) {
+    let now = chrono::Utc::now().timestamp_millis();
+    let outcome = match outcome {
+        Ok(result) if crate::mcp::call_failed(&result) => {
+            Err((crate::server_logs::message_text(&result["result"]), false))
+        }
+        Ok(result) => Ok(result),
+        Err(e) => Err((e.message, permanent(e.code))),
+    };
+    let event = state()
+        .file
+        .update(|file| {
+            let index = file.items.iter().position(|item| item.id == id)?;
+            let policy = file.policy.clone();
+            match outcome {
+                Ok(result) => {
+                    let item = file.items.remove(index);
+                    tracing::info!("Delivered queued call {} ({}/{})", item.id, item.server_id, item.tool);
+                    Some(("outbox/delivered", serde_json::json!({ "item": item, "result": result })))
+                }
+                Err((message, permanent)) => {
+                    let item = &mut file.items[index];
+                    item.attempts += 1;
+                    item.last_error = Some(message);
+                    let expired = now - item.created_at > policy.max_age_hours as i64 * 3600 * 1000;
+                    if permanent || item.attempts >= policy.max_attempts || expired {
+                        item.status = Status::Failed;
+                        tracing::warn!("Giving up on queued call {} after {} attempts", item.id, item.attempts);
+                        Some(("outbox/failed", serde_json::json!({ "item": item })))
+                    } else {
+                        item.next_attempt_at = now + policy.delay_ms(item.attempts);
+                        None
+                    }
+                }
+            }
+        })
+        .await;
+    if let Some((name, payload)) = event {
+        events::emit(name, payload);
+    }
+}
+
+// ============================================================================
+// RPC Handlers
+// ============================================================================
+
+/// Queue a blocked call:
+/// `{ source, server_id, tool, args?, blocked: "offline" | "auth", error? }`.
+/// Offline calls are first retried after the policy's initial delay; calls
+/// waiting on auth wait for `outbox.retry` or the next backoff step.
+pub async fn rpc_enqueue(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    crate::rpc::require_extension("use the outbox")?;
+    let str_param = |key: &str| {
+        params
+            .get(key)
+            .and_then(|v| v.as_str())
+            .map(String::from)
+            .ok_or_else(|| RpcError::invalid_params(format!("Missing '{}' parameter", key)))
+    };
+    let blocked: Blocked = serde_json::from_value(params.get("blocked").cloned().unwrap_or_default())
+        .map_err(|_| RpcError::invalid_params("'blocked' must be \"offline\" or \"auth\""))?;
+    let now = chrono::Utc::now().timestamp_millis();
+    let mut item = OutboxItem {
+        id: format!("outbox-{:016x}", rand::random::<u64>()),
+        source: str_param("source")?,
+        server_id: str_param("server_id")?,
+        tool: str_param("tool")?,
+        args: params.get("args").cloned().unwrap_or_else(|| serde_json::json!({})),
+        blocked,
+        status: Status::Pending,
+        attempts: 0,
+        last_error: params.get("error").and_then(|v| v.as_str()).map(String::from),
+        created_at: now,
+        next_attempt_at: now,
+    };
+
+    state()
+        .file
+        .update(|file| {
+            if file.items.len() >= MAX_ITEMS {
+                return Err(RpcError::new(
+                    -32000,
+                    format!("The outbox is full ({} calls); cancel some first", MAX_ITEMS),
+                ));
+            }
+            item.next_attempt_at = now + file.policy.delay_ms(1);
+            file.items.push(item.clone());
+            Ok(())
+        })
+        .await?;
+    tracing::info!("Queued {}/{} from {} ({:?})", item.server_id, item.tool, item.source, item.blocked);
+    Ok(serde_json::json!({ "item": item }))
+}
+
+/// List queued calls: `{ status?: "pending" | "failed", source? }`.
+pub async fn rpc_list(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    let status: Option<Status> = match params.get("status") {
+        Some(status) => Some(
+            serde_json::from_value(status.clone())
+                .map_err(|_| RpcError::invalid_params("'status' must be \"pending\" or \"failed\""))?,
+        ),
+        None => None,
+    };
+    let source = params.get("source").and_then(|v| v.as_str());
+    let (items, policy) = state()
+        .file
+        .read(|file| {
+            let items: Vec<OutboxItem> = file
+                .items
+                .iter()
+                .filter(|item| status.is_none() || status == Some(item.status))
+                .filter(|item| source.is_none() || source == Some(item.source.as_str()))
+                .cloned()
+                .collect();
+            (items, file.policy.clone())
+        })
+        .await;
+    Ok(serde_json::json!({ "items": items, "policy": policy }))
+}
+
+/// Remove a queued call: `{ id }`.
+pub async fn rpc_cancel(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    crate::rpc::require_extension("use the outbox")?;
+    let id = params
+        .get("id")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| RpcError::invalid_params("Missing 'id' parameter"))?;
+    let cancelled = state()
+        .file
+        .update(|file| {
+            let before = file.items.len();
+            file.items.retain(|item| item.id != id);
+            file.items.len() != before
+        })
+        .await;
+    Ok(serde_json::json!({ "cancelled": cancelled }))
+}
+
+/// Retry queued calls now, e.g. after reconnecting or signing in again:
+/// `{ id?, server_id?, blocked? }`. Failed calls that match are retried too,
+/// with a fresh set of attempts.
+pub async fn rpc_retry(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    crate::rpc::require_extension("use the outbox")?;
+    let id = params.get("id").and_then(|v| v.as_str());
+    let server_id = params.get("server_id").and_then(|v| v.as_str());
+    let blocked: Option<Blocked> = params.get("blocked").and_then(|v| serde_json::from_value(v.clone()).ok());
+    let now = chrono::Utc::now().timestamp_millis();
+
+    let retried = state()
+        .file
+        .update(|file| {
+            let mut retried = 0;
+            for item in file.items.iter_mut() {
+                if (id.is_none() || id == Some(item.id.as_str()))
+                    && (server_id.is_none() || server_id == Some(item.server_id.as_str()))
+                    && (blocked.is_none() || blocked == Some(item.blocked))
+                {
+                    if item.status == Status::Failed {
+                        item.status = Status::Pending;
+                        item.attempts = 0;
+                        item.created_at = now;
+                    }
+                    item.next_attempt_at = now;
+                    retried += 1;
+                }
+            }
+            retried
+        })
+        .await;
+
+    tokio::spawn(run_due());
+    Ok(serde_json::json!({ "retried": retried }))
+}
+
+/// Update the retry policy:
+/// `{ max_attempts?, initial_delay_secs?, max_delay_secs?, max_age_hours? }`.
+pub async fn rpc_configure(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    crate::rpc::require_extension("use the outbox")?;
+    let number = |key: &str| params.get(key).and_then(|v| v.as_u64());
+    let policy = state()
+        .file
+        .update(|file| {
+            let policy = &mut file.policy;
+            if let Some(n) = number("max_attempts") {
+                policy.max_attempts = (n as u32).max(1);
+            }
+            if let Some(n) = number("initial_delay_secs") {
+                policy.initial_delay_secs = n.max(1);
+            }
+            if let Some(n) = number("max_delay_secs") {
+                policy.max_delay_secs = n.max(policy.initial_delay_secs);
+            }
+            if let Some(n) = number("max_age_hours") {
+                policy.max_age_hours = n.max(1);
+            }
+            policy.clone()
+        })
+        .await;
+    Ok(serde_json::json!(policy))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff() {
+        let policy = RetryPolicy {
+            initial_delay_secs: 60,
+            max_delay_secs: 300,
+            ..Default::default()
+        };
+        assert_eq!(policy.delay_ms(1), 60_000);
+        assert_eq!(policy.delay_ms(2), 120_000);
+        assert_eq!(policy.delay_ms(3), 240_000);
+        assert_eq!(policy.delay_ms(10), 300_000);
+    }
+
+    #[tokio::test]
+    async fn test_record_outcomes() {
+        let item = |id: &str| OutboxItem {
+            id: id.to_string(),
+            source: "test".to_string(),
+            server_id: "mail".to_string(),
+            tool: "send".to_string(),
+            args: serde_json::json!({}),
+            blocked: Blocked::Offline,
+            status: Status::Pending,
+            attempts: 0,
+            last_error: None,
+            created_at: chrono::Utc::now().timestamp_millis(),
+            next_attempt_at: 0,
+        };
+        let ids = ["outbox-test-error", "outbox-test-invalid", "outbox-test-ok"];
+        state().file.update(|file| file.items.extend(ids.map(item))).await;
+        let find = |id| {
+            state()
+                .file
+                .read(move |file| file.items.iter().find(|i| i.id == id).cloned())
+        };
+
+        // A result marked isError is a failed attempt, retried later
+        let failed = serde_json::json!({ "result": "Mailbox full", "isError": true });
+        record(ids[0], Ok(failed)).await;
+        let retried = find(ids[0]).await.unwrap();
+        assert_eq!((retried.status, retried.attempts), (Status::Pending, 1));
+        assert_eq!(retried.last_error.as_deref(), Some("Mailbox full"));
+
+        // Invalid arguments won't get better
+        record(ids[1], Err(RpcError::invalid_params("Missing 'to'"))).await;
+        assert_eq!(find(ids[1]).await.unwrap().status, Status::Failed);
+
+        record(ids[2], Ok(serde_json::json!({ "result": "Sent" }))).await;
+        assert!(find(ids[2]).await.is_none());
+    }
+}
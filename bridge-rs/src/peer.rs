@@ -0,0 +1,390 @@
+//! Server-to-server tool calls.
+//!
+//! A composite server (e.g. an "email digest" built on a mail server and a
+//! summarizer) calls another server's tool through the bridge with
+//! `peer.call`, instead of reimplementing its API. Calls are denied unless
+//! the user's policy allows the caller to reach the target tool.
+//!
+//! JS servers call `peer.call(target, tool, arguments, token)` in their
+//! sandbox and components the `harbor:mcp/peer` import. Either way the
+//! bridge names the caller from the server making the call, never from
+//! what it sends.
+//!
+//! Each forwarded call carries a token in `_meta["harbor/peerToken"]`. A
+//! server that makes peer calls while handling one must pass that token
+//! back, which lets the bridge follow the whole call chain: a call back into
+//! a server already in the chain is a cycle and is refused, as is a chain
+//! longer than the policy's `max_depth`. The token also carries the
+//! profiles the call's flow has touched (see `crate::profiles`), which
+//! every server along the chain is checked against.
+//!
+//! The policy is kept in `~/.harbor/peer_policy.json`.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::rpc::RpcError;
+use crate::store::{JsonStore, Stored};
+
+/// Error code for peer calls refused by policy, cycles or depth.
+const PEER_DENIED: i64 = -32030;
+
+/// Longest a peer call may take.
+const CALL_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Which servers may call which tools.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PeerPolicy {
+    /// Targets each caller may reach: `server` (any tool) or `server/tool`
+    pub allow: BTreeMap<String, Vec<String>>,
+    /// Most calls in one chain
+    pub max_depth: usize,
+}
+
+impl Stored for PeerPolicy {
+    const FILE_NAME: &'static str = "peer_policy.json";
+}
+
+impl Default for PeerPolicy {
+    fn default() -> Self {
+        Self {
+            allow: BTreeMap::new(),
+            max_depth: 3,
+        }
+    }
+}
+
+impl PeerPolicy {
+    fn allows(&self, caller: &str, target: &str, tool: &str) -> bool {
+        self.allow.get(caller).is_some_and(|targets| {
+            targets
+                .iter()
+                .any(|t| t == target || t.strip_prefix(target).and_then(|t| t.strip_prefix('/')) == Some(tool))
+        })
+    }
+}
+
+/// A call in flight that the server handling it may make peer calls from.
+#[derive(Debug, Clone, Default)]
+struct Flow {
+    /// Servers from the first caller to the one handling the call
+    chain: Vec<String>,
+    /// Profiles touched so far
+    profiles: BTreeSet<String>,
+}
+
+/// Peer call subsystem state.
+#[derive(Default)]
+pub struct PeerState {
+    /// Policy, loaded on first use
+    policy: JsonStore<PeerPolicy>,
+    /// Calls in flight, by token
+    active: Mutex<HashMap<String, Flow>>,
+}
+
+fn state() -> &'static PeerState {
+    &crate::state::get().peer
+}
+
+/// Whether the policy lets `caller` reach `target`'s `tool`.
+pub async fn allows(caller: &str, target: &str, tool: &str) -> bool {
+    state().policy.read(|policy| policy.allows(caller, target, tool)).await
+}
+
+/// The chain after `caller` (the chain's last server) calls `target`.
+fn extend_chain(chain: &[String], target: &str, max_depth: usize) -> Result<Vec<String>, String> {
+    if chain.iter().any(|server| server == target) {
+        return Err(format!("Call cycle: {} -> {}", chain.join(" -> "), target));
+    }
+    // A chain of n servers is n - 1 calls
+    if chain.len() > max_depth {
+        return Err(format!(
+            "Call chain {} -> {} is deeper than {} calls",
+            chain.join(" -> "),
+            target,
+            max_depth
+        ));
+    }
+    let mut extended = chain.to_vec();
+    extended.push(target.to_string());
+    Ok(extended)
+}
+
+/// The flow a call from `caller` continues, checked against the token it
+/// passed, if any.
+async fn incoming_flow(caller: &str, token: Option<&str>) -> Result<Flow, String> {
+    let active = state().active.lock().await;
+    let handles = |flow: &Flow| flow.chain.last().map(String::as_str) == Some(caller);
+    match token {
+        Some(token) => match active.get(token) {
+            Some(flow) if handles(flow) => Ok(flow.clone()),
+            _ => Err("Unknown or expired peer call token".to_string()),
+        },
+        None if active.values().any(handles) => Err(format!(
+            "'{}' is handling a tool call; pass its harbor/peerToken to make further calls",
+            caller
+        )),
+        None => Ok(Flow {
+            chain: vec![caller.to_string()],
+            profiles: BTreeSet::new(),
+        }),
+    }
+}
+
+/// Hand `server_id` a token for the tool call it is about to handle, which
+/// carries the current profile flow into the peer calls it makes. `close`
+/// it once the call is over.
+pub async fn open(server_id: &str) -> String {
+    let token = format!("peer-{:016x}", rand::random::<u64>());
+    let flow = Flow {
+        chain: vec![server_id.to_string()],
+        profiles: crate::profiles::flow(),
+    };
+    state().active.lock().await.insert(token.clone(), flow);
+    token
+}
+
+pub async fn close(token: &str) {
+    state().active.lock().await.remove(token);
+}
+
+// ============================================================================
+// RPC Handlers
+// ============================================================================
+
+/// Call another server's tool: `{ target, tool, arguments?, token? }`.
+/// The caller is the server the dispatcher received the request from; a
+/// caller can't be named in the params.
+pub async fn rpc_call(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    let caller = crate::rpc::caller()
+        .ok_or_else(|| RpcError::invalid_params("peer.call must be made by a server"))?;
+    call(&caller, params).await
+}
+
+/// Call another server's tool for the running server `caller`, as
+/// `rpc_call` takes it, returning the tool's result.
+pub async fn call(caller: &str, params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    let str_param = |key: &str| {
+        params
+            .get(key)
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| RpcError::invalid_params(format!("Missing '{}' parameter", key)))
+    };
+    let target = str_param("target")?;
+    let tool = str_param("tool")?;
+    let arguments = params.get("arguments").cloned().unwrap_or_else(|| serde_json::json!({}));
+    let token = params.get("token").and_then(|v| v.as_str());
+
+    let policy = state().policy.read(|policy| policy.clone()).await;
+    if !policy.allows(caller, target, tool) {
+        return Err(RpcError::new(
+            PEER_DENIED,
+            format!("'{}' is not allowed to call {}/{}", caller, target, tool),
+        ));
+    }
+    let incoming = incoming_flow(caller, token).await.map_err(|e| RpcError::new(PEER_DENIED, e))?;
+    let chain = extend_chain(&incoming.chain, target, policy.max_depth).map_err(|e| RpcError::new(PEER_DENIED, e))?;
+    let profiles = crate::profiles::current()
+        .await
+        .extend_flow(&incoming.profiles, chain.iter().map(String::as_str))
+        .map_err(|e| {
+            RpcError::new(
+                crate::profiles::PROFILE_DENIED,
+                format!("Call chain {}: {}", chain.join(" -> "), e),
+            )
+        })?;
+    let component = crate::wasm::is_running(target).await;
+    if !component && crate::js::definition(target).await.is_none() {
+        return Err(RpcError::new(-32000, format!("Server '{}' is not running in the bridge", target)));
+    }
+    crate::mcp::pause::check(target)?;
+
+    let call_token = format!("peer-{:016x}", rand::random::<u64>());
+    let flow = Flow {
+        chain: chain.clone(),
+        profiles,
+    };
+    state().active.lock().await.insert(call_token.clone(), flow);
+    tracing::debug!("Peer call {}/{} via {}", target, tool, chain.join(" -> "));
+
+    let request = serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": call_token,
+        "method": "tools/call",
+        "params": {
+            "name": tool,
+            "arguments": arguments,
+            "_meta": { "harbor/peerToken": call_token, "harbor/peerChain": chain },
+        },
+    });
+    let result = if component {
+        tokio::time::timeout(CALL_TIMEOUT, crate::wasm::call(target, &request)).await
+    } else {
+        let call = crate::js::call_server(serde_json::json!({ "id": target, "request": request }));
+        tokio::time::timeout(CALL_TIMEOUT, call).await
+    };
+    state().active.lock().await.remove(&call_token);
+
+    let response = result.map_err(|_| {
+        RpcError::new(
+            -32000,
+            format!("{}/{} did not answer within {}s", target, tool, CALL_TIMEOUT.as_secs()),
+        )
+    })??;
+    if let Some(error) = response.get("error") {
+        return Err(RpcError::new(
+            -32000,
+            format!(
+                "{}/{} failed: {}",
+                target,
+                tool,
+                error.get("message").and_then(|m| m.as_str()).unwrap_or("unknown error")
+            ),
+        ));
+    }
+    Ok(response.get("result").cloned().unwrap_or(response))
+}
+
+/// Get the peer call policy.
+pub async fn rpc_get_policy(_params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    let policy = state().policy.read(|policy| policy.clone()).await;
+    Ok(serde_json::json!(policy))
+}
+
+/// Replace one caller's allowed targets, or set the depth limit:
+/// `{ caller?, allow?: ["server", "server/tool"], max_depth? }`.
+pub async fn rpc_set_policy(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    crate::rpc::require_extension("change the peer policy")?;
+    let caller = params.get("caller").and_then(|v| v.as_str());
+    let allow: Option<Vec<String>> = match params.get("allow") {
+        Some(allow) => Some(
+            serde_json::from_value(allow.clone())
+                .map_err(|_| RpcError::invalid_params("'allow' must be a list of targets"))?,
+        ),
+        None => None,
+    };
+    if allow.is_some() && caller.is_none() {
+        return Err(RpcError::invalid_params("Missing 'caller' parameter"));
+    }
+    let max_depth = params.get("max_depth").and_then(|v| v.as_u64());
+
+    let policy = state()
+        .policy
+        .update(|policy| {
+            if let (Some(caller), Some(allow)) = (caller, allow) {
+                if allow.is_empty() {
+                    policy.allow.remove(caller);
+                } else {
+                    policy.allow.insert(caller.to_string(), allow);
+                }
+            }
+            if let Some(max_depth) = max_depth {
+                policy.max_depth = (max_depth as usize).max(1);
+            }
+            policy.clone()
+        })
+        .await;
+    Ok(serde_json::json!(policy))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chain_cycles_and_depth() {
+        let chain = vec!["digest".to_string()];
+        let chain = extend_chain(&chain, "gmail", 2).unwrap();
+        assert!(extend_chain(&chain, "digest", 2).is_err());
+        let chain = extend_chain(&chain, "summarizer", 2).unwrap();
+        assert_eq!(chain, ["digest", "gmail", "summarizer"]);
+        assert!(extend_chain(&chain, "translate", 2).is_err());
+    }
+
+    #[test]
+    fn test_chain_stays_in_one_profile() {
+        let profiles = crate::profiles::tests::work_and_personal();
+        // A shared server in between doesn't launder a call into another profile
+        let chain = extend_chain(&["notes".to_string()], "summarizer", 4).unwrap();
+        assert!(profiles.check_servers(chain.iter().map(String::as_str)).is_ok());
+        let chain = extend_chain(&chain, "gmail-work", 4).unwrap();
+        assert!(profiles.check_servers(chain.iter().map(String::as_str)).is_err());
+    }
+
+    #[test]
+    fn test_policy_targets() {
+        let mut policy = PeerPolicy::default();
+        policy.allow.insert("digest".into(), vec!["gmail".into(), "llm/summarize".into()]);
+        assert!(policy.allows("digest", "gmail", "search"));
+        assert!(policy.allows("digest", "llm", "summarize"));
+        assert!(!policy.allows("digest", "llm", "chat"));
+        assert!(!policy.allows("gmail", "digest", "run"));
+    }
+
+    /// A JS server answering `tools/call` with `answer(params)`.
+    fn js_server(answer: &str) -> String {
+        format!(
+            r#"
+            async function main() {{
+                while (true) {{
+                    const request = JSON.parse(await MCP.readLine());
+                    let text;
+                    try {{
+                        text = await ({})(request.params);
+                    }} catch (e) {{
+                        text = 'Failed: ' + e.message;
+                    }}
+                    MCP.writeLine(JSON.stringify({{
+                        jsonrpc: '2.0',
+                        id: request.id,
+                        result: {{ content: [{{ type: 'text', text: text }}] }},
+                    }}));
+                }}
+            }}
+            main();
+            "#,
+            answer
+        )
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_server_calls_a_peer() {
+        let digest = js_server(
+            "async (params) => {
+                const token = params._meta['harbor/peerToken'];
+                const called = await peer.call('peer-notes', 'latest', {}, token);
+                return 'Digest: ' + called.content[0].text;
+            }",
+        );
+        let notes = js_server("async () => 'buy milk'");
+        crate::js::start_server(serde_json::json!({ "id": "peer-digest", "code": digest })).await.unwrap();
+        crate::js::start_server(serde_json::json!({ "id": "peer-notes", "code": notes })).await.unwrap();
+        let call = || {
+            crate::mcp::call_tool(serde_json::json!({ "serverId": "peer-digest", "toolName": "digest", "args": {} }))
+        };
+
+        // Denied until the policy allows it
+        let denied = call().await.unwrap();
+        assert!(denied["result"].as_str().unwrap().starts_with("Failed: 'peer-digest' is not allowed"));
+
+        let allow = serde_json::json!({ "caller": "peer-digest", "allow": ["peer-notes"] });
+        crate::rpc::as_extension(rpc_set_policy(allow)).await.unwrap();
+        let called = call().await.unwrap();
+        assert_eq!(called["result"], "Digest: buy milk");
+
+        for id in ["peer-digest", "peer-notes"] {
+            crate::js::stop_server(serde_json::json!({ "id": id })).await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_only_the_extension_sets_the_policy() {
+        let params = serde_json::json!({ "caller": "digest", "allow": ["gmail"] });
+        assert!(rpc_set_policy(params.clone()).await.is_err());
+        assert!(crate::rpc::as_caller("digest", rpc_set_policy(params)).await.is_err());
+    }
+}
@@ -0,0 +1,88 @@
+//! System sleep/resume handling.
+//!
+//! The bridge notices a resume when a periodic tick arrives far later by the
+//! wall clock than it was scheduled: tokio's timers run on a monotonic clock
+//! that stops while the machine sleeps on most platforms, so the gap is the
+//! time spent asleep. This needs no OS hooks and works everywhere.
+//!
+//! On resume the bridge refreshes tokens that expired in the meantime,
+//! pings running servers, rearms file watchers, runs maintenance that came
+//! due, and emits `bridge/resumed` with what it found.
+
+use std::time::{Duration, SystemTime};
+
+use crate::events;
+
+/// How often the wall clock is compared with the timer.
+const TICK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Gap beyond the expected tick that counts as a sleep.
+const SLEEP_THRESHOLD: Duration = Duration::from_secs(30);
+
+/// How long a server has to answer a ping after resume.
+const PING_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Start watching for resumes. Must be called from within the tokio runtime.
+pub fn start() {
+    tokio::spawn(async {
+        let mut interval = tokio::time::interval(TICK_INTERVAL);
+        // Don't fire a burst of missed ticks after a sleep
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        let mut last = SystemTime::now();
+        loop {
+            interval.tick().await;
+            let now = SystemTime::now();
+            let elapsed = now.duration_since(last).unwrap_or_default();
+            last = now;
+            if let Some(slept) = slept_for(elapsed) {
+                on_resume(slept).await;
+            }
+        }
+    });
+}
+
+/// Time asleep, if the wall clock moved well past one tick.
+fn slept_for(elapsed: Duration) -> Option<Duration> {
+    (elapsed > TICK_INTERVAL + SLEEP_THRESHOLD).then(|| elapsed - TICK_INTERVAL)
+}
+
+async fn on_resume(slept: Duration) {
+    tracing::info!("Resumed after sleeping for about {:?}", slept);
+
+    let (tokens_refreshed, tokens_failed) = crate::oauth::revalidate_tokens().await;
+    let sessions_pruned = crate::auth::aws::prune_sessions().await;
+    let unresponsive = crate::state::get().js.ping_all(PING_TIMEOUT).await;
+    for id in &unresponsive {
+        tracing::warn!("JS MCP server {} did not answer a ping after resume", id);
+    }
+    let watches = crate::fs::rearm_watches();
+    let maintenance = crate::maintenance::run_if_due("resume").await;
+
+    events::emit(
+        "bridge/resumed",
+        serde_json::json!({
+            "slept_ms": slept.as_millis() as u64,
+            "tokens_refreshed": tokens_refreshed,
+            "tokens_failed": tokens_failed,
+            "aws_sessions_pruned": sessions_pruned,
+            "unresponsive_servers": unresponsive,
+            "watches_rearmed": watches,
+            "maintenance_ran": maintenance,
+        }),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slept_for() {
+        assert_eq!(slept_for(TICK_INTERVAL), None);
+        assert_eq!(slept_for(TICK_INTERVAL + Duration::from_secs(10)), None);
+        assert_eq!(
+            slept_for(TICK_INTERVAL + Duration::from_secs(3600)),
+            Some(Duration::from_secs(3600))
+        );
+    }
+}
@@ -0,0 +1,337 @@
+//! Profile boundaries between servers.
+//!
+//! A profile ("work", "personal") groups the servers holding one side of a
+//! user's data and credentials. Data must not flow between profiles: a
+//! composite tool, a chain of peer calls, or a tool call made on behalf of
+//! a profile may use servers of one profile only, plus servers assigned to
+//! none (shared utilities like a clock). A personal pipeline therefore can't
+//! reach the work Gmail server, directly or through any layer in between.
+//!
+//! Within a tool call or a composite tool run, the profiles touched so far
+//! are tracked in a task-local flow, so every step is checked against the
+//! steps before it. A JS server handling a call gets a peer token that
+//! carries the flow (see `crate::peer`), so the peer calls it makes, and
+//! theirs in turn, are checked against it too. A call made by a server acts
+//! for that server's profile, whatever it asks for. An explicit override
+//! lets two profiles be combined.
+//!
+//! Assignments and overrides are kept in `~/.harbor/profiles.json`.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::future::Future;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::rpc::RpcError;
+use crate::store::{self, JsonStore, Stored};
+
+/// Error code for calls that would carry data across profiles.
+pub const PROFILE_DENIED: i64 = -32060;
+
+/// Profile assignments and overrides.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Profiles {
+    /// Profile of each assigned server
+    pub servers: BTreeMap<String, String>,
+    /// Pairs of profiles allowed to be combined, each sorted
+    pub overrides: BTreeSet<(String, String)>,
+}
+
+impl Stored for Profiles {
+    const FILE_NAME: &'static str = "profiles.json";
+}
+
+fn pair(a: &str, b: &str) -> (String, String) {
+    if a <= b {
+        (a.to_string(), b.to_string())
+    } else {
+        (b.to_string(), a.to_string())
+    }
+}
+
+impl Profiles {
+    pub fn profile_of(&self, server_id: &str) -> Option<&str> {
+        self.servers.get(server_id).map(String::as_str)
+    }
+
+    /// Whether data may flow between two profiles.
+    pub fn compatible(&self, a: &str, b: &str) -> bool {
+        a == b || self.overrides.contains(&pair(a, b))
+    }
+
+    /// Check that `server_id` can join a flow that has touched `touched`,
+    /// and add its profile if so.
+    fn admit(&self, touched: &mut BTreeSet<String>, server_id: &str) -> Result<(), String> {
+        let profile = match self.profile_of(server_id) {
+            Some(profile) => profile,
+            None => return Ok(()),
+        };
+        if let Some(other) = touched.iter().find(|other| !self.compatible(other, profile)) {
+            return Err(format!(
+                "'{}' belongs to profile '{}' and can't be combined with profile '{}'",
+                server_id, profile, other
+            ));
+        }
+        touched.insert(profile.to_string());
+        Ok(())
+    }
+
+    /// Check that a set of servers, used together, stays within one profile
+    /// (or overridden pairs).
+    pub fn check_servers<'a>(&self, servers: impl IntoIterator<Item = &'a str>) -> Result<(), String> {
+        self.extend_flow(&BTreeSet::new(), servers).map(|_| ())
+    }
+
+    /// Check that a set of servers can join a flow that has touched
+    /// `touched`, and return the profiles touched with them.
+    pub fn extend_flow<'a>(
+        &self,
+        touched: &BTreeSet<String>,
+        servers: impl IntoIterator<Item = &'a str>,
+    ) -> Result<BTreeSet<String>, String> {
+        let mut touched = touched.clone();
+        servers.into_iter().try_for_each(|server| self.admit(&mut touched, server))?;
+        Ok(touched)
+    }
+}
+
+/// Profile subsystem state.
+#[derive(Default)]
+pub struct ProfilesState {
+    /// Assignments, loaded on first use
+    file: JsonStore<Profiles>,
+}
+
+fn state() -> &'static ProfilesState {
+    &crate::state::get().profiles
+}
+
+/// Names of the profiles servers are assigned to, read from disk.
+pub fn names() -> Vec<String> {
+    let names: BTreeSet<String> = store::read_now::<Profiles>().servers.into_values().collect();
+    names.into_iter().collect()
+}
+
+/// A snapshot of the current assignments.
+pub async fn current() -> Profiles {
+    state().file.read(|profiles| profiles.clone()).await
+}
+
+tokio::task_local! {
+    /// Profiles touched by the flow (composite run, or call on behalf of a
+    /// profile) the current task belongs to.
+    static FLOW: std::sync::Mutex<BTreeSet<String>>;
+}
+
+/// Run `f` as one flow, starting from `acting` if given. Calls made inside
+/// an existing flow stay part of it.
+pub async fn scope<F: Future>(acting: Option<String>, f: F) -> F::Output {
+    if FLOW.try_with(|_| ()).is_ok() {
+        return f.await;
+    }
+    FLOW.scope(std::sync::Mutex::new(acting.into_iter().collect()), f).await
+}
+
+/// The profiles the current flow has touched; none outside a flow.
+pub fn flow() -> BTreeSet<String> {
+    FLOW.try_with(|touched| touched.lock().unwrap().clone()).unwrap_or_default()
+}
+
+/// The profile a call acts for: the calling server's own, if a server is
+/// calling, or else `requested`. A server asking for another profile is
+/// refused.
+pub async fn acting(requested: Option<String>) -> Result<Option<String>, RpcError> {
+    let Some(caller) = crate::rpc::caller() else {
+        return Ok(requested);
+    };
+    let own = current().await.profile_of(&caller).map(String::from);
+    match requested {
+        Some(requested) if Some(&requested) != own.as_ref() => Err(RpcError::new(
+            PROFILE_DENIED,
+            format!("Server '{}' cannot act for profile '{}'", caller, requested),
+        )),
+        _ => Ok(own),
+    }
+}
+
+/// Check a call to `server_id` against the current flow, if any, and
+/// record its profile in it.
+pub async fn enter(server_id: &str) -> Result<(), RpcError> {
+    if FLOW.try_with(|_| ()).is_err() {
+        return Ok(());
+    }
+    let profiles = current().await;
+    FLOW.with(|touched| profiles.admit(&mut touched.lock().unwrap(), server_id))
+        .map_err(|e| RpcError::new(PROFILE_DENIED, e))
+}
+
+// ============================================================================
+// RPC Handlers
+// ============================================================================
+
+/// Get profile assignments and overrides.
+pub async fn rpc_get(_params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    let profiles = current().await;
+    let overrides: Vec<[&String; 2]> = profiles.overrides.iter().map(|(a, b)| [a, b]).collect();
+    Ok(serde_json::json!({ "servers": profiles.servers, "overrides": overrides }))
+}
+
+/// Assign a server to a profile: `{ server_id, profile }`. A null profile
+/// makes the server shared again.
+pub async fn rpc_assign(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    crate::rpc::require_extension("change profiles")?;
+    let server_id = params
+        .get("server_id")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| RpcError::invalid_params("Missing 'server_id' parameter"))?
+        .to_string();
+    let profile = params
+        .get("profile")
+        .and_then(|v| v.as_str())
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .map(String::from);
+    state()
+        .file
+        .update(|profiles| {
+            match &profile {
+                Some(profile) => profiles.servers.insert(server_id.clone(), profile.clone()),
+                None => profiles.servers.remove(&server_id),
+            };
+        })
+        .await;
+    Ok(serde_json::json!({ "server_id": server_id, "profile": profile }))
+}
+
+/// Allow or disallow combining two profiles: `{ profiles: [a, b], allowed }`.
+pub async fn rpc_set_override(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    crate::rpc::require_extension("change profiles")?;
+    let names: Vec<String> = params
+        .get("profiles")
+        .cloned()
+        .and_then(|v| serde_json::from_value(v).ok())
+        .filter(|names: &Vec<String>| names.len() == 2 && names[0] != names[1])
+        .ok_or_else(|| RpcError::invalid_params("'profiles' must be two different profile names"))?;
+    let allowed = params.get("allowed").and_then(|v| v.as_bool()).unwrap_or(true);
+    let key = pair(&names[0], &names[1]);
+    state()
+        .file
+        .update(|profiles| {
+            if allowed {
+                tracing::warn!("Allowing data flow between profiles '{}' and '{}'", key.0, key.1);
+                profiles.overrides.insert(key);
+            } else {
+                profiles.overrides.remove(&key);
+            }
+        })
+        .await;
+    Ok(serde_json::json!({ "profiles": names, "allowed": allowed }))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    pub(crate) fn work_and_personal() -> Profiles {
+        let mut profiles = Profiles::default();
+        profiles.servers.insert("gmail-work".to_string(), "work".to_string());
+        profiles.servers.insert("jira".to_string(), "work".to_string());
+        profiles.servers.insert("notes".to_string(), "personal".to_string());
+        profiles
+    }
+
+    #[test]
+    fn test_check_servers() {
+        let mut profiles = work_and_personal();
+        assert!(profiles.check_servers(["gmail-work", "time", "jira"]).is_ok());
+        let error = profiles.check_servers(["notes", "time", "gmail-work"]).unwrap_err();
+        assert!(error.contains("'gmail-work' belongs to profile 'work'"));
+
+        profiles.overrides.insert(pair("work", "personal"));
+        assert!(profiles.check_servers(["notes", "gmail-work"]).is_ok());
+    }
+
+    #[test]
+    fn test_override_is_pairwise() {
+        let mut profiles = work_and_personal();
+        profiles.servers.insert("bank".to_string(), "finance".to_string());
+        profiles.overrides.insert(pair("personal", "finance"));
+        profiles.overrides.insert(pair("work", "finance"));
+        // finance may join either, but work and personal still can't meet
+        assert!(profiles.check_servers(["bank", "notes"]).is_ok());
+        assert!(profiles.check_servers(["bank", "notes", "jira"]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_flow_tracks_nested_calls() {
+        let profiles = work_and_personal();
+        let admit = |server: &str| FLOW.with(|touched| profiles.admit(&mut touched.lock().unwrap(), server));
+
+        // A flow acting for "personal" can't reach work servers at any depth
+        scope(Some("personal".to_string()), async {
+            assert!(admit("notes").is_ok());
+            scope(None, async {
+                assert!(admit("time").is_ok());
+                assert!(admit("gmail-work").is_err());
+            })
+            .await;
+        })
+        .await;
+
+        // The first assigned server pins a flow without an acting profile
+        scope(None, async {
+            assert!(admit("jira").is_ok());
+            assert!(admit("notes").is_err());
+        })
+        .await;
+
+        // Outside a flow, nothing is tracked
+        assert!(enter("notes").await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_flow_reaches_composites_and_peer_calls() {
+        use crate::rpc::{as_caller, as_extension};
+        let assign =
+            |server: &str, profile: &str| rpc_assign(serde_json::json!({ "server_id": server, "profile": profile }));
+        as_extension(assign("flow-work", "work")).await.unwrap();
+        as_extension(assign("flow-notes", "personal")).await.unwrap();
+        let call = |profile: &str| {
+            crate::mcp::call_tool(serde_json::json!({
+                "serverId": "flow-work", "toolName": "search", "args": {}, "profile": profile,
+            }))
+        };
+
+        // A direct call acting for "personal"
+        assert_eq!(call("personal").await.unwrap_err().code, PROFILE_DENIED);
+        // A server acts for its own profile, whatever it asks for
+        let denied = as_caller("flow-notes", call("work")).await.unwrap_err();
+        assert_eq!(denied.code, PROFILE_DENIED);
+
+        // A composite run inside a "personal" flow
+        let define = crate::composite::rpc_define(serde_json::json!({
+            "name": "flow-search",
+            "steps": [{ "server_id": "flow-work", "tool": "search", "args": {} }],
+        }));
+        as_extension(define).await.unwrap();
+        let run = crate::composite::run("flow-search".to_string(), serde_json::json!({}));
+        let denied = scope(Some("personal".to_string()), run).await.unwrap_err();
+        assert_eq!(denied.code, PROFILE_DENIED);
+
+        // A peer call made while handling a call that is acting for "personal"
+        let allow = serde_json::json!({ "caller": "flow-helper", "allow": ["flow-work"] });
+        as_extension(crate::peer::rpc_set_policy(allow)).await.unwrap();
+        let denied = scope(Some("personal".to_string()), async {
+            let token = crate::peer::open("flow-helper").await;
+            let params = serde_json::json!({ "target": "flow-work", "tool": "search", "token": token });
+            let called = as_caller("flow-helper", crate::peer::rpc_call(params)).await;
+            crate::peer::close(&token).await;
+            called
+        })
+        .await
+        .unwrap_err();
+        assert_eq!(denied.code, PROFILE_DENIED);
+    }
+}
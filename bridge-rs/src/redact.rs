@@ -0,0 +1,124 @@
+//! Secret redaction for log output and error messages.
+//!
+//! `redact` masks access/refresh tokens, authorization codes, client secrets
+//! and bearer credentials in free-form text. `Redacting` wraps a tracing
+//! `MakeWriter` so every formatted log line passes through it before being
+//! written, regardless of which module produced it.
+
+use std::borrow::Cow;
+use std::io;
+
+use regex::Regex;
+use tracing_subscriber::fmt::MakeWriter;
+
+const MASK: &str = "[REDACTED]";
+
+/// Field names whose values are always secret.
+const SECRET_FIELDS: &str =
+    "access_token|refresh_token|id_token|client_secret|code|code_verifier|device_code|api_key|password";
+
+lazy_static::lazy_static! {
+    /// JSON string fields: `"access_token": "..."`
+    static ref JSON_FIELD: Regex =
+        Regex::new(&format!(r#""({})"\s*:\s*"[^"]*""#, SECRET_FIELDS)).unwrap();
+
+    /// Form and query parameters: `code=...&`
+    static ref FORM_FIELD: Regex =
+        Regex::new(&format!(r#"\b({})=[^&\s"']+"#, SECRET_FIELDS)).unwrap();
+
+    /// Authorization header values
+    static ref BEARER: Regex = Regex::new(r"(?i)\bbearer\s+[A-Za-z0-9\-._~+/]+=*").unwrap();
+
+    /// Well-known token formats (GitHub, Google, OpenAI-style API keys)
+    static ref KNOWN_TOKEN: Regex =
+        Regex::new(r"\b(gh[pousr]_[A-Za-z0-9]{20,}|ya29\.[A-Za-z0-9\-_]+|sk-[A-Za-z0-9\-_]{20,})").unwrap();
+}
+
+/// Mask known secret patterns in `text`.
+pub fn redact(text: &str) -> Cow<'_, str> {
+    let mut out = Cow::Borrowed(text);
+
+    if JSON_FIELD.is_match(&out) {
+        out = Cow::Owned(JSON_FIELD.replace_all(&out, format!(r#""$1": "{}""#, MASK)).into_owned());
+    }
+    if FORM_FIELD.is_match(&out) {
+        out = Cow::Owned(FORM_FIELD.replace_all(&out, format!("$1={}", MASK)).into_owned());
+    }
+    if BEARER.is_match(&out) {
+        out = Cow::Owned(BEARER.replace_all(&out, format!("Bearer {}", MASK)).into_owned());
+    }
+    if KNOWN_TOKEN.is_match(&out) {
+        out = Cow::Owned(KNOWN_TOKEN.replace_all(&out, MASK).into_owned());
+    }
+
+    out
+}
+
+/// A `MakeWriter` that redacts everything written through it.
+pub struct Redacting<M> {
+    inner: M,
+}
+
+impl<M> Redacting<M> {
+    pub fn new(inner: M) -> Self {
+        Self { inner }
+    }
+}
+
+impl<'a, M: MakeWriter<'a>> MakeWriter<'a> for Redacting<M> {
+    type Writer = RedactingWriter<M::Writer>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        RedactingWriter {
+            inner: self.inner.make_writer(),
+        }
+    }
+}
+
+/// Writer returned by `Redacting`. The fmt layer writes each event in a
+/// single call, so patterns never straddle two writes.
+pub struct RedactingWriter<W> {
+    inner: W,
+}
+
+impl<W: io::Write> io::Write for RedactingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match std::str::from_utf8(buf) {
+            Ok(text) => self.inner.write_all(redact(text).as_bytes())?,
+            Err(_) => self.inner.write_all(buf)?,
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_token_response() {
+        let body = r#"{"access_token":"abc123","token_type":"Bearer","refresh_token": "r-456"}"#;
+        let redacted = redact(body);
+        assert!(!redacted.contains("abc123"));
+        assert!(!redacted.contains("r-456"));
+        assert!(redacted.contains(r#""token_type":"Bearer""#));
+    }
+
+    #[test]
+    fn test_redact_query_and_header() {
+        let text = "GET /oauth/callback?code=4/0AbCd&state=xyz Authorization: Bearer eyJhbGciOi.J9";
+        let redacted = redact(text);
+        assert!(!redacted.contains("4/0AbCd"));
+        assert!(!redacted.contains("eyJhbGciOi"));
+        assert!(redacted.contains("state=xyz"));
+    }
+
+    #[test]
+    fn test_plain_text_is_borrowed() {
+        assert!(matches!(redact("Loaded 3 stored OAuth tokens"), Cow::Borrowed(_)));
+    }
+}
@@ -0,0 +1,135 @@
+//! The environment report: what this bridge is and how it is set up, as
+//! one line of JSON.
+//!
+//! It is written to stderr at startup (and by `--print-env-report`, which
+//! then exits), so the context needed to triage an issue can be grepped out
+//! of any log with `harbor_bridge_start`. It holds paths, names and counts
+//! only, never configuration values.
+
+use std::path::PathBuf;
+
+use serde::Serialize;
+
+use crate::llm::LlmConfig;
+
+/// How the bridge was started.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Mode {
+    NativeMessaging,
+    HttpServer,
+    Standalone,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Ports {
+    /// The HTTP server's port, in HTTP server mode
+    pub http: Option<u16>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Counts {
+    /// Servers in the tool catalog
+    pub servers: usize,
+    pub providers: usize,
+    pub providers_enabled: usize,
+    pub models: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EnvReport {
+    /// Constant marker to find the line by
+    pub event: &'static str,
+    pub version: &'static str,
+    pub pid: u32,
+    pub os: &'static str,
+    pub arch: &'static str,
+    pub mode: Mode,
+    pub config_path: PathBuf,
+    pub data_dir: PathBuf,
+    pub log_path: Option<PathBuf>,
+    /// Profiles servers are assigned to; calls name the profile they act for
+    pub profiles: Vec<String>,
+    pub ports: Ports,
+    pub subsystems: Vec<&'static str>,
+    /// Optional features compiled in
+    pub features: Vec<&'static str>,
+    pub counts: Counts,
+}
+
+/// Subsystems started in every mode.
+const SUBSYSTEMS: &[&str] = &["watchdog", "oauth", "maintenance", "outbox", "scheduler", "power", "wasm"];
+
+fn features() -> Vec<&'static str> {
+    let mut features = Vec::new();
+    if cfg!(feature = "onnx") {
+        features.push("onnx");
+    }
+    features
+}
+
+/// Gather the report. Reads the LLM configuration from disk if it hasn't
+/// been loaded yet.
+pub fn collect(mode: Mode, http_port: u16) -> EnvReport {
+    let llm = crate::llm::get_config()
+        .or_else(|| LlmConfig::load().ok())
+        .unwrap_or_default();
+    let mut subsystems = SUBSYSTEMS.to_vec();
+    subsystems.push(match mode {
+        Mode::HttpServer => "http_server",
+        Mode::NativeMessaging | Mode::Standalone => "native_messaging",
+    });
+    let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
+
+    EnvReport {
+        event: "harbor_bridge_start",
+        version: env!("CARGO_PKG_VERSION"),
+        pid: std::process::id(),
+        os: std::env::consts::OS,
+        arch: std::env::consts::ARCH,
+        mode,
+        config_path: LlmConfig::config_path(),
+        data_dir: home.join(".harbor"),
+        // Other modes log to stderr
+        log_path: (mode == Mode::NativeMessaging).then(crate::maintenance::log_path),
+        profiles: crate::profiles::names(),
+        ports: Ports {
+            http: (mode == Mode::HttpServer).then_some(http_port),
+        },
+        subsystems,
+        features: features(),
+        counts: Counts {
+            servers: crate::catalog::server_count(),
+            providers: llm.providers.len(),
+            providers_enabled: llm.providers.values().filter(|p| p.enabled).count(),
+            models: llm.models.len(),
+        },
+    }
+}
+
+/// Write the report to stderr as a single line.
+pub fn emit(report: &EnvReport) {
+    match serde_json::to_string(report) {
+        Ok(line) => eprintln!("{}", line),
+        Err(e) => tracing::warn!("Failed to serialize environment report: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_is_one_line() {
+        let report = collect(Mode::HttpServer, 9000);
+        let line = serde_json::to_string(&report).unwrap();
+        assert!(!line.contains('\n'));
+
+        let value: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(value["event"], "harbor_bridge_start");
+        assert_eq!(value["mode"], "http_server");
+        assert_eq!(value["ports"]["http"], 9000);
+        assert!(value["log_path"].is_null());
+        assert!(value["subsystems"].as_array().unwrap().contains(&serde_json::json!("http_server")));
+    }
+}
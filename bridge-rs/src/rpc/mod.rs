@@ -11,7 +11,11 @@ use std::sync::OnceLock;
 
 use serde::{Deserialize, Serialize};
 
-use crate::{fs, js, llm, mcp, oauth};
+use crate::{
+  auth, automation, browser, budget, capabilities, catalog, chaos, composite, concurrency, context, embeddings, fs,
+  history, js, llm, maintenance, mcp, metrics, oauth, outbox, peer, profiles, scheduler, secrets, server_logs, servers,
+  signing, wasm, workspace,
+};
 
 // =============================================================================
 // Types
@@ -23,6 +27,10 @@ pub struct RpcRequest {
   pub method: String,
   #[serde(default)]
   pub params: serde_json::Value,
+  /// MCP server the request is made on behalf of, when the extension
+  /// forwards a server's request rather than making its own
+  #[serde(default)]
+  pub caller: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -104,6 +112,9 @@ fn get_handlers() -> &'static HashMap<&'static str, RpcHandler> {
     handlers.insert("system.health", |_| {
       Box::pin(async { Ok(serde_json::json!({ "status": "ok" })) })
     });
+    handlers.insert("bridge.capabilities_changelog", |p| Box::pin(capabilities::rpc_changelog(p)));
+    handlers.insert("metrics.latency", |p| Box::pin(metrics::rpc_latency(p)));
+    handlers.insert("metrics.concurrency", |p| Box::pin(concurrency::rpc_stats(p)));
 
     // LLM handlers
     register_llm_handlers(&mut handlers);
@@ -114,16 +125,82 @@ fn get_handlers() -> &'static HashMap<&'static str, RpcHandler> {
     // JavaScript MCP server handlers
     register_js_handlers(&mut handlers);
 
+    // WASM component server handlers
+    register_wasm_handlers(&mut handlers);
+
     // OAuth handlers
     register_oauth_handlers(&mut handlers);
 
+    // Managed credential handlers
+    register_auth_handlers(&mut handlers);
+
+    // Cost and budget handlers
+    register_budget_handlers(&mut handlers);
+
     // MCP tool registry handlers
     register_mcp_handlers(&mut handlers);
 
+    // Tool catalog handlers
+    register_catalog_handlers(&mut handlers);
+
+    // Workspace handlers
+    register_workspace_handlers(&mut handlers);
+
+    // Automation handlers
+    register_automation_handlers(&mut handlers);
+
+    // Housekeeping handlers
+    register_maintenance_handlers(&mut handlers);
+
+    // Embedding provider handlers
+    register_embeddings_handlers(&mut handlers);
+
+    // Shared conversation context handlers
+    register_context_handlers(&mut handlers);
+
+    // Server-to-server call handlers
+    register_peer_handlers(&mut handlers);
+
+    // Composite tool handlers
+    register_composite_handlers(&mut handlers);
+
+    // Outbox handlers
+    register_outbox_handlers(&mut handlers);
+
+    // Scheduled tool call handlers
+    register_scheduler_handlers(&mut handlers);
+
+    // Fault injection handlers
+    register_chaos_handlers(&mut handlers);
+
+    // History handlers
+    register_history_handlers(&mut handlers);
+
+    // Profile handlers
+    register_profiles_handlers(&mut handlers);
+
+    // Secret handlers
+    register_secrets_handlers(&mut handlers);
+
+    // Package signature handlers
+    register_signing_handlers(&mut handlers);
+
+    // Installed server handlers
+    register_servers_handlers(&mut handlers);
+
+    // Browser context handlers
+    register_browser_handlers(&mut handlers);
+
     handlers
   })
 }
 
+/// Every registered method name.
+#[cfg(test)]
+pub(crate) fn method_names() -> Vec<&'static str> {
+  get_handlers().keys().copied().collect()
+}
+
 // =============================================================================
 // Domain Handler Registration
 // =============================================================================
@@ -154,6 +231,25 @@ fn register_fs_handlers(handlers: &mut HashMap<&'static str, RpcHandler>) {
   handlers.insert("fs.read", |p| Box::pin(fs::read(p)));
   handlers.insert("fs.write", |p| Box::pin(fs::write(p)));
   handlers.insert("fs.list", |p| Box::pin(fs::list(p)));
+  handlers.insert("fs.stat", |p| Box::pin(fs::stat(p)));
+  handlers.insert("fs.search", |p| Box::pin(fs::search(p)));
+  handlers.insert("fs.delete", |p| Box::pin(fs::delete(p)));
+  handlers.insert("fs.move", |p| Box::pin(fs::move_path(p)));
+  handlers.insert("fs.copy", |p| Box::pin(fs::copy(p)));
+  handlers.insert("fs.diff", |p| Box::pin(fs::diff(p)));
+  handlers.insert("fs.apply_patch", |p| Box::pin(fs::apply_patch(p)));
+  handlers.insert("fs.zip", |p| Box::pin(fs::zip(p)));
+  handlers.insert("fs.unzip", |p| Box::pin(fs::unzip(p)));
+  handlers.insert("fs.request_access", |p| Box::pin(fs::request_access(p)));
+  handlers.insert("fs.revoke_access", |p| Box::pin(fs::revoke_access(p)));
+  handlers.insert("fs.list_access", |p| Box::pin(fs::list_access(p)));
+  handlers.insert("fs.set_jail", |p| Box::pin(fs::set_jail(p)));
+  handlers.insert("fs.set_symlink_policy", |p| Box::pin(fs::set_symlink_policy(p)));
+  handlers.insert("fs.set_quota", |p| Box::pin(fs::set_quota(p)));
+  handlers.insert("fs.usage", |p| Box::pin(fs::usage(p)));
+  handlers.insert("fs.watch", |p| Box::pin(fs::watch(p)));
+  handlers.insert("fs.unwatch", |p| Box::pin(fs::unwatch(p)));
+  handlers.insert("fs.list_watches", |p| Box::pin(fs::list_watches(p)));
 }
 
 fn register_js_handlers(handlers: &mut HashMap<&'static str, RpcHandler>) {
@@ -161,12 +257,34 @@ fn register_js_handlers(handlers: &mut HashMap<&'static str, RpcHandler>) {
   handlers.insert("js.stop_server", |p| Box::pin(js::stop_server(p)));
   handlers.insert("js.call", |p| Box::pin(js::call_server(p)));
   handlers.insert("js.list_servers", |_| Box::pin(js::list_servers()));
+  handlers.insert("js.restart", |_| Box::pin(js::restart()));
+  handlers.insert("js.upgrade_server", |p| Box::pin(js::upgrade_server(p)));
+  handlers.insert("js.canary_status", |p| Box::pin(js::canary_status(p)));
+  handlers.insert("js.promote_canary", |p| Box::pin(js::promote_canary(p)));
+  handlers.insert("js.rollback_canary", |p| Box::pin(js::rollback_canary(p)));
+  handlers.insert("js.eval", |p| Box::pin(js::eval_expression(p)));
+}
+
+fn register_wasm_handlers(handlers: &mut HashMap<&'static str, RpcHandler>) {
+  handlers.insert("wasm.review", |p| Box::pin(wasm::review(p)));
+  handlers.insert("wasm.approve", |p| Box::pin(wasm::approve(p)));
+  handlers.insert("wasm.start_server", |p| Box::pin(wasm::start_server(p)));
+  handlers.insert("wasm.stop_server", |p| Box::pin(wasm::stop_server(p)));
+  handlers.insert("wasm.call", |p| Box::pin(wasm::call_server(p)));
+  handlers.insert("wasm.list_servers", |p| Box::pin(wasm::list_servers(p)));
+  handlers.insert("wasm.read_manifest", |p| Box::pin(wasm::read_manifest(p)));
+  handlers.insert("wasm.kv_get", |p| Box::pin(wasm::kv_get(p)));
+  handlers.insert("wasm.kv_set", |p| Box::pin(wasm::kv_set(p)));
+  handlers.insert("wasm.kv_delete", |p| Box::pin(wasm::kv_delete(p)));
+  handlers.insert("wasm.kv_list", |p| Box::pin(wasm::kv_list(p)));
 }
 
 fn register_oauth_handlers(handlers: &mut HashMap<&'static str, RpcHandler>) {
   handlers.insert("oauth.start_flow", |p| Box::pin(oauth::rpc_start_flow(p)));
+  handlers.insert("oauth.submit_code", |p| Box::pin(oauth::rpc_submit_code(p)));
   handlers.insert("oauth.get_tokens", |p| Box::pin(oauth::rpc_get_tokens(p)));
   handlers.insert("oauth.status", |p| Box::pin(oauth::rpc_status(p)));
+  handlers.insert("oauth.list_authenticated", |p| Box::pin(oauth::rpc_list_authenticated(p)));
   handlers.insert("oauth.revoke", |p| Box::pin(oauth::rpc_revoke(p)));
   handlers.insert("oauth.list_providers", |p| Box::pin(oauth::rpc_list_providers(p)));
   handlers.insert("oauth.get_credentials_status", |p| {
@@ -174,6 +292,28 @@ fn register_oauth_handlers(handlers: &mut HashMap<&'static str, RpcHandler>) {
   });
   handlers.insert("oauth.set_credentials", |p| Box::pin(oauth::rpc_set_credentials(p)));
   handlers.insert("oauth.remove_credentials", |p| Box::pin(oauth::rpc_remove_credentials(p)));
+  handlers.insert("oauth.set_scope_policy", |p| Box::pin(oauth::rpc_set_scope_policy(p)));
+  handlers.insert("oauth.remove_scope_policy", |p| Box::pin(oauth::rpc_remove_scope_policy(p)));
+  handlers.insert("oauth.import_external", |p| Box::pin(oauth::rpc_import_external(p)));
+  handlers.insert("oauth.restart", |p| Box::pin(oauth::rpc_restart(p)));
+  handlers.insert("oauth.remote_authorize", |p| Box::pin(oauth::rpc_remote_authorize(p)));
+}
+
+fn register_auth_handlers(handlers: &mut HashMap<&'static str, RpcHandler>) {
+  handlers.insert("auth.set_credentials", |p| Box::pin(auth::rpc_set_credentials(p)));
+  handlers.insert("auth.remove_credentials", |p| Box::pin(auth::rpc_remove_credentials(p)));
+  handlers.insert("auth.status", |p| Box::pin(auth::rpc_status(p)));
+  handlers.insert("aws.list_profiles", |p| Box::pin(auth::aws::rpc_list_profiles(p)));
+  handlers.insert("aws.assume_role", |p| Box::pin(auth::aws::rpc_assume_role(p)));
+  handlers.insert("aws.submit_mfa", |p| Box::pin(auth::aws::rpc_submit_mfa(p)));
+  handlers.insert("aws.clear_sessions", |p| Box::pin(auth::aws::rpc_clear_sessions(p)));
+}
+
+fn register_budget_handlers(handlers: &mut HashMap<&'static str, RpcHandler>) {
+  handlers.insert("budget.declare_costs", |p| Box::pin(budget::rpc_declare_costs(p)));
+  handlers.insert("budget.set", |p| Box::pin(budget::rpc_set_budget(p)));
+  handlers.insert("budget.remove", |p| Box::pin(budget::rpc_remove_budget(p)));
+  handlers.insert("budget.status", |p| Box::pin(budget::rpc_status(p)));
 }
 
 fn register_mcp_handlers(handlers: &mut HashMap<&'static str, RpcHandler>) {
@@ -183,19 +323,201 @@ fn register_mcp_handlers(handlers: &mut HashMap<&'static str, RpcHandler>) {
   handlers.insert("mcp.call_tool", |p| Box::pin(mcp::call_tool(p)));
   handlers.insert("mcp.poll_pending_calls", |_| Box::pin(mcp::poll_pending_calls()));
   handlers.insert("mcp.submit_call_result", |p| Box::pin(mcp::submit_call_result(p)));
+  handlers.insert("mcp.pause_server", |p| Box::pin(mcp::pause::rpc_pause(p)));
+  handlers.insert("mcp.resume_server", |p| Box::pin(mcp::pause::rpc_resume(p)));
+  handlers.insert("mcp.list_paused", |p| Box::pin(mcp::pause::rpc_list(p)));
+  handlers.insert("mcp.set_tool_timeout", |p| Box::pin(mcp::timeout::rpc_set_default(p)));
+}
+
+fn register_catalog_handlers(handlers: &mut HashMap<&'static str, RpcHandler>) {
+  handlers.insert("catalog.get", |p| Box::pin(catalog::rpc_get(p)));
+  handlers.insert("catalog.changes", |p| Box::pin(catalog::rpc_changes(p)));
+  handlers.insert("catalog.set_references", |p| Box::pin(catalog::rpc_set_references(p)));
+  handlers.insert("catalog.configure", |p| Box::pin(catalog::rpc_configure(p)));
+  handlers.insert("catalog.confirm_change", |p| Box::pin(catalog::rpc_confirm_change(p)));
+  handlers.insert("catalog.reject_change", |p| Box::pin(catalog::rpc_reject_change(p)));
+}
+
+fn register_workspace_handlers(handlers: &mut HashMap<&'static str, RpcHandler>) {
+  handlers.insert("workspace.export", |p| Box::pin(workspace::rpc_export(p)));
+  handlers.insert("workspace.import", |p| Box::pin(workspace::rpc_import(p)));
+  handlers.insert("workspace.stats", |p| Box::pin(workspace::stats::rpc_stats(p)));
+}
+
+fn register_automation_handlers(handlers: &mut HashMap<&'static str, RpcHandler>) {
+  handlers.insert("automation.dry_run", |p| Box::pin(automation::rpc_dry_run(p)));
+  handlers.insert("automation.next_runs", |p| Box::pin(automation::rpc_next_runs(p)));
+}
+
+fn register_maintenance_handlers(handlers: &mut HashMap<&'static str, RpcHandler>) {
+  handlers.insert("maintenance.status", |p| Box::pin(maintenance::rpc_status(p)));
+  handlers.insert("maintenance.configure", |p| Box::pin(maintenance::rpc_configure(p)));
+  handlers.insert("maintenance.run", |p| Box::pin(maintenance::rpc_run(p)));
+}
+
+fn register_embeddings_handlers(handlers: &mut HashMap<&'static str, RpcHandler>) {
+  handlers.insert("embeddings.list_indexes", |p| Box::pin(embeddings::rpc_list_indexes(p)));
+  handlers.insert("embeddings.configure_index", |p| Box::pin(embeddings::rpc_configure_index(p)));
+  handlers.insert("embeddings.remove_index", |p| Box::pin(embeddings::rpc_remove_index(p)));
+  handlers.insert("embeddings.set_api_key", |p| Box::pin(embeddings::rpc_set_api_key(p)));
+  handlers.insert("embeddings.embed", |p| Box::pin(embeddings::rpc_embed(p)));
+}
+
+fn register_context_handlers(handlers: &mut HashMap<&'static str, RpcHandler>) {
+  handlers.insert("context.set", |p| Box::pin(context::rpc_set(p)));
+  handlers.insert("context.clear", |p| Box::pin(context::rpc_clear(p)));
+  handlers.insert("context.get", |p| Box::pin(context::rpc_get(p)));
+  handlers.insert("context.get_policy", |p| Box::pin(context::rpc_get_policy(p)));
+  handlers.insert("context.set_policy", |p| Box::pin(context::rpc_set_policy(p)));
+}
+
+fn register_peer_handlers(handlers: &mut HashMap<&'static str, RpcHandler>) {
+  handlers.insert("peer.call", |p| Box::pin(peer::rpc_call(p)));
+  handlers.insert("peer.get_policy", |p| Box::pin(peer::rpc_get_policy(p)));
+  handlers.insert("peer.set_policy", |p| Box::pin(peer::rpc_set_policy(p)));
+}
+
+fn register_composite_handlers(handlers: &mut HashMap<&'static str, RpcHandler>) {
+  handlers.insert("composite.list", |p| Box::pin(composite::rpc_list(p)));
+  handlers.insert("composite.define", |p| Box::pin(composite::rpc_define(p)));
+  handlers.insert("composite.remove", |p| Box::pin(composite::rpc_remove(p)));
+}
+
+fn register_outbox_handlers(handlers: &mut HashMap<&'static str, RpcHandler>) {
+  handlers.insert("outbox.enqueue", |p| Box::pin(outbox::rpc_enqueue(p)));
+  handlers.insert("outbox.list", |p| Box::pin(outbox::rpc_list(p)));
+  handlers.insert("outbox.cancel", |p| Box::pin(outbox::rpc_cancel(p)));
+  handlers.insert("outbox.retry", |p| Box::pin(outbox::rpc_retry(p)));
+  handlers.insert("outbox.configure", |p| Box::pin(outbox::rpc_configure(p)));
+}
+
+fn register_scheduler_handlers(handlers: &mut HashMap<&'static str, RpcHandler>) {
+  handlers.insert("schedule.create", |p| Box::pin(scheduler::rpc_create(p)));
+  handlers.insert("schedule.list", |p| Box::pin(scheduler::rpc_list(p)));
+  handlers.insert("schedule.delete", |p| Box::pin(scheduler::rpc_delete(p)));
+  handlers.insert("schedule.results", |p| Box::pin(scheduler::rpc_results(p)));
+}
+
+fn register_chaos_handlers(handlers: &mut HashMap<&'static str, RpcHandler>) {
+  handlers.insert("chaos.configure", |p| Box::pin(chaos::rpc_configure(p)));
+  handlers.insert("chaos.status", |p| Box::pin(chaos::rpc_status(p)));
+}
+
+fn register_history_handlers(handlers: &mut HashMap<&'static str, RpcHandler>) {
+  handlers.insert("history.transcript", |p| Box::pin(history::transcript::rpc_transcript(p)));
+}
+
+fn register_profiles_handlers(handlers: &mut HashMap<&'static str, RpcHandler>) {
+  handlers.insert("profiles.get", |p| Box::pin(profiles::rpc_get(p)));
+  handlers.insert("profiles.assign", |p| Box::pin(profiles::rpc_assign(p)));
+  handlers.insert("profiles.set_override", |p| Box::pin(profiles::rpc_set_override(p)));
+}
+
+fn register_secrets_handlers(handlers: &mut HashMap<&'static str, RpcHandler>) {
+  handlers.insert("secrets.set", |p| Box::pin(secrets::rpc_set(p)));
+  handlers.insert("secrets.remove", |p| Box::pin(secrets::rpc_remove(p)));
+  handlers.insert("secrets.list", |p| Box::pin(secrets::rpc_list(p)));
+}
+
+fn register_signing_handlers(handlers: &mut HashMap<&'static str, RpcHandler>) {
+  handlers.insert("signing.verify", |p| Box::pin(signing::rpc_verify(p)));
+  handlers.insert("signing.trust", |p| Box::pin(signing::rpc_trust(p)));
+  handlers.insert("signing.untrust", |p| Box::pin(signing::rpc_untrust(p)));
+  handlers.insert("signing.list_keys", |p| Box::pin(signing::rpc_list(p)));
+  handlers.insert("signing.set_policy", |p| Box::pin(signing::rpc_set_policy(p)));
+}
+
+fn register_servers_handlers(handlers: &mut HashMap<&'static str, RpcHandler>) {
+  handlers.insert("servers.check_updates", |p| Box::pin(servers::rpc_check_updates(p)));
+  handlers.insert("servers.install", |p| Box::pin(servers::rpc_install(p)));
+  handlers.insert("servers.list", |p| Box::pin(servers::rpc_list(p)));
+  handlers.insert("servers.log", |p| Box::pin(server_logs::rpc_log(p)));
+  handlers.insert("servers.logs", |p| Box::pin(server_logs::rpc_logs(p)));
+  handlers.insert("servers.pin", |p| Box::pin(servers::rpc_pin(p)));
+  handlers.insert("servers.clear_logs", |p| Box::pin(server_logs::rpc_clear_logs(p)));
+  handlers.insert("servers.read", |p| Box::pin(servers::rpc_read(p)));
+  handlers.insert("servers.remove", |p| Box::pin(servers::rpc_remove(p)));
+  handlers.insert("servers.rollback", |p| Box::pin(servers::rpc_rollback(p)));
+  handlers.insert("servers.upgrade", |p| Box::pin(servers::rpc_upgrade(p)));
+}
+
+fn register_browser_handlers(handlers: &mut HashMap<&'static str, RpcHandler>) {
+  handlers.insert("browser.respond", |p| Box::pin(browser::rpc_respond(p)));
 }
 
 // =============================================================================
 // Request Handling
 // =============================================================================
 
-/// Handle an RPC request and return a response.
-pub async fn handle(request: RpcRequest) -> RpcResponse {
+/// How a request reached the bridge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transport {
+  /// The extension, over native messaging
+  NativeMessaging,
+  /// Safari's extension, over HTTP
+  Extension,
+  /// Another local program, over the HTTP API
+  Client,
+}
+
+tokio::task_local! {
+  static CALLER: Option<String>;
+  static TRANSPORT: Transport;
+}
+
+/// The MCP server the current request is made on behalf of, if any.
+pub fn caller() -> Option<String> {
+  CALLER.try_with(|caller| caller.clone()).ok().flatten()
+}
+
+/// How the current request reached the bridge; `None` outside a request.
+pub fn transport() -> Option<Transport> {
+  TRANSPORT.try_with(|transport| *transport).ok()
+}
+
+/// Reject a request that isn't the extension's own: one made on behalf of
+/// a server, or by a program over the HTTP API. `action` finishes "cannot
+/// ...", e.g. "change profiles".
+pub fn require_extension(action: &str) -> Result<(), RpcError> {
+  if let Some(caller) = caller() {
+    return Err(RpcError::new(-32000, format!("Server '{}' cannot {}", caller, action)));
+  }
+  match transport() {
+    Some(Transport::NativeMessaging | Transport::Extension) => Ok(()),
+    _ => Err(RpcError::new(-32000, format!("Only the Harbor extension can {}", action))),
+  }
+}
+
+/// Like `require_extension`, but only the extension's native messaging
+/// connection passes, for answers to requests sent over it alone.
+pub fn require_native_messaging(action: &str) -> Result<(), RpcError> {
+  require_extension(action)?;
+  match transport() {
+    Some(Transport::NativeMessaging) => Ok(()),
+    _ => Err(RpcError::new(-32000, format!("Only the Harbor extension's native messaging connection can {}", action))),
+  }
+}
+
+/// Run `f` as if it were a request from the extension over native
+/// messaging.
+#[cfg(test)]
+pub async fn as_extension<F: Future>(f: F) -> F::Output {
+  TRANSPORT.scope(Transport::NativeMessaging, f).await
+}
+
+/// Run `f` as if it were a request made on behalf of the server `caller`.
+#[cfg(test)]
+pub async fn as_caller<F: Future>(caller: &str, f: F) -> F::Output {
+  CALLER.scope(Some(caller.to_string()), f).await
+}
+
+/// Handle an RPC request that arrived over `transport` and return a response.
+pub async fn handle(request: RpcRequest, transport: Transport) -> RpcResponse {
   let handlers = get_handlers();
 
   match handlers.get(request.method.as_str()) {
     Some(handler) => {
-      let result = handler(request.params).await;
+      let result = TRANSPORT.scope(transport, CALLER.scope(request.caller, handler(request.params))).await;
       match result {
         Ok(value) => RpcResponse::success(request.id, value),
         Err(error) => RpcResponse::error(request.id, error),
@@ -234,9 +556,10 @@ mod tests {
       id: serde_json::json!(1),
       method: "system.health".to_string(),
       params: serde_json::json!({}),
+      caller: None,
     };
 
-    let response = handle(request).await;
+    let response = handle(request, Transport::Extension).await;
     assert!(response.error.is_none());
     assert!(response.result.is_some());
   }
@@ -247,13 +570,23 @@ mod tests {
       id: serde_json::json!(1),
       method: "unknown.method".to_string(),
       params: serde_json::json!({}),
+      caller: None,
     };
 
-    let response = handle(request).await;
+    let response = handle(request, Transport::Extension).await;
     assert!(response.error.is_some());
     assert_eq!(response.error.unwrap().code, -32601);
   }
 
+  #[tokio::test]
+  async fn test_require_native_messaging() {
+    let action = "answer browser requests";
+    assert!(TRANSPORT.scope(Transport::NativeMessaging, async { require_native_messaging(action) }).await.is_ok());
+    assert!(TRANSPORT.scope(Transport::Extension, async { require_extension(action) }).await.is_ok());
+    assert!(TRANSPORT.scope(Transport::Extension, async { require_native_messaging(action) }).await.is_err());
+    assert!(TRANSPORT.scope(Transport::Client, async { require_native_messaging(action) }).await.is_err());
+  }
+
   #[test]
   fn test_list_methods() {
     let methods = list_methods();
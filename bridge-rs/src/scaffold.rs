@@ -0,0 +1,180 @@
+//! New WASM server projects, made from the Rust template
+//! (`mcp-servers/templates/wasm-rust`), which is built into the bridge.
+//!
+//! The template's placeholder names (`my-mcp-server`, `my-wasm-server`,
+//! "My WASM Server", ...) are replaced with the new server's, and its
+//! dependencies on the SDK and harbor-test, paths within this repository,
+//! are pointed at `sdk` and the harbor-test beside it. Neither crate is
+//! published, so a checkout of the repository is needed.
+
+use std::path::{Path, PathBuf};
+
+const TEMPLATE: &[(&str, &str)] = &[
+    ("Cargo.toml", include_str!("../../mcp-servers/templates/wasm-rust/Cargo.toml")),
+    ("README.md", include_str!("../../mcp-servers/templates/wasm-rust/README.md")),
+    (".gitignore", include_str!("../../mcp-servers/templates/wasm-rust/.gitignore")),
+    ("manifest.json", include_str!("../../mcp-servers/templates/wasm-rust/manifest.json")),
+    ("src/main.rs", include_str!("../../mcp-servers/templates/wasm-rust/src/main.rs")),
+    (
+        "resources/usage.md",
+        include_str!("../../mcp-servers/templates/wasm-rust/resources/usage.md"),
+    ),
+    (
+        "tests/server.rs",
+        include_str!("../../mcp-servers/templates/wasm-rust/tests/server.rs"),
+    ),
+];
+
+/// The template's SDK dependency, with the comment above it.
+const TEMPLATE_SDK: &str = "# The Harbor MCP SDK (mcp-servers/sdk); point the path at it when the
+# server lives outside this repository
+harbor-mcp-sdk = { path = \"../../sdk\" }";
+
+/// The template's harbor-test dependency, with the comment above it.
+const TEMPLATE_TEST: &str = "# Runs the built module in tests (tests/server.rs); the path is to
+# mcp-servers/harbor-test, like the SDK's
+harbor-test = { path = \"../../harbor-test\" }";
+
+/// Where a new project gets the SDK and harbor-test from, as Cargo
+/// dependency specs.
+struct Dependencies {
+    sdk: String,
+    test: String,
+}
+
+/// Whether `name` works as a server ID and a Cargo package name.
+pub fn valid_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_lowercase())
+        && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+}
+
+/// "weather-tools" → "Weather Tools"
+fn display_name(name: &str) -> String {
+    name.split('-')
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|first| first.to_ascii_uppercase().to_string() + chars.as_str())
+                .unwrap_or_default()
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// A template file with the placeholders filled in.
+fn fill(contents: &str, name: &str, dependencies: &Dependencies) -> String {
+    let display = display_name(name);
+    contents
+        .replace(TEMPLATE_SDK, &format!("# The Harbor MCP SDK\nharbor-mcp-sdk = {}", dependencies.sdk))
+        .replace(
+            TEMPLATE_TEST,
+            &format!("# Runs the built module in tests (tests/server.rs)\nharbor-test = {}", dependencies.test),
+        )
+        .replace("my-mcp-server", name)
+        .replace("my_mcp_server", &name.replace('-', "_"))
+        .replace("my-wasm-server", name)
+        .replace("My WASM Server", &display)
+        .replace("My MCP Server", &display)
+}
+
+/// The dependencies, by path from the SDK's.
+fn dependencies(sdk: &Path) -> Result<Dependencies, String> {
+    let path = sdk
+        .canonicalize()
+        .map_err(|e| format!("Failed to resolve SDK path {:?}: {}", path, e))?;
+    if !path.join("Cargo.toml").is_file() {
+        return Err(format!("{:?} is not the SDK (no Cargo.toml)", path));
+    }
+    let spec = |path: &Path| format!("{{ path = {:?} }}", path.display().to_string());
+    let test = path.parent().map(|parent| parent.join("harbor-test")).unwrap_or_default();
+    if !test.join("Cargo.toml").is_file() {
+        return Err(format!("No harbor-test beside the SDK at {:?}", test));
+    }
+    Ok(Dependencies {
+        sdk: spec(&path),
+        test: spec(&test),
+    })
+}
+
+/// Write a new project for the server `name` into `dir`, which must not
+/// exist or be empty, using the SDK at `sdk` (`mcp-servers/sdk` in a
+/// checkout of the repository). Returns the files written.
+pub fn create(name: &str, dir: &Path, sdk: &Path) -> Result<Vec<PathBuf>, String> {
+    if !valid_name(name) {
+        return Err(format!(
+            "'{}' is not a valid server name: use lowercase letters, digits and '-', starting with a letter",
+            name
+        ));
+    }
+    if dir.read_dir().is_ok_and(|mut entries| entries.next().is_some()) {
+        return Err(format!("{:?} already exists and is not empty", dir));
+    }
+    let dependencies = dependencies(sdk)?;
+
+    let mut written = Vec::new();
+    for (file, contents) in TEMPLATE {
+        let path = dir.join(file);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create {:?}: {}", parent, e))?;
+        }
+        std::fs::write(&path, fill(contents, name, &dependencies))
+            .map_err(|e| format!("Failed to write {:?}: {}", path, e))?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_names() {
+        assert!(valid_name("weather-2"));
+        assert!(!valid_name("Weather"));
+        assert!(!valid_name("2weather"));
+        assert!(!valid_name("weather_tools"));
+        assert_eq!(display_name("weather-tools"), "Weather Tools");
+    }
+
+    #[test]
+    fn test_fill_template() {
+        let dependencies = Dependencies {
+            sdk: "{ path = \"/sdk\" }".to_string(),
+            test: "{ path = \"/harbor-test\" }".to_string(),
+        };
+        let files: Vec<String> = TEMPLATE
+            .iter()
+            .map(|(_, contents)| fill(contents, "weather-tools", &dependencies))
+            .collect();
+        for contents in &files {
+            assert!(!contents.contains("my-mcp-server") && !contents.contains("my-wasm-server"));
+            assert!(!contents.contains("My WASM Server") && !contents.contains("My MCP Server"));
+        }
+        // The template's dependencies are still where `fill` expects them
+        assert!(files[0].contains("harbor-mcp-sdk = { path = \"/sdk\" }"));
+        assert!(files[0].contains("harbor-test = { path = \"/harbor-test\" }"));
+        assert!(files[6].contains("TestServer::build(\"weather_tools\")"));
+        assert!(files[0].contains("name = \"weather-tools\""));
+        let manifest: serde_json::Value = serde_json::from_str(&files[3]).unwrap();
+        assert_eq!(manifest["id"], "weather-tools");
+        assert_eq!(manifest["wasm"]["file"], "target/wasm32-wasip1/release/weather_tools.wasm");
+    }
+
+    #[test]
+    fn test_create() {
+        let dir = std::env::temp_dir().join(format!("harbor-scaffold-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let sdk = Path::new(env!("CARGO_MANIFEST_DIR")).join("../mcp-servers/sdk");
+        let written = create("weather", &dir, &sdk).unwrap();
+        assert_eq!(written.len(), TEMPLATE.len());
+        assert!(dir.join("src/main.rs").is_file());
+        let manifest = std::fs::read_to_string(dir.join("Cargo.toml")).unwrap();
+        assert!(manifest.contains("mcp-servers/sdk\" }") && manifest.contains("mcp-servers/harbor-test\" }"));
+        assert!(create("weather", &dir, &sdk).unwrap_err().contains("not empty"));
+        assert!(create("weather", &dir.join("other"), &dir.join("src")).unwrap_err().contains("not the SDK"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
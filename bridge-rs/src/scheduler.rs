@@ -0,0 +1,483 @@
+//! Scheduled tool calls.
+//!
+//! A schedule calls one tool, with fixed arguments, on a cron expression or
+//! recurrence phrase (see `automation::Timing`) in an optional timezone:
+//! "fetch this feed every hour". The extension manages schedules with the
+//! `schedule.*` methods; a component granted `schedule` creates its own
+//! through the `harbor:mcp/schedule` import, which is how the built-in
+//! schedule server offers them to chat. A server only sees and deletes the
+//! schedules it created.
+//!
+//! A server's schedule runs as that server: it may only call the server's
+//! own tools or those the peer policy lets it reach (see `crate::peer`),
+//! checked when it is created and again at each run, and the call is made
+//! on behalf of the server's profile and charged to the target's budget
+//! like any other.
+//!
+//! Each schedule keeps its last few results, and every run is announced as
+//! a `schedule/ran` event. A run missed while the bridge wasn't running
+//! happens once when it starts again. Everything is kept in
+//! `~/.harbor/schedules.json`.
+
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::automation::Timing;
+use crate::events;
+use crate::rpc::RpcError;
+use crate::store::{JsonStore, Stored};
+
+/// How often due schedules are looked for.
+const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Most schedules kept, across all owners.
+const MAX_SCHEDULES: usize = 200;
+
+/// Most schedules one server may have.
+const MAX_PER_OWNER: usize = 20;
+
+/// Results kept per schedule, newest first.
+const RESULTS_KEPT: usize = 10;
+
+/// Longest result kept, as JSON text; longer ones are cut to a string.
+const MAX_RESULT_CHARS: usize = 16 * 1024;
+
+/// Shortest time allowed between two runs of a schedule.
+const MIN_GAP_MINUTES: i64 = 5;
+
+/// Who created a schedule when the extension did.
+const EXTENSION_OWNER: &str = "extension";
+
+/// What's needed to create a schedule.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NewSchedule {
+    pub name: String,
+    pub server_id: String,
+    pub tool: String,
+    #[serde(default)]
+    pub args: serde_json::Value,
+    /// Cron expression or recurrence phrase
+    pub schedule: String,
+    /// IANA timezone the schedule is in; defaults to the system's
+    #[serde(default)]
+    pub timezone: Option<String>,
+}
+
+/// A scheduled tool call.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Schedule {
+    pub id: String,
+    pub name: String,
+    /// `extension`, or the ID of the server that created it
+    pub owner: String,
+    pub server_id: String,
+    pub tool: String,
+    #[serde(default)]
+    pub args: serde_json::Value,
+    pub schedule: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<String>,
+    pub created_at: i64,
+    /// When it runs next (Unix timestamp ms); none if it never runs again
+    pub next_run_at: Option<i64>,
+    /// Latest first
+    #[serde(default)]
+    pub results: Vec<RunResult>,
+}
+
+/// The outcome of one run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunResult {
+    /// When the run started (Unix timestamp ms)
+    pub started_at: i64,
+    pub duration_ms: u64,
+    pub ok: bool,
+    /// The tool's result; cut to a string if it was too long
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default)]
+    pub truncated: bool,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SchedulesFile {
+    #[serde(default)]
+    schedules: Vec<Schedule>,
+}
+
+impl Stored for SchedulesFile {
+    const FILE_NAME: &'static str = "schedules.json";
+}
+
+/// Scheduler subsystem state.
+#[derive(Default)]
+pub struct SchedulerState {
+    /// Schedules, loaded on first use
+    file: JsonStore<SchedulesFile>,
+    /// Held while due schedules run
+    running: Mutex<()>,
+}
+
+fn state() -> &'static SchedulerState {
+    &crate::state::get().scheduler
+}
+
+/// Up to `count` run times after `from`, in the schedule's timezone.
+fn upcoming(timing: &Timing, timezone: Option<&str>, from: DateTime<Utc>, count: usize) -> Result<Vec<i64>, String> {
+    fn runs<Z: TimeZone>(timing: &Timing, from: DateTime<Z>, count: usize) -> Vec<i64> {
+        let horizon = chrono::Duration::days(366);
+        timing.upcoming(from, count, horizon).iter().map(|t| t.timestamp_millis()).collect()
+    }
+    match timezone {
+        Some(name) => {
+            let tz: chrono_tz::Tz = name.parse().map_err(|_| format!("Unknown timezone '{}'", name))?;
+            Ok(runs(timing, from.with_timezone(&tz), count))
+        }
+        None => Ok(runs(timing, from.with_timezone(&chrono::Local), count)),
+    }
+}
+
+/// When `schedule` runs next after `from`.
+fn next_run(schedule: &Schedule, from: DateTime<Utc>) -> Option<i64> {
+    let timing = Timing::parse(&schedule.schedule).ok()?;
+    upcoming(&timing, schedule.timezone.as_deref(), from, 1).ok()?.first().copied()
+}
+
+/// Check a new schedule and work out its first run.
+fn validate(new: &NewSchedule, now: DateTime<Utc>) -> Result<i64, String> {
+    for (field, value) in [("name", &new.name), ("server_id", &new.server_id), ("tool", &new.tool)] {
+        if value.trim().is_empty() {
+            return Err(format!("'{}' may not be empty", field));
+        }
+    }
+    if !(new.args.is_null() || new.args.is_object()) {
+        return Err("'args' must be an object".to_string());
+    }
+    let timing = Timing::parse(&new.schedule).map_err(|e| format!("Invalid schedule '{}': {}", new.schedule, e))?;
+    let runs = upcoming(&timing, new.timezone.as_deref(), now, 10)?;
+    let first = *runs.first().ok_or("The schedule never runs")?;
+    if runs.windows(2).any(|pair| pair[1] - pair[0] < MIN_GAP_MINUTES * 60 * 1000) {
+        return Err(format!("Schedules may run at most every {} minutes", MIN_GAP_MINUTES));
+    }
+    Ok(first)
+}
+
+/// Whether `owner` may see and change `schedule`. The extension may see
+/// them all.
+fn visible_to(schedule: &Schedule, owner: Option<&str>) -> bool {
+    match owner {
+        Some(owner) => schedule.owner == owner,
+        None => true,
+    }
+}
+
+/// Fail unless server `owner` may call `server_id`'s `tool`: its own, or
+/// one the peer policy allows.
+async fn check_target(owner: &str, server_id: &str, tool: &str) -> Result<(), String> {
+    if owner == server_id || crate::peer::allows(owner, server_id, tool).await {
+        return Ok(());
+    }
+    Err(format!("'{}' is not allowed to call {}/{}", owner, server_id, tool))
+}
+
+/// Create a schedule for `owner` (a server ID, or `None` for the
+/// extension).
+pub async fn create(owner: Option<&str>, new: NewSchedule) -> Result<Schedule, String> {
+    let now = Utc::now();
+    let next_run_at = validate(&new, now)?;
+    if let Some(owner) = owner {
+        check_target(owner, &new.server_id, &new.tool).await?;
+    }
+    let schedule = Schedule {
+        id: format!("schedule-{:016x}", rand::random::<u64>()),
+        name: new.name.trim().to_string(),
+        owner: owner.unwrap_or(EXTENSION_OWNER).to_string(),
+        server_id: new.server_id,
+        tool: new.tool,
+        args: if new.args.is_null() { serde_json::json!({}) } else { new.args },
+        schedule: new.schedule,
+        timezone: new.timezone,
+        created_at: now.timestamp_millis(),
+        next_run_at: Some(next_run_at),
+        results: Vec::new(),
+    };
+    state()
+        .file
+        .update(|file| {
+            if file.schedules.len() >= MAX_SCHEDULES {
+                return Err(format!("There are already {} schedules; delete some first", MAX_SCHEDULES));
+            }
+            if owner.is_some() && file.schedules.iter().filter(|s| s.owner == schedule.owner).count() >= MAX_PER_OWNER {
+                return Err(format!("A server may have at most {} schedules; delete some first", MAX_PER_OWNER));
+            }
+            file.schedules.push(schedule.clone());
+            Ok(())
+        })
+        .await?;
+    tracing::info!(
+        "Scheduled {}/{} '{}' ({}) for {}",
+        schedule.server_id,
+        schedule.tool,
+        schedule.schedule,
+        schedule.id,
+        schedule.owner
+    );
+    Ok(schedule)
+}
+
+/// The schedules `owner` may see, oldest first.
+pub async fn list(owner: Option<&str>) -> Vec<Schedule> {
+    state().file.read(|file| file.schedules.iter().filter(|s| visible_to(s, owner)).cloned().collect()).await
+}
+
+/// Delete a schedule, returning whether `owner` had one with that ID.
+pub async fn delete(owner: Option<&str>, id: &str) -> bool {
+    state()
+        .file
+        .update(|file| {
+            let before = file.schedules.len();
+            file.schedules.retain(|s| s.id != id || !visible_to(s, owner));
+            file.schedules.len() != before
+        })
+        .await
+}
+
+/// Start running due schedules. Must be called from within the tokio
+/// runtime.
+pub fn start() {
+    tokio::spawn(async {
+        let mut interval = tokio::time::interval(CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            run_due().await;
+        }
+    });
+}
+
+/// Run every schedule that is due, one at a time.
+async fn run_due() {
+    let _running = state().running.lock().await;
+    let now = Utc::now();
+    // Move each due schedule on first, so a slow call can't make it run twice
+    let due: Vec<Schedule> = state()
+        .file
+        .update(|file| {
+            let mut due = Vec::new();
+            for schedule in file.schedules.iter_mut() {
+                if schedule.next_run_at.is_some_and(|at| at <= now.timestamp_millis()) {
+                    due.push(schedule.clone());
+                    schedule.next_run_at = next_run(schedule, now);
+                }
+            }
+            due
+        })
+        .await;
+
+    for schedule in due {
+        let result = run(&schedule).await;
+        record(&schedule, result).await;
+    }
+}
+
+/// Call a schedule's tool, as its owner.
+async fn run(schedule: &Schedule) -> RunResult {
+    let started_at = Utc::now().timestamp_millis();
+    let started = Instant::now();
+    let outcome = call(schedule).await;
+    let duration_ms = started.elapsed().as_millis() as u64;
+    match outcome {
+        Ok(response) => {
+            let ok = !crate::mcp::call_failed(&response);
+            let (result, truncated) = clip(response.get("result").cloned().unwrap_or_default());
+            RunResult {
+                started_at,
+                duration_ms,
+                ok,
+                result: Some(result),
+                error: None,
+                truncated,
+            }
+        }
+        Err(e) => {
+            tracing::warn!(
+                "Scheduled call {} ({}/{}) failed: {}",
+                schedule.id,
+                schedule.server_id,
+                schedule.tool,
+                e.message
+            );
+            RunResult {
+                started_at,
+                duration_ms,
+                ok: false,
+                result: None,
+                error: Some(e.message),
+                truncated: false,
+            }
+        }
+    }
+}
+
+/// Make a schedule's call: for a server's schedule, only while the peer
+/// policy still allows it, and on behalf of the server's profile.
+async fn call(schedule: &Schedule) -> Result<serde_json::Value, RpcError> {
+    let mut profile = None;
+    if schedule.owner != EXTENSION_OWNER {
+        check_target(&schedule.owner, &schedule.server_id, &schedule.tool)
+            .await
+            .map_err(|e| RpcError::new(-32000, e))?;
+        profile = crate::profiles::current().await.profile_of(&schedule.owner).map(String::from);
+    }
+    crate::mcp::call_tool(serde_json::json!({
+        "serverId": schedule.server_id,
+        "toolName": schedule.tool,
+        "args": schedule.args,
+        "profile": profile,
+    }))
+    .await
+}
+
+/// A result no longer than `MAX_RESULT_CHARS` as JSON text, and whether it
+/// had to be cut.
+fn clip(result: serde_json::Value) -> (serde_json::Value, bool) {
+    let text = match &result {
+        serde_json::Value::String(text) => text.clone(),
+        other => other.to_string(),
+    };
+    if text.chars().count() <= MAX_RESULT_CHARS {
+        return (result, false);
+    }
+    (serde_json::Value::String(text.chars().take(MAX_RESULT_CHARS).collect()), true)
+}
+
+/// Keep a run's result, unless the schedule was deleted meanwhile, and
+/// announce it.
+async fn record(schedule: &Schedule, result: RunResult) {
+    state()
+        .file
+        .update(|file| {
+            if let Some(current) = file.schedules.iter_mut().find(|s| s.id == schedule.id) {
+                current.results.insert(0, result.clone());
+                current.results.truncate(RESULTS_KEPT);
+            }
+        })
+        .await;
+    events::emit(
+        "schedule/ran",
+        serde_json::json!({
+            "id": schedule.id,
+            "name": schedule.name,
+            "owner": schedule.owner,
+            "server_id": schedule.server_id,
+            "tool": schedule.tool,
+            "result": result,
+        }),
+    );
+}
+
+// ============================================================================
+// RPC Handlers
+// ============================================================================
+
+fn id_param(params: &serde_json::Value) -> Result<&str, RpcError> {
+    params
+        .get("id")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| RpcError::invalid_params("Missing 'id' parameter"))
+}
+
+/// Create a schedule:
+/// `{ name, server_id, tool, args?, schedule, timezone? }`.
+pub async fn rpc_create(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    crate::rpc::require_extension("manage schedules over RPC")?;
+    let new: NewSchedule = serde_json::from_value(params)
+        .map_err(|e| RpcError::invalid_params(format!("Invalid schedule: {}", e)))?;
+    let schedule = create(None, new).await.map_err(RpcError::invalid_params)?;
+    Ok(serde_json::json!({ "schedule": schedule }))
+}
+
+/// List schedules, without their results: `{ owner? }`.
+pub async fn rpc_list(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    crate::rpc::require_extension("manage schedules over RPC")?;
+    let owner = params.get("owner").and_then(|v| v.as_str());
+    let schedules: Vec<Schedule> = list(None)
+        .await
+        .into_iter()
+        .filter(|s| owner.is_none() || owner == Some(s.owner.as_str()))
+        .map(|s| Schedule {
+            results: Vec::new(),
+            ..s
+        })
+        .collect();
+    Ok(serde_json::json!({ "schedules": schedules }))
+}
+
+/// Delete a schedule: `{ id }`.
+pub async fn rpc_delete(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    crate::rpc::require_extension("manage schedules over RPC")?;
+    let deleted = delete(None, id_param(&params)?).await;
+    Ok(serde_json::json!({ "deleted": deleted }))
+}
+
+/// A schedule's recent results, latest first: `{ id }`.
+pub async fn rpc_results(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    crate::rpc::require_extension("manage schedules over RPC")?;
+    let id = id_param(&params)?;
+    let schedule = list(None)
+        .await
+        .into_iter()
+        .find(|s| s.id == id)
+        .ok_or_else(|| RpcError::new(-32000, format!("No schedule '{}'", id)))?;
+    Ok(serde_json::json!({ "id": schedule.id, "results": schedule.results }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_schedule(schedule: &str) -> NewSchedule {
+        NewSchedule {
+            name: "Feed".to_string(),
+            server_id: "fetch".to_string(),
+            tool: "fetch.url".to_string(),
+            args: serde_json::json!({ "url": "https://example.com/feed.xml" }),
+            schedule: schedule.to_string(),
+            timezone: Some("UTC".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        let now = Utc.with_ymd_and_hms(2024, 6, 3, 9, 10, 0).unwrap();
+        let next = validate(&new_schedule("every hour"), now).unwrap();
+        assert_eq!(next, Utc.with_ymd_and_hms(2024, 6, 3, 10, 0, 0).unwrap().timestamp_millis());
+        assert!(validate(&new_schedule("0 * * * *"), now).is_ok());
+
+        let error = validate(&new_schedule("* * * * *"), now).unwrap_err();
+        assert!(error.contains("at most every 5 minutes"));
+        assert!(validate(&new_schedule("every blursday"), now).is_err());
+
+        let mut unnamed = new_schedule("every hour");
+        unnamed.name = " ".to_string();
+        assert!(validate(&unnamed, now).is_err());
+        let mut elsewhere = new_schedule("every hour");
+        elsewhere.timezone = Some("Mars/Olympus".to_string());
+        assert!(validate(&elsewhere, now).unwrap_err().contains("Unknown timezone"));
+    }
+
+    #[test]
+    fn test_clip() {
+        let small = serde_json::json!({ "items": [1, 2, 3] });
+        assert_eq!(clip(small.clone()), (small, false));
+
+        let (clipped, truncated) = clip(serde_json::Value::String("é".repeat(MAX_RESULT_CHARS + 1)));
+        assert!(truncated);
+        assert_eq!(clipped.as_str().unwrap().chars().count(), MAX_RESULT_CHARS);
+    }
+}
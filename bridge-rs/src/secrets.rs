@@ -0,0 +1,272 @@
+//! Named secrets for servers.
+//!
+//! A server declares the secrets it needs by name (`API_KEY`), in its
+//! manifest or at start, and the user enters the values once. They are
+//! kept in the OS keychain, one entry per server holding all of its
+//! secrets, and handed to the server when it starts, so keys never have to
+//! be built into a module. Values can be set and removed over RPC but
+//! never read back. Secrets are stored by server ID, so a component server
+//! is handed them only once the user has approved them for that very
+//! module (see `wasm::approval`); another module started under the same
+//! ID has to be approved again first.
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+
+use crate::rpc::RpcError;
+
+const KEYCHAIN_SERVICE: &str = "harbor-secrets";
+
+/// A secret a server needs.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct SecretDecl {
+    /// UPPER_SNAKE_CASE, as it is also the environment variable's name
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// The server can't start without it
+    #[serde(default = "default_true")]
+    pub required: bool,
+    /// Also set it as an environment variable, besides `get-secret`
+    #[serde(default = "default_true")]
+    pub env: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl SecretDecl {
+    pub fn validate(&self) -> Result<(), String> {
+        let valid = !self.name.is_empty()
+            && !self.name.starts_with(|c: char| c.is_ascii_digit())
+            && self
+                .name
+                .chars()
+                .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_');
+        if !valid {
+            return Err(format!("Secret names must be UPPER_SNAKE_CASE: '{}'", self.name));
+        }
+        Ok(())
+    }
+}
+
+/// Secret values handed to a server. `Debug` shows the names only.
+#[derive(Clone, Default)]
+pub struct Secrets {
+    values: BTreeMap<String, String>,
+    /// Names to set as environment variables
+    env: Vec<String>,
+}
+
+impl Secrets {
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.values.get(name).map(String::as_str)
+    }
+
+    /// The secrets to set as environment variables.
+    pub fn env(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.env
+            .iter()
+            .filter_map(|name| Some((name.as_str(), self.values.get(name)?.as_str())))
+    }
+}
+
+impl std::fmt::Debug for Secrets {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.values.keys()).finish()
+    }
+}
+
+/// Secrets subsystem state.
+#[derive(Default)]
+pub struct SecretsState {
+    /// Values read from the keychain, keyed by server ID
+    stored: RwLock<HashMap<String, BTreeMap<String, String>>>,
+}
+
+fn state() -> &'static SecretsState {
+    &crate::state::get().secrets
+}
+
+fn entry(server_id: &str) -> Result<keyring::Entry, String> {
+    keyring::Entry::new(KEYCHAIN_SERVICE, server_id)
+        .map_err(|e| format!("Failed to open keychain entry for {}: {}", server_id, e))
+}
+
+/// All secrets stored for a server.
+async fn stored(server_id: &str) -> Result<BTreeMap<String, String>, String> {
+    if let Some(values) = state().stored.read().await.get(server_id) {
+        return Ok(values.clone());
+    }
+    let values = match entry(server_id)?.get_password() {
+        Ok(json) => serde_json::from_str(&json)
+            .map_err(|e| format!("Failed to parse secrets for {}: {}", server_id, e))?,
+        Err(keyring::Error::NoEntry) => BTreeMap::new(),
+        Err(e) => return Err(format!("Failed to read secrets for {}: {}", server_id, e)),
+    };
+    state().stored.write().await.insert(server_id.to_string(), values.clone());
+    Ok(values)
+}
+
+async fn store(server_id: &str, values: BTreeMap<String, String>) -> Result<(), String> {
+    let entry = entry(server_id)?;
+    if values.is_empty() {
+        match entry.delete_password() {
+            Ok(()) | Err(keyring::Error::NoEntry) => {}
+            Err(e) => return Err(format!("Failed to delete secrets from keychain: {}", e)),
+        }
+    } else {
+        let json = serde_json::to_string(&values).map_err(|e| format!("Failed to serialize secrets: {}", e))?;
+        entry
+            .set_password(&json)
+            .map_err(|e| format!("Failed to store secrets in keychain: {}", e))?;
+    }
+    state().stored.write().await.insert(server_id.to_string(), values);
+    Ok(())
+}
+
+/// Pick the declared secrets out of `stored`, failing if a required one is
+/// missing.
+fn select(declared: &[SecretDecl], stored: &BTreeMap<String, String>) -> Result<Secrets, String> {
+    let missing: Vec<&str> = declared
+        .iter()
+        .filter(|decl| decl.required && !stored.contains_key(&decl.name))
+        .map(|decl| decl.name.as_str())
+        .collect();
+    if !missing.is_empty() {
+        return Err(format!("Missing required secrets: {}", missing.join(", ")));
+    }
+    let mut secrets = Secrets::default();
+    for decl in declared {
+        if let Some(value) = stored.get(&decl.name) {
+            secrets.values.insert(decl.name.clone(), value.clone());
+            if decl.env {
+                secrets.env.push(decl.name.clone());
+            }
+        }
+    }
+    Ok(secrets)
+}
+
+/// The values of the secrets `server_id` declares. Undeclared secrets are
+/// never handed out.
+pub async fn resolve(server_id: &str, declared: &[SecretDecl]) -> Result<Secrets, String> {
+    if declared.is_empty() {
+        return Ok(Secrets::default());
+    }
+    for decl in declared {
+        decl.validate()?;
+    }
+    select(declared, &stored(server_id).await?)
+}
+
+// ============================================================================
+// RPC Handlers
+// ============================================================================
+
+fn string_param<'a>(params: &'a serde_json::Value, name: &str) -> Result<&'a str, RpcError> {
+    params
+        .get(name)
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| RpcError::invalid_params(format!("Missing '{}' parameter", name)))
+}
+
+/// Store a secret for a server: `{ server_id, name, value }`. Takes effect
+/// when the server next starts.
+pub async fn rpc_set(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    crate::rpc::require_extension("manage secrets")?;
+    let server_id = string_param(&params, "server_id")?;
+    let name = string_param(&params, "name")?;
+    let value = string_param(&params, "value")?;
+    let decl = SecretDecl {
+        name: name.to_string(),
+        description: None,
+        required: false,
+        env: false,
+    };
+    decl.validate().map_err(RpcError::invalid_params)?;
+
+    let mut values = stored(server_id).await.map_err(RpcError::internal)?;
+    values.insert(name.to_string(), value.to_string());
+    store(server_id, values).await.map_err(RpcError::internal)?;
+    tracing::info!("Stored secret {} for server {}", name, server_id);
+    Ok(serde_json::json!({ "success": true }))
+}
+
+/// Remove a secret: `{ server_id, name }`.
+pub async fn rpc_remove(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    crate::rpc::require_extension("manage secrets")?;
+    let server_id = string_param(&params, "server_id")?;
+    let name = string_param(&params, "name")?;
+    let mut values = stored(server_id).await.map_err(RpcError::internal)?;
+    let removed = values.remove(name).is_some();
+    if removed {
+        store(server_id, values).await.map_err(RpcError::internal)?;
+    }
+    Ok(serde_json::json!({ "removed": removed }))
+}
+
+/// Names of the secrets stored for a server: `{ server_id }`. Values are
+/// not returned.
+pub async fn rpc_list(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    crate::rpc::require_extension("manage secrets")?;
+    let server_id = string_param(&params, "server_id")?;
+    let names: Vec<String> = stored(server_id)
+        .await
+        .map_err(RpcError::internal)?
+        .into_keys()
+        .collect();
+    Ok(serde_json::json!({ "names": names }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decl(name: &str, required: bool, env: bool) -> SecretDecl {
+        SecretDecl {
+            name: name.to_string(),
+            description: None,
+            required,
+            env,
+        }
+    }
+
+    #[test]
+    fn test_select() {
+        let stored: BTreeMap<String, String> = [("API_KEY", "k1"), ("REGION", "eu"), ("OTHER", "x")]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        let declared = [decl("API_KEY", true, true), decl("REGION", false, false), decl("DEBUG", false, true)];
+        let secrets = select(&declared, &stored).unwrap();
+        assert_eq!(secrets.get("API_KEY"), Some("k1"));
+        assert_eq!(secrets.get("REGION"), Some("eu"));
+        // Stored but not declared
+        assert_eq!(secrets.get("OTHER"), None);
+        assert_eq!(secrets.env().collect::<Vec<_>>(), vec![("API_KEY", "k1")]);
+        assert_eq!(format!("{:?}", secrets), r#"{"API_KEY", "REGION"}"#);
+
+        let err = select(&[decl("TOKEN", true, true)], &stored).unwrap_err();
+        assert!(err.contains("TOKEN"));
+    }
+
+    #[test]
+    fn test_names() {
+        assert!(decl("API_KEY_2", true, true).validate().is_ok());
+        assert!(decl("api_key", true, true).validate().is_err());
+        assert!(decl("2FA", true, true).validate().is_err());
+        assert!(decl("", true, true).validate().is_err());
+    }
+
+    #[test]
+    fn test_decl_defaults() {
+        let decl: SecretDecl = serde_json::from_value(serde_json::json!({ "name": "API_KEY" })).unwrap();
+        assert!(decl.required);
+        assert!(decl.env);
+    }
+}
@@ -0,0 +1,284 @@
+//! What WASM servers print and log, kept per server for `servers.logs`.
+//!
+//! Two streams are recorded: `stderr`, whatever a module writes there
+//! (panic messages, `eprintln!` debugging), and `log`, its MCP log messages
+//! (`notifications/message`). Components the bridge runs have their stderr
+//! captured line by line (see `wasm::component`); the extension, which runs
+//! preview 1 modules, forwards theirs with `servers.log`. Every line also
+//! goes to the bridge log as `[WASM:<id>]`, with the server and stream as
+//! fields.
+//!
+//! The most recent lines of each server are kept in memory, numbered in
+//! the order they arrived. `servers.logs` returns them, and with `since`
+//! and `wait_ms` waits for lines after a cursor, for tailing.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Notify;
+
+use crate::rpc::RpcError;
+
+/// Most lines kept per server.
+const MAX_LINES: usize = 1000;
+
+/// Longest line kept; longer ones are cut here.
+const MAX_LINE_BYTES: usize = 8 * 1024;
+
+/// Lines returned by default.
+const DEFAULT_LIMIT: usize = 200;
+
+/// Longest `servers.logs` waits for new lines.
+const MAX_WAIT: Duration = Duration::from_secs(30);
+
+/// Which output a line came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Stream {
+    Stderr,
+    Log,
+}
+
+/// One recorded line.
+#[derive(Debug, Clone, Serialize)]
+pub struct LogLine {
+    /// Position among all servers' lines, for `since`
+    pub seq: u64,
+    pub time: DateTime<Utc>,
+    pub server_id: String,
+    pub stream: Stream,
+    /// MCP log level, for `log` lines
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub level: Option<String>,
+    pub message: String,
+}
+
+#[derive(Default)]
+struct Buffers {
+    last_seq: u64,
+    servers: HashMap<String, VecDeque<LogLine>>,
+}
+
+/// Server log state.
+#[derive(Default)]
+pub struct ServerLogsState {
+    buffers: Mutex<Buffers>,
+    /// Woken when a line is recorded
+    recorded: Notify,
+}
+
+fn state() -> &'static ServerLogsState {
+    &crate::state::get().server_logs
+}
+
+impl ServerLogsState {
+    fn record(&self, server_id: &str, stream: Stream, level: Option<&str>, message: &str) {
+        let mut message = message.trim_end().to_string();
+        if message.len() > MAX_LINE_BYTES {
+            let mut end = MAX_LINE_BYTES;
+            while !message.is_char_boundary(end) {
+                end -= 1;
+            }
+            message.truncate(end);
+            message.push('…');
+        }
+        let mut buffers = self.buffers.lock().unwrap();
+        buffers.last_seq += 1;
+        let line = LogLine {
+            seq: buffers.last_seq,
+            time: Utc::now(),
+            server_id: server_id.to_string(),
+            stream,
+            level: level.map(str::to_string),
+            message,
+        };
+        let lines = buffers.servers.entry(server_id.to_string()).or_default();
+        if lines.len() == MAX_LINES {
+            lines.pop_front();
+        }
+        lines.push_back(line);
+        drop(buffers);
+        self.recorded.notify_waiters();
+    }
+
+    /// Lines of `server_id` (or all servers) after `since`, oldest first:
+    /// the first `limit` of them, or without `since` the last `limit`.
+    /// Also returns the cursor to pass as `since` next time.
+    fn read(&self, server_id: Option<&str>, since: Option<u64>, limit: usize) -> (Vec<LogLine>, u64) {
+        let buffers = self.buffers.lock().unwrap();
+        let after = since.unwrap_or(0);
+        let mut lines: Vec<LogLine> = buffers
+            .servers
+            .iter()
+            .filter(|(id, _)| server_id.is_none() || server_id == Some(id.as_str()))
+            .flat_map(|(_, lines)| lines.iter().filter(|line| line.seq > after).cloned())
+            .collect();
+        lines.sort_by_key(|line| line.seq);
+        if since.is_some() {
+            lines.truncate(limit);
+        } else {
+            lines.drain(..lines.len().saturating_sub(limit));
+        }
+        let cursor = match lines.last() {
+            Some(line) if since.is_some() => line.seq,
+            _ => buffers.last_seq.max(after),
+        };
+        (lines, cursor)
+    }
+
+    fn clear(&self, server_id: &str) {
+        self.buffers.lock().unwrap().servers.remove(server_id);
+    }
+}
+
+/// Record a line from `server_id`, and log it. Lines of a multi-line
+/// message are recorded separately.
+pub fn record(server_id: &str, stream: Stream, level: Option<&str>, message: &str) {
+    let name = match stream {
+        Stream::Stderr => "stderr",
+        Stream::Log => "log",
+    };
+    for line in message.lines().filter(|line| !line.trim().is_empty()) {
+        match (stream, level.unwrap_or("info")) {
+            (Stream::Stderr, _) | (Stream::Log, "info" | "notice") => {
+                tracing::info!(server_id, stream = name, "[WASM:{}] {}", server_id, line)
+            }
+            (Stream::Log, "debug") => tracing::debug!(server_id, stream = name, "[WASM:{}] {}", server_id, line),
+            (Stream::Log, "warning") => tracing::warn!(server_id, stream = name, "[WASM:{}] {}", server_id, line),
+            (Stream::Log, _) => tracing::error!(server_id, stream = name, "[WASM:{}] {}", server_id, line),
+        }
+        state().record(server_id, stream, level, line);
+    }
+}
+
+/// A log message's `data` as text: strings as they are, anything else as
+/// JSON.
+pub fn message_text(data: &serde_json::Value) -> String {
+    match data {
+        serde_json::Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+// ============================================================================
+// RPC Handlers
+// ============================================================================
+
+#[derive(Deserialize)]
+struct LogParams {
+    id: String,
+    #[serde(default = "default_stream")]
+    stream: Stream,
+    level: Option<String>,
+    #[serde(default)]
+    message: serde_json::Value,
+}
+
+fn default_stream() -> Stream {
+    Stream::Log
+}
+
+/// Record output from a server the extension runs: `{ id, stream?, level?,
+/// message }`, where `stream` is `log` (the default, with MCP's log levels)
+/// or `stderr`.
+pub async fn rpc_log(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    crate::rpc::require_extension("read or write server logs")?;
+    let params: LogParams =
+        serde_json::from_value(params).map_err(|e| RpcError::invalid_params(format!("Invalid params: {}", e)))?;
+    let level = match params.stream {
+        Stream::Log => Some(params.level.as_deref().unwrap_or("info")),
+        Stream::Stderr => None,
+    };
+    record(&params.id, params.stream, level, &message_text(&params.message));
+    Ok(serde_json::json!({ "ok": true }))
+}
+
+#[derive(Deserialize)]
+struct LogsParams {
+    id: Option<String>,
+    since: Option<u64>,
+    limit: Option<usize>,
+    #[serde(default)]
+    wait_ms: u64,
+}
+
+/// A server's recent lines, or all servers' without `id`:
+/// `{ id?, since?, limit?, wait_ms? }`. Returns `{ lines, cursor }`; pass
+/// `cursor` back as `since` for the lines after these. With `since` and
+/// `wait_ms`, waits up to that long (at most 30s) for a line if there are
+/// none yet.
+pub async fn rpc_logs(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    crate::rpc::require_extension("read or write server logs")?;
+    let params: LogsParams =
+        serde_json::from_value(params).map_err(|e| RpcError::invalid_params(format!("Invalid params: {}", e)))?;
+    let limit = params.limit.unwrap_or(DEFAULT_LIMIT).max(1);
+    let deadline = tokio::time::Instant::now() + Duration::from_millis(params.wait_ms).min(MAX_WAIT);
+    loop {
+        // Registered before reading, so a line recorded in between still wakes it
+        let recorded = state().recorded.notified();
+        let (lines, cursor) = state().read(params.id.as_deref(), params.since, limit);
+        let waiting = params.since.is_some() && lines.is_empty() && tokio::time::Instant::now() < deadline;
+        if !waiting || tokio::time::timeout_at(deadline, recorded).await.is_err() {
+            return Ok(serde_json::json!({ "lines": lines, "cursor": cursor }));
+        }
+    }
+}
+
+/// Forget a server's lines: `{ id }`.
+pub async fn rpc_clear_logs(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    crate::rpc::require_extension("read or write server logs")?;
+    let id = params
+        .get("id")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| RpcError::invalid_params("Missing 'id' parameter"))?;
+    state().clear(id);
+    Ok(serde_json::json!({ "ok": true }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_and_tail() {
+        let logs = ServerLogsState::default();
+        logs.record("a", Stream::Stderr, None, "panicked at src/main.rs:3:5");
+        logs.record("b", Stream::Log, Some("info"), "hello");
+        logs.record("a", Stream::Log, Some("debug"), "adding");
+
+        let (lines, cursor) = logs.read(Some("a"), None, 10);
+        assert_eq!(lines.iter().map(|l| l.seq).collect::<Vec<_>>(), vec![1, 3]);
+        assert_eq!(lines[0].stream, Stream::Stderr);
+        assert_eq!(cursor, 3);
+
+        // The last lines without `since`, the first after it with
+        let (lines, _) = logs.read(None, None, 2);
+        assert_eq!(lines.iter().map(|l| l.seq).collect::<Vec<_>>(), vec![2, 3]);
+        let (lines, cursor) = logs.read(None, Some(1), 1);
+        assert_eq!(lines[0].seq, 2);
+        assert_eq!(cursor, 2);
+
+        let (lines, cursor) = logs.read(Some("a"), Some(3), 10);
+        assert!(lines.is_empty());
+        assert_eq!(cursor, 3);
+
+        logs.clear("a");
+        assert!(logs.read(Some("a"), None, 10).0.is_empty());
+    }
+
+    #[test]
+    fn test_buffer_limits() {
+        let logs = ServerLogsState::default();
+        for i in 0..MAX_LINES + 5 {
+            logs.record("a", Stream::Stderr, None, &i.to_string());
+        }
+        logs.record("a", Stream::Stderr, None, &"x".repeat(MAX_LINE_BYTES + 10));
+        let (lines, _) = logs.read(Some("a"), None, usize::MAX);
+        assert_eq!(lines.len(), MAX_LINES);
+        assert_eq!(lines[0].message, "6");
+        assert!(lines.last().unwrap().message.ends_with('…'));
+    }
+}
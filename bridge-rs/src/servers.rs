@@ -0,0 +1,1174 @@
+//! WASM servers installed by the bridge from a URL or an OCI registry.
+//!
+//! `servers.install` (or `harbor-bridge install <source>`) takes either an
+//! HTTPS URL or an OCI reference:
+//!
+//! - `https://…/manifest.json`: a package manifest whose module is embedded
+//!   (`wasmBase64`) or linked (`wasmUrl`, relative to the manifest). A URL
+//!   serving a bare module is accepted too, and gets a minimal manifest.
+//! - `oci://registry/repository:tag` (or `@sha256:…`): an artifact with a
+//!   WASM layer and optionally a Harbor manifest layer
+//!   (`application/vnd.harbor.manifest.v1+json`). Registries that want a
+//!   token get an anonymous one.
+//!
+//! The module is checked against `sha256`, if given, and against the
+//! registry's digests, and then its signature is checked under the trust
+//! store's policy (see `signing`). Packages are stored in
+//! `~/.harbor/servers/<id>/` as `module.wasm` and `manifest.json`, and
+//! recorded in the server config, `~/.harbor/servers.json`, which the
+//! extension picks them up from.
+//!
+//! `servers.check_updates` looks for a newer version at each server's
+//! source: the manifest's `version` at a URL or a tag, or for an OCI
+//! reference tagged with a version, the registry's highest version tag.
+//! `servers.upgrade` installs it unless the server is pinned
+//! (`servers.pin`). Installing over a different module keeps the one it
+//! replaces in `previous/`, which `servers.rollback` swaps back. The config can also hold dev entries for
+//! projects being worked on (`harbor-bridge new --register`), whose module
+//! is read from the project's build output. The bridge watches those
+//! builds and emits `servers/changed` when one is rebuilt, which the
+//! extension reloads the server on (see `harbor-bridge dev`).
+
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{DateTime, Utc};
+use reqwest::header::{ACCEPT, WWW_AUTHENTICATE};
+use reqwest::StatusCode;
+use notify::RecursiveMode;
+use serde::{Deserialize, Serialize};
+
+use crate::changes::{self, Changes};
+use crate::events;
+use crate::rpc::RpcError;
+use crate::signing::hex_sha256;
+use crate::store::{self, JsonStore, Stored};
+
+const CONFIG_FILE_NAME: &str = "servers.json";
+const MODULE_FILE_NAME: &str = "module.wasm";
+const MANIFEST_FILE_NAME: &str = "manifest.json";
+/// Subdirectory of a server's directory holding the version it replaced.
+const PREVIOUS_DIR_NAME: &str = "previous";
+
+/// Media type of the Harbor manifest layer in OCI artifacts.
+pub const HARBOR_MANIFEST_TYPE: &str = "application/vnd.harbor.manifest.v1+json";
+
+const OCI_MANIFEST_TYPES: &str =
+    "application/vnd.oci.image.manifest.v1+json, application/vnd.docker.distribution.manifest.v2+json";
+
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(120);
+const MAX_REDIRECTS: usize = 5;
+/// Largest download accepted, module or manifest.
+const MAX_DOWNLOAD_BYTES: usize = 64 * 1024 * 1024;
+
+/// Manifest fields that carry the module, dropped from the stored manifest.
+const MODULE_FIELDS: &[&str] = &["wasmBase64", "moduleBytesBase64"];
+
+/// A server in the server config.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InstalledServer {
+    pub id: String,
+    pub name: String,
+    pub version: String,
+    /// The URL or OCI reference it was installed from
+    pub source: String,
+    /// Hex SHA-256 of the module; none for dev entries
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    /// Directory holding `module.wasm` and `manifest.json`, or for a dev
+    /// entry, the project
+    pub dir: PathBuf,
+    /// A project under development, whose module is read from where it is
+    /// built (the manifest's `wasm.file`) rather than stored
+    #[serde(default)]
+    pub dev: bool,
+    pub installed_at: DateTime<Utc>,
+    /// Kept at its version: `servers.upgrade` refuses it
+    #[serde(default)]
+    pub pinned: bool,
+    /// The version this one replaced, kept for `servers.rollback`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous: Option<PreviousVersion>,
+}
+
+/// A replaced version of a server, stored in its `previous/` directory.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PreviousVersion {
+    pub version: String,
+    pub source: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    pub installed_at: DateTime<Utc>,
+}
+
+impl PreviousVersion {
+    fn of(server: &InstalledServer) -> Self {
+        Self {
+            version: server.version.clone(),
+            source: server.source.clone(),
+            sha256: server.sha256.clone(),
+            installed_at: server.installed_at,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ServerConfig {
+    /// Servers by ID
+    pub servers: BTreeMap<String, InstalledServer>,
+}
+
+impl Stored for ServerConfig {
+    const FILE_NAME: &'static str = CONFIG_FILE_NAME;
+    // Installs from the command line show up in a running bridge
+    const SHARED: bool = true;
+}
+
+/// Installed servers state.
+#[derive(Default)]
+pub struct ServersState {
+    config: JsonStore<ServerConfig>,
+}
+
+fn state() -> &'static ServersState {
+    &crate::state::get().servers
+}
+
+fn harbor_dir() -> PathBuf {
+    let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
+    home.join(".harbor")
+}
+
+/// The server config as it is now.
+async fn load() -> ServerConfig {
+    state().config.read(ServerConfig::clone).await
+}
+
+/// Run `f` with the server config, saving it afterwards if `f` succeeds.
+async fn with_config<T>(f: impl FnOnce(&mut ServerConfig) -> Result<T, String>) -> Result<T, String> {
+    state().config.try_update(f).await
+}
+
+// ============================================================================
+// Sources
+// ============================================================================
+
+#[derive(Debug, Clone, PartialEq)]
+enum Source {
+    Url(url::Url),
+    Oci {
+        registry: String,
+        repository: String,
+        /// A tag or a `sha256:` digest
+        reference: String,
+    },
+}
+
+fn parse_source(source: &str) -> Result<Source, String> {
+    if let Some(rest) = source.strip_prefix("oci://") {
+        let (registry, path) = rest
+            .split_once('/')
+            .ok_or_else(|| format!("'{}' needs a registry and a repository", source))?;
+        let (repository, reference) = match path.split_once('@') {
+            Some((repository, digest)) => (repository, digest),
+            None => match path.rsplit_once(':') {
+                Some((repository, tag)) => (repository, tag),
+                None => (path, "latest"),
+            },
+        };
+        if registry.is_empty() || repository.is_empty() || reference.is_empty() {
+            return Err(format!("Invalid OCI reference '{}'", source));
+        }
+        return Ok(Source::Oci {
+            registry: registry.to_string(),
+            repository: repository.to_string(),
+            reference: reference.to_string(),
+        });
+    }
+    let url = url::Url::parse(source).map_err(|e| format!("Invalid source '{}': {}", source, e))?;
+    if url.scheme() != "https" {
+        return Err("Only https:// URLs and oci:// references can be installed".to_string());
+    }
+    Ok(Source::Url(url))
+}
+
+/// A downloaded package.
+struct Package {
+    manifest: serde_json::Value,
+    module: Vec<u8>,
+}
+
+/// A manifest for a module that came without one.
+fn minimal_manifest(id: &str, version: &str) -> serde_json::Value {
+    serde_json::json!({
+        "id": id,
+        "name": id,
+        "version": version,
+        "runtime": "wasm",
+        "permissions": [],
+    })
+}
+
+fn client() -> Result<reqwest::Client, String> {
+    reqwest::Client::builder()
+        .timeout(DOWNLOAD_TIMEOUT)
+        .redirect(reqwest::redirect::Policy::custom(|attempt| {
+            if attempt.previous().len() >= MAX_REDIRECTS {
+                attempt.error("Too many redirects")
+            } else if attempt.url().scheme() != "https" {
+                attempt.error("Redirected away from https")
+            } else {
+                attempt.follow()
+            }
+        }))
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))
+}
+
+/// Read a response's body, stopping as soon as it passes
+/// `MAX_DOWNLOAD_BYTES`, whatever its `Content-Length` says.
+async fn read_body(mut response: reqwest::Response, url: &str) -> Result<Vec<u8>, String> {
+    if !response.status().is_success() {
+        return Err(format!("Fetching {} failed: HTTP {}", url, response.status()));
+    }
+    let too_large = || format!("{} is larger than {} MB", url, MAX_DOWNLOAD_BYTES / (1024 * 1024));
+    if response.content_length().unwrap_or(0) > MAX_DOWNLOAD_BYTES as u64 {
+        return Err(too_large());
+    }
+    let mut body = Vec::new();
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|e| format!("Failed to download {}: {}", url, e))?
+    {
+        if body.len() + chunk.len() > MAX_DOWNLOAD_BYTES {
+            return Err(too_large());
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(body)
+}
+
+async fn get(client: &reqwest::Client, url: &str) -> Result<Vec<u8>, String> {
+    let response = client
+        .get(url)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to fetch {}: {}", url, e))?;
+    read_body(response, url).await
+}
+
+async fn fetch_url(client: &reqwest::Client, url: &url::Url) -> Result<Package, String> {
+    let body = get(client, url.as_str()).await?;
+    if body.starts_with(b"\0asm") {
+        let id = url
+            .path_segments()
+            .and_then(|mut segments| segments.next_back())
+            .and_then(|file| file.strip_suffix(".wasm"))
+            .filter(|stem| !stem.is_empty())
+            .ok_or_else(|| format!("Can't name the module at {}; install its manifest instead", url))?;
+        return Ok(Package {
+            manifest: minimal_manifest(id, "0.0.0"),
+            module: body,
+        });
+    }
+
+    let manifest: serde_json::Value =
+        serde_json::from_slice(&body).map_err(|e| format!("{} is neither a manifest nor a module: {}", url, e))?;
+    let embedded = MODULE_FIELDS.iter().find_map(|field| manifest.get(*field).and_then(|v| v.as_str()));
+    let linked = ["wasmUrl", "moduleUrl"]
+        .iter()
+        .find_map(|field| manifest.get(*field).and_then(|v| v.as_str()));
+    let module = match (embedded, linked) {
+        (Some(encoded), _) => STANDARD
+            .decode(encoded.trim())
+            .map_err(|e| format!("The manifest's module is not valid base64: {}", e))?,
+        (None, Some(link)) => {
+            let module_url = url.join(link).map_err(|e| format!("Invalid module URL '{}': {}", link, e))?;
+            if module_url.scheme() != "https" {
+                return Err(format!("The module URL {} is not https", module_url));
+            }
+            get(client, module_url.as_str()).await?
+        }
+        (None, None) => return Err("The manifest has no module (wasmBase64 or wasmUrl)".to_string()),
+    };
+    Ok(Package { manifest, module })
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct OciManifest {
+    #[serde(default)]
+    layers: Vec<OciDescriptor>,
+    #[serde(default)]
+    annotations: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct OciDescriptor {
+    media_type: String,
+    digest: String,
+}
+
+/// The realm and query of a `WWW-Authenticate: Bearer` challenge.
+fn parse_challenge(header: &str) -> Option<(String, Vec<(String, String)>)> {
+    let rest = header.strip_prefix("Bearer ")?;
+    let pattern = regex::Regex::new(r#"(\w+)="([^"]*)""#).expect("valid regex");
+    let mut realm = None;
+    let mut query = Vec::new();
+    for capture in pattern.captures_iter(rest) {
+        match &capture[1] {
+            "realm" => realm = Some(capture[2].to_string()),
+            key => query.push((key.to_string(), capture[2].to_string())),
+        }
+    }
+    Some((realm?, query))
+}
+
+/// An anonymous pull token for a registry's challenge.
+async fn registry_token(client: &reqwest::Client, challenge: &str) -> Result<String, String> {
+    let (realm, query) =
+        parse_challenge(challenge).ok_or_else(|| format!("Unsupported registry authentication: {}", challenge))?;
+    let response = client
+        .get(&realm)
+        .query(&query)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to get a registry token: {}", e))?;
+    let body: serde_json::Value = serde_json::from_slice(&read_body(response, &realm).await?)
+        .map_err(|e| format!("Invalid registry token response: {}", e))?;
+    body.get("token")
+        .or_else(|| body.get("access_token"))
+        .and_then(|v| v.as_str())
+        .map(String::from)
+        .ok_or_else(|| "The registry did not return a token".to_string())
+}
+
+/// GET from a registry, getting a token first if it asks for one.
+async fn registry_get(
+    client: &reqwest::Client,
+    url: &str,
+    accept: &str,
+    token: &mut Option<String>,
+) -> Result<Vec<u8>, String> {
+    loop {
+        let mut request = client.get(url).header(ACCEPT, accept);
+        if let Some(token) = token.as_deref() {
+            request = request.bearer_auth(token);
+        }
+        let response = request
+            .send()
+            .await
+            .map_err(|e| format!("Failed to fetch {}: {}", url, e))?;
+        if response.status() == StatusCode::UNAUTHORIZED && token.is_none() {
+            let challenge = response
+                .headers()
+                .get(WWW_AUTHENTICATE)
+                .and_then(|v| v.to_str().ok())
+                .ok_or_else(|| format!("{} needs authentication", url))?
+                .to_string();
+            *token = Some(registry_token(client, &challenge).await?);
+            continue;
+        }
+        return read_body(response, url).await;
+    }
+}
+
+/// Fail unless `bytes` have the `sha256:` digest `digest`.
+fn check_digest(bytes: &[u8], digest: &str, what: &str) -> Result<(), String> {
+    let expected = digest
+        .strip_prefix("sha256:")
+        .ok_or_else(|| format!("Unsupported digest '{}'", digest))?;
+    if !hex_sha256(bytes).eq_ignore_ascii_case(expected) {
+        return Err(format!("The {} does not match its digest {}", what, digest));
+    }
+    Ok(())
+}
+
+async fn fetch_oci(client: &reqwest::Client, registry: &str, repository: &str, reference: &str) -> Result<Package, String> {
+    let base = format!("https://{}/v2/{}", registry, repository);
+    let mut token = None;
+    let body = registry_get(client, &format!("{}/manifests/{}", base, reference), OCI_MANIFEST_TYPES, &mut token).await?;
+    if reference.starts_with("sha256:") {
+        check_digest(&body, reference, "registry manifest")?;
+    }
+    let oci: OciManifest =
+        serde_json::from_slice(&body).map_err(|e| format!("Invalid registry manifest: {}", e))?;
+
+    let blob = |layer: &OciDescriptor| format!("{}/blobs/{}", base, layer.digest);
+    let wasm_layer = oci
+        .layers
+        .iter()
+        .find(|layer| layer.media_type.ends_with("wasm"))
+        .ok_or_else(|| format!("{}/{} has no WASM layer", registry, repository))?;
+    let module = registry_get(client, &blob(wasm_layer), "*/*", &mut token).await?;
+    check_digest(&module, &wasm_layer.digest, "module")?;
+
+    let manifest = match oci.layers.iter().find(|layer| layer.media_type == HARBOR_MANIFEST_TYPE) {
+        Some(layer) => {
+            let body = registry_get(client, &blob(layer), "*/*", &mut token).await?;
+            check_digest(&body, &layer.digest, "manifest")?;
+            serde_json::from_slice(&body).map_err(|e| format!("Invalid Harbor manifest: {}", e))?
+        }
+        None => {
+            let id = repository.rsplit('/').next().unwrap_or(repository);
+            let version = oci
+                .annotations
+                .get("org.opencontainers.image.version")
+                .map(String::as_str)
+                .unwrap_or(if reference.starts_with("sha256:") { "0.0.0" } else { reference });
+            minimal_manifest(id, version)
+        }
+    };
+    Ok(Package { manifest, module })
+}
+
+// ============================================================================
+// Installing
+// ============================================================================
+
+/// Whether `id` is usable as a directory name.
+fn valid_id(id: &str) -> bool {
+    !id.is_empty()
+        && !id.starts_with('.')
+        && id.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+fn server_dir(id: &str) -> PathBuf {
+    harbor_dir().join("servers").join(id)
+}
+
+/// The outcome of an install.
+#[derive(Debug, Serialize)]
+pub struct Installed {
+    #[serde(flatten)]
+    pub server: InstalledServer,
+    /// An earlier install with the same ID was replaced
+    pub replaced: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signature_warning: Option<String>,
+}
+
+/// What an install may replace.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Replacing<'a> {
+    /// Nothing: the ID must be new
+    Nothing,
+    /// Whatever is installed with the package's ID
+    Any,
+    /// The server with this ID, which the package must have
+    Server(&'a str),
+}
+
+async fn fetch(client: &reqwest::Client, source: &Source) -> Result<Package, String> {
+    match source {
+        Source::Url(url) => fetch_url(client, url).await,
+        Source::Oci {
+            registry,
+            repository,
+            reference,
+        } => fetch_oci(client, registry, repository, reference).await,
+    }
+}
+
+/// Download, check and store a package, and add it to the server config.
+/// `sha256` is the module's expected hex SHA-256. A package whose ID is
+/// already installed is refused unless `replace` is set, so a manifest
+/// can't take over another server by naming it.
+pub async fn install(source: &str, sha256: Option<&str>, replace: bool) -> Result<Installed, RpcError> {
+    let parsed = parse_source(source).map_err(RpcError::invalid_params)?;
+    let client = client().map_err(RpcError::internal)?;
+    let package = fetch(&client, &parsed).await.map_err(|e| RpcError::new(-32000, e))?;
+    let replacing = if replace { Replacing::Any } else { Replacing::Nothing };
+    store_package(source, package, sha256, replacing).await
+}
+
+/// Check and store a downloaded package, and add it to the server config.
+async fn store_package(
+    source: &str,
+    package: Package,
+    sha256: Option<&str>,
+    replacing: Replacing<'_>,
+) -> Result<Installed, RpcError> {
+    let Package { mut manifest, module } = package;
+    let digest = hex_sha256(&module);
+    if let Some(expected) = sha256 {
+        if !digest.eq_ignore_ascii_case(expected.trim()) {
+            return Err(RpcError::new(
+                -32000,
+                format!("Checksum mismatch: expected {}, got {}", expected.trim(), digest),
+            ));
+        }
+    }
+    let (_, signature_warning) = crate::signing::check(&module, &manifest).await?;
+
+    let id = manifest
+        .get("id")
+        .and_then(|v| v.as_str())
+        .filter(|id| valid_id(id))
+        .ok_or_else(|| RpcError::new(-32000, "The manifest has no usable 'id'"))?
+        .to_string();
+    if let Replacing::Server(installed) = replacing {
+        if id != installed {
+            return Err(RpcError::new(
+                -32000,
+                format!("The package at {} is '{}', not '{}'", source, id, installed),
+            ));
+        }
+    }
+    let text = |field: &str, default: &str| {
+        manifest.get(field).and_then(|v| v.as_str()).unwrap_or(default).to_string()
+    };
+    let server = InstalledServer {
+        name: text("name", &id),
+        version: text("version", "0.0.0"),
+        source: source.to_string(),
+        sha256: Some(digest),
+        dir: server_dir(&id),
+        dev: false,
+        installed_at: Utc::now(),
+        pinned: false,
+        previous: None,
+        id,
+    };
+
+    // The module is stored beside the manifest rather than in it
+    if let Some(fields) = manifest.as_object_mut() {
+        for field in MODULE_FIELDS {
+            fields.remove(*field);
+        }
+    }
+    let manifest_json = serde_json::to_string_pretty(&manifest).map_err(|e| RpcError::internal(e.to_string()))?;
+    let (server, replaced) = with_config(|config| {
+        let mut server = server;
+        if let Some(existing) = config.servers.get(&server.id).filter(|_| replacing == Replacing::Nothing) {
+            return Err(format!(
+                "A server '{}' is already installed from {}; pass replace to install over it",
+                server.id, existing.source
+            ));
+        }
+        // A pin stays; a different module is kept to roll back to
+        if let Some(existing) = config.servers.get(&server.id).filter(|existing| !existing.dev) {
+            server.pinned = existing.pinned;
+            server.previous = if existing.sha256 == server.sha256 {
+                existing.previous.clone()
+            } else {
+                keep_previous(&server.dir)?;
+                Some(PreviousVersion::of(existing))
+            };
+        }
+        store::write_atomic(&server.dir.join(MODULE_FILE_NAME), &module)
+            .map_err(|e| format!("Failed to write module: {}", e))?;
+        store::write_atomic(&server.dir.join(MANIFEST_FILE_NAME), manifest_json.as_bytes())
+            .map_err(|e| format!("Failed to write manifest: {}", e))?;
+        let replaced = config.servers.insert(server.id.clone(), server.clone()).is_some();
+        Ok((server, replaced))
+    })
+    .await
+    .map_err(|e| RpcError::new(-32000, e))?;
+
+    tracing::info!("Installed server '{}' {} from {}", server.id, server.version, source);
+    Ok(Installed {
+        server,
+        replaced,
+        signature_warning,
+    })
+}
+
+/// Add a dev entry for the project in `dir`, whose `manifest.json` names
+/// the server, replacing any entry with the same ID.
+pub async fn register_dev(dir: &std::path::Path) -> Result<InstalledServer, String> {
+    let dir = dir
+        .canonicalize()
+        .map_err(|e| format!("Failed to resolve {:?}: {}", dir, e))?;
+    let text = std::fs::read_to_string(dir.join(MANIFEST_FILE_NAME))
+        .map_err(|e| format!("Failed to read {}: {}", MANIFEST_FILE_NAME, e))?;
+    let manifest: serde_json::Value =
+        serde_json::from_str(&text).map_err(|e| format!("Invalid {}: {}", MANIFEST_FILE_NAME, e))?;
+    let field = |name: &str| manifest.get(name).and_then(|v| v.as_str());
+    let id = field("id")
+        .filter(|id| valid_id(id))
+        .ok_or("The manifest has no usable 'id'")?
+        .to_string();
+    let server = InstalledServer {
+        name: field("name").unwrap_or(&id).to_string(),
+        version: field("version").unwrap_or("0.0.0").to_string(),
+        source: dir.display().to_string(),
+        sha256: None,
+        dir,
+        dev: true,
+        installed_at: Utc::now(),
+        pinned: false,
+        previous: None,
+        id,
+    };
+    with_config(|config| {
+        config.servers.insert(server.id.clone(), server.clone());
+        Ok(())
+    })
+    .await?;
+    tracing::info!("Registered dev server '{}' at {:?}", server.id, server.dir);
+    Ok(server)
+}
+
+/// Where a server's module is: stored, or for a dev entry, built.
+fn module_path(server: &InstalledServer, manifest: &serde_json::Value) -> Result<PathBuf, String> {
+    if !server.dev {
+        return Ok(server.dir.join(MODULE_FILE_NAME));
+    }
+    let file = manifest
+        .pointer("/wasm/file")
+        .and_then(|v| v.as_str())
+        .ok_or("The manifest has no 'wasm.file' to find the build in")?;
+    Ok(server.dir.join(file))
+}
+
+// ============================================================================
+// Upgrades
+// ============================================================================
+
+/// Move the stored module and manifest in `dir` to `previous/`, replacing
+/// what was there.
+fn keep_previous(dir: &Path) -> Result<(), String> {
+    let previous = dir.join(PREVIOUS_DIR_NAME);
+    for file in [MODULE_FILE_NAME, MANIFEST_FILE_NAME] {
+        if !dir.join(file).exists() {
+            continue;
+        }
+        std::fs::create_dir_all(&previous).map_err(|e| format!("Failed to create {:?}: {}", previous, e))?;
+        std::fs::rename(dir.join(file), previous.join(file))
+            .map_err(|e| format!("Failed to keep the previous {}: {}", file, e))?;
+    }
+    Ok(())
+}
+
+/// Swap the stored module and manifest in `dir` with those in `previous/`.
+/// Both versions are read whole first and each file is replaced
+/// atomically, so if a step fails, everything written is put back.
+fn swap_previous(dir: &Path) -> Result<(), String> {
+    const FILES: [&str; 2] = [MODULE_FILE_NAME, MANIFEST_FILE_NAME];
+    let previous = dir.join(PREVIOUS_DIR_NAME);
+    let read = |dir: &Path| {
+        FILES
+            .iter()
+            .map(|file| {
+                let path = dir.join(file);
+                std::fs::read(&path).map_err(|e| format!("Failed to read {:?}: {}", path, e))
+            })
+            .collect::<Result<Vec<_>, _>>()
+    };
+    let write = |dir: &Path, contents: &[Vec<u8>]| {
+        FILES
+            .iter()
+            .zip(contents)
+            .try_for_each(|(file, bytes)| store::write_atomic(&dir.join(file), bytes))
+    };
+    let (current, kept) = (read(dir)?, read(&previous)?);
+    if let Err(e) = write(dir, &kept).and_then(|_| write(&previous, &current)) {
+        let _ = write(dir, &current).and_then(|_| write(&previous, &kept));
+        return Err(format!("Failed to restore the previous version: {}", e));
+    }
+    Ok(())
+}
+
+/// Split a version like `v1.10.0-beta.2+build` into its numbered parts and
+/// pre-release, or `None` if it doesn't start with a number.
+fn parse_version(version: &str) -> Option<(Vec<u64>, Option<&str>)> {
+    let version = version.trim().trim_start_matches('v');
+    let version = version.split_once('+').map_or(version, |(version, _)| version);
+    let (release, pre) = match version.split_once('-') {
+        Some((release, pre)) => (release, Some(pre)),
+        None => (version, None),
+    };
+    let parts = release.split('.').map(|part| part.parse().ok()).collect::<Option<Vec<u64>>>()?;
+    Some((parts, pre))
+}
+
+/// Order versions part by part, numerically, with a pre-release before its
+/// release. Versions that don't parse order before those that do.
+fn compare_versions(a: &str, b: &str) -> Ordering {
+    let (a, b) = match (parse_version(a), parse_version(b)) {
+        (Some(a), Some(b)) => (a, b),
+        (a, b) => return a.is_some().cmp(&b.is_some()),
+    };
+    let len = a.0.len().max(b.0.len());
+    let part = |parts: &[u64], i: usize| parts.get(i).copied().unwrap_or(0);
+    (0..len)
+        .map(|i| part(&a.0, i).cmp(&part(&b.0, i)))
+        .find(|ordering| ordering.is_ne())
+        .unwrap_or_else(|| match (a.1, b.1) {
+            (None, None) => Ordering::Equal,
+            (None, Some(_)) => Ordering::Greater,
+            (Some(_), None) => Ordering::Less,
+            (Some(a), Some(b)) => a.cmp(b),
+        })
+}
+
+fn package_version(manifest: &serde_json::Value) -> String {
+    manifest.get("version").and_then(|v| v.as_str()).unwrap_or("0.0.0").to_string()
+}
+
+#[derive(Debug, Deserialize)]
+struct TagList {
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+/// A newer version of an installed server, and where to install it from.
+#[derive(Debug, Serialize)]
+pub struct Update {
+    pub id: String,
+    pub installed: String,
+    pub available: String,
+    pub source: String,
+    pub pinned: bool,
+}
+
+/// Look for a newer version of `server` at its source. An OCI reference
+/// tagged with a version looks through the registry's tags; one pinned to a
+/// digest has no updates. The package is returned too when it had to be
+/// downloaded to find its version.
+async fn find_update(
+    client: &reqwest::Client,
+    server: &InstalledServer,
+) -> Result<Option<(Update, Option<Package>)>, String> {
+    let mut package = None;
+    let (available, source) = match parse_source(&server.source)? {
+        Source::Oci { reference, .. } if reference.starts_with("sha256:") => return Ok(None),
+        Source::Oci {
+            registry,
+            repository,
+            reference,
+        } if parse_version(&reference).is_some() => {
+            let url = format!("https://{}/v2/{}/tags/list", registry, repository);
+            let body = registry_get(client, &url, "application/json", &mut None).await?;
+            let list: TagList = serde_json::from_slice(&body).map_err(|e| format!("Invalid tag list: {}", e))?;
+            let Some(tag) = list
+                .tags
+                .into_iter()
+                .filter(|tag| parse_version(tag).is_some())
+                .max_by(|a, b| compare_versions(a, b))
+            else {
+                return Ok(None);
+            };
+            let source = format!("oci://{}/{}:{}", registry, repository, tag);
+            (tag, source)
+        }
+        Source::Oci {
+            registry,
+            repository,
+            reference,
+        } => {
+            let fetched = fetch_oci(client, &registry, &repository, &reference).await?;
+            (package_version(&package.insert(fetched).manifest), server.source.clone())
+        }
+        Source::Url(url) => {
+            let fetched = fetch_url(client, &url).await?;
+            (package_version(&package.insert(fetched).manifest), server.source.clone())
+        }
+    };
+    if compare_versions(&available, &server.version) != Ordering::Greater {
+        return Ok(None);
+    }
+    let update = Update {
+        id: server.id.clone(),
+        installed: server.version.clone(),
+        available,
+        source,
+        pinned: server.pinned,
+    };
+    Ok(Some((update, package)))
+}
+
+async fn installed_server(id: &str) -> Result<InstalledServer, RpcError> {
+    load()
+        .await
+        .servers
+        .remove(id)
+        .ok_or_else(|| RpcError::new(-32000, format!("Server '{}' is not installed", id)))
+}
+
+/// Install the newest version of a server from its source, if there is a
+/// newer one. The package found must have the server's ID.
+pub async fn upgrade(id: &str) -> Result<Option<Installed>, RpcError> {
+    let server = installed_server(id).await?;
+    if server.dev {
+        return Err(RpcError::new(-32000, format!("Server '{}' is a dev server, rebuilt rather than upgraded", id)));
+    }
+    if server.pinned {
+        return Err(RpcError::new(
+            -32000,
+            format!("Server '{}' is pinned to {}; unpin it to upgrade", id, server.version),
+        ));
+    }
+    let client = client().map_err(RpcError::internal)?;
+    let Some((update, package)) = find_update(&client, &server).await.map_err(|e| RpcError::new(-32000, e))? else {
+        return Ok(None);
+    };
+    let package = match package {
+        Some(package) => package,
+        None => {
+            let source = parse_source(&update.source).map_err(RpcError::invalid_params)?;
+            fetch(&client, &source).await.map_err(|e| RpcError::new(-32000, e))?
+        }
+    };
+    let installed = store_package(&update.source, package, None, Replacing::Server(id)).await?;
+    tracing::info!("Upgraded server '{}' from {} to {}", id, update.installed, installed.server.version);
+    Ok(Some(installed))
+}
+
+/// Go back to the version a server replaced. Rolling back again returns to
+/// the newer one.
+pub async fn rollback(id: &str) -> Result<InstalledServer, RpcError> {
+    let server = with_config(|config| {
+        let server = config
+            .servers
+            .get_mut(id)
+            .ok_or_else(|| format!("Server '{}' is not installed", id))?;
+        let previous = server
+            .previous
+            .take()
+            .ok_or_else(|| format!("Server '{}' has no previous version", id))?;
+        swap_previous(&server.dir)?;
+        let current = PreviousVersion::of(server);
+        server.version = previous.version;
+        server.source = previous.source;
+        server.sha256 = previous.sha256;
+        server.installed_at = previous.installed_at;
+        server.previous = Some(current);
+        Ok(server.clone())
+    })
+    .await
+    .map_err(|e| RpcError::new(-32000, e))?;
+    tracing::info!("Rolled server '{}' back to {}", id, server.version);
+    Ok(server)
+}
+
+// ============================================================================
+// Dev builds
+// ============================================================================
+
+/// How often build directories that don't exist yet are looked for.
+const WATCH_RETRY: Duration = Duration::from_secs(2);
+
+/// The module path of each dev entry, with its server ID.
+fn dev_builds(config: &ServerConfig) -> HashMap<PathBuf, String> {
+    config
+        .servers
+        .values()
+        .filter(|server| server.dev)
+        .filter_map(|server| {
+            let text = std::fs::read_to_string(server.dir.join(MANIFEST_FILE_NAME)).ok()?;
+            let manifest: serde_json::Value = serde_json::from_str(&text).ok()?;
+            Some((module_path(server, &manifest).ok()?, server.id.clone()))
+        })
+        .collect()
+}
+
+/// The module digest of each installed (not dev) server.
+fn installed_modules(config: &ServerConfig) -> HashMap<String, Option<String>> {
+    config
+        .servers
+        .values()
+        .filter(|server| !server.dev)
+        .map(|server| (server.id.clone(), server.sha256.clone()))
+        .collect()
+}
+
+/// Watch the builds of dev entries and emit `servers/changed` when one is
+/// rebuilt, for the extension to load the new build. The server config is
+/// watched too, so entries added while the bridge runs are picked up, and
+/// installed servers whose module was replaced (reinstalled, upgraded or
+/// rolled back) are announced the same way. Must be called from within
+/// the tokio runtime.
+pub fn start() {
+    tokio::spawn(async {
+        if let Err(e) = watch_dev_builds().await {
+            tracing::warn!("Not watching dev server builds: {}", e);
+        }
+    });
+}
+
+async fn watch_dev_builds() -> Result<(), String> {
+    let config_path = harbor_dir().join(CONFIG_FILE_NAME);
+    std::fs::create_dir_all(harbor_dir()).map_err(|e| format!("Failed to create directory: {}", e))?;
+
+    let mut changes = Changes::new(|kind, _| changes::is_write(kind))?;
+    changes.watch(&harbor_dir(), RecursiveMode::NonRecursive)?;
+
+    // Build directories are watched rather than modules, which are replaced
+    // rather than written to. A directory that doesn't exist yet is tried
+    // again until the first build makes it, which is then announced.
+    let config = load().await;
+    let mut builds = dev_builds(&config);
+    let mut modules = installed_modules(&config);
+    let mut watched = HashSet::new();
+    let mut starting = true;
+    loop {
+        for (module, id) in &builds {
+            let Some(dir) = module.parent() else { continue };
+            if watched.contains(dir) || changes.watch(dir, RecursiveMode::NonRecursive).is_err() {
+                continue;
+            }
+            watched.insert(dir.to_path_buf());
+            if !starting && module.exists() {
+                events::emit("servers/changed", serde_json::json!({ "server_id": id }));
+            }
+        }
+        starting = false;
+
+        let Some(changed) = changes.next_within(WATCH_RETRY).await else {
+            return Ok(());
+        };
+        if changed.contains(&config_path) {
+            let config = load().await;
+            builds = dev_builds(&config);
+            let previous = std::mem::replace(&mut modules, installed_modules(&config));
+            for (id, sha256) in &modules {
+                if previous.get(id).is_some_and(|before| before != sha256) {
+                    tracing::info!("[WASM:{}] Module replaced; reloading", id);
+                    events::emit("servers/changed", serde_json::json!({ "server_id": id }));
+                }
+            }
+        }
+        for id in changed.iter().filter_map(|path| builds.get(path)) {
+            tracing::info!("[WASM:{}] Rebuilt; reloading", id);
+            events::emit("servers/changed", serde_json::json!({ "server_id": id }));
+        }
+    }
+}
+
+// ============================================================================
+// RPC Handlers
+// ============================================================================
+
+fn id_param(params: &serde_json::Value) -> Result<&str, RpcError> {
+    params
+        .get("id")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| RpcError::invalid_params("Missing 'id' parameter"))
+}
+
+/// Install a server: `{ source, sha256?, replace? }`, where `source` is an
+/// HTTPS URL or an `oci://` reference. `replace` allows installing over a
+/// server with the same ID.
+pub async fn rpc_install(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    crate::rpc::require_extension("install, change or remove servers")?;
+    let source = params
+        .get("source")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| RpcError::invalid_params("Missing 'source' parameter"))?;
+    let sha256 = params.get("sha256").and_then(|v| v.as_str());
+    let replace = params.get("replace").and_then(|v| v.as_bool()).unwrap_or(false);
+    let installed = install(source, sha256, replace).await?;
+    serde_json::to_value(installed).map_err(|e| RpcError::internal(e.to_string()))
+}
+
+/// List installed servers.
+pub async fn rpc_list(_params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    let config = load().await;
+    let servers: Vec<&InstalledServer> = config.servers.values().collect();
+    Ok(serde_json::json!({ "servers": servers }))
+}
+
+/// An installed server's manifest, with the module embedded as
+/// `wasmBase64`: `{ id }`.
+pub async fn rpc_read(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    let id = id_param(&params)?;
+    let server = load()
+        .await
+        .servers
+        .remove(id)
+        .ok_or_else(|| RpcError::new(-32000, format!("Server '{}' is not installed", id)))?;
+    let read = |path: PathBuf| {
+        std::fs::read(&path).map_err(|e| RpcError::new(-32000, format!("Failed to read {:?}: {}", path, e)))
+    };
+    let mut manifest: serde_json::Value = serde_json::from_slice(&read(server.dir.join(MANIFEST_FILE_NAME))?)
+        .map_err(|e| RpcError::internal(e.to_string()))?;
+    let module = read(module_path(&server, &manifest).map_err(|e| RpcError::new(-32000, e))?)?;
+    manifest["wasmBase64"] = serde_json::Value::from(STANDARD.encode(module));
+    if manifest.get("permissions").is_none() {
+        manifest["permissions"] = serde_json::json!([]);
+    }
+    Ok(manifest)
+}
+
+/// Uninstall a server: `{ id }`.
+pub async fn rpc_remove(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    crate::rpc::require_extension("install, change or remove servers")?;
+    let id = id_param(&params)?;
+    let removed = with_config(|config| Ok(config.servers.remove(id)))
+        .await
+        .map_err(RpcError::internal)?;
+    // A dev entry's directory is the project, which stays
+    if let Some(server) = removed.as_ref().filter(|server| !server.dev) {
+        if let Err(e) = std::fs::remove_dir_all(&server.dir) {
+            tracing::warn!("Failed to remove {:?}: {}", server.dir, e);
+        }
+    }
+    if removed.is_some() {
+        tracing::info!("Removed server '{}'", id);
+    }
+    Ok(serde_json::json!({ "removed": removed.is_some() }))
+}
+
+/// Newer versions of installed servers: `{ id? }`, all servers if no `id`.
+/// Returns `{ updates, errors }`, where `errors` lists the servers whose
+/// source couldn't be checked.
+pub async fn rpc_check_updates(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    let servers: Vec<InstalledServer> = match params.get("id").and_then(|v| v.as_str()) {
+        Some(id) => vec![installed_server(id).await?],
+        None => load().await.servers.into_values().collect(),
+    };
+    let client = client().map_err(RpcError::internal)?;
+    let mut updates = Vec::new();
+    let mut errors = Vec::new();
+    for server in servers.iter().filter(|server| !server.dev) {
+        match find_update(&client, server).await {
+            Ok(update) => updates.extend(update.map(|(update, _)| update)),
+            Err(e) => errors.push(serde_json::json!({ "id": server.id, "error": e })),
+        }
+    }
+    Ok(serde_json::json!({ "updates": updates, "errors": errors }))
+}
+
+/// Upgrade a server to the newest version at its source: `{ id }`. Returns
+/// `{ upgraded, server }`.
+pub async fn rpc_upgrade(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    crate::rpc::require_extension("install, change or remove servers")?;
+    let id = id_param(&params)?;
+    let (upgraded, server) = match upgrade(id).await? {
+        Some(installed) => (true, serde_json::to_value(installed)),
+        None => (false, serde_json::to_value(installed_server(id).await?)),
+    };
+    let server = server.map_err(|e| RpcError::internal(e.to_string()))?;
+    Ok(serde_json::json!({ "upgraded": upgraded, "server": server }))
+}
+
+/// Go back to the version a server's last install replaced: `{ id }`.
+pub async fn rpc_rollback(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    crate::rpc::require_extension("install, change or remove servers")?;
+    let server = rollback(id_param(&params)?).await?;
+    Ok(serde_json::json!({ "server": server }))
+}
+
+/// Pin a server at its version, or unpin it: `{ id, pinned? }`, pinning
+/// unless `pinned` is false.
+pub async fn rpc_pin(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    crate::rpc::require_extension("install, change or remove servers")?;
+    let id = id_param(&params)?;
+    let pinned = params.get("pinned").and_then(|v| v.as_bool()).unwrap_or(true);
+    let server = with_config(|config| {
+        let server = config
+            .servers
+            .get_mut(id)
+            .ok_or_else(|| format!("Server '{}' is not installed", id))?;
+        server.pinned = pinned;
+        Ok(server.clone())
+    })
+    .await
+    .map_err(|e| RpcError::new(-32000, e))?;
+    Ok(serde_json::json!({ "server": server }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_source() {
+        assert_eq!(
+            parse_source("oci://ghcr.io/acme/tools/weather:1.2.0").unwrap(),
+            Source::Oci {
+                registry: "ghcr.io".to_string(),
+                repository: "acme/tools/weather".to_string(),
+                reference: "1.2.0".to_string(),
+            }
+        );
+        assert_eq!(
+            parse_source("oci://localhost:5000/weather@sha256:abc").unwrap(),
+            Source::Oci {
+                registry: "localhost:5000".to_string(),
+                repository: "weather".to_string(),
+                reference: "sha256:abc".to_string(),
+            }
+        );
+        match parse_source("oci://ghcr.io/weather").unwrap() {
+            Source::Oci { reference, .. } => assert_eq!(reference, "latest"),
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(matches!(parse_source("https://example.com/manifest.json"), Ok(Source::Url(_))));
+        assert!(parse_source("http://example.com/manifest.json").is_err());
+        assert!(parse_source("oci://ghcr.io").is_err());
+    }
+
+    #[test]
+    fn test_parse_challenge() {
+        let (realm, query) = parse_challenge(
+            r#"Bearer realm="https://ghcr.io/token",service="ghcr.io",scope="repository:acme/weather:pull,push""#,
+        )
+        .unwrap();
+        assert_eq!(realm, "https://ghcr.io/token");
+        assert_eq!(
+            query,
+            vec![
+                ("service".to_string(), "ghcr.io".to_string()),
+                ("scope".to_string(), "repository:acme/weather:pull,push".to_string()),
+            ]
+        );
+        assert!(parse_challenge(r#"Basic realm="x""#).is_none());
+    }
+
+    #[test]
+    fn test_check_digest() {
+        let digest = format!("sha256:{}", hex_sha256(b"module"));
+        assert!(check_digest(b"module", &digest, "module").is_ok());
+        assert!(check_digest(b"other", &digest, "module").is_err());
+        assert!(check_digest(b"module", "md5:abc", "module").is_err());
+    }
+
+    #[test]
+    fn test_compare_versions() {
+        assert_eq!(compare_versions("1.10.0", "1.9.2"), Ordering::Greater);
+        assert_eq!(compare_versions("v2.0", "2.0.0"), Ordering::Equal);
+        assert_eq!(compare_versions("1.0.0-beta.1", "1.0.0"), Ordering::Less);
+        assert_eq!(compare_versions("1.0.0+build.5", "1.0.0"), Ordering::Equal);
+        assert_eq!(compare_versions("latest", "0.0.1"), Ordering::Less);
+        assert!(parse_version("sha256:abc").is_none());
+    }
+
+    #[test]
+    fn test_keep_and_swap_previous() {
+        let dir = std::env::temp_dir().join(format!("harbor-servers-previous-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let write = |file: &str, text: &str| std::fs::write(dir.join(file), text).unwrap();
+        let read = |path: PathBuf| std::fs::read_to_string(path).unwrap();
+        write(MODULE_FILE_NAME, "v1");
+        write(MANIFEST_FILE_NAME, "{1}");
+        keep_previous(&dir).unwrap();
+        write(MODULE_FILE_NAME, "v2");
+        write(MANIFEST_FILE_NAME, "{2}");
+
+        swap_previous(&dir).unwrap();
+        assert_eq!(read(dir.join(MODULE_FILE_NAME)), "v1");
+        assert_eq!(read(dir.join(MANIFEST_FILE_NAME)), "{1}");
+        assert_eq!(read(dir.join(PREVIOUS_DIR_NAME).join(MODULE_FILE_NAME)), "v2");
+        swap_previous(&dir).unwrap();
+        assert_eq!(read(dir.join(MODULE_FILE_NAME)), "v2");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_valid_id() {
+        assert!(valid_id("weather-2.0_beta"));
+        assert!(!valid_id(""));
+        assert!(!valid_id(".."));
+        assert!(!valid_id("a/b"));
+    }
+}
@@ -0,0 +1,469 @@
+//! Signatures on WASM server packages.
+//!
+//! A package's manifest can carry an ed25519 `signature` over the module
+//! and the manifest itself. The signed message is
+//!
+//! ```text
+//! harbor-wasm-signature-v1
+//! <hex SHA-256 of the module bytes>
+//! <hex SHA-256 of the canonical manifest>
+//! ```
+//!
+//! where the canonical manifest is the manifest without `signature` and the
+//! fields Harbor fills in or rewrites at install (embedded code, code URLs,
+//! `tools`, `autostart`), serialized as JSON with sorted keys and no
+//! whitespace. Everything a package asks for (capabilities, OAuth scopes,
+//! secrets and the values of `secrets`) is signed, and the bridge grants a
+//! component started from a package no more than its manifest declares.
+//!
+//! Signatures are checked against a local trust store of public keys,
+//! `~/.harbor/trusted_keys.json`. What happens to packages that aren't
+//! signed by a trusted key depends on the store's policy: `off` loads
+//! them, `warn` (the default) loads them with a warning and `require`
+//! refuses them. A signature that doesn't match is refused unless the
+//! policy is `off`, since the module or manifest was changed after signing.
+//! Local builds, started from a file during development, can't be signed,
+//! so `require` refuses them too.
+
+use std::collections::BTreeMap;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::rpc::RpcError;
+use crate::store::{JsonStore, Stored};
+
+/// First line of the signed message; changes if the scheme does.
+const SCHEME: &str = "harbor-wasm-signature-v1";
+
+/// Manifest fields left out of the signed manifest.
+const UNSIGNED_FIELDS: &[&str] = &[
+    "signature",
+    "wasmBase64",
+    "moduleBytesBase64",
+    "scriptBase64",
+    "moduleUrl",
+    "wasmUrl",
+    "scriptUrl",
+    "tools",
+    "autostart",
+];
+
+/// Error code for packages refused by the signature policy.
+pub const SIGNATURE_REFUSED: i64 = -32060;
+
+/// What to do with packages not signed by a trusted key.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Policy {
+    Off,
+    #[default]
+    Warn,
+    Require,
+}
+
+/// A public key the user trusts.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrustedKey {
+    /// Base64 ed25519 public key
+    pub public_key: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TrustStore {
+    pub policy: Policy,
+    /// Keys by key ID
+    pub keys: BTreeMap<String, TrustedKey>,
+}
+
+impl Stored for TrustStore {
+    const FILE_NAME: &'static str = "trusted_keys.json";
+}
+
+/// The `signature` block of a manifest.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SignatureBlock {
+    algorithm: String,
+    value: String,
+    #[serde(default, alias = "public_key")]
+    public_key: Option<String>,
+    #[serde(default, alias = "key_id")]
+    key_id: Option<String>,
+}
+
+/// The outcome of checking a package's signature.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "status", rename_all = "lowercase")]
+pub enum Verdict {
+    /// Signed by a key in the trust store
+    Trusted { key_id: String },
+    /// Validly signed, by a key that isn't trusted
+    Untrusted { key_id: String },
+    Unsigned,
+    /// The signature doesn't match, or can't be checked
+    Invalid { reason: String },
+}
+
+/// Signing subsystem state.
+#[derive(Default)]
+pub struct SigningState {
+    /// Trust store, loaded on first use
+    store: JsonStore<TrustStore>,
+}
+
+fn state() -> &'static SigningState {
+    &crate::state::get().signing
+}
+
+/// A key's ID when none is given: the start of its SHA-256.
+pub fn fingerprint(public_key: &[u8]) -> String {
+    Sha256::digest(public_key)[..8].iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub(crate) fn hex_sha256(bytes: &[u8]) -> String {
+    Sha256::digest(bytes).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// JSON with object keys sorted and no whitespace.
+fn canonical_json(value: &serde_json::Value, out: &mut String) {
+    match value {
+        serde_json::Value::Object(map) => {
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(b.0));
+            out.push('{');
+            for (i, (key, value)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&serde_json::Value::from(key.as_str()).to_string());
+                out.push(':');
+                canonical_json(value, out);
+            }
+            out.push('}');
+        }
+        serde_json::Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                canonical_json(item, out);
+            }
+            out.push(']');
+        }
+        other => out.push_str(&other.to_string()),
+    }
+}
+
+/// The message a package's signature is over.
+pub fn signed_message(module: &[u8], manifest: &serde_json::Value) -> String {
+    let mut signed = manifest.clone();
+    if let Some(map) = signed.as_object_mut() {
+        for field in UNSIGNED_FIELDS {
+            map.remove(*field);
+        }
+    }
+    let mut canonical = String::new();
+    canonical_json(&signed, &mut canonical);
+    format!("{}\n{}\n{}\n", SCHEME, hex_sha256(module), hex_sha256(canonical.as_bytes()))
+}
+
+fn decode_key(public_key: &str) -> Result<VerifyingKey, String> {
+    let bytes = STANDARD
+        .decode(public_key.trim())
+        .map_err(|e| format!("Public key is not base64: {}", e))?;
+    let bytes: [u8; 32] = bytes
+        .try_into()
+        .map_err(|_| "An ed25519 public key is 32 bytes".to_string())?;
+    VerifyingKey::from_bytes(&bytes).map_err(|e| format!("Invalid public key: {}", e))
+}
+
+/// Check a package's signature against the trust store.
+pub fn verify(store: &TrustStore, module: &[u8], manifest: &serde_json::Value) -> Verdict {
+    let invalid = |reason: String| Verdict::Invalid { reason };
+    let Some(block) = manifest.get("signature") else {
+        return Verdict::Unsigned;
+    };
+    let block: SignatureBlock = match serde_json::from_value(block.clone()) {
+        Ok(block) => block,
+        Err(e) => return invalid(format!("Malformed signature: {}", e)),
+    };
+    if block.algorithm != "ed25519" {
+        return invalid(format!("Unsupported signature algorithm '{}'", block.algorithm));
+    }
+
+    // A trusted key is looked up by ID, or failing that by value; the
+    // signature's own key is only used when neither is trusted
+    let trusted = block
+        .key_id
+        .as_ref()
+        .and_then(|id| store.keys.get_key_value(id))
+        .or_else(|| {
+            let public_key = block.public_key.as_deref()?;
+            store.keys.iter().find(|(_, key)| key.public_key == public_key)
+        });
+    let public_key = match (trusted, &block.public_key) {
+        (Some((_, key)), _) => key.public_key.as_str(),
+        (None, Some(public_key)) => public_key.as_str(),
+        (None, None) => return invalid("The signature names no public key and its key ID isn't trusted".to_string()),
+    };
+    let key = match decode_key(public_key) {
+        Ok(key) => key,
+        Err(e) => return invalid(e),
+    };
+    let signature = match STANDARD.decode(block.value.trim()) {
+        Ok(bytes) => match Signature::from_slice(&bytes) {
+            Ok(signature) => signature,
+            Err(e) => return invalid(format!("Malformed signature: {}", e)),
+        },
+        Err(e) => return invalid(format!("Signature is not base64: {}", e)),
+    };
+    if key.verify(signed_message(module, manifest).as_bytes(), &signature).is_err() {
+        return invalid("The signature does not match the module and manifest".to_string());
+    }
+
+    match trusted {
+        Some((key_id, _)) => Verdict::Trusted { key_id: key_id.clone() },
+        None => Verdict::Untrusted {
+            key_id: block.key_id.unwrap_or_else(|| fingerprint(key.as_bytes())),
+        },
+    }
+}
+
+/// Apply the policy to a verdict: `Ok` with a warning to show, if any, or
+/// `Err` if the package is refused.
+pub fn decide(policy: Policy, verdict: &Verdict) -> Result<Option<String>, String> {
+    let problem = match verdict {
+        Verdict::Trusted { .. } => return Ok(None),
+        _ if policy == Policy::Off => return Ok(None),
+        Verdict::Invalid { reason } => return Err(format!("Invalid signature: {}", reason)),
+        Verdict::Untrusted { key_id } => format!("Signed by untrusted key '{}'", key_id),
+        Verdict::Unsigned => "Not signed".to_string(),
+    };
+    match policy {
+        Policy::Require => Err(format!("{}; only packages signed by a trusted key are allowed", problem)),
+        _ => Ok(Some(problem)),
+    }
+}
+
+/// Check a package before it is loaded. Returns the verdict and a warning
+/// to show, or fails with `SIGNATURE_REFUSED` if the policy refuses it.
+pub async fn check(module: &[u8], manifest: &serde_json::Value) -> Result<(Verdict, Option<String>), RpcError> {
+    let (verdict, decision) = state()
+        .store
+        .read(|store| {
+            let verdict = verify(store, module, manifest);
+            let decision = decide(store.policy, &verdict);
+            (verdict, decision)
+        })
+        .await;
+    match decision {
+        Ok(warning) => Ok((verdict, warning)),
+        Err(e) => Err(RpcError::new(SIGNATURE_REFUSED, e)),
+    }
+}
+
+/// Check a local build, started from its file rather than installed from a
+/// package. It can't be signed, so the `require` policy refuses it; other
+/// policies load it without a warning.
+pub async fn check_local(path: &std::path::Path) -> Result<(), RpcError> {
+    let policy = state().store.read(|store| store.policy).await;
+    match decide(policy, &Verdict::Unsigned) {
+        Ok(_) => Ok(()),
+        Err(e) => Err(RpcError::new(
+            SIGNATURE_REFUSED,
+            format!("'{}' is a local build and can't be signed: {}", path.display(), e),
+        )),
+    }
+}
+
+// ============================================================================
+// RPC Handlers
+// ============================================================================
+
+fn string_param<'a>(params: &'a serde_json::Value, name: &str) -> Result<&'a str, RpcError> {
+    params
+        .get(name)
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| RpcError::invalid_params(format!("Missing '{}' parameter", name)))
+}
+
+/// Check a package: `{ wasm_base64, manifest }`. Returns the verdict, and
+/// whether the policy allows it, with a warning if it should be shown.
+pub async fn rpc_verify(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    let module = STANDARD
+        .decode(string_param(&params, "wasm_base64")?)
+        .map_err(|e| RpcError::invalid_params(format!("Invalid base64: {}", e)))?;
+    let manifest = params
+        .get("manifest")
+        .ok_or_else(|| RpcError::invalid_params("Missing 'manifest' parameter"))?;
+    let (verdict, decision) = state()
+        .store
+        .read(|store| {
+            let verdict = verify(store, &module, manifest);
+            let decision = decide(store.policy, &verdict);
+            (verdict, decision)
+        })
+        .await;
+    Ok(match decision {
+        Ok(warning) => serde_json::json!({ "verdict": verdict, "allowed": true, "warning": warning }),
+        Err(error) => serde_json::json!({ "verdict": verdict, "allowed": false, "error": error }),
+    })
+}
+
+/// Trust a public key: `{ public_key, key_id?, name? }`. The key ID
+/// defaults to the key's fingerprint.
+pub async fn rpc_trust(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    crate::rpc::require_extension("change trusted keys")?;
+    let public_key = string_param(&params, "public_key")?.trim().to_string();
+    let key = decode_key(&public_key).map_err(RpcError::invalid_params)?;
+    let key_id = params
+        .get("key_id")
+        .and_then(|v| v.as_str())
+        .map(String::from)
+        .unwrap_or_else(|| fingerprint(key.as_bytes()));
+    let trusted = TrustedKey {
+        public_key,
+        name: params.get("name").and_then(|v| v.as_str()).map(String::from),
+    };
+    state()
+        .store
+        .try_update(|store| {
+            store.keys.insert(key_id.clone(), trusted);
+            Ok(())
+        })
+        .await
+        .map_err(RpcError::internal)?;
+    tracing::info!("Trusted signing key {}", key_id);
+    Ok(serde_json::json!({ "key_id": key_id }))
+}
+
+/// Stop trusting a key: `{ key_id }`.
+pub async fn rpc_untrust(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    crate::rpc::require_extension("change trusted keys")?;
+    let key_id = string_param(&params, "key_id")?;
+    let removed = state()
+        .store
+        .try_update(|store| Ok(store.keys.remove(key_id).is_some()))
+        .await
+        .map_err(RpcError::internal)?;
+    Ok(serde_json::json!({ "removed": removed }))
+}
+
+/// The policy and trusted keys.
+pub async fn rpc_list(_params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    let store = state().store.read(|store| store.clone()).await;
+    serde_json::to_value(store).map_err(|e| RpcError::internal(e.to_string()))
+}
+
+/// Set the policy: `{ policy: "off" | "warn" | "require" }`.
+pub async fn rpc_set_policy(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    crate::rpc::require_extension("change trusted keys")?;
+    let policy: Policy = params
+        .get("policy")
+        .cloned()
+        .ok_or_else(|| RpcError::invalid_params("Missing 'policy' parameter"))
+        .and_then(|v| serde_json::from_value(v).map_err(|e| RpcError::invalid_params(e.to_string())))?;
+    state()
+        .store
+        .try_update(|store| {
+            store.policy = policy;
+            Ok(())
+        })
+        .await
+        .map_err(RpcError::internal)?;
+    Ok(serde_json::json!({ "policy": policy }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signer, SigningKey};
+
+    fn signing_key() -> SigningKey {
+        SigningKey::from_bytes(&[7u8; 32])
+    }
+
+    fn signed(module: &[u8], manifest: serde_json::Value, key_id: Option<&str>) -> serde_json::Value {
+        let key = signing_key();
+        let signature = key.sign(signed_message(module, &manifest).as_bytes());
+        let mut manifest = manifest;
+        manifest["signature"] = serde_json::json!({
+            "algorithm": "ed25519",
+            "value": STANDARD.encode(signature.to_bytes()),
+            "publicKey": STANDARD.encode(key.verifying_key().as_bytes()),
+            "keyId": key_id,
+        });
+        manifest
+    }
+
+    fn store(policy: Policy) -> TrustStore {
+        let mut store = TrustStore {
+            policy,
+            ..Default::default()
+        };
+        store.keys.insert(
+            "author".to_string(),
+            TrustedKey {
+                public_key: STANDARD.encode(signing_key().verifying_key().as_bytes()),
+                name: None,
+            },
+        );
+        store
+    }
+
+    #[test]
+    fn test_verify() {
+        let module = b"\0asm module";
+        let manifest = signed(module, serde_json::json!({ "id": "demo", "version": "1.0.0" }), Some("author"));
+        let verdict = verify(&store(Policy::Warn), module, &manifest);
+        assert_eq!(verdict, Verdict::Trusted { key_id: "author".to_string() });
+
+        // Fields Harbor rewrites at install aren't signed
+        let mut installed = manifest.clone();
+        installed["wasmBase64"] = serde_json::json!("AAAA");
+        installed["autostart"] = serde_json::json!(true);
+        assert!(matches!(verify(&store(Policy::Warn), module, &installed), Verdict::Trusted { .. }));
+
+        let verdict = verify(&TrustStore::default(), module, &manifest);
+        assert_eq!(verdict, Verdict::Untrusted { key_id: "author".to_string() });
+
+        let mut tampered = manifest.clone();
+        tampered["version"] = serde_json::json!("2.0.0");
+        assert!(matches!(verify(&store(Policy::Warn), module, &tampered), Verdict::Invalid { .. }));
+        // Nor can values for the server's environment be slipped in
+        let mut tampered = manifest.clone();
+        tampered["secrets"] = serde_json::json!({ "API_BASE": "https://evil.example" });
+        assert!(matches!(verify(&store(Policy::Warn), module, &tampered), Verdict::Invalid { .. }));
+        assert!(matches!(verify(&store(Policy::Warn), b"other", &manifest), Verdict::Invalid { .. }));
+
+        let unsigned = serde_json::json!({ "id": "demo" });
+        assert_eq!(verify(&store(Policy::Warn), module, &unsigned), Verdict::Unsigned);
+    }
+
+    #[test]
+    fn test_decide() {
+        let untrusted = Verdict::Untrusted { key_id: "k".to_string() };
+        let invalid = Verdict::Invalid { reason: "bad".to_string() };
+        assert_eq!(decide(Policy::Require, &Verdict::Trusted { key_id: "k".to_string() }), Ok(None));
+        assert!(decide(Policy::Warn, &Verdict::Unsigned).unwrap().is_some());
+        assert!(decide(Policy::Warn, &untrusted).unwrap().is_some());
+        assert!(decide(Policy::Warn, &invalid).is_err());
+        assert!(decide(Policy::Require, &Verdict::Unsigned).is_err());
+        assert!(decide(Policy::Require, &untrusted).is_err());
+        assert_eq!(decide(Policy::Off, &invalid), Ok(None));
+    }
+
+    #[test]
+    fn test_canonical_json() {
+        let mut out = String::new();
+        canonical_json(&serde_json::json!({ "b": [1, { "d": true, "c": null }], "a": "x\"y" }), &mut out);
+        assert_eq!(out, r#"{"a":"x\"y","b":[1,{"c":null,"d":true}]}"#);
+    }
+}
@@ -0,0 +1,71 @@
+//! Bridge application state.
+//!
+//! Each subsystem keeps its state in its own struct with `start`/`stop`
+//! methods, collected here in `AppState`. The bridge installs a single
+//! instance at startup; tests construct subsystem state directly instead of
+//! sharing process-wide globals.
+
+use std::sync::OnceLock;
+
+use crate::auth::AuthState;
+use crate::budget::BudgetState;
+use crate::catalog::CatalogState;
+use crate::chaos::ChaosState;
+use crate::composite::CompositeState;
+use crate::concurrency::Limiters;
+use crate::context::ContextState;
+use crate::embeddings::EmbeddingsState;
+use crate::fs::FsState;
+use crate::js::JsState;
+use crate::maintenance::MaintenanceState;
+use crate::oauth::OAuthState;
+use crate::outbox::OutboxState;
+use crate::peer::PeerState;
+use crate::profiles::ProfilesState;
+use crate::scheduler::SchedulerState;
+use crate::secrets::SecretsState;
+use crate::server_logs::ServerLogsState;
+use crate::servers::ServersState;
+use crate::signing::SigningState;
+use crate::wasm::WasmState;
+
+/// State for every restartable subsystem.
+#[derive(Default)]
+pub struct AppState {
+    pub oauth: OAuthState,
+    pub js: JsState,
+    pub concurrency: Limiters,
+    pub auth: AuthState,
+    pub budget: BudgetState,
+    pub fs: FsState,
+    pub maintenance: MaintenanceState,
+    pub catalog: CatalogState,
+    pub embeddings: EmbeddingsState,
+    pub context: ContextState,
+    pub peer: PeerState,
+    pub composite: CompositeState,
+    pub outbox: OutboxState,
+    pub scheduler: SchedulerState,
+    pub wasm: WasmState,
+    pub chaos: ChaosState,
+    pub profiles: ProfilesState,
+    pub secrets: SecretsState,
+    pub servers: ServersState,
+    pub server_logs: ServerLogsState,
+    pub signing: SigningState,
+}
+
+static STATE: OnceLock<AppState> = OnceLock::new();
+
+/// Install the application state. Call once at startup, before any subsystem
+/// is used.
+pub fn install(state: AppState) {
+    if STATE.set(state).is_err() {
+        tracing::warn!("Application state already installed");
+    }
+}
+
+/// Get the application state, installing a default one if needed.
+pub fn get() -> &'static AppState {
+    STATE.get_or_init(AppState::default)
+}
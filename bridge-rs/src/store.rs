@@ -0,0 +1,259 @@
+//! JSON files in `~/.harbor` that subsystems keep their settings and
+//! records in.
+//!
+//! A `JsonStore` loads its file on first use and writes it back after a
+//! change. Writes go to a fresh temporary file that is renamed over the old
+//! one, so a crash or a second bridge never leaves half a file, and happen
+//! on a blocking thread once the data's lock is released, so a slow disk
+//! doesn't hold up other requests. When changes pile up, only the newest is
+//! written.
+
+use std::path::{Path, PathBuf};
+
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::sync::Mutex;
+
+/// The contents of a file kept in a `JsonStore`.
+pub trait Stored: Default + Serialize + DeserializeOwned + Send + 'static {
+    /// The file's name in `~/.harbor`
+    const FILE_NAME: &'static str;
+    /// Read the file afresh on every use, for files the `harbor` command
+    /// line changes while the bridge runs
+    const SHARED: bool = false;
+}
+
+struct Loaded<T> {
+    value: T,
+    /// The value as last loaded or written
+    json: String,
+}
+
+/// A JSON file and its loaded contents.
+pub struct JsonStore<T> {
+    /// Where the file is kept, if not in `~/.harbor`
+    path: Option<PathBuf>,
+    data: Mutex<Option<Loaded<T>>>,
+    /// Numbers each change, so an older change is never written over a newer one
+    changes: std::sync::atomic::AtomicU64,
+    /// The last change written
+    written: Mutex<u64>,
+}
+
+impl<T> Default for JsonStore<T> {
+    fn default() -> Self {
+        Self {
+            path: None,
+            data: Mutex::new(None),
+            changes: Default::default(),
+            written: Mutex::new(0),
+        }
+    }
+}
+
+#[cfg(not(test))]
+fn harbor_dir() -> PathBuf {
+    let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
+    home.join(".harbor")
+}
+
+/// Tests keep the global stores out of the real `~/.harbor`.
+#[cfg(test)]
+fn harbor_dir() -> PathBuf {
+    std::env::temp_dir().join(format!("harbor-test-{}", std::process::id()))
+}
+
+/// Write `contents` to `path` through a temporary file in the same
+/// directory, so readers see the old file or the new one and never part
+/// of either.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let dir = path.parent().unwrap_or_else(|| Path::new("."));
+    std::fs::create_dir_all(dir)?;
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("file");
+    let temp = dir.join(format!(".{}.{:016x}.tmp", name, rand::random::<u64>()));
+    let written = std::fs::write(&temp, contents).and_then(|_| std::fs::rename(&temp, path));
+    if written.is_err() {
+        let _ = std::fs::remove_file(&temp);
+    }
+    written
+}
+
+/// Like `write_atomic`, for secrets: the file is readable by the current
+/// user alone before anything is written to it.
+pub fn write_private(path: &Path, contents: &[u8]) -> Result<(), String> {
+    let dir = path.parent().unwrap_or_else(|| Path::new("."));
+    std::fs::create_dir_all(dir).map_err(|e| format!("Failed to create {:?}: {}", dir, e))?;
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("file");
+    let temp = dir.join(format!(".{}.{:016x}.tmp", name, rand::random::<u64>()));
+    let written = std::fs::write(&temp, b"")
+        .map_err(|e| format!("Failed to write {:?}: {}", temp, e))
+        .and_then(|_| crate::oauth::permissions::restrict_to_owner(&temp))
+        .and_then(|_| std::fs::write(&temp, contents).map_err(|e| format!("Failed to write {:?}: {}", temp, e)))
+        .and_then(|_| std::fs::rename(&temp, path).map_err(|e| format!("Failed to replace {:?}: {}", path, e)));
+    if written.is_err() {
+        let _ = std::fs::remove_file(&temp);
+    }
+    written
+}
+
+fn parse<T: Stored>(path: &Path, contents: std::io::Result<String>) -> T {
+    match contents {
+        Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+            tracing::warn!("Failed to parse {:?}: {}", path, e);
+            T::default()
+        }),
+        Err(_) => T::default(),
+    }
+}
+
+/// Read a stored file straight from disk, bypassing any store, for the
+/// startup report.
+pub fn read_now<T: Stored>() -> T {
+    let path = harbor_dir().join(T::FILE_NAME);
+    parse(&path, std::fs::read_to_string(&path))
+}
+
+impl<T: Stored> JsonStore<T> {
+    /// A store kept at `path` rather than in `~/.harbor`.
+    #[cfg(test)]
+    pub fn at(path: PathBuf) -> Self {
+        Self {
+            path: Some(path),
+            ..Default::default()
+        }
+    }
+
+    /// Where the file is kept.
+    pub fn path(&self) -> PathBuf {
+        self.path.clone().unwrap_or_else(|| harbor_dir().join(T::FILE_NAME))
+    }
+
+    async fn load(&self) -> Loaded<T> {
+        let path = self.path();
+        let value: T = parse(&path, tokio::fs::read_to_string(&path).await);
+        let json = serde_json::to_string_pretty(&value).unwrap_or_default();
+        Loaded { value, json }
+    }
+
+    async fn loaded(&self) -> tokio::sync::MutexGuard<'_, Option<Loaded<T>>> {
+        let mut data = self.data.lock().await;
+        if data.is_none() || T::SHARED {
+            *data = Some(self.load().await);
+        }
+        data
+    }
+
+    /// Run `f` with the file's contents.
+    pub async fn read<R>(&self, f: impl FnOnce(&T) -> R) -> R {
+        let data = self.loaded().await;
+        f(&data.as_ref().expect("store is loaded").value)
+    }
+
+    /// Run `f` with the file's contents, keeping its changes only if it
+    /// succeeds, and write them. A failed write is returned.
+    pub async fn try_update<R>(&self, f: impl FnOnce(&mut T) -> Result<R, String>) -> Result<R, String> {
+        let (result, change) = {
+            let mut data = self.loaded().await;
+            let loaded = data.as_mut().expect("store is loaded");
+            let result = match f(&mut loaded.value) {
+                Ok(result) => result,
+                Err(e) => {
+                    // Drop the half-made change; the file still has the last good contents
+                    *data = None;
+                    return Err(e);
+                }
+            };
+            let json = serde_json::to_string_pretty(&loaded.value)
+                .map_err(|e| format!("Failed to serialize {}: {}", T::FILE_NAME, e))?;
+            if json == loaded.json {
+                (result, None)
+            } else {
+                loaded.json = json.clone();
+                let number = self.changes.fetch_add(1, std::sync::atomic::Ordering::SeqCst) + 1;
+                (result, Some((number, json)))
+            }
+        };
+
+        if let Some((number, json)) = change {
+            let mut written = self.written.lock().await;
+            if number > *written {
+                let path = self.path();
+                tokio::task::spawn_blocking(move || write_atomic(&path, json.as_bytes()))
+                    .await
+                    .map_err(|e| e.to_string())
+                    .and_then(|r| r.map_err(|e| e.to_string()))
+                    .map_err(|e| format!("Failed to write {}: {}", T::FILE_NAME, e))?;
+                *written = number;
+            }
+        }
+        Ok(result)
+    }
+
+    /// Run `f` with the file's contents and write any change it makes. A
+    /// failed write is logged; the change is still kept in memory.
+    pub async fn update<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        let mut result = None;
+        let updated = self
+            .try_update(|value| {
+                result = Some(f(value));
+                Ok(())
+            })
+            .await;
+        if let Err(e) = updated {
+            tracing::warn!("{}", e);
+        }
+        result.expect("update ran")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, Default, Serialize, Deserialize)]
+    struct Counter {
+        count: u32,
+    }
+
+    impl Stored for Counter {
+        const FILE_NAME: &'static str = "counter.json";
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("harbor-store-{}-{:08x}", name, rand::random::<u32>())).join("counter.json")
+    }
+
+    #[tokio::test]
+    async fn test_update_writes_and_reloads() {
+        let path = temp_path("update");
+        let store: JsonStore<Counter> = JsonStore::at(path.clone());
+        assert_eq!(store.read(|c| c.count).await, 0);
+        assert!(!path.exists());
+
+        store.update(|c| c.count += 2).await;
+        let reopened: JsonStore<Counter> = JsonStore::at(path.clone());
+        assert_eq!(reopened.read(|c| c.count).await, 2);
+
+        // Nothing but the file is left in the directory
+        let entries = std::fs::read_dir(path.parent().unwrap()).unwrap().count();
+        assert_eq!(entries, 1);
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_failed_update_is_dropped() {
+        let path = temp_path("failed");
+        let store: JsonStore<Counter> = JsonStore::at(path.clone());
+        store.update(|c| c.count = 1).await;
+
+        let failed = store
+            .try_update(|c| {
+                c.count = 5;
+                Err::<(), _>("no".to_string())
+            })
+            .await;
+        assert!(failed.is_err());
+        assert_eq!(store.read(|c| c.count).await, 1);
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+}
@@ -0,0 +1,149 @@
+//! What the user has approved each component server to have.
+//!
+//! A server that asks for anything beyond running (hosts, directories,
+//! secrets, an account, the clipboard...) only starts once the user has
+//! approved exactly that, for exactly that module. An approval records the
+//! module (its SHA-256, or for a local build, its path) and the consent
+//! lines the user was shown; a start is allowed when the module matches
+//! and every line it would be granted is among them. A new build or a
+//! wider request is asked about again, and a server can't take over
+//! another's approval, secrets or tokens by reusing its ID.
+//!
+//! The extension checks with `wasm.review` before starting, shows the
+//! lines, and records the answer with `wasm.approve`. Approvals are kept in
+//! `~/.harbor/wasm_approvals.json`.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use super::WASM_APPROVAL_REQUIRED;
+use crate::rpc::RpcError;
+use crate::store::Stored;
+
+/// Approvals by server ID.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Approvals {
+    #[serde(default)]
+    pub servers: BTreeMap<String, Approval>,
+}
+
+impl Stored for Approvals {
+    const FILE_NAME: &'static str = "wasm_approvals.json";
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Approval {
+    /// `sha256:<hex>` of the component, or `path:<path>` for a local build
+    pub module: String,
+    /// The consent lines the user approved
+    pub consent: Vec<String>,
+    /// When (Unix timestamp ms)
+    pub approved_at: i64,
+}
+
+impl Approval {
+    fn covers(&self, module: &str, consent: &[String]) -> bool {
+        self.module == module && consent.iter().all(|line| self.consent.contains(line))
+    }
+}
+
+/// How an approval names a component: by its bytes, or for a local build,
+/// which changes as it is rebuilt, by its file.
+pub fn module_id(bytes: &[u8], path: Option<&Path>) -> String {
+    match path {
+        Some(path) => {
+            let path = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+            format!("path:{}", path.display())
+        }
+        None => format!("sha256:{:x}", Sha256::digest(bytes)),
+    }
+}
+
+/// Whether `server_id` may start `module` with what `consent` lists.
+pub async fn is_approved(server_id: &str, module: &str, consent: &[String]) -> bool {
+    if consent.is_empty() {
+        return true;
+    }
+    super::state()
+        .approvals
+        .read(|approvals| {
+            approvals
+                .servers
+                .get(server_id)
+                .is_some_and(|a| a.covers(module, consent))
+        })
+        .await
+}
+
+/// Fail unless `server_id` may start `module` with what `consent` lists.
+pub async fn check(server_id: &str, module: &str, consent: &[String]) -> Result<(), RpcError> {
+    if is_approved(server_id, module, consent).await {
+        return Ok(());
+    }
+    Err(RpcError::new(
+        WASM_APPROVAL_REQUIRED,
+        format!(
+            "'{}' needs approval to: {}; review it with wasm.review and record the user's answer with wasm.approve",
+            server_id,
+            consent.join("; ")
+        ),
+    ))
+}
+
+#[derive(Debug, Deserialize)]
+struct ApproveParams {
+    id: String,
+    /// As returned by `wasm.review`
+    module: String,
+    consent: Vec<String>,
+}
+
+/// Record that the user approved a server: `{ id, module, consent }`, as
+/// returned by `wasm.review`. Replaces the server's earlier approval.
+pub async fn approve(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    crate::rpc::require_extension("approve WASM servers")?;
+    let params: ApproveParams =
+        serde_json::from_value(params).map_err(|e| RpcError::invalid_params(format!("Invalid params: {}", e)))?;
+    let approval = Approval {
+        module: params.module,
+        consent: params.consent,
+        approved_at: chrono::Utc::now().timestamp_millis(),
+    };
+    super::state()
+        .approvals
+        .try_update(|approvals| {
+            approvals.servers.insert(params.id.clone(), approval);
+            Ok(())
+        })
+        .await
+        .map_err(|e| RpcError::new(-32000, e))?;
+    tracing::info!("[WASM:{}] Approved", params.id);
+    Ok(serde_json::json!({ "approved": true }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_covers() {
+        let approval = Approval {
+            module: module_id(b"v1", None),
+            consent: vec![
+                "Connect to api.example.com".to_string(),
+                "Read the secret KEY".to_string(),
+            ],
+            approved_at: 0,
+        };
+        let module = module_id(b"v1", None);
+        assert!(approval.covers(&module, &["Connect to api.example.com".to_string()]));
+        assert!(approval.covers(&module, &approval.consent));
+        // A different build, or more than was approved
+        assert!(!approval.covers(&module_id(b"v2", None), &approval.consent));
+        assert!(!approval.covers(&module, &["Connect to *".to_string()]));
+        assert!(module.starts_with("sha256:"));
+    }
+}
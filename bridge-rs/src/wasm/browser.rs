@@ -0,0 +1,33 @@
+//! The `harbor:mcp/browser` import: tabs, page text, bookmarks and history
+//! for a component.
+//!
+//! Only servers granted `browser` (declared in the manifest, or passed at
+//! start for one without) may use it. Requests are answered by the
+//! extension; see `crate::browser`.
+
+use super::component::harbor::mcp::browser::Host;
+use super::component::HostState;
+
+impl HostState {
+    fn require_browser(&self) -> Result<(), String> {
+        if self.browser {
+            Ok(())
+        } else {
+            tracing::warn!("[WASM:{}] Blocked use of the browser", self.server_id);
+            Err("The browser is not granted to this server; declare capabilities.browser".to_string())
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Host for HostState {
+    async fn request(&mut self, method: String, params: String) -> Result<String, String> {
+        self.require_browser()?;
+        let params: serde_json::Value = match params.trim() {
+            "" => serde_json::json!({}),
+            text => serde_json::from_str(text).map_err(|e| format!("Invalid params: {}", e))?,
+        };
+        let result = crate::browser::request(&self.server_id, &method, params).await?;
+        serde_json::to_string(&result).map_err(|e| e.to_string())
+    }
+}
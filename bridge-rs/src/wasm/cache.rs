@@ -0,0 +1,211 @@
+//! Compiled component cache.
+//!
+//! Compiling a component to native code takes hundreds of milliseconds, so
+//! the result is kept in `~/.harbor/cache/wasm` and later starts load it
+//! directly. Entries are keyed by the SHA-256 of the component bytes and of
+//! the engine's compatibility hash, which covers the wasmtime version and
+//! configuration; an upgraded bridge just misses the cache.
+//!
+//! Loading an entry runs its native code, so each one starts with the
+//! SHA-256 of the rest, which is checked before anything is deserialized,
+//! from memory rather than the file. The directory is private to the user,
+//! and an entry others could have written, a torn one, or one that fails
+//! to load is compiled again and overwritten.
+
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use sha2::{Digest, Sha256};
+use wasmtime::component::Component;
+use wasmtime::Engine;
+
+const EXTENSION: &str = "cwasm";
+
+/// Most compiled components kept; the least recently written go first.
+const MAX_ENTRIES: usize = 64;
+
+/// Length of the digest each entry starts with.
+const DIGEST_LEN: usize = 32;
+
+pub fn cache_dir() -> PathBuf {
+    let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
+    home.join(".harbor").join("cache").join("wasm")
+}
+
+/// Feeds what is hashed into SHA-256, which, unlike `DefaultHasher`, is
+/// the same in every build.
+struct Sha256Hasher(Sha256);
+
+impl Hasher for Sha256Hasher {
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.update(bytes);
+    }
+
+    fn finish(&self) -> u64 {
+        let digest = self.0.clone().finalize();
+        u64::from_be_bytes(digest[..8].try_into().unwrap())
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// The cache key for `bytes` compiled by `engine`.
+fn key(engine: &Engine, bytes: &[u8]) -> String {
+    let mut hasher = Sha256Hasher(Sha256::new());
+    engine.precompile_compatibility_hash().hash(&mut hasher);
+    format!("{}-{}", hex(&Sha256::digest(bytes)), hex(&hasher.0.finalize()[..8]))
+}
+
+fn entry_path(dir: &Path, engine: &Engine, bytes: &[u8]) -> PathBuf {
+    dir.join(key(engine, bytes)).with_extension(EXTENSION)
+}
+
+/// Whether only the owner of the cache directory, which is private to
+/// the user, could have written `path`.
+#[cfg(unix)]
+fn private(dir: &Path, path: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    let (Ok(dir), Ok(file)) = (std::fs::symlink_metadata(dir), std::fs::symlink_metadata(path)) else {
+        return false;
+    };
+    dir.is_dir() && dir.mode() & 0o022 == 0 && file.is_file() && file.uid() == dir.uid() && file.mode() & 0o022 == 0
+}
+
+#[cfg(not(unix))]
+fn private(_dir: &Path, _path: &Path) -> bool {
+    true
+}
+
+/// The compiled code in an entry, if its digest matches.
+fn verified(entry: &[u8]) -> Option<&[u8]> {
+    if entry.len() < DIGEST_LEN {
+        return None;
+    }
+    let (digest, compiled) = entry.split_at(DIGEST_LEN);
+    (Sha256::digest(compiled).as_slice() == digest).then_some(compiled)
+}
+
+/// Load the compiled form of `bytes`, if cached.
+pub fn load(dir: &Path, engine: &Engine, bytes: &[u8]) -> Option<Component> {
+    let path = entry_path(dir, engine, bytes);
+    let entry = std::fs::read(&path).ok()?;
+    let started = Instant::now();
+    let compiled = match verified(&entry) {
+        Some(compiled) if private(dir, &path) => compiled,
+        _ => {
+            tracing::warn!("Discarding compiled component {:?} that failed its integrity check", path);
+            let _ = std::fs::remove_file(&path);
+            return None;
+        }
+    };
+    // SAFETY: the bytes are exactly what `store` wrote, from
+    // `Component::serialize` with an engine of the same compatibility hash:
+    // their digest matches and the file could only have been written by the
+    // user. Deserialization still checks the header and rejects artifacts
+    // for a different engine.
+    match unsafe { Component::deserialize(engine, compiled) } {
+        Ok(component) => {
+            tracing::debug!("Loaded compiled component {:?} in {:?}", path, started.elapsed());
+            Some(component)
+        }
+        Err(e) => {
+            tracing::warn!("Discarding unusable compiled component {:?}: {}", path, e);
+            let _ = std::fs::remove_file(&path);
+            None
+        }
+    }
+}
+
+/// Create the cache directory, readable and writable by the user alone.
+fn create_dir(dir: &Path) -> std::io::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+        std::fs::DirBuilder::new().recursive(true).mode(0o700).create(dir)?;
+        std::fs::set_permissions(dir, std::fs::Permissions::from_mode(0o700))
+    }
+    #[cfg(not(unix))]
+    std::fs::create_dir_all(dir)
+}
+
+/// Store the compiled form of `bytes`.
+pub fn store(dir: &Path, engine: &Engine, bytes: &[u8], component: &Component) {
+    let path = entry_path(dir, engine, bytes);
+    // Written through a temporary file of its own, so a concurrent load or
+    // store never sees half an entry
+    let result = create_dir(dir)
+        .map_err(|e| e.to_string())
+        .and_then(|_| component.serialize().map_err(|e| e.to_string()))
+        .and_then(|compiled| {
+            let mut entry = Sha256::digest(&compiled).to_vec();
+            entry.extend_from_slice(&compiled);
+            crate::store::write_atomic(&path, &entry).map_err(|e| e.to_string())
+        });
+    if let Err(e) = result {
+        tracing::warn!("Failed to cache compiled component: {}", e);
+        return;
+    }
+    prune(dir);
+}
+
+/// Remove the oldest entries past `MAX_ENTRIES`.
+fn prune(dir: &Path) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    let mut entries: Vec<(std::time::SystemTime, PathBuf)> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().map(|e| e == EXTENSION).unwrap_or(false))
+        .filter_map(|path| Some((std::fs::metadata(&path).ok()?.modified().ok()?, path)))
+        .collect();
+    if entries.len() <= MAX_ENTRIES {
+        return;
+    }
+    entries.sort();
+    for (_, path) in &entries[..entries.len() - MAX_ENTRIES] {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An empty component.
+    const EMPTY: &[u8] = b"\0asm\x0d\x00\x01\x00";
+
+    #[test]
+    fn test_store_and_load() {
+        let dir = std::env::temp_dir().join(format!("harbor-wasm-cache-{}", std::process::id()));
+        let engine = Engine::default();
+        assert!(load(&dir, &engine, EMPTY).is_none());
+
+        let component = Component::new(&engine, EMPTY).unwrap();
+        store(&dir, &engine, EMPTY, &component);
+        assert!(load(&dir, &engine, EMPTY).is_some());
+
+        // A corrupt or altered entry is dropped rather than loaded
+        let path = entry_path(&dir, &engine, EMPTY);
+        std::fs::write(&path, b"not native code").unwrap();
+        assert!(load(&dir, &engine, EMPTY).is_none());
+        assert!(!path.exists());
+        store(&dir, &engine, EMPTY, &component);
+        let mut entry = std::fs::read(&path).unwrap();
+        *entry.last_mut().unwrap() ^= 1;
+        std::fs::write(&path, &entry).unwrap();
+        assert!(load(&dir, &engine, EMPTY).is_none());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_key_depends_on_bytes() {
+        let engine = Engine::default();
+        assert_ne!(key(&engine, EMPTY), key(&engine, b"\0asm\x0d\x00\x01\x00\x00"));
+        assert_eq!(key(&engine, EMPTY), key(&engine, EMPTY));
+    }
+}
@@ -0,0 +1,123 @@
+//! The `harbor:mcp/clipboard` import: the OS clipboard's text for a
+//! component.
+//!
+//! Only servers granted `clipboard` (declared in the manifest, or passed at
+//! start for one without) may use it, and every read and write is logged.
+//! Like any grant, it needs the user's approval for the module first (see
+//! `super::approval`); passing `clipboard` to `wasm.start_server` alone
+//! gets a server nothing.
+//! The clipboard is owned by one thread for the life of the bridge: on X11
+//! and Wayland, text is only served to other applications while the
+//! handle that wrote it is alive, and the handle isn't `Send` everywhere.
+
+use std::sync::{mpsc, OnceLock};
+
+use tokio::sync::oneshot;
+
+use super::component::harbor::mcp::clipboard::Host;
+use super::component::HostState;
+
+/// Text longer than this isn't written.
+const MAX_TEXT_BYTES: usize = 1024 * 1024;
+
+enum Op {
+    Get(oneshot::Sender<Result<Option<String>, String>>),
+    Set(String, oneshot::Sender<Result<(), String>>),
+}
+
+fn run(ops: mpsc::Receiver<Op>) {
+    let mut clipboard = None;
+    for op in ops {
+        if clipboard.is_none() {
+            clipboard = arboard::Clipboard::new()
+                .map_err(|e| tracing::warn!("No clipboard is available: {}", e))
+                .ok();
+        }
+        let unavailable = || "No clipboard is available to the bridge".to_string();
+        match op {
+            Op::Get(reply) => {
+                let text = match clipboard.as_mut() {
+                    Some(clipboard) => match clipboard.get_text() {
+                        Ok(text) => Ok(Some(text)),
+                        // Empty, or holding an image or files
+                        Err(arboard::Error::ContentNotAvailable) => Ok(None),
+                        Err(e) => Err(e.to_string()),
+                    },
+                    None => Err(unavailable()),
+                };
+                let _ = reply.send(text);
+            }
+            Op::Set(text, reply) => {
+                let done = match clipboard.as_mut() {
+                    Some(clipboard) => clipboard.set_text(text).map_err(|e| e.to_string()),
+                    None => Err(unavailable()),
+                };
+                let _ = reply.send(done);
+            }
+        }
+    }
+}
+
+fn send(op: Op) -> Result<(), String> {
+    static OPS: OnceLock<mpsc::Sender<Op>> = OnceLock::new();
+    let ops = OPS.get_or_init(|| {
+        let (tx, rx) = mpsc::channel();
+        std::thread::Builder::new()
+            .name("clipboard".to_string())
+            .spawn(move || run(rx))
+            .expect("Failed to start the clipboard thread");
+        tx
+    });
+    ops.send(op).map_err(|_| "The clipboard thread has stopped".to_string())
+}
+
+/// Check that `text` may be written to the clipboard.
+fn check_text(text: &str) -> Result<(), String> {
+    if text.len() > MAX_TEXT_BYTES {
+        return Err(format!("Text is {} bytes; the clipboard takes at most {}", text.len(), MAX_TEXT_BYTES));
+    }
+    Ok(())
+}
+
+impl HostState {
+    fn require_clipboard(&self) -> Result<(), String> {
+        if self.clipboard {
+            Ok(())
+        } else {
+            tracing::warn!("[WASM:{}] Blocked use of the clipboard", self.server_id);
+            Err("The clipboard is not granted to this server; declare capabilities.clipboard".to_string())
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Host for HostState {
+    async fn get_text(&mut self) -> Result<Option<String>, String> {
+        self.require_clipboard()?;
+        tracing::info!("[WASM:{}] Read the clipboard", self.server_id);
+        let (tx, rx) = oneshot::channel();
+        send(Op::Get(tx))?;
+        rx.await.map_err(|_| "The clipboard thread has stopped".to_string())?
+    }
+
+    async fn set_text(&mut self, text: String) -> Result<(), String> {
+        self.require_clipboard()?;
+        check_text(&text)?;
+        tracing::info!("[WASM:{}] Wrote {} bytes to the clipboard", self.server_id, text.len());
+        let (tx, rx) = oneshot::channel();
+        send(Op::Set(text, tx))?;
+        rx.await.map_err(|_| "The clipboard thread has stopped".to_string())?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_text() {
+        assert!(check_text("hello").is_ok());
+        assert!(check_text(&"x".repeat(MAX_TEXT_BYTES)).is_ok());
+        assert!(check_text(&"x".repeat(MAX_TEXT_BYTES + 1)).is_err());
+    }
+}
@@ -0,0 +1,506 @@
+//! Running `harbor:mcp` components with wasmtime.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use bytes::Bytes;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use serde::Deserialize;
+use wasmtime::component::{Component, Linker, ResourceTable};
+use wasmtime::{Config, Engine, ResourceLimiter, Store, Trap};
+use wasmtime_wasi::{
+    DirPerms, FilePerms, HostMonotonicClock, HostOutputStream, HostWallClock, StdoutStream, StreamResult, Subscribe,
+    WasiCtx, WasiCtxBuilder, WasiView,
+};
+
+use super::manifest::OAuthDecl;
+use crate::js::NetworkCapabilities;
+use crate::secrets::Secrets;
+use crate::server_logs::{self, Stream};
+
+wasmtime::component::bindgen!({
+    path: "../mcp-servers/wit",
+    world: "harbor:mcp/mcp-server",
+    async: true,
+});
+
+/// How often running guests are interrupted to check their deadline.
+const EPOCH_TICK: Duration = Duration::from_millis(10);
+
+/// Most table elements a component may grow to.
+const MAX_TABLE_ELEMENTS: usize = 100_000;
+
+/// Longest stderr output held while waiting for the end of its line.
+const MAX_STDERR_PENDING: usize = 64 * 1024;
+
+/// Resource limits for one server's instances.
+#[derive(Debug, Clone, Copy)]
+pub struct Limits {
+    /// Most linear memory, across all of an instance's memories
+    pub memory_bytes: usize,
+    /// Fuel for each request; roughly one unit per instruction
+    pub fuel: u64,
+    /// Longest a single request may run
+    pub timeout: Duration,
+    /// Most bytes of keys and values in the server's `kv` namespace
+    pub kv_bytes: u64,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            memory_bytes: 256 * 1024 * 1024,
+            fuel: 10_000_000_000,
+            timeout: Duration::from_secs(30),
+            kv_bytes: super::kv::DEFAULT_QUOTA_BYTES,
+        }
+    }
+}
+
+/// Why a request to an instance failed.
+#[derive(Debug)]
+pub enum CallError {
+    /// Ran out of fuel: an endless or overly long computation
+    OutOfFuel { fuel: u64 },
+    /// Tried to grow memory past the limit
+    MemoryLimit { bytes: usize },
+    /// Didn't answer in time, e.g. while blocked on the host
+    Timeout(Duration),
+    /// Any other trap
+    Trap(String),
+}
+
+impl std::fmt::Display for CallError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CallError::OutOfFuel { fuel } => write!(f, "Component ran out of fuel ({} units per request)", fuel),
+            CallError::MemoryLimit { bytes } => {
+                write!(f, "Component exceeded its memory limit of {} MiB", bytes / (1024 * 1024))
+            }
+            CallError::Timeout(timeout) => write!(f, "Component did not answer within {}ms", timeout.as_millis()),
+            CallError::Trap(e) => write!(f, "Component trapped: {}", e),
+        }
+    }
+}
+
+/// Memory and table caps for a store. The memory cap covers all of the
+/// store's memories together, since a component can have several. Growing
+/// past it traps (rather than failing the grow) so the call ends with a
+/// clear error.
+struct StoreLimiter {
+    memory_bytes: usize,
+    /// Bytes of every memory created in the store so far
+    memory_used: usize,
+    exceeded: bool,
+}
+
+impl ResourceLimiter for StoreLimiter {
+    fn memory_growing(&mut self, current: usize, desired: usize, _maximum: Option<usize>) -> wasmtime::Result<bool> {
+        // Creating a memory is growing it from zero
+        let used = self.memory_used - current + desired;
+        if used > self.memory_bytes {
+            self.exceeded = true;
+            return Err(wasmtime::Error::msg("memory limit exceeded"));
+        }
+        self.memory_used = used;
+        Ok(true)
+    }
+
+    fn table_growing(&mut self, _current: usize, desired: usize, _maximum: Option<usize>) -> wasmtime::Result<bool> {
+        Ok(desired <= MAX_TABLE_ELEMENTS)
+    }
+}
+
+/// A host directory visible to the guest.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Preopen {
+    pub host: PathBuf,
+    /// Where the guest sees it
+    pub guest: String,
+    pub write: bool,
+}
+
+/// Clocks that always read zero, for servers not granted the time.
+struct FrozenClock;
+
+impl HostWallClock for FrozenClock {
+    fn resolution(&self) -> Duration {
+        Duration::from_secs(1)
+    }
+
+    fn now(&self) -> Duration {
+        Duration::ZERO
+    }
+}
+
+impl HostMonotonicClock for FrozenClock {
+    fn resolution(&self) -> u64 {
+        1_000_000_000
+    }
+
+    fn now(&self) -> u64 {
+        0
+    }
+}
+
+/// A server's stderr: each line written is recorded for `servers.logs`.
+struct StderrCapture {
+    server_id: String,
+}
+
+impl StdoutStream for StderrCapture {
+    fn stream(&self) -> Box<dyn HostOutputStream> {
+        Box::new(StderrLines {
+            server_id: self.server_id.clone(),
+            pending: Vec::new(),
+        })
+    }
+
+    fn isatty(&self) -> bool {
+        false
+    }
+}
+
+/// One stderr stream a guest opened, and the unfinished line written to it.
+struct StderrLines {
+    server_id: String,
+    pending: Vec<u8>,
+}
+
+impl StderrLines {
+    fn record(&mut self, end: usize) {
+        let line: Vec<u8> = self.pending.drain(..end).collect();
+        server_logs::record(&self.server_id, Stream::Stderr, None, &String::from_utf8_lossy(&line));
+    }
+}
+
+impl HostOutputStream for StderrLines {
+    fn write(&mut self, bytes: Bytes) -> StreamResult<()> {
+        self.pending.extend_from_slice(&bytes);
+        while let Some(newline) = self.pending.iter().position(|&b| b == b'\n') {
+            self.record(newline + 1);
+        }
+        if self.pending.len() > MAX_STDERR_PENDING {
+            self.record(self.pending.len());
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> StreamResult<()> {
+        Ok(())
+    }
+
+    fn check_write(&mut self) -> StreamResult<usize> {
+        Ok(MAX_STDERR_PENDING)
+    }
+}
+
+#[async_trait::async_trait]
+impl Subscribe for StderrLines {
+    async fn ready(&mut self) {}
+}
+
+impl Drop for StderrLines {
+    fn drop(&mut self) {
+        if !self.pending.is_empty() {
+            self.record(self.pending.len());
+        }
+    }
+}
+
+/// The user's language and time zone, as the extension reports them, for
+/// servers granted `locale`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Locale {
+    /// BCP 47 language tag, e.g. "en-US"
+    pub language: String,
+    /// IANA time zone, e.g. "America/New_York"
+    pub timezone: String,
+    /// Offset from UTC in minutes, east positive, when the server started
+    #[serde(default, alias = "utcOffsetMinutes")]
+    pub utc_offset_minutes: i32,
+}
+
+impl Locale {
+    /// The environment variables a server reads the locale from, as the
+    /// extension sets them for preview 1 modules.
+    fn env(&self) -> [(&'static str, String); 4] {
+        let mut subtags = self.language.split('-');
+        let lang = subtags.next().unwrap_or_default().to_ascii_lowercase();
+        let region = subtags.find(|tag| {
+            (tag.len() == 2 && tag.chars().all(|c| c.is_ascii_alphabetic()))
+                || (tag.len() == 3 && tag.chars().all(|c| c.is_ascii_digit()))
+        });
+        let posix = match region {
+            Some(region) => format!("{}_{}.UTF-8", lang, region.to_ascii_uppercase()),
+            None => format!("{}.UTF-8", lang),
+        };
+        [
+            ("HARBOR_LOCALE", self.language.clone()),
+            ("TZ", self.timezone.clone()),
+            ("HARBOR_UTC_OFFSET_MINUTES", self.utc_offset_minutes.to_string()),
+            ("LANG", posix),
+        ]
+    }
+}
+
+/// What a server's instances get from the host.
+#[derive(Debug, Clone, Default)]
+pub struct HostConfig {
+    pub server_id: String,
+    pub env: HashMap<String, String>,
+    /// Declared secrets, read by `get-secret` and some also set in `env`
+    pub secrets: Secrets,
+    /// Hosts reachable through the `http` import
+    pub network: NetworkCapabilities,
+    /// Directories visible through WASI filesystem imports
+    pub preopens: Vec<Preopen>,
+    /// Clocks read zero rather than the time
+    pub frozen_clock: bool,
+    /// Random bytes come from a generator with this seed, the same on
+    /// every run, rather than the OS
+    pub random_seed: Option<u64>,
+    /// Set in the environment (see `Locale::env`)
+    pub locale: Option<Locale>,
+    /// May create schedules through the `schedule` import
+    pub schedule: bool,
+    /// Provider and scopes the `oauth` import hands out tokens for
+    pub oauth: Option<OAuthDecl>,
+    /// May ask the extension about tabs, bookmarks and history through the
+    /// `browser` import
+    pub browser: bool,
+    /// May read and write the OS clipboard through the `clipboard` import
+    pub clipboard: bool,
+    pub limits: Limits,
+}
+
+/// Per-instance host state.
+pub struct HostState {
+    wasi: WasiCtx,
+    table: ResourceTable,
+    limiter: StoreLimiter,
+    pub(super) server_id: String,
+    pub(super) network: NetworkCapabilities,
+    pub(super) kv_quota: u64,
+    pub(super) schedule: bool,
+    pub(super) oauth: Option<OAuthDecl>,
+    pub(super) browser: bool,
+    pub(super) clipboard: bool,
+    secrets: Secrets,
+}
+
+impl WasiView for HostState {
+    fn table(&mut self) -> &mut ResourceTable {
+        &mut self.table
+    }
+
+    fn ctx(&mut self) -> &mut WasiCtx {
+        &mut self.wasi
+    }
+}
+
+#[async_trait::async_trait]
+impl harbor::mcp::secrets::Host for HostState {
+    async fn get_secret(&mut self, name: String) -> Option<String> {
+        self.secrets.get(&name).map(str::to_string)
+    }
+}
+
+/// The shared engine. Guests yield back to the async runtime every epoch
+/// tick, so a call blocked on the host can still be timed out, and burn
+/// fuel, so one that spins forever runs out.
+fn engine() -> &'static Engine {
+    static ENGINE: OnceLock<Engine> = OnceLock::new();
+    ENGINE.get_or_init(|| {
+        let mut config = Config::new();
+        config
+            .async_support(true)
+            .wasm_component_model(true)
+            .epoch_interruption(true)
+            .consume_fuel(true);
+        let engine = Engine::new(&config).expect("Invalid wasmtime configuration");
+        let ticker = engine.clone();
+        std::thread::spawn(move || loop {
+            std::thread::sleep(EPOCH_TICK);
+            ticker.increment_epoch();
+        });
+        engine
+    })
+}
+
+fn linker() -> Result<&'static Linker<HostState>, String> {
+    static LINKER: OnceLock<Result<Linker<HostState>, String>> = OnceLock::new();
+    LINKER
+        .get_or_init(|| {
+            let mut linker = Linker::new(engine());
+            wasmtime_wasi::add_to_linker_async(&mut linker).map_err(|e| e.to_string())?;
+            McpServer::add_to_linker(&mut linker, |state: &mut HostState| state).map_err(|e| e.to_string())?;
+            Ok(linker)
+        })
+        .as_ref()
+        .map_err(Clone::clone)
+}
+
+/// Whether `bytes` is a component rather than a core module. Both start
+/// with `\0asm`; components have a different version/layer field.
+pub fn is_component(bytes: &[u8]) -> bool {
+    bytes.len() >= 8 && &bytes[0..4] == b"\0asm" && bytes[6..8] == [0x01, 0x00] && bytes[4..6] != [0x01, 0x00]
+}
+
+/// Compile a component, or load it from the compiled component cache.
+/// Slow on a cache miss; call off the async runtime.
+pub fn compile(bytes: &[u8]) -> Result<Component, String> {
+    if !is_component(bytes) {
+        return Err("Not a WebAssembly component; WASI preview 1 modules run in the extension".to_string());
+    }
+    let dir = super::cache::cache_dir();
+    if let Some(component) = super::cache::load(&dir, engine(), bytes) {
+        return Ok(component);
+    }
+    let started = std::time::Instant::now();
+    let component = Component::new(engine(), bytes).map_err(|e| format!("Failed to compile component: {}", e))?;
+    tracing::info!("Compiled component ({} bytes) in {:?}", bytes.len(), started.elapsed());
+    super::cache::store(&dir, engine(), bytes, &component);
+    Ok(component)
+}
+
+/// A live instance of a component.
+pub struct Instance {
+    store: Store<HostState>,
+    bindings: McpServer,
+    limits: Limits,
+}
+
+impl Instance {
+    pub async fn new(component: &Component, config: &HostConfig) -> Result<Self, String> {
+        let mut wasi = WasiCtxBuilder::new();
+        wasi.stderr(StderrCapture {
+            server_id: config.server_id.clone(),
+        });
+        for (key, value) in &config.env {
+            wasi.env(key, value);
+        }
+        for (name, value) in config.secrets.env() {
+            wasi.env(name, value);
+        }
+        for (name, value) in config.locale.iter().flat_map(Locale::env) {
+            wasi.env(name, value);
+        }
+        for dir in &config.preopens {
+            let (dir_perms, file_perms) = match dir.write {
+                true => (DirPerms::all(), FilePerms::all()),
+                false => (DirPerms::READ, FilePerms::READ),
+            };
+            wasi.preopened_dir(&dir.host, &dir.guest, dir_perms, file_perms)
+                .map_err(|e| format!("Failed to open '{}': {}", dir.host.display(), e))?;
+        }
+        if config.frozen_clock {
+            wasi.wall_clock(FrozenClock).monotonic_clock(FrozenClock);
+        }
+        if let Some(seed) = config.random_seed {
+            wasi.secure_random(StdRng::seed_from_u64(seed))
+                .insecure_random(StdRng::seed_from_u64(seed))
+                .insecure_random_seed(u128::from(seed));
+        }
+        let state = HostState {
+            wasi: wasi.build(),
+            table: ResourceTable::new(),
+            limiter: StoreLimiter {
+                memory_bytes: config.limits.memory_bytes,
+                memory_used: 0,
+                exceeded: false,
+            },
+            server_id: config.server_id.clone(),
+            network: config.network.clone(),
+            kv_quota: config.limits.kv_bytes,
+            schedule: config.schedule,
+            oauth: config.oauth.clone(),
+            browser: config.browser,
+            clipboard: config.clipboard,
+            secrets: config.secrets.clone(),
+        };
+        let mut store = Store::new(engine(), state);
+        store.limiter(|state| &mut state.limiter);
+        store.epoch_deadline_async_yield_and_update(1);
+        // Start functions run on the first request's budget
+        store.set_fuel(config.limits.fuel).map_err(|e| e.to_string())?;
+
+        let bindings = McpServer::instantiate_async(&mut store, component, linker()?)
+            .await
+            .map_err(|e| format!("Failed to instantiate component: {}", e))?;
+        Ok(Self {
+            store,
+            bindings,
+            limits: config.limits,
+        })
+    }
+
+    /// Send one JSON-RPC message. After an error the instance may be in a
+    /// broken state and should be dropped.
+    pub async fn handle(&mut self, request: &str) -> Result<String, CallError> {
+        let limits = self.limits;
+        self.store
+            .set_fuel(limits.fuel)
+            .map_err(|e| CallError::Trap(e.to_string()))?;
+        let call = self.bindings.harbor_mcp_server().call_handle(&mut self.store, request);
+        let error = match tokio::time::timeout(limits.timeout, call).await {
+            Ok(Ok(response)) => return Ok(response),
+            Ok(Err(error)) => error,
+            Err(_) => return Err(CallError::Timeout(limits.timeout)),
+        };
+        if self.store.data().limiter.exceeded {
+            return Err(CallError::MemoryLimit {
+                bytes: limits.memory_bytes,
+            });
+        }
+        match error.downcast_ref::<Trap>() {
+            Some(Trap::OutOfFuel) => Err(CallError::OutOfFuel { fuel: limits.fuel }),
+            _ => Err(CallError::Trap(error.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_component() {
+        let core = b"\0asm\x01\x00\x00\x00";
+        let component = b"\0asm\x0d\x00\x01\x00";
+        assert!(!is_component(core));
+        assert!(is_component(component));
+        assert!(!is_component(b"\0asm"));
+    }
+
+    #[test]
+    fn test_locale_env() {
+        let locale = Locale {
+            language: "zh-Hant-TW".to_string(),
+            timezone: "Asia/Taipei".to_string(),
+            utc_offset_minutes: 480,
+        };
+        let env = locale.env();
+        assert_eq!(env[1], ("TZ", "Asia/Taipei".to_string()));
+        assert_eq!(env[2], ("HARBOR_UTC_OFFSET_MINUTES", "480".to_string()));
+        assert_eq!(env[3], ("LANG", "zh_TW.UTF-8".to_string()));
+    }
+
+    #[test]
+    fn test_memory_limit_covers_all_memories() {
+        let mut limiter = StoreLimiter {
+            memory_bytes: 100,
+            memory_used: 0,
+            exceeded: false,
+        };
+        assert!(limiter.memory_growing(0, 60, None).unwrap());
+        assert!(limiter.memory_growing(60, 70, None).unwrap());
+        // A second memory fits only in what the first leaves
+        assert!(limiter.memory_growing(0, 40, None).is_err());
+        assert!(limiter.exceeded);
+        assert!(limiter.memory_growing(0, 30, None).unwrap());
+    }
+}
@@ -0,0 +1,226 @@
+//! The `harbor:mcp/http` import: HTTP requests made for a component.
+//!
+//! Components get no sockets from WASI, so this is their only way out. A
+//! request is made only if its host is in the server's allowlist (from the
+//! manifest's `capabilities.network.hosts`), and redirects are followed only
+//! while they stay on allowed hosts.
+//!
+//! Whatever the allowlist says, a component never reaches the user's own
+//! machine or network: every hop's host must resolve to a public address,
+//! and only those addresses are connected to, so a name (or a redirect)
+//! pointing at loopback, a private range or link-local is refused.
+
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+
+use super::component::harbor::mcp::http::{Host, Request, Response};
+use super::component::HostState;
+use crate::js::NetworkCapabilities;
+
+/// Longest a single request may take, including reading the body.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// Largest response body handed back to a component.
+const MAX_RESPONSE_BYTES: usize = 10 * 1024 * 1024;
+
+const MAX_REDIRECTS: usize = 5;
+
+#[async_trait::async_trait]
+impl Host for HostState {
+    async fn fetch(&mut self, request: Request) -> Result<Response, String> {
+        fetch(&self.server_id, &self.network, request).await
+    }
+}
+
+/// Whether `ip` is on the public internet: not loopback, private,
+/// link-local, shared (CGNAT), unspecified, broadcast or multicast.
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => {
+            let [a, b, ..] = v4.octets();
+            !(v4.is_loopback()
+                || v4.is_private()
+                || v4.is_link_local()
+                || v4.is_unspecified()
+                || v4.is_broadcast()
+                || v4.is_multicast()
+                || v4.is_documentation()
+                || a == 0
+                || (a == 100 && (64..128).contains(&b)))
+        }
+        IpAddr::V6(v6) => {
+            if let Some(v4) = v6.to_ipv4_mapped() {
+                return is_public(IpAddr::V4(v4));
+            }
+            let first = v6.segments()[0];
+            !(v6.is_loopback()
+                || v6.is_unspecified()
+                || v6.is_multicast()
+                || (first & 0xfe00) == 0xfc00
+                || (first & 0xffc0) == 0xfe80)
+        }
+    }
+}
+
+/// Whether `url`'s host is public, if it is an IP address; names are
+/// checked as they are resolved, by `PublicOnly`.
+fn public_literal(url: &url::Url) -> bool {
+    match url.host() {
+        Some(url::Host::Ipv4(ip)) => is_public(IpAddr::V4(ip)),
+        Some(url::Host::Ipv6(ip)) => is_public(IpAddr::V6(ip)),
+        Some(url::Host::Domain(_)) => true,
+        None => false,
+    }
+}
+
+/// Resolves names to their public addresses only.
+struct PublicOnly;
+
+impl Resolve for PublicOnly {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let host = name.as_str();
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, 0))
+                .await?
+                .filter(|addr| is_public(addr.ip()))
+                .collect();
+            if addrs.is_empty() {
+                return Err(format!("{} has no public address", host).into());
+            }
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// Perform `request` for `server_id` if `network` allows its host.
+pub async fn fetch(server_id: &str, network: &NetworkCapabilities, request: Request) -> Result<Response, String> {
+    if !network.is_host_allowed(&request.url) {
+        tracing::warn!("[WASM:{}] Blocked request to {}", server_id, request.url);
+        return Err(format!(
+            "Network access denied: {} is not in the server's allowed hosts",
+            request.url
+        ));
+    }
+    let url = url::Url::parse(&request.url).map_err(|e| format!("Invalid URL {}: {}", request.url, e))?;
+    if !public_literal(&url) {
+        tracing::warn!("[WASM:{}] Blocked request to private address {}", server_id, request.url);
+        return Err(format!("Network access denied: {} is not a public address", request.url));
+    }
+    let method = reqwest::Method::from_bytes(request.method.to_uppercase().as_bytes())
+        .map_err(|_| format!("Unsupported method: {}", request.method))?;
+
+    let allowed = network.clone();
+    let client = reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .dns_resolver(Arc::new(PublicOnly))
+        .redirect(reqwest::redirect::Policy::custom(move |attempt| {
+            if attempt.previous().len() >= MAX_REDIRECTS {
+                attempt.error("Too many redirects")
+            } else if !public_literal(attempt.url()) {
+                attempt.error("Redirected to a private address")
+            } else if allowed.is_host_allowed(attempt.url().as_str()) {
+                attempt.follow()
+            } else {
+                attempt.stop()
+            }
+        }))
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+
+    let mut builder = client.request(method.clone(), url);
+    for (name, value) in &request.headers {
+        builder = builder.header(name.as_str(), value.as_str());
+    }
+    if let Some(body) = request.body {
+        builder = builder.body(body);
+    }
+    let mut response = builder.send().await.map_err(|e| format!("Request failed: {}", e))?;
+
+    let status = response.status().as_u16();
+    let headers = response
+        .headers()
+        .iter()
+        .filter_map(|(name, value)| value.to_str().ok().map(|v| (name.to_string(), v.to_string())))
+        .collect();
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await.map_err(|e| format!("Failed to read response: {}", e))? {
+        if body.len() + chunk.len() > MAX_RESPONSE_BYTES {
+            return Err(format!("Response is larger than {} MiB", MAX_RESPONSE_BYTES / (1024 * 1024)));
+        }
+        body.extend_from_slice(&chunk);
+    }
+    tracing::info!("[WASM:{}] {} {} -> {} ({} bytes)", server_id, method, request.url, status, body.len());
+
+    Ok(Response { status, headers, body })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_fetch_requires_allowed_host() {
+        let network = NetworkCapabilities {
+            allowed_hosts: vec!["api.example.com".to_string()],
+        };
+        let request = Request {
+            method: "GET".to_string(),
+            url: "https://evil.example.net/steal".to_string(),
+            headers: Vec::new(),
+            body: None,
+        };
+        let error = fetch("test", &network, request).await.unwrap_err();
+        assert!(error.contains("Network access denied"));
+
+        let request = Request {
+            method: "GET".to_string(),
+            url: "https://api.example.com/".to_string(),
+            headers: Vec::new(),
+            body: None,
+        };
+        let error = fetch("test", &NetworkCapabilities::default(), request).await.unwrap_err();
+        assert!(error.contains("Network access denied"));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_refuses_private_addresses() {
+        let network = NetworkCapabilities {
+            allowed_hosts: vec!["*".to_string()],
+        };
+        for url in ["http://127.0.0.1:8080/", "http://169.254.169.254/latest/meta-data/", "http://localhost/"] {
+            let request = Request {
+                method: "GET".to_string(),
+                url: url.to_string(),
+                headers: Vec::new(),
+                body: None,
+            };
+            assert!(fetch("test", &network, request).await.is_err(), "{}", url);
+        }
+    }
+
+    #[test]
+    fn test_is_public() {
+        for ip in ["93.184.216.34", "2606:2800:220:1::"] {
+            assert!(is_public(ip.parse().unwrap()), "{}", ip);
+        }
+        let private = [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+        ];
+        for ip in private {
+            assert!(!is_public(ip.parse().unwrap()), "{}", ip);
+        }
+    }
+}
@@ -0,0 +1,355 @@
+//! The `harbor:mcp/kv` import: a small key-value store per server.
+//!
+//! Instances don't outlive a failure, an idle eviction or a reload, so a
+//! server that needs to remember something keeps it here. Each server sees
+//! its own namespace (its ID) and may store up to its quota, counted as the
+//! bytes of its keys and values; `limits.kv_mb` at start sets it. Entries
+//! live in `~/.harbor/state/kv.sqlite3` and survive restarts of the server
+//! and the bridge. The same entries can be read and changed with the
+//! `wasm.kv_*` RPCs, e.g. to inspect or clear a server's state: by the
+//! extension for any server, and by a server for its own. SQLite runs on
+//! blocking threads, off the async runtime.
+
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Deserialize;
+
+use super::component::harbor::mcp::kv::Host;
+use super::component::HostState;
+use crate::rpc::RpcError;
+
+const FILE_NAME: &str = "kv.sqlite3";
+
+/// Quota for a server that doesn't set one.
+pub const DEFAULT_QUOTA_BYTES: u64 = 1024 * 1024;
+
+/// Longest key.
+const MAX_KEY_BYTES: usize = 512;
+
+pub fn state_dir() -> PathBuf {
+    let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
+    home.join(".harbor").join("state")
+}
+
+pub struct Store {
+    conn: Mutex<Connection>,
+}
+
+impl Store {
+    pub fn open(path: &Path) -> Result<Self, String> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| format!("Failed to create '{}': {}", dir.display(), e))?;
+        }
+        let conn = Connection::open(path).map_err(|e| format!("Failed to open '{}': {}", path.display(), e))?;
+        Self::init(conn)
+    }
+
+    #[cfg(test)]
+    fn in_memory() -> Self {
+        Self::init(Connection::open_in_memory().unwrap()).unwrap()
+    }
+
+    fn init(conn: Connection) -> Result<Self, String> {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS kv (
+                namespace TEXT NOT NULL,
+                key TEXT NOT NULL,
+                value BLOB NOT NULL,
+                PRIMARY KEY (namespace, key)
+            )",
+        )
+        .map_err(|e| e.to_string())?;
+        Ok(Self { conn: Mutex::new(conn) })
+    }
+
+    pub fn get(&self, namespace: &str, key: &str) -> Result<Option<Vec<u8>>, String> {
+        self.conn
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT value FROM kv WHERE namespace = ?1 AND key = ?2",
+                params![namespace, key],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| e.to_string())
+    }
+
+    /// Store `value` under `key`, unless the namespace would then exceed
+    /// `quota` bytes.
+    pub fn set(&self, namespace: &str, key: &str, value: &[u8], quota: u64) -> Result<(), String> {
+        if key.is_empty() || key.len() > MAX_KEY_BYTES {
+            return Err(format!("Keys must be 1 to {} bytes", MAX_KEY_BYTES));
+        }
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        let others: i64 = tx
+            .query_row(
+                "SELECT COALESCE(SUM(LENGTH(CAST(key AS BLOB)) + LENGTH(value)), 0)
+                 FROM kv WHERE namespace = ?1 AND key != ?2",
+                params![namespace, key],
+                |row| row.get(0),
+            )
+            .map_err(|e| e.to_string())?;
+        let total = others as u64 + (key.len() + value.len()) as u64;
+        if total > quota {
+            return Err(format!("Storage quota exceeded: {} of {} bytes", total, quota));
+        }
+        tx.execute(
+            "INSERT INTO kv (namespace, key, value) VALUES (?1, ?2, ?3)
+             ON CONFLICT (namespace, key) DO UPDATE SET value = excluded.value",
+            params![namespace, key, value],
+        )
+        .map_err(|e| e.to_string())?;
+        tx.commit().map_err(|e| e.to_string())
+    }
+
+    /// Remove `key`; returns whether it existed.
+    pub fn delete(&self, namespace: &str, key: &str) -> Result<bool, String> {
+        self.conn
+            .lock()
+            .unwrap()
+            .execute("DELETE FROM kv WHERE namespace = ?1 AND key = ?2", params![namespace, key])
+            .map(|removed| removed > 0)
+            .map_err(|e| e.to_string())
+    }
+
+    /// Keys starting with `prefix`, in order.
+    pub fn list(&self, namespace: &str, prefix: &str) -> Result<Vec<String>, String> {
+        let conn = self.conn.lock().unwrap();
+        let mut statement = conn
+            .prepare("SELECT key FROM kv WHERE namespace = ?1 AND SUBSTR(key, 1, LENGTH(?2)) = ?2 ORDER BY key")
+            .map_err(|e| e.to_string())?;
+        let keys = statement
+            .query_map(params![namespace, prefix], |row| row.get(0))
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<String>, _>>()
+            .map_err(|e| e.to_string());
+        keys
+    }
+
+    /// Bytes used by the namespace.
+    pub fn usage(&self, namespace: &str) -> Result<u64, String> {
+        self.conn
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT COALESCE(SUM(LENGTH(CAST(key AS BLOB)) + LENGTH(value)), 0) FROM kv WHERE namespace = ?1",
+                params![namespace],
+                |row| row.get::<_, i64>(0),
+            )
+            .map(|bytes| bytes as u64)
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// Run `f` with the shared store, opened on first use, on a blocking
+/// thread.
+async fn with_store<R: Send + 'static>(
+    f: impl FnOnce(&'static Store) -> Result<R, String> + Send + 'static,
+) -> Result<R, String> {
+    static STORE: OnceLock<Result<Store, String>> = OnceLock::new();
+    tokio::task::spawn_blocking(move || {
+        let store = STORE
+            .get_or_init(|| Store::open(&state_dir().join(FILE_NAME)))
+            .as_ref()
+            .map_err(Clone::clone)?;
+        f(store)
+    })
+    .await
+    .map_err(|e| format!("Key-value store task failed: {}", e))?
+}
+
+#[async_trait::async_trait]
+impl Host for HostState {
+    async fn get(&mut self, key: String) -> Result<Option<Vec<u8>>, String> {
+        let namespace = self.server_id.clone();
+        with_store(move |store| store.get(&namespace, &key)).await
+    }
+
+    async fn set(&mut self, key: String, value: Vec<u8>) -> Result<(), String> {
+        let (namespace, quota) = (self.server_id.clone(), self.kv_quota);
+        with_store(move |store| store.set(&namespace, &key, &value, quota)).await
+    }
+
+    async fn delete(&mut self, key: String) -> Result<bool, String> {
+        let namespace = self.server_id.clone();
+        with_store(move |store| store.delete(&namespace, &key)).await
+    }
+
+    async fn list(&mut self, prefix: String) -> Result<Vec<String>, String> {
+        let namespace = self.server_id.clone();
+        with_store(move |store| store.list(&namespace, &prefix)).await
+    }
+}
+
+// ============================================================================
+// RPC Handlers
+// ============================================================================
+
+#[derive(Debug, Deserialize)]
+struct KeyParams {
+    #[serde(default, alias = "serverId")]
+    server_id: Option<String>,
+    key: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct SetParams {
+    #[serde(default, alias = "serverId")]
+    server_id: Option<String>,
+    key: String,
+    /// Text to store
+    #[serde(default)]
+    value: Option<String>,
+    /// Bytes to store, instead of `value`
+    #[serde(default, alias = "valueBase64")]
+    value_base64: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ListParams {
+    #[serde(default, alias = "serverId")]
+    server_id: Option<String>,
+    #[serde(default)]
+    prefix: String,
+}
+
+fn parse<T: serde::de::DeserializeOwned>(params: serde_json::Value) -> Result<T, RpcError> {
+    serde_json::from_value(params).map_err(|e| RpcError::invalid_params(format!("Invalid params: {}", e)))
+}
+
+/// The namespace a request may use: a server's own, or, for the extension,
+/// the one it names.
+fn namespace(server_id: Option<String>) -> Result<String, RpcError> {
+    match crate::rpc::caller() {
+        Some(caller) if server_id.as_ref().is_some_and(|id| *id != caller) => Err(RpcError::new(
+            -32000,
+            format!("Server '{}' cannot use another server's storage", caller),
+        )),
+        Some(caller) => Ok(caller),
+        None => {
+            crate::rpc::require_extension("use a server's storage")?;
+            server_id.ok_or_else(|| RpcError::invalid_params("Missing 'server_id' parameter"))
+        }
+    }
+}
+
+async fn storage<R: Send + 'static>(
+    f: impl FnOnce(&'static Store) -> Result<R, String> + Send + 'static,
+) -> Result<R, RpcError> {
+    with_store(f).await.map_err(|e| RpcError::new(-32000, e))
+}
+
+/// Read a server's entry: `{ server_id, key }`; a server may leave out
+/// `server_id`. Returns `{ value_base64,
+/// value }`, with `value` the text if it is UTF-8, or nulls if unset.
+pub async fn rpc_get(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    let params: KeyParams = parse(params)?;
+    let namespace = namespace(params.server_id)?;
+    let value = storage(move |store| store.get(&namespace, &params.key)).await?;
+    let text = value.as_ref().and_then(|bytes| String::from_utf8(bytes.clone()).ok());
+    Ok(serde_json::json!({
+        "value_base64": value.map(|bytes| STANDARD.encode(bytes)),
+        "value": text,
+    }))
+}
+
+/// Write a server's entry: `{ server_id, key, value | value_base64 }`,
+/// within the server's quota.
+pub async fn rpc_set(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    let params: SetParams = parse(params)?;
+    let value = match (params.value, params.value_base64) {
+        (Some(text), None) => text.into_bytes(),
+        (None, Some(encoded)) => STANDARD
+            .decode(encoded.trim())
+            .map_err(|e| RpcError::invalid_params(format!("Invalid base64: {}", e)))?,
+        _ => return Err(RpcError::invalid_params("Pass exactly one of 'value' and 'value_base64'")),
+    };
+    let namespace = namespace(params.server_id)?;
+    let quota = super::kv_quota(&namespace).await;
+    storage(move |store| store.set(&namespace, &params.key, &value, quota)).await?;
+    Ok(serde_json::json!({ "ok": true }))
+}
+
+/// Remove a server's entry: `{ server_id, key }`.
+pub async fn rpc_delete(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    let params: KeyParams = parse(params)?;
+    let namespace = namespace(params.server_id)?;
+    let deleted = storage(move |store| store.delete(&namespace, &params.key)).await?;
+    Ok(serde_json::json!({ "deleted": deleted }))
+}
+
+/// List a server's keys: `{ server_id, prefix? }`. Returns `{ keys,
+/// used_bytes, quota_bytes }`.
+pub async fn rpc_list(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    let params: ListParams = parse(params)?;
+    let namespace = namespace(params.server_id)?;
+    let quota = super::kv_quota(&namespace).await;
+    let (keys, used) = storage(move |store| {
+        let keys = store.list(&namespace, &params.prefix)?;
+        Ok((keys, store.usage(&namespace)?))
+    })
+    .await?;
+    Ok(serde_json::json!({
+        "keys": keys,
+        "used_bytes": used,
+        "quota_bytes": quota,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_set_delete_list() {
+        let store = Store::in_memory();
+        assert_eq!(store.get("notes", "a").unwrap(), None);
+
+        store.set("notes", "a", b"1", 100).unwrap();
+        store.set("notes", "ab", b"2", 100).unwrap();
+        store.set("notes", "b", b"3", 100).unwrap();
+        store.set("notes", "a", b"4", 100).unwrap();
+        assert_eq!(store.get("notes", "a").unwrap(), Some(b"4".to_vec()));
+        assert_eq!(store.list("notes", "a").unwrap(), vec!["a", "ab"]);
+        assert_eq!(store.list("notes", "").unwrap().len(), 3);
+
+        assert!(store.delete("notes", "a").unwrap());
+        assert!(!store.delete("notes", "a").unwrap());
+        assert_eq!(store.usage("notes").unwrap(), 5);
+    }
+
+    #[test]
+    fn test_namespaces_are_separate() {
+        let store = Store::in_memory();
+        store.set("notes", "key", b"mine", 100).unwrap();
+        assert_eq!(store.get("weather", "key").unwrap(), None);
+        assert!(store.list("weather", "").unwrap().is_empty());
+        assert!(!store.delete("weather", "key").unwrap());
+    }
+
+    #[test]
+    fn test_quota() {
+        let store = Store::in_memory();
+        store.set("notes", "k", &[0; 9], 10).unwrap();
+        assert!(store.set("notes", "j", &[0], 10).is_err());
+        // Replacing a value counts the new size only
+        store.set("notes", "k", &[0; 8], 10).unwrap();
+        store.set("notes", "j", &[], 10).unwrap();
+        // Another namespace has its own quota
+        store.set("weather", "k", &[0; 9], 10).unwrap();
+        assert!(store.set("notes", "", b"x", 10).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_rpcs_need_the_extension() {
+        assert!(namespace(Some("notes".to_string())).is_err());
+        let named = crate::rpc::as_extension(async { namespace(Some("notes".to_string())) }).await;
+        assert_eq!(named.unwrap(), "notes");
+        assert!(crate::rpc::as_extension(async { namespace(None) }).await.is_err());
+    }
+}
@@ -0,0 +1,474 @@
+//! `harbor.toml`: the manifest shipped next to a component.
+//!
+//! It names the server and declares what it needs from the host, so the
+//! user is asked once, at install, and the bridge holds the server to it
+//! from then on:
+//!
+//! ```toml
+//! name = "notes"
+//! version = "1.0.0"
+//!
+//! [capabilities]
+//! clock = true
+//! locale = true
+//! schedule = true
+//! browser = true
+//! clipboard = true
+//! network = { hosts = ["api.example.com"] }
+//! filesystem = [{ path = "~/Notes", mount = "/notes", write = true }]
+//! oauth = { provider = "google", scopes = ["drive.readonly"] }
+//!
+//! [[tools]]
+//! name = "search_notes"
+//! description = "Search notes by keyword"
+//!
+//! [[secrets]]
+//! name = "NOTES_API_KEY"
+//! description = "Key for the notes service"
+//! ```
+//!
+//! `wasm.read_manifest` parses a manifest and lists what it asks for, to be
+//! shown for consent. A server with a manifest then runs with what it
+//! declared and nothing else: `http` reaches only the declared hosts, only
+//! the declared directories are visible, clocks read zero unless `clock`
+//! is declared, the user's locale is withheld unless `locale` is, the
+//! `schedule`, `browser` and `clipboard` imports fail unless they are, the
+//! `oauth` import hands out tokens only for the declared provider and
+//! scopes, and when tools are declared no other tool is listed or called.
+
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use super::component::Preopen;
+use crate::js::NetworkCapabilities;
+use crate::rpc::RpcError;
+use crate::secrets::SecretDecl;
+
+pub const FILE_NAME: &str = "harbor.toml";
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    pub name: String,
+    pub version: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub capabilities: Declared,
+    #[serde(default)]
+    pub tools: Vec<ToolMeta>,
+    #[serde(default)]
+    pub secrets: Vec<SecretDecl>,
+}
+
+/// What a server asks of the host.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Declared {
+    #[serde(default)]
+    pub network: NetworkDecl,
+    #[serde(default)]
+    pub filesystem: Vec<PathDecl>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub oauth: Option<OAuthDecl>,
+    /// Read the wall and monotonic clocks
+    #[serde(default)]
+    pub clock: bool,
+    /// Know the user's language and time zone
+    #[serde(default)]
+    pub locale: bool,
+    /// Have the bridge call tools on a schedule (see `crate::scheduler`)
+    #[serde(default)]
+    pub schedule: bool,
+    /// See open tabs, read pages, and search bookmarks and history (see
+    /// `crate::browser`)
+    #[serde(default)]
+    pub browser: bool,
+    /// Read and write the text on the OS clipboard
+    #[serde(default)]
+    pub clipboard: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct NetworkDecl {
+    /// Host patterns, as in `allowed_hosts` ("api.example.com", "*.example.com")
+    #[serde(default)]
+    pub hosts: Vec<String>,
+}
+
+/// A host directory made visible to the server.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct PathDecl {
+    /// Absolute, or under the home directory with `~/`
+    pub path: String,
+    /// Where the server sees it; defaults to `/` and the directory's name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mount: Option<String>,
+    #[serde(default)]
+    pub write: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct OAuthDecl {
+    pub provider: String,
+    #[serde(default)]
+    pub scopes: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ToolMeta {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+impl Declared {
+    /// What is asked for, one line each, for the consent prompt. Empty
+    /// when it is nothing beyond running.
+    pub fn consent(&self) -> Vec<String> {
+        let mut lines: Vec<String> = self
+            .network
+            .hosts
+            .iter()
+            .map(|host| format!("Connect to {}", host))
+            .collect();
+        for dir in &self.filesystem {
+            let access = if dir.write { "Read and write" } else { "Read" };
+            lines.push(format!("{} files in {}", access, dir.path));
+        }
+        if let Some(oauth) = &self.oauth {
+            lines.push(format!("Use your {} account ({})", oauth.provider, oauth.scopes.join(", ")));
+        }
+        if self.clock {
+            lines.push("Read the current time".to_string());
+        }
+        if self.locale {
+            lines.push("Know your language and time zone".to_string());
+        }
+        if self.schedule {
+            lines.push("Run tools from any server on a schedule, while you're away".to_string());
+        }
+        if self.browser {
+            lines.push("See your open tabs and read their pages, and search your bookmarks and history".to_string());
+        }
+        if self.clipboard {
+            lines.push("Read and change what's on your clipboard".to_string());
+        }
+        lines
+    }
+}
+
+impl OAuthDecl {
+    /// Check that a provider and the scopes to be used are named.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.provider.is_empty() {
+            return Err("'oauth.provider' may not be empty".to_string());
+        }
+        if self.scopes.is_empty() {
+            return Err("'oauth.scopes' must list the scopes the server uses".to_string());
+        }
+        Ok(())
+    }
+}
+
+impl PathDecl {
+    fn mount(&self) -> String {
+        match &self.mount {
+            Some(mount) => mount.clone(),
+            None => {
+                let name = Path::new(self.path.trim_end_matches('/'))
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                format!("/{}", name)
+            }
+        }
+    }
+
+    fn host_path(&self) -> PathBuf {
+        match self.path.strip_prefix("~/") {
+            Some(rest) => dirs::home_dir().unwrap_or_else(|| PathBuf::from(".")).join(rest),
+            None => PathBuf::from(&self.path),
+        }
+    }
+}
+
+impl Manifest {
+    /// Parse and check a manifest.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let manifest: Manifest = toml::from_str(text).map_err(|e| format!("Invalid {}: {}", FILE_NAME, e))?;
+        manifest.validate()?;
+        Ok(manifest)
+    }
+
+    /// The manifest in the same directory as the component at `module`, if
+    /// there is one.
+    pub fn beside(module: &Path) -> Result<Option<Self>, String> {
+        let path = module.with_file_name(FILE_NAME);
+        match std::fs::read_to_string(&path) {
+            Ok(text) => Self::parse(&text).map(Some),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(format!("Failed to read '{}': {}", path.display(), e)),
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let valid_name = !self.name.is_empty()
+            && self
+                .name
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_');
+        if !valid_name {
+            return Err(format!("'name' must be lowercase letters, digits, '-' and '_': '{}'", self.name));
+        }
+        if self.version.trim().is_empty() {
+            return Err("'version' may not be empty".to_string());
+        }
+        for host in &self.capabilities.network.hosts {
+            if host.is_empty() || host.contains("://") || host.contains('/') {
+                return Err(format!("Network host must be a host name or pattern: '{}'", host));
+            }
+        }
+        let mut mounts = BTreeSet::new();
+        for dir in &self.capabilities.filesystem {
+            if !(dir.path.starts_with('/') || dir.path.starts_with("~/")) {
+                return Err(format!("Filesystem path must be absolute or start with '~/': '{}'", dir.path));
+            }
+            let mount = dir.mount();
+            if !mount.starts_with('/') || mount == "/" {
+                return Err(format!("Mount point must be an absolute path below '/': '{}'", mount));
+            }
+            if !mounts.insert(mount.clone()) {
+                return Err(format!("Mount point '{}' is declared twice", mount));
+            }
+        }
+        if let Some(oauth) = &self.capabilities.oauth {
+            oauth.validate()?;
+        }
+        let mut secrets = BTreeSet::new();
+        for secret in &self.secrets {
+            secret.validate()?;
+            if !secrets.insert(secret.name.as_str()) {
+                return Err(format!("Secret '{}' is declared twice", secret.name));
+            }
+        }
+        let mut tools = BTreeSet::new();
+        for tool in &self.tools {
+            if !tools.insert(tool.name.as_str()) {
+                return Err(format!("Tool '{}' is declared twice", tool.name));
+            }
+        }
+        Ok(())
+    }
+
+    /// What the server asks for, one line each, for the consent prompt.
+    /// Empty when it asks for nothing beyond running.
+    pub fn consent(&self) -> Vec<String> {
+        let mut lines = self.capabilities.consent();
+        lines.extend(secrets_consent(&self.secrets));
+        lines
+    }
+
+    /// The network access to grant: the declared hosts, or the subset of
+    /// them in `requested` if the caller narrows it.
+    pub fn grant_network(&self, requested: &NetworkCapabilities) -> Result<NetworkCapabilities, String> {
+        let declared = &self.capabilities.network.hosts;
+        if requested.allowed_hosts.is_empty() {
+            return Ok(NetworkCapabilities {
+                allowed_hosts: declared.clone(),
+            });
+        }
+        if let Some(host) = requested.allowed_hosts.iter().find(|host| !declared.contains(host)) {
+            return Err(format!("Host '{}' is not declared in the server's {}", host, FILE_NAME));
+        }
+        Ok(requested.clone())
+    }
+
+    /// The declared directories, as preopens.
+    pub fn preopens(&self) -> Vec<Preopen> {
+        self.capabilities
+            .filesystem
+            .iter()
+            .map(|dir| Preopen {
+                host: dir.host_path(),
+                guest: dir.mount(),
+                write: dir.write,
+            })
+            .collect()
+    }
+
+    /// Whether the server may expose `tool`. A manifest that declares no
+    /// tools doesn't restrict them.
+    pub fn allows_tool(&self, tool: &str) -> bool {
+        self.tools.is_empty() || self.tools.iter().any(|t| t.name == tool)
+    }
+}
+
+/// The consent lines for handing a server `secrets`, one each.
+pub fn secrets_consent(secrets: &[SecretDecl]) -> Vec<String> {
+    secrets
+        .iter()
+        .map(|secret| match &secret.description {
+            Some(description) => format!("Use your secret {} ({})", secret.name, description),
+            None => format!("Use your secret {}", secret.name),
+        })
+        .collect()
+}
+
+/// Parse a manifest for review before install: `{ path }` with the
+/// component's path (the manifest is read from beside it) or `{ text }`
+/// with the manifest itself. Returns `{ manifest, consent }`, where
+/// `consent` lists what the server asks for.
+pub async fn read_manifest(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    crate::rpc::require_extension("read server manifests")?;
+    let text = params.get("text").and_then(|v| v.as_str());
+    let path = params.get("path").and_then(|v| v.as_str());
+    let manifest = match (text, path) {
+        (Some(text), None) => Manifest::parse(text).map_err(RpcError::invalid_params)?,
+        (None, Some(path)) => Manifest::beside(Path::new(path))
+            .map_err(|e| RpcError::new(-32000, e))?
+            .ok_or_else(|| RpcError::new(-32000, format!("No {} beside '{}'", FILE_NAME, path)))?,
+        _ => return Err(RpcError::invalid_params("Pass exactly one of 'text' and 'path'")),
+    };
+    let consent = manifest.consent();
+    Ok(serde_json::json!({ "manifest": manifest, "consent": consent }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOTES: &str = r#"
+name = "notes"
+version = "1.0.0"
+
+[capabilities]
+clock = true
+network = { hosts = ["api.example.com", "*.cdn.example.com"] }
+filesystem = [{ path = "~/Notes", write = true }, { path = "/srv/shared", mount = "/shared" }]
+oauth = { provider = "google", scopes = ["drive.readonly"] }
+
+[[tools]]
+name = "search_notes"
+description = "Search notes by keyword"
+
+[[secrets]]
+name = "NOTES_API_KEY"
+required = false
+"#;
+
+    #[test]
+    fn test_parse_and_consent() {
+        let manifest = Manifest::parse(NOTES).unwrap();
+        assert_eq!(manifest.name, "notes");
+        assert!(!manifest.secrets[0].required);
+        assert_eq!(
+            manifest.consent(),
+            vec![
+                "Connect to api.example.com",
+                "Connect to *.cdn.example.com",
+                "Read and write files in ~/Notes",
+                "Read files in /srv/shared",
+                "Use your google account (drive.readonly)",
+                "Read the current time",
+                "Use your secret NOTES_API_KEY",
+            ]
+        );
+
+        let preopens = manifest.preopens();
+        assert_eq!(preopens[0].guest, "/Notes");
+        assert!(preopens[0].write);
+        assert!(preopens[0].host.ends_with("Notes"));
+        assert_eq!(preopens[1].guest, "/shared");
+        assert!(!preopens[1].write);
+
+        let bare = Manifest::parse("name = \"echo\"\nversion = \"0.1.0\"").unwrap();
+        assert!(bare.consent().is_empty());
+        assert!(bare.preopens().is_empty());
+    }
+
+    #[test]
+    fn test_locale_consent() {
+        let manifest = Manifest::parse("name = \"clock\"\nversion = \"1.0.0\"\n[capabilities]\nlocale = true\n").unwrap();
+        assert!(manifest.capabilities.locale);
+        assert_eq!(manifest.consent(), vec!["Know your language and time zone"]);
+    }
+
+    #[test]
+    fn test_schedule_consent() {
+        let manifest = Manifest::parse("name = \"cron\"\nversion = \"1.0.0\"\n[capabilities]\nschedule = true\n").unwrap();
+        assert!(manifest.capabilities.schedule);
+        assert_eq!(manifest.consent(), vec!["Run tools from any server on a schedule, while you're away"]);
+    }
+
+    #[test]
+    fn test_browser_consent() {
+        let manifest = Manifest::parse("name = \"tabs\"\nversion = \"1.0.0\"\n[capabilities]\nbrowser = true\n").unwrap();
+        assert!(manifest.capabilities.browser);
+        assert_eq!(
+            manifest.consent(),
+            vec!["See your open tabs and read their pages, and search your bookmarks and history"]
+        );
+    }
+
+    #[test]
+    fn test_clipboard_consent() {
+        let manifest = Manifest::parse("name = \"clip\"\nversion = \"1.0.0\"\n[capabilities]\nclipboard = true\n").unwrap();
+        assert!(manifest.capabilities.clipboard);
+        assert_eq!(manifest.consent(), vec!["Read and change what's on your clipboard"]);
+    }
+
+    #[test]
+    fn test_invalid_manifests() {
+        assert!(Manifest::parse("name = \"Notes\"\nversion = \"1.0.0\"").is_err());
+        assert!(Manifest::parse("name = \"notes\"\nversion = \"1.0.0\"\nextra = 1").is_err());
+        assert!(Manifest::parse(
+            "name = \"notes\"\nversion = \"1.0.0\"\n[capabilities]\nnetwork = { hosts = [\"https://api.example.com\"] }"
+        )
+        .is_err());
+        assert!(Manifest::parse(
+            "name = \"notes\"\nversion = \"1.0.0\"\n[capabilities]\nfilesystem = [{ path = \"Notes\" }]"
+        )
+        .is_err());
+        assert!(Manifest::parse(
+            "name = \"notes\"\nversion = \"1.0.0\"\n[capabilities]\noauth = { provider = \"google\" }"
+        )
+        .is_err());
+        assert!(Manifest::parse(
+            "name = \"notes\"\nversion = \"1.0.0\"\n[capabilities]\nfilesystem = [{ path = \"/a/x\" }, { path = \"/b/x\" }]"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_grant_network() {
+        let manifest = Manifest::parse(NOTES).unwrap();
+        let granted = manifest.grant_network(&NetworkCapabilities::default()).unwrap();
+        assert_eq!(granted.allowed_hosts.len(), 2);
+
+        let narrowed = NetworkCapabilities {
+            allowed_hosts: vec!["api.example.com".to_string()],
+        };
+        assert_eq!(manifest.grant_network(&narrowed).unwrap().allowed_hosts, narrowed.allowed_hosts);
+
+        let wider = NetworkCapabilities {
+            allowed_hosts: vec!["evil.example.org".to_string()],
+        };
+        assert!(manifest.grant_network(&wider).is_err());
+    }
+
+    #[test]
+    fn test_allows_tool() {
+        let manifest = Manifest::parse(NOTES).unwrap();
+        assert!(manifest.allows_tool("search_notes"));
+        assert!(!manifest.allows_tool("delete_notes"));
+        let bare = Manifest::parse("name = \"echo\"\nversion = \"0.1.0\"").unwrap();
+        assert!(bare.allows_tool("anything"));
+    }
+}
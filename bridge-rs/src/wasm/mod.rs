@@ -0,0 +1,996 @@
+//! WASM MCP servers built as components (WASI preview 2).
+//!
+//! Core WASI preview 1 modules speak JSON-RPC over stdio and run in the
+//! extension. Components implementing the `harbor:mcp` world
+//! (`mcp-servers/wit/harbor-mcp.wit`) run here instead, on wasmtime: the
+//! extension hands over the component bytes with `wasm.start_server` and
+//! forwards requests with `wasm.call`, as it does for JS servers.
+//! Components reach the network only through the `http` import, limited to
+//! the hosts passed in `capabilities` at start. A component can ship a
+//! `harbor.toml` declaring what it needs, which then bounds what it gets
+//! (see `manifest`). Requests are passed through as they are, except that
+//! a server is only sent the optional parts of MCP (resources, prompts) if it
+//! advertised them in its `initialize` answer; otherwise the bridge
+//! answers `-32601` itself. A component may write notifications (log
+//! lines, `notifications/tools/list_changed`) ahead of its response, one
+//! message per line; they are published as `wasm/notification` events and,
+//! if asked for, returned with the response. What a component writes to
+//! stderr is recorded line by line beside its log messages, for
+//! `servers.logs` (see `crate::server_logs`).
+//!
+//! Each server has a pool of instances (one by default; see `pool`), each
+//! handling one request at a time. An instance that traps or times out is
+//! dropped and a fresh one is created for the next request. Compiled
+//! components are cached on disk (see `cache`), so only the first start of
+//! a component pays for compilation. State that should outlive an instance
+//! goes in the per-server key-value store (see `kv`). During development a server can be
+//! started from a file and reloaded as it is rebuilt (see `reload`). A
+//! server granted `schedule` can have the bridge call tools on a schedule
+//! (see `schedule` and `crate::scheduler`), and one granted `oauth` gets
+//! access tokens for the account the user connected to it (see `oauth`).
+//! One granted `browser` can see the user's tabs, bookmarks and history,
+//! which the extension answers for (see `crate::browser`), and one granted
+//! `clipboard` can read and write the OS clipboard (see `clipboard`).
+//! Any server can call other servers' tools through the `peer` import,
+//! as far as the user's peer policy allows (see `crate::peer`).
+//! Components started from bytes have their package signature checked
+//! first, under the trust store's policy (see `crate::signing`), and
+//! whatever a server gets beyond running must have been approved by the
+//! user for that very module (see `approval`).
+//!
+//! Every server runs under limits, set per server at start: a cap on linear
+//! memory, fuel for each request (so a module spinning the CPU runs out
+//! rather than running forever) and a timeout. Exceeding one fails the
+//! request with a `WASM_*` error code; the bridge carries on. Tool calls
+//! are also held to the per-call tool timeout (see `mcp::timeout`), which
+//! fails them with `TOOL_TIMEOUT`.
+
+mod approval;
+mod browser;
+mod cache;
+mod clipboard;
+mod component;
+mod http;
+mod kv;
+mod manifest;
+mod oauth;
+mod peer;
+mod pool;
+mod reload;
+mod schedule;
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+use wasmtime::component::Component;
+
+use crate::js::Capabilities;
+use crate::mcp::{pause, timeout};
+use crate::rpc::RpcError;
+use crate::secrets::SecretDecl;
+use crate::server_logs::{self, Stream};
+use crate::store::JsonStore;
+use component::{CallError, HostConfig, Instance, Limits, Locale, Preopen};
+use manifest::{Declared, Manifest, NetworkDecl, OAuthDecl, PathDecl};
+pub use approval::approve;
+pub use kv::{rpc_delete as kv_delete, rpc_get as kv_get, rpc_list as kv_list, rpc_set as kv_set};
+pub use manifest::read_manifest;
+use pool::{Pool, PoolConfig, PoolParams, PoolStatus};
+
+/// Error code for a request that ran out of fuel.
+pub const WASM_FUEL_EXHAUSTED: i64 = -32050;
+
+/// Error code for a request that exceeded the server's memory limit.
+pub const WASM_MEMORY_EXCEEDED: i64 = -32051;
+
+/// Error code for a request that didn't finish in time.
+pub const WASM_TIMEOUT: i64 = -32052;
+
+/// Error code for a start that grants what the user hasn't approved.
+pub const WASM_APPROVAL_REQUIRED: i64 = -32053;
+
+/// Upper bounds for per-server limits.
+const MAX_MEMORY_MB: u64 = 4096;
+const MAX_FUEL: u64 = 1_000_000_000_000;
+const MAX_TIMEOUT_MS: u64 = 300_000;
+const MAX_KV_MB: u64 = 1024;
+
+/// Where a server started with `files` sees the file sandbox.
+const FILES_MOUNT: &str = "/files";
+
+struct Server {
+    component: Component,
+    config: HostConfig,
+    /// Instances, created as needed and again after a failure
+    pool: Pool<Instance>,
+    /// What the server declared, as approved at start
+    manifest: Option<Arc<Manifest>>,
+    /// The capabilities the server answered `initialize` with, once it has
+    capabilities: std::sync::RwLock<Option<serde_json::Value>>,
+}
+
+/// WASM component subsystem state.
+#[derive(Default)]
+pub struct WasmState {
+    servers: RwLock<HashMap<String, Arc<Server>>>,
+    /// File watches of servers started with `watch` (see `reload`)
+    watches: std::sync::Mutex<HashMap<String, reload::Reloading>>,
+    /// What the user approved each server to have (see `approval`)
+    approvals: JsonStore<approval::Approvals>,
+}
+
+fn state() -> &'static WasmState {
+    &crate::state::get().wasm
+}
+
+#[derive(Debug, Deserialize)]
+struct StartServerParams {
+    id: String,
+    /// The component, base64-encoded
+    #[serde(default, alias = "wasmBase64", alias = "moduleBytesBase64")]
+    wasm_base64: Option<String>,
+    /// The component's file, instead of `wasm_base64`
+    #[serde(default)]
+    path: Option<PathBuf>,
+    /// Reload the server when `path` changes
+    #[serde(default)]
+    watch: bool,
+    #[serde(default)]
+    env: HashMap<String, String>,
+    /// Hosts the server may reach: `{ network: { allowed_hosts } }`
+    #[serde(default)]
+    capabilities: Capabilities,
+    /// Secrets to hand the server, for one without a manifest; see `secrets`
+    #[serde(default)]
+    secrets: Vec<SecretDecl>,
+    /// Seed for a fixed random sequence instead of OS randomness, to make
+    /// runs reproducible
+    #[serde(default, alias = "randomSeed")]
+    random_seed: Option<u64>,
+    /// The user's language and time zone, for a server granted them
+    #[serde(default)]
+    locale: Option<Locale>,
+    /// Show a server without a manifest the files it has been granted,
+    /// read-only under `FILES_MOUNT` (see `files_preopens`)
+    #[serde(default)]
+    files: bool,
+    /// Let a server without a manifest create schedules (see
+    /// `crate::scheduler`)
+    #[serde(default)]
+    schedule: bool,
+    /// OAuth provider and scopes a server without a manifest may get
+    /// tokens for: `{ provider, scopes }`
+    #[serde(default)]
+    oauth: Option<OAuthDecl>,
+    /// Let a server without a manifest ask the extension about tabs,
+    /// bookmarks and history (see `crate::browser`)
+    #[serde(default)]
+    browser: bool,
+    /// Let a server without a manifest read and write the OS clipboard
+    #[serde(default)]
+    clipboard: bool,
+    /// The server's `harbor.toml`, when it isn't beside `path`
+    #[serde(default)]
+    manifest: Option<String>,
+    /// The package manifest the component was installed with, whose
+    /// `signature` is checked against the trust store (see `signing`)
+    #[serde(default)]
+    package: Option<serde_json::Value>,
+    #[serde(default)]
+    limits: LimitParams,
+    #[serde(default)]
+    pool: PoolParams,
+}
+
+/// Per-server limits; omitted fields use the defaults.
+#[derive(Debug, Default, Deserialize)]
+struct LimitParams {
+    #[serde(alias = "memoryMb")]
+    memory_mb: Option<u64>,
+    fuel: Option<u64>,
+    #[serde(alias = "timeoutMs")]
+    timeout_ms: Option<u64>,
+    #[serde(alias = "kvMb")]
+    kv_mb: Option<u64>,
+}
+
+impl LimitParams {
+    fn resolve(&self) -> Result<Limits, String> {
+        let defaults = Limits::default();
+        let check = |name: &str, value: Option<u64>, max: u64| match value {
+            Some(0) => Err(format!("'limits.{}' must be positive", name)),
+            Some(v) if v > max => Err(format!("'limits.{}' may be at most {}", name, max)),
+            _ => Ok(value),
+        };
+        Ok(Limits {
+            memory_bytes: check("memory_mb", self.memory_mb, MAX_MEMORY_MB)?
+                .map(|mb| (mb * 1024 * 1024) as usize)
+                .unwrap_or(defaults.memory_bytes),
+            fuel: check("fuel", self.fuel, MAX_FUEL)?.unwrap_or(defaults.fuel),
+            timeout: check("timeout_ms", self.timeout_ms, MAX_TIMEOUT_MS)?
+                .map(Duration::from_millis)
+                .unwrap_or(defaults.timeout),
+            kv_bytes: check("kv_mb", self.kv_mb, MAX_KV_MB)?
+                .map(|mb| mb * 1024 * 1024)
+                .unwrap_or(defaults.kv_bytes),
+        })
+    }
+}
+
+impl From<CallError> for RpcError {
+    fn from(e: CallError) -> Self {
+        let code = match e {
+            CallError::OutOfFuel { .. } => WASM_FUEL_EXHAUSTED,
+            CallError::MemoryLimit { .. } => WASM_MEMORY_EXCEEDED,
+            CallError::Timeout(_) => WASM_TIMEOUT,
+            CallError::Trap(_) => -32000,
+        };
+        RpcError::new(code, e.to_string())
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct ServerInfo {
+    id: String,
+    running: bool,
+    /// Running, but not taking tool calls (see `mcp::pause`)
+    paused: bool,
+    instances: PoolStatus,
+    /// From the server's `initialize` answer; absent until it is initialized
+    #[serde(skip_serializing_if = "Option::is_none")]
+    capabilities: Option<serde_json::Value>,
+}
+
+/// How often idle instances are checked for eviction.
+const EVICT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Start evicting idle instances. Must be called from within the tokio
+/// runtime.
+pub fn start() {
+    tokio::spawn(async {
+        let mut interval = tokio::time::interval(EVICT_INTERVAL);
+        loop {
+            interval.tick().await;
+            let servers: Vec<(String, Arc<Server>)> = state()
+                .servers
+                .read()
+                .await
+                .iter()
+                .map(|(id, server)| (id.clone(), server.clone()))
+                .collect();
+            for (id, server) in servers {
+                let evicted = server.pool.evict_idle();
+                if evicted > 0 {
+                    tracing::debug!("[WASM:{}] Evicted {} idle instance(s)", id, evicted);
+                }
+            }
+        }
+    });
+}
+
+/// The `kv` quota of a server: its own if running, otherwise the default.
+async fn kv_quota(server_id: &str) -> u64 {
+    state()
+        .servers
+        .read()
+        .await
+        .get(server_id)
+        .map(|server| server.config.limits.kv_bytes)
+        .unwrap_or(kv::DEFAULT_QUOTA_BYTES)
+}
+
+impl Server {
+    /// Whether the server advertised `capability` at initialize; assumed
+    /// until it has been initialized.
+    fn advertises(&self, capability: &str) -> bool {
+        match &*self.capabilities.read().unwrap() {
+            Some(capabilities) => capabilities.get(capability).is_some(),
+            None => true,
+        }
+    }
+}
+
+/// The capability a server must advertise to be sent `method`, for the
+/// optional parts of MCP.
+fn capability_of(method: &str) -> Option<&'static str> {
+    match method.split_once('/') {
+        Some(("resources", _)) => Some("resources"),
+        Some(("prompts", _)) => Some("prompts"),
+        _ => None,
+    }
+}
+
+/// The JSON-RPC answer to a request for a capability the server lacks.
+fn not_advertised(request: &serde_json::Value, server_id: &str, capability: &str) -> serde_json::Value {
+    serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": request.get("id").cloned().unwrap_or(serde_json::Value::Null),
+        "error": {
+            "code": -32601,
+            "message": format!("Server '{}' does not provide {}", server_id, capability),
+        },
+    })
+}
+
+/// Whether a component server with this ID is running.
+pub async fn is_running(server_id: &str) -> bool {
+    state().servers.read().await.contains_key(server_id)
+}
+
+/// A component's answer to one request.
+struct Exchange {
+    response: serde_json::Value,
+    /// Notifications the component sent while handling the request
+    notifications: Vec<serde_json::Value>,
+}
+
+/// Split a component's output, one message per line, into its response
+/// (the message with an `id`; `Null` if there is none) and notifications.
+fn parse_output(output: &str) -> Result<Exchange, String> {
+    let mut exchange = Exchange {
+        response: serde_json::Value::Null,
+        notifications: Vec::new(),
+    };
+    for line in output.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        let message: serde_json::Value =
+            serde_json::from_str(line).map_err(|e| format!("Invalid response from WASM server: {}", e))?;
+        if message.get("id").is_some() {
+            exchange.response = message;
+        } else {
+            exchange.notifications.push(message);
+        }
+    }
+    Ok(exchange)
+}
+
+/// Log and publish a component's notifications as `wasm/notification`.
+/// Log messages are also recorded for `servers.logs`.
+fn publish(server_id: &str, notifications: &[serde_json::Value]) {
+    for notification in notifications {
+        let method = notification.get("method").and_then(|m| m.as_str()).unwrap_or_default();
+        if method == "notifications/message" {
+            let params = notification.get("params");
+            let level = params.and_then(|p| p.get("level")).and_then(|l| l.as_str()).unwrap_or("info");
+            let data = params.and_then(|p| p.get("data")).cloned().unwrap_or_default();
+            server_logs::record(server_id, Stream::Log, Some(level), &server_logs::message_text(&data));
+        }
+        crate::events::emit(
+            "wasm/notification",
+            serde_json::json!({ "server_id": server_id, "notification": notification }),
+        );
+    }
+}
+
+/// Send one MCP request to a running component and return its response.
+pub async fn call(server_id: &str, request: &serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    exchange(server_id, request).await.map(|exchange| exchange.response)
+}
+
+/// Send one MCP request to a running component, returning its response and
+/// the notifications it sent along the way.
+async fn exchange(server_id: &str, request: &serde_json::Value) -> Result<Exchange, RpcError> {
+    exchange_with(running(server_id).await?, server_id, request).await
+}
+
+/// The running server with this ID.
+async fn running(server_id: &str) -> Result<Arc<Server>, RpcError> {
+    state()
+        .servers
+        .read()
+        .await
+        .get(server_id)
+        .cloned()
+        .ok_or_else(|| RpcError::new(-32000, format!("WASM server '{}' is not running", server_id)))
+}
+
+/// `exchange`, starting with `server`. A request still waiting for an
+/// instance when the server is reloaded goes to the new build.
+async fn exchange_with(
+    mut server: Arc<Server>,
+    server_id: &str,
+    request: &serde_json::Value,
+) -> Result<Exchange, RpcError> {
+    let mut message = request.clone();
+    if message.get("jsonrpc").is_none() {
+        message["jsonrpc"] = serde_json::json!("2.0");
+    }
+    if message.get("id").is_none() {
+        message["id"] = serde_json::json!(1);
+    }
+    let method = message.get("method").and_then(|m| m.as_str());
+    if let (Some(manifest), Some("tools/call")) = (&server.manifest, method) {
+        let tool = message.pointer("/params/name").and_then(|n| n.as_str()).unwrap_or_default();
+        if !manifest.allows_tool(tool) {
+            return Err(RpcError::new(
+                -32000,
+                format!("Tool '{}' is not declared in {}'s {}", tool, server_id, manifest::FILE_NAME),
+            ));
+        }
+    }
+    // A server that didn't advertise a capability at initialize isn't
+    // asked for it
+    if let Some(capability) = method.and_then(capability_of) {
+        if !server.advertises(capability) {
+            return Ok(Exchange {
+                response: not_advertised(&message, server_id, capability),
+                notifications: Vec::new(),
+            });
+        }
+    }
+    let lists_tools = method == Some("tools/list");
+    let initializes = method == Some("initialize");
+    let text = serde_json::to_string(&message).map_err(|e| RpcError::internal(e.to_string()))?;
+
+    let output = loop {
+        let current = server.clone();
+        let lease = current
+            .pool
+            .checkout(|| Instance::new(&current.component, &current.config))
+            .await
+            .map_err(|e| RpcError::new(-32000, e))?;
+        // Drained: the server was replaced while the request waited
+        let Some(mut lease) = lease else {
+            server = running(server_id).await?;
+            continue;
+        };
+        // Only returned to the pool if the call succeeds
+        let output = match lease.instance().handle(&text).await {
+            Ok(output) => output,
+            Err(e) => {
+                tracing::warn!("[WASM:{}] {}; the instance will be recreated", server_id, e);
+                return Err(e.into());
+            }
+        };
+        lease.release();
+        break output;
+    };
+
+    let mut exchange = parse_output(&output).map_err(|e| RpcError::new(-32000, e))?;
+    publish(server_id, &exchange.notifications);
+    let response = &mut exchange.response;
+    if initializes {
+        if let Some(capabilities) = response.pointer("/result/capabilities") {
+            *server.capabilities.write().unwrap() = Some(capabilities.clone());
+        }
+    }
+    if let (Some(manifest), true) = (&server.manifest, lists_tools) {
+        if let Some(tools) = response.pointer_mut("/result/tools").and_then(|t| t.as_array_mut()) {
+            tools.retain(|tool| {
+                let name = tool.get("name").and_then(|n| n.as_str());
+                name.map(|name| manifest.allows_tool(name)).unwrap_or(false)
+            });
+        }
+    }
+    Ok(exchange)
+}
+
+// ============================================================================
+// RPC Handlers
+// ============================================================================
+
+/// Compile `bytes` and instantiate its first instances.
+async fn load_server(
+    bytes: Vec<u8>,
+    config: HostConfig,
+    pool: PoolConfig,
+    manifest: Option<Arc<Manifest>>,
+) -> Result<Server, String> {
+    let component = tokio::task::spawn_blocking(move || component::compile(&bytes))
+        .await
+        .map_err(|e| format!("Compile task failed: {}", e))??;
+    // Instantiate now so a broken component fails here rather than on first call
+    let mut warm = Vec::new();
+    for _ in 0..pool.min_idle.max(1) {
+        warm.push(Instance::new(&component, &config).await?);
+    }
+    Ok(Server {
+        component,
+        config,
+        pool: Pool::new(pool, warm),
+        manifest,
+        capabilities: Default::default(),
+    })
+}
+
+/// Replace a running server with a new build from `path`, keeping its
+/// configuration and approved manifest; a changed `harbor.toml` takes
+/// effect on the next start. The new build must answer `initialize` and
+/// `tools/list` before it takes over, and its tools are returned. Requests
+/// already running finish on the old build; those waiting for an instance
+/// move to the new one.
+async fn reload(id: &str, path: &Path) -> Result<serde_json::Value, String> {
+    let old = state()
+        .servers
+        .read()
+        .await
+        .get(id)
+        .cloned()
+        .ok_or_else(|| format!("WASM server '{}' is not running", id))?;
+    let bytes = tokio::fs::read(path)
+        .await
+        .map_err(|e| format!("Failed to read '{}': {}", path.display(), e))?;
+    let server = load_server(bytes, old.config.clone(), old.pool.config(), old.manifest.clone()).await?;
+    let server = Arc::new(server);
+    let tools = reload::handshake(id, &server).await?;
+
+    {
+        let mut servers = state().servers.write().await;
+        // Stopped or replaced meanwhile
+        if !servers.get(id).map(|current| Arc::ptr_eq(current, &old)).unwrap_or(false) {
+            return Err(format!("WASM server '{}' was stopped or replaced during reload", id));
+        }
+        servers.insert(id.to_string(), server);
+    }
+    old.pool.drain().await;
+    tracing::info!("Reloaded WASM component server: {}", id);
+    Ok(tools)
+}
+
+/// A start, checked and configured but not yet run.
+struct Prepared {
+    bytes: Vec<u8>,
+    /// The component, as approvals name it (see `approval::module_id`)
+    module: String,
+    config: HostConfig,
+    pool: PoolConfig,
+    manifest: Option<Manifest>,
+    /// The secrets to read from the store once the start is approved
+    secrets: Vec<SecretDecl>,
+    /// What the start grants, for approval
+    consent: Vec<String>,
+    signature_warning: Option<String>,
+}
+
+/// Check that a start grants no more than the package's manifest asks for,
+/// so that what its signature covers bounds what the server gets.
+fn within_package(params: &StartServerParams, package: &serde_json::Value) -> Result<(), String> {
+    let strings = |pointer: &str| -> Vec<String> {
+        let values = package.pointer(pointer).and_then(|v| v.as_array());
+        values.into_iter().flatten().filter_map(|v| v.as_str().map(str::to_string)).collect()
+    };
+    let flags = [
+        ("files", params.files),
+        ("schedule", params.schedule),
+        ("browser", params.browser),
+        ("clipboard", params.clipboard),
+        ("locale", params.locale.is_some()),
+    ];
+    for (flag, requested) in flags {
+        let declared = package.pointer(&format!("/capabilities/{}", flag)).and_then(|v| v.as_bool());
+        if requested && declared != Some(true) {
+            return Err(format!("The package doesn't ask for 'capabilities.{}'", flag));
+        }
+    }
+    let hosts = strings("/capabilities/network/hosts");
+    if let Some(host) = params.capabilities.network.allowed_hosts.iter().find(|host| !hosts.contains(host)) {
+        return Err(format!("The package doesn't ask to connect to '{}'", host));
+    }
+    if let Some(oauth) = &params.oauth {
+        let provider = package.pointer("/oauth/provider").and_then(|v| v.as_str());
+        let scopes = strings("/oauth/scopes");
+        if provider != Some(oauth.provider.as_str()) || oauth.scopes.iter().any(|scope| !scopes.contains(scope)) {
+            return Err(format!("The package doesn't ask for {} with these scopes", oauth.provider));
+        }
+    }
+    let secrets: Vec<&str> = package
+        .get("declaredSecrets")
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|decl| decl.get("name").and_then(|n| n.as_str()))
+        .collect();
+    if let Some(secret) = params.secrets.iter().find(|secret| !secrets.contains(&secret.name.as_str())) {
+        return Err(format!("The package doesn't ask for the secret {}", secret.name));
+    }
+    Ok(())
+}
+
+/// The directories a server started with `files` sees: its jail at
+/// `FILES_MOUNT`, or each directory it holds a grant for at the same path
+/// below `FILES_MOUNT`. Always read-only.
+async fn files_preopens(server_id: &str) -> Result<Vec<Preopen>, RpcError> {
+    let dirs = crate::fs::readable_dirs(server_id).await?;
+    Ok(dirs
+        .into_iter()
+        .map(|(host, path)| Preopen {
+            host,
+            guest: match path.trim_matches('/') {
+                "" => FILES_MOUNT.to_string(),
+                path => format!("{}/{}", FILES_MOUNT, path),
+            },
+            write: false,
+        })
+        .collect())
+}
+
+/// What `config` and `secrets` grant, as a manifest would declare it, for
+/// the consent lines. Servers without a manifest always have the clock.
+fn granted(config: &HostConfig, has_manifest: bool, secrets: &[SecretDecl]) -> Vec<String> {
+    let declared = Declared {
+        network: NetworkDecl {
+            hosts: config.network.allowed_hosts.clone(),
+        },
+        filesystem: config
+            .preopens
+            .iter()
+            .map(|preopen| PathDecl {
+                path: preopen.host.display().to_string(),
+                mount: Some(preopen.guest.clone()),
+                write: preopen.write,
+            })
+            .collect(),
+        oauth: config.oauth.clone(),
+        clock: has_manifest && !config.frozen_clock,
+        locale: config.locale.is_some(),
+        schedule: config.schedule,
+        browser: config.browser,
+        clipboard: config.clipboard,
+    };
+    let mut consent = declared.consent();
+    consent.extend(manifest::secrets_consent(secrets));
+    consent
+}
+
+/// Read the component and work out how it would run, without running it.
+async fn prepare(params: &StartServerParams) -> Result<Prepared, RpcError> {
+    let limits = params.limits.resolve().map_err(RpcError::invalid_params)?;
+    let pool = params.pool.resolve().map_err(RpcError::invalid_params)?;
+    let bytes = match (&params.wasm_base64, &params.path) {
+        (Some(encoded), None) => STANDARD
+            .decode(encoded.trim())
+            .map_err(|e| RpcError::invalid_params(format!("Invalid base64: {}", e)))?,
+        (None, Some(path)) => tokio::fs::read(path)
+            .await
+            .map_err(|e| RpcError::new(-32000, format!("Failed to read '{}': {}", path.display(), e)))?,
+        _ => return Err(RpcError::invalid_params("Pass exactly one of 'wasm_base64' and 'path'")),
+    };
+    if params.watch && params.path.is_none() {
+        return Err(RpcError::invalid_params("'watch' needs a 'path'"));
+    }
+    if let Some(oauth) = &params.oauth {
+        oauth.validate().map_err(RpcError::invalid_params)?;
+    }
+    // Local builds started from a path aren't packages and can't be signed
+    let signature_warning = match &params.path {
+        Some(path) => {
+            crate::signing::check_local(path).await?;
+            None
+        }
+        None => {
+            let package = params.package.clone().unwrap_or_else(|| serde_json::json!({}));
+            let warning = crate::signing::check(&bytes, &package).await?.1;
+            if params.package.is_some() {
+                within_package(params, &package).map_err(RpcError::invalid_params)?;
+            }
+            warning
+        }
+    };
+
+    let manifest = match (&params.manifest, &params.path) {
+        (Some(text), _) => Some(Manifest::parse(text).map_err(RpcError::invalid_params)?),
+        (None, Some(path)) => Manifest::beside(path).map_err(|e| RpcError::new(-32000, e))?,
+        (None, None) => None,
+    };
+
+    let mut config = HostConfig {
+        server_id: params.id.clone(),
+        env: params.env.clone(),
+        network: params.capabilities.network.clone(),
+        limits,
+        random_seed: params.random_seed,
+        locale: params.locale.clone(),
+        schedule: params.schedule,
+        oauth: params.oauth.clone(),
+        browser: params.browser,
+        clipboard: params.clipboard,
+        ..Default::default()
+    };
+    if params.files {
+        if manifest.is_some() {
+            return Err(RpcError::invalid_params(format!(
+                "'files' is for servers without a manifest; declare 'capabilities.filesystem' in {} instead",
+                manifest::FILE_NAME
+            )));
+        }
+        config.preopens = files_preopens(&params.id).await?;
+    }
+    if let Some(manifest) = &manifest {
+        config.network = manifest.grant_network(&config.network).map_err(RpcError::invalid_params)?;
+        config.preopens = manifest.preopens();
+        config.frozen_clock = !manifest.capabilities.clock;
+        if !manifest.capabilities.locale {
+            config.locale = None;
+        }
+        config.schedule = manifest.capabilities.schedule;
+        config.oauth = manifest.capabilities.oauth.clone();
+        config.browser = manifest.capabilities.browser;
+        config.clipboard = manifest.capabilities.clipboard;
+    }
+    let secrets = match &manifest {
+        Some(manifest) => manifest.secrets.clone(),
+        None => params.secrets.clone(),
+    };
+    Ok(Prepared {
+        module: approval::module_id(&bytes, params.path.as_deref()),
+        consent: granted(&config, manifest.is_some(), &secrets),
+        bytes,
+        config,
+        pool,
+        manifest,
+        secrets,
+        signature_warning,
+    })
+}
+
+/// What starting a component server would grant it, for the user to
+/// approve: takes `wasm.start_server`'s params and returns `{ module,
+/// consent, approved }`, where `consent` lists what the server would get
+/// and `approved` is whether the user has already approved it. Pass
+/// `module` and `consent` to `wasm.approve` once they do.
+pub async fn review(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    crate::rpc::require_extension("review WASM servers")?;
+    let params: StartServerParams =
+        serde_json::from_value(params).map_err(|e| RpcError::invalid_params(format!("Invalid params: {}", e)))?;
+    let prepared = prepare(&params).await?;
+    let approved = approval::is_approved(&params.id, &prepared.module, &prepared.consent).await;
+    Ok(serde_json::json!({
+        "id": params.id,
+        "module": prepared.module,
+        "consent": prepared.consent,
+        "approved": approved,
+        "signature_warning": prepared.signature_warning,
+    }))
+}
+
+/// Start a component server: `{ id, wasm_base64 | path, watch?, env?,
+/// capabilities?, secrets?, random_seed?, files?, schedule?, oauth?,
+/// browser?, clipboard?, manifest?, limits?, pool? }`, where `limits` is
+/// `{ memory_mb?, fuel?, timeout_ms?, kv_mb? }` and `pool` is `{ size?,
+/// min_idle?, idle_timeout_ms? }`. With `watch`, the server is reloaded
+/// whenever the file at `path` changes. With `random_seed`, the server's
+/// randomness is a fixed sequence. Replaces a running server with the same
+/// ID.
+///
+/// The manifest is `manifest` or the `harbor.toml` beside `path`, and
+/// `capabilities` may only narrow what it declares. With `files`, a server
+/// without a manifest can read what it has been granted of the file
+/// sandbox under `/files`, with
+/// `schedule` it can create schedules, with `oauth` it can get tokens,
+/// with `browser` it can ask the extension about tabs, bookmarks and
+/// history and with `clipboard` it can read and write the clipboard; a
+/// manifest declares these instead. Whatever the server gets beyond
+/// running must have been approved for this module (see `approval`), or
+/// the start fails with `WASM_APPROVAL_REQUIRED`. The secrets it declares
+/// (or `secrets`, without a manifest) are then read from the secrets
+/// store; a missing required one fails the start.
+pub async fn start_server(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    crate::rpc::require_extension("start WASM servers")?;
+    let params: StartServerParams =
+        serde_json::from_value(params).map_err(|e| RpcError::invalid_params(format!("Invalid params: {}", e)))?;
+    let Prepared {
+        bytes,
+        module,
+        mut config,
+        pool,
+        manifest,
+        secrets,
+        consent,
+        signature_warning,
+    } = prepare(&params).await?;
+    if let Some(warning) = &signature_warning {
+        tracing::warn!("[WASM:{}] {}", params.id, warning);
+    }
+    approval::check(&params.id, &module, &consent).await?;
+    config.secrets = crate::secrets::resolve(&params.id, &secrets)
+        .await
+        .map_err(|e| RpcError::new(-32000, e))?;
+    let server = load_server(bytes, config, pool, manifest.map(Arc::new))
+        .await
+        .map_err(|e| RpcError::new(-32000, e))?;
+    let watch = match params.path.filter(|_| params.watch) {
+        Some(path) => Some(reload::watch(params.id.clone(), path).map_err(|e| RpcError::new(-32000, e))?),
+        None => None,
+    };
+
+    state().servers.write().await.insert(params.id.clone(), Arc::new(server));
+    let mut watches = state().watches.lock().unwrap();
+    match watch {
+        Some(watch) => watches.insert(params.id.clone(), watch),
+        None => watches.remove(&params.id),
+    };
+    drop(watches);
+    tracing::info!("Started WASM component server: {}", params.id);
+
+    Ok(serde_json::json!({
+        "id": params.id,
+        "status": "running",
+        "watching": params.watch,
+        "signature_warning": signature_warning,
+    }))
+}
+
+/// Stop a component server: `{ id }`.
+pub async fn stop_server(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    crate::rpc::require_extension("stop WASM servers")?;
+    let id = params
+        .get("id")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| RpcError::invalid_params("Missing 'id' parameter"))?;
+    let stopped = state().servers.write().await.remove(id).is_some();
+    state().watches.lock().unwrap().remove(id);
+    if stopped {
+        tracing::info!("Stopped WASM component server: {}", id);
+    }
+    Ok(serde_json::json!({ "stopped": stopped }))
+}
+
+/// Send an MCP request to a component server: `{ id, request,
+/// timeout_ms?, notifications? }`. `timeout_ms` applies to tool calls.
+/// Returns the response, or with `notifications: true`, `{ response,
+/// notifications }` with the notifications the server sent meanwhile.
+pub async fn call_server(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    crate::rpc::require_extension("call WASM servers directly")?;
+    let id = params
+        .get("id")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| RpcError::invalid_params("Missing 'id' parameter"))?;
+    let request = params
+        .get("request")
+        .ok_or_else(|| RpcError::invalid_params("Missing 'request' parameter"))?;
+    // A paused server stays running but takes no tool calls and lists no tools
+    let exchange = match request.get("method").and_then(|m| m.as_str()) {
+        Some("tools/call") => {
+            pause::check(id)?;
+            let limit = timeout::resolve(params.get("timeout_ms").and_then(|v| v.as_u64()))?;
+            timeout::limit(id, limit, exchange(id, request)).await?
+        }
+        Some("tools/list") if pause::is_paused(id) => Exchange {
+            response: pause::empty_tools_list(request),
+            notifications: Vec::new(),
+        },
+        _ => exchange(id, request).await?,
+    };
+    if params.get("notifications").and_then(|v| v.as_bool()).unwrap_or(false) {
+        Ok(serde_json::json!({ "response": exchange.response, "notifications": exchange.notifications }))
+    } else {
+        Ok(exchange.response)
+    }
+}
+
+/// List running component servers.
+pub async fn list_servers(_params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    let mut servers: Vec<ServerInfo> = state()
+        .servers
+        .read()
+        .await
+        .iter()
+        .map(|(id, server)| ServerInfo {
+            id: id.clone(),
+            running: true,
+            paused: pause::is_paused(id),
+            instances: server.pool.status(),
+            capabilities: server.capabilities.read().unwrap().clone(),
+        })
+        .collect();
+    servers.sort_by(|a, b| a.id.cmp(&b.id));
+    Ok(serde_json::json!({ "servers": servers }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_limits() {
+        let limits = LimitParams::default().resolve().unwrap();
+        assert_eq!(limits.fuel, Limits::default().fuel);
+
+        let params: LimitParams = serde_json::from_value(serde_json::json!({ "memoryMb": 64, "fuel": 1000 })).unwrap();
+        let limits = params.resolve().unwrap();
+        assert_eq!(limits.memory_bytes, 64 * 1024 * 1024);
+        assert_eq!(limits.fuel, 1000);
+
+        let params: LimitParams = serde_json::from_value(serde_json::json!({ "memory_mb": 0 })).unwrap();
+        assert!(params.resolve().is_err());
+        let params: LimitParams = serde_json::from_value(serde_json::json!({ "timeout_ms": MAX_TIMEOUT_MS + 1 })).unwrap();
+        assert!(params.resolve().is_err());
+    }
+
+    #[test]
+    fn test_granted() {
+        assert!(granted(&HostConfig::default(), false, &[]).is_empty());
+        let config = HostConfig {
+            network: crate::js::NetworkCapabilities {
+                allowed_hosts: vec!["*".to_string()],
+            },
+            clipboard: true,
+            ..Default::default()
+        };
+        assert_eq!(granted(&config, false, &[]), vec!["Connect to *", "Read and change what's on your clipboard"]);
+        // Only a manifest's clock is granted rather than given
+        assert_eq!(granted(&HostConfig::default(), true, &[]), vec!["Read the current time"]);
+
+        // Secrets are handed out only with the user's approval
+        let secrets: Vec<SecretDecl> = serde_json::from_value(serde_json::json!([{ "name": "API_KEY" }])).unwrap();
+        assert_eq!(granted(&HostConfig::default(), false, &secrets), vec!["Use your secret API_KEY"]);
+    }
+
+    #[test]
+    fn test_within_package() {
+        let params = |extra: serde_json::Value| -> StartServerParams {
+            let mut params = serde_json::json!({ "id": "search-wasm", "wasm_base64": "" });
+            params.as_object_mut().unwrap().extend(extra.as_object().unwrap().clone());
+            serde_json::from_value(params).unwrap()
+        };
+        let package = serde_json::json!({
+            "capabilities": { "network": { "hosts": ["api.example.com"] }, "clipboard": true },
+            "declaredSecrets": [{ "name": "API_KEY" }],
+        });
+        let asked = params(serde_json::json!({
+            "capabilities": { "network": { "allowed_hosts": ["api.example.com"] } },
+            "clipboard": true,
+            "secrets": [{ "name": "API_KEY" }],
+        }));
+        assert!(within_package(&asked, &package).is_ok());
+
+        // Anything the signed manifest doesn't ask for is refused
+        let wider = params(serde_json::json!({ "capabilities": { "network": { "allowed_hosts": ["*"] } } }));
+        assert!(within_package(&wider, &package).is_err());
+        assert!(within_package(&params(serde_json::json!({ "browser": true })), &package).is_err());
+        assert!(within_package(&params(serde_json::json!({ "secrets": [{ "name": "OTHER" }] })), &package).is_err());
+        let oauth = params(serde_json::json!({ "oauth": { "provider": "google", "scopes": ["drive"] } }));
+        assert!(within_package(&oauth, &package).is_err());
+    }
+
+    #[test]
+    fn test_call_error_codes() {
+        assert_eq!(RpcError::from(CallError::OutOfFuel { fuel: 10 }).code, WASM_FUEL_EXHAUSTED);
+        assert_eq!(
+            RpcError::from(CallError::MemoryLimit { bytes: 1 << 20 }).code,
+            WASM_MEMORY_EXCEEDED
+        );
+        assert_eq!(RpcError::from(CallError::Timeout(Duration::from_secs(1))).code, WASM_TIMEOUT);
+    }
+
+    #[test]
+    fn test_capability_routing() {
+        assert_eq!(capability_of("resources/list"), Some("resources"));
+        assert_eq!(capability_of("resources/read"), Some("resources"));
+        assert_eq!(capability_of("prompts/get"), Some("prompts"));
+        assert_eq!(capability_of("tools/call"), None);
+        assert_eq!(capability_of("initialize"), None);
+
+        let request = serde_json::json!({ "jsonrpc": "2.0", "id": 7, "method": "resources/list" });
+        let response = not_advertised(&request, "time", "resources");
+        assert_eq!(response["id"], 7);
+        assert_eq!(response["error"]["code"], -32601);
+    }
+
+    #[test]
+    fn test_parse_output() {
+        let output = concat!(
+            r#"{"jsonrpc":"2.0","method":"notifications/message","params":{"level":"info","data":"hi"}}"#,
+            "\n",
+            r#"{"jsonrpc":"2.0","method":"notifications/tools/list_changed"}"#,
+            "\n",
+            r#"{"jsonrpc":"2.0","id":3,"result":{}}"#,
+        );
+        let exchange = parse_output(output).unwrap();
+        assert_eq!(exchange.response["id"], 3);
+        assert_eq!(exchange.notifications.len(), 2);
+        assert_eq!(exchange.notifications[1]["method"], "notifications/tools/list_changed");
+
+        // A single response, as from servers that send no notifications
+        let exchange = parse_output(r#"{"jsonrpc":"2.0","id":1,"result":{}}"#).unwrap();
+        assert_eq!(exchange.response["id"], 1);
+        assert!(exchange.notifications.is_empty());
+
+        assert!(parse_output("").unwrap().response.is_null());
+        assert!(parse_output("not json").is_err());
+    }
+}
@@ -0,0 +1,165 @@
+//! The `harbor:mcp/oauth` import: access tokens for a component.
+//!
+//! A server that declares `capabilities.oauth` (or, without a manifest, is
+//! started with `oauth`) gets tokens for the account the user connected to
+//! it, from the token store (see `crate::oauth`). A token is handed out only
+//! for the declared provider, only for scopes that were both declared and
+//! granted, and only if the server's scope policy allows the stored tokens.
+//! Tokens are stored by server ID; a server is only started with `oauth`
+//! once the user has approved it for that module (see `super::approval`),
+//! so another module can't reuse the ID to get them. A server must declare
+//! the scopes it uses and name those it wants for each token.
+
+use super::component::harbor::mcp::oauth::Host;
+use super::component::HostState;
+use super::manifest::OAuthDecl;
+use crate::oauth::StoredTokens;
+
+impl HostState {
+    fn require_oauth(&self) -> Result<&OAuthDecl, String> {
+        self.oauth.as_ref().ok_or_else(|| {
+            tracing::warn!("[WASM:{}] Blocked use of OAuth tokens", self.server_id);
+            "OAuth is not granted to this server; declare capabilities.oauth".to_string()
+        })
+    }
+}
+
+/// `server_id`'s stored tokens, if they are for the declared provider and
+/// within its scope policy.
+async fn stored_tokens(server_id: &str, declared: &OAuthDecl) -> Result<StoredTokens, String> {
+    let stored = crate::oauth::get_token_store()
+        .await
+        .as_ref()
+        .and_then(|s| s.get_tokens(server_id).cloned())
+        .ok_or_else(|| format!("No {} account is connected to '{}'", declared.provider, server_id))?;
+    if stored.provider != declared.provider {
+        return Err(format!(
+            "The connected account is for {}, not {}",
+            stored.provider, declared.provider
+        ));
+    }
+    if let Some(policy) = crate::oauth::get_scope_policy(server_id).await {
+        policy
+            .check_tokens(&stored)
+            .map_err(|e| format!("Scope policy for '{}' rejected stored tokens: {}", server_id, e))?;
+    }
+    Ok(stored)
+}
+
+/// The scopes the provider granted, or those asked for if it didn't say.
+/// Google separates them with spaces, GitHub with commas.
+fn granted(stored: &StoredTokens) -> Vec<String> {
+    match &stored.tokens.scope {
+        Some(scope) if !scope.trim().is_empty() => scope
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|s| !s.is_empty())
+            .map(str::to_string)
+            .collect(),
+        _ => stored.scopes.clone(),
+    }
+}
+
+/// Check that `wanted` names some scopes, each declared and granted.
+fn check_scopes(declared: &OAuthDecl, granted: &[String], wanted: &[String]) -> Result<(), String> {
+    if declared.scopes.is_empty() {
+        return Err("The server declares no OAuth scopes; declare those it uses in capabilities.oauth".to_string());
+    }
+    if wanted.is_empty() {
+        return Err("Name the scopes the token is for".to_string());
+    }
+    let undeclared: Vec<&str> = wanted
+        .iter()
+        .filter(|s| !declared.scopes.contains(s))
+        .map(String::as_str)
+        .collect();
+    if !undeclared.is_empty() {
+        return Err(format!("Scopes not declared by the server: {}", undeclared.join(", ")));
+    }
+    let missing: Vec<&str> = wanted
+        .iter()
+        .filter(|s| !granted.contains(s))
+        .map(String::as_str)
+        .collect();
+    if !missing.is_empty() {
+        return Err(format!(
+            "The connected account hasn't granted: {}; sign in again to grant them",
+            missing.join(", ")
+        ));
+    }
+    Ok(())
+}
+
+#[async_trait::async_trait]
+impl Host for HostState {
+    async fn granted_scopes(&mut self) -> Result<Vec<String>, String> {
+        let declared = self.require_oauth()?;
+        let stored = stored_tokens(&self.server_id, declared).await?;
+        Ok(granted(&stored))
+    }
+
+    async fn access_token(&mut self, scopes: Vec<String>) -> Result<String, String> {
+        let declared = self.require_oauth()?;
+        let stored = stored_tokens(&self.server_id, declared).await?;
+        check_scopes(declared, &granted(&stored), &scopes)?;
+        let token = crate::oauth::get_access_token(&self.server_id).await?;
+        tracing::info!("[WASM:{}] Handed out {} token ({})", self.server_id, stored.provider, scopes.join(", "));
+        Ok(token)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::oauth::OAuthTokens;
+
+    const READONLY: &str = "https://www.googleapis.com/auth/gmail.readonly";
+    const SEND: &str = "https://www.googleapis.com/auth/gmail.send";
+
+    fn stored(scope: Option<&str>, scopes: &[&str]) -> StoredTokens {
+        StoredTokens {
+            server_id: "gmail-wasm".to_string(),
+            provider: "google".to_string(),
+            tokens: OAuthTokens {
+                access_token: "token".to_string(),
+                refresh_token: None,
+                expires_at: None,
+                token_type: "Bearer".to_string(),
+                scope: scope.map(str::to_string),
+            },
+            scopes: scopes.iter().map(|s| s.to_string()).collect(),
+            created_at: 0,
+            updated_at: 0,
+        }
+    }
+
+    #[test]
+    fn test_granted() {
+        let google = stored(Some(format!("{} openid", READONLY).as_str()), &[READONLY, SEND]);
+        assert_eq!(granted(&google), vec![READONLY, "openid"]);
+        assert_eq!(granted(&stored(Some("repo,gist"), &[])), vec!["repo", "gist"]);
+        assert_eq!(granted(&stored(None, &[READONLY, SEND])), vec![READONLY, SEND]);
+    }
+
+    #[test]
+    fn test_check_scopes() {
+        let declared = OAuthDecl {
+            provider: "google".to_string(),
+            scopes: vec![READONLY.to_string(), SEND.to_string()],
+        };
+        let granted = vec![READONLY.to_string()];
+        assert!(check_scopes(&declared, &granted, &[READONLY.to_string()]).is_ok());
+        assert!(check_scopes(&declared, &granted, &[]).is_err());
+
+        let error = check_scopes(&declared, &granted, &[SEND.to_string()]).unwrap_err();
+        assert!(error.contains("hasn't granted"));
+        let error = check_scopes(&declared, &granted, &["openid".to_string()]).unwrap_err();
+        assert!(error.contains("not declared"));
+
+        // Declaring no scopes doesn't mean any scope
+        let undeclared = OAuthDecl {
+            provider: "google".to_string(),
+            scopes: Vec::new(),
+        };
+        assert!(check_scopes(&undeclared, &granted, &[READONLY.to_string()]).is_err());
+    }
+}
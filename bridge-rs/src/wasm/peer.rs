@@ -0,0 +1,25 @@
+//! The `harbor:mcp/peer` import: tool calls from a component to other
+//! servers, made by `crate::peer` with the component as the caller.
+
+use super::component::harbor::mcp::peer::Host;
+use super::component::HostState;
+
+#[async_trait::async_trait]
+impl Host for HostState {
+    async fn call(
+        &mut self,
+        target: String,
+        tool: String,
+        arguments: String,
+        token: Option<String>,
+    ) -> Result<String, String> {
+        let arguments: serde_json::Value = if arguments.trim().is_empty() {
+            serde_json::json!({})
+        } else {
+            serde_json::from_str(&arguments).map_err(|e| format!("Invalid arguments: {}", e))?
+        };
+        let params = serde_json::json!({ "target": target, "tool": tool, "arguments": arguments, "token": token });
+        let result = crate::peer::call(&self.server_id, params).await.map_err(|e| e.message)?;
+        Ok(result.to_string())
+    }
+}
@@ -0,0 +1,246 @@
+//! Warm instance pools.
+//!
+//! An instance handles one request at a time, so a server with one instance
+//! serializes bursts of calls behind each other. A server can instead keep a
+//! pool: up to `size` instances, each taking one request at a time, with at
+//! least `min_idle` kept initialized between bursts and the rest evicted
+//! once idle for `idle_timeout`. Instances don't share state, so a pool
+//! larger than one suits stateless servers only.
+
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::{Semaphore, SemaphorePermit};
+
+/// Upper bounds for pool settings.
+const MAX_SIZE: usize = 16;
+const MAX_IDLE_TIMEOUT_MS: u64 = 3_600_000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PoolConfig {
+    /// Most instances, and so most requests handled at once
+    pub size: usize,
+    /// Instances kept warm however long they sit idle
+    pub min_idle: usize,
+    /// How long other idle instances are kept
+    pub idle_timeout: Duration,
+}
+
+impl Default for PoolConfig {
+    fn default() -> Self {
+        Self {
+            size: 1,
+            min_idle: 1,
+            idle_timeout: Duration::from_secs(300),
+        }
+    }
+}
+
+/// Pool settings as passed to `wasm.start_server`; omitted fields use the
+/// defaults.
+#[derive(Debug, Default, Deserialize)]
+pub struct PoolParams {
+    size: Option<usize>,
+    #[serde(alias = "minIdle")]
+    min_idle: Option<usize>,
+    #[serde(alias = "idleTimeoutMs")]
+    idle_timeout_ms: Option<u64>,
+}
+
+impl PoolParams {
+    pub fn resolve(&self) -> Result<PoolConfig, String> {
+        let defaults = PoolConfig::default();
+        let size = self.size.unwrap_or(defaults.size);
+        if size == 0 || size > MAX_SIZE {
+            return Err(format!("'pool.size' must be 1 to {}", MAX_SIZE));
+        }
+        let min_idle = self.min_idle.unwrap_or(defaults.min_idle);
+        if min_idle > size {
+            return Err("'pool.min_idle' may not exceed 'pool.size'".to_string());
+        }
+        let idle_timeout = match self.idle_timeout_ms {
+            Some(ms) if ms > MAX_IDLE_TIMEOUT_MS => {
+                return Err(format!("'pool.idle_timeout_ms' may be at most {}", MAX_IDLE_TIMEOUT_MS))
+            }
+            Some(ms) => Duration::from_millis(ms),
+            None => defaults.idle_timeout,
+        };
+        Ok(PoolConfig {
+            size,
+            min_idle,
+            idle_timeout,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct PoolStatus {
+    pub size: usize,
+    pub idle: usize,
+    pub busy: usize,
+}
+
+pub struct Pool<T> {
+    config: PoolConfig,
+    /// One permit per instance that may be in use
+    slots: Semaphore,
+    /// Idle instances, most recently used last, with when they were returned
+    idle: Mutex<Vec<(T, Instant)>>,
+}
+
+impl<T> Pool<T> {
+    /// A pool holding `warm` initialized instances.
+    pub fn new(config: PoolConfig, warm: Vec<T>) -> Self {
+        let now = Instant::now();
+        Self {
+            config,
+            slots: Semaphore::new(config.size),
+            idle: Mutex::new(warm.into_iter().map(|instance| (instance, now)).collect()),
+        }
+    }
+
+    /// Wait for a free slot and take an idle instance, or create one with
+    /// `create` if none is idle. `None` if the pool was drained meanwhile,
+    /// when the server has been replaced and the new one should be asked.
+    pub async fn checkout<F>(&self, create: impl FnOnce() -> F) -> Result<Option<Lease<'_, T>>, String>
+    where
+        F: Future<Output = Result<T, String>>,
+    {
+        let Ok(permit) = self.slots.acquire().await else {
+            return Ok(None);
+        };
+        let idle = self.idle.lock().unwrap().pop().map(|(instance, _)| instance);
+        let instance = match idle {
+            Some(instance) => instance,
+            None => create().await?,
+        };
+        Ok(Some(Lease {
+            pool: self,
+            instance: Some(instance),
+            _permit: permit,
+        }))
+    }
+
+    /// Drop instances idle for longer than the idle timeout, keeping
+    /// `min_idle` of the most recently used.
+    pub fn evict_idle(&self) -> usize {
+        let mut idle = self.idle.lock().unwrap();
+        let keep_from = idle.len().saturating_sub(self.config.min_idle);
+        let timeout = self.config.idle_timeout;
+        let mut index = 0;
+        let before = idle.len();
+        idle.retain(|(_, since)| {
+            index += 1;
+            index > keep_from || since.elapsed() < timeout
+        });
+        before - idle.len()
+    }
+
+    pub fn config(&self) -> PoolConfig {
+        self.config
+    }
+
+    /// Wait until no instance is in use, and keep it that way.
+    pub async fn drain(&self) {
+        if let Ok(permits) = self.slots.acquire_many(self.config.size as u32).await {
+            permits.forget();
+        }
+        self.slots.close();
+    }
+
+    pub fn status(&self) -> PoolStatus {
+        PoolStatus {
+            size: self.config.size,
+            idle: self.idle.lock().unwrap().len(),
+            busy: self.config.size - self.slots.available_permits(),
+        }
+    }
+}
+
+/// An instance checked out of a pool. `release` returns it; dropped
+/// without releasing (after a failure, or a call cancelled mid-request),
+/// the instance is discarded since its state can't be trusted.
+pub struct Lease<'a, T> {
+    pool: &'a Pool<T>,
+    instance: Option<T>,
+    _permit: SemaphorePermit<'a>,
+}
+
+impl<T> Lease<'_, T> {
+    pub fn instance(&mut self) -> &mut T {
+        self.instance.as_mut().expect("lease holds an instance until released")
+    }
+
+    pub fn release(mut self) {
+        if let Some(instance) = self.instance.take() {
+            self.pool.idle.lock().unwrap().push((instance, Instant::now()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(size: usize, min_idle: usize, idle_timeout: Duration) -> PoolConfig {
+        PoolConfig {
+            size,
+            min_idle,
+            idle_timeout,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reuse_and_discard() {
+        let pool = Pool::new(config(2, 1, Duration::from_secs(60)), vec![1]);
+        let created = &std::sync::atomic::AtomicUsize::new(0);
+        let create = move || async move { Ok(10 + created.fetch_add(1, std::sync::atomic::Ordering::SeqCst)) };
+
+        // Two at once: the warm instance and a new one
+        let mut first = pool.checkout(create).await.unwrap().unwrap();
+        let mut second = pool.checkout(create).await.unwrap().unwrap();
+        assert_eq!((*first.instance(), *second.instance()), (1, 10));
+        assert_eq!(pool.status().busy, 2);
+
+        first.release();
+        drop(second);
+        assert_eq!(pool.status().idle, 1);
+        assert_eq!(pool.status().busy, 0);
+
+        // The released instance is reused; the discarded one is replaced
+        let mut third = pool.checkout(create).await.unwrap().unwrap();
+        assert_eq!(*third.instance(), 1);
+        let mut fourth = pool.checkout(create).await.unwrap().unwrap();
+        assert_eq!(*fourth.instance(), 11);
+    }
+
+    #[tokio::test]
+    async fn test_evict_keeps_min_idle() {
+        let pool = Pool::new(config(4, 1, Duration::ZERO), vec![1, 2, 3]);
+        assert_eq!(pool.evict_idle(), 2);
+        assert_eq!(pool.status().idle, 1);
+        // The most recently returned instance is the one kept
+        let mut lease = pool.checkout(|| async { Ok(0) }).await.unwrap().unwrap();
+        assert_eq!(*lease.instance(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_drained_pool_hands_nothing_out() {
+        let pool = Pool::new(config(1, 0, Duration::from_secs(60)), vec![1]);
+        pool.drain().await;
+        assert!(pool.checkout(|| async { Ok(0) }).await.unwrap().is_none());
+    }
+
+    #[test]
+    fn test_resolve() {
+        assert_eq!(PoolParams::default().resolve().unwrap(), PoolConfig::default());
+        let params: PoolParams = serde_json::from_value(serde_json::json!({ "size": 4, "minIdle": 2 })).unwrap();
+        assert_eq!(params.resolve().unwrap().min_idle, 2);
+        let params: PoolParams = serde_json::from_value(serde_json::json!({ "size": 2, "min_idle": 3 })).unwrap();
+        assert!(params.resolve().is_err());
+        let params: PoolParams = serde_json::from_value(serde_json::json!({ "size": 0 })).unwrap();
+        assert!(params.resolve().is_err());
+    }
+}
@@ -0,0 +1,107 @@
+//! Hot reload for component servers under development.
+//!
+//! A server started from a `path` with `watch: true` is reloaded whenever
+//! the file changes. The new build is compiled and instantiated with the
+//! same configuration, and `initialize` and `tools/list` are run against
+//! it. If it answers, it takes over new requests, while the old one
+//! finishes the requests it already had, and `wasm/tools_changed` is
+//! emitted with its tools. A build that fails to load or answer is
+//! reported with `wasm/reload_failed`, and the running one stays.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use notify::RecursiveMode;
+
+use super::{exchange_with, Server};
+use crate::changes::{self, Changes};
+use crate::events;
+
+/// Reloading of a watched server, which stops when this is dropped.
+pub struct Reloading(tokio::task::AbortHandle);
+
+impl Drop for Reloading {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// Watch `path` and reload `server_id` from it on change, until the
+/// returned `Reloading` is dropped.
+pub fn watch(server_id: String, path: PathBuf) -> Result<Reloading, String> {
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_os_string())
+        .ok_or_else(|| format!("'{}' is not a file", path.display()))?;
+    let parent = path.parent().map(Path::to_path_buf).unwrap_or_else(|| PathBuf::from("."));
+
+    // The directory is watched rather than the file, since build tools often
+    // replace the file instead of writing to it
+    let mut changes = Changes::new(move |kind, changed| {
+        changes::is_write(kind) && changed.file_name() == Some(file_name.as_os_str())
+    })?;
+    changes.watch(&parent, RecursiveMode::NonRecursive)?;
+
+    let task = tokio::spawn(reload_on_change(server_id, path, changes));
+    Ok(Reloading(task.abort_handle()))
+}
+
+async fn reload_on_change(server_id: String, path: PathBuf, mut changes: Changes) {
+    while changes.next().await.is_some() {
+        tracing::info!("[WASM:{}] {} changed; reloading", server_id, path.display());
+        match super::reload(&server_id, &path).await {
+            Ok(tools) => {
+                record_tools(&server_id, &tools).await;
+                events::emit(
+                    "wasm/tools_changed",
+                    serde_json::json!({ "server_id": server_id, "tools": tools }),
+                )
+            }
+            Err(e) => {
+                tracing::warn!("[WASM:{}] Reload failed: {}", server_id, e);
+                events::emit(
+                    "wasm/reload_failed",
+                    serde_json::json!({ "server_id": server_id, "error": e }),
+                );
+            }
+        }
+    }
+}
+
+/// Run the MCP handshake against a new build of `server_id`, before it
+/// takes over, and return its tools.
+pub(super) async fn handshake(server_id: &str, server: &Arc<Server>) -> Result<serde_json::Value, String> {
+    let initialize = serde_json::json!({
+        "id": "reload-initialize",
+        "method": "initialize",
+        "params": {
+            "protocolVersion": "2024-11-05",
+            "capabilities": {},
+            "clientInfo": { "name": "harbor-bridge", "version": env!("CARGO_PKG_VERSION") },
+        },
+    });
+    let call = |request| async move {
+        let exchange = exchange_with(server.clone(), server_id, &request).await.map_err(|e| e.message)?;
+        Ok::<_, String>(exchange.response)
+    };
+    call(initialize).await?;
+    let response = call(serde_json::json!({ "id": "reload-tools", "method": "tools/list" })).await?;
+    response
+        .pointer("/result/tools")
+        .cloned()
+        .ok_or_else(|| "tools/list returned no tools".to_string())
+}
+
+/// Record a reloaded server's tools in the catalog.
+async fn record_tools(server_id: &str, tools: &serde_json::Value) {
+    let schemas = tools
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|tool| {
+            let name = tool.get("name")?.as_str()?;
+            Some((name.to_string(), tool.get("inputSchema").cloned().unwrap_or_default()))
+        })
+        .collect();
+    crate::catalog::observe(server_id, None, schemas, tools.clone(), false).await;
+}
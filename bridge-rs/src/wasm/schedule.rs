@@ -0,0 +1,41 @@
+//! The `harbor:mcp/schedule` import: scheduled tool calls for a component.
+//!
+//! Only servers granted `schedule` (declared in the manifest, or passed at
+//! start for one without) may use it. Schedules are owned by the server
+//! that creates them; see `crate::scheduler`.
+
+use super::component::harbor::mcp::schedule::Host;
+use super::component::HostState;
+use crate::scheduler::{self, NewSchedule};
+
+impl HostState {
+    fn require_schedule(&self) -> Result<(), String> {
+        if self.schedule {
+            Ok(())
+        } else {
+            tracing::warn!("[WASM:{}] Blocked use of schedules", self.server_id);
+            Err("Schedules are not granted to this server; declare capabilities.schedule".to_string())
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Host for HostState {
+    async fn create(&mut self, definition: String) -> Result<String, String> {
+        self.require_schedule()?;
+        let new: NewSchedule = serde_json::from_str(&definition).map_err(|e| format!("Invalid schedule: {}", e))?;
+        let schedule = scheduler::create(Some(self.server_id.as_str()), new).await?;
+        serde_json::to_string(&schedule).map_err(|e| e.to_string())
+    }
+
+    async fn list(&mut self) -> Result<String, String> {
+        self.require_schedule()?;
+        let schedules = scheduler::list(Some(self.server_id.as_str())).await;
+        serde_json::to_string(&schedules).map_err(|e| e.to_string())
+    }
+
+    async fn delete(&mut self, id: String) -> Result<bool, String> {
+        self.require_schedule()?;
+        Ok(scheduler::delete(Some(self.server_id.as_str()), &id).await)
+    }
+}
@@ -0,0 +1,194 @@
+//! Watchdog for the bridge's own liveness.
+//!
+//! A dedicated OS thread, outside the tokio runtime, watches heartbeats from
+//! the runtime itself and from critical tasks (the native messaging writer
+//! and router). If any of them stops making progress it logs diagnostics and
+//! exits, so the extension reconnects to a fresh bridge instead of talking
+//! to a wedged one.
+//!
+//! Task dumps are included when built with
+//! `RUSTFLAGS="--cfg tokio_unstable --cfg tokio_taskdump"` on Linux.
+
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+/// How often the watchdog thread checks for stalls.
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How often the runtime heartbeat task ticks.
+const RUNTIME_HEARTBEAT_INTERVAL: Duration = Duration::from_millis(500);
+
+/// How long the runtime may go without running the heartbeat task.
+const RUNTIME_STALL_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A check that comes this much later than `CHECK_INTERVAL` means the
+/// machine was asleep.
+const SUSPEND_GAP: Duration = Duration::from_secs(5);
+
+/// Exit code used when the watchdog restarts the bridge.
+pub const STALL_EXIT_CODE: i32 = 70;
+
+lazy_static::lazy_static! {
+    static ref WATCHES: Mutex<Vec<Arc<Watch>>> = Mutex::new(Vec::new());
+}
+
+/// Milliseconds since the watchdog clock started (monotonic).
+fn now_ms() -> u64 {
+    static START: OnceLock<Instant> = OnceLock::new();
+    START.get_or_init(Instant::now).elapsed().as_millis() as u64
+}
+
+/// Progress tracker for a single component.
+///
+/// Only busy components are expected to make progress, so an idle writer
+/// waiting for messages is never reported as stalled.
+pub struct Watch {
+    name: &'static str,
+    timeout: Duration,
+    busy: AtomicBool,
+    last_progress: AtomicU64,
+}
+
+impl Watch {
+    /// Mark the start of a unit of work that must finish within the timeout.
+    pub fn begin(&self) {
+        self.last_progress.store(now_ms(), Ordering::Relaxed);
+        self.busy.store(true, Ordering::Relaxed);
+    }
+
+    /// Mark the current unit of work as finished.
+    pub fn end(&self) {
+        self.busy.store(false, Ordering::Relaxed);
+        self.last_progress.store(now_ms(), Ordering::Relaxed);
+    }
+
+    /// Record progress without changing the busy state.
+    pub fn beat(&self) {
+        self.last_progress.store(now_ms(), Ordering::Relaxed);
+    }
+
+    /// How long the component has been stuck, if it exceeded its timeout.
+    fn stalled_for(&self, now: u64) -> Option<Duration> {
+        if !self.busy.load(Ordering::Relaxed) {
+            return None;
+        }
+        let elapsed = Duration::from_millis(now.saturating_sub(self.last_progress.load(Ordering::Relaxed)));
+        (elapsed > self.timeout).then_some(elapsed)
+    }
+}
+
+/// Register a component with the watchdog.
+pub fn register(name: &'static str, timeout: Duration) -> Arc<Watch> {
+    let watch = Arc::new(Watch {
+        name,
+        timeout,
+        busy: AtomicBool::new(false),
+        last_progress: AtomicU64::new(now_ms()),
+    });
+    WATCHES.lock().unwrap().push(watch.clone());
+    watch
+}
+
+/// Start the runtime heartbeat and the watchdog thread.
+/// Must be called from within the tokio runtime.
+pub fn start() {
+    let runtime = register("runtime", RUNTIME_STALL_TIMEOUT);
+    // The runtime is always expected to make progress
+    runtime.begin();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(RUNTIME_HEARTBEAT_INTERVAL);
+        loop {
+            interval.tick().await;
+            runtime.beat();
+        }
+    });
+
+    let handle = tokio::runtime::Handle::current();
+    let spawned = std::thread::Builder::new()
+        .name("harbor-watchdog".to_string())
+        .spawn(move || monitor(handle));
+    if let Err(e) = spawned {
+        tracing::error!("Failed to start watchdog thread: {}", e);
+    }
+}
+
+fn monitor(handle: tokio::runtime::Handle) {
+    let mut last_check = std::time::SystemTime::now();
+    loop {
+        std::thread::sleep(CHECK_INTERVAL);
+
+        // On some platforms the monotonic clock keeps running while the
+        // machine sleeps. Give every component a fresh start after a resume
+        // rather than reporting the sleep as a stall.
+        let wall = std::time::SystemTime::now();
+        let gap = wall.duration_since(last_check).unwrap_or_default();
+        last_check = wall;
+        let now = now_ms();
+        if gap > CHECK_INTERVAL + SUSPEND_GAP {
+            tracing::info!("Watchdog: resumed after {:?}, resetting progress", gap);
+            for watch in WATCHES.lock().unwrap().iter() {
+                watch.last_progress.store(now, Ordering::Relaxed);
+            }
+            continue;
+        }
+
+        let stalled: Vec<(&'static str, Duration)> = WATCHES
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|w| w.stalled_for(now).map(|d| (w.name, d)))
+            .collect();
+        if stalled.is_empty() {
+            continue;
+        }
+
+        for (name, duration) in &stalled {
+            tracing::error!("Watchdog: {} made no progress for {:?}", name, duration);
+        }
+        let runtime_stalled = stalled.iter().any(|(name, _)| *name == "runtime");
+        log_diagnostics(&handle, runtime_stalled);
+
+        tracing::error!("Watchdog: restarting bridge (exit code {})", STALL_EXIT_CODE);
+        std::process::exit(STALL_EXIT_CODE);
+    }
+}
+
+#[cfg_attr(not(all(tokio_unstable, tokio_taskdump)), allow(unused_variables))]
+fn log_diagnostics(handle: &tokio::runtime::Handle, runtime_stalled: bool) {
+    tracing::error!("Watchdog: runtime has {} worker threads", handle.metrics().num_workers());
+
+    // A dump needs a responsive runtime, so only try it when a task is stuck
+    #[cfg(all(tokio_unstable, tokio_taskdump))]
+    if !runtime_stalled {
+        let dump_handle = handle.clone();
+        let dump = handle.block_on(async move {
+            tokio::time::timeout(Duration::from_secs(2), dump_handle.dump()).await
+        });
+        match dump {
+            Ok(dump) => {
+                for (i, task) in dump.tasks().iter().enumerate() {
+                    tracing::error!("Watchdog: task {} trace:\n{}", i, task.trace());
+                }
+            }
+            Err(_) => tracing::error!("Watchdog: task dump timed out"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_idle_watch_never_stalls() {
+        let watch = register("test-idle", Duration::from_millis(0));
+        assert!(watch.stalled_for(now_ms() + 1_000).is_none());
+
+        watch.begin();
+        assert!(watch.stalled_for(now_ms() + 1_000).is_some());
+
+        watch.end();
+        assert!(watch.stalled_for(now_ms() + 1_000).is_none());
+    }
+}
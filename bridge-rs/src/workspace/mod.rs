@@ -0,0 +1,424 @@
+//! Workspace export and import.
+//!
+//! A workspace bundle captures a shareable agent toolkit: the JS servers that
+//! are running (with versions and capabilities), LLM provider and model setup,
+//! and which OAuth providers/scopes the servers rely on. Secrets (API keys,
+//! client secrets, tokens, env values) are never exported; on import the
+//! receiver is told which ones they need to supply themselves.
+
+pub mod stats;
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::js::{self, ServerDefinition};
+use crate::llm::{self, ProviderInstance};
+use crate::oauth;
+use crate::rpc::RpcError;
+
+const BUNDLE_FORMAT: &str = "harbor-workspace";
+const BUNDLE_VERSION: u32 = 1;
+
+/// A shareable workspace bundle.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkspaceBundle {
+    pub format: String,
+    pub version: u32,
+    pub name: String,
+    /// When the bundle was exported (Unix timestamp ms)
+    pub exported_at: i64,
+    #[serde(default)]
+    pub servers: Vec<ServerDefinition>,
+    #[serde(default)]
+    pub llm: LlmSection,
+    #[serde(default)]
+    pub oauth: OAuthSection,
+}
+
+/// Non-secret LLM configuration.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LlmSection {
+    #[serde(default)]
+    pub providers: Vec<ExportedProvider>,
+    #[serde(default)]
+    pub models: Vec<llm::ModelAlias>,
+    #[serde(default)]
+    pub default_provider: Option<String>,
+    #[serde(default)]
+    pub default_model: Option<String>,
+}
+
+/// A provider instance with its API key stripped.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportedProvider {
+    pub id: String,
+    pub provider_type: String,
+    pub name: String,
+    #[serde(default)]
+    pub base_url: Option<String>,
+    /// Whether the exporter had an API key configured (receiver must supply one)
+    #[serde(default)]
+    pub needs_api_key: bool,
+}
+
+/// OAuth requirements of the exported servers.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OAuthSection {
+    /// Provider IDs that had client credentials configured
+    #[serde(default)]
+    pub providers: Vec<String>,
+    /// Per-server authorizations the receiver needs to redo
+    #[serde(default)]
+    pub servers: Vec<OAuthRequirement>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OAuthRequirement {
+    pub server_id: String,
+    pub provider: String,
+    pub scopes: Vec<String>,
+}
+
+/// Something the receiver of a bundle must provide.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Requirement {
+    ApiKey { provider_id: String, provider_type: String },
+    OauthCredentials { provider: String },
+    OauthAuthorization { server_id: String, provider: String, scopes: Vec<String> },
+    Env { server_id: String, name: String },
+}
+
+/// Secrets supplied by the receiver during import.
+#[derive(Debug, Default, Deserialize)]
+pub struct ImportSecrets {
+    /// provider instance ID -> API key
+    #[serde(default)]
+    pub api_keys: HashMap<String, String>,
+    /// server ID -> env var name -> value
+    #[serde(default)]
+    pub env: HashMap<String, HashMap<String, String>>,
+}
+
+// ============================================================================
+// Export
+// ============================================================================
+
+/// Build a bundle from the current bridge state.
+pub async fn export_bundle(name: &str) -> WorkspaceBundle {
+    let cfg = llm::get_config().unwrap_or_default();
+
+    let mut providers: Vec<ExportedProvider> = cfg
+        .providers
+        .values()
+        .map(|p| ExportedProvider {
+            id: p.id.clone(),
+            provider_type: p.provider_type.clone(),
+            name: p.name.clone(),
+            base_url: p.base_url.clone(),
+            needs_api_key: p.api_key.is_some(),
+        })
+        .collect();
+    providers.sort_by(|a, b| a.id.cmp(&b.id));
+
+    let servers = js::list_definitions().await;
+
+    let mut oauth_servers = Vec::new();
+    {
+        let store = oauth::get_token_store().await;
+        if let Some(s) = store.as_ref() {
+            for stored in s.tokens.values() {
+                oauth_servers.push(OAuthRequirement {
+                    server_id: stored.server_id.clone(),
+                    provider: stored.provider.clone(),
+                    scopes: stored.scopes.clone(),
+                });
+            }
+        }
+    }
+    oauth_servers.sort_by(|a, b| a.server_id.cmp(&b.server_id));
+
+    let mut oauth_providers = oauth::list_configured_providers().await;
+    oauth_providers.sort();
+
+    WorkspaceBundle {
+        format: BUNDLE_FORMAT.to_string(),
+        version: BUNDLE_VERSION,
+        name: name.to_string(),
+        exported_at: chrono::Utc::now().timestamp_millis(),
+        servers,
+        llm: LlmSection {
+            providers,
+            models: cfg.models.clone(),
+            default_provider: cfg.default_provider.clone(),
+            default_model: cfg.default_model.clone(),
+        },
+        oauth: OAuthSection {
+            providers: oauth_providers,
+            servers: oauth_servers,
+        },
+    }
+}
+
+// ============================================================================
+// Import
+// ============================================================================
+
+/// Validate a bundle's header.
+fn validate(bundle: &WorkspaceBundle) -> Result<(), String> {
+    if bundle.format != BUNDLE_FORMAT {
+        return Err(format!("Not a Harbor workspace bundle (format '{}')", bundle.format));
+    }
+    if bundle.version > BUNDLE_VERSION {
+        return Err(format!(
+            "Bundle version {} is newer than supported version {}",
+            bundle.version, BUNDLE_VERSION
+        ));
+    }
+    Ok(())
+}
+
+/// Work out what the receiver still needs to provide for a bundle.
+async fn requirements(bundle: &WorkspaceBundle, secrets: &ImportSecrets) -> Vec<Requirement> {
+    let mut required = Vec::new();
+    let cfg = llm::get_config().unwrap_or_default();
+
+    for p in &bundle.llm.providers {
+        let has_key = secrets.api_keys.contains_key(&p.id)
+            || cfg.get_instance(&p.id).map(|i| i.api_key.is_some()).unwrap_or(false);
+        if p.needs_api_key && !has_key {
+            required.push(Requirement::ApiKey {
+                provider_id: p.id.clone(),
+                provider_type: p.provider_type.clone(),
+            });
+        }
+    }
+
+    let configured = oauth::list_configured_providers().await;
+    for provider in &bundle.oauth.providers {
+        if !configured.contains(provider) {
+            required.push(Requirement::OauthCredentials {
+                provider: provider.clone(),
+            });
+        }
+    }
+
+    {
+        let store = oauth::get_token_store().await;
+        for req in &bundle.oauth.servers {
+            let authorized = store
+                .as_ref()
+                .map(|s| s.has_tokens(&req.server_id))
+                .unwrap_or(false);
+            if !authorized {
+                required.push(Requirement::OauthAuthorization {
+                    server_id: req.server_id.clone(),
+                    provider: req.provider.clone(),
+                    scopes: req.scopes.clone(),
+                });
+            }
+        }
+    }
+
+    for server in &bundle.servers {
+        let supplied = secrets.env.get(&server.id);
+        for key in &server.env_keys {
+            if !supplied.map(|e| e.contains_key(key)).unwrap_or(false) {
+                required.push(Requirement::Env {
+                    server_id: server.id.clone(),
+                    name: key.clone(),
+                });
+            }
+        }
+    }
+
+    required
+}
+
+/// Apply a bundle's LLM configuration, merging into the existing config.
+fn apply_llm(section: &LlmSection, secrets: &ImportSecrets) -> Result<(), String> {
+    let mut cfg = llm::get_config().unwrap_or_default();
+    // Adding the first provider makes it the default, so check beforehand
+    let had_default = cfg.default_provider.is_some();
+
+    for p in &section.providers {
+        let api_key = secrets.api_keys.get(&p.id).cloned();
+        match cfg.get_instance_mut(&p.id) {
+            Some(existing) => {
+                existing.base_url = p.base_url.clone();
+                if api_key.is_some() {
+                    existing.api_key = api_key;
+                }
+            }
+            None => {
+                let mut instance = ProviderInstance::with_id(&p.id, &p.provider_type, &p.name);
+                instance.base_url = p.base_url.clone();
+                instance.api_key = api_key;
+                cfg.add_instance(instance);
+            }
+        }
+    }
+
+    for model in &section.models {
+        if cfg.get_model(&model.name).is_none() {
+            cfg.add_model(&model.model_id, Some(&model.name));
+        }
+    }
+
+    // Like the default model, the bundle's default provider only fills a gap
+    if !had_default {
+        if let Some(ref provider) = section.default_provider {
+            cfg.set_global_default(provider);
+        }
+    }
+    if cfg.default_model.is_none() {
+        cfg.default_model = section.default_model.clone();
+    }
+
+    llm::set_config(cfg.clone());
+    cfg.save().map_err(|e| format!("Failed to save LLM config: {}", e))
+}
+
+// ============================================================================
+// RPC Handlers
+// ============================================================================
+
+/// Export the current workspace as a bundle.
+pub async fn rpc_export(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    crate::rpc::require_extension("export the workspace")?;
+    let name = params
+        .get("name")
+        .and_then(|v| v.as_str())
+        .unwrap_or("Harbor workspace");
+
+    let bundle = export_bundle(name).await;
+
+    tracing::info!(
+        "Exported workspace '{}' ({} servers, {} providers)",
+        bundle.name,
+        bundle.servers.len(),
+        bundle.llm.providers.len()
+    );
+
+    serde_json::to_value(&bundle).map_err(|e| RpcError::internal(e.to_string()))
+}
+
+/// Import a workspace bundle.
+///
+/// With `dry_run: true` (or when secrets are still missing and `partial` is
+/// not set) nothing is applied; the response lists what the receiver needs to
+/// provide so the extension can prompt for it and call again.
+pub async fn rpc_import(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    crate::rpc::require_extension("import a workspace")?;
+    let bundle: WorkspaceBundle = params
+        .get("bundle")
+        .cloned()
+        .ok_or_else(|| RpcError::invalid_params("Missing 'bundle' parameter"))
+        .and_then(|b| {
+            serde_json::from_value(b).map_err(|e| RpcError::invalid_params(format!("Invalid bundle: {}", e)))
+        })?;
+    validate(&bundle).map_err(RpcError::invalid_params)?;
+
+    let secrets: ImportSecrets = match params.get("secrets") {
+        Some(s) => serde_json::from_value(s.clone())
+            .map_err(|e| RpcError::invalid_params(format!("Invalid secrets: {}", e)))?,
+        None => ImportSecrets::default(),
+    };
+    let dry_run = params.get("dry_run").and_then(|v| v.as_bool()).unwrap_or(false);
+    let partial = params.get("partial").and_then(|v| v.as_bool()).unwrap_or(false);
+
+    let required = requirements(&bundle, &secrets).await;
+    // OAuth authorizations can only happen after import, so they never block it
+    let blocking = required
+        .iter()
+        .any(|r| !matches!(r, Requirement::OauthAuthorization { .. }));
+
+    if dry_run || (blocking && !partial) {
+        return Ok(serde_json::json!({
+            "applied": false,
+            "name": bundle.name,
+            "servers": bundle.servers.iter().map(|s| &s.id).collect::<Vec<_>>(),
+            "required": required,
+        }));
+    }
+
+    apply_llm(&bundle.llm, &secrets).map_err(|e| RpcError::new(-32000, e))?;
+
+    let mut started = Vec::new();
+    let mut failed = Vec::new();
+    let running: Vec<String> = js::list_definitions().await.into_iter().map(|d| d.id).collect();
+
+    for server in bundle.servers {
+        if running.contains(&server.id) {
+            continue;
+        }
+        let env = secrets.env.get(&server.id).cloned().unwrap_or_default();
+        let start_params = serde_json::json!({
+            "id": server.id,
+            "code": server.code,
+            "env": env,
+            "capabilities": server.capabilities,
+            "version": server.version,
+        });
+        match js::start_server(start_params).await {
+            Ok(_) => started.push(server.id),
+            Err(e) => failed.push(serde_json::json!({ "id": server.id, "error": e.message })),
+        }
+    }
+
+    tracing::info!(
+        "Imported workspace '{}' ({} servers started, {} failed)",
+        bundle.name,
+        started.len(),
+        failed.len()
+    );
+
+    Ok(serde_json::json!({
+        "applied": true,
+        "name": bundle.name,
+        "started": started,
+        "failed": failed,
+        "required": required,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bundle() -> WorkspaceBundle {
+        WorkspaceBundle {
+            format: BUNDLE_FORMAT.to_string(),
+            version: BUNDLE_VERSION,
+            name: "research".to_string(),
+            exported_at: 0,
+            servers: vec![],
+            llm: LlmSection::default(),
+            oauth: OAuthSection::default(),
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(validate(&bundle()).is_ok());
+
+        let mut wrong_format = bundle();
+        wrong_format.format = "something-else".to_string();
+        assert!(validate(&wrong_format).is_err());
+
+        let mut too_new = bundle();
+        too_new.version = BUNDLE_VERSION + 1;
+        assert!(validate(&too_new).is_err());
+    }
+
+    #[test]
+    fn test_requirement_serialization() {
+        let req = Requirement::Env {
+            server_id: "gmail".to_string(),
+            name: "API_KEY".to_string(),
+        };
+        let json = serde_json::to_value(&req).unwrap();
+        assert_eq!(json["kind"], "env");
+        assert_eq!(json["name"], "API_KEY");
+    }
+}
@@ -0,0 +1,248 @@
+//! Aggregate usage statistics for a workspace.
+//!
+//! Stats are computed locally from the tool call history and bucketed over
+//! time. When an `epsilon` is supplied, counts and average durations are
+//! perturbed with Laplace noise and tiny buckets are suppressed, so the numbers can be shared (e.g.
+//! in a bug report or team dashboard) without revealing individual calls.
+
+use rand::Rng;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use crate::history::{self, HistoryEntry};
+use crate::rpc::RpcError;
+
+/// Default estimate of manual effort replaced by one successful tool call.
+const DEFAULT_SECONDS_SAVED_PER_CALL: f64 = 60.0;
+
+/// Buckets with fewer calls than this are suppressed in noisy mode.
+const MIN_BUCKET_CALLS: f64 = 5.0;
+
+/// Most one call adds to a bucket's total duration in noisy mode, which
+/// bounds how much noise hides it.
+const MAX_NOISY_DURATION_MS: f64 = 60_000.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BucketSize {
+    Hour,
+    Day,
+    Week,
+}
+
+impl BucketSize {
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "hour" => Some(Self::Hour),
+            "day" => Some(Self::Day),
+            "week" => Some(Self::Week),
+            _ => None,
+        }
+    }
+
+    fn millis(self) -> i64 {
+        match self {
+            Self::Hour => 3_600_000,
+            Self::Day => 86_400_000,
+            Self::Week => 7 * 86_400_000,
+        }
+    }
+
+    /// Start of the bucket containing `timestamp`.
+    fn floor(self, timestamp: i64) -> i64 {
+        timestamp - timestamp.rem_euclid(self.millis())
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ToolStats {
+    pub calls: f64,
+    pub errors: f64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BucketStats {
+    /// Bucket start (Unix timestamp ms)
+    pub start: i64,
+    pub calls: f64,
+    pub errors: f64,
+    pub error_rate: f64,
+    pub avg_duration_ms: f64,
+    pub time_saved_seconds: f64,
+    /// Keyed by "server_id/tool"
+    pub tools: BTreeMap<String, ToolStats>,
+    /// Total duration with each call capped at `MAX_NOISY_DURATION_MS`
+    #[serde(skip)]
+    capped_duration_ms: f64,
+}
+
+/// Aggregate history entries into time buckets.
+pub fn aggregate(entries: &[HistoryEntry], bucket: BucketSize, seconds_saved_per_call: f64) -> Vec<BucketStats> {
+    let mut buckets: BTreeMap<i64, (BucketStats, u64)> = BTreeMap::new();
+
+    for entry in entries {
+        let start = bucket.floor(entry.timestamp);
+        let (stats, total_duration) = buckets.entry(start).or_insert_with(|| {
+            (BucketStats { start, ..Default::default() }, 0)
+        });
+
+        stats.calls += 1.0;
+        *total_duration += entry.duration_ms;
+        stats.capped_duration_ms += (entry.duration_ms as f64).min(MAX_NOISY_DURATION_MS);
+        let tool = stats
+            .tools
+            .entry(format!("{}/{}", entry.server_id, entry.tool))
+            .or_default();
+        tool.calls += 1.0;
+        if !entry.ok {
+            stats.errors += 1.0;
+            tool.errors += 1.0;
+        }
+    }
+
+    buckets
+        .into_values()
+        .map(|(mut stats, total_duration)| {
+            stats.avg_duration_ms = total_duration as f64 / stats.calls;
+            finish(&mut stats, seconds_saved_per_call);
+            stats
+        })
+        .collect()
+}
+
+/// Recompute derived fields from the counts.
+fn finish(stats: &mut BucketStats, seconds_saved_per_call: f64) {
+    stats.error_rate = if stats.calls > 0.0 { stats.errors / stats.calls } else { 0.0 };
+    stats.time_saved_seconds = (stats.calls - stats.errors).max(0.0) * seconds_saved_per_call;
+}
+
+/// Sample Laplace(0, scale) noise.
+fn laplace(rng: &mut impl Rng, scale: f64) -> f64 {
+    let u: f64 = rng.gen_range(-0.5..0.5);
+    -scale * u.signum() * (1.0 - 2.0 * u.abs()).ln()
+}
+
+/// Perturb counts with Laplace noise (sensitivity 1) and drop small buckets.
+/// The average duration is recomputed from the capped total duration with
+/// noise scaled to the cap.
+fn add_noise(buckets: Vec<BucketStats>, epsilon: f64, seconds_saved_per_call: f64) -> Vec<BucketStats> {
+    let mut rng = rand::thread_rng();
+    let scale = 1.0 / epsilon;
+
+    buckets
+        .into_iter()
+        .filter_map(|mut stats| {
+            stats.calls = (stats.calls + laplace(&mut rng, scale)).max(0.0).round();
+            if stats.calls < MIN_BUCKET_CALLS {
+                return None;
+            }
+            stats.errors = (stats.errors + laplace(&mut rng, scale)).clamp(0.0, stats.calls).round();
+            let total = stats.capped_duration_ms + laplace(&mut rng, scale * MAX_NOISY_DURATION_MS);
+            stats.avg_duration_ms = (total / stats.calls).clamp(0.0, MAX_NOISY_DURATION_MS).round();
+            stats.tools.retain(|_, tool| {
+                tool.calls = (tool.calls + laplace(&mut rng, scale)).max(0.0).round();
+                tool.errors = (tool.errors + laplace(&mut rng, scale)).clamp(0.0, tool.calls).round();
+                tool.calls >= MIN_BUCKET_CALLS
+            });
+            finish(&mut stats, seconds_saved_per_call);
+            Some(stats)
+        })
+        .collect()
+}
+
+/// Compute usage stats for the workspace.
+///
+/// Params: `bucket` ("hour" | "day" | "week", default "day"), optional
+/// `since`/`until` (Unix ms), `seconds_saved_per_call`, and `epsilon` to
+/// enable noisy (privacy-preserving) output.
+pub async fn rpc_stats(params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+    let bucket_name = params.get("bucket").and_then(|v| v.as_str()).unwrap_or("day");
+    let bucket = BucketSize::parse(bucket_name)
+        .ok_or_else(|| RpcError::invalid_params(format!("Unknown bucket '{}'", bucket_name)))?;
+    let since = params.get("since").and_then(|v| v.as_i64());
+    let until = params.get("until").and_then(|v| v.as_i64());
+    let seconds_saved = params
+        .get("seconds_saved_per_call")
+        .and_then(|v| v.as_f64())
+        .unwrap_or(DEFAULT_SECONDS_SAVED_PER_CALL);
+    let epsilon = params.get("epsilon").and_then(|v| v.as_f64());
+
+    if let Some(e) = epsilon {
+        if e <= 0.0 {
+            return Err(RpcError::invalid_params("'epsilon' must be positive"));
+        }
+    }
+
+    let entries = tokio::task::spawn_blocking(move || history::load(since, until))
+        .await
+        .map_err(|e| RpcError::internal(e.to_string()))?;
+
+    let mut buckets = aggregate(&entries, bucket, seconds_saved);
+    if let Some(e) = epsilon {
+        buckets = add_noise(buckets, e, seconds_saved);
+    }
+
+    let total_calls: f64 = buckets.iter().map(|b| b.calls).sum();
+    let total_errors: f64 = buckets.iter().map(|b| b.errors).sum();
+
+    Ok(serde_json::json!({
+        "bucket": bucket_name,
+        "noisy": epsilon.is_some(),
+        "totals": {
+            "calls": total_calls,
+            "errors": total_errors,
+            "error_rate": if total_calls > 0.0 { total_errors / total_calls } else { 0.0 },
+            "time_saved_seconds": buckets.iter().map(|b| b.time_saved_seconds).sum::<f64>(),
+        },
+        "buckets": buckets,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(timestamp: i64, ok: bool) -> HistoryEntry {
+        HistoryEntry {
+            timestamp,
+            server_id: "time".to_string(),
+            tool: "time.now".to_string(),
+            duration_ms: 10,
+            ok,
+            error: None,
+            args: None,
+            result: None,
+        }
+    }
+
+    #[test]
+    fn test_aggregate_buckets() {
+        let hour = 3_600_000;
+        let entries = vec![entry(0, true), entry(10, false), entry(hour + 5, true)];
+        let buckets = aggregate(&entries, BucketSize::Hour, 60.0);
+
+        assert_eq!(buckets.len(), 2);
+        assert_eq!(buckets[0].calls, 2.0);
+        assert_eq!(buckets[0].errors, 1.0);
+        assert_eq!(buckets[0].error_rate, 0.5);
+        assert_eq!(buckets[0].time_saved_seconds, 60.0);
+        assert_eq!(buckets[1].start, hour);
+    }
+
+    #[test]
+    fn test_noise_suppresses_small_buckets() {
+        let buckets = aggregate(&[entry(0, true)], BucketSize::Day, 60.0);
+        // A huge epsilon means negligible noise, so one call stays below the threshold
+        assert!(add_noise(buckets, 1e9, 60.0).is_empty());
+    }
+
+    #[test]
+    fn test_noise_covers_durations() {
+        let mut entries: Vec<HistoryEntry> = (0..10).map(|i| entry(i, true)).collect();
+        entries[0].duration_ms = 3_600_000;
+        let buckets = aggregate(&entries, BucketSize::Day, 60.0);
+        assert_eq!(buckets[0].avg_duration_ms, 360_009.0);
+        // One slow call counts for no more than the cap once noise is added
+        let noisy = add_noise(buckets, 1e9, 60.0);
+        assert_eq!(noisy[0].avg_duration_ms, 6_009.0);
+    }
+}
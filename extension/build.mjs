@@ -1,11 +1,14 @@
 import { build, context } from 'esbuild';
-import { copyFile, mkdir, cp, rm, readFile, writeFile } from 'node:fs/promises';
+import { copyFile, mkdir, cp, rm, readFile, readdir, writeFile } from 'node:fs/promises';
 import { existsSync } from 'node:fs';
+import { execFileSync } from 'node:child_process';
 import sharp from 'sharp';
 
 const isWatch = process.argv.includes('--watch');
 const isChrome = process.argv.includes('--chrome');
 const isSafari = process.argv.includes('--safari');
+// Use the builtin modules already in assets/ instead of compiling them
+const skipBuiltins = process.argv.includes('--skip-builtins');
 
 // Determine target browser
 const targetBrowser = isChrome ? 'chrome' : isSafari ? 'safari' : 'firefox';
@@ -35,8 +38,47 @@ const entryPoints = [
   'src/sidebar.ts',
   'src/demo-bootstrap.ts',
   'src/js-runtime/worker-loader.ts',
+  'src/wasm/worker.ts',
 ];
 
+const BUILTIN_DIR = '../mcp-servers/builtin';
+
+// Compile each WASM builtin in mcp-servers/builtin into assets/<name>.wasm,
+// the module the extension installs it from (see src/storage/servers.ts).
+// Fails if any builtin's module is missing afterwards, since the extension
+// would silently leave that builtin out.
+async function buildBuiltins() {
+  const missing = [];
+  for (const dir of (await readdir(BUILTIN_DIR)).sort()) {
+    const manifestPath = `${BUILTIN_DIR}/${dir}/manifest.json`;
+    if (!existsSync(manifestPath)) {
+      continue;
+    }
+    const manifest = JSON.parse(await readFile(manifestPath, 'utf-8'));
+    // JS builtins are inlined into the background script
+    if (!manifest.wasm) {
+      continue;
+    }
+    const asset = `assets/${manifest.name}.wasm`;
+    if (!skipBuiltins) {
+      const target = manifest.wasm.wasi?.version === 'preview2' ? 'wasm32-wasip2' : 'wasm32-wasip1';
+      console.log(`[Harbor] Building ${manifest.id} (${target})...`);
+      execFileSync('cargo', ['build', '--release', '--target', target], {
+        cwd: `${BUILTIN_DIR}/${dir}`,
+        stdio: 'inherit',
+      });
+      await copyFile(`${BUILTIN_DIR}/${dir}/${manifest.wasm.file}`, asset);
+    }
+    if (!existsSync(asset)) {
+      missing.push(`${manifest.id} (${asset} from ${BUILTIN_DIR}/${dir}/${manifest.wasm.file})`);
+    }
+  }
+  if (missing.length > 0) {
+    throw new Error(`[Harbor] Builtin modules missing from assets/:\n  ${missing.join('\n  ')}`);
+  }
+  console.log('[Harbor] ✓ Builtin modules are in assets/');
+}
+
 // Generate PNG icons from SVG for Chrome (Chrome prefers PNG)
 async function generatePngIcons(svgPath, outputDir) {
   const sizes = [16, 32, 48, 128];
@@ -139,6 +181,8 @@ async function copyStatic() {
 
 console.log(`[Harbor] Building for ${targetBrowser}${useESM ? ' (ESM)' : ' (IIFE)'}...`);
 
+await buildBuiltins();
+
 if (isWatch) {
   const ctx = await context({
     ...common,
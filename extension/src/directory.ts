@@ -10,6 +10,7 @@
 
 import { browserAPI } from './browser-compat';
 import { loadFromUrl, loadFromFile, type LoadResult } from './storage/package-loader';
+import { validateServer } from './mcp/approval-prompt';
 // NOTE: Feature flags are enforced by Web Agents API extension, not Harbor
 
 // Make this a module to avoid global scope conflicts
@@ -316,10 +317,7 @@ async function installServer(server: BundledServer): Promise<void> {
       }
 
       // Start the server
-      await browserAPI.runtime.sendMessage({
-        type: 'sidebar_validate_server',
-        serverId: server.id,
-      });
+      await validateServer(server.id, server.name);
     } else if (server.runtime === 'js' && server.manifestUrl) {
       // Load JS manifest
       const manifestResponse = await fetch(browserAPI.runtime.getURL(server.manifestUrl));
@@ -390,10 +388,7 @@ async function installServer(server: BundledServer): Promise<void> {
       }
 
       // Start the server
-      await browserAPI.runtime.sendMessage({
-        type: 'sidebar_validate_server',
-        serverId: server.id,
-      });
+      await validateServer(server.id, server.name);
     }
 
     installedServerIds.add(server.id);
@@ -613,13 +608,14 @@ async function handleLoadResult(result: LoadResult): Promise<void> {
     }
 
     // Start the server to validate it
-    await browserAPI.runtime.sendMessage({
-      type: 'sidebar_validate_server',
-      serverId: manifest.id,
-    });
+    await validateServer(manifest.id, manifest.name);
 
     installedServerIds.add(manifest.id);
-    showToast(`Installed ${manifest.name}`, 'success');
+    if (response.warning) {
+      showToast(`Installed ${manifest.name}. ${response.warning}`, 'info');
+    } else {
+      showToast(`Installed ${manifest.name}`, 'success');
+    }
     refreshList();
   } catch (err) {
     showToast(`Failed to install: ${err instanceof Error ? err.message : String(err)}`, 'error');
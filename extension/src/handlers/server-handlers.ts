@@ -6,7 +6,10 @@
 
 import { registerAsyncHandler, registerHandler, errorResponse } from './types';
 import {
+  addBridgeServer,
   addServer,
+  approveServer,
+  listBridgeOffers,
   startServer,
   stopServer,
   validateAndStartServer,
@@ -56,7 +59,7 @@ export function registerServerHandlers(): void {
       return true;
     }
     addServer(message.manifest as Parameters<typeof addServer>[0])
-      .then(() => sendResponse({ ok: true }))
+      .then(({ warning }) => sendResponse({ ok: true, warning }))
       .catch((error) => sendResponse(errorResponse(error)));
     return true;
   });
@@ -74,6 +77,38 @@ export function registerServerHandlers(): void {
     return true;
   });
 
+  // Servers installed through the bridge, waiting for the user to add them
+  registerAsyncHandler('sidebar_get_bridge_offers', async () => {
+    return { ok: true, offers: listBridgeOffers() };
+  });
+
+  // Add one of them
+  registerHandler('sidebar_add_bridge_server', (message, _sender, sendResponse) => {
+    const serverId = message.serverId as string | undefined;
+    if (!serverId) {
+      sendResponse({ ok: false, error: 'Missing serverId' });
+      return true;
+    }
+    addBridgeServer(serverId)
+      .then(({ warning }) => sendResponse({ ok: true, warning }))
+      .catch((error) => sendResponse(errorResponse(error)));
+    return true;
+  });
+
+  // Record the user's approval of what a server asked for
+  registerHandler('sidebar_approve_server', (message, _sender, sendResponse) => {
+    const serverId = message.serverId as string | undefined;
+    const approval = message.approval as Parameters<typeof approveServer>[1] | undefined;
+    if (!serverId || !approval) {
+      sendResponse({ ok: false, error: 'Missing serverId or approval' });
+      return true;
+    }
+    approveServer(serverId, approval)
+      .then(() => sendResponse({ ok: true }))
+      .catch((error) => sendResponse(errorResponse(error)));
+    return true;
+  });
+
   // Remove a server
   registerHandler('sidebar_remove_server', (message, _sender, sendResponse) => {
     const serverId = message.serverId as string | undefined;
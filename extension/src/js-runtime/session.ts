@@ -50,6 +50,9 @@ async function fetchOAuthTokens(manifest: McpServerManifest): Promise<Record<str
   }
 
   const { provider, scopes, tokenEnvVar, refreshTokenEnvVar } = manifest.oauth;
+  if (!tokenEnvVar) {
+    throw new Error(`Server "${manifest.name}" must set oauth.tokenEnvVar`);
+  }
   
   // Check OAuth status
   const statusResult = await bridgeRequest<{
@@ -24,6 +24,23 @@ const NATIVE_APP_ID = useSafariMode ? NATIVE_APP_ID_SAFARI : NATIVE_APP_ID_DEFAU
 const SAFARI_HTTP_PORT = 8766;
 const SAFARI_HTTP_BASE = `http://127.0.0.1:${SAFARI_HTTP_PORT}`;
 
+let safariToken: string | null = null;
+
+/**
+ * Safari-specific: The token harbor-bridge wants on /rpc from the
+ * extension, kept in ~/.harbor/extension-token. The extension can't read
+ * the file, so it asks the containing app, Harbor.app.
+ */
+async function safariBridgeToken(): Promise<string> {
+  if (safariToken) return safariToken;
+  const reply = (await browserAPI.runtime.sendNativeMessage(NATIVE_APP_ID_SAFARI, { type: 'extension_token' })) as
+    | { token?: string }
+    | undefined;
+  if (!reply?.token) throw new Error('Harbor.app did not give the bridge token');
+  safariToken = reply.token;
+  return safariToken;
+}
+
 /**
  * Safari-specific: Send RPC request via HTTP to harbor-bridge server.
  * This avoids sandbox restrictions with native messaging.
@@ -37,6 +54,7 @@ async function safariHttpRequest<T>(method: string, params: unknown = {}): Promi
       method: 'POST',
       headers: {
         'Content-Type': 'application/json',
+        Authorization: `Bearer ${await safariBridgeToken()}`,
       },
       body: JSON.stringify({
         id,
@@ -46,6 +64,8 @@ async function safariHttpRequest<T>(method: string, params: unknown = {}): Promi
     });
     
     if (!response.ok) {
+      // A bridge started afresh may have made a new token
+      if (response.status === 401) safariToken = null;
       const text = await response.text().catch(() => '');
       throw new Error(`HTTP ${response.status}: ${response.statusText} - ${text}`);
     }
@@ -132,7 +152,8 @@ type IncomingMessage =
   | { type: 'status'; status: string; message: string }
   | { type: 'rpc_response'; id: string; result?: unknown; error?: { code: number; message: string } }
   | { type: 'stream'; id: string; event: StreamEvent }
-  | { type: 'console'; server_id: string; level: string; message: string };
+  | { type: 'console'; server_id: string; level: string; message: string }
+  | { type: 'event'; event: string; payload: unknown };
 
 type StreamEvent = {
   id: string;
@@ -168,6 +189,10 @@ const pendingStreams = new Map<string, PendingStream>();
 type ConsoleLogListener = (serverId: string, level: string, message: string) => void;
 const consoleLogListeners: ConsoleLogListener[] = [];
 
+// Bridge event listeners (events::emit on the bridge side)
+type BridgeEventListener = (event: string, payload: unknown) => void;
+const bridgeEventListeners: BridgeEventListener[] = [];
+
 export type ConnectionState = {
   connected: boolean;
   bridgeReady: boolean;
@@ -206,6 +231,17 @@ function updateState(update: Partial<ConnectionState>): void {
   notifyConnectionListeners();
 }
 
+/**
+ * Add a listener for events the bridge emits, such as `servers/changed`
+ */
+export function onBridgeEvent(listener: BridgeEventListener): () => void {
+  bridgeEventListeners.push(listener);
+  return () => {
+    const idx = bridgeEventListeners.indexOf(listener);
+    if (idx >= 0) bridgeEventListeners.splice(idx, 1);
+  };
+}
+
 /**
  * Add a listener for console logs from JS servers
  */
@@ -308,6 +344,17 @@ function handleMessage(message: IncomingMessage): void {
       }
       break;
     }
+
+    case 'event': {
+      for (const listener of bridgeEventListeners) {
+        try {
+          listener(message.event, message.payload);
+        } catch (e) {
+          console.error('[Harbor] Bridge event listener error:', e);
+        }
+      }
+      break;
+    }
   }
 }
 
@@ -0,0 +1,38 @@
+/**
+ * Starting a server from an extension page (sidebar, directory), asking
+ * the user to approve what it would get the first time, and again when a
+ * new build asks for more.
+ */
+
+import { browserAPI } from '../browser-compat';
+import type { ApprovalRequest } from '../wasm/session';
+
+type ValidateResponse = { ok: boolean; error?: string; approval?: ApprovalRequest };
+
+function validate(serverId: string): Promise<ValidateResponse> {
+  return browserAPI.runtime.sendMessage({ type: 'sidebar_validate_server', serverId });
+}
+
+/**
+ * Start and validate a server. If it needs approval, show the user what it
+ * asks for and, if they allow it, record that and start it again.
+ */
+export async function validateServer(serverId: string, name: string): Promise<ValidateResponse> {
+  const response = await validate(serverId);
+  if (response?.ok || !response?.approval) {
+    return response;
+  }
+  const lines = response.approval.consent.map((line) => `• ${line}`).join('\n');
+  if (!confirm(`${name} asks to:\n\n${lines}\n\nAllow it?`)) {
+    return response;
+  }
+  const approved = await browserAPI.runtime.sendMessage({
+    type: 'sidebar_approve_server',
+    serverId,
+    approval: response.approval,
+  });
+  if (!approved?.ok) {
+    return { ok: false, error: approved?.error || 'Failed to record approval' };
+  }
+  return validate(serverId);
+}
@@ -0,0 +1,133 @@
+/**
+ * Browser Context
+ *
+ * Answers the bridge's `browser/request` events, made for WASM servers
+ * granted `browser` (the `harbor:mcp/browser` import): the open tabs, the
+ * text of a tab, and bookmark and history search. Each answer goes back
+ * with `browser.respond`. Private windows are left out. Bookmarks and
+ * history are optional permissions, asked for when the browser server is
+ * started from the sidebar.
+ */
+
+import { browserAPI } from '../browser-compat';
+import { getTabReadability } from '../agents/browser-api';
+import { bridgeRequest } from '../llm/bridge-client';
+import { onBridgeEvent } from '../llm/native-bridge';
+
+interface BrowserRequest {
+  request_id: string;
+  server_id: string;
+  method: string;
+  params?: Record<string, unknown>;
+}
+
+const DEFAULT_RESULTS = 20;
+const MAX_RESULTS = 100;
+const DEFAULT_HISTORY_DAYS = 7;
+const DAY_MS = 24 * 60 * 60 * 1000;
+
+function positive(value: unknown, fallback: number, max: number): number {
+  const n = typeof value === 'number' && Number.isFinite(value) ? Math.floor(value) : fallback;
+  return Math.min(Math.max(n, 1), max);
+}
+
+function query(params: Record<string, unknown>): string {
+  const text = typeof params.query === 'string' ? params.query.trim() : '';
+  if (!text) throw new Error('Give a search query');
+  return text;
+}
+
+async function listTabs(): Promise<unknown> {
+  const tabs = await browserAPI.tabs.query({});
+  return tabs
+    .filter((tab) => !tab.incognito)
+    .map((tab) => ({
+      id: tab.id,
+      window_id: tab.windowId,
+      title: tab.title,
+      url: tab.url,
+      active: tab.active,
+      pinned: tab.pinned,
+    }));
+}
+
+async function readTab(params: Record<string, unknown>): Promise<unknown> {
+  const tabId = params.tab_id;
+  if (typeof tabId !== 'number') throw new Error("Missing 'tab_id'");
+  const tab = await browserAPI.tabs.get(tabId).catch(() => undefined);
+  if (!tab || tab.incognito) throw new Error(`No tab with ID ${tabId}`);
+  return getTabReadability(tabId);
+}
+
+async function requirePermission(permission: 'bookmarks' | 'history'): Promise<void> {
+  const granted = await browserAPI.permissions?.contains({ permissions: [permission] }).catch(() => false);
+  if (!granted) throw new Error(`The user hasn't allowed ${permission} access`);
+}
+
+async function searchBookmarks(params: Record<string, unknown>): Promise<unknown> {
+  await requirePermission('bookmarks');
+  if (!browserAPI.bookmarks) throw new Error('Bookmarks are not available in this browser');
+  const maxResults = positive(params.max_results, DEFAULT_RESULTS, MAX_RESULTS);
+  const nodes = await browserAPI.bookmarks.search(query(params));
+  return nodes
+    .filter((node) => node.url)
+    .slice(0, maxResults)
+    .map((node) => ({
+      title: node.title,
+      url: node.url,
+      added_at: node.dateAdded ? new Date(node.dateAdded).toISOString() : null,
+    }));
+}
+
+async function searchHistory(params: Record<string, unknown>): Promise<unknown> {
+  await requirePermission('history');
+  if (!browserAPI.history) throw new Error('History is not available in this browser');
+  const days = positive(params.days, DEFAULT_HISTORY_DAYS, 365);
+  const items = await browserAPI.history.search({
+    text: query(params),
+    startTime: Date.now() - days * DAY_MS,
+    maxResults: positive(params.max_results, DEFAULT_RESULTS, MAX_RESULTS),
+  });
+  return items.map((item) => ({
+    title: item.title,
+    url: item.url,
+    last_visit_at: item.lastVisitTime ? new Date(item.lastVisitTime).toISOString() : null,
+    visit_count: item.visitCount,
+  }));
+}
+
+function answer(method: string, params: Record<string, unknown>): Promise<unknown> {
+  switch (method) {
+    case 'tabs.list':
+      return listTabs();
+    case 'tab.readContent':
+      return readTab(params);
+    case 'bookmarks.search':
+      return searchBookmarks(params);
+    case 'history.search':
+      return searchHistory(params);
+    default:
+      return Promise.reject(new Error(`Unknown browser method '${method}'`));
+  }
+}
+
+async function respond({ request_id, server_id, method, params }: BrowserRequest): Promise<void> {
+  try {
+    const result = await answer(method, params || {});
+    await bridgeRequest('browser.respond', { request_id, result });
+  } catch (e) {
+    const error = e instanceof Error ? e.message : String(e);
+    console.warn('[Harbor] Browser request', method, 'from', server_id, 'failed:', error);
+    await bridgeRequest('browser.respond', { request_id, error });
+  }
+}
+
+/**
+ * Start answering the bridge's browser requests.
+ */
+export function initializeBrowserContext(): void {
+  onBridgeEvent((event, payload) => {
+    if (event !== 'browser/request') return;
+    respond(payload as BrowserRequest).catch((e) => console.warn('[Harbor] Failed to answer browser request:', e));
+  });
+}
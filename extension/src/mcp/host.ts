@@ -5,6 +5,7 @@ import {
   initializeMcpRuntime,
   listMcpServers,
   listRunningServerIds,
+  pendingApproval,
   registerMcpServer,
   startMcpServer,
   stopMcpServer,
@@ -16,15 +17,36 @@ import {
   removeInstalledServer,
   updateInstalledServer,
 } from '../storage/servers';
+import { bridgeRequest } from '../llm/bridge-client';
+import { onBridgeEvent, onConnectionStateChange } from '../llm/native-bridge';
+import { checkSignature } from '../wasm/signature';
+import type { ApprovalRequest } from '../wasm/session';
 import type { McpServerManifest } from '../wasm/types';
+import { initializeBrowserContext } from './browser-context';
 
 export function initializeMcpHost(): void {
   console.log('[Harbor] MCP host starting...');
   initializeMcpRuntime();
+  // Tabs, bookmarks and history for servers granted `browser`
+  initializeBrowserContext();
   ensureBuiltinServers().then(async (servers) => {
     // Register all servers
     servers.forEach((server) => registerMcpServer(server));
     console.log('[Harbor] MCP host ready (WASM + JS support).');
+
+    // Servers installed through the bridge (servers.install or its CLI)
+    onConnectionStateChange((state) => {
+      if (state.bridgeReady) {
+        syncBridgeServers().catch((e) => console.warn('[Harbor] Failed to sync bridge-installed servers:', e));
+      }
+    });
+
+    // Dev servers are reloaded as they are rebuilt (harbor-bridge dev)
+    onBridgeEvent((event, payload) => {
+      if (event !== 'servers/changed') return;
+      const { server_id: id } = payload as { server_id: string };
+      reloadBridgeServer(id).catch((e) => console.warn('[Harbor] Failed to reload', id, e));
+    });
     
     // Auto-start servers that were previously running
     const autoStartServers = servers.filter(s => s.autostart);
@@ -35,6 +57,9 @@ export function initializeMcpHost(): void {
           const started = await startMcpServer(server.id);
           if (started) {
             console.log('[Harbor] Auto-started:', server.id);
+          } else if (pendingApproval(server.id)) {
+            // Never approved without the user: it waits for Start in the sidebar
+            console.warn('[Harbor] Not auto-starting', server.id, 'until you approve what it asks for');
           } else {
             console.warn('[Harbor] Failed to auto-start:', server.id);
           }
@@ -46,6 +71,66 @@ export function initializeMcpHost(): void {
   });
 }
 
+/** A server installed through the bridge that the user hasn't added yet. */
+export type BridgeOffer = { id: string; name: string; version: string; source: string };
+
+/** Bridge-installed servers waiting for the user to add them, by ID. */
+const bridgeOffers = new Map<string, BridgeOffer>();
+
+/**
+ * Find servers installed through the bridge that the extension doesn't have
+ * yet. The bridge keeps its own copy, so they survive being installed while
+ * the browser is closed. They are only offered: the user adds each one in
+ * the sidebar (`addBridgeServer`). Dev servers already added are reloaded,
+ * as they may have been rebuilt meanwhile.
+ */
+async function syncBridgeServers(): Promise<void> {
+  const { servers } = await bridgeRequest<{ servers: Array<BridgeOffer & { dev?: boolean }> }>('servers.list');
+  bridgeOffers.clear();
+  for (const { id, name, version, source, dev } of servers) {
+    if (dev && getMcpServer(id)) {
+      await reloadBridgeServer(id).catch((e) => console.warn('[Harbor] Could not reload dev server', id, e));
+      continue;
+    }
+    if (getMcpServer(id)) continue;
+    bridgeOffers.set(id, { id, name, version, source });
+    console.log('[Harbor] Bridge-installed server waiting to be added:', id);
+  }
+}
+
+export function listBridgeOffers(): BridgeOffer[] {
+  return [...bridgeOffers.values()];
+}
+
+/** Add a bridge-installed server the user chose from `listBridgeOffers`. */
+export async function addBridgeServer(id: string): Promise<{ warning?: string }> {
+  if (!bridgeOffers.has(id)) {
+    throw new Error(`No bridge-installed server '${id}' is waiting to be added`);
+  }
+  const manifest = await bridgeRequest<McpServerManifest>('servers.read', { id });
+  const added = await addServer(manifest);
+  bridgeOffers.delete(id);
+  return added;
+}
+
+/**
+ * Replace a bridge-installed server with the bridge's current build,
+ * restarting it if it was running.
+ */
+async function reloadBridgeServer(id: string): Promise<void> {
+  const manifest = await bridgeRequest<McpServerManifest>('servers.read', { id });
+  const autostart = getMcpServer(id)?.manifest.autostart;
+  const wasRunning = listRunningServerIds().includes(id);
+  if (wasRunning) {
+    stopMcpServer(id);
+  }
+  await addServer({ ...manifest, autostart });
+  if (wasRunning) {
+    await startMcpServer(id);
+  }
+  console.log('[Harbor] Reloaded', id);
+}
+
 export async function listRegisteredServers(): Promise<McpServerManifest[]> {
   return listMcpServers().map((handle) => handle.manifest);
 }
@@ -58,9 +143,21 @@ export async function listServersWithStatus(): Promise<Array<McpServerManifest &
   }));
 }
 
-export async function addServer(manifest: McpServerManifest): Promise<void> {
+/**
+ * Install a server. WASM packages are refused or flagged according to the
+ * bridge's signature policy; a warning comes back for the caller to show.
+ */
+export async function addServer(manifest: McpServerManifest): Promise<{ warning?: string }> {
+  const signature = await checkSignature(manifest);
+  if (!signature.allowed) {
+    throw new Error(signature.error || 'Package signature was refused');
+  }
+  if (signature.warning) {
+    console.warn('[Harbor]', manifest.id, signature.warning);
+  }
   registerMcpServer(manifest);
   await addInstalledServer(manifest);
+  return { warning: signature.warning };
 }
 
 export async function startServer(serverId: string): Promise<boolean> {
@@ -77,9 +174,19 @@ export async function startServer(serverId: string): Promise<boolean> {
   return started;
 }
 
-export async function validateAndStartServer(serverId: string): Promise<{ ok: boolean; tools?: McpServerManifest['tools']; error?: string }> {
+export async function validateAndStartServer(serverId: string): Promise<{
+  ok: boolean;
+  tools?: McpServerManifest['tools'];
+  error?: string;
+  /** Set when the server needs the user's approval before it can start */
+  approval?: ApprovalRequest;
+}> {
   const started = await startMcpServer(serverId);
   if (!started) {
+    const approval = pendingApproval(serverId);
+    if (approval) {
+      return { ok: false, error: 'The server needs your approval to start', approval };
+    }
     return { ok: false, error: 'Failed to start server' };
   }
   
@@ -110,6 +217,11 @@ export async function validateAndStartServer(serverId: string): Promise<{ ok: bo
   }
 }
 
+/** Record that the user approved what a server asked for (see `validateAndStartServer`). */
+export async function approveServer(serverId: string, approval: ApprovalRequest): Promise<void> {
+  await bridgeRequest('wasm.approve', { id: serverId, module: approval.module, consent: approval.consent });
+}
+
 export async function stopServer(serverId: string): Promise<boolean> {
   const stopped = stopMcpServer(serverId);
   if (stopped) {
@@ -127,6 +239,8 @@ export async function stopServer(serverId: string): Promise<boolean> {
 export async function removeServer(serverId: string): Promise<void> {
   unregisterMcpServer(serverId);
   await removeInstalledServer(serverId);
+  // Or it comes back on the next sync
+  await bridgeRequest('servers.remove', { id: serverId }).catch(() => undefined);
 }
 
 export async function listTools(serverId: string): Promise<McpServerManifest['tools']> {
@@ -139,9 +253,5 @@ export function callTool(
   toolName: string,
   args: Record<string, unknown>,
 ): Promise<{ ok: boolean; result?: unknown; error?: string }> {
-  const finalArgs = { ...args };
-  if (serverId === 'time-wasm' && toolName === 'time.now' && !finalArgs.now) {
-    finalArgs.now = new Date().toISOString();
-  }
-  return callMcpTool(serverId, toolName, finalArgs);
+  return callMcpTool(serverId, toolName, args);
 }
@@ -6,6 +6,13 @@ export type StdioEndpoint = {
   onData: (handler: (data: Uint8Array) => void) => void;
 };
 
+/** A message from the server that isn't a response: no `id`. */
+export type McpNotification = {
+  jsonrpc: '2.0';
+  method: string;
+  params?: unknown;
+};
+
 type PendingRequest = {
   resolve: (response: McpResponse) => void;
   reject: (error: Error) => void;
@@ -16,6 +23,7 @@ export class McpStdioTransport implements McpTransport {
   private readonly decoder = new TextDecoder();
   private buffer = '';
   private readonly pending = new Map<string, PendingRequest>();
+  private notificationHandler: ((notification: McpNotification) => void) | null = null;
 
   constructor(private readonly endpoint: StdioEndpoint) {
     this.endpoint.onData((data) => this.handleData(data));
@@ -31,6 +39,11 @@ export class McpStdioTransport implements McpTransport {
     });
   }
 
+  /** Receive the notifications the server writes between responses. */
+  onNotification(handler: (notification: McpNotification) => void): void {
+    this.notificationHandler = handler;
+  }
+
   private handleData(data: Uint8Array): void {
     this.buffer += this.decoder.decode(data, { stream: true });
     let newlineIndex = this.buffer.indexOf('\n');
@@ -46,12 +59,16 @@ export class McpStdioTransport implements McpTransport {
   }
 
   private handleLine(line: string): void {
-    let message: McpResponse | null = null;
+    let message: (McpResponse & Partial<McpNotification>) | null = null;
     try {
-      message = JSON.parse(line) as McpResponse;
+      message = JSON.parse(line) as McpResponse & Partial<McpNotification>;
     } catch (error) {
       return;
     }
+    if (message && message.id === undefined && typeof message.method === 'string') {
+      this.notificationHandler?.(message as McpNotification);
+      return;
+    }
     if (!message?.id) {
       return;
     }
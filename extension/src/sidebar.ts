@@ -2,6 +2,7 @@
 export {};
 
 import { browserAPI } from './browser-compat';
+import { validateServer } from './mcp/approval-prompt';
 
 type ServerStatus = {
   id: string;
@@ -11,9 +12,13 @@ type ServerStatus = {
   entrypoint?: string;
   remoteUrl?: string;
   tools?: Array<{ name: string }>;
+  capabilities?: { browser?: boolean };
   running: boolean;
 };
 
+// Optional permissions the browser server's bookmark and history search need
+const BROWSER_SERVER_PERMISSIONS = ['bookmarks', 'history'];
+
 type BridgeStatus = {
   ok: boolean;
   connected: boolean;
@@ -342,11 +347,17 @@ function renderServer(server: ServerStatus): HTMLElement {
     startButton.className = 'btn btn-secondary btn-sm';
     startButton.textContent = 'Start';
     startButton.addEventListener('click', async () => {
+      // Asked for while the click still counts as a user gesture
+      if (server.capabilities?.browser) {
+        const granted = await browserAPI.permissions
+          .request({ permissions: BROWSER_SERVER_PERMISSIONS })
+          .catch(() => false);
+        if (!granted) {
+          showToast(`${server.name} can only see open tabs without bookmarks and history access`);
+        }
+      }
       startButton.disabled = true;
-      const response = await browserAPI.runtime.sendMessage({
-        type: 'sidebar_validate_server',
-        serverId: server.id,
-      });
+      const response = await validateServer(server.id, server.name);
       if (!response?.ok) {
         console.error(response?.error || 'Failed to start server');
       }
@@ -431,6 +442,50 @@ function renderServer(server: ServerStatus): HTMLElement {
   return item;
 }
 
+type BridgeOffer = { id: string; name: string; version: string; source: string };
+
+/** A server installed through the bridge, which the user may add. */
+function renderBridgeOffer(offer: BridgeOffer): HTMLElement {
+  const item = document.createElement('div');
+  item.className = 'server';
+
+  const header = document.createElement('div');
+  header.className = 'server-title';
+  const name = document.createElement('span');
+  name.textContent = `${offer.name} ${offer.version}`;
+  header.appendChild(name);
+
+  const actions = document.createElement('span');
+  actions.className = 'server-actions';
+  const addButton = document.createElement('button');
+  addButton.className = 'btn btn-secondary btn-sm';
+  addButton.textContent = 'Add';
+  addButton.addEventListener('click', async () => {
+    if (!confirm(`${offer.name} was installed through harbor-bridge from ${offer.source}. Add it to Harbor?`)) {
+      return;
+    }
+    addButton.disabled = true;
+    const response = await browserAPI.runtime.sendMessage({ type: 'sidebar_add_bridge_server', serverId: offer.id });
+    if (!response?.ok) {
+      showToast('Failed to add server: ' + (response?.error || 'unknown error'));
+    } else if (response.warning) {
+      showToast(response.warning);
+    }
+    await loadServers();
+  });
+  actions.appendChild(addButton);
+  header.appendChild(actions);
+
+  const meta = document.createElement('div');
+  meta.className = 'server-meta';
+  meta.textContent = 'Installed through the bridge, not added yet';
+  meta.style.color = 'var(--color-text-muted)';
+
+  item.appendChild(header);
+  item.appendChild(meta);
+  return item;
+}
+
 let isLoadingServers = false;
 async function loadServers(): Promise<void> {
   if (isLoadingServers) {
@@ -447,8 +502,12 @@ async function loadServers(): Promise<void> {
       return;
     }
     const servers = response.servers as ServerStatus[];
+    const offers = await browserAPI.runtime.sendMessage({ type: 'sidebar_get_bridge_offers' });
+    ((offers?.offers || []) as BridgeOffer[]).forEach((offer) => serversEl.appendChild(renderBridgeOffer(offer)));
     if (!servers || servers.length === 0) {
-      serversEl.textContent = 'No servers installed.';
+      if (!serversEl.hasChildNodes()) {
+        serversEl.textContent = 'No servers installed.';
+      }
       return;
     }
     // Deduplicate by ID just in case
@@ -534,10 +593,7 @@ fileInput.addEventListener('change', async () => {
     showToast('Failed to install server');
   }
   fileInput.value = '';
-  const validate = await browserAPI.runtime.sendMessage({
-    type: 'sidebar_validate_server',
-    serverId: manifest.id,
-  });
+  const validate = await validateServer(manifest.id, manifest.name);
   if (!validate?.ok) {
     console.error(validate?.error || 'Failed to validate server');
     showToast('Failed to start server: ' + (validate?.error || 'unknown error'));
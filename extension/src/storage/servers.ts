@@ -1,5 +1,32 @@
 import { browserAPI, getExtensionURL } from '../browser-compat';
-import type { McpServerManifest } from '../wasm/types';
+import type {
+  McpSecretDecl,
+  McpServerCapabilities,
+  McpServerManifest,
+  McpServerOAuth,
+  McpToolDefinition,
+} from '../wasm/types';
+import timeManifest from '../../../mcp-servers/builtin/time-wasm/manifest.json';
+import echoManifest from '../../../mcp-servers/builtin/echo-js/manifest.json';
+import fetchManifest from '../../../mcp-servers/builtin/fetch-wasm/manifest.json';
+import memoryManifest from '../../../mcp-servers/builtin/memory-wasm/manifest.json';
+import calculatorManifest from '../../../mcp-servers/builtin/calculator-wasm/manifest.json';
+import regexManifest from '../../../mcp-servers/builtin/regex-wasm/manifest.json';
+import jsonManifest from '../../../mcp-servers/builtin/json-wasm/manifest.json';
+import randomManifest from '../../../mcp-servers/builtin/random-wasm/manifest.json';
+import encodingManifest from '../../../mcp-servers/builtin/encoding-wasm/manifest.json';
+import convertManifest from '../../../mcp-servers/builtin/convert-wasm/manifest.json';
+import markdownManifest from '../../../mcp-servers/builtin/markdown-wasm/manifest.json';
+import csvManifest from '../../../mcp-servers/builtin/csv-wasm/manifest.json';
+import diffManifest from '../../../mcp-servers/builtin/diff-wasm/manifest.json';
+import scheduleManifest from '../../../mcp-servers/builtin/schedule-wasm/manifest.json';
+import gmailManifest from '../../../mcp-servers/builtin/gmail-wasm/manifest.json';
+import driveManifest from '../../../mcp-servers/builtin/drive-wasm/manifest.json';
+import calendarManifest from '../../../mcp-servers/builtin/calendar-wasm/manifest.json';
+import githubManifest from '../../../mcp-servers/builtin/github-wasm/manifest.json';
+import searchManifest from '../../../mcp-servers/builtin/search-wasm/manifest.json';
+import browserManifest from '../../../mcp-servers/builtin/browser-wasm/manifest.json';
+import clipboardManifest from '../../../mcp-servers/builtin/clipboard-wasm/manifest.json';
 
 // New storage key for unified MCP servers
 const STORAGE_KEY = 'harbor_mcp_servers';
@@ -173,9 +200,153 @@ async function main() {
 main().catch(err => console.error('Echo server error:', err));
 `;
 
+/**
+ * A builtin's manifest.json (docs/schemas/mcp-wasm-manifest.v1.schema.json),
+ * as much of it as the extension installs the builtin from.
+ */
+type BuiltinManifest = {
+  id: string;
+  name: string;
+  displayName?: string;
+  version: string;
+  runtime?: string;
+  wasm?: { wasi?: { version?: string } };
+  capabilities?: {
+    network?: { hosts: string[] };
+    filesystem?: unknown;
+    locale?: boolean;
+    random?: boolean;
+    schedule?: boolean;
+    browser?: boolean;
+    clipboard?: boolean;
+  };
+  oauth?: { provider: string; scopes: string[] };
+  secrets?: McpSecretDecl[];
+  tools?: McpToolDefinition[];
+};
+
+const BUILTIN_MANIFESTS: BuiltinManifest[] = [
+  timeManifest,
+  echoManifest,
+  fetchManifest,
+  memoryManifest,
+  calculatorManifest,
+  regexManifest,
+  jsonManifest,
+  randomManifest,
+  encodingManifest,
+  convertManifest,
+  markdownManifest,
+  csvManifest,
+  diffManifest,
+  scheduleManifest,
+  gmailManifest,
+  driveManifest,
+  calendarManifest,
+  githubManifest,
+  searchManifest,
+  browserManifest,
+  clipboardManifest,
+];
+
+/** Sources of the JS builtins, which are inlined rather than shipped as assets */
+const BUILTIN_SCRIPTS: Record<string, string> = {
+  'echo-js': ECHO_SERVER_SOURCE,
+};
+
+/** What the extension sets for a builtin that its manifest.json can't say */
+const BUILTIN_OVERRIDES: Record<string, Partial<McpServerManifest>> = {
+  // The regex engine runs in linear time, but a huge text could still keep
+  // it busy, so calls get less time than the default.
+  'regex-wasm': { limits: { timeoutMs: 10_000 } },
+};
+
+const CAPABILITY_FLAGS = ['locale', 'random', 'schedule', 'browser', 'clipboard'] as const;
+
+/** The capabilities a WASM builtin is installed with: what its manifest declares */
+function builtinCapabilities(builtin: BuiltinManifest): McpServerCapabilities {
+  const declared = builtin.capabilities ?? {};
+  const capabilities: McpServerCapabilities = {};
+  if (declared.network) {
+    capabilities.network = { hosts: declared.network.hosts };
+  }
+  if (declared.filesystem) {
+    capabilities.files = true;
+  }
+  for (const flag of CAPABILITY_FLAGS) {
+    if (declared[flag]) {
+      capabilities[flag] = true;
+    }
+  }
+  return capabilities;
+}
+
+/**
+ * The server to install for a builtin, or null if the extension wasn't
+ * built with its module (see assets/README.md).
+ */
+async function builtinServer(builtin: BuiltinManifest): Promise<McpServerManifest | null> {
+  const base = {
+    id: builtin.id,
+    name: builtin.displayName ?? builtin.name,
+    version: builtin.version,
+    permissions: [],
+    tools: builtin.tools,
+  };
+  const overrides = BUILTIN_OVERRIDES[builtin.id];
+
+  if (builtin.runtime === 'js') {
+    const source = BUILTIN_SCRIPTS[builtin.id];
+    return source ? { ...base, runtime: 'js', scriptBase64: btoa(source), capabilities: {}, ...overrides } : null;
+  }
+
+  const entrypoint = `${builtin.name}.wasm`;
+  const moduleUrl = getExtensionURL(`assets/${entrypoint}`);
+  if (!(await hasAsset(moduleUrl))) {
+    return null;
+  }
+
+  const server: McpServerManifest = {
+    ...base,
+    runtime: 'wasm',
+    entrypoint,
+    moduleUrl,
+    capabilities: builtinCapabilities(builtin),
+  };
+  if (builtin.wasm?.wasi?.version === 'preview2') {
+    server.wasi = 'preview2';
+  }
+  if (builtin.oauth) {
+    server.oauth = {
+      provider: builtin.oauth.provider as McpServerOAuth['provider'],
+      scopes: builtin.oauth.scopes,
+    };
+  }
+  if (builtin.secrets) {
+    server.declaredSecrets = builtin.secrets.map(({ name, description, required, env }) => ({
+      name,
+      description,
+      required,
+      env,
+    }));
+  }
+  return { ...server, ...overrides };
+}
+
+async function hasAsset(url: string): Promise<boolean> {
+  try {
+    const response = await fetch(url);
+    await response.body?.cancel();
+    return response.ok;
+  } catch {
+    return false;
+  }
+}
+
 /**
  * Ensure built-in servers are always installed on startup.
  * If they were deleted, they get re-added. Only one instance of each.
+ * Builtins whose module isn't among the extension's assets are left out.
  * Also fixes moduleUrl for existing servers (Safari compatibility).
  */
 export async function ensureBuiltinServers(): Promise<McpServerManifest[]> {
@@ -190,83 +361,36 @@ export async function ensureBuiltinServers(): Promise<McpServerManifest[]> {
       timeServer.moduleUrl = correctUrl;
       await saveInstalledServers(existing);
     }
+    // Installs made for the demo module list only some of the tools, and
+    // may hold capabilities the manifest doesn't declare; both follow the
+    // manifest now that the module is built from it
+    const tools = timeManifest.tools.map((tool) => tool.name).join();
+    const capabilities = builtinCapabilities(timeManifest);
+    if (
+      timeServer.tools?.map((tool) => tool.name).join() !== tools ||
+      JSON.stringify(timeServer.capabilities ?? {}) !== JSON.stringify(capabilities)
+    ) {
+      timeServer.tools = timeManifest.tools;
+      timeServer.capabilities = capabilities;
+      await saveInstalledServers(existing);
+    }
   }
   
-  const hasTime = existing.some((s) => s.id === 'time-wasm');
-  const hasEcho = existing.some((s) => s.id === 'echo-js');
-  
-  if (hasTime && hasEcho) {
-    console.log('[Harbor] Built-in servers already present');
-    return existing;
-  }
-  
+  const installed = new Set(existing.map((s) => s.id));
   const serversToAdd: McpServerManifest[] = [];
-  
-  // WASM time server
-  if (!hasTime) {
-    const timeManifest: McpServerManifest = {
-      id: 'time-wasm',
-      name: 'Time Server',
-      version: '0.1.0',
-      runtime: 'wasm',
-      entrypoint: 'mcp-time.wasm',
-      moduleUrl: getExtensionURL('assets/mcp-time.wasm'),
-      permissions: [],
-      tools: [
-        {
-          name: 'time.now',
-          description: 'Get current time from host',
-          inputSchema: {
-            type: 'object',
-            properties: {
-              now: { type: 'string' },
-            },
-            required: ['now'],
-          },
-        },
-      ],
-    };
-    serversToAdd.push(timeManifest);
+  for (const builtin of BUILTIN_MANIFESTS) {
+    if (installed.has(builtin.id)) {
+      continue;
+    }
+    const server = await builtinServer(builtin);
+    if (server) {
+      serversToAdd.push(server);
+    }
   }
   
-  // JS echo server
-  if (!hasEcho) {
-    const echoManifest: McpServerManifest = {
-      id: 'echo-js',
-      name: 'Echo Server',
-      version: '0.1.0',
-      runtime: 'js',
-      scriptBase64: btoa(ECHO_SERVER_SOURCE),
-      permissions: [],
-      capabilities: {
-        // No network access needed for echo server
-      },
-      tools: [
-        {
-          name: 'echo',
-          description: 'Echo back the input message',
-          inputSchema: {
-            type: 'object',
-            properties: {
-              message: { type: 'string', description: 'The message to echo back' },
-            },
-            required: ['message'],
-          },
-        },
-        {
-          name: 'reverse',
-          description: 'Reverse a string',
-          inputSchema: {
-            type: 'object',
-            properties: {
-              text: { type: 'string', description: 'The text to reverse' },
-            },
-            required: ['text'],
-          },
-        },
-      ],
-    };
-    serversToAdd.push(echoManifest);
+  if (serversToAdd.length === 0) {
+    console.log('[Harbor] Built-in servers already present');
+    return existing;
   }
   
   const next = [...existing, ...serversToAdd];
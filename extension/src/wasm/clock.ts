@@ -0,0 +1,67 @@
+/**
+ * Host clocks for WASI preview 1 modules.
+ *
+ * The browser WASI runtime doesn't give modules a usable clock, so
+ * `clock_time_get` and `clock_res_get` are answered from the host instead:
+ * the realtime clock from `Date`/`performance.timeOrigin`, the others from
+ * `performance.now()`. With these, `SystemTime::now()` and `Instant::now()`
+ * work in modules without any help from their callers.
+ */
+
+const CLOCK_REALTIME = 0;
+const CLOCK_MONOTONIC = 1;
+const CLOCK_PROCESS_CPUTIME_ID = 2;
+const CLOCK_THREAD_CPUTIME_ID = 3;
+
+const ERRNO_SUCCESS = 0;
+const ERRNO_FAULT = 21;
+const ERRNO_INVAL = 28;
+
+/** Browsers coarsen timers; don't promise better than a millisecond. */
+const RESOLUTION_NS = 1_000_000n;
+
+type Imports = Record<string, Record<string, unknown>>;
+
+function nowNs(clockId: number): bigint | null {
+  switch (clockId) {
+    case CLOCK_REALTIME: {
+      const ms = performance.timeOrigin + performance.now();
+      return BigInt(Math.floor(ms)) * 1_000_000n + BigInt(Math.floor((ms % 1) * 1_000_000));
+    }
+    case CLOCK_MONOTONIC:
+    case CLOCK_PROCESS_CPUTIME_ID:
+    case CLOCK_THREAD_CPUTIME_ID:
+      return BigInt(Math.floor(performance.now() * 1_000_000));
+    default:
+      return null;
+  }
+}
+
+/**
+ * Replace the clock functions in a module's WASI imports. `getMemory`
+ * returns the instance's memory once it has been instantiated.
+ */
+export function withHostClocks(imports: Imports, getMemory: () => WebAssembly.Memory | undefined): Imports {
+  const write = (ptr: number, value: bigint): number => {
+    const memory = getMemory();
+    if (!memory || ptr < 0 || ptr + 8 > memory.buffer.byteLength) {
+      return ERRNO_FAULT;
+    }
+    new DataView(memory.buffer).setBigUint64(ptr, value, true);
+    return ERRNO_SUCCESS;
+  };
+
+  return {
+    ...imports,
+    wasi_snapshot_preview1: {
+      ...imports.wasi_snapshot_preview1,
+      clock_time_get: (clockId: number, _precision: bigint, resultPtr: number): number => {
+        const now = nowNs(clockId);
+        return now === null ? ERRNO_INVAL : write(resultPtr, now);
+      },
+      clock_res_get: (clockId: number, resultPtr: number): number => {
+        return nowNs(clockId) === null ? ERRNO_INVAL : write(resultPtr, RESOLUTION_NS);
+      },
+    },
+  };
+}
@@ -0,0 +1,45 @@
+/**
+ * The user's locale and time zone, for WASM servers granted
+ * `capabilities.locale`.
+ *
+ * WASI has no notion of either, so they are passed in the environment:
+ * `HARBOR_LOCALE` (BCP 47), `TZ` (IANA zone), `HARBOR_UTC_OFFSET_MINUTES`
+ * (east positive, so a server needs no time zone database) and a POSIX
+ * `LANG` for libraries that read it.
+ */
+
+export type HostLocale = {
+  /** BCP 47 language tag, e.g. "en-US" */
+  language: string;
+  /** IANA time zone, e.g. "America/New_York" */
+  timezone: string;
+  /** Current offset from UTC in minutes, east positive */
+  utcOffsetMinutes: number;
+};
+
+export function hostLocale(now = new Date()): HostLocale {
+  const resolved = Intl.DateTimeFormat().resolvedOptions();
+  return {
+    language: globalThis.navigator?.language || resolved.locale,
+    timezone: resolved.timeZone || 'UTC',
+    utcOffsetMinutes: -now.getTimezoneOffset(),
+  };
+}
+
+/**
+ * "de-DE" → "de_DE.UTF-8"; scripts and variants are left out.
+ */
+function posixLang(language: string): string {
+  const [lang, ...subtags] = language.split('-');
+  const region = subtags.find((tag) => /^([A-Za-z]{2}|\d{3})$/.test(tag));
+  return `${lang.toLowerCase()}${region ? `_${region.toUpperCase()}` : ''}.UTF-8`;
+}
+
+export function localeEnv(locale: HostLocale): Record<string, string> {
+  return {
+    HARBOR_LOCALE: locale.language,
+    TZ: locale.timezone,
+    HARBOR_UTC_OFFSET_MINUTES: String(locale.utcOffsetMinutes),
+    LANG: posixLang(locale.language),
+  };
+}
@@ -0,0 +1,212 @@
+/**
+ * A pool of instances of one WASI preview 1 module, so a server can handle
+ * several requests at once. Each instance runs in its own worker where the
+ * background context can start workers (Firefox's background page); in a
+ * service worker (Chrome, Safari) there is one instance, run in place.
+ *
+ * Instances are started on demand up to `size`; requests beyond that wait
+ * in order. Idle instances above `minIdle` are stopped after
+ * `idleTimeoutMs`.
+ */
+
+import { browserAPI } from '../browser-compat';
+import { runModule, type RunRequest, type RunResult } from './preview1';
+
+/** Most instances of one module running at once */
+export const MAX_POOL_SIZE = 8;
+const DEFAULT_IDLE_TIMEOUT_MS = 60_000;
+const DEFAULT_TIMEOUT_MS = 30_000;
+
+export type PoolOptions = {
+  size?: number;
+  minIdle?: number;
+  idleTimeoutMs?: number;
+  /** How long a request may run before its worker is stopped */
+  timeoutMs?: number;
+};
+
+type Instance = {
+  run: (request: RunRequest) => Promise<RunResult>;
+  terminate: () => void;
+};
+
+type Idle = {
+  instance: Instance;
+  timer?: ReturnType<typeof setTimeout>;
+};
+
+function canStartWorkers(): boolean {
+  return typeof Worker !== 'undefined';
+}
+
+async function startWorker(wasmModule: WebAssembly.Module, timeoutMs: number): Promise<Instance> {
+  const worker = new Worker(browserAPI.runtime.getURL('wasm/worker.js'), { type: 'module' });
+  const pending = new Map<number, { resolve: (result: RunResult) => void; reject: (error: Error) => void }>();
+  let nextId = 0;
+
+  const fail = (error: Error) => {
+    pending.forEach(({ reject }) => reject(error));
+    pending.clear();
+    worker.terminate();
+  };
+
+  await new Promise<void>((resolve, reject) => {
+    worker.addEventListener('message', (event: MessageEvent) => {
+      const message = event.data as { type: 'ready' } | { type: 'result'; id: number; result: RunResult };
+      if (message?.type === 'ready') {
+        resolve();
+      } else if (message?.type === 'result') {
+        pending.get(message.id)?.resolve(message.result);
+        pending.delete(message.id);
+      }
+    });
+    worker.addEventListener('error', (event: ErrorEvent) => {
+      const error = new Error(event.message || 'WASM worker failed');
+      reject(error);
+      fail(error);
+    });
+    worker.postMessage({ type: 'init', module: wasmModule });
+  });
+
+  return {
+    run: (request) =>
+      new Promise<RunResult>((resolve, reject) => {
+        const id = nextId++;
+        // Only stopping the worker ends a runaway module
+        const timer = setTimeout(
+          () => fail(new Error(`Request did not finish within ${timeoutMs} ms`)),
+          timeoutMs,
+        );
+        pending.set(id, {
+          resolve: (result) => {
+            clearTimeout(timer);
+            resolve(result);
+          },
+          reject: (error) => {
+            clearTimeout(timer);
+            reject(error);
+          },
+        });
+        worker.postMessage({ type: 'run', id, request });
+      }),
+    terminate: () => fail(new Error('WASM worker stopped')),
+  };
+}
+
+export class InstancePool {
+  private readonly size: number;
+  private readonly minIdle: number;
+  private readonly idleTimeoutMs: number;
+  private readonly timeoutMs: number;
+  private idle: Idle[] = [];
+  private waiting: Array<{ resolve: (instance: Instance) => void; reject: (error: Error) => void }> = [];
+  /** Instances running, idle or starting */
+  private count = 0;
+  private closed = false;
+
+  constructor(
+    private readonly wasmModule: WebAssembly.Module,
+    options: PoolOptions = {},
+  ) {
+    this.size = canStartWorkers() ? Math.min(Math.max(options.size ?? 1, 1), MAX_POOL_SIZE) : 1;
+    this.minIdle = Math.min(Math.max(options.minIdle ?? 0, 0), this.size);
+    this.idleTimeoutMs = options.idleTimeoutMs ?? DEFAULT_IDLE_TIMEOUT_MS;
+    this.timeoutMs = options.timeoutMs ?? DEFAULT_TIMEOUT_MS;
+  }
+
+  async run(request: RunRequest): Promise<RunResult> {
+    const instance = await this.acquire();
+    let result: RunResult;
+    try {
+      result = await instance.run(request);
+    } catch (e) {
+      // The worker is gone (crashed or timed out); a new one takes its place
+      this.count--;
+      this.handOn();
+      throw e;
+    }
+    this.release(instance);
+    return result;
+  }
+
+  close(): void {
+    this.closed = true;
+    this.idle.forEach(({ instance, timer }) => {
+      clearTimeout(timer);
+      instance.terminate();
+    });
+    this.idle = [];
+    this.waiting.forEach(({ reject }) => reject(new Error('WASM server stopped')));
+    this.waiting = [];
+  }
+
+  private start(): Promise<Instance> {
+    if (!canStartWorkers()) {
+      return Promise.resolve({
+        run: (request) => runModule(this.wasmModule, request),
+        terminate: () => undefined,
+      });
+    }
+    return startWorker(this.wasmModule, this.timeoutMs);
+  }
+
+  private async acquire(): Promise<Instance> {
+    if (this.closed) {
+      throw new Error('WASM server stopped');
+    }
+    const idle = this.idle.pop();
+    if (idle) {
+      clearTimeout(idle.timer);
+      return idle.instance;
+    }
+    if (this.count < this.size) {
+      this.count++;
+      try {
+        return await this.start();
+      } catch (e) {
+        this.count--;
+        throw e;
+      }
+    }
+    return new Promise((resolve, reject) => this.waiting.push({ resolve, reject }));
+  }
+
+  private release(instance: Instance): void {
+    if (this.closed) {
+      instance.terminate();
+      return;
+    }
+    const next = this.waiting.shift();
+    if (next) {
+      next.resolve(instance);
+      return;
+    }
+    const idle: Idle = { instance };
+    if (this.idle.length >= this.minIdle) {
+      idle.timer = setTimeout(() => this.retire(idle), this.idleTimeoutMs);
+    }
+    this.idle.push(idle);
+  }
+
+  private retire(idle: Idle): void {
+    const index = this.idle.indexOf(idle);
+    if (index >= 0) {
+      this.idle.splice(index, 1);
+      this.count--;
+      idle.instance.terminate();
+    }
+  }
+
+  /** Start an instance for the next waiting request, after one was lost */
+  private handOn(): void {
+    const next = this.waiting.shift();
+    if (!next || this.closed) {
+      return;
+    }
+    this.count++;
+    this.start().then(next.resolve, (e) => {
+      this.count--;
+      next.reject(e instanceof Error ? e : new Error(String(e)));
+    });
+  }
+}
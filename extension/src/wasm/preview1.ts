@@ -0,0 +1,59 @@
+/**
+ * One run of a WASI preview 1 module: a fresh instance gets a request on
+ * stdin and exits, and what it wrote to stdout and stderr comes back.
+ * Used on the background page and in instance workers (see `worker.ts`).
+ */
+
+import { init, WASI } from '@wasmer/wasi';
+import { Buffer } from 'buffer';
+import { withHostClocks } from './clock';
+import { secureRandom, seededRandom, withHostRandom } from './random';
+
+export type RunRequest = {
+  stdin: Uint8Array;
+  env: Record<string, string>;
+  /** Seed for a fixed random sequence, for servers with `capabilities.random: false` */
+  randomSeed?: number;
+};
+
+export type RunResult = {
+  stdout: Uint8Array;
+  stderr: Uint8Array;
+  /** Why the run failed, e.g. a trap; the output is what was written before it */
+  error?: string;
+};
+
+let initialized: Promise<void> | null = null;
+
+export async function runModule(wasmModule: WebAssembly.Module, request: RunRequest): Promise<RunResult> {
+  if (!('Buffer' in globalThis)) {
+    (globalThis as typeof globalThis & { Buffer?: typeof Buffer }).Buffer = Buffer;
+  }
+  initialized ??= init();
+  await initialized;
+
+  const wasi = new WASI({ args: [], env: request.env });
+  // Answer clock and random calls from the host so SystemTime::now() and
+  // getrandom work; a fixed sequence per run if randomness is disabled
+  let memory: WebAssembly.Memory | undefined;
+  const random = request.randomSeed === undefined ? secureRandom : seededRandom(request.randomSeed);
+  const imports = withHostRandom(
+    withHostClocks(wasi.getImports(wasmModule) as Record<string, Record<string, unknown>>, () => memory),
+    () => memory,
+    random,
+  );
+  const instance = await WebAssembly.instantiate(wasmModule, imports as WebAssembly.Imports);
+  memory = instance.exports.memory as WebAssembly.Memory | undefined;
+  wasi.instantiate(instance, {});
+  if (request.stdin.length > 0) {
+    wasi.setStdinBuffer(request.stdin);
+  }
+  let error: string | undefined;
+  try {
+    wasi.start(instance);
+  } catch (e) {
+    error = e instanceof Error ? e.message : String(e);
+  }
+  // Read even when the module traps, which is when stderr has the panic
+  return { stdout: wasi.getStdoutBuffer(), stderr: wasi.getStderrBuffer(), error };
+}
@@ -0,0 +1,68 @@
+/**
+ * Host randomness for WASI preview 1 modules.
+ *
+ * The browser WASI runtime doesn't give modules randomness, so crates that
+ * use `getrandom` (UUIDs, nonces, hash map seeds) fail. `random_get` is
+ * answered from `crypto.getRandomValues` instead. A server whose manifest
+ * sets `capabilities.random: false` gets a seeded generator (`randomSeed`,
+ * default 0), so every run sees the same bytes, e.g. for tests.
+ */
+
+const ERRNO_SUCCESS = 0;
+const ERRNO_FAULT = 21;
+
+/** `crypto.getRandomValues` fills at most this many bytes per call. */
+const MAX_CRYPTO_BYTES = 65536;
+
+type Imports = Record<string, Record<string, unknown>>;
+
+/** A source of random bytes. */
+export type RandomSource = (buffer: Uint8Array) => void;
+
+export function secureRandom(buffer: Uint8Array): void {
+  for (let offset = 0; offset < buffer.length; offset += MAX_CRYPTO_BYTES) {
+    crypto.getRandomValues(buffer.subarray(offset, offset + MAX_CRYPTO_BYTES));
+  }
+}
+
+/**
+ * A deterministic source (mulberry32): the same seed gives the same bytes.
+ * Not for anything secret.
+ */
+export function seededRandom(seed: number): RandomSource {
+  let state = seed >>> 0;
+  return (buffer: Uint8Array) => {
+    for (let i = 0; i < buffer.length; i++) {
+      state = (state + 0x6d2b79f5) >>> 0;
+      let t = state;
+      t = Math.imul(t ^ (t >>> 15), t | 1);
+      t ^= t + Math.imul(t ^ (t >>> 7), t | 61);
+      buffer[i] = ((t ^ (t >>> 14)) >>> 0) & 0xff;
+    }
+  };
+}
+
+/**
+ * Replace `random_get` in a module's WASI imports. `getMemory` returns the
+ * instance's memory once it has been instantiated.
+ */
+export function withHostRandom(
+  imports: Imports,
+  getMemory: () => WebAssembly.Memory | undefined,
+  source: RandomSource = secureRandom,
+): Imports {
+  return {
+    ...imports,
+    wasi_snapshot_preview1: {
+      ...imports.wasi_snapshot_preview1,
+      random_get: (bufPtr: number, bufLen: number): number => {
+        const memory = getMemory();
+        if (!memory || bufPtr < 0 || bufLen < 0 || bufPtr + bufLen > memory.buffer.byteLength) {
+          return ERRNO_FAULT;
+        }
+        source(new Uint8Array(memory.buffer, bufPtr, bufLen));
+        return ERRNO_SUCCESS;
+      },
+    },
+  };
+}
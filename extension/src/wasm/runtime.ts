@@ -1,8 +1,8 @@
 import type { McpServerHandle, McpServerManifest } from './types';
 import type { McpResponse, ToolCallParams } from '../mcp/protocol';
 import type { McpTransport } from '../mcp/transport';
-import { McpStdioTransport } from '../mcp/stdio-transport';
-import { createWasmSession } from './session';
+import { McpStdioTransport, type McpNotification } from '../mcp/stdio-transport';
+import { ApprovalRequiredError, createWasmSession, type ApprovalRequest } from './session';
 import { createJsSession } from '../js-runtime/session';
 import { createRemoteTransport, type McpSseTransport, type McpWebSocketTransport } from '../mcp/remote-transport';
 import { rpcRequest, isNativeBridgeReady } from '../llm/native-bridge';
@@ -18,6 +18,8 @@ const toolIndex = new Map<string, ToolEntry>();
 const activeSessions = new Map<string, { transport: McpTransport; close: () => void }>();
 // Track remote transports separately for connection status
 const remoteTransports = new Map<string, McpSseTransport | McpWebSocketTransport>();
+// What servers that failed to start for want of approval asked for
+const pendingApprovals = new Map<string, ApprovalRequest>();
 
 /**
  * Initialize the MCP runtime (both WASM and JS).
@@ -127,6 +129,7 @@ export async function startMcpServer(serverId: string): Promise<boolean> {
   if (activeSessions.has(serverId)) {
     return true;
   }
+  pendingApprovals.delete(serverId);
 
   const runtime = getServerRuntime(handle.manifest);
 
@@ -155,7 +158,7 @@ export async function startMcpServer(serverId: string): Promise<boolean> {
         runtime: 'js',
       });
       activeSessions.set(serverId, {
-        transport: new McpStdioTransport(session.endpoint),
+        transport: watchNotifications(serverId, new McpStdioTransport(session.endpoint)),
         close: session.close,
       });
       console.log('[Harbor] Started JS MCP server:', serverId);
@@ -163,7 +166,7 @@ export async function startMcpServer(serverId: string): Promise<boolean> {
       // Create WASM session (existing path)
       const session = await createWasmSession(handle.manifest);
       activeSessions.set(serverId, {
-        transport: new McpStdioTransport(session.endpoint),
+        transport: watchNotifications(serverId, new McpStdioTransport(session.endpoint), !session.bridged),
         close: session.close,
       });
       console.log('[Harbor] Started WASM MCP server:', serverId);
@@ -174,11 +177,24 @@ export async function startMcpServer(serverId: string): Promise<boolean> {
     
     return true;
   } catch (error) {
+    if (error instanceof ApprovalRequiredError) {
+      console.warn(`[Harbor] ${error.message}`);
+      pendingApprovals.set(serverId, error.request);
+      return false;
+    }
     console.error(`[Harbor] Failed to start ${runtime} MCP server:`, error);
     return false;
   }
 }
 
+/**
+ * What a server asked for when it last failed to start for want of the
+ * user's approval, if it did.
+ */
+export function pendingApproval(serverId: string): ApprovalRequest | undefined {
+  return pendingApprovals.get(serverId);
+}
+
 /**
  * Safari: Sync server tools to the bridge for Web Agents compatibility.
  * In Safari, Web Agents can only communicate with the bridge, not Harbor directly.
@@ -210,6 +226,58 @@ async function syncToolsToBridge(serverId: string, manifest: McpServerManifest):
   }
 }
 
+/**
+ * Act on the notifications a stdio server sends: log messages go to the
+ * console, and a changed tool list is fetched again. With `forwardLogs`,
+ * log messages also go to the bridge log, where `harbor-bridge dev` shows
+ * them.
+ */
+function watchNotifications(serverId: string, transport: McpStdioTransport, forwardLogs = false): McpStdioTransport {
+  transport.onNotification((notification: McpNotification) => {
+    switch (notification.method) {
+      case 'notifications/tools/list_changed':
+        refreshTools(serverId).catch((err) => {
+          console.warn(`[Harbor] Failed to refresh tools for ${serverId}:`, err);
+        });
+        break;
+      case 'notifications/message': {
+        const { level, data } = (notification.params || {}) as { level?: string; data?: unknown };
+        const log = level === 'debug' ? console.debug
+          : level === 'info' || level === 'notice' ? console.info
+          : level === 'warning' ? console.warn
+          : console.error;
+        log(`[MCP:${serverId}]`, data);
+        if (forwardLogs && isNativeBridgeReady()) {
+          rpcRequest('servers.log', { id: serverId, level, message: data }).catch(() => undefined);
+        }
+        break;
+      }
+      default:
+        break;
+    }
+  });
+  return transport;
+}
+
+/**
+ * Re-read a server's tools after it announced they changed.
+ */
+async function refreshTools(serverId: string): Promise<void> {
+  const response = await callMcpMethod(serverId, 'tools/list');
+  if (response.error) {
+    throw new Error(response.error.message);
+  }
+  const handle = runningServers.get(serverId);
+  if (!handle) {
+    return;
+  }
+  const tools = (response.result as { tools?: McpServerManifest['tools'] })?.tools || [];
+  const manifest: McpServerManifest = { ...handle.manifest, tools };
+  registerMcpServer(manifest);
+  await syncToolsToBridge(serverId, manifest);
+  console.log(`[Harbor] Tools changed for ${serverId}: ${tools.length} tools`);
+}
+
 /** @deprecated Use startMcpServer instead */
 export const startWasmServer = startMcpServer;
 
@@ -1,68 +1,199 @@
-import { init, WASI } from '@wasmer/wasi';
-import { Buffer } from 'buffer';
 import type { StdioEndpoint } from '../mcp/stdio-transport';
 import type { WasmServerManifest } from './types';
+import { bridgeRequest } from '../llm/bridge-client';
+import { hostLocale, localeEnv } from './locale';
+import { InstancePool } from './pool';
 
 export type WasmSession = {
   endpoint: StdioEndpoint;
   close: () => void;
+  /** The bridge runs the server, and so already logs what it sends */
+  bridged?: boolean;
 };
 
+/**
+ * The server side of a module's stdio. Writes are replaced by the session,
+ * which runs each request as it arrives.
+ */
 function createStdioEndpoint(): {
   endpoint: StdioEndpoint;
   pushStdout: (data: Uint8Array) => void;
-  drainStdin: () => Uint8Array;
   close: () => void;
 } {
   let handler: ((data: Uint8Array) => void) | null = null;
-  const stdinQueue: Uint8Array[] = [];
 
   const endpoint: StdioEndpoint = {
-    write(data: Uint8Array) {
-      stdinQueue.push(data);
-    },
+    write() {},
     onData(nextHandler: (data: Uint8Array) => void) {
       handler = nextHandler;
     },
   };
 
-  const pushStdout = (data: Uint8Array) => {
-    handler?.(data);
+  return {
+    endpoint,
+    pushStdout: (data: Uint8Array) => {
+      handler?.(data);
+    },
+    close: () => {
+      handler = null;
+    },
   };
+}
 
-  const drainStdin = (): Uint8Array => {
-    if (stdinQueue.length === 0) {
-      return new Uint8Array(0);
-    }
-    const total = stdinQueue.reduce((sum, chunk) => sum + chunk.length, 0);
-    const merged = new Uint8Array(total);
-    let offset = 0;
-    stdinQueue.forEach((chunk) => {
-      merged.set(chunk, offset);
-      offset += chunk.length;
-    });
-    stdinQueue.length = 0;
-    return merged;
+/**
+ * Whether the bytes are a WebAssembly component rather than a core module.
+ * Both start with `\0asm`; components use a different version/layer field.
+ */
+function isComponent(bytes: Uint8Array): boolean {
+  return bytes.length >= 8 && bytes[6] === 0x01 && bytes[7] === 0x00 && !(bytes[4] === 0x01 && bytes[5] === 0x00);
+}
+
+function toBase64(bytes: Uint8Array): string {
+  let binary = '';
+  for (let i = 0; i < bytes.length; i += 0x8000) {
+    binary += String.fromCharCode(...bytes.subarray(i, i + 0x8000));
+  }
+  return btoa(binary);
+}
+
+/** The manifest's environment variables that have defaults. */
+function manifestEnv(manifest: WasmServerManifest): Record<string, string> {
+  const env: Record<string, string> = {};
+  for (const variable of manifest.environment || []) {
+    if (variable.default !== undefined) env[variable.name] = String(variable.default);
+  }
+  return env;
+}
+
+/** What a component server needs the user to approve, as `wasm.review` reports it. */
+export type ApprovalRequest = { module: string; consent: string[] };
+
+/** Thrown when a component server would get something the user hasn't approved. */
+export class ApprovalRequiredError extends Error {
+  constructor(
+    readonly serverId: string,
+    readonly request: ApprovalRequest,
+  ) {
+    super(`${serverId} needs approval to: ${request.consent.join('; ')}`);
+    this.name = 'ApprovalRequiredError';
+  }
+}
+
+function toBridgeLocale() {
+  const { language, timezone, utcOffsetMinutes } = hostLocale();
+  return { language, timezone, utc_offset_minutes: utcOffsetMinutes };
+}
+
+/**
+ * Creates a session for a WASI preview 2 component. Browsers can't run
+ * components directly, so the bridge runs it (wasmtime) and requests are
+ * proxied through `wasm.call`, as for JS servers.
+ */
+async function createComponentSession(
+  manifest: WasmServerManifest,
+  bytes: Uint8Array,
+): Promise<WasmSession> {
+  // The bridge fetches for the component (harbor:mcp/http), only from these hosts
+  const params = {
+    id: manifest.id,
+    wasm_base64: toBase64(bytes),
+    env: manifestEnv(manifest),
+    capabilities: {
+      network: {
+        allowed_hosts: manifest.capabilities?.network?.hosts || [],
+      },
+    },
+    limits: manifest.limits || {},
+    pool: manifest.pool || {},
+    random_seed: manifest.capabilities?.random === false ? manifest.randomSeed ?? 0 : undefined,
+    locale: manifest.capabilities?.locale ? toBridgeLocale() : undefined,
+    files: manifest.capabilities?.files === true,
+    schedule: manifest.capabilities?.schedule === true,
+    browser: manifest.capabilities?.browser === true,
+    clipboard: manifest.capabilities?.clipboard === true,
+    // Values come from the bridge's secrets store, through harbor:mcp/secrets
+    secrets: manifest.declaredSecrets || [],
+    // Tokens come from the bridge's store, through harbor:mcp/oauth
+    oauth: manifest.oauth ? { provider: manifest.oauth.provider, scopes: manifest.oauth.scopes } : undefined,
+    // For the signature check; the module is already in wasm_base64
+    package: { ...manifest, wasmBase64: undefined, moduleBytesBase64: undefined },
+  };
+  // What the server would get must have been approved for this module
+  const review = await bridgeRequest<ApprovalRequest & { approved: boolean }>('wasm.review', params);
+  if (!review.approved) {
+    throw new ApprovalRequiredError(manifest.id, { module: review.module, consent: review.consent });
+  }
+  await bridgeRequest<{ id: string; status: string }>('wasm.start_server', params);
+  console.log('[Harbor] Started WASM component server via bridge:', manifest.id);
+
+  let handler: ((data: Uint8Array) => void) | null = null;
+  const encoder = new TextEncoder();
+  const decoder = new TextDecoder();
+  const respond = (message: unknown) => {
+    handler?.(encoder.encode(JSON.stringify(message) + '\n'));
+  };
+
+  const endpoint: StdioEndpoint = {
+    async write(data: Uint8Array) {
+      const jsonString = decoder.decode(data).trim();
+      if (!jsonString) return;
+      let request: { id?: unknown };
+      try {
+        request = JSON.parse(jsonString);
+      } catch {
+        return;
+      }
+      try {
+        // Notifications come back with the response and are passed on first,
+        // as a stdio server would write them
+        const { response, notifications } = await bridgeRequest<{ response: unknown; notifications: unknown[] }>(
+          'wasm.call',
+          { id: manifest.id, request, notifications: true },
+        );
+        notifications.forEach(respond);
+        if (response !== null) {
+          respond(response);
+        }
+      } catch (e) {
+        console.error('[Harbor] Bridge WASM call error:', e);
+        respond({
+          jsonrpc: '2.0',
+          id: request.id,
+          error: { code: -32000, message: e instanceof Error ? e.message : 'Unknown error' },
+        });
+      }
+    },
+    onData(nextHandler: (data: Uint8Array) => void) {
+      handler = nextHandler;
+    },
   };
 
   return {
     endpoint,
-    pushStdout,
-    drainStdin,
+    bridged: true,
     close: () => {
-      stdinQueue.length = 0;
       handler = null;
+      bridgeRequest('wasm.stop_server', { id: manifest.id })
+        .then(() => console.log('[Harbor] Stopped WASM component server via bridge:', manifest.id))
+        .catch((e) => console.warn('[Harbor] Failed to stop WASM component server:', e));
     },
   };
 }
 
+/**
+ * Show what a module wrote to stderr in the console, and send it to the
+ * bridge, which keeps it for `servers.logs`.
+ */
+function forwardStderr(serverId: string, text: string): void {
+  console.warn(`[WASM:${serverId}] stderr:`, text);
+  bridgeRequest('servers.log', { id: serverId, stream: 'stderr', message: text }).catch(() => undefined);
+}
+
 export async function createWasmSession(
   manifest: WasmServerManifest,
 ): Promise<WasmSession> {
-  if (!('Buffer' in globalThis)) {
-    (globalThis as typeof globalThis & { Buffer?: typeof Buffer }).Buffer = Buffer;
-  }
-  if (!manifest.moduleUrl && !manifest.moduleBytesBase64) {
+  const embeddedBase64 = manifest.moduleBytesBase64 || manifest.wasmBase64;
+  if (!manifest.moduleUrl && !embeddedBase64) {
     const { createStubEndpoint } = await import('./stdio-endpoint');
     return {
       endpoint: createStubEndpoint(),
@@ -72,15 +203,6 @@ export async function createWasmSession(
     };
   }
 
-  console.log('[Harbor] Initializing WASI runtime...');
-  try {
-    await init();
-    console.log('[Harbor] WASI runtime initialized successfully');
-  } catch (initError) {
-    console.error('[Harbor] WASI init failed:', initError);
-    throw initError;
-  }
-
   // Resolve the WASM URL - use runtime.getURL if available (background context)
   let wasmUrl = manifest.moduleUrl as string;
   if (wasmUrl && !wasmUrl.startsWith('http') && !wasmUrl.startsWith('safari-web-extension:') && !wasmUrl.startsWith('moz-extension:') && !wasmUrl.startsWith('chrome-extension:')) {
@@ -100,8 +222,8 @@ export async function createWasmSession(
   }
   
   console.log('[Harbor] Loading WASM module from:', wasmUrl);
-  const wasmBytes = manifest.moduleBytesBase64
-    ? Uint8Array.from(atob(manifest.moduleBytesBase64), (char) => char.charCodeAt(0)).buffer
+  const wasmBytes = embeddedBase64
+    ? Uint8Array.from(atob(embeddedBase64), (char) => char.charCodeAt(0)).buffer
     : await fetch(wasmUrl).then((response) => {
         console.log('[Harbor] WASM fetch response:', response.status, response.ok);
         if (!response.ok) {
@@ -110,35 +232,53 @@ export async function createWasmSession(
         return response.arrayBuffer();
       });
 
+  if (manifest.wasi === 'preview2' || isComponent(new Uint8Array(wasmBytes))) {
+    return createComponentSession(manifest, new Uint8Array(wasmBytes));
+  }
+
   const wasmModule = await WebAssembly.compile(wasmBytes);
-  const { endpoint, pushStdout, drainStdin, close } = createStdioEndpoint();
+  const pool = new InstancePool(wasmModule, { ...manifest.pool, timeoutMs: manifest.limits?.timeoutMs });
+  const { endpoint, pushStdout, close } = createStdioEndpoint();
+  const encoder = new TextEncoder();
+  const decoder = new TextDecoder();
 
-  const runOnce = async () => {
-    const wasi = new WASI({
-      args: [],
-      env: {},
+  const handle = async (data: Uint8Array) => {
+    // Each request is a fresh run, so the UTC offset is always current
+    const { stdout, stderr, error } = await pool.run({
+      stdin: data,
+      env: { ...manifestEnv(manifest), ...(manifest.capabilities?.locale ? localeEnv(hostLocale()) : {}) },
+      randomSeed: manifest.capabilities?.random === false ? manifest.randomSeed ?? 0 : undefined,
     });
-    const instance = await wasi.instantiate(wasmModule, {});
-    const stdinBuffer = drainStdin();
-    if (stdinBuffer.length > 0) {
-      wasi.setStdinBuffer(stdinBuffer);
+    if (stderr.length > 0) {
+      forwardStderr(manifest.id, decoder.decode(stderr));
     }
-    wasi.start(instance);
-    const stdout = wasi.getStdoutBuffer();
     if (stdout.length > 0) {
       pushStdout(stdout);
     }
-    const stderr = wasi.getStderrBuffer();
-    if (stderr.length > 0) {
-      pushStdout(stderr);
+    if (error) {
+      throw new Error(error);
     }
   };
 
-  const originalWrite = endpoint.write.bind(endpoint);
   endpoint.write = (data: Uint8Array) => {
-    originalWrite(data);
-    runOnce().catch((error) => {
+    handle(data).catch((error) => {
       console.error('[Harbor] WASM run failed', error);
+      // Answer the request so the caller isn't left waiting
+      let request: { id?: unknown } | null = null;
+      try {
+        request = JSON.parse(decoder.decode(data));
+      } catch {
+        return;
+      }
+      if (request?.id === undefined) {
+        return;
+      }
+      const response = {
+        jsonrpc: '2.0',
+        id: request.id,
+        error: { code: -32000, message: error instanceof Error ? error.message : 'Unknown error' },
+      };
+      pushStdout(encoder.encode(JSON.stringify(response) + '\n'));
     });
   };
 
@@ -146,6 +286,7 @@ export async function createWasmSession(
     endpoint,
     close: () => {
       close();
+      pool.close();
       console.log('[Harbor] Closing WASM session', manifest.id);
     },
   };
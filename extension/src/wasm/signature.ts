@@ -0,0 +1,42 @@
+/**
+ * Signature checks for WASM packages at install time.
+ *
+ * The bridge holds the trust store and the policy (`off`, `warn` or
+ * `require`), so the check is a `signing.verify` call. Components are
+ * checked again by the bridge when they start.
+ */
+
+import { bridgeRequest } from '../llm/bridge-client';
+import type { McpServerManifest } from './types';
+
+export type SignatureCheck = {
+  allowed: boolean;
+  warning?: string;
+  error?: string;
+};
+
+/**
+ * Check a package's signature against the bridge's trust store. Packages
+ * without embedded module bytes (JS, remote, fetched by URL) aren't
+ * checked. Without the bridge the signature can't be checked, and the
+ * package is refused.
+ */
+export async function checkSignature(manifest: McpServerManifest): Promise<SignatureCheck> {
+  const wasmBase64 = manifest.wasmBase64 || manifest.moduleBytesBase64;
+  if ((manifest.runtime && manifest.runtime !== 'wasm') || !wasmBase64) {
+    return { allowed: true };
+  }
+
+  try {
+    return await bridgeRequest<SignatureCheck>('signing.verify', {
+      wasm_base64: wasmBase64,
+      manifest: { ...manifest, wasmBase64: undefined, moduleBytesBase64: undefined },
+    });
+  } catch (error) {
+    console.warn('[Harbor] Could not verify signature of', manifest.id, error);
+    return {
+      allowed: false,
+      error: `The signature of "${manifest.name}" could not be checked (bridge not connected)`,
+    };
+  }
+}
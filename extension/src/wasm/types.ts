@@ -26,6 +26,40 @@ export type NetworkCapability = {
 export type McpServerCapabilities = {
   /** Network access configuration */
   network?: NetworkCapability;
+  /**
+   * Randomness for WASM servers (WASI `random_get`), on by default. With
+   * `false`, the server gets a fixed sequence from `randomSeed` instead,
+   * so its runs are reproducible.
+   */
+  random?: boolean;
+  /**
+   * The user's language and time zone, passed to WASM servers in the
+   * environment (`HARBOR_LOCALE`, `TZ`, `HARBOR_UTC_OFFSET_MINUTES`, `LANG`).
+   * Off by default.
+   */
+  locale?: boolean;
+  /**
+   * Read access to the server's jail, or to the directories of the bridge's
+   * file sandbox it has been granted, which WASM components without a
+   * `harbor.toml` see under `/files`. Off by default.
+   */
+  files?: boolean;
+  /**
+   * Lets WASM components have the bridge call tools on a schedule (the
+   * `harbor:mcp/schedule` import). Off by default.
+   */
+  schedule?: boolean;
+  /**
+   * Lets WASM components list tabs, read pages and search bookmarks and
+   * history, answered by the extension (the `harbor:mcp/browser` import).
+   * Off by default.
+   */
+  browser?: boolean;
+  /**
+   * Lets WASM components read and write the text on the OS clipboard (the
+   * `harbor:mcp/clipboard` import). Off by default.
+   */
+  clipboard?: boolean;
 };
 
 /**
@@ -45,12 +79,39 @@ export type McpServerOAuth = {
   provider: 'google' | 'github';
   /** Required OAuth scopes */
   scopes: string[];
-  /** Environment variable name for the access token */
-  tokenEnvVar: string;
+  /**
+   * Environment variable name for the access token (JS servers). Components
+   * ask the bridge for tokens through the `harbor:mcp/oauth` import instead.
+   */
+  tokenEnvVar?: string;
   /** Environment variable name for the refresh token (optional) */
   refreshTokenEnvVar?: string;
 };
 
+/**
+ * A secret a component reads from the bridge's secrets store, where the
+ * user sets it with `secrets.set`. Matches `[[secrets]]` in `harbor.toml`.
+ */
+export type McpSecretDecl = {
+  /** UPPER_SNAKE_CASE name */
+  name: string;
+  description?: string;
+  /** The server can't start without it (default true) */
+  required?: boolean;
+  /** Also set it as an environment variable (default true) */
+  env?: boolean;
+};
+
+/**
+ * A non-secret environment variable a server reads, as in the manifest's
+ * `environment`.
+ */
+export type McpEnvVarDecl = {
+  name: string;
+  description?: string;
+  default?: string | number | boolean;
+};
+
 /**
  * Unified manifest type for both WASM and JS MCP servers.
  */
@@ -75,6 +136,40 @@ export type McpServerManifest = {
   moduleBytesBase64?: string;
   /** Base64-encoded WASM module bytes (alias for moduleBytesBase64) */
   wasmBase64?: string;
+  /**
+   * WASI version the module targets. 'preview1' (default) is a core module
+   * speaking JSON-RPC over stdio, run in the extension. 'preview2' is a
+   * component implementing the `harbor:mcp` world, run by the bridge.
+   * Components are also recognized from their bytes.
+   */
+  wasi?: 'preview1' | 'preview2';
+  /**
+   * Resource limits for components run by the bridge: memory cap, fuel per
+   * request (roughly one unit per instruction), request timeout and `kv`
+   * storage quota. Omitted fields use the bridge's defaults (256 MB,
+   * 10 billion, 30 s, 1 MB). Modules run in workers use `timeoutMs` too.
+   */
+  limits?: {
+    memoryMb?: number;
+    fuel?: number;
+    timeoutMs?: number;
+    kvMb?: number;
+  };
+  /**
+   * Instance pool: up to `size` requests handled at once, `minIdle`
+   * instances kept warm, others dropped after `idleTimeoutMs` idle. Only for
+   * servers that keep no state between requests, since instances don't
+   * share memory. Defaults to one instance. Components run in the bridge;
+   * modules run in workers where the browser allows them (Firefox), and
+   * one at a time otherwise.
+   */
+  pool?: {
+    size?: number;
+    minIdle?: number;
+    idleTimeoutMs?: number;
+  };
+  /** Seed for the fixed random sequence when `capabilities.random` is false (default 0) */
+  randomSeed?: number;
 
   // JS-specific fields
   /** URL to fetch JS bundle from */
@@ -99,13 +194,36 @@ export type McpServerManifest = {
   // Environment configuration
   /** Environment variable names to pass through */
   env?: string[];
+  /**
+   * Non-secret environment variables the server reads; those with a
+   * `default` are set to it when the server starts.
+   */
+  environment?: McpEnvVarDecl[];
   /** Secret values to inject as process.env (name -> value) */
   secrets?: Record<string, string>;
+  /**
+   * Secrets a component declares, for the bridge to hand it through the
+   * `harbor:mcp/secrets` import (and the environment, unless `env` is false).
+   */
+  declaredSecrets?: McpSecretDecl[];
 
   // OAuth configuration
   /** OAuth requirements for this server */
   oauth?: McpServerOAuth;
 
+  /**
+   * ed25519 signature over the module and this manifest, checked against
+   * the bridge's trust store when the package is installed and started.
+   */
+  signature?: {
+    algorithm: 'ed25519';
+    /** Base64 signature */
+    value: string;
+    /** Base64 public key, used when `keyId` isn't in the trust store */
+    publicKey?: string;
+    keyId?: string;
+  };
+
   /** Tool definitions exposed by this server */
   tools?: McpToolDefinition[];
 
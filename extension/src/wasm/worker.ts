@@ -0,0 +1,34 @@
+/**
+ * A worker running instances of one WASI preview 1 module, one request at
+ * a time, so a slow request doesn't hold up the background page or the
+ * server's other requests (see `pool.ts`).
+ *
+ * Messages in: `{ type: 'init', module }` once, then
+ * `{ type: 'run', id, request }`. Out: `{ type: 'ready' }`, then
+ * `{ type: 'result', id, result }` for each run.
+ */
+
+import { runModule, type RunRequest } from './preview1';
+
+let wasmModule: WebAssembly.Module | null = null;
+
+globalThis.addEventListener('message', (event: MessageEvent) => {
+  const message = event.data as
+    | { type: 'init'; module: WebAssembly.Module }
+    | { type: 'run'; id: number; request: RunRequest };
+  if (message?.type === 'init') {
+    wasmModule = message.module;
+    postMessage({ type: 'ready' });
+  } else if (message?.type === 'run') {
+    const run = wasmModule
+      ? runModule(wasmModule, message.request)
+      : Promise.reject(new Error('Worker has no module'));
+    run
+      .catch((e) => ({
+        stdout: new Uint8Array(0),
+        stderr: new Uint8Array(0),
+        error: e instanceof Error ? e.message : String(e),
+      }))
+      .then((result) => postMessage({ type: 'result', id: message.id, result }));
+  }
+});
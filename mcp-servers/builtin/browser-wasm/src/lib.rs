@@ -0,0 +1,155 @@
+//! Browser MCP Server (WASM component)
+//!
+//! Lets chat agents see what the user has open and has been reading:
+//! `tabs.list` lists the open tabs, `tab.readContent` reads one's text,
+//! and `bookmarks.search` and `history.search` look through bookmarks and
+//! recent history. The bridge can't see the browser, so each request goes
+//! through the `harbor:mcp/browser` import to the extension, which
+//! answers it; private windows are left out.
+//!
+//! Build with:
+//!   cargo build --release --target wasm32-wasip2
+
+mod page;
+
+use harbor_mcp_sdk::{harbor_tool, Error, Server, ToolInput, ToolResult};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+wit_bindgen::generate!({ path: "../../wit", world: "mcp-server" });
+
+use harbor::mcp::browser;
+
+const DEFAULT_CHARS: usize = 10_000;
+/// As much as the extension reads of a page.
+const MAX_CHARS: usize = 50_000;
+const DEFAULT_RESULTS: usize = 10;
+const MAX_RESULTS: usize = 50;
+const DEFAULT_DAYS: u32 = 7;
+const MAX_DAYS: u32 = 365;
+
+#[derive(Deserialize, ToolInput)]
+#[serde(deny_unknown_fields)]
+struct TabsArgs {}
+
+#[derive(Deserialize, ToolInput)]
+#[serde(deny_unknown_fields)]
+struct ReadArgs {
+    /// The tab's id, from tabs.list
+    tab_id: u64,
+    /// Most characters of text to return (default 10000, at most 50000)
+    max_chars: Option<usize>,
+}
+
+#[derive(Deserialize, ToolInput)]
+#[serde(deny_unknown_fields)]
+struct BookmarksArgs {
+    /// Words to find in bookmark titles and URLs
+    query: String,
+    /// Most bookmarks to return (default 10, at most 50)
+    max_results: Option<usize>,
+}
+
+#[derive(Deserialize, ToolInput)]
+#[serde(deny_unknown_fields)]
+struct HistoryArgs {
+    /// Words to find in the titles and URLs of visited pages
+    query: String,
+    /// Most pages to return (default 10, at most 50)
+    max_results: Option<usize>,
+    /// How many days back to look (default 7, at most 365)
+    days: Option<u32>,
+}
+
+/// Ask the extension, through the bridge.
+fn ask(method: &str, params: Value) -> Result<Value, String> {
+    let answer = browser::request(method, &params.to_string())?;
+    serde_json::from_str(&answer).map_err(|e| format!("Unexpected answer from the browser: {}", e))
+}
+
+fn query(text: &str) -> Result<&str, String> {
+    match text.trim() {
+        "" => Err("Give a search query".to_string()),
+        query => Ok(query),
+    }
+}
+
+fn max_results(asked: Option<usize>) -> usize {
+    asked.unwrap_or(DEFAULT_RESULTS).clamp(1, MAX_RESULTS)
+}
+
+fn list_tabs() -> Result<Value, String> {
+    Ok(json!({ "tabs": ask("tabs.list", json!({}))? }))
+}
+
+fn read_tab(args: ReadArgs) -> Result<Value, String> {
+    let max_chars = args.max_chars.unwrap_or(DEFAULT_CHARS).clamp(1, MAX_CHARS);
+    let read = ask("tab.readContent", json!({ "tab_id": args.tab_id }))?;
+    Ok(page::view(&read, max_chars))
+}
+
+fn search_bookmarks(args: BookmarksArgs) -> Result<Value, String> {
+    let params = json!({ "query": query(&args.query)?, "max_results": max_results(args.max_results) });
+    Ok(json!({ "bookmarks": ask("bookmarks.search", params)? }))
+}
+
+fn search_history(args: HistoryArgs) -> Result<Value, String> {
+    let params = json!({
+        "query": query(&args.query)?,
+        "max_results": max_results(args.max_results),
+        "days": args.days.unwrap_or(DEFAULT_DAYS).clamp(1, MAX_DAYS),
+    });
+    Ok(json!({ "pages": ask("history.search", params)? }))
+}
+
+fn reply(result: Result<Value, String>) -> Result<ToolResult, Error> {
+    Ok(match result {
+        Ok(value) => ToolResult::json(&value),
+        Err(e) => ToolResult::error(e),
+    })
+}
+
+/// List the tabs open in the browser, with their ids, titles and URLs and
+/// which is active in each window.
+#[harbor_tool(name = "tabs.list")]
+fn tabs(_args: TabsArgs) -> Result<ToolResult, Error> {
+    reply(list_tabs())
+}
+
+/// Read the text of an open tab's page, by its id from tabs.list. Long
+/// pages are cut, and marked truncated.
+#[harbor_tool(name = "tab.readContent")]
+fn read_content(args: ReadArgs) -> Result<ToolResult, Error> {
+    reply(read_tab(args))
+}
+
+/// Search the user's bookmarks by title and URL.
+#[harbor_tool(name = "bookmarks.search")]
+fn bookmarks(args: BookmarksArgs) -> Result<ToolResult, Error> {
+    reply(search_bookmarks(args))
+}
+
+/// Search the pages the user visited recently by title and URL, most
+/// recent first, with when each was last visited.
+#[harbor_tool(name = "history.search")]
+fn history(args: HistoryArgs) -> Result<ToolResult, Error> {
+    reply(search_history(args))
+}
+
+fn server() -> Server {
+    Server::new("mcp-browser", "1.0.0")
+        .register(tabs_tool())
+        .register(read_content_tool())
+        .register(bookmarks_tool())
+        .register(history_tool())
+}
+
+struct Component;
+
+impl exports::harbor::mcp::server::Guest for Component {
+    fn handle(request: String) -> String {
+        server().handle(&request)
+    }
+}
+
+export!(Component);
@@ -0,0 +1,58 @@
+//! A tab's text as `tab.readContent` returns it: tidied and cut to size.
+
+use serde_json::{json, Value};
+
+/// `text` with runs of blank lines and trailing spaces dropped, as pages
+/// are full of both.
+pub fn tidy(text: &str) -> String {
+    let mut tidied = String::with_capacity(text.len());
+    let mut blank = 0;
+    for line in text.lines().map(str::trim_end) {
+        if line.trim().is_empty() {
+            blank += 1;
+            continue;
+        }
+        if !tidied.is_empty() {
+            tidied.push_str(if blank > 0 { "\n\n" } else { "\n" });
+        }
+        tidied.push_str(line);
+        blank = 0;
+    }
+    tidied
+}
+
+/// The page the extension read, its text tidied and cut to `max_chars`.
+pub fn view(page: &Value, max_chars: usize) -> Value {
+    let text = tidy(page["text"].as_str().unwrap_or_default());
+    let (text, truncated) = match text.char_indices().nth(max_chars) {
+        Some((end, _)) => (text[..end].to_string(), true),
+        None => (text, false),
+    };
+    json!({
+        "url": page["url"],
+        "title": page["title"],
+        "text": text,
+        "truncated": truncated,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tidy() {
+        assert_eq!(tidy("Title  \n\n\n\nFirst\nSecond   \n \n"), "Title\n\nFirst\nSecond");
+        assert_eq!(tidy("\n\n  \n"), "");
+    }
+
+    #[test]
+    fn test_view() {
+        let page = json!({ "url": "https://example.org/", "title": "Example", "text": "héllo\n\n\nworld" });
+        let shown = view(&page, 5);
+        assert_eq!(shown["text"], "héllo");
+        assert_eq!(shown["truncated"], true);
+        assert_eq!(view(&page, 100)["text"], "héllo\n\nworld");
+        assert_eq!(view(&page, 100)["truncated"], false);
+    }
+}
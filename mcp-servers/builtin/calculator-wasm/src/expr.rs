@@ -0,0 +1,613 @@
+//! Arithmetic expressions, parsed and evaluated in one pass.
+//!
+//! Integers are exact at any size (`2^200`, `50!`). A result becomes a
+//! float when a division leaves a remainder, a function like `sqrt` is
+//! applied, or a float is involved. By precedence, lowest first:
+//!
+//! - `+` `-`
+//! - `*` `/` `%` (remainder, with the sign of the dividend)
+//! - unary `-` and `+`
+//! - `^` (right associative, so `2^3^2` is `2^9`; `-2^2` is `-4`)
+//! - postfix `!` (factorial)
+//!
+//! Functions: `sqrt cbrt exp ln log log2 log10 sin cos tan asin acos atan
+//! sinh cosh tanh abs floor ceil round trunc` of one argument, `log(x,
+//! base)`, and `min max gcd lcm` of two or more. Constants: `pi`, `e`,
+//! `tau`.
+
+use std::fmt;
+
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_traits::{FromPrimitive, One, Signed, ToPrimitive, Zero};
+
+/// Longest expression accepted, in characters.
+pub const MAX_LENGTH: usize = 1000;
+/// Deepest nesting of parentheses and operators.
+const MAX_DEPTH: usize = 64;
+/// Largest integer result, in bits (about 10,000 decimal digits).
+const MAX_BITS: u64 = 33_220;
+/// Largest `n` in `n!`.
+const MAX_FACTORIAL: u64 = 3000;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Number {
+    Int(BigInt),
+    Float(f64),
+}
+
+impl Number {
+    pub fn to_f64(&self) -> f64 {
+        match self {
+            Number::Int(n) => n.to_f64().unwrap_or(f64::NAN),
+            Number::Float(x) => *x,
+        }
+    }
+
+    pub fn is_exact(&self) -> bool {
+        matches!(self, Number::Int(_))
+    }
+}
+
+impl fmt::Display for Number {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Number::Int(n) => write!(f, "{}", n),
+            // 15 significant digits, so 0.1 + 0.2 shows as 0.3
+            Number::Float(x) => {
+                let rounded: f64 = format!("{:.14e}", x).parse().unwrap_or(*x);
+                if rounded != 0.0 && !(1e-7..1e21).contains(&rounded.abs()) {
+                    write!(f, "{:e}", rounded)
+                } else {
+                    write!(f, "{}", rounded)
+                }
+            }
+        }
+    }
+}
+
+/// Why an expression couldn't be evaluated, with the character position
+/// the problem was found at when there is one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExprError {
+    pub message: String,
+    pub position: Option<usize>,
+}
+
+impl ExprError {
+    fn at(position: usize, message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            position: Some(position),
+        }
+    }
+
+    fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            position: None,
+        }
+    }
+}
+
+impl fmt::Display for ExprError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.position {
+            Some(position) => write!(f, "{} (at character {})", self.message, position + 1),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(Number),
+    Name(String),
+    Op(char),
+}
+
+fn tokenize(input: &str) -> Result<Vec<(usize, Token)>, ExprError> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let start = i;
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() || c == '.' {
+            let in_number = |c: char| c.is_ascii_digit() || c == '.' || c == '_';
+            while i < chars.len() && in_number(chars[i]) {
+                i += 1;
+            }
+            // An exponent: e, an optional sign and digits
+            if i < chars.len() && matches!(chars[i], 'e' | 'E') {
+                let sign = usize::from(matches!(chars.get(i + 1), Some('+' | '-')));
+                if chars.get(i + 1 + sign).is_some_and(char::is_ascii_digit) {
+                    i += 1 + sign;
+                    while i < chars.len() && chars[i].is_ascii_digit() {
+                        i += 1;
+                    }
+                }
+            }
+            let text: String = chars[start..i].iter().filter(|&&c| c != '_').collect();
+            tokens.push((start, Token::Number(parse_number(&text, start)?)));
+        } else if c.is_alphabetic() {
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            let name: String = chars[start..i].iter().collect();
+            tokens.push((start, Token::Name(name.to_lowercase())));
+        } else {
+            let op = match c {
+                '×' | '·' => '*',
+                '÷' => '/',
+                '−' => '-',
+                '+' | '-' | '*' | '/' | '%' | '^' | '!' | '(' | ')' | ',' => c,
+                _ => return Err(ExprError::at(i, format!("Unexpected '{}'", c))),
+            };
+            // `**` is accepted for `^`
+            if op == '*' && chars.get(i + 1) == Some(&'*') {
+                i += 1;
+                tokens.push((start, Token::Op('^')));
+            } else {
+                tokens.push((start, Token::Op(op)));
+            }
+            i += 1;
+        }
+    }
+    Ok(tokens)
+}
+
+fn parse_number(text: &str, position: usize) -> Result<Number, ExprError> {
+    if text.bytes().all(|b| b.is_ascii_digit()) {
+        return text
+            .parse::<BigInt>()
+            .map(Number::Int)
+            .map_err(|_| ExprError::at(position, format!("Invalid number '{}'", text)));
+    }
+    text.parse::<f64>()
+        .ok()
+        .filter(|x| x.is_finite())
+        .map(Number::Float)
+        .ok_or_else(|| ExprError::at(position, format!("Invalid number '{}'", text)))
+}
+
+fn float(x: f64, what: &str) -> Result<Number, ExprError> {
+    if x.is_finite() {
+        Ok(Number::Float(x))
+    } else {
+        Err(ExprError::new(format!("{} is not a finite number", what)))
+    }
+}
+
+fn check_size(n: BigInt) -> Result<Number, ExprError> {
+    if n.bits() > MAX_BITS {
+        return Err(ExprError::new("The result has more than 10,000 digits"));
+    }
+    Ok(Number::Int(n))
+}
+
+fn add(a: Number, b: Number) -> Result<Number, ExprError> {
+    match (a, b) {
+        (Number::Int(a), Number::Int(b)) => check_size(a + b),
+        (a, b) => float(a.to_f64() + b.to_f64(), "The sum"),
+    }
+}
+
+fn subtract(a: Number, b: Number) -> Result<Number, ExprError> {
+    match (a, b) {
+        (Number::Int(a), Number::Int(b)) => check_size(a - b),
+        (a, b) => float(a.to_f64() - b.to_f64(), "The difference"),
+    }
+}
+
+fn multiply(a: Number, b: Number) -> Result<Number, ExprError> {
+    match (a, b) {
+        (Number::Int(a), Number::Int(b)) => {
+            if a.bits() + b.bits() > MAX_BITS + 1 {
+                return Err(ExprError::new("The result has more than 10,000 digits"));
+            }
+            check_size(a * b)
+        }
+        (a, b) => float(a.to_f64() * b.to_f64(), "The product"),
+    }
+}
+
+fn divide(a: Number, b: Number) -> Result<Number, ExprError> {
+    if b.to_f64() == 0.0 {
+        return Err(ExprError::new("Division by zero"));
+    }
+    match (a, b) {
+        (Number::Int(a), Number::Int(b)) if (&a % &b).is_zero() => Ok(Number::Int(a / b)),
+        (a, b) => float(a.to_f64() / b.to_f64(), "The quotient"),
+    }
+}
+
+fn remainder(a: Number, b: Number) -> Result<Number, ExprError> {
+    if b.to_f64() == 0.0 {
+        return Err(ExprError::new("Division by zero"));
+    }
+    match (a, b) {
+        (Number::Int(a), Number::Int(b)) => Ok(Number::Int(a % b)),
+        (a, b) => float(a.to_f64() % b.to_f64(), "The remainder"),
+    }
+}
+
+fn power(base: Number, exponent: Number) -> Result<Number, ExprError> {
+    if let (Number::Int(base), Number::Int(exponent)) = (&base, &exponent) {
+        if !exponent.is_negative() {
+            // 0 and ±1 stay small whatever the exponent
+            if base.abs() <= BigInt::one() {
+                if exponent.is_zero() || (base.is_negative() && exponent.is_even()) {
+                    return Ok(Number::Int(BigInt::one()));
+                }
+                return Ok(Number::Int(base.clone()));
+            }
+            let log2 = base.abs().to_f64().map_or(f64::INFINITY, f64::log2);
+            let exponent = exponent
+                .to_u32()
+                .filter(|e| log2 * f64::from(*e) <= MAX_BITS as f64)
+                .ok_or_else(|| ExprError::new("The result has more than 10,000 digits"))?;
+            return check_size(base.pow(exponent));
+        }
+    }
+    float(base.to_f64().powf(exponent.to_f64()), "The power")
+}
+
+fn factorial(n: Number, position: usize) -> Result<Number, ExprError> {
+    let n = match n {
+        Number::Int(n) if !n.is_negative() => n,
+        Number::Float(x) if x >= 0.0 && x.fract() == 0.0 => BigInt::from_f64(x).unwrap_or_default(),
+        _ => return Err(ExprError::at(position, "Factorial needs a whole number of at least 0")),
+    };
+    let n = n
+        .to_u64()
+        .filter(|n| *n <= MAX_FACTORIAL)
+        .ok_or_else(|| {
+            ExprError::at(position, format!("Factorial is limited to {}!", MAX_FACTORIAL))
+        })?;
+    Ok(Number::Int((2..=n).fold(BigInt::one(), |product, k| product * k)))
+}
+
+/// Round `x` to an integer with `f`, keeping it exact.
+fn to_integer(x: Number, f: fn(f64) -> f64) -> Result<Number, ExprError> {
+    match x {
+        Number::Int(n) => Ok(Number::Int(n)),
+        Number::Float(x) => BigInt::from_f64(f(x))
+            .map(Number::Int)
+            .ok_or_else(|| ExprError::new("The value is too large to round")),
+    }
+}
+
+fn integers(name: &str, args: Vec<Number>) -> Result<Vec<BigInt>, ExprError> {
+    let whole = || ExprError::new(format!("{} needs whole numbers", name));
+    args.into_iter()
+        .map(|arg| match arg {
+            Number::Int(n) => Ok(n),
+            Number::Float(x) if x.fract() == 0.0 => BigInt::from_f64(x).ok_or_else(whole),
+            _ => Err(whole()),
+        })
+        .collect()
+}
+
+fn call(name: &str, args: Vec<Number>, position: usize) -> Result<Number, ExprError> {
+    let arity = |expected: &str| ExprError::at(position, format!("{} takes {}", name, expected));
+    match name {
+        "min" | "max" | "gcd" | "lcm" if args.len() < 2 => {
+            return Err(arity("two or more arguments"));
+        }
+        "min" | "max" => {
+            let mut args = args.into_iter();
+            let first = args.next().expect("two or more arguments");
+            return Ok(args.fold(first, |best, x| {
+                let better = if name == "min" {
+                    x.to_f64() < best.to_f64()
+                } else {
+                    x.to_f64() > best.to_f64()
+                };
+                if better {
+                    x
+                } else {
+                    best
+                }
+            }));
+        }
+        "gcd" | "lcm" => {
+            let numbers = integers(name, args)?;
+            let first = numbers[0].clone();
+            let result = numbers[1..].iter().fold(first, |acc, n| {
+                if name == "gcd" {
+                    acc.gcd(n)
+                } else {
+                    acc.lcm(n)
+                }
+            });
+            return check_size(result);
+        }
+        "log" if args.len() == 2 => {
+            let (x, base) = (args[0].to_f64(), args[1].to_f64());
+            return float(x.ln() / base.ln(), &format!("log({}, {})", args[0], args[1]));
+        }
+        _ => {}
+    }
+    let [x] = <[Number; 1]>::try_from(args).map_err(|_| arity("one argument"))?;
+    let value = x.to_f64();
+    let f: fn(f64) -> f64 = match name {
+        "abs" => {
+            return Ok(match x {
+                Number::Int(n) => Number::Int(n.abs()),
+                Number::Float(x) => Number::Float(x.abs()),
+            })
+        }
+        "floor" => return to_integer(x, f64::floor),
+        "ceil" => return to_integer(x, f64::ceil),
+        "round" => return to_integer(x, f64::round),
+        "trunc" => return to_integer(x, f64::trunc),
+        "sqrt" => f64::sqrt,
+        "cbrt" => f64::cbrt,
+        "exp" => f64::exp,
+        "ln" => f64::ln,
+        "log" | "log10" => f64::log10,
+        "log2" => f64::log2,
+        "sin" => f64::sin,
+        "cos" => f64::cos,
+        "tan" => f64::tan,
+        "asin" => f64::asin,
+        "acos" => f64::acos,
+        "atan" => f64::atan,
+        "sinh" => f64::sinh,
+        "cosh" => f64::cosh,
+        "tanh" => f64::tanh,
+        _ => return Err(ExprError::at(position, format!("Unknown function '{}'", name))),
+    };
+    // An exact square root stays exact
+    if let (Number::Int(n), "sqrt") = (&x, name) {
+        if !n.is_negative() {
+            let root = n.sqrt();
+            if &root * &root == *n {
+                return Ok(Number::Int(root));
+            }
+        }
+    }
+    float(f(value), &format!("{}({})", name, x))
+}
+
+fn constant(name: &str) -> Option<f64> {
+    match name {
+        "pi" | "π" => Some(std::f64::consts::PI),
+        "e" => Some(std::f64::consts::E),
+        "tau" => Some(std::f64::consts::TAU),
+        _ => None,
+    }
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    next: usize,
+    depth: usize,
+    /// Where the input ends, for errors there
+    end: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.next).map(|(_, token)| token)
+    }
+
+    fn position(&self) -> usize {
+        self.tokens.get(self.next).map_or(self.end, |(position, _)| *position)
+    }
+
+    fn eat(&mut self, op: char) -> bool {
+        if self.peek() == Some(&Token::Op(op)) {
+            self.next += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, op: char) -> Result<(), ExprError> {
+        if self.eat(op) {
+            Ok(())
+        } else {
+            Err(ExprError::at(self.position(), format!("Expected '{}'", op)))
+        }
+    }
+
+    fn nested<T>(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> Result<T, ExprError>,
+    ) -> Result<T, ExprError> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(ExprError::at(self.position(), "The expression is nested too deeply"));
+        }
+        let result = f(self);
+        self.depth -= 1;
+        result
+    }
+
+    fn expression(&mut self) -> Result<Number, ExprError> {
+        self.nested(|parser| {
+            let mut value = parser.term()?;
+            loop {
+                if parser.eat('+') {
+                    value = add(value, parser.term()?)?;
+                } else if parser.eat('-') {
+                    value = subtract(value, parser.term()?)?;
+                } else {
+                    return Ok(value);
+                }
+            }
+        })
+    }
+
+    fn term(&mut self) -> Result<Number, ExprError> {
+        let mut value = self.unary()?;
+        loop {
+            if self.eat('*') {
+                value = multiply(value, self.unary()?)?;
+            } else if self.eat('/') {
+                value = divide(value, self.unary()?)?;
+            } else if self.eat('%') {
+                value = remainder(value, self.unary()?)?;
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    fn unary(&mut self) -> Result<Number, ExprError> {
+        self.nested(|parser| {
+            if parser.eat('-') {
+                let value = parser.unary()?;
+                return Ok(match value {
+                    Number::Int(n) => Number::Int(-n),
+                    Number::Float(x) => Number::Float(-x),
+                });
+            }
+            if parser.eat('+') {
+                return parser.unary();
+            }
+            let base = parser.postfix()?;
+            if parser.eat('^') {
+                return power(base, parser.unary()?);
+            }
+            Ok(base)
+        })
+    }
+
+    fn postfix(&mut self) -> Result<Number, ExprError> {
+        let mut value = self.primary()?;
+        while self.peek() == Some(&Token::Op('!')) {
+            let position = self.position();
+            self.next += 1;
+            value = factorial(value, position)?;
+        }
+        Ok(value)
+    }
+
+    fn primary(&mut self) -> Result<Number, ExprError> {
+        let position = self.position();
+        let Some((_, token)) = self.tokens.get(self.next).cloned() else {
+            return Err(ExprError::at(position, "Unexpected end of expression"));
+        };
+        self.next += 1;
+        match token {
+            Token::Number(n) => Ok(n),
+            Token::Op('(') => {
+                let value = self.expression()?;
+                self.expect(')')?;
+                Ok(value)
+            }
+            Token::Name(name) if self.eat('(') => {
+                let mut args = vec![self.expression()?];
+                while self.eat(',') {
+                    args.push(self.expression()?);
+                }
+                self.expect(')')?;
+                call(&name, args, position)
+            }
+            Token::Name(name) => constant(&name)
+                .map(Number::Float)
+                .ok_or_else(|| ExprError::at(position, format!("Unknown name '{}'", name))),
+            Token::Op(op) => Err(ExprError::at(position, format!("Unexpected '{}'", op))),
+        }
+    }
+}
+
+/// Evaluate an expression.
+pub fn evaluate(input: &str) -> Result<Number, ExprError> {
+    let length = input.chars().count();
+    if length > MAX_LENGTH {
+        return Err(ExprError::new(format!("Expressions are limited to {} characters", MAX_LENGTH)));
+    }
+    let mut parser = Parser {
+        tokens: tokenize(input)?,
+        next: 0,
+        depth: 0,
+        end: length,
+    };
+    if parser.tokens.is_empty() {
+        return Err(ExprError::new("The expression is empty"));
+    }
+    let value = parser.expression()?;
+    if parser.next < parser.tokens.len() {
+        let position = parser.position();
+        return Err(match parser.peek() {
+            Some(Token::Op(')')) => ExprError::at(position, "Unmatched ')'"),
+            _ => ExprError::at(position, "Expected an operator"),
+        });
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(input: &str) -> String {
+        evaluate(input).map(|n| n.to_string()).unwrap_or_else(|e| e.to_string())
+    }
+
+    #[test]
+    fn test_precedence() {
+        assert_eq!(eval("1 + 2 * 3"), "7");
+        assert_eq!(eval("(1 + 2) * 3"), "9");
+        assert_eq!(eval("2^3^2"), "512");
+        assert_eq!(eval("-2^2"), "-4");
+        assert_eq!(eval("2^-1"), "0.5");
+        assert_eq!(eval("10 - 4 - 3"), "3");
+        assert_eq!(eval("7 % 3 + 3!"), "7");
+        assert_eq!(eval("2 ** 10"), "1024");
+    }
+
+    #[test]
+    fn test_exact_integers() {
+        assert_eq!(eval("2^100"), "1267650600228229401496703205376");
+        assert_eq!(eval("25!"), "15511210043330985984000000");
+        assert_eq!(eval("99999999999999999999 + 1"), "100000000000000000000");
+        assert_eq!(eval("6 / 3"), "2");
+        assert!(evaluate("6 / 3").unwrap().is_exact());
+        assert_eq!(eval("1 / 3"), "0.333333333333333");
+        assert_eq!(eval("sqrt(144)"), "12");
+        assert_eq!(eval("gcd(12, 18, 27)"), "3");
+        assert_eq!(eval("lcm(4, 6)"), "12");
+        assert_eq!(eval("(-1)^1000001"), "-1");
+        assert_eq!(eval("1_000 * 3"), "3000");
+    }
+
+    #[test]
+    fn test_floats_and_functions() {
+        assert_eq!(eval("0.1 + 0.2"), "0.3");
+        assert_eq!(eval("sqrt(2)"), "1.4142135623731");
+        assert_eq!(eval("ln(e)"), "1");
+        assert_eq!(eval("log(1000)"), "3");
+        assert_eq!(eval("log(8, 2)"), "3");
+        assert_eq!(eval("round(2.5) + floor(-1.5)"), "1");
+        assert_eq!(eval("max(1, 2.5, -3)"), "2.5");
+        assert_eq!(eval("1.5e3 / 2"), "750");
+        assert_eq!(eval("cos(pi)"), "-1");
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(eval("1 / 0"), "Division by zero");
+        assert_eq!(eval("2 +"), "Unexpected end of expression (at character 4)");
+        assert_eq!(eval("(1 + 2"), "Expected ')' (at character 7)");
+        assert_eq!(eval("1 + 2)"), "Unmatched ')' (at character 6)");
+        assert_eq!(eval("2 3"), "Expected an operator (at character 3)");
+        assert_eq!(eval("foo(1)"), "Unknown function 'foo' (at character 1)");
+        assert_eq!(eval("x + 1"), "Unknown name 'x' (at character 1)");
+        assert_eq!(eval("1 $ 2"), "Unexpected '$' (at character 3)");
+        assert_eq!(eval("sqrt(-1)"), "sqrt(-1) is not a finite number");
+        assert_eq!(eval("ln(0)"), "ln(0) is not a finite number");
+        assert_eq!(eval("(-3)!"), "Factorial needs a whole number of at least 0 (at character 5)");
+        assert!(eval("10^100000").contains("10,000 digits"));
+        assert!(eval("5000!").contains("limited"));
+        assert!(eval(&"(".repeat(200)).contains("nested too deeply"));
+        assert_eq!(eval("   "), "The expression is empty");
+    }
+}
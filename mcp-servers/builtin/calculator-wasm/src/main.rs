@@ -0,0 +1,95 @@
+//! Calculator MCP Server (WASM)
+//!
+//! Evaluates arithmetic expressions so agents don't have to do the sums
+//! themselves: precedence, parentheses, functions like `sqrt` and `ln`,
+//! and integers exact at any size. See `expr` for the syntax.
+//!
+//! Build with:
+//!   cargo build --release --target wasm32-wasip1
+
+mod expr;
+
+use harbor_mcp_sdk::{harbor_tool, Error, Server, ToolInput, ToolResult};
+use serde::Deserialize;
+use serde_json::json;
+
+use expr::Number;
+
+/// Integer results with more digits than this also get an approximation.
+const APPROXIMATE_DIGITS: usize = 15;
+
+#[derive(Deserialize, ToolInput)]
+#[serde(deny_unknown_fields)]
+struct EvaluateArgs {
+    /// The expression, e.g. "2^64 - 1", "sqrt(2) * 10" or "20! / (5! * 15!)"
+    expression: String,
+}
+
+/// `x` in scientific notation to 15 significant digits, e.g. `1.8e19`.
+fn scientific(x: f64) -> String {
+    let formatted = format!("{:.14e}", x);
+    match formatted.split_once('e') {
+        Some((mantissa, exponent)) => {
+            let mantissa = mantissa.trim_end_matches('0').trim_end_matches('.');
+            format!("{}e{}", mantissa, exponent)
+        }
+        None => formatted,
+    }
+}
+
+/// A result as the tool returns it: `result` is exact when `exact` is
+/// true, and long integers also come as an `approximate` float.
+fn view(expression: &str, value: &Number) -> serde_json::Value {
+    let result = value.to_string();
+    let mut view = json!({
+        "expression": expression,
+        "result": result,
+        "exact": value.is_exact(),
+    });
+    let digits = result.trim_start_matches('-').len();
+    if value.is_exact() && digits > APPROXIMATE_DIGITS {
+        view["digits"] = json!(digits);
+        view["approximate"] = json!(scientific(value.to_f64()));
+    }
+    view
+}
+
+/// Evaluate an arithmetic expression exactly. Supports + - * / % ^ and !,
+/// parentheses, sqrt, cbrt, exp, ln, log, log2, log10, trigonometric
+/// functions, abs, floor, ceil, round, trunc, min, max, gcd, lcm and the
+/// constants pi, e and tau. Integers are exact at any size; other results
+/// are floats to 15 significant digits.
+#[harbor_tool(name = "calc.evaluate")]
+fn evaluate(args: EvaluateArgs) -> Result<ToolResult, Error> {
+    let expression = args.expression.trim();
+    match expr::evaluate(expression) {
+        Ok(value) => Ok(ToolResult::json(&view(expression, &value))),
+        Err(e) => Ok(ToolResult::error(e.to_string())),
+    }
+}
+
+fn main() {
+    Server::new("mcp-calculator", "1.0.0")
+        .register(evaluate_tool())
+        .run();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_view() {
+        let value = expr::evaluate("2^64").unwrap();
+        let view = view("2^64", &value);
+        assert_eq!(view["result"], "18446744073709551616");
+        assert_eq!(view["exact"], true);
+        assert_eq!(view["digits"], 20);
+        assert_eq!(view["approximate"], "1.84467440737096e19");
+
+        let view = super::view("1/4", &expr::evaluate("1/4").unwrap());
+        assert_eq!(view["result"], "0.25");
+        assert_eq!(view["exact"], false);
+        assert!(view.get("approximate").is_none());
+    }
+}
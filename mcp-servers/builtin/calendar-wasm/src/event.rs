@@ -0,0 +1,166 @@
+//! Calendar API events as the tools show and create them.
+
+use serde_json::{json, Value};
+
+use crate::time;
+
+/// Characters of an event's description shown in a listing.
+const MAX_DESCRIPTION_CHARS: usize = 500;
+
+/// An event's start or end: its date-time, or its date if all-day.
+fn when(point: &Value) -> Value {
+    point.get("dateTime").or_else(|| point.get("date")).cloned().unwrap_or(Value::Null)
+}
+
+/// An event as the tools show it.
+pub fn view(event: &Value) -> Value {
+    let mut shown = json!({
+        "id": event["id"],
+        "summary": event["summary"],
+        "start": when(&event["start"]),
+        "end": when(&event["end"]),
+        "all_day": event["start"].get("date").is_some(),
+        "status": event["status"],
+        "html_link": event["htmlLink"],
+    });
+    for (key, field) in [("location", "location"), ("meet_link", "hangoutLink")] {
+        if let Some(value) = event.get(field) {
+            shown[key] = value.clone();
+        }
+    }
+    if let Some(description) = event["description"].as_str() {
+        shown["description"] = match description.char_indices().nth(MAX_DESCRIPTION_CHARS) {
+            Some((end, _)) => json!(format!("{}...", &description[..end])),
+            None => json!(description),
+        };
+    }
+    if let Some(organizer) = event["organizer"]["email"].as_str() {
+        shown["organizer"] = json!(organizer);
+    }
+    if let Some(attendees) = event["attendees"].as_array() {
+        shown["attendees"] = attendees
+            .iter()
+            .map(|a| json!({ "email": a["email"], "response": a["responseStatus"] }))
+            .collect();
+    }
+    shown
+}
+
+/// What's needed to create an event.
+pub struct NewEvent<'a> {
+    pub summary: &'a str,
+    pub start: &'a str,
+    pub end: &'a str,
+    pub timezone: Option<&'a str>,
+    pub description: Option<&'a str>,
+    pub location: Option<&'a str>,
+    pub attendees: &'a [String],
+}
+
+/// The start or end of a new event, for the API.
+fn point(text: &str, timezone: Option<&str>) -> Value {
+    if time::is_date(text) {
+        return json!({ "date": text });
+    }
+    match timezone {
+        Some(timezone) => json!({ "dateTime": text, "timeZone": timezone }),
+        None => json!({ "dateTime": text }),
+    }
+}
+
+/// The API body for a new event. Start and end must both be dates, for an
+/// all-day event (the end date is exclusive), or both date-times; those
+/// without a UTC offset need a time zone.
+pub fn body(event: &NewEvent) -> Result<Value, String> {
+    if event.summary.trim().is_empty() {
+        return Err("Give the event a summary".to_string());
+    }
+    let dates = time::is_date(event.start) && time::is_date(event.end);
+    let date_times = time::is_date_time(event.start) && time::is_date_time(event.end);
+    if !dates && !date_times {
+        return Err(format!(
+            "'{}' and '{}' must both be dates (2024-06-03) or both date-times (2024-06-03T09:00:00)",
+            event.start, event.end
+        ));
+    }
+    if date_times && event.timezone.is_none() && !(time::has_offset(event.start) && time::has_offset(event.end)) {
+        return Err("Give a timezone, or times with a UTC offset such as 2024-06-03T09:00:00+02:00".to_string());
+    }
+    // Dates, and times in one zone, sort as text; Google checks the rest
+    let comparable = dates || !(time::has_offset(event.start) || time::has_offset(event.end));
+    if comparable && event.end < event.start {
+        return Err(format!("The event ends ({}) before it starts ({})", event.end, event.start));
+    }
+    let mut body = json!({
+        "summary": event.summary,
+        "start": point(event.start, event.timezone),
+        "end": point(event.end, event.timezone),
+    });
+    if let Some(description) = event.description {
+        body["description"] = json!(description);
+    }
+    if let Some(location) = event.location {
+        body["location"] = json!(location);
+    }
+    if !event.attendees.is_empty() {
+        body["attendees"] = event.attendees.iter().map(|email| json!({ "email": email })).collect();
+    }
+    Ok(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_view() {
+        let event = json!({
+            "id": "ev1",
+            "status": "confirmed",
+            "htmlLink": "https://www.google.com/calendar/event?eid=ev1",
+            "summary": "Standup",
+            "start": { "dateTime": "2024-06-03T09:00:00+02:00" },
+            "end": { "dateTime": "2024-06-03T09:15:00+02:00" },
+            "hangoutLink": "https://meet.google.com/abc-defg-hij",
+            "organizer": { "email": "ada@example.com" },
+            "attendees": [{ "email": "grace@example.com", "responseStatus": "accepted" }]
+        });
+        let shown = view(&event);
+        assert_eq!(shown["start"], "2024-06-03T09:00:00+02:00");
+        assert_eq!(shown["all_day"], false);
+        assert_eq!(shown["meet_link"], "https://meet.google.com/abc-defg-hij");
+        assert_eq!(shown["attendees"][0], json!({ "email": "grace@example.com", "response": "accepted" }));
+        assert!(shown.get("location").is_none());
+
+        let holiday = view(&json!({ "start": { "date": "2024-12-25" }, "end": { "date": "2024-12-26" } }));
+        assert_eq!(holiday["start"], "2024-12-25");
+        assert_eq!(holiday["all_day"], true);
+    }
+
+    fn new<'a>(start: &'a str, end: &'a str, timezone: Option<&'a str>) -> NewEvent<'a> {
+        NewEvent {
+            summary: "Lunch",
+            start,
+            end,
+            timezone,
+            description: None,
+            location: Some("Canteen"),
+            attendees: &[],
+        }
+    }
+
+    #[test]
+    fn test_body() {
+        let timed = body(&new("2024-06-03T12:00:00", "2024-06-03T13:00:00", Some("Europe/Paris"))).unwrap();
+        assert_eq!(timed["start"], json!({ "dateTime": "2024-06-03T12:00:00", "timeZone": "Europe/Paris" }));
+        assert_eq!(timed["location"], "Canteen");
+
+        let all_day = body(&new("2024-06-03", "2024-06-04", None)).unwrap();
+        assert_eq!(all_day["end"], json!({ "date": "2024-06-04" }));
+        assert!(body(&new("2024-06-03T12:00:00Z", "2024-06-03T13:00:00Z", None)).is_ok());
+
+        assert!(body(&new("2024-06-03T12:00:00", "2024-06-03T13:00:00", None)).is_err());
+        assert!(body(&new("2024-06-03", "2024-06-03T13:00:00Z", None)).is_err());
+        assert!(body(&new("2024-06-04", "2024-06-03", None)).is_err());
+    }
+}
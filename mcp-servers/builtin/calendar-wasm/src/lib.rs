@@ -0,0 +1,284 @@
+//! Google Calendar MCP Server (WASM component)
+//!
+//! `calendar.listEvents`, `calendar.createEvent` and `calendar.freeBusy`
+//! tools over the Calendar API, for the Google account the user connected
+//! in Harbor. Tokens come from the bridge through the `harbor:mcp/oauth`
+//! import, for one scope at a time: listing events and free/busy need
+//! `calendar.readonly`, creating events only `calendar.events`.
+//!
+//! Times are RFC 3339 (see `time`). Ranges default to starting now, and
+//! new events without a UTC offset are placed in the user's time zone when
+//! the bridge passed the locale.
+//!
+//! Build with:
+//!   cargo build --release --target wasm32-wasip2
+
+mod event;
+mod time;
+
+use harbor_mcp_sdk::{encode_query, harbor_tool, rfc3339, Api, Error, Locale, Server, ToolInput, ToolResult};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+wit_bindgen::generate!({ path: "../../wit", world: "mcp-server" });
+
+use harbor::mcp::{http, oauth};
+
+const API: &str = "https://www.googleapis.com/calendar/v3";
+// The bridge's `google_scopes`, which its tests check these against
+const READONLY_SCOPE: &str = "https://www.googleapis.com/auth/calendar.readonly";
+const EVENTS_SCOPE: &str = "https://www.googleapis.com/auth/calendar.events";
+
+const DEFAULT_RESULTS: usize = 25;
+const MAX_RESULTS: usize = 100;
+/// How far ahead listing looks unless the call says.
+const DEFAULT_LIST_DAYS: i64 = 7;
+/// How far ahead free/busy looks unless the call says.
+const DEFAULT_FREE_BUSY_DAYS: i64 = 1;
+/// Calendars asked about in one free/busy call; Google's limit is 50.
+const MAX_CALENDARS: usize = 20;
+
+#[derive(Deserialize, ToolInput)]
+#[serde(deny_unknown_fields)]
+struct ListArgs {
+    /// The calendar's id (default "primary", the user's own calendar)
+    calendar_id: Option<String>,
+    /// Start of the range, e.g. "2024-06-03T00:00:00Z" (default now)
+    time_min: Option<String>,
+    /// End of the range (default a week after the start)
+    time_max: Option<String>,
+    /// Words to find in event titles, descriptions, places and attendees
+    query: Option<String>,
+    /// Most events to return (default 25, at most 100)
+    max_results: Option<usize>,
+}
+
+#[derive(Deserialize, ToolInput)]
+#[serde(deny_unknown_fields)]
+struct CreateArgs {
+    /// The event's title
+    summary: String,
+    /// Start: a date-time such as "2024-06-03T09:00:00", or a date such
+    /// as "2024-06-03" for an all-day event
+    start: String,
+    /// End, in the same form as the start; an all-day event's end date is
+    /// the day after its last day
+    end: String,
+    /// IANA time zone for times without a UTC offset, e.g.
+    /// "Europe/Paris" (default the user's)
+    timezone: Option<String>,
+    /// Notes on the event
+    description: Option<String>,
+    /// Where the event takes place
+    location: Option<String>,
+    /// Email addresses to invite
+    #[serde(default)]
+    attendees: Vec<String>,
+    /// The calendar's id (default "primary")
+    calendar_id: Option<String>,
+    /// Email the invitation to the attendees (default true)
+    notify_attendees: Option<bool>,
+}
+
+#[derive(Deserialize, ToolInput)]
+#[serde(deny_unknown_fields)]
+struct FreeBusyArgs {
+    /// Start of the range, e.g. "2024-06-03T09:00:00Z" (default now)
+    time_min: Option<String>,
+    /// End of the range (default a day after the start)
+    time_max: Option<String>,
+    /// Ids of the calendars to check (default ["primary"]); other
+    /// people's work calendars are usually their email addresses
+    #[serde(default)]
+    calendars: Vec<String>,
+}
+
+/// The Calendar API, with a token for one scope.
+fn connect(scope: &str) -> Result<Api, String> {
+    let token = oauth::access_token(&[scope.to_string()])?;
+    Ok(Api::new("Calendar", "Google", token, fetch))
+}
+
+harbor_mcp_sdk::http_fetch!();
+
+/// A range's bounds, as given or from now, the end `days` after the start.
+fn range(time_min: Option<String>, time_max: Option<String>, days: i64) -> Result<(String, String), String> {
+    let seconds = |given: &str| {
+        time::seconds(given).ok_or_else(|| {
+            format!("'{}' isn't a date-time with a UTC offset, such as 2024-06-03T09:00:00Z", given)
+        })
+    };
+    let start = match &time_min {
+        Some(given) => seconds(given)?,
+        None => time::now(),
+    };
+    let end = match &time_max {
+        Some(given) => seconds(given)?,
+        None => start + days * 86_400,
+    };
+    if end <= start {
+        return Err("The range ends before it starts".to_string());
+    }
+    Ok((
+        time_min.unwrap_or_else(|| rfc3339(start)),
+        time_max.unwrap_or_else(|| rfc3339(end)),
+    ))
+}
+
+fn list_events(args: ListArgs) -> Result<Value, String> {
+    let calendar_id = args.calendar_id.unwrap_or_else(|| "primary".to_string());
+    let (time_min, time_max) = range(args.time_min, args.time_max, DEFAULT_LIST_DAYS)?;
+    let max_results = args.max_results.unwrap_or(DEFAULT_RESULTS).clamp(1, MAX_RESULTS);
+    let mut url = format!(
+        "{}/calendars/{}/events?singleEvents=true&orderBy=startTime&timeMin={}&timeMax={}&maxResults={}",
+        API,
+        encode_query(&calendar_id),
+        encode_query(&time_min),
+        encode_query(&time_max),
+        max_results
+    );
+    if let Some(query) = args.query.as_deref().map(str::trim).filter(|q| !q.is_empty()) {
+        url.push_str(&format!("&q={}", encode_query(query)));
+    }
+    let listed = connect(READONLY_SCOPE)?.json("GET", &url, None)?;
+    let events: Vec<Value> = listed["items"].as_array().into_iter().flatten().map(event::view).collect();
+    Ok(json!({
+        "calendar": listed["summary"],
+        "time_zone": listed["timeZone"],
+        "time_min": time_min,
+        "time_max": time_max,
+        "events": events,
+        "more": listed["nextPageToken"].is_string(),
+    }))
+}
+
+fn create_event(args: CreateArgs) -> Result<Value, String> {
+    let timezone = args.timezone.or_else(|| Locale::from_env().timezone);
+    let body = event::body(&event::NewEvent {
+        summary: &args.summary,
+        start: args.start.trim(),
+        end: args.end.trim(),
+        timezone: timezone.as_deref(),
+        description: args.description.as_deref(),
+        location: args.location.as_deref(),
+        attendees: &args.attendees,
+    })?;
+    let calendar_id = args.calendar_id.unwrap_or_else(|| "primary".to_string());
+    let send_updates = if args.notify_attendees.unwrap_or(true) { "all" } else { "none" };
+    let url = format!(
+        "{}/calendars/{}/events?sendUpdates={}",
+        API,
+        encode_query(&calendar_id),
+        send_updates
+    );
+    let created = connect(EVENTS_SCOPE)?.json("POST", &url, Some(&body))?;
+    Ok(event::view(&created))
+}
+
+fn free_busy(args: FreeBusyArgs) -> Result<Value, String> {
+    let (time_min, time_max) = range(args.time_min, args.time_max, DEFAULT_FREE_BUSY_DAYS)?;
+    let calendars = if args.calendars.is_empty() {
+        vec!["primary".to_string()]
+    } else {
+        args.calendars
+    };
+    if calendars.len() > MAX_CALENDARS {
+        return Err(format!("Ask about at most {} calendars at a time", MAX_CALENDARS));
+    }
+    let mut body = json!({
+        "timeMin": time_min,
+        "timeMax": time_max,
+        "items": calendars.iter().map(|id| json!({ "id": id })).collect::<Vec<_>>(),
+    });
+    if let Some(timezone) = Locale::from_env().timezone {
+        body["timeZone"] = json!(timezone);
+    }
+    let answer = connect(READONLY_SCOPE)?.json("POST", &format!("{}/freeBusy", API), Some(&body))?;
+    Ok(json!({
+        "time_min": time_min,
+        "time_max": time_max,
+        "calendars": calendars.iter().map(|id| busy_view(id, &answer["calendars"][id])).collect::<Vec<_>>(),
+    }))
+}
+
+/// One calendar's busy times, or why Google couldn't say.
+fn busy_view(id: &str, calendar: &Value) -> Value {
+    let mut shown = json!({
+        "id": id,
+        "busy": calendar["busy"].as_array().cloned().unwrap_or_default(),
+    });
+    if let Some(errors) = calendar["errors"].as_array().filter(|errors| !errors.is_empty()) {
+        shown["errors"] = errors.iter().map(|e| e["reason"].clone()).collect();
+    }
+    shown
+}
+
+fn reply(result: Result<Value, String>) -> Result<ToolResult, Error> {
+    Ok(match result {
+        Ok(value) => ToolResult::json(&value),
+        Err(e) => ToolResult::error(e),
+    })
+}
+
+/// List events on the user's Google Calendar in a time range, soonest
+/// first, with recurring events expanded. By default, the next 7 days.
+#[harbor_tool(name = "calendar.listEvents")]
+fn list(args: ListArgs) -> Result<ToolResult, Error> {
+    reply(list_events(args))
+}
+
+/// Create an event on the user's Google Calendar, optionally inviting
+/// attendees by email. Returns the new event with its link.
+#[harbor_tool(name = "calendar.createEvent")]
+fn create(args: CreateArgs) -> Result<ToolResult, Error> {
+    reply(create_event(args))
+}
+
+/// Find when calendars are busy in a time range, to pick a free slot.
+/// By default, the user's own calendar for the next day.
+#[harbor_tool(name = "calendar.freeBusy")]
+fn busy(args: FreeBusyArgs) -> Result<ToolResult, Error> {
+    reply(free_busy(args))
+}
+
+fn server() -> Server {
+    Server::new("mcp-calendar", "1.0.0")
+        .register(list_tool())
+        .register(create_tool())
+        .register(busy_tool())
+}
+
+struct Component;
+
+impl exports::harbor::mcp::server::Guest for Component {
+    fn handle(request: String) -> String {
+        server().handle(&request)
+    }
+}
+
+export!(Component);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_range() {
+        let given = range(Some("2024-06-03T00:00:00+02:00".to_string()), None, 1).unwrap();
+        assert_eq!(given, ("2024-06-03T00:00:00+02:00".to_string(), "2024-06-03T22:00:00Z".to_string()));
+        let (start, end) = range(None, None, 7).unwrap();
+        assert_eq!(time::seconds(&end).unwrap() - time::seconds(&start).unwrap(), 7 * 86_400);
+        assert!(range(Some("2024-06-03T00:00:00".to_string()), None, 1).is_err());
+        assert!(range(None, Some("tomorrow".to_string()), 1).is_err());
+        assert!(range(Some("2024-06-03T00:00:00Z".to_string()), Some("2024-06-02T00:00:00Z".to_string()), 1).is_err());
+    }
+
+    #[test]
+    fn test_busy_view() {
+        let calendar = json!({ "busy": [{ "start": "2024-06-03T09:00:00Z", "end": "2024-06-03T10:00:00Z" }] });
+        assert_eq!(busy_view("primary", &calendar)["busy"][0]["end"], "2024-06-03T10:00:00Z");
+        let hidden = busy_view("grace@example.com", &json!({ "errors": [{ "reason": "notFound" }] }));
+        assert_eq!(hidden["errors"], json!(["notFound"]));
+        assert_eq!(hidden["busy"], json!([]));
+    }
+}
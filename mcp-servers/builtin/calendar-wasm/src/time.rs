@@ -0,0 +1,119 @@
+//! Times as the Calendar API takes them: RFC 3339 date-times, and plain
+//! dates for all-day events.
+
+use harbor_mcp_sdk::days_from_civil;
+
+/// Seconds since the epoch of an RFC 3339 date-time with a UTC offset,
+/// ignoring fractions of a second.
+pub fn seconds(text: &str) -> Option<i64> {
+    if !is_date_time(text) || !has_offset(text) {
+        return None;
+    }
+    let (date, time) = text.split_once(['T', 't'])?;
+    let number = |s: &str| s.parse::<i64>().ok();
+    let (year, month, day) = (number(&date[..4])?, number(&date[5..7])?, number(&date[8..])?);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    let (clock, offset) = match time.find(['Z', 'z', '+', '-']) {
+        Some(i) => (&time[..i], &time[i..]),
+        None => (time, ""),
+    };
+    let clock = clock.split('.').next()?;
+    let mut fields = clock.split(':').map(number);
+    let (hour, minute) = (fields.next()??, fields.next()??);
+    let second = fields.next().unwrap_or(Some(0))?;
+    let offset = match offset.as_bytes().first() {
+        Some(b'+') | Some(b'-') => {
+            let minutes = number(&offset[1..3])? * 60 + number(&offset[4..])?;
+            if offset.starts_with('-') {
+                -minutes
+            } else {
+                minutes
+            }
+        }
+        _ => 0,
+    };
+    let days = days_from_civil(year, month as u32, day as u32);
+    Some(days * 86_400 + hour * 3600 + minute * 60 + second - offset * 60)
+}
+
+/// The current time in seconds since the epoch.
+pub fn now() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs() as i64)
+}
+
+fn digits(text: &str) -> bool {
+    !text.is_empty() && text.bytes().all(|b| b.is_ascii_digit())
+}
+
+/// Whether `text` is a date, YYYY-MM-DD.
+pub fn is_date(text: &str) -> bool {
+    let parts: Vec<&str> = text.split('-').collect();
+    matches!(parts.as_slice(), [y, m, d] if y.len() == 4 && m.len() == 2 && d.len() == 2
+        && digits(y) && digits(m) && digits(d))
+}
+
+/// Whether `text` is a date and time, YYYY-MM-DDTHH:MM[:SS[.fff]], with
+/// or without a UTC offset (`Z`, `+02:00`).
+pub fn is_date_time(text: &str) -> bool {
+    let Some((date, time)) = text.split_once(['T', 't']) else {
+        return false;
+    };
+    let clock = time.trim_end_matches(['Z', 'z']);
+    let clock = match clock.rfind(['+', '-']) {
+        Some(i) if i > 0 => {
+            let offset = &clock[i + 1..];
+            if !(offset.len() == 5 && digits(&offset[..2]) && &offset[2..3] == ":" && digits(&offset[3..])) {
+                return false;
+            }
+            &clock[..i]
+        }
+        _ => clock,
+    };
+    let clock = clock.split('.').next().unwrap_or_default();
+    let fields: Vec<&str> = clock.split(':').collect();
+    is_date(date) && (2..=3).contains(&fields.len()) && fields.iter().all(|f| f.len() == 2 && digits(f))
+}
+
+/// Whether a date-time names its UTC offset, so needs no time zone.
+pub fn has_offset(text: &str) -> bool {
+    let time = text.split_once(['T', 't']).map_or("", |(_, time)| time);
+    time.ends_with(['Z', 'z']) || time.contains(['+', '-'])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seconds() {
+        assert_eq!(seconds("1970-01-01T00:00:00Z"), Some(0));
+        assert_eq!(seconds("2024-06-03T09:00:45Z"), Some(1_717_405_245));
+        assert_eq!(seconds("2024-06-03T11:00:45.5+02:00"), Some(1_717_405_245));
+        assert_eq!(seconds("2000-02-29T00:00Z"), Some(951_782_400));
+        assert_eq!(seconds("1999-12-31T19:00:00-05:00"), Some(946_684_800));
+        assert_eq!(seconds("2024-06-03T09:00:00"), None);
+        assert_eq!(seconds("2024-13-03T09:00:00Z"), None);
+    }
+
+    #[test]
+    fn test_formats() {
+        assert!(is_date("2024-06-03"));
+        assert!(!is_date("2024-6-3"));
+        assert!(!is_date("2024-06-03T09:00"));
+
+        for valid in ["2024-06-03T09:00", "2024-06-03T09:00:00Z", "2024-06-03T09:00:00.000+02:00"] {
+            assert!(is_date_time(valid), "{}", valid);
+        }
+        for invalid in ["2024-06-03", "2024-06-03T9:00", "2024-06-03T09:00+2", "tomorrow at 9"] {
+            assert!(!is_date_time(invalid), "{}", invalid);
+        }
+
+        assert!(has_offset("2024-06-03T09:00:00Z"));
+        assert!(has_offset("2024-06-03T09:00:00-05:00"));
+        assert!(!has_offset("2024-06-03T09:00:00"));
+    }
+}
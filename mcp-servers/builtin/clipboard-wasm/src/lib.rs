@@ -0,0 +1,110 @@
+//! Clipboard MCP Server (WASM component)
+//!
+//! Lets chat agents work with what the user copied: `clipboard.get` reads
+//! the text on the OS clipboard and `clipboard.set` puts text there to be
+//! pasted elsewhere. The bridge owns the clipboard (see the
+//! `harbor:mcp/clipboard` import) and logs every read and write.
+//!
+//! Build with:
+//!   cargo build --release --target wasm32-wasip2
+
+use harbor_mcp_sdk::{harbor_tool, Error, Server, ToolInput, ToolResult};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+wit_bindgen::generate!({ path: "../../wit", world: "mcp-server" });
+
+use harbor::mcp::clipboard;
+
+const DEFAULT_CHARS: usize = 20_000;
+const MAX_CHARS: usize = 100_000;
+
+#[derive(Deserialize, ToolInput)]
+#[serde(deny_unknown_fields)]
+struct GetArgs {
+    /// Most characters to return (default 20000, at most 100000)
+    max_chars: Option<usize>,
+}
+
+#[derive(Deserialize, ToolInput)]
+#[serde(deny_unknown_fields)]
+struct SetArgs {
+    /// The text to put on the clipboard, replacing what is there
+    text: String,
+}
+
+/// The clipboard's text as `clipboard.get` shows it, cut to `max_chars`.
+fn view(text: Option<String>, max_chars: usize) -> Value {
+    let Some(text) = text else {
+        return json!({ "text": null, "note": "The clipboard is empty or holds something other than text" });
+    };
+    let chars = text.chars().count();
+    match text.char_indices().nth(max_chars) {
+        Some((end, _)) => json!({ "text": &text[..end], "chars": chars, "truncated": true }),
+        None => json!({ "text": text, "chars": chars, "truncated": false }),
+    }
+}
+
+fn get_text(args: GetArgs) -> Result<Value, String> {
+    let max_chars = args.max_chars.unwrap_or(DEFAULT_CHARS).clamp(1, MAX_CHARS);
+    Ok(view(clipboard::get_text()?, max_chars))
+}
+
+fn set_text(args: SetArgs) -> Result<Value, String> {
+    let chars = args.text.chars().count();
+    clipboard::set_text(&args.text)?;
+    Ok(json!({ "copied": true, "chars": chars }))
+}
+
+fn reply(result: Result<Value, String>) -> Result<ToolResult, Error> {
+    Ok(match result {
+        Ok(value) => ToolResult::json(&value),
+        Err(e) => ToolResult::error(e),
+    })
+}
+
+/// Read the text on the user's clipboard, such as something they just
+/// copied. Long text is cut, and marked truncated.
+#[harbor_tool(name = "clipboard.get")]
+fn get(args: GetArgs) -> Result<ToolResult, Error> {
+    reply(get_text(args))
+}
+
+/// Put text on the user's clipboard, replacing what is there, for them to
+/// paste elsewhere.
+#[harbor_tool(name = "clipboard.set")]
+fn set(args: SetArgs) -> Result<ToolResult, Error> {
+    reply(set_text(args))
+}
+
+fn server() -> Server {
+    Server::new("mcp-clipboard", "1.0.0")
+        .register(get_tool())
+        .register(set_tool())
+}
+
+struct Component;
+
+impl exports::harbor::mcp::server::Guest for Component {
+    fn handle(request: String) -> String {
+        server().handle(&request)
+    }
+}
+
+export!(Component);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_view() {
+        let shown = view(Some("héllo world".to_string()), 5);
+        assert_eq!(shown["text"], "héllo");
+        assert_eq!(shown["chars"], 11);
+        assert_eq!(shown["truncated"], true);
+
+        assert_eq!(view(Some("hi".to_string()), 5)["truncated"], false);
+        assert!(view(None, 5)["text"].is_null());
+    }
+}
@@ -0,0 +1,204 @@
+//! Convert MCP Server (WASM component)
+//!
+//! `units.convert` converts lengths, masses, temperatures and data sizes
+//! with no outside help. `currency.convert` uses the European Central
+//! Bank's daily reference rates, fetched through the host's
+//! `harbor:mcp/http` import and cached in `kv`, so the feed is read at
+//! most every few hours; when it can't be reached, the last rates fetched
+//! are used and the result says so.
+//!
+//! Build with:
+//!   cargo build --release --target wasm32-wasip2
+
+mod rates;
+mod units;
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use harbor_mcp_sdk::{harbor_tool, Error, Server, ToolInput, ToolResult};
+use serde::Deserialize;
+use serde_json::json;
+
+use rates::Rates;
+use units::Category;
+
+wit_bindgen::generate!({ path: "../../wit", world: "mcp-server" });
+
+use harbor::mcp::{http, kv};
+
+/// kv key of the last rates fetched.
+const RATES_KEY: &str = "rates";
+const USER_AGENT: &str = "Harbor-MCP-Convert/1.0";
+
+#[derive(Deserialize, ToolInput)]
+#[serde(deny_unknown_fields)]
+struct UnitsArgs {
+    /// The amount to convert
+    value: f64,
+    /// The unit it is in, e.g. "mi", "kg", "°F" or "GiB"
+    from: String,
+    /// The unit to convert it to
+    to: String,
+}
+
+#[derive(Deserialize, ToolInput)]
+#[serde(deny_unknown_fields)]
+struct ListArgs {
+    /// Only this category: length, mass, temperature or data size
+    category: Option<String>,
+}
+
+#[derive(Deserialize, ToolInput)]
+#[serde(deny_unknown_fields)]
+struct CurrencyArgs {
+    /// The amount of money
+    amount: f64,
+    /// Its currency, as an ISO 4217 code such as "USD"
+    from: String,
+    /// The currency to convert it to, such as "EUR" or "JPY"
+    to: String,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn cached_rates() -> Option<Rates> {
+    let stored = kv::get(RATES_KEY).ok().flatten()?;
+    serde_json::from_slice(&stored).ok()
+}
+
+fn fetch_rates(now: u64) -> Result<Rates, String> {
+    let request = http::Request {
+        method: "GET".to_string(),
+        url: rates::FEED_URL.to_string(),
+        headers: vec![("User-Agent".to_string(), USER_AGENT.to_string())],
+        body: None,
+    };
+    let response = http::fetch(&request)?;
+    if response.status != 200 {
+        return Err(format!("the rates feed answered HTTP {}", response.status));
+    }
+    rates::parse(&String::from_utf8_lossy(&response.body), now)
+}
+
+/// Rates no older than `MAX_AGE_SECS` if they can be had, else the last
+/// ones fetched with a note saying why they are old.
+fn current_rates() -> Result<(Rates, Option<String>), String> {
+    let now = now();
+    let cached = cached_rates();
+    if let Some(cached) = &cached {
+        if now.saturating_sub(cached.fetched_at) < rates::MAX_AGE_SECS {
+            return Ok((cached.clone(), None));
+        }
+    }
+    match fetch_rates(now) {
+        Ok(fetched) => {
+            // Not caching only costs another fetch next time
+            if let Ok(bytes) = serde_json::to_vec(&fetched) {
+                let _ = kv::set(RATES_KEY, &bytes);
+            }
+            Ok((fetched, None))
+        }
+        Err(e) => match cached {
+            Some(cached) => {
+                let note =
+                    format!("The rates couldn't be refreshed ({}), so these are from {}", e, cached.date);
+                Ok((cached, Some(note)))
+            }
+            None => Err(format!("Exchange rates couldn't be fetched: {}", e)),
+        },
+    }
+}
+
+/// Convert a length, mass, temperature or data size between units, e.g.
+/// miles to kilometres, °F to °C or GiB to MB.
+#[harbor_tool(name = "units.convert")]
+fn convert_units(args: UnitsArgs) -> Result<ToolResult, Error> {
+    let result = match units::convert(args.value, &args.from, &args.to) {
+        Ok(result) => units::round(result),
+        Err(e) => return Ok(ToolResult::error(e)),
+    };
+    Ok(ToolResult::json(&json!({
+        "value": args.value,
+        "from": args.from,
+        "to": args.to,
+        "result": result,
+        "text": format!("{} {} = {} {}", args.value, args.from.trim(), result, args.to.trim()),
+    })))
+}
+
+/// List the units units.convert knows, by category: the names of each,
+/// its symbol first.
+#[harbor_tool(name = "units.list")]
+fn list_units(args: ListArgs) -> Result<ToolResult, Error> {
+    let categories = [Category::Length, Category::Mass, Category::Temperature, Category::DataSize];
+    let wanted = args.category.as_deref().map(|name| name.trim().to_lowercase().replace('_', " "));
+    let mut listed = serde_json::Map::new();
+    for category in categories {
+        if wanted.as_deref().is_some_and(|wanted| wanted != category.name()) {
+            continue;
+        }
+        let names: Vec<_> = units::UNITS
+            .iter()
+            .filter(|unit| unit.category == category)
+            .map(|unit| unit.names)
+            .collect();
+        listed.insert(category.name().to_string(), json!(names));
+    }
+    if listed.is_empty() {
+        let message = "Unknown category; use length, mass, temperature or data size";
+        return Ok(ToolResult::error(message));
+    }
+    Ok(ToolResult::json(&serde_json::Value::Object(listed)))
+}
+
+/// Convert an amount of money between currencies at the European Central
+/// Bank's latest daily reference rates (about 30 major currencies).
+/// Reference rates are indicative, not what a bank would charge.
+#[harbor_tool(name = "currency.convert")]
+fn convert_currency(args: CurrencyArgs) -> Result<ToolResult, Error> {
+    let (rates, note) = match current_rates() {
+        Ok(current) => current,
+        Err(e) => return Ok(ToolResult::error(e)),
+    };
+    let (result, rate) = match rates.convert(args.amount, &args.from, &args.to) {
+        Ok(converted) => converted,
+        Err(e) => return Ok(ToolResult::error(e)),
+    };
+    let (from, to) = (args.from.trim().to_uppercase(), args.to.trim().to_uppercase());
+    let mut converted = json!({
+        "amount": args.amount,
+        "from": from,
+        "to": to,
+        "result": units::round(result),
+        "rate": units::round(rate),
+        "text": format!("{} {} = {:.2} {}", args.amount, from, result, to),
+        "date": rates.date,
+        "source": "European Central Bank reference rates",
+    });
+    if let Some(note) = note {
+        converted["note"] = json!(note);
+    }
+    Ok(ToolResult::json(&converted))
+}
+
+fn server() -> Server {
+    Server::new("mcp-convert", "1.0.0")
+        .register(convert_units_tool())
+        .register(list_units_tool())
+        .register(convert_currency_tool())
+}
+
+struct Component;
+
+impl exports::harbor::mcp::server::Guest for Component {
+    fn handle(request: String) -> String {
+        server().handle(&request)
+    }
+}
+
+export!(Component);
@@ -0,0 +1,109 @@
+//! Currency exchange rates from the European Central Bank's daily
+//! reference rates: about 30 currencies against the euro, published on
+//! working days around 16:00 CET. The feed is a small XML document of
+//! `<Cube currency='USD' rate='1.0945'/>` elements under a
+//! `<Cube time='2024-01-15'>` one.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+pub const FEED_URL: &str = "https://www.ecb.europa.eu/stats/eurofxref/eurofxref-daily.xml";
+
+/// Rates older than this are fetched again.
+pub const MAX_AGE_SECS: u64 = 6 * 3600;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Rates {
+    /// The day the rates are for, YYYY-MM-DD
+    pub date: String,
+    /// When they were fetched, in seconds since the Unix epoch
+    pub fetched_at: u64,
+    /// Units of each currency per euro, EUR included
+    pub rates: BTreeMap<String, f64>,
+}
+
+/// The value of `name` in the attributes of a tag, quoted either way.
+fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let start = tag.find(&format!("{}=", name))? + name.len() + 1;
+    let quote = tag[start..].chars().next().filter(|c| *c == '\'' || *c == '"')?;
+    let value = &tag[start + 1..];
+    Some(&value[..value.find(quote)?])
+}
+
+/// Parse the ECB feed, fetched at `fetched_at`.
+pub fn parse(xml: &str, fetched_at: u64) -> Result<Rates, String> {
+    let mut date = None;
+    let mut rates = BTreeMap::from([("EUR".to_string(), 1.0)]);
+    for tag in xml.split("<Cube").skip(1) {
+        let tag = &tag[..tag.find('>').unwrap_or(tag.len())];
+        if let Some(time) = attribute(tag, "time") {
+            date = Some(time.to_string());
+        }
+        if let (Some(currency), Some(rate)) = (attribute(tag, "currency"), attribute(tag, "rate")) {
+            let rate: f64 = rate
+                .parse()
+                .map_err(|_| format!("The rate for {} isn't a number: {}", currency, rate))?;
+            rates.insert(currency.to_uppercase(), rate);
+        }
+    }
+    match date {
+        Some(date) if rates.len() > 1 => Ok(Rates {
+            date,
+            fetched_at,
+            rates,
+        }),
+        _ => Err("The rates feed has no rates in it".to_string()),
+    }
+}
+
+impl Rates {
+    /// Convert `amount` between currencies, through the euro.
+    pub fn convert(&self, amount: f64, from: &str, to: &str) -> Result<(f64, f64), String> {
+        let rate = |code: &str| {
+            self.rates.get(&code.trim().to_uppercase()).copied().ok_or_else(|| {
+                let known: Vec<&str> = self.rates.keys().map(String::as_str).collect();
+                format!("No rate for '{}'; known currencies: {}", code, known.join(", "))
+            })
+        };
+        let rate = rate(to)? / rate(from)?;
+        Ok((amount * rate, rate))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FEED: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<gesmes:Envelope xmlns:gesmes="http://www.gesmes.org/xml/2002-08-01">
+  <gesmes:subject>Reference rates</gesmes:subject>
+  <Cube>
+    <Cube time='2024-01-15'>
+      <Cube currency='USD' rate='1.0945'/>
+      <Cube currency='JPY' rate='160.25'/>
+      <Cube currency="GBP" rate="0.86"/>
+    </Cube>
+  </Cube>
+</gesmes:Envelope>"#;
+
+    #[test]
+    fn test_parse() {
+        let rates = parse(FEED, 7).unwrap();
+        assert_eq!(rates.date, "2024-01-15");
+        assert_eq!(rates.rates.len(), 4);
+        assert_eq!(rates.rates["GBP"], 0.86);
+        assert!(parse("<html>maintenance</html>", 7).is_err());
+    }
+
+    #[test]
+    fn test_convert() {
+        let rates = parse(FEED, 7).unwrap();
+        let (amount, rate) = rates.convert(100.0, "eur", "USD").unwrap();
+        assert!((amount - 109.45).abs() < 1e-9 && (rate - 1.0945).abs() < 1e-12);
+        let (amount, _) = rates.convert(86.0, "GBP", "EUR").unwrap();
+        assert!((amount - 100.0).abs() < 1e-9);
+        let unknown = rates.convert(1.0, "USD", "XYZ").unwrap_err();
+        assert!(unknown.contains("known currencies: EUR, GBP"));
+    }
+}
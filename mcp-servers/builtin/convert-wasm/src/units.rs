@@ -0,0 +1,205 @@
+//! Units of length, mass, temperature and data size. Each unit converts
+//! to its category's base unit (metres, kilograms, kelvins, bytes) as
+//! `value * factor + offset`; only temperatures have an offset.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Category {
+    Length,
+    Mass,
+    Temperature,
+    DataSize,
+}
+
+impl Category {
+    pub fn name(self) -> &'static str {
+        match self {
+            Category::Length => "length",
+            Category::Mass => "mass",
+            Category::Temperature => "temperature",
+            Category::DataSize => "data size",
+        }
+    }
+}
+
+pub struct Unit {
+    /// The symbol first, then other names it is known by
+    pub names: &'static [&'static str],
+    pub category: Category,
+    factor: f64,
+    offset: f64,
+}
+
+const fn unit(names: &'static [&'static str], category: Category, factor: f64) -> Unit {
+    Unit {
+        names,
+        category,
+        factor,
+        offset: 0.0,
+    }
+}
+
+use Category::*;
+
+pub const UNITS: &[Unit] = &[
+    unit(&["m", "meter", "meters", "metre", "metres"], Length, 1.0),
+    unit(&["km", "kilometer", "kilometers", "kilometre", "kilometres"], Length, 1000.0),
+    unit(&["cm", "centimeter", "centimeters", "centimetre", "centimetres"], Length, 0.01),
+    unit(&["mm", "millimeter", "millimeters", "millimetre", "millimetres"], Length, 0.001),
+    unit(&["µm", "um", "micrometer", "micrometers", "micron", "microns"], Length, 1e-6),
+    unit(&["nm", "nanometer", "nanometers", "nanometre", "nanometres"], Length, 1e-9),
+    unit(&["in", "inch", "inches", "\""], Length, 0.0254),
+    unit(&["ft", "foot", "feet", "'"], Length, 0.3048),
+    unit(&["yd", "yard", "yards"], Length, 0.9144),
+    unit(&["mi", "mile", "miles"], Length, 1609.344),
+    unit(&["nmi", "nautical mile", "nautical miles"], Length, 1852.0),
+    unit(&["au", "astronomical unit", "astronomical units"], Length, 149_597_870_700.0),
+    unit(&["ly", "light year", "light years", "light-year"], Length, 9_460_730_472_580_800.0),
+    unit(&["kg", "kilogram", "kilograms", "kilo", "kilos"], Mass, 1.0),
+    unit(&["g", "gram", "grams"], Mass, 0.001),
+    unit(&["mg", "milligram", "milligrams"], Mass, 1e-6),
+    unit(&["µg", "ug", "mcg", "microgram", "micrograms"], Mass, 1e-9),
+    unit(&["t", "tonne", "tonnes", "metric ton", "metric tons"], Mass, 1000.0),
+    unit(&["lb", "lbs", "pound", "pounds"], Mass, 0.453_592_37),
+    unit(&["oz", "ounce", "ounces"], Mass, 0.028_349_523_125),
+    unit(&["st", "stone", "stones"], Mass, 6.350_293_18),
+    unit(&["ton", "tons", "short ton", "short tons"], Mass, 907.184_74),
+    unit(&["long ton", "long tons"], Mass, 1016.046_908_8),
+    Unit {
+        names: &["K", "kelvin", "kelvins"],
+        category: Temperature,
+        factor: 1.0,
+        offset: 0.0,
+    },
+    Unit {
+        names: &["°C", "C", "celsius", "degC", "centigrade"],
+        category: Temperature,
+        factor: 1.0,
+        offset: 273.15,
+    },
+    Unit {
+        names: &["°F", "F", "fahrenheit", "degF"],
+        category: Temperature,
+        factor: 5.0 / 9.0,
+        offset: 273.15 - 32.0 * 5.0 / 9.0,
+    },
+    Unit {
+        names: &["°R", "R", "rankine"],
+        category: Temperature,
+        factor: 5.0 / 9.0,
+        offset: 0.0,
+    },
+    // Data sizes are case-sensitive: b is a bit and B a byte
+    unit(&["B", "byte", "bytes"], DataSize, 1.0),
+    unit(&["b", "bit", "bits"], DataSize, 0.125),
+    unit(&["kB", "KB", "kilobyte", "kilobytes"], DataSize, 1e3),
+    unit(&["MB", "megabyte", "megabytes"], DataSize, 1e6),
+    unit(&["GB", "gigabyte", "gigabytes"], DataSize, 1e9),
+    unit(&["TB", "terabyte", "terabytes"], DataSize, 1e12),
+    unit(&["PB", "petabyte", "petabytes"], DataSize, 1e15),
+    unit(&["KiB", "kibibyte", "kibibytes"], DataSize, 1024.0),
+    unit(&["MiB", "mebibyte", "mebibytes"], DataSize, 1_048_576.0),
+    unit(&["GiB", "gibibyte", "gibibytes"], DataSize, 1_073_741_824.0),
+    unit(&["TiB", "tebibyte", "tebibytes"], DataSize, 1_099_511_627_776.0),
+    unit(&["PiB", "pebibyte", "pebibytes"], DataSize, 1_125_899_906_842_624.0),
+    unit(&["kb", "kbit", "kilobit", "kilobits"], DataSize, 125.0),
+    unit(&["Mb", "Mbit", "megabit", "megabits"], DataSize, 125e3),
+    unit(&["Gb", "Gbit", "gigabit", "gigabits"], DataSize, 125e6),
+];
+
+/// The unit called `name`: its symbol exactly (case matters for data
+/// sizes), or any of its names ignoring case.
+pub fn find(name: &str) -> Option<&'static Unit> {
+    let name = name.trim();
+    let name = name
+        .strip_prefix("degrees ")
+        .or_else(|| name.strip_prefix("degree "))
+        .unwrap_or(name);
+    UNITS
+        .iter()
+        .find(|unit| unit.names.contains(&name))
+        .or_else(|| {
+            UNITS.iter().find(|unit| {
+                // Symbols like "b" and "B" differ only by case
+                unit.category != DataSize
+                    && unit.names.iter().any(|known| known.eq_ignore_ascii_case(name))
+            })
+        })
+        .or_else(|| {
+            let lower = name.to_lowercase();
+            UNITS.iter().find(|unit| unit.names[1..].contains(&lower.as_str()))
+        })
+}
+
+/// Convert `value` from one unit to another of the same category.
+pub fn convert(value: f64, from: &str, to: &str) -> Result<f64, String> {
+    let unknown = |name: &str| format!("Unknown unit '{}'", name);
+    let from_unit = find(from).ok_or_else(|| unknown(from))?;
+    let to_unit = find(to).ok_or_else(|| unknown(to))?;
+    if from_unit.category != to_unit.category {
+        return Err(format!(
+            "Can't convert {} ({}) to {} ({})",
+            from,
+            from_unit.category.name(),
+            to,
+            to_unit.category.name()
+        ));
+    }
+    let base = value * from_unit.factor + from_unit.offset;
+    if from_unit.category == Temperature && base < -1e-9 {
+        return Err(format!("{} {} is below absolute zero", value, from));
+    }
+    Ok((base - to_unit.offset) / to_unit.factor)
+}
+
+/// `value` rounded to 12 significant digits, which hides the binary
+/// floating point noise in results like 0.1 + 0.2.
+pub fn round(value: f64) -> f64 {
+    format!("{:.11e}", value).parse().unwrap_or(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() <= 1e-9 * b.abs().max(1.0)
+    }
+
+    #[test]
+    fn test_length_and_mass() {
+        assert!(close(convert(5.0, "mi", "km").unwrap(), 8.04672));
+        assert!(close(convert(12.0, "inches", "ft").unwrap(), 1.0));
+        assert!(close(convert(1.0, "Pound", "g").unwrap(), 453.59237));
+        assert!(close(convert(2.0, "st", "lb").unwrap(), 28.0));
+    }
+
+    #[test]
+    fn test_temperature() {
+        assert!(close(convert(100.0, "°C", "°F").unwrap(), 212.0));
+        assert!(close(convert(32.0, "fahrenheit", "celsius").unwrap(), 0.0));
+        assert!(close(convert(0.0, "K", "degrees celsius").unwrap(), -273.15));
+        assert!(close(convert(-40.0, "F", "C").unwrap(), -40.0));
+        assert!(convert(-300.0, "C", "K").unwrap_err().contains("absolute zero"));
+    }
+
+    #[test]
+    fn test_data_size() {
+        assert!(close(convert(1.0, "GiB", "MiB").unwrap(), 1024.0));
+        assert!(close(convert(1.0, "GB", "MB").unwrap(), 1000.0));
+        assert!(close(convert(100.0, "Mb", "MB").unwrap(), 12.5));
+        assert!(close(convert(8.0, "bits", "bytes").unwrap(), 1.0));
+        assert!(close(convert(1.0, "Kilobyte", "B").unwrap(), 1000.0));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(convert(1.0, "m", "kg").unwrap_err().contains("length"));
+        assert_eq!(convert(1.0, "parsec", "m").unwrap_err(), "Unknown unit 'parsec'");
+    }
+
+    #[test]
+    fn test_round() {
+        assert_eq!(round(0.1 + 0.2), 0.3);
+        assert_eq!(round(8.046720000000001), 8.04672);
+    }
+}
@@ -0,0 +1,98 @@
+//! Where CSV files are and how they are split. Paths are relative to the
+//! file sandbox, which the bridge mounts read-only at `/files`; the
+//! delimiter is guessed from the first line unless given.
+
+use std::path::{Component, PathBuf};
+
+/// Where the bridge mounts the file sandbox.
+pub const MOUNT: &str = "/files";
+
+/// The delimiters guessed between, most usual first.
+const CANDIDATES: [u8; 4] = [b',', b';', b'\t', b'|'];
+
+/// `path` inside the sandbox, e.g. `reports/sales.csv`. A leading `/` or
+/// `/files/` is allowed; `..` is not.
+pub fn resolve(path: &str) -> Result<PathBuf, String> {
+    let trimmed = path.trim();
+    let relative = trimmed
+        .strip_prefix(MOUNT)
+        .filter(|rest| rest.is_empty() || rest.starts_with('/'))
+        .unwrap_or(trimmed)
+        .trim_start_matches('/');
+    if relative.is_empty() {
+        return Err("Give the path of a CSV file in the file sandbox".to_string());
+    }
+    let mut resolved = PathBuf::from(MOUNT);
+    for component in PathBuf::from(relative).components() {
+        match component {
+            Component::Normal(part) => resolved.push(part),
+            Component::CurDir => {}
+            _ => return Err(format!("'{}' leaves the file sandbox", path)),
+        }
+    }
+    Ok(resolved)
+}
+
+/// The delimiter a user named: the character itself, or `tab`.
+pub fn named_delimiter(name: &str) -> Result<u8, String> {
+    match name {
+        "tab" | "\\t" | "\t" => Ok(b'\t'),
+        name if name.len() == 1 && name.is_ascii() && name != "\"" => Ok(name.as_bytes()[0]),
+        _ => Err(format!("'{}' can't be a delimiter; give one character, or \"tab\"", name)),
+    }
+}
+
+/// The delimiter of a file whose first line is `line`: tab for `.tsv`
+/// files, else the candidate found most often outside quotes.
+pub fn guess_delimiter(path: &str, line: &str) -> u8 {
+    if path.to_lowercase().ends_with(".tsv") {
+        return b'\t';
+    }
+    let mut counts = [0usize; CANDIDATES.len()];
+    let mut quoted = false;
+    for byte in line.bytes() {
+        if byte == b'"' {
+            quoted = !quoted;
+        } else if !quoted {
+            if let Some(i) = CANDIDATES.iter().position(|&c| c == byte) {
+                counts[i] += 1;
+            }
+        }
+    }
+    // The first of equal counts wins, so a line without any gives a comma
+    let mut best = 0;
+    for (i, &count) in counts.iter().enumerate() {
+        if count > counts[best] {
+            best = i;
+        }
+    }
+    CANDIDATES[best]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve() {
+        let expected = PathBuf::from("/files/data/sales.csv");
+        assert_eq!(resolve("data/sales.csv").unwrap(), expected);
+        assert_eq!(resolve("/data/./sales.csv").unwrap(), expected);
+        assert_eq!(resolve("/files/data/sales.csv").unwrap(), expected);
+        assert_eq!(resolve("filesystem.csv").unwrap(), PathBuf::from("/files/filesystem.csv"));
+        assert!(resolve("../etc/passwd").is_err());
+        assert!(resolve("data/../../x.csv").is_err());
+        assert!(resolve(" / ").is_err());
+    }
+
+    #[test]
+    fn test_delimiters() {
+        assert_eq!(guess_delimiter("a.csv", "name;age;\"a,b,c\""), b';');
+        assert_eq!(guess_delimiter("a.csv", "name\tage"), b'\t');
+        assert_eq!(guess_delimiter("a.csv", "name"), b',');
+        assert_eq!(guess_delimiter("a.TSV", "a,b"), b'\t');
+        assert_eq!(named_delimiter("tab"), Ok(b'\t'));
+        assert_eq!(named_delimiter("|"), Ok(b'|'));
+        assert!(named_delimiter("ab").is_err());
+    }
+}
@@ -0,0 +1,509 @@
+//! CSV MCP Server (WASM component)
+//!
+//! Reads CSV files in Harbor's file sandbox, which the bridge mounts
+//! read-only at `/files`. `csv.preview` shows a file's columns and first
+//! rows, `csv.query` filters, sorts, groups and aggregates its rows, and
+//! `csv.toJson` turns a page of it into JSON records. Files are read a row
+//! at a time, so memory holds only the rows returned (and, while sorting,
+//! the best rows so far), however large the file is.
+//!
+//! Build with:
+//!   cargo build --release --target wasm32-wasip2
+
+mod files;
+mod query;
+mod types;
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, ErrorKind};
+
+use csv::{Reader, ReaderBuilder, StringRecord};
+use harbor_mcp_sdk::{harbor_tool, Error, Server, ToolInput, ToolResult};
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+
+use query::{Accumulator, Aggregate, Filter, Row};
+use types::{Kind, Typed};
+
+wit_bindgen::generate!({ path: "../../wit", world: "mcp-server" });
+
+const DEFAULT_PREVIEW_ROWS: usize = 10;
+const MAX_PREVIEW_ROWS: usize = 100;
+/// Rows looked at to guess the columns' types.
+const TYPE_SAMPLE_ROWS: usize = 1000;
+const DEFAULT_QUERY_LIMIT: usize = 100;
+const MAX_QUERY_LIMIT: usize = 1000;
+const DEFAULT_JSON_LIMIT: usize = 1000;
+const MAX_JSON_LIMIT: usize = 10_000;
+/// Most groups a grouped query keeps.
+const MAX_GROUPS: usize = 10_000;
+/// Most characters of cells one call returns, roughly.
+const MAX_OUTPUT_CHARS: usize = 1_000_000;
+
+impl Row for StringRecord {
+    fn field(&self, index: usize) -> &str {
+        self.get(index).unwrap_or("")
+    }
+}
+
+impl Row for [String] {
+    fn field(&self, index: usize) -> &str {
+        self.get(index).map_or("", String::as_str)
+    }
+}
+
+#[derive(Deserialize, ToolInput)]
+#[serde(deny_unknown_fields)]
+struct PreviewArgs {
+    /// Path of the file in the file sandbox, e.g. "reports/sales.csv"
+    path: String,
+    /// Rows to show (default 10, at most 100)
+    rows: Option<usize>,
+    /// The character between fields, or "tab" (default guessed from the first line)
+    delimiter: Option<String>,
+    /// Whether the first row names the columns (default true)
+    header: Option<bool>,
+}
+
+#[derive(Deserialize, ToolInput)]
+#[serde(deny_unknown_fields)]
+struct QueryArgs {
+    /// Path of the file in the file sandbox, e.g. "reports/sales.csv"
+    path: String,
+    /// Rows to keep, e.g. "amount > 100 and (country = NZ or country = AU)". Compare with
+    /// = != < <= > >=, contains, startswith, endswith or "is empty"; join with and, or, not
+    #[serde(rename = "where")]
+    filter: Option<String>,
+    /// Columns to return (default all)
+    #[serde(default)]
+    select: Vec<String>,
+    /// Columns to group rows by, giving one row per group
+    #[serde(default)]
+    group_by: Vec<String>,
+    /// Aggregates per group, or over all rows kept: "count", "sum(col)", "avg(col)",
+    /// "min(col)" or "max(col)" (default count when grouping)
+    #[serde(default)]
+    aggregate: Vec<String>,
+    /// Columns to sort by, e.g. "amount desc, name"
+    sort: Option<String>,
+    /// Most rows to return (default 100, at most 1000)
+    limit: Option<usize>,
+    /// The character between fields, or "tab" (default guessed from the first line)
+    delimiter: Option<String>,
+    /// Whether the first row names the columns (default true)
+    header: Option<bool>,
+}
+
+#[derive(Deserialize, ToolInput)]
+#[serde(deny_unknown_fields)]
+struct JsonArgs {
+    /// Path of the file in the file sandbox, e.g. "reports/sales.csv"
+    path: String,
+    /// Columns to include (default all)
+    #[serde(default)]
+    columns: Vec<String>,
+    /// Rows to skip first (default 0)
+    offset: Option<usize>,
+    /// Most records to return (default 1000, at most 10000)
+    limit: Option<usize>,
+    /// Whether numbers, true/false and empty cells become JSON numbers, booleans and null,
+    /// rather than strings (default true)
+    typed: Option<bool>,
+    /// The character between fields, or "tab" (default guessed from the first line)
+    delimiter: Option<String>,
+    /// Whether the first row names the columns (default true)
+    header: Option<bool>,
+}
+
+fn read_error(e: csv::Error) -> String {
+    format!("The file couldn't be read: {}", e)
+}
+
+/// Column names from a header row, numbering any that are blank.
+fn names(header: &StringRecord) -> Vec<String> {
+    header
+        .iter()
+        .enumerate()
+        .map(|(i, name)| match name.trim_start_matches('\u{feff}').trim() {
+            "" => format!("column_{}", i + 1),
+            name => name.to_string(),
+        })
+        .collect()
+}
+
+/// An open CSV file, read a row at a time.
+struct Table {
+    headers: Vec<String>,
+    delimiter: u8,
+    reader: Reader<BufReader<File>>,
+    /// The first row, when it isn't a header row and hasn't been read yet
+    first: Option<StringRecord>,
+    record: StringRecord,
+}
+
+impl Table {
+    fn open(path: &str, delimiter: Option<&str>, header: Option<bool>) -> Result<Table, String> {
+        let resolved = files::resolve(path)?;
+        let file = File::open(&resolved).map_err(|e| match e.kind() {
+            ErrorKind::NotFound => format!("There is no file '{}' in the file sandbox", path),
+            _ => format!("'{}' couldn't be opened: {}", path, e),
+        })?;
+        let mut buffered = BufReader::new(file);
+        let delimiter = match delimiter {
+            Some(name) => files::named_delimiter(name)?,
+            None => {
+                let start = buffered
+                    .fill_buf()
+                    .map_err(|e| format!("'{}' couldn't be read: {}", path, e))?;
+                let line = start.split(|&b| b == b'\n').next().unwrap_or_default();
+                files::guess_delimiter(path, &String::from_utf8_lossy(line))
+            }
+        };
+        let mut reader = ReaderBuilder::new()
+            .delimiter(delimiter)
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(buffered);
+        let mut first = StringRecord::new();
+        if !reader.read_record(&mut first).map_err(read_error)? {
+            return Err(format!("'{}' is empty", path));
+        }
+        let (headers, first) = if header.unwrap_or(true) {
+            (names(&first), None)
+        } else {
+            let numbered = (1..=first.len()).map(|i| format!("column_{}", i)).collect();
+            (numbered, Some(first))
+        };
+        Ok(Table { headers, delimiter, reader, first, record: StringRecord::new() })
+    }
+
+    /// The next row, or `None` at the end of the file.
+    fn read(&mut self) -> Result<Option<&StringRecord>, String> {
+        if let Some(first) = self.first.take() {
+            self.record = first;
+            return Ok(Some(&self.record));
+        }
+        match self.reader.read_record(&mut self.record) {
+            Ok(true) => Ok(Some(&self.record)),
+            Ok(false) => Ok(None),
+            Err(e) => Err(read_error(e)),
+        }
+    }
+
+    /// The indexes of columns `names`, or of every column if there are none.
+    fn columns(&self, names: &[String]) -> Result<Vec<usize>, String> {
+        if names.is_empty() {
+            return Ok((0..self.headers.len()).collect());
+        }
+        names.iter().map(|name| query::column(name, &self.headers)).collect()
+    }
+
+    fn names(&self, columns: &[usize]) -> Vec<String> {
+        columns.iter().map(|&i| self.headers[i].clone()).collect()
+    }
+}
+
+/// The cells of `row` in `columns`, with how many characters they hold.
+fn cells<R: Row + ?Sized>(row: &R, columns: &[usize]) -> (Vec<String>, usize) {
+    let cells: Vec<String> = columns.iter().map(|&i| row.field(i).to_string()).collect();
+    let size = cells.iter().map(String::len).sum();
+    (cells, size)
+}
+
+fn number_value(n: f64) -> Value {
+    if n.fract() == 0.0 && n.abs() < 1e15 {
+        json!(n as i64)
+    } else {
+        json!(n)
+    }
+}
+
+fn typed_value(field: &str) -> Value {
+    match types::typed(field) {
+        Typed::Null => Value::Null,
+        Typed::Boolean(b) => json!(b),
+        Typed::Integer(n) => json!(n),
+        Typed::Number(n) => json!(n),
+        Typed::Text(text) => json!(text),
+    }
+}
+
+fn preview_table(args: PreviewArgs) -> Result<Value, String> {
+    let mut table = Table::open(&args.path, args.delimiter.as_deref(), args.header)?;
+    let shown = args.rows.unwrap_or(DEFAULT_PREVIEW_ROWS).min(MAX_PREVIEW_ROWS);
+    let all = table.columns(&[])?;
+    let mut kinds = vec![Kind::Empty; all.len()];
+    let mut rows = Vec::new();
+    let mut total = 0usize;
+    while let Some(record) = table.read()? {
+        if total < TYPE_SAMPLE_ROWS {
+            for (i, kind) in kinds.iter_mut().enumerate() {
+                *kind = kind.merge(types::kind(record.field(i)));
+            }
+        }
+        if rows.len() < shown {
+            rows.push(cells(record, &all).0);
+        }
+        total += 1;
+    }
+    let columns: Vec<Value> = table
+        .headers
+        .iter()
+        .zip(kinds)
+        .map(|(name, kind)| json!({ "name": name, "type": kind.name() }))
+        .collect();
+    Ok(json!({
+        "columns": columns,
+        "delimiter": (table.delimiter as char).to_string(),
+        "rows": rows,
+        "total_rows": total,
+    }))
+}
+
+/// Rows matching the filter, sorted, keeping only the best `limit` while
+/// reading so a sort never holds more than twice that many.
+fn select_rows(
+    table: &mut Table,
+    filter: Option<&Filter>,
+    args: &QueryArgs,
+) -> Result<Value, String> {
+    let columns = table.columns(&args.select)?;
+    let keys = args.sort.as_deref().map(|sort| query::sort_keys(sort, &table.headers)).transpose()?;
+    let limit = args.limit.unwrap_or(DEFAULT_QUERY_LIMIT).clamp(1, MAX_QUERY_LIMIT);
+    let mut kept: Vec<StringRecord> = Vec::new();
+    let mut matched = 0usize;
+    while let Some(record) = table.read()? {
+        if !keeps(filter, record) {
+            continue;
+        }
+        matched += 1;
+        match &keys {
+            Some(keys) => {
+                kept.push(record.clone());
+                if kept.len() >= 2 * limit {
+                    kept.sort_by(|a, b| query::order(a, b, keys));
+                    kept.truncate(limit);
+                }
+            }
+            None if kept.len() < limit => kept.push(record.clone()),
+            None => {}
+        }
+    }
+    if let Some(keys) = &keys {
+        kept.sort_by(|a, b| query::order(a, b, keys));
+        kept.truncate(limit);
+    }
+    let mut rows = Vec::with_capacity(kept.len());
+    let mut size = 0;
+    for record in &kept {
+        let (row, chars) = cells(record, &columns);
+        size += chars;
+        if size > MAX_OUTPUT_CHARS {
+            break;
+        }
+        rows.push(row);
+    }
+    Ok(json!({
+        "columns": table.names(&columns),
+        "truncated": matched > rows.len(),
+        "matched": matched,
+        "rows": rows,
+    }))
+}
+
+/// One row per group of rows matching the filter, with the group's
+/// columns and then its aggregates.
+fn group_rows(
+    table: &mut Table,
+    filter: Option<&Filter>,
+    args: &QueryArgs,
+) -> Result<Value, String> {
+    if !args.select.is_empty() {
+        let message = "select can't be used with group_by or aggregate; the result has the \
+                       grouped columns and then the aggregates";
+        return Err(message.to_string());
+    }
+    let group_columns = args
+        .group_by
+        .iter()
+        .map(|name| query::column(name, &table.headers))
+        .collect::<Result<Vec<_>, _>>()?;
+    let count = ["count".to_string()];
+    let aggregates = if args.aggregate.is_empty() { &count[..] } else { &args.aggregate[..] };
+    let aggregates: Vec<Aggregate> = aggregates
+        .iter()
+        .map(|text| Aggregate::parse(text, &table.headers))
+        .collect::<Result<_, _>>()?;
+    let mut output = table.names(&group_columns);
+    output.extend(aggregates.iter().map(|aggregate| aggregate.label.clone()));
+    let keys = args.sort.as_deref().map(|sort| query::sort_keys(sort, &output)).transpose()?;
+    let limit = args.limit.unwrap_or(DEFAULT_QUERY_LIMIT).clamp(1, MAX_QUERY_LIMIT);
+
+    let mut index: HashMap<Vec<String>, usize> = HashMap::new();
+    let mut groups: Vec<(Vec<String>, Vec<Accumulator>)> = Vec::new();
+    let mut matched = 0usize;
+    while let Some(record) = table.read()? {
+        if !keeps(filter, record) {
+            continue;
+        }
+        matched += 1;
+        let key = cells(record, &group_columns).0;
+        let position = match index.get(&key) {
+            Some(&position) => position,
+            None if groups.len() >= MAX_GROUPS => {
+                let message = format!("There are more than {} groups", MAX_GROUPS);
+                return Err(message + "; group by fewer columns, or filter first");
+            }
+            None => {
+                index.insert(key.clone(), groups.len());
+                groups.push((key, vec![Accumulator::default(); aggregates.len()]));
+                groups.len() - 1
+            }
+        };
+        let accumulators = &mut groups[position].1;
+        for (aggregate, accumulator) in aggregates.iter().zip(accumulators.iter_mut()) {
+            aggregate.add(accumulator, record);
+        }
+    }
+    // Aggregates over no rows at all still give one row, of zero counts
+    if groups.is_empty() && group_columns.is_empty() {
+        groups.push((Vec::new(), vec![Accumulator::default(); aggregates.len()]));
+    }
+
+    // Each row as text to sort by, and as it is returned
+    let mut rows: Vec<(Vec<String>, Vec<Value>)> = groups
+        .into_iter()
+        .map(|(key, accumulators)| {
+            let mut sortable = key.clone();
+            let mut row: Vec<Value> = key.into_iter().map(Value::String).collect();
+            for (aggregate, accumulator) in aggregates.iter().zip(&accumulators) {
+                let (cell, value) = match aggregate.result(accumulator) {
+                    query::Value::Number(n) => (n.to_string(), number_value(n)),
+                    query::Value::Text(text) => (text.clone(), Value::String(text)),
+                    query::Value::Null => (String::new(), Value::Null),
+                };
+                sortable.push(cell);
+                row.push(value);
+            }
+            (sortable, row)
+        })
+        .collect();
+    let total = rows.len();
+    if let Some(keys) = &keys {
+        rows.sort_by(|a, b| query::order(&a.0[..], &b.0[..], keys));
+    }
+    let rows: Vec<Vec<Value>> = rows.into_iter().take(limit).map(|(_, row)| row).collect();
+    Ok(json!({
+        "columns": output,
+        "truncated": total > rows.len(),
+        "groups": total,
+        "matched": matched,
+        "rows": rows,
+    }))
+}
+
+/// Whether there's no filter, or `row` matches it.
+fn keeps(filter: Option<&Filter>, row: &StringRecord) -> bool {
+    match filter {
+        Some(filter) => filter.matches(row),
+        None => true,
+    }
+}
+
+fn query_table(args: QueryArgs) -> Result<Value, String> {
+    let mut table = Table::open(&args.path, args.delimiter.as_deref(), args.header)?;
+    let filter = args.filter.as_deref();
+    let filter = filter.map(|text| Filter::parse(text, &table.headers)).transpose()?;
+    if args.group_by.is_empty() && args.aggregate.is_empty() {
+        select_rows(&mut table, filter.as_ref(), &args)
+    } else {
+        group_rows(&mut table, filter.as_ref(), &args)
+    }
+}
+
+fn table_to_json(args: JsonArgs) -> Result<Value, String> {
+    let mut table = Table::open(&args.path, args.delimiter.as_deref(), args.header)?;
+    let columns = table.columns(&args.columns)?;
+    let names = table.names(&columns);
+    let offset = args.offset.unwrap_or(0);
+    let limit = args.limit.unwrap_or(DEFAULT_JSON_LIMIT).clamp(1, MAX_JSON_LIMIT);
+    let typed = args.typed.unwrap_or(true);
+    let mut records = Vec::new();
+    let mut size = 0;
+    let mut skipped = 0;
+    let mut more = false;
+    while let Some(record) = table.read()? {
+        if skipped < offset {
+            skipped += 1;
+            continue;
+        }
+        let (row, chars) = cells(record, &columns);
+        size += chars;
+        if records.len() >= limit || size > MAX_OUTPUT_CHARS {
+            more = true;
+            break;
+        }
+        let fields: Map<String, Value> = names
+            .iter()
+            .zip(row)
+            .map(|(name, cell)| {
+                let value = if typed { typed_value(&cell) } else { Value::String(cell) };
+                (name.clone(), value)
+            })
+            .collect();
+        records.push(Value::Object(fields));
+    }
+    let mut result = json!({ "records": records, "offset": offset });
+    if more {
+        result["next_offset"] = json!(offset + records.len());
+    }
+    Ok(result)
+}
+
+fn reply(result: Result<Value, String>) -> Result<ToolResult, Error> {
+    Ok(match result {
+        Ok(value) => ToolResult::json(&value),
+        Err(e) => ToolResult::error(e),
+    })
+}
+
+/// Show a CSV file's columns, with the type of each (guessed from the
+/// first 1000 rows), its first rows and how many rows it has. Use this
+/// first, to learn the columns csv.query and csv.toJson can use.
+#[harbor_tool(name = "csv.preview")]
+fn preview(args: PreviewArgs) -> Result<ToolResult, Error> {
+    reply(preview_table(args))
+}
+
+/// Query a CSV file: keep rows matching a filter, pick columns, sort, and
+/// group with counts, sums, averages, minimums and maximums. Large files
+/// are fine; they are read a row at a time.
+#[harbor_tool(name = "csv.query")]
+fn query_rows(args: QueryArgs) -> Result<ToolResult, Error> {
+    reply(query_table(args))
+}
+
+/// Turn rows of a CSV file into JSON records keyed by column name, a page
+/// at a time: pass the returned next_offset to get the next page.
+#[harbor_tool(name = "csv.toJson")]
+fn to_json(args: JsonArgs) -> Result<ToolResult, Error> {
+    reply(table_to_json(args))
+}
+
+fn server() -> Server {
+    Server::new("mcp-csv", "1.0.0")
+        .register(preview_tool())
+        .register(query_rows_tool())
+        .register(to_json_tool())
+}
+
+struct Component;
+
+impl exports::harbor::mcp::server::Guest for Component {
+    fn handle(request: String) -> String {
+        server().handle(&request)
+    }
+}
+
+export!(Component);
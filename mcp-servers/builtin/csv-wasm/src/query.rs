@@ -0,0 +1,541 @@
+//! The small language of `csv.query`: filters such as
+//! `age >= 30 and (country = "NZ" or country = AU)`, sort keys such as
+//! `amount desc, name` and aggregates such as `sum(amount)`. Values
+//! compare as numbers when both sides are numbers, and otherwise as text,
+//! ignoring case.
+
+use std::cmp::Ordering;
+
+/// Most columns named in an error listing them.
+const MAX_LISTED_COLUMNS: usize = 20;
+/// Deepest nesting of parentheses and `not` in a filter.
+const MAX_DEPTH: usize = 32;
+
+/// A row's fields by index; missing fields are empty.
+pub trait Row {
+    fn field(&self, index: usize) -> &str;
+}
+
+impl Row for [&str] {
+    fn field(&self, index: usize) -> &str {
+        self.get(index).copied().unwrap_or("")
+    }
+}
+
+/// The index of column `name`: an exact match, else one ignoring case
+/// and surrounding spaces.
+pub fn column(name: &str, headers: &[String]) -> Result<usize, String> {
+    let name = name.trim();
+    headers
+        .iter()
+        .position(|header| header == name)
+        .or_else(|| headers.iter().position(|header| header.trim().eq_ignore_ascii_case(name)))
+        .ok_or_else(|| {
+            let mut listed: Vec<_> =
+                headers.iter().take(MAX_LISTED_COLUMNS).map(String::as_str).collect();
+            if headers.len() > MAX_LISTED_COLUMNS {
+                listed.push("...");
+            }
+            format!("Unknown column '{}'; the columns are {}", name, listed.join(", "))
+        })
+}
+
+/// `text` as a finite number, if it is one.
+pub fn number(text: &str) -> Option<f64> {
+    let text = text.trim();
+    if text.is_empty() {
+        return None;
+    }
+    text.parse::<f64>().ok().filter(|n| n.is_finite())
+}
+
+/// Numbers in order when both are numbers, else text ignoring case.
+pub fn compare(a: &str, b: &str) -> Ordering {
+    match (number(a), number(b)) {
+        (Some(x), Some(y)) => x.partial_cmp(&y).unwrap_or(Ordering::Equal),
+        _ => a.trim().to_lowercase().cmp(&b.trim().to_lowercase()),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Contains,
+    StartsWith,
+    EndsWith,
+    Empty,
+    NotEmpty,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Filter {
+    Compare { column: usize, op: Op, value: String },
+    And(Box<Filter>, Box<Filter>),
+    Or(Box<Filter>, Box<Filter>),
+    Not(Box<Filter>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    /// A bare word: a column, a value or a keyword
+    Word(String),
+    /// A quoted value
+    Quoted(String),
+    /// A column name in backticks
+    Column(String),
+    Op(Op),
+    Open,
+    Close,
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' | ')' => {
+                chars.next();
+                tokens.push(if c == '(' { Token::Open } else { Token::Close });
+            }
+            '"' | '\'' | '`' => {
+                chars.next();
+                let mut quoted = String::new();
+                loop {
+                    match chars.next() {
+                        Some(q) if q == c => break,
+                        // A backslash escapes the quote
+                        Some('\\') if chars.peek() == Some(&c) => quoted.extend(chars.next()),
+                        Some(other) => quoted.push(other),
+                        None => return Err(format!("Unclosed {} in the filter", c)),
+                    }
+                }
+                tokens.push(if c == '`' { Token::Column(quoted) } else { Token::Quoted(quoted) });
+            }
+            '=' | '!' | '<' | '>' => {
+                chars.next();
+                let next = chars.peek().copied();
+                let (op, pair) = match (c, next) {
+                    ('=', Some('=')) => (Op::Eq, true),
+                    ('=', _) => (Op::Eq, false),
+                    ('!', Some('=')) => (Op::Ne, true),
+                    ('<', Some('>')) => (Op::Ne, true),
+                    ('<', Some('=')) => (Op::Le, true),
+                    ('<', _) => (Op::Lt, false),
+                    ('>', Some('=')) => (Op::Ge, true),
+                    ('>', _) => (Op::Gt, false),
+                    _ => return Err("Unexpected '!' in the filter; use != or not".to_string()),
+                };
+                if pair {
+                    chars.next();
+                }
+                tokens.push(Token::Op(op));
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || "()\"'`=!<>".contains(c) {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                tokens.push(Token::Word(word));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    position: usize,
+    headers: &'a [String],
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    /// Consume the keyword `word` if it comes next.
+    fn keyword(&mut self, word: &str) -> bool {
+        let found = matches!(self.peek(), Some(Token::Word(w)) if w.eq_ignore_ascii_case(word));
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    fn or(&mut self) -> Result<Filter, String> {
+        let mut filter = self.and()?;
+        while self.keyword("or") {
+            filter = Filter::Or(Box::new(filter), Box::new(self.and()?));
+        }
+        Ok(filter)
+    }
+
+    fn and(&mut self) -> Result<Filter, String> {
+        let mut filter = self.unary()?;
+        while self.keyword("and") {
+            filter = Filter::And(Box::new(filter), Box::new(self.unary()?));
+        }
+        Ok(filter)
+    }
+
+    fn unary(&mut self) -> Result<Filter, String> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err("The filter is nested too deeply".to_string());
+        }
+        let filter = if self.keyword("not") {
+            Filter::Not(Box::new(self.unary()?))
+        } else if self.peek() == Some(&Token::Open) {
+            self.position += 1;
+            let inner = self.or()?;
+            if self.next() != Some(Token::Close) {
+                return Err("Missing ')' in the filter".to_string());
+            }
+            inner
+        } else {
+            self.comparison()?
+        };
+        self.depth -= 1;
+        Ok(filter)
+    }
+
+    fn comparison(&mut self) -> Result<Filter, String> {
+        let name = match self.next() {
+            Some(Token::Word(name)) | Some(Token::Column(name)) => name,
+            Some(_) | None => return Err("Expected a column name in the filter".to_string()),
+        };
+        let column = column(&name, self.headers)?;
+        let op = match self.next() {
+            Some(Token::Op(op)) => op,
+            Some(Token::Word(word)) => match word.to_lowercase().as_str() {
+                "contains" => Op::Contains,
+                "startswith" | "starts_with" => Op::StartsWith,
+                "endswith" | "ends_with" => Op::EndsWith,
+                "is" => {
+                    let negated = self.keyword("not");
+                    if !self.keyword("empty") {
+                        return Err(format!("Expected 'empty' after '{} is'", name));
+                    }
+                    let op = if negated { Op::NotEmpty } else { Op::Empty };
+                    return Ok(Filter::Compare { column, op, value: String::new() });
+                }
+                _ => return Err(format!("Unknown operator '{}' in the filter", word)),
+            },
+            _ => return Err(format!("Expected an operator after '{}'", name)),
+        };
+        let value = match self.next() {
+            Some(Token::Word(value)) | Some(Token::Quoted(value)) => value,
+            _ => return Err(format!("Expected a value to compare '{}' with", name)),
+        };
+        Ok(Filter::Compare { column, op, value })
+    }
+}
+
+impl Filter {
+    /// Parse a filter over columns named `headers`.
+    pub fn parse(text: &str, headers: &[String]) -> Result<Filter, String> {
+        let mut parser = Parser { tokens: tokenize(text)?, position: 0, headers, depth: 0 };
+        let filter = parser.or()?;
+        if parser.position < parser.tokens.len() {
+            let message = "Unexpected text at the end of the filter; join conditions with and/or";
+            return Err(message.to_string());
+        }
+        Ok(filter)
+    }
+
+    pub fn matches<R: Row + ?Sized>(&self, row: &R) -> bool {
+        match self {
+            Filter::Compare { column, op, value } => {
+                let field = row.field(*column);
+                let lower = || (field.to_lowercase(), value.to_lowercase());
+                match op {
+                    Op::Eq => compare(field, value) == Ordering::Equal,
+                    Op::Ne => compare(field, value) != Ordering::Equal,
+                    Op::Lt => compare(field, value) == Ordering::Less,
+                    Op::Le => compare(field, value) != Ordering::Greater,
+                    Op::Gt => compare(field, value) == Ordering::Greater,
+                    Op::Ge => compare(field, value) != Ordering::Less,
+                    Op::Contains => {
+                        let (field, value) = lower();
+                        field.contains(&value)
+                    }
+                    Op::StartsWith => {
+                        let (field, value) = lower();
+                        field.starts_with(&value)
+                    }
+                    Op::EndsWith => {
+                        let (field, value) = lower();
+                        field.ends_with(&value)
+                    }
+                    Op::Empty => field.trim().is_empty(),
+                    Op::NotEmpty => !field.trim().is_empty(),
+                }
+            }
+            Filter::And(a, b) => a.matches(row) && b.matches(row),
+            Filter::Or(a, b) => a.matches(row) || b.matches(row),
+            Filter::Not(filter) => !filter.matches(row),
+        }
+    }
+}
+
+/// A column to sort by, and which way.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SortKey {
+    pub column: usize,
+    pub descending: bool,
+}
+
+/// Parse sort keys such as `amount desc, name`, over columns `headers`.
+pub fn sort_keys(text: &str, headers: &[String]) -> Result<Vec<SortKey>, String> {
+    text.split(',')
+        .map(|key| {
+            let key = key.trim();
+            let (name, descending) = match key.rsplit_once(char::is_whitespace) {
+                Some((name, way)) if way.eq_ignore_ascii_case("desc") => (name, true),
+                Some((name, way)) if way.eq_ignore_ascii_case("asc") => (name, false),
+                _ => (key, false),
+            };
+            let name = name.trim().trim_matches('`');
+            Ok(SortKey { column: column(name, headers)?, descending })
+        })
+        .collect()
+}
+
+/// The order of two rows by `keys`.
+pub fn order<R: Row + ?Sized>(a: &R, b: &R, keys: &[SortKey]) -> Ordering {
+    for key in keys {
+        let ordering = compare(a.field(key.column), b.field(key.column));
+        let ordering = if key.descending { ordering.reverse() } else { ordering };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    Ordering::Equal
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Function {
+    Count,
+    Sum,
+    Avg,
+    Min,
+    Max,
+}
+
+/// An aggregate such as `count`, `sum(amount)` or `max(date)`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Aggregate {
+    function: Function,
+    /// `None` for `count` of rows
+    column: Option<usize>,
+    pub label: String,
+}
+
+/// What an aggregate has seen so far.
+#[derive(Debug, Clone, Default)]
+pub struct Accumulator {
+    count: u64,
+    sum: f64,
+    min: Option<String>,
+    max: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Number(f64),
+    Text(String),
+    Null,
+}
+
+impl Aggregate {
+    pub fn parse(text: &str, headers: &[String]) -> Result<Aggregate, String> {
+        let text = text.trim();
+        let (name, argument) = match text.split_once('(') {
+            Some((name, rest)) => {
+                let argument = rest
+                    .strip_suffix(')')
+                    .ok_or_else(|| format!("Missing ')' in '{}'", text))?;
+                (name.trim(), Some(argument.trim().trim_matches('`')))
+            }
+            None => (text, None),
+        };
+        let function = match name.to_lowercase().as_str() {
+            "count" => Function::Count,
+            "sum" => Function::Sum,
+            "avg" | "mean" | "average" => Function::Avg,
+            "min" => Function::Min,
+            "max" => Function::Max,
+            _ => {
+                let message = "use count, sum, avg, min or max";
+                return Err(format!("Unknown aggregate '{}'; {}", name, message));
+            }
+        };
+        let column = match argument {
+            None | Some("*") if function == Function::Count => None,
+            Some(name) if !name.is_empty() && name != "*" => Some(column(name, headers)?),
+            _ => return Err(format!("'{}' needs a column, e.g. {}(amount)", name, name)),
+        };
+        Ok(Aggregate { function, column, label: text.to_string() })
+    }
+
+    pub fn add<R: Row + ?Sized>(&self, accumulator: &mut Accumulator, row: &R) {
+        let field = match self.column {
+            Some(column) => row.field(column),
+            None => {
+                accumulator.count += 1;
+                return;
+            }
+        };
+        if field.trim().is_empty() {
+            return;
+        }
+        match self.function {
+            Function::Count => accumulator.count += 1,
+            Function::Sum | Function::Avg => {
+                if let Some(n) = number(field) {
+                    accumulator.count += 1;
+                    accumulator.sum += n;
+                }
+            }
+            Function::Min => {
+                if replaces(&accumulator.min, field, Ordering::Greater) {
+                    accumulator.min = Some(field.to_string());
+                }
+            }
+            Function::Max => {
+                if replaces(&accumulator.max, field, Ordering::Less) {
+                    accumulator.max = Some(field.to_string());
+                }
+            }
+        }
+    }
+
+    pub fn result(&self, accumulator: &Accumulator) -> Value {
+        let extreme = |value: &Option<String>| match value {
+            Some(text) => number(text).map_or_else(|| Value::Text(text.clone()), Value::Number),
+            None => Value::Null,
+        };
+        match self.function {
+            Function::Count => Value::Number(accumulator.count as f64),
+            Function::Sum => Value::Number(accumulator.sum),
+            Function::Avg if accumulator.count == 0 => Value::Null,
+            Function::Avg => Value::Number(accumulator.sum / accumulator.count as f64),
+            Function::Min => extreme(&accumulator.min),
+            Function::Max => extreme(&accumulator.max),
+        }
+    }
+}
+
+/// Whether `field` replaces the extreme `current`, which compares to it
+/// as `beaten` when it should.
+fn replaces(current: &Option<String>, field: &str, beaten: Ordering) -> bool {
+    match current {
+        Some(current) => compare(current, field) == beaten,
+        None => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers() -> Vec<String> {
+        ["name", "age", "Country", "joined"].iter().map(|h| h.to_string()).collect()
+    }
+
+    fn matches(filter: &str, row: &[&str]) -> bool {
+        Filter::parse(filter, &headers()).unwrap().matches(row)
+    }
+
+    #[test]
+    fn test_compare() {
+        assert_eq!(compare("9", "10"), Ordering::Less);
+        assert_eq!(compare(" 2.50", "2.5"), Ordering::Equal);
+        assert_eq!(compare("apple", "Banana"), Ordering::Less);
+        assert_eq!(compare("2024-02-01", "2024-10-01"), Ordering::Less);
+    }
+
+    #[test]
+    fn test_filter() {
+        let ana = ["Ana", "34", "NZ", "2021-03-01"];
+        assert!(matches("age >= 30 and country = nz", &ana));
+        assert!(matches("age > 40 or (Country = \"NZ\" and not name = Bob)", &ana));
+        assert!(matches("`name` startswith an and joined < 2022-01-01", &ana));
+        assert!(matches("name contains 'N' and age != 35 and age <> 33", &ana));
+        assert!(!matches("age < 34", &ana));
+        assert!(matches("country is not empty", &ana));
+        assert!(matches("joined is empty", &["Bob", "20", "AU", ""]));
+        assert!(matches("joined is empty", &["Bob"]));
+    }
+
+    #[test]
+    fn test_filter_errors() {
+        let parse = |text: &str| Filter::parse(text, &headers()).unwrap_err();
+        assert!(parse("height > 2").contains("Unknown column 'height'"));
+        assert!(parse("age >").contains("Expected a value"));
+        assert!(parse("age 30").contains("Unknown operator '30'"));
+        assert!(parse("(age > 3").contains("Missing ')'"));
+        assert!(parse("age > 3 name = x").contains("join conditions"));
+        assert!(parse("name = \"x").contains("Unclosed"));
+        assert!(parse(&"(".repeat(100)).contains("nested too deeply"));
+    }
+
+    #[test]
+    fn test_sort() {
+        let keys = sort_keys("country ASC, age  desc", &headers()).unwrap();
+        let mut rows = vec![["a", "9", "NZ"], ["b", "30", "AU"], ["c", "10", "NZ"]];
+        rows.sort_by(|a, b| order(&a[..], &b[..], &keys));
+        let names: Vec<_> = rows.iter().map(|row| row[0]).collect();
+        assert_eq!(names, vec!["b", "c", "a"]);
+        assert!(sort_keys("age up", &headers()).is_err());
+    }
+
+    #[test]
+    fn test_aggregates() {
+        let rows: [&[&str]; 4] = [&["a", "30"], &["b", "x"], &["c", ""], &["d", "12.5"]];
+        let texts = ["count", "count(age)", "sum(age)", "avg(age)", "min(age)", "max(name)"];
+        let results: Vec<Value> = texts
+            .iter()
+            .map(|text| {
+                let aggregate = Aggregate::parse(text, &headers()).unwrap();
+                let mut accumulator = Accumulator::default();
+                for row in rows {
+                    aggregate.add(&mut accumulator, row);
+                }
+                aggregate.result(&accumulator)
+            })
+            .collect();
+        assert_eq!(
+            results,
+            vec![
+                Value::Number(4.0),
+                Value::Number(3.0),
+                Value::Number(42.5),
+                Value::Number(21.25),
+                Value::Number(12.5),
+                Value::Text("d".to_string()),
+            ]
+        );
+        assert!(Aggregate::parse("median(age)", &headers()).is_err());
+        assert!(Aggregate::parse("sum", &headers()).unwrap_err().contains("needs a column"));
+    }
+}
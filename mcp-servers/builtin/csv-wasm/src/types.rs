@@ -0,0 +1,127 @@
+//! What the values of a column look like. A cell is empty, a boolean, an
+//! integer, a number, a date or text; a column's type is the one its
+//! non-empty cells share, with integers and numbers making a number and
+//! anything else mixed making text.
+
+/// The type of a cell, or of a column.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    Empty,
+    Boolean,
+    Integer,
+    Number,
+    Date,
+    Text,
+}
+
+impl Kind {
+    pub fn name(self) -> &'static str {
+        match self {
+            Kind::Empty => "empty",
+            Kind::Boolean => "boolean",
+            Kind::Integer => "integer",
+            Kind::Number => "number",
+            Kind::Date => "date",
+            Kind::Text => "text",
+        }
+    }
+
+    /// The type of a column of `self` that also holds `other`.
+    pub fn merge(self, other: Kind) -> Kind {
+        match (self, other) {
+            (a, b) if a == b => a,
+            (Kind::Empty, kind) | (kind, Kind::Empty) => kind,
+            (Kind::Integer, Kind::Number) | (Kind::Number, Kind::Integer) => Kind::Number,
+            _ => Kind::Text,
+        }
+    }
+}
+
+/// A cell as a JSON value would hold it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Typed<'a> {
+    Null,
+    Boolean(bool),
+    Integer(i64),
+    Number(f64),
+    Text(&'a str),
+}
+
+/// Whether `text` starts like an ISO 8601 date, `YYYY-MM-DD`.
+fn is_date(text: &str) -> bool {
+    let bytes = text.as_bytes();
+    bytes.len() >= 10
+        && bytes[..10].iter().enumerate().all(|(i, &b)| match i {
+            4 | 7 => b == b'-',
+            _ => b.is_ascii_digit(),
+        })
+        && (bytes.len() == 10 || matches!(bytes[10], b'T' | b' '))
+}
+
+/// `field` typed, keeping numbers with leading zeros (such as postcodes
+/// and ids) as text.
+pub fn typed(field: &str) -> Typed<'_> {
+    let text = field.trim();
+    if text.is_empty() {
+        return Typed::Null;
+    }
+    match text.to_ascii_lowercase().as_str() {
+        "true" => return Typed::Boolean(true),
+        "false" => return Typed::Boolean(false),
+        _ => {}
+    }
+    let digits = text.trim_start_matches(['-', '+']);
+    let padded = digits.len() > 1 && digits.starts_with('0') && !digits.starts_with("0.");
+    let numeric = digits.starts_with(|c: char| c.is_ascii_digit() || c == '.');
+    if padded || !numeric {
+        return Typed::Text(field);
+    }
+    if let Ok(n) = text.parse::<i64>() {
+        return Typed::Integer(n);
+    }
+    match text.parse::<f64>() {
+        Ok(n) if n.is_finite() => Typed::Number(n),
+        _ => Typed::Text(field),
+    }
+}
+
+/// The type of one cell.
+pub fn kind(field: &str) -> Kind {
+    match typed(field) {
+        Typed::Null => Kind::Empty,
+        Typed::Boolean(_) => Kind::Boolean,
+        Typed::Integer(_) => Kind::Integer,
+        Typed::Number(_) => Kind::Number,
+        Typed::Text(text) if is_date(text.trim()) => Kind::Date,
+        Typed::Text(_) => Kind::Text,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_typed() {
+        assert_eq!(typed(" 42 "), Typed::Integer(42));
+        assert_eq!(typed("-3.5"), Typed::Number(-3.5));
+        assert_eq!(typed("0.5"), Typed::Number(0.5));
+        assert_eq!(typed("TRUE"), Typed::Boolean(true));
+        assert_eq!(typed(""), Typed::Null);
+        assert_eq!(typed("00123"), Typed::Text("00123"));
+        assert_eq!(typed("inf"), Typed::Text("inf"));
+        assert_eq!(typed("1e400"), Typed::Text("1e400"));
+    }
+
+    #[test]
+    fn test_kinds() {
+        assert_eq!(kind("2024-01-15"), Kind::Date);
+        assert_eq!(kind("2024-01-15T10:30:00Z"), Kind::Date);
+        assert_eq!(kind("2024-01-150"), Kind::Text);
+        let fields = ["1", "", "2.5"];
+        let column = fields.iter().fold(Kind::Empty, |column, field| column.merge(kind(field)));
+        assert_eq!(column, Kind::Number);
+        assert_eq!(Kind::Integer.merge(Kind::Date), Kind::Text);
+        assert_eq!(Kind::Empty.merge(Kind::Boolean), Kind::Boolean);
+    }
+}
@@ -0,0 +1,62 @@
+//! Files to compare, from the file sandbox the bridge mounts read-only
+//! at `/files`.
+
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Component, PathBuf};
+
+/// Where the bridge mounts the file sandbox.
+pub const MOUNT: &str = "/files";
+
+/// `path` inside the sandbox, e.g. `drafts/letter.md`. A leading `/` or
+/// `/files/` is allowed; `..` is not.
+pub fn resolve(path: &str) -> Result<PathBuf, String> {
+    let trimmed = path.trim();
+    let relative = trimmed
+        .strip_prefix(MOUNT)
+        .filter(|rest| rest.is_empty() || rest.starts_with('/'))
+        .unwrap_or(trimmed)
+        .trim_start_matches('/');
+    if relative.is_empty() {
+        return Err("Give the path of a file in the file sandbox".to_string());
+    }
+    let mut resolved = PathBuf::from(MOUNT);
+    for component in PathBuf::from(relative).components() {
+        match component {
+            Component::Normal(part) => resolved.push(part),
+            Component::CurDir => {}
+            _ => return Err(format!("'{}' leaves the file sandbox", path)),
+        }
+    }
+    Ok(resolved)
+}
+
+/// The text of the file at `path`, if it is UTF-8 and at most `limit`
+/// bytes.
+pub fn read(path: &str, limit: u64) -> Result<String, String> {
+    let resolved = resolve(path)?;
+    let opened = |e: std::io::Error| match e.kind() {
+        ErrorKind::NotFound => format!("There is no file '{}' in the file sandbox", path),
+        _ => format!("'{}' couldn't be read: {}", path, e),
+    };
+    let size = fs::metadata(&resolved).map_err(opened)?.len();
+    if size > limit {
+        return Err(format!("'{}' is {} bytes; files can be at most {}", path, size, limit));
+    }
+    let bytes = fs::read(&resolved).map_err(opened)?;
+    String::from_utf8(bytes).map_err(|_| format!("'{}' isn't UTF-8 text", path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve() {
+        let expected = PathBuf::from("/files/drafts/letter.md");
+        assert_eq!(resolve("drafts/letter.md").unwrap(), expected);
+        assert_eq!(resolve("/files/drafts/./letter.md").unwrap(), expected);
+        assert!(resolve("../letter.md").is_err());
+        assert!(resolve("").is_err());
+    }
+}
@@ -0,0 +1,198 @@
+//! Diff MCP Server (WASM component)
+//!
+//! Shows what changed between two texts: `diff.unified` as a unified diff,
+//! and `diff.words` inline, word by word, as `git diff --word-diff` does.
+//! Either side can be a file in Harbor's file sandbox, which the bridge
+//! mounts read-only at `/files`.
+//!
+//! Build with:
+//!   cargo build --release --target wasm32-wasip2
+
+mod files;
+mod words;
+
+use std::time::Duration;
+
+use harbor_mcp_sdk::{harbor_tool, Error, Server, ToolInput, ToolResult};
+use serde::Deserialize;
+use serde_json::json;
+use similar::{ChangeTag, TextDiff, TextDiffConfig};
+
+use words::Op;
+
+wit_bindgen::generate!({ path: "../../wit", world: "mcp-server" });
+
+/// Longest text compared on either side, in bytes.
+const MAX_TEXT_BYTES: usize = 1_000_000;
+const DEFAULT_CONTEXT: usize = 3;
+const MAX_CONTEXT: usize = 100;
+/// How long a diff looks for the fewest changes before settling for more.
+const DIFF_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Deserialize, ToolInput)]
+#[serde(deny_unknown_fields)]
+struct UnifiedArgs {
+    /// The original text
+    old: Option<String>,
+    /// The changed text
+    new: Option<String>,
+    /// A file in the file sandbox to use as the original, instead of old
+    old_path: Option<String>,
+    /// A file in the file sandbox to use as the changed text, instead of new
+    new_path: Option<String>,
+    /// Unchanged lines shown around each change (default 3)
+    context: Option<usize>,
+}
+
+#[derive(Deserialize, ToolInput)]
+#[serde(deny_unknown_fields)]
+struct WordsArgs {
+    /// The original text
+    old: Option<String>,
+    /// The changed text
+    new: Option<String>,
+    /// A file in the file sandbox to use as the original, instead of old
+    old_path: Option<String>,
+    /// A file in the file sandbox to use as the changed text, instead of new
+    new_path: Option<String>,
+    /// "words" (default), or "chars" for short strings such as ids and codes
+    by: Option<String>,
+}
+
+/// One side of a comparison.
+struct Side {
+    text: String,
+    /// What a unified diff's header calls it
+    name: String,
+}
+
+/// The `which` side of a comparison, from its text or its file's.
+fn side(
+    text: Option<String>,
+    path: Option<String>,
+    which: &str,
+    prefix: &str,
+) -> Result<Side, String> {
+    let side = match (text, path) {
+        (Some(text), None) => Side { text, name: which.to_string() },
+        (None, Some(path)) => {
+            let resolved = files::resolve(&path)?;
+            let shown = resolved.strip_prefix(files::MOUNT).unwrap_or(&resolved).display();
+            let name = format!("{}/{}", prefix, shown);
+            Side { text: files::read(&path, MAX_TEXT_BYTES as u64)?, name }
+        }
+        (Some(_), Some(_)) => return Err(format!("Give {0} or {0}_path, not both", which)),
+        (None, None) => {
+            let message = format!("Give the {0} text as {0}, or a file as {0}_path", which);
+            return Err(message);
+        }
+    };
+    if side.text.len() > MAX_TEXT_BYTES {
+        return Err(format!("The {} text is over the {} byte limit", which, MAX_TEXT_BYTES));
+    }
+    Ok(side)
+}
+
+fn config() -> TextDiffConfig {
+    let mut config = TextDiff::configure();
+    config.timeout(DIFF_TIMEOUT);
+    config
+}
+
+/// How alike two texts are, from 0 to 1, to three places.
+fn similarity(diff: &TextDiff<'_, '_, '_, str>) -> f64 {
+    (f64::from(diff.ratio()) * 1000.0).round() / 1000.0
+}
+
+fn unified_diff(args: UnifiedArgs) -> Result<serde_json::Value, String> {
+    let old = side(args.old, args.old_path, "old", "a")?;
+    let new = side(args.new, args.new_path, "new", "b")?;
+    let context = args.context.unwrap_or(DEFAULT_CONTEXT).min(MAX_CONTEXT);
+    let diff = config().diff_lines(&old.text, &new.text);
+    let (mut added, mut removed) = (0, 0);
+    for change in diff.iter_all_changes() {
+        match change.tag() {
+            ChangeTag::Insert => added += 1,
+            ChangeTag::Delete => removed += 1,
+            ChangeTag::Equal => {}
+        }
+    }
+    let text = diff
+        .unified_diff()
+        .context_radius(context)
+        .header(&old.name, &new.name)
+        .to_string();
+    Ok(json!({
+        "diff": text,
+        "changed": old.text != new.text,
+        "added": added,
+        "removed": removed,
+        "similarity": similarity(&diff),
+    }))
+}
+
+fn word_diff(args: WordsArgs) -> Result<serde_json::Value, String> {
+    let old = side(args.old, args.old_path, "old", "a")?;
+    let new = side(args.new, args.new_path, "new", "b")?;
+    let diff = match args.by.as_deref().unwrap_or("words") {
+        "words" => config().diff_words(&old.text, &new.text),
+        "chars" => config().diff_chars(&old.text, &new.text),
+        other => return Err(format!("'by' is \"words\" or \"chars\", not '{}'", other)),
+    };
+    let pieces = diff.iter_all_changes().map(|change| {
+        let op = match change.tag() {
+            ChangeTag::Equal => Op::Equal,
+            ChangeTag::Delete => Op::Delete,
+            ChangeTag::Insert => Op::Insert,
+        };
+        (op, change.value())
+    });
+    let runs = words::runs(pieces);
+    let (removed, added) = words::counts(&runs);
+    Ok(json!({
+        "marked": words::marked(&runs),
+        "changed": old.text != new.text,
+        "added_words": added,
+        "removed_words": removed,
+        "similarity": similarity(&diff),
+    }))
+}
+
+fn reply(result: Result<serde_json::Value, String>) -> Result<ToolResult, Error> {
+    Ok(match result {
+        Ok(value) => ToolResult::json(&value),
+        Err(e) => ToolResult::error(e),
+    })
+}
+
+/// Compare two texts line by line, as a unified diff (the format of
+/// `diff -u` and `git diff`), with counts of lines added and removed.
+/// Either can be a file in the file sandbox.
+#[harbor_tool(name = "diff.unified")]
+fn unified(args: UnifiedArgs) -> Result<ToolResult, Error> {
+    reply(unified_diff(args))
+}
+
+/// Compare two texts word by word, marking what was removed as [-text-]
+/// and what was added as {+text+} in the changed text. Best for prose and
+/// short edits, where a line diff would show whole paragraphs.
+#[harbor_tool(name = "diff.words")]
+fn words_changed(args: WordsArgs) -> Result<ToolResult, Error> {
+    reply(word_diff(args))
+}
+
+fn server() -> Server {
+    Server::new("mcp-diff", "1.0.0")
+        .register(unified_tool())
+        .register(words_changed_tool())
+}
+
+struct Component;
+
+impl exports::harbor::mcp::server::Guest for Component {
+    fn handle(request: String) -> String {
+        server().handle(&request)
+    }
+}
+
+export!(Component);
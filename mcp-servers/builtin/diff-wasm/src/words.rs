@@ -0,0 +1,182 @@
+//! Word-level changes as runs of kept, removed and added text, marked up
+//! inline as `git diff --word-diff` does: `the [-quick-]{+slow+} fox`.
+//! Spaces between two replacements join them, so a changed phrase reads
+//! as one change rather than a change per word.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Op {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/// Text kept, removed or added.
+#[derive(Debug, PartialEq)]
+pub struct Run {
+    pub op: Op,
+    pub text: String,
+}
+
+#[derive(Default)]
+struct Runs {
+    runs: Vec<Run>,
+    deleted: String,
+    inserted: String,
+    /// Whitespace kept after a replacement, joined into it if another
+    /// change follows
+    gap: String,
+}
+
+impl Runs {
+    fn push(&mut self, op: Op, text: &str) {
+        match self.runs.last_mut() {
+            Some(last) if last.op == op => last.text.push_str(text),
+            _ if text.is_empty() => {}
+            _ => self.runs.push(Run { op, text: text.to_string() }),
+        }
+    }
+
+    /// End the change in progress, and any gap after it.
+    fn flush(&mut self) {
+        // Whitespace both sides end with was kept, not changed
+        let common = self
+            .deleted
+            .chars()
+            .rev()
+            .zip(self.inserted.chars().rev())
+            .take_while(|(a, b)| a == b && a.is_whitespace())
+            .map(|(a, _)| a.len_utf8())
+            .sum::<usize>();
+        let kept = self.deleted.split_off(self.deleted.len() - common);
+        self.inserted.truncate(self.inserted.len() - common);
+        let deleted = std::mem::take(&mut self.deleted);
+        let inserted = std::mem::take(&mut self.inserted);
+        let gap = std::mem::take(&mut self.gap);
+        self.push(Op::Delete, &deleted);
+        self.push(Op::Insert, &inserted);
+        self.push(Op::Equal, &kept);
+        self.push(Op::Equal, &gap);
+    }
+
+    fn change(&mut self, op: Op, text: &str) {
+        if !self.gap.is_empty() {
+            let gap = std::mem::take(&mut self.gap);
+            self.deleted.push_str(&gap);
+            self.inserted.push_str(&gap);
+        }
+        match op {
+            Op::Delete => self.deleted.push_str(text),
+            _ => self.inserted.push_str(text),
+        }
+    }
+}
+
+/// Runs from the pieces of a diff, in order.
+pub fn runs<'a>(pieces: impl IntoIterator<Item = (Op, &'a str)>) -> Vec<Run> {
+    let pieces: Vec<(Op, &str)> = pieces.into_iter().collect();
+    let mut runs = Runs::default();
+    for (i, &(op, text)) in pieces.iter().enumerate() {
+        let replacing = !runs.deleted.is_empty() && !runs.inserted.is_empty();
+        match op {
+            Op::Equal if replacing && text.trim().is_empty() => runs.gap.push_str(text),
+            Op::Equal => {
+                runs.flush();
+                runs.push(Op::Equal, text);
+            }
+            // A gap joins only a replacement to another replacement
+            _ if !runs.gap.is_empty() && !replaces(&pieces[i..]) => {
+                runs.flush();
+                runs.change(op, text);
+            }
+            _ => runs.change(op, text),
+        }
+    }
+    runs.flush();
+    runs.runs
+}
+
+/// Whether the changes at the start of `pieces` both remove and add text.
+fn replaces(pieces: &[(Op, &str)]) -> bool {
+    let changes = pieces.iter().take_while(|(op, _)| *op != Op::Equal);
+    let (mut deletes, mut inserts) = (false, false);
+    for (op, _) in changes {
+        deletes |= *op == Op::Delete;
+        inserts |= *op == Op::Insert;
+    }
+    deletes && inserts
+}
+
+/// The runs as one text, removals in `[-…-]` and additions in `{+…+}`.
+pub fn marked(runs: &[Run]) -> String {
+    let mut text = String::new();
+    for run in runs {
+        match run.op {
+            Op::Equal => text.push_str(&run.text),
+            Op::Delete => {
+                text.push_str("[-");
+                text.push_str(&run.text);
+                text.push_str("-]");
+            }
+            Op::Insert => {
+                text.push_str("{+");
+                text.push_str(&run.text);
+                text.push_str("+}");
+            }
+        }
+    }
+    text
+}
+
+/// Words removed and added.
+pub fn counts(runs: &[Run]) -> (usize, usize) {
+    let words = |op: Op| -> usize {
+        runs.iter()
+            .filter(|run| run.op == op)
+            .map(|run| run.text.split_whitespace().count())
+            .sum()
+    };
+    (words(Op::Delete), words(Op::Insert))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Op::*;
+
+    fn mark(pieces: &[(Op, &str)]) -> String {
+        marked(&runs(pieces.iter().copied()))
+    }
+
+    #[test]
+    fn test_marked() {
+        let pieces =
+            [(Equal, "the"), (Equal, " "), (Delete, "quick"), (Insert, "slow"), (Equal, " fox")];
+        assert_eq!(mark(&pieces), "the [-quick-]{+slow+} fox");
+        assert_eq!(mark(&[(Equal, "same")]), "same");
+        assert_eq!(mark(&[]), "");
+    }
+
+    #[test]
+    fn test_joined_replacements() {
+        let pieces = [
+            (Delete, "quick"),
+            (Insert, "slow"),
+            (Equal, " "),
+            (Delete, "brown"),
+            (Insert, "red"),
+            (Equal, " fox"),
+        ];
+        assert_eq!(mark(&pieces), "[-quick brown-]{+slow red+} fox");
+        // Nor a removal alone
+        let pieces = [(Delete, "a"), (Insert, "b"), (Equal, " "), (Delete, "c"), (Equal, "d")];
+        assert_eq!(mark(&pieces), "[-a-]{+b+} [-c-]d");
+    }
+
+    #[test]
+    fn test_separate_changes() {
+        let pieces = [(Delete, "old"), (Equal, " "), (Insert, "new"), (Equal, " end")];
+        assert_eq!(mark(&pieces), "[-old-] {+new+} end");
+        let runs = runs(pieces);
+        assert_eq!(counts(&runs), (1, 1));
+    }
+}
@@ -0,0 +1,293 @@
+//! Google Drive MCP Server (WASM component)
+//!
+//! `drive.search`, `drive.read` and `drive.upload` tools over the Drive
+//! API, for the Google account the user connected in Harbor. Tokens come
+//! from the bridge through the `harbor:mcp/oauth` import, for one scope at
+//! a time: searching and reading need `drive.readonly`, uploading only
+//! `drive.file`, which reaches just the files this server creates.
+//!
+//! Docs, Sheets and Slides have no content of their own and are exported
+//! to text (see `mime`); other files are read only if they are text and at
+//! most `MAX_READ_BYTES`. Uploads are text, at most `MAX_UPLOAD_BYTES`.
+//!
+//! Build with:
+//!   cargo build --release --target wasm32-wasip2
+
+mod mime;
+
+use harbor_mcp_sdk::{encode_query, harbor_tool, Api, Error, Server, ToolInput, ToolResult};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+wit_bindgen::generate!({ path: "../../wit", world: "mcp-server" });
+
+use harbor::mcp::{http, oauth};
+
+const API: &str = "https://www.googleapis.com/drive/v3";
+const UPLOAD_API: &str = "https://www.googleapis.com/upload/drive/v3";
+const READONLY_SCOPE: &str = "https://www.googleapis.com/auth/drive.readonly";
+const FILE_SCOPE: &str = "https://www.googleapis.com/auth/drive.file";
+
+/// The file fields tools show.
+const FIELDS: &str = "id,name,mimeType,size,modifiedTime,webViewLink";
+
+const DEFAULT_RESULTS: usize = 10;
+const MAX_RESULTS: usize = 50;
+/// Characters of a file returned unless the call asks for more.
+const DEFAULT_MAX_CHARS: usize = 20_000;
+const MAX_CHARS: usize = 100_000;
+/// Largest file downloaded to be read.
+const MAX_READ_BYTES: u64 = 5 * 1024 * 1024;
+/// Largest upload; Drive's limit for a single-request upload.
+const MAX_UPLOAD_BYTES: usize = 5 * 1024 * 1024;
+
+#[derive(Deserialize, ToolInput)]
+#[serde(deny_unknown_fields)]
+struct SearchArgs {
+    /// Words to find in file names and contents; empty lists the most
+    /// recently modified files
+    #[serde(default)]
+    query: String,
+    /// Only files of this MIME type, e.g. "application/pdf" or
+    /// "application/vnd.google-apps.document" for Google Docs
+    mime_type: Option<String>,
+    /// Most files to return (default 10, at most 50)
+    max_results: Option<usize>,
+    /// next_page_token from a previous search, for the next page
+    page_token: Option<String>,
+}
+
+#[derive(Deserialize, ToolInput)]
+#[serde(deny_unknown_fields)]
+struct ReadArgs {
+    /// The file's id, from drive.search
+    id: String,
+    /// Most characters of the content to return (default 20000)
+    max_chars: Option<usize>,
+}
+
+#[derive(Deserialize, ToolInput)]
+#[serde(deny_unknown_fields)]
+struct UploadArgs {
+    /// The new file's name, e.g. "notes.md"
+    name: String,
+    /// The file's content, as text
+    content: String,
+    /// The content's MIME type (default text/plain); must be a text type
+    mime_type: Option<String>,
+    /// Id of the folder to put the file in; defaults to My Drive
+    folder_id: Option<String>,
+    /// Convert the file to a Google Doc, e.g. from text/plain or text/html
+    #[serde(default)]
+    as_google_doc: bool,
+}
+
+/// The Drive API, with a token for one scope.
+fn connect(scope: &str) -> Result<Api, String> {
+    let token = oauth::access_token(&[scope.to_string()])?;
+    Ok(Api::new("Drive", "Google", token, fetch))
+}
+
+harbor_mcp_sdk::http_fetch!();
+
+/// Drive ids are letters, digits, '-' and '_'; refuse anything that could
+/// change the request path.
+fn check_id(id: &str) -> Result<(), String> {
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err(format!("'{}' isn't a Drive file id", id));
+    }
+    Ok(())
+}
+
+/// A file as the tools show it, with its size as a number.
+fn file_view(file: &Value) -> Value {
+    json!({
+        "id": file["id"],
+        "name": file["name"],
+        "mime_type": file["mimeType"],
+        "size": file["size"].as_str().and_then(|size| size.parse::<u64>().ok()),
+        "modified_time": file["modifiedTime"],
+        "web_view_link": file["webViewLink"],
+    })
+}
+
+fn search_files(args: SearchArgs) -> Result<Value, String> {
+    let drive = connect(READONLY_SCOPE)?;
+    let page_size = args.max_results.unwrap_or(DEFAULT_RESULTS).clamp(1, MAX_RESULTS);
+    let q = mime::search_query(&args.query, args.mime_type.as_deref());
+    let mut url = format!(
+        "{}/files?q={}&pageSize={}&fields={}&supportsAllDrives=true&includeItemsFromAllDrives=true",
+        API,
+        encode_query(&q),
+        page_size,
+        encode_query(&format!("nextPageToken,files({})", FIELDS))
+    );
+    // Drive can't sort full-text results
+    if args.query.trim().is_empty() {
+        url.push_str("&orderBy=modifiedTime%20desc");
+    }
+    if let Some(page_token) = &args.page_token {
+        url.push_str(&format!("&pageToken={}", encode_query(page_token)));
+    }
+    let listed = drive.json("GET", &url, None)?;
+    let files: Vec<Value> = listed["files"].as_array().into_iter().flatten().map(file_view).collect();
+    Ok(json!({ "files": files, "next_page_token": listed["nextPageToken"] }))
+}
+
+fn read_file(args: ReadArgs) -> Result<Value, String> {
+    check_id(&args.id)?;
+    let drive = connect(READONLY_SCOPE)?;
+    let url = format!("{}/files/{}?fields={}&supportsAllDrives=true", API, args.id, FIELDS);
+    let file = drive.json("GET", &url, None)?;
+    let mime_type = file["mimeType"].as_str().unwrap_or_default();
+    let name = file["name"].as_str().unwrap_or_default();
+
+    let mut shown = file_view(&file);
+    let response = if let Some(export) = mime::export_type(mime_type) {
+        shown["exported_as"] = json!(export);
+        let url = format!("{}/files/{}/export?mimeType={}", API, args.id, encode_query(export));
+        drive.request("GET", &url, None)?
+    } else if mime_type == mime::GOOGLE_FOLDER {
+        return Err(format!("'{}' is a folder; search for files in it by name", name));
+    } else if mime_type.starts_with(mime::GOOGLE_APPS) {
+        return Err(format!("'{}' ({}) can't be read as text", name, mime_type));
+    } else if !mime::is_text(mime_type) {
+        return Err(format!("'{}' is {}, not text, and can't be shown", name, mime_type));
+    } else {
+        let size = shown["size"].as_u64().unwrap_or(0);
+        if size > MAX_READ_BYTES {
+            return Err(format!(
+                "'{}' is {} KB; files over {} MB aren't read",
+                name,
+                size / 1024,
+                MAX_READ_BYTES / (1024 * 1024)
+            ));
+        }
+        let url = format!("{}/files/{}?alt=media&supportsAllDrives=true", API, args.id);
+        drive.request("GET", &url, None)?
+    };
+
+    let content = String::from_utf8_lossy(&response.body);
+    let max_chars = args.max_chars.unwrap_or(DEFAULT_MAX_CHARS).min(MAX_CHARS);
+    match content.char_indices().nth(max_chars) {
+        Some((end, _)) => {
+            shown["content"] = json!(&content[..end]);
+            shown["truncated"] = json!(true);
+            shown["content_chars"] = json!(content.chars().count());
+        }
+        None => shown["content"] = json!(content),
+    }
+    Ok(shown)
+}
+
+fn upload_file(args: UploadArgs) -> Result<Value, String> {
+    let mime_type = args.mime_type.unwrap_or_else(|| "text/plain".to_string());
+    if !mime_type.contains('/') || mime_type.contains(['\r', '\n']) {
+        return Err(format!("'{}' isn't a MIME type", mime_type));
+    }
+    if !mime::is_text(&mime_type) {
+        return Err(format!("Only text can be uploaded, not {}", mime_type));
+    }
+    if args.content.len() > MAX_UPLOAD_BYTES {
+        return Err(format!(
+            "The content is {} KB; uploads are at most {} MB",
+            args.content.len() / 1024,
+            MAX_UPLOAD_BYTES / (1024 * 1024)
+        ));
+    }
+    let mut metadata = json!({ "name": args.name });
+    if let Some(folder_id) = &args.folder_id {
+        check_id(folder_id)?;
+        metadata["parents"] = json!([folder_id]);
+    }
+    if args.as_google_doc {
+        metadata["mimeType"] = json!(mime::GOOGLE_DOC);
+    }
+
+    let drive = connect(FILE_SCOPE)?;
+    let (body, boundary) = mime::multipart(&metadata.to_string(), &mime_type, args.content.as_bytes());
+    let url = format!(
+        "{}/files?uploadType=multipart&fields={}&supportsAllDrives=true",
+        UPLOAD_API,
+        encode_query(FIELDS)
+    );
+    let content_type = format!("multipart/related; boundary={}", boundary);
+    let response = drive.request("POST", &url, Some((content_type, body)))?;
+    let created: Value =
+        serde_json::from_slice(&response.body).map_err(|e| format!("Unexpected answer from Drive: {}", e))?;
+    Ok(file_view(&created))
+}
+
+fn reply(result: Result<Value, String>) -> Result<ToolResult, Error> {
+    Ok(match result {
+        Ok(value) => ToolResult::json(&value),
+        Err(e) => ToolResult::error(e),
+    })
+}
+
+/// Search the user's Google Drive for files by the words in their names
+/// and contents. Returns each file's id, name, type, size, last change and
+/// link; read one with drive.read.
+#[harbor_tool(name = "drive.search")]
+fn search(args: SearchArgs) -> Result<ToolResult, Error> {
+    reply(search_files(args))
+}
+
+/// Read a Drive file as text. Google Docs and Slides come back as plain
+/// text and Sheets as CSV (the first sheet); other files must be text and
+/// at most 5 MB.
+#[harbor_tool(name = "drive.read")]
+fn read(args: ReadArgs) -> Result<ToolResult, Error> {
+    reply(read_file(args))
+}
+
+/// Create a text file in the user's Google Drive, optionally converted to
+/// a Google Doc. Returns the new file's id and link.
+#[harbor_tool(name = "drive.upload")]
+fn upload(args: UploadArgs) -> Result<ToolResult, Error> {
+    reply(upload_file(args))
+}
+
+fn server() -> Server {
+    Server::new("mcp-drive", "1.0.0")
+        .register(search_tool())
+        .register(read_tool())
+        .register(upload_tool())
+}
+
+struct Component;
+
+impl exports::harbor::mcp::server::Guest for Component {
+    fn handle(request: String) -> String {
+        server().handle(&request)
+    }
+}
+
+export!(Component);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_id() {
+        assert!(check_id("1a2B3c_-XyZ").is_ok());
+        assert!(check_id("").is_err());
+        assert!(check_id("../about").is_err());
+    }
+
+    #[test]
+    fn test_file_view() {
+        let file = json!({
+            "id": "1a2B",
+            "name": "Budget",
+            "mimeType": "application/vnd.google-apps.spreadsheet",
+            "modifiedTime": "2024-06-03T09:00:00.000Z",
+            "webViewLink": "https://docs.google.com/spreadsheets/d/1a2B/edit"
+        });
+        let shown = file_view(&file);
+        assert_eq!(shown["mime_type"], "application/vnd.google-apps.spreadsheet");
+        assert_eq!(shown["size"], Value::Null);
+        assert_eq!(file_view(&json!({ "size": "5120" }))["size"], 5120);
+    }
+}
@@ -0,0 +1,122 @@
+//! File types: which files can be read as text, what Google's own formats
+//! are exported as, and the multipart body of an upload.
+
+/// Prefix of the MIME types of Google Docs, Sheets, Slides and the rest,
+/// which have no content of their own to download, only exports.
+pub const GOOGLE_APPS: &str = "application/vnd.google-apps.";
+
+pub const GOOGLE_DOC: &str = "application/vnd.google-apps.document";
+pub const GOOGLE_FOLDER: &str = "application/vnd.google-apps.folder";
+
+/// What a Google format is exported as to be read, if it can be read as
+/// text: Docs and Slides as plain text, Sheets (the first sheet) as CSV.
+pub fn export_type(mime_type: &str) -> Option<&'static str> {
+    match mime_type.strip_prefix(GOOGLE_APPS)? {
+        "document" | "presentation" => Some("text/plain"),
+        "spreadsheet" => Some("text/csv"),
+        _ => None,
+    }
+}
+
+/// Whether a file of `mime_type` is text, and so can be read or uploaded
+/// as it is.
+pub fn is_text(mime_type: &str) -> bool {
+    let mime_type = mime_type.to_ascii_lowercase();
+    let essence = mime_type.split(';').next().unwrap_or_default().trim();
+    essence.starts_with("text/")
+        || ["json", "xml", "javascript", "yaml", "csv", "markdown", "x-sh", "sql"]
+            .iter()
+            .any(|kind| essence.ends_with(kind) || essence.contains(&format!("{}+", kind)))
+}
+
+/// `text` as a string literal in a Drive query, in single quotes with
+/// quotes and backslashes escaped.
+pub fn quote(text: &str) -> String {
+    format!("'{}'", text.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+/// A Drive `q` finding files with all of `words` in their name or content,
+/// not in the trash, optionally of one type.
+pub fn search_query(words: &str, mime_type: Option<&str>) -> String {
+    let mut terms = vec!["trashed = false".to_string()];
+    if !words.trim().is_empty() {
+        terms.push(format!("fullText contains {}", quote(words.trim())));
+    }
+    if let Some(mime_type) = mime_type {
+        terms.push(format!("mimeType = {}", quote(mime_type)));
+    }
+    terms.join(" and ")
+}
+
+/// A `multipart/related` upload body: the file's metadata as JSON, then
+/// its content. Returns the body and the boundary used, which doesn't
+/// occur in `content`.
+pub fn multipart(metadata: &str, mime_type: &str, content: &[u8]) -> (Vec<u8>, String) {
+    let boundary = (0..)
+        .map(|n| format!("harbor-drive-{}", n))
+        .find(|boundary| !contains(content, boundary.as_bytes()))
+        .expect("some boundary is free");
+    let mut body = format!(
+        "--{b}\r\nContent-Type: application/json; charset=UTF-8\r\n\r\n{}\r\n--{b}\r\nContent-Type: {}\r\n\r\n",
+        metadata,
+        mime_type,
+        b = boundary
+    )
+    .into_bytes();
+    body.extend_from_slice(content);
+    body.extend_from_slice(format!("\r\n--{}--\r\n", boundary).as_bytes());
+    (body, boundary)
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_export_type() {
+        assert_eq!(export_type(GOOGLE_DOC), Some("text/plain"));
+        assert_eq!(export_type("application/vnd.google-apps.spreadsheet"), Some("text/csv"));
+        assert_eq!(export_type("application/vnd.google-apps.drawing"), None);
+        assert_eq!(export_type("text/plain"), None);
+    }
+
+    #[test]
+    fn test_is_text() {
+        assert!(is_text("text/markdown"));
+        assert!(is_text("application/json; charset=utf-8"));
+        assert!(is_text("application/ld+json"));
+        assert!(is_text("image/svg+xml"));
+        assert!(!is_text("application/pdf"));
+        assert!(!is_text("image/png"));
+        assert!(!is_text(""));
+    }
+
+    #[test]
+    fn test_search_query() {
+        assert_eq!(search_query("", None), "trashed = false");
+        assert_eq!(
+            search_query(" Ada's budget ", Some("application/vnd.google-apps.spreadsheet")),
+            "trashed = false and fullText contains 'Ada\\'s budget' \
+             and mimeType = 'application/vnd.google-apps.spreadsheet'"
+        );
+        assert_eq!(quote("a\\b"), "'a\\\\b'");
+    }
+
+    #[test]
+    fn test_multipart() {
+        let (body, boundary) = multipart("{\"name\":\"notes.txt\"}", "text/plain", b"hello");
+        assert_eq!(boundary, "harbor-drive-0");
+        assert_eq!(
+            String::from_utf8(body).unwrap(),
+            "--harbor-drive-0\r\nContent-Type: application/json; charset=UTF-8\r\n\r\n{\"name\":\"notes.txt\"}\r\n\
+             --harbor-drive-0\r\nContent-Type: text/plain\r\n\r\nhello\r\n--harbor-drive-0--\r\n"
+        );
+
+        let (_, boundary) = multipart("{}", "text/plain", b"--harbor-drive-0 and harbor-drive-1");
+        assert_eq!(boundary, "harbor-drive-2");
+    }
+}
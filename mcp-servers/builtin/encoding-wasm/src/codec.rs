@@ -0,0 +1,166 @@
+//! Text encodings: base64 (standard and URL-safe), hex and percent
+//! encoding. Decoding is lenient where it costs nothing: base64 accepts
+//! either alphabet, with or without padding, and ignores whitespace.
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+const BASE64_URL: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// Base64, URL-safe and unpadded when `url` is set, as JWTs use.
+pub fn base64_encode(bytes: &[u8], url: bool) -> String {
+    let alphabet = if url { BASE64_URL } else { BASE64 };
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, b)| n | (u32::from(*b) << (16 - 8 * i)));
+        for i in 0..=chunk.len() {
+            out.push(alphabet[((n >> (18 - 6 * i)) & 63) as usize] as char);
+        }
+        if !url {
+            for _ in chunk.len()..3 {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+pub fn base64_decode(text: &str) -> Result<Vec<u8>, String> {
+    let mut out = Vec::with_capacity(text.len() / 4 * 3);
+    let mut n = 0u32;
+    let mut bits = 0;
+    for (i, c) in text.char_indices() {
+        let value = match c {
+            'A'..='Z' => c as u32 - 'A' as u32,
+            'a'..='z' => c as u32 - 'a' as u32 + 26,
+            '0'..='9' => c as u32 - '0' as u32 + 52,
+            '+' | '-' => 62,
+            '/' | '_' => 63,
+            '=' => break,
+            c if c.is_whitespace() => continue,
+            c => return Err(format!("'{}' at position {} isn't base64", c, i)),
+        };
+        n = (n << 6) | value;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((n >> bits) as u8);
+            n &= (1 << bits) - 1;
+        }
+    }
+    // Six bits left over can't come from whole bytes
+    if bits >= 6 {
+        return Err("The base64 text is cut short".to_string());
+    }
+    Ok(out)
+}
+
+pub fn hex_encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Decode hex, ignoring whitespace, `:` separators and a `0x` prefix.
+pub fn hex_decode(text: &str) -> Result<Vec<u8>, String> {
+    let text = text.trim();
+    let text = text.strip_prefix("0x").unwrap_or(text);
+    let digits: Vec<char> = text.chars().filter(|c| !c.is_whitespace() && *c != ':').collect();
+    if digits.len() % 2 != 0 {
+        return Err("Hex needs an even number of digits".to_string());
+    }
+    digits
+        .chunks(2)
+        .map(|pair| {
+            let high = pair[0].to_digit(16);
+            let low = pair[1].to_digit(16);
+            match (high, low) {
+                (Some(high), Some(low)) => Ok((high * 16 + low) as u8),
+                _ => Err(format!("'{}{}' isn't hex", pair[0], pair[1])),
+            }
+        })
+        .collect()
+}
+
+/// Percent-encode everything but the unreserved characters of RFC 3986,
+/// as for a query parameter or path segment. With `form`, spaces become
+/// `+` as in `application/x-www-form-urlencoded`.
+pub fn url_encode(text: &str, form: bool) -> String {
+    let mut out = String::with_capacity(text.len());
+    for byte in text.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                out.push(byte as char)
+            }
+            b' ' if form => out.push('+'),
+            _ => out.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    out
+}
+
+/// Decode percent-encoding, and `+` as a space with `form`.
+pub fn url_decode(text: &str, form: bool) -> Result<Vec<u8>, String> {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' => {
+                let pair = text.get(i + 1..i + 3).unwrap_or("");
+                let byte = u8::from_str_radix(pair, 16)
+                    .ok()
+                    .filter(|_| pair.len() == 2)
+                    .ok_or_else(|| format!("'%{}' at position {} isn't an escape", pair, i))?;
+                out.push(byte);
+                i += 3;
+            }
+            b'+' if form => {
+                out.push(b' ');
+                i += 1;
+            }
+            byte => {
+                out.push(byte);
+                i += 1;
+            }
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base64() {
+        let cases = [("", ""), ("f", "Zg=="), ("fo", "Zm8="), ("foob", "Zm9vYg==")];
+        for (text, encoded) in cases {
+            assert_eq!(base64_encode(text.as_bytes(), false), encoded);
+            assert_eq!(base64_decode(encoded).unwrap(), text.as_bytes());
+        }
+        assert_eq!(base64_encode(&[0xfb, 0xff], true), "-_8");
+        assert_eq!(base64_decode("-_8").unwrap(), vec![0xfb, 0xff]);
+        assert_eq!(base64_decode("Zm9v\nYg").unwrap(), b"foob");
+        assert!(base64_decode("Zm9v!").unwrap_err().contains("'!'"));
+        assert!(base64_decode("Z").is_err());
+    }
+
+    #[test]
+    fn test_hex() {
+        assert_eq!(hex_encode(&[0, 0xab, 0x10]), "00ab10");
+        assert_eq!(hex_decode("0x00AB10").unwrap(), vec![0, 0xab, 0x10]);
+        assert_eq!(hex_decode("de:ad be:ef").unwrap(), vec![0xde, 0xad, 0xbe, 0xef]);
+        assert!(hex_decode("abc").is_err());
+        assert!(hex_decode("zz").is_err());
+    }
+
+    #[test]
+    fn test_url() {
+        assert_eq!(url_encode("a b&c=d/é~", false), "a%20b%26c%3Dd%2F%C3%A9~");
+        assert_eq!(url_encode("a b", true), "a+b");
+        assert_eq!(url_decode("a%20b%26c+d", false).unwrap(), b"a b&c+d");
+        assert_eq!(url_decode("a+b%C3%A9", true).unwrap(), "a bé".as_bytes());
+        assert!(url_decode("100%", false).is_err());
+        assert!(url_decode("%zz", false).is_err());
+    }
+}
@@ -0,0 +1,95 @@
+//! Reading JSON Web Tokens without verifying them: the header and claims
+//! are decoded for display, and the registered time claims (`exp`, `nbf`,
+//! `iat`) are shown as dates and checked against the current time. A
+//! signature can't be checked without the issuer's key, so a decoded token
+//! says nothing about whether it is genuine.
+
+use harbor_mcp_sdk::rfc3339;
+use serde_json::{json, Map, Value};
+
+use crate::codec;
+
+fn part(segment: &str, name: &str) -> Result<Value, String> {
+    let bytes = codec::base64_decode(segment)
+        .map_err(|e| format!("The {} isn't base64url: {}", name, e))?;
+    serde_json::from_slice(&bytes).map_err(|_| format!("The {} isn't JSON", name))
+}
+
+/// Decode `token` as of `now` (seconds since the epoch).
+pub fn decode(token: &str, now: u64) -> Result<Value, String> {
+    let token = token.trim();
+    let token = token.strip_prefix("Bearer ").unwrap_or(token).trim();
+    let segments: Vec<&str> = token.split('.').collect();
+    match segments.len() {
+        3 => {}
+        5 => return Err("This is an encrypted token (JWE); its claims can't be read".to_string()),
+        n => return Err(format!("A JWT has 3 parts separated by '.', this has {}", n)),
+    }
+    let header = part(segments[0], "header")?;
+    let claims = part(segments[1], "payload")?;
+    let algorithm = header.get("alg").and_then(Value::as_str).unwrap_or("none");
+    let signature = if segments[2].is_empty() {
+        "none".to_string()
+    } else {
+        format!("{} signature, not verified", algorithm)
+    };
+    let mut decoded = json!({ "header": header, "claims": claims, "signature": signature });
+
+    let mut times = Map::new();
+    for name in ["exp", "nbf", "iat"] {
+        if let Some(secs) = claims.get(name).and_then(Value::as_i64) {
+            times.insert(name.to_string(), json!(rfc3339(secs)));
+        }
+    }
+    if !times.is_empty() {
+        decoded["times"] = Value::Object(times);
+    }
+    let exp = claims.get("exp").and_then(Value::as_u64);
+    let nbf = claims.get("nbf").and_then(Value::as_u64);
+    let status = match (exp, nbf) {
+        (Some(exp), _) if exp <= now => "expired",
+        (_, Some(nbf)) if nbf > now => "not yet valid",
+        (Some(_), _) | (_, Some(_)) => "current",
+        _ => "no expiry",
+    };
+    decoded["status"] = json!(status);
+    Ok(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // jwt.io's example: {"alg":"HS256","typ":"JWT"} and
+    // {"sub":"1234567890","name":"John Doe","iat":1516239022}
+    const TOKEN: &str = "eyJhbGciOiJIUzI1NiIsInR5cCI6IkpXVCJ9.\
+        eyJzdWIiOiIxMjM0NTY3ODkwIiwibmFtZSI6IkpvaG4gRG9lIiwiaWF0IjoxNTE2MjM5MDIyfQ.\
+        SflKxwRJSMeKKF2QT4fwpMeJf36POk6yJV_adQssw5c";
+
+    #[test]
+    fn test_decode() {
+        let decoded = decode(&format!("Bearer {}", TOKEN), 1_700_000_000).unwrap();
+        assert_eq!(decoded["header"]["alg"], "HS256");
+        assert_eq!(decoded["claims"]["name"], "John Doe");
+        assert_eq!(decoded["times"]["iat"], "2018-01-18T01:30:22Z");
+        assert_eq!(decoded["signature"], "HS256 signature, not verified");
+        assert_eq!(decoded["status"], "no expiry");
+    }
+
+    #[test]
+    fn test_status() {
+        let claims = codec::base64_encode(br#"{"exp":100,"nbf":50}"#, true);
+        let token = format!("e30.{}.", claims);
+        assert_eq!(decode(&token, 10).unwrap()["status"], "not yet valid");
+        assert_eq!(decode(&token, 60).unwrap()["status"], "current");
+        assert_eq!(decode(&token, 100).unwrap()["status"], "expired");
+        assert_eq!(decode(&token, 60).unwrap()["signature"], "none");
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(decode("abc", 0).unwrap_err().contains("3 parts"));
+        assert!(decode("a.b.c.d.e", 0).unwrap_err().contains("encrypted"));
+        assert!(decode("e30.!!!.x", 0).unwrap_err().contains("payload"));
+    }
+}
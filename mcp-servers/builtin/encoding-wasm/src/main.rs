@@ -0,0 +1,187 @@
+//! Encoding MCP Server (WASM)
+//!
+//! The conversions that come up constantly in developer workflows and that
+//! models get subtly wrong by hand: base64, hex and URL encoding and
+//! decoding, SHA-256/SHA-512/SHA-1/MD5 digests, and reading JWTs.
+//!
+//! Build with:
+//!   cargo build --release --target wasm32-wasip1
+
+mod codec;
+mod jwt;
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use harbor_mcp_sdk::{harbor_tool, Error, Server, ToolInput, ToolResult};
+use md5::Md5;
+use serde::Deserialize;
+use serde_json::json;
+use sha1::Sha1;
+use sha2::{Digest, Sha256, Sha512};
+
+fn unknown_format(format: &str) -> ToolResult {
+    let formats = "base64, base64url, hex, url or form";
+    ToolResult::error(format!("Unknown format '{}'; use {}", format, formats))
+}
+
+#[derive(Deserialize, ToolInput)]
+#[serde(deny_unknown_fields)]
+struct EncodeArgs {
+    /// The text to encode
+    text: String,
+    /// base64, base64url (unpadded, as in JWTs), hex, url (percent-encoding) or form (+ for spaces)
+    format: String,
+}
+
+#[derive(Deserialize, ToolInput)]
+#[serde(deny_unknown_fields)]
+struct DecodeArgs {
+    /// The encoded text
+    text: String,
+    /// base64 (either alphabet, padded or not), base64url, hex, url or form
+    format: String,
+}
+
+#[derive(Deserialize, ToolInput)]
+#[serde(deny_unknown_fields)]
+struct HashArgs {
+    /// The data to hash
+    text: String,
+    /// sha256 (default), sha512, sha1 or md5
+    algorithm: Option<String>,
+    /// How `text` is given: text (default, hashed as UTF-8), hex or base64
+    input: Option<String>,
+    /// hex (default) or base64
+    output: Option<String>,
+}
+
+#[derive(Deserialize, ToolInput)]
+#[serde(deny_unknown_fields)]
+struct JwtArgs {
+    /// The token, with or without a "Bearer " prefix
+    token: String,
+}
+
+/// Decoded bytes as the tools return them: as text when they are UTF-8,
+/// and as hex when they aren't.
+fn bytes_result(bytes: Vec<u8>) -> ToolResult {
+    match String::from_utf8(bytes) {
+        Ok(text) => ToolResult::json(&json!({ "text": text })),
+        Err(e) => {
+            let bytes = e.into_bytes();
+            ToolResult::json(&json!({
+                "hex": codec::hex_encode(&bytes),
+                "bytes": bytes.len(),
+                "note": "The decoded bytes aren't UTF-8 text, so they are shown as hex",
+            }))
+        }
+    }
+}
+
+/// Encode text as base64, base64url, hex or URL percent-encoding.
+#[harbor_tool(name = "encoding.encode")]
+fn encode(args: EncodeArgs) -> Result<ToolResult, Error> {
+    let bytes = args.text.as_bytes();
+    let encoded = match args.format.trim().to_lowercase().as_str() {
+        "base64" => codec::base64_encode(bytes, false),
+        "base64url" => codec::base64_encode(bytes, true),
+        "hex" => codec::hex_encode(bytes),
+        "url" => codec::url_encode(&args.text, false),
+        "form" => codec::url_encode(&args.text, true),
+        other => return Ok(unknown_format(other)),
+    };
+    Ok(ToolResult::json(&json!({ "text": encoded })))
+}
+
+/// Decode base64, base64url, hex or URL percent-encoding. Returns text, or
+/// hex when the result isn't UTF-8.
+#[harbor_tool(name = "encoding.decode")]
+fn decode(args: DecodeArgs) -> Result<ToolResult, Error> {
+    let decoded = match args.format.trim().to_lowercase().as_str() {
+        "base64" | "base64url" => codec::base64_decode(&args.text),
+        "hex" => codec::hex_decode(&args.text),
+        "url" => codec::url_decode(&args.text, false),
+        "form" => codec::url_decode(&args.text, true),
+        other => return Ok(unknown_format(other)),
+    };
+    Ok(match decoded {
+        Ok(bytes) => bytes_result(bytes),
+        Err(e) => ToolResult::error(e),
+    })
+}
+
+/// Hash data with SHA-256, SHA-512, SHA-1 or MD5, e.g. to compare with a
+/// published checksum. SHA-1 and MD5 are for compatibility, not security.
+#[harbor_tool(name = "hash.digest")]
+fn digest(args: HashArgs) -> Result<ToolResult, Error> {
+    let data = match args.input.as_deref().unwrap_or("text") {
+        "text" => Ok(args.text.into_bytes()),
+        "hex" => codec::hex_decode(&args.text),
+        "base64" => codec::base64_decode(&args.text),
+        other => Err(format!("Unknown input '{}'; use text, hex or base64", other)),
+    };
+    let data = match data {
+        Ok(data) => data,
+        Err(e) => return Ok(ToolResult::error(e)),
+    };
+    let algorithm = args.algorithm.as_deref().unwrap_or("sha256").to_lowercase().replace('-', "");
+    let digest = match algorithm.as_str() {
+        "sha256" => Sha256::digest(&data).to_vec(),
+        "sha512" => Sha512::digest(&data).to_vec(),
+        "sha1" => Sha1::digest(&data).to_vec(),
+        "md5" => Md5::digest(&data).to_vec(),
+        other => {
+            let message = format!("Unknown algorithm '{}'; use sha256, sha512, sha1 or md5", other);
+            return Ok(ToolResult::error(message));
+        }
+    };
+    let digest = match args.output.as_deref().unwrap_or("hex") {
+        "hex" => codec::hex_encode(&digest),
+        "base64" => codec::base64_encode(&digest, false),
+        other => {
+            return Ok(ToolResult::error(format!("Unknown output '{}'; use hex or base64", other)));
+        }
+    };
+    Ok(ToolResult::json(&json!({ "algorithm": algorithm, "digest": digest, "bytes": data.len() })))
+}
+
+/// Decode a JSON Web Token to show its header and claims, with its time
+/// claims as dates and whether it has expired. The signature is NOT
+/// verified, so this can't tell whether the token is genuine.
+#[harbor_tool(name = "jwt.decode")]
+fn decode_jwt(args: JwtArgs) -> Result<ToolResult, Error> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .map_err(|_| Error::new(-32000, "The host clock is unavailable"))?;
+    Ok(match jwt::decode(&args.token, now) {
+        Ok(decoded) => ToolResult::json(&decoded),
+        Err(e) => ToolResult::error(e),
+    })
+}
+
+fn main() {
+    Server::new("mcp-encoding", "1.0.0")
+        .register(encode_tool())
+        .register(decode_tool())
+        .register(digest_tool())
+        .register(decode_jwt_tool())
+        .run();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_digests() {
+        // The FIPS 180 and RFC 1321 test vectors for "abc"
+        assert_eq!(
+            codec::hex_encode(&Sha256::digest(b"abc")),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        let sha1 = codec::hex_encode(&Sha1::digest(b"abc"));
+        assert_eq!(sha1, "a9993e364706816aba3e25717850c26c9cd0d89d");
+        assert_eq!(codec::hex_encode(&Md5::digest(b"abc")), "900150983cd24fb0d6963f7d28e17f72");
+    }
+}
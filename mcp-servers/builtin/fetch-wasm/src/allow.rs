@@ -0,0 +1,110 @@
+//! Which URLs the server fetches: http and https only, never loopback,
+//! private or link-local addresses, and when the user has set a domain
+//! allowlist, only domains on it.
+//!
+//! Names aren't resolved here (the host makes the request), so a public
+//! name that points at a private address isn't caught.
+
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use url::{Host, Url};
+
+/// Parse the domains of an allowlist: separated by commas or whitespace,
+/// with an optional leading `*.`.
+pub fn parse_domains(text: &str) -> Vec<String> {
+    text.split(|c: char| c == ',' || c.is_whitespace())
+        .map(|domain| {
+            let domain = domain.trim().trim_start_matches("*.").trim_end_matches('.');
+            domain.to_ascii_lowercase()
+        })
+        .filter(|domain| !domain.is_empty())
+        .collect()
+}
+
+/// Whether `host` is `domain` or one of its subdomains.
+fn in_domain(host: &str, domain: &str) -> bool {
+    host == domain || host.strip_suffix(domain).is_some_and(|prefix| prefix.ends_with('.'))
+}
+
+fn is_local_v4(ip: Ipv4Addr) -> bool {
+    ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+}
+
+fn is_local_v6(ip: Ipv6Addr) -> bool {
+    let first = ip.segments()[0];
+    ip.is_loopback()
+        || ip.is_unspecified()
+        // Unique local (fc00::/7) and link-local (fe80::/10)
+        || first & 0xfe00 == 0xfc00
+        || first & 0xffc0 == 0xfe80
+        || ip.to_ipv4_mapped().is_some_and(is_local_v4)
+}
+
+/// Check `url` before fetching it. `allowed` is the domain allowlist, or
+/// empty for any domain. The error says why, for the model.
+pub fn check(url: &str, allowed: &[String]) -> Result<Url, String> {
+    let parsed = Url::parse(url).map_err(|e| format!("Invalid URL '{}': {}", url, e))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        let scheme = parsed.scheme();
+        return Err(format!("Only http and https URLs can be fetched, not {}:", scheme));
+    }
+    let local = match parsed.host() {
+        Some(Host::Domain(domain)) => {
+            let domain = domain.trim_end_matches('.').to_ascii_lowercase();
+            in_domain(&domain, "localhost") || in_domain(&domain, "local")
+        }
+        Some(Host::Ipv4(ip)) => is_local_v4(ip),
+        Some(Host::Ipv6(ip)) => is_local_v6(ip),
+        None => return Err(format!("'{}' has no host", url)),
+    };
+    let host = parsed.host_str().unwrap_or_default().trim_end_matches('.').to_ascii_lowercase();
+    if local {
+        return Err(format!("{} is a local or private address, which can't be fetched", host));
+    }
+    if !allowed.is_empty() && !allowed.iter().any(|domain| in_domain(&host, domain)) {
+        return Err(format!("{} is not in the allowed domains: {}", host, allowed.join(", ")));
+    }
+    Ok(parsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check() {
+        assert!(check("https://example.com/page", &[]).is_ok());
+        assert!(check("ftp://example.com/file", &[]).is_err());
+        assert!(check("not a url", &[]).is_err());
+        for local in [
+            "http://localhost:8080/",
+            "http://api.localhost/",
+            "http://printer.local/",
+            "http://127.0.0.1/",
+            "http://10.1.2.3/",
+            "http://192.168.0.1/",
+            "http://169.254.169.254/latest/meta-data",
+            "http://[::1]/",
+            "http://[fd00::1]/",
+            "http://[::ffff:10.0.0.1]/",
+        ] {
+            assert!(check(local, &[]).unwrap_err().contains("local or private"), "{}", local);
+        }
+    }
+
+    #[test]
+    fn test_allowlist() {
+        let allowed = parse_domains("example.com, *.docs.rs\nwikipedia.org.");
+        assert_eq!(allowed, vec!["example.com", "docs.rs", "wikipedia.org"]);
+        assert!(check("https://example.com/", &allowed).is_ok());
+        assert!(check("https://en.wikipedia.org/wiki/Rust", &allowed).is_ok());
+        assert!(check("https://serde.docs.rs/", &allowed).is_ok());
+        let error = check("https://notexample.com/", &allowed).unwrap_err();
+        assert!(error.contains("not in the allowed domains"));
+        assert!(parse_domains("  ,\n").is_empty());
+    }
+}
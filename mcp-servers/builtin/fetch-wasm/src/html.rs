@@ -0,0 +1,293 @@
+//! HTML to readable text: tags dropped, scripts and styles skipped, blocks
+//! on their own lines, list items bulleted, headings marked with `#` and
+//! links followed by their URL. Not a full HTML parser, but pages come out
+//! far shorter and easier for a model to read than their markup.
+
+/// Elements whose content isn't text for the reader.
+const SKIPPED: &[&str] = &["script", "style", "noscript", "template", "svg", "iframe"];
+
+/// Elements that start a new line.
+const LINES: &[&str] = &["br", "div", "li", "tr", "dt", "dd", "option", "figcaption"];
+
+/// Elements set off by a blank line.
+const PARAGRAPHS: &[&str] = &[
+    "p", "h1", "h2", "h3", "h4", "h5", "h6", "ul", "ol", "dl", "table", "pre", "blockquote", "hr",
+    "section", "article", "header", "footer", "nav", "main", "aside", "form", "figure", "title",
+];
+
+/// Whether a response is HTML, by its content type or, without one, by
+/// how it starts.
+pub fn is_html(content_type: &str, body: &str) -> bool {
+    if !content_type.is_empty() {
+        return content_type.to_ascii_lowercase().contains("html");
+    }
+    let start: String = body.trim_start().chars().take(15).collect();
+    let start = start.to_ascii_lowercase();
+    start.starts_with("<!doctype html") || start.starts_with("<html")
+}
+
+/// Text being written out, with whitespace collapsed as a browser would.
+#[derive(Default)]
+struct Text {
+    out: String,
+    /// Whitespace was seen since the last word
+    space: bool,
+}
+
+impl Text {
+    fn push(&mut self, text: &str, preformatted: bool) {
+        if preformatted {
+            self.out.push_str(text);
+            self.space = false;
+            return;
+        }
+        for c in text.chars() {
+            if c.is_whitespace() {
+                self.space = true;
+                continue;
+            }
+            if self.space && !self.out.is_empty() && !self.out.ends_with(&['\n', ' '][..]) {
+                self.out.push(' ');
+            }
+            self.space = false;
+            self.out.push(c);
+        }
+    }
+
+    /// End the current line, with `blank` lines after it, unless already at
+    /// the start of one.
+    fn break_line(&mut self, blank: bool) {
+        let trimmed = self.out.trim_end_matches(' ').len();
+        self.out.truncate(trimmed);
+        self.space = false;
+        if self.out.is_empty() {
+            return;
+        }
+        let wanted = if blank { "\n\n" } else { "\n" };
+        while !self.out.ends_with(wanted) {
+            self.out.push('\n');
+        }
+    }
+
+    fn finish(self) -> String {
+        let mut text = String::new();
+        let mut blank = 0;
+        for line in self.out.lines().map(str::trim_end) {
+            blank = if line.is_empty() { blank + 1 } else { 0 };
+            if blank < 2 {
+                text.push_str(line);
+                text.push('\n');
+            }
+        }
+        text.trim().to_string()
+    }
+}
+
+/// Where the tag starting at `<` in `html` ends (its `>`), skipping quoted
+/// attribute values.
+fn tag_end(html: &str) -> Option<usize> {
+    let mut quote = None;
+    for (i, c) in html.char_indices().skip(1) {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '>') => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// The value of attribute `name` in a tag's text.
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    let lower = tag.to_ascii_lowercase();
+    let mut from = 0;
+    while let Some(found) = lower[from..].find(name) {
+        let start = from + found;
+        from = start + name.len();
+        let before = lower[..start].chars().next_back();
+        if !before.is_some_and(char::is_whitespace) {
+            continue;
+        }
+        let rest = tag[from..].trim_start();
+        let Some(value) = rest.strip_prefix('=') else { continue };
+        let value = value.trim_start();
+        let value = match value.chars().next() {
+            Some(q @ ('"' | '\'')) => value[1..].split(q).next().unwrap_or_default(),
+            _ => value.split(|c: char| c.is_whitespace() || c == '/').next().unwrap_or_default(),
+        };
+        return Some(decode_entities(value));
+    }
+    None
+}
+
+/// Replace character references: the common named ones and numeric ones.
+pub fn decode_entities(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let decoded = rest[1..].find(';').filter(|&end| end <= 10).and_then(|end| {
+            let name = &rest[1..1 + end];
+            let c = match name {
+                "amp" => '&',
+                "lt" => '<',
+                "gt" => '>',
+                "quot" => '"',
+                "apos" => '\'',
+                "nbsp" => ' ',
+                "ndash" => '–',
+                "mdash" => '—',
+                "hellip" => '…',
+                "lsquo" => '‘',
+                "rsquo" => '’',
+                "ldquo" => '“',
+                "rdquo" => '”',
+                "copy" => '©',
+                _ => {
+                    let number = name.strip_prefix('#')?;
+                    let code = match number.strip_prefix(&['x', 'X'][..]) {
+                        Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                        None => number.parse().ok()?,
+                    };
+                    char::from_u32(code)?
+                }
+            };
+            Some((c, end + 2))
+        });
+        match decoded {
+            Some((c, len)) => {
+                out.push(c);
+                rest = &rest[len..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Convert an HTML document to text.
+pub fn to_text(html: &str) -> String {
+    let mut text = Text::default();
+    let mut rest = html;
+    let mut preformatted = 0usize;
+    let mut link: Option<String> = None;
+
+    while let Some(start) = rest.find('<') {
+        text.push(&decode_entities(&rest[..start]), preformatted > 0);
+        let tail = &rest[start..];
+        if let Some(comment) = tail.strip_prefix("<!--") {
+            rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+            continue;
+        }
+        // A '<' that starts no tag is text
+        let Some(end) = tag_end(tail) else {
+            text.push("<", preformatted > 0);
+            rest = &tail[1..];
+            continue;
+        };
+        let tag = &tail[1..end];
+        rest = &tail[end + 1..];
+
+        let closing = tag.starts_with('/');
+        let name = tag
+            .trim_start_matches('/')
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric())
+            .collect::<String>()
+            .to_ascii_lowercase();
+        // Skipped to their end tag: scripts may hold '<' and '>' of their own
+        if !closing && SKIPPED.contains(&name.as_str()) && !tag.ends_with('/') {
+            let end_tag = format!("</{}", name);
+            rest = match rest.to_ascii_lowercase().find(&end_tag) {
+                Some(at) => rest[at..].find('>').map_or("", |end| &rest[at + end + 1..]),
+                None => "",
+            };
+            continue;
+        }
+
+        match name.as_str() {
+            "a" if !closing => {
+                link = attribute(tag, "href").filter(|href| href.starts_with("http"));
+            }
+            "a" => {
+                if let Some(href) = link.take() {
+                    text.push(&format!(" ({})", href), false);
+                }
+            }
+            "li" if !closing => {
+                text.break_line(false);
+                text.push("- ", true);
+            }
+            "pre" => {
+                text.break_line(true);
+                preformatted = if closing {
+                    preformatted.saturating_sub(1)
+                } else {
+                    preformatted + 1
+                };
+            }
+            heading @ ("h1" | "h2" | "h3" | "h4" | "h5" | "h6") if !closing => {
+                text.break_line(true);
+                let level = heading[1..].parse().unwrap_or(1);
+                text.push(&format!("{} ", "#".repeat(level)), true);
+            }
+            name if PARAGRAPHS.contains(&name) => text.break_line(true),
+            name if LINES.contains(&name) => text.break_line(false),
+            _ => {}
+        }
+    }
+    text.push(&decode_entities(rest), preformatted > 0);
+    text.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_text() {
+        let html = r#"<!DOCTYPE html>
+<html><head><title>Rust &amp; WASM</title>
+<style>body { color: red; }</style>
+<script>if (a < b) { alert("<p>hi</p>"); }</script></head>
+<body>
+  <h1>Getting   started</h1>
+  <p>Read the <a href="https://doc.rust-lang.org/book/" class='x'>book</a>,
+     then <b>build</b> something.</p>
+  <!-- <p>hidden</p> -->
+  <ul><li>One</li><li>Two&nbsp;&#x2014;&#51;</li></ul>
+  <pre>fn main() {
+    println!("hi");
+}</pre>
+</body></html>"#;
+        assert_eq!(
+            to_text(html),
+            "Rust & WASM\n\n# Getting started\n\n\
+             Read the book (https://doc.rust-lang.org/book/), then build something.\n\n\
+             - One\n- Two —3\n\n\
+             fn main() {\n    println!(\"hi\");\n}"
+        );
+    }
+
+    #[test]
+    fn test_decode_entities() {
+        let decoded = decode_entities("a &lt;b&gt; &#39;c&#39; &unknown; & d");
+        assert_eq!(decoded, "a <b> 'c' &unknown; & d");
+        assert_eq!(decode_entities("&#xZZ;&"), "&#xZZ;&");
+    }
+
+    #[test]
+    fn test_is_html() {
+        assert!(is_html("text/html; charset=utf-8", ""));
+        assert!(!is_html("application/json", "<html>"));
+        assert!(is_html("", "  <!DOCTYPE html><html>"));
+        assert!(!is_html("", "{\"a\": 1}"));
+    }
+}
@@ -0,0 +1,218 @@
+//! Fetch MCP Server (WASM component)
+//!
+//! `http.get` and `http.post` tools, so an agent can read web pages and
+//! call APIs. Requests go through the host's `harbor:mcp/http` import, so
+//! the bridge makes them, and only to hosts the user approved. HTML comes
+//! back as text, bodies are cut to `max_chars`, and local and private
+//! addresses are refused.
+//!
+//! The manifest asks for any host. Users who want less can store a list
+//! of domains under the kv key `allowed-domains` (the `wasm.kv_set` bridge
+//! RPC), and only those domains and their subdomains are fetched.
+//!
+//! Build with:
+//!   cargo build --release --target wasm32-wasip2
+
+mod allow;
+mod html;
+
+use std::collections::BTreeMap;
+
+use harbor_mcp_sdk::{harbor_tool, Error, Server, ToolInput, ToolResult};
+use serde::Deserialize;
+
+wit_bindgen::generate!({ path: "../../wit", world: "mcp-server" });
+
+use harbor::mcp::{http, kv};
+
+/// Characters of the body returned unless the call asks for more.
+const DEFAULT_MAX_CHARS: usize = 20_000;
+const MAX_CHARS: usize = 200_000;
+const USER_AGENT: &str = "Harbor-Fetch/1.0 (+https://github.com/r/harbor)";
+/// kv key of the user's domain allowlist.
+const ALLOWED_DOMAINS_KEY: &str = "allowed-domains";
+
+#[derive(Deserialize, ToolInput)]
+#[serde(deny_unknown_fields)]
+struct GetArgs {
+    /// The http or https URL to fetch
+    url: String,
+    /// Extra request headers, by name
+    #[serde(default)]
+    headers: BTreeMap<String, String>,
+    /// Most characters of the body to return (default 20000)
+    max_chars: Option<usize>,
+    /// Return HTML as it is instead of converted to text
+    #[serde(default)]
+    raw: bool,
+}
+
+#[derive(Deserialize, ToolInput)]
+#[serde(deny_unknown_fields)]
+struct PostArgs {
+    /// The http or https URL to post to
+    url: String,
+    /// The request body
+    body: String,
+    /// Content type of the body (default application/json)
+    content_type: Option<String>,
+    /// Extra request headers, by name
+    #[serde(default)]
+    headers: BTreeMap<String, String>,
+    /// Most characters of the response body to return (default 20000)
+    max_chars: Option<usize>,
+    /// Return HTML as it is instead of converted to text
+    #[serde(default)]
+    raw: bool,
+}
+
+/// Fetch a URL with GET. Returns the status, content type and body; HTML
+/// pages come back as readable text.
+#[harbor_tool(name = "http.get")]
+fn get(args: GetArgs) -> Result<ToolResult, Error> {
+    let output = Output {
+        max_chars: args.max_chars,
+        raw: args.raw,
+    };
+    fetch("GET", &args.url, args.headers, None, output)
+}
+
+/// Send a POST request, JSON unless another content type is given.
+/// Returns the status, content type and response body.
+#[harbor_tool(name = "http.post")]
+fn post(args: PostArgs) -> Result<ToolResult, Error> {
+    let mut headers = args.headers;
+    if !headers.keys().any(|name| name.eq_ignore_ascii_case("content-type")) {
+        let content_type = args.content_type.unwrap_or_else(|| "application/json".to_string());
+        headers.insert("Content-Type".to_string(), content_type);
+    }
+    let output = Output {
+        max_chars: args.max_chars,
+        raw: args.raw,
+    };
+    fetch("POST", &args.url, headers, Some(args.body.into_bytes()), output)
+}
+
+/// How a response body is returned.
+struct Output {
+    max_chars: Option<usize>,
+    raw: bool,
+}
+
+/// The user's domain allowlist; empty for none.
+fn allowed_domains() -> Result<Vec<String>, Error> {
+    let stored = kv::get(ALLOWED_DOMAINS_KEY).map_err(Error::internal)?;
+    Ok(stored.map_or_else(Vec::new, |list| allow::parse_domains(&String::from_utf8_lossy(&list))))
+}
+
+fn header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+}
+
+/// Whether a body of `content_type` is text rather than, say, an image.
+fn is_text(content_type: &str) -> bool {
+    let content_type = content_type.to_ascii_lowercase();
+    content_type.starts_with("text/")
+        || ["json", "xml", "html", "javascript", "csv", "yaml"]
+            .iter()
+            .any(|kind| content_type.contains(kind))
+}
+
+/// The first `max` characters of `text`, and the total if it was cut.
+fn truncate(text: &str, max: usize) -> (&str, Option<usize>) {
+    match text.char_indices().nth(max) {
+        Some((end, _)) => (&text[..end], Some(text.chars().count())),
+        None => (text, None),
+    }
+}
+
+fn fetch(
+    method: &str,
+    url: &str,
+    headers: BTreeMap<String, String>,
+    body: Option<Vec<u8>>,
+    output: Output,
+) -> Result<ToolResult, Error> {
+    // Refusals and failed requests are results, for the model to act on
+    let url = match allow::check(url, &allowed_domains()?) {
+        Ok(url) => url,
+        Err(reason) => return Ok(ToolResult::error(reason)),
+    };
+    let mut headers: Vec<(String, String)> = headers.into_iter().collect();
+    if header(&headers, "user-agent").is_none() {
+        headers.push(("User-Agent".to_string(), USER_AGENT.to_string()));
+    }
+    let request = http::Request {
+        method: method.to_string(),
+        url: url.to_string(),
+        headers,
+        body,
+    };
+    let response = match http::fetch(&request) {
+        Ok(response) => response,
+        Err(e) => return Ok(ToolResult::error(e)),
+    };
+
+    let content_type = header(&response.headers, "content-type").unwrap_or_default();
+    let mut text = format!("HTTP {}\nContent-Type: {}\n", response.status, content_type);
+    if let Some(location) = header(&response.headers, "location") {
+        text.push_str(&format!("Location: {}\n", location));
+    }
+    text.push('\n');
+    let body = match std::str::from_utf8(&response.body) {
+        Ok(body) if !output.raw && html::is_html(content_type, body) => html::to_text(body),
+        Ok(body) => body.to_string(),
+        Err(_) if is_text(content_type) => String::from_utf8_lossy(&response.body).into_owned(),
+        Err(_) => format!("[{} bytes of binary content]", response.body.len()),
+    };
+    let max_chars = output.max_chars.unwrap_or(DEFAULT_MAX_CHARS).min(MAX_CHARS);
+    let (shown, total) = truncate(&body, max_chars);
+    text.push_str(shown);
+    if let Some(total) = total {
+        text.push_str(&format!(
+            "\n\n[Truncated: {} of {} characters shown; ask for more with max_chars]",
+            max_chars, total
+        ));
+    }
+    Ok(if response.status >= 400 {
+        ToolResult::error(text)
+    } else {
+        ToolResult::text(text)
+    })
+}
+
+fn server() -> Server {
+    Server::new("mcp-fetch", "1.0.0").register(get_tool()).register(post_tool())
+}
+
+struct Component;
+
+impl exports::harbor::mcp::server::Guest for Component {
+    fn handle(request: String) -> String {
+        server().handle(&request)
+    }
+}
+
+export!(Component);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truncate() {
+        assert_eq!(truncate("héllo", 10), ("héllo", None));
+        assert_eq!(truncate("héllo", 2), ("hé", Some(5)));
+    }
+
+    #[test]
+    fn test_is_text() {
+        assert!(is_text("application/json; charset=utf-8"));
+        assert!(is_text("text/plain"));
+        assert!(!is_text("image/png"));
+        assert!(!is_text(""));
+    }
+}
@@ -0,0 +1,90 @@
+//! Names and paths as they go into GitHub API URLs, and file contents as
+//! the contents API returns them.
+
+use harbor_mcp_sdk::encode_query;
+
+/// Check an owner or repository name: letters, digits, '-', '_' and '.',
+/// and not a path of its own.
+pub fn check_name(what: &str, name: &str) -> Result<(), String> {
+    let allowed = name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if name.is_empty() || !allowed || name == "." || name == ".." {
+        return Err(format!("'{}' isn't a GitHub {} name", name, what));
+    }
+    Ok(())
+}
+
+/// A path in a repository for the contents API: each segment
+/// percent-encoded, without leading or trailing slashes. Refuses `..` and
+/// empty segments, which would reach outside the contents endpoint.
+pub fn encode_path(path: &str) -> Result<String, String> {
+    let trimmed = path.trim_matches('/');
+    if trimmed.is_empty() {
+        return Ok(String::new());
+    }
+    let mut segments = Vec::new();
+    for segment in trimmed.split('/') {
+        if segment.is_empty() || segment == "." || segment == ".." {
+            return Err(format!("'{}' isn't a path in the repository", path));
+        }
+        segments.push(encode_query(segment));
+    }
+    Ok(segments.join("/"))
+}
+
+/// Decode base64 as the contents API returns files, wrapped every 60
+/// characters.
+pub fn decode_base64(text: &str) -> Result<Vec<u8>, String> {
+    let mut out = Vec::with_capacity(text.len() * 3 / 4);
+    let (mut n, mut bits) = (0u32, 0);
+    for c in text.chars().filter(|c| !c.is_whitespace() && *c != '=') {
+        let value = match c {
+            'A'..='Z' => c as u32 - 'A' as u32,
+            'a'..='z' => c as u32 - 'a' as u32 + 26,
+            '0'..='9' => c as u32 - '0' as u32 + 52,
+            '+' => 62,
+            '/' => 63,
+            c => return Err(format!("'{}' isn't base64", c)),
+        };
+        n = n << 6 | value;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((n >> bits) as u8);
+        }
+    }
+    if bits >= 6 {
+        return Err("The base64 data is cut short".to_string());
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_name() {
+        assert!(check_name("owner", "rust-lang").is_ok());
+        assert!(check_name("repository", "harbor.dev_2").is_ok());
+        assert!(check_name("repository", "").is_err());
+        assert!(check_name("repository", "..").is_err());
+        assert!(check_name("owner", "a/b").is_err());
+    }
+
+    #[test]
+    fn test_encode_path() {
+        assert_eq!(encode_path("/src/main.rs").unwrap(), "src/main.rs");
+        assert_eq!(encode_path("docs/Read me.md").unwrap(), "docs/Read%20me.md");
+        assert_eq!(encode_path("").unwrap(), "");
+        assert!(encode_path("src/../../secrets").is_err());
+        assert!(encode_path("src//main.rs").is_err());
+    }
+
+    #[test]
+    fn test_decode_base64() {
+        assert_eq!(decode_base64("aGVsbG8g\nd29ybGQ=\n").unwrap(), b"hello world");
+        assert_eq!(decode_base64("").unwrap(), b"");
+        assert!(decode_base64("aGVsbG8-").is_err());
+        assert!(decode_base64("aGVsb").is_err());
+    }
+}
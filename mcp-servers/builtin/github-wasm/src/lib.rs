@@ -0,0 +1,372 @@
+//! GitHub MCP Server (WASM component)
+//!
+//! Tools that search repositories and issues, read files, list pull
+//! requests and open issues over the GitHub REST API, as the GitHub
+//! account the user connected in Harbor; no personal access token needed.
+//! Tokens come from the bridge through the `harbor:mcp/oauth` import.
+//! Reading asks for no particular scope, so public repositories can be
+//! read whatever was granted; private ones, and creating issues, need
+//! `repo`.
+//!
+//! Build with:
+//!   cargo build --release --target wasm32-wasip2
+
+mod content;
+mod view;
+
+use harbor_mcp_sdk::{encode_query, harbor_tool, Api, Error, HttpResponse, Server, ToolInput, ToolResult};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use content::check_name;
+
+wit_bindgen::generate!({ path: "../../wit", world: "mcp-server" });
+
+use harbor::mcp::{http, oauth};
+
+const API: &str = "https://api.github.com";
+const REPO_SCOPE: &str = "repo";
+
+const DEFAULT_RESULTS: usize = 10;
+const DEFAULT_PULLS: usize = 20;
+const MAX_RESULTS: usize = 100;
+/// Characters of a file returned unless the call asks for more.
+const DEFAULT_MAX_CHARS: usize = 20_000;
+const MAX_CHARS: usize = 100_000;
+/// Largest file read; the contents API only returns files up to 1 MB.
+const MAX_READ_BYTES: u64 = 1024 * 1024;
+
+/// Qualifiers that say whether issue search finds issues or pull requests.
+const KIND_QUALIFIERS: [&str; 5] = ["is:issue", "is:pr", "is:pull-request", "type:issue", "type:pr"];
+
+#[derive(Deserialize, ToolInput)]
+#[serde(deny_unknown_fields)]
+struct SearchReposArgs {
+    /// GitHub search query, e.g. "wasm runtime language:rust" or
+    /// "user:octocat"
+    query: String,
+    /// "stars", "forks" or "updated" (default best match)
+    sort: Option<String>,
+    /// Most repositories to return (default 10, at most 100)
+    max_results: Option<usize>,
+}
+
+#[derive(Deserialize, ToolInput)]
+#[serde(deny_unknown_fields)]
+struct SearchIssuesArgs {
+    /// GitHub search query, e.g. "repo:octo/harbor is:open crash";
+    /// issues only unless it has is:pr
+    query: String,
+    /// "created", "updated" or "comments" (default best match)
+    sort: Option<String>,
+    /// Most results to return (default 10, at most 100)
+    max_results: Option<usize>,
+}
+
+#[derive(Deserialize, ToolInput)]
+#[serde(deny_unknown_fields)]
+struct ReadFileArgs {
+    /// The repository's owner, a user or organization
+    owner: String,
+    /// The repository's name
+    repo: String,
+    /// Path of the file in the repository, e.g. "src/main.rs"; a
+    /// directory, or "" for the top, lists its entries
+    #[serde(default)]
+    path: String,
+    /// Branch, tag or commit (default the default branch)
+    #[serde(rename = "ref")]
+    git_ref: Option<String>,
+    /// Most characters of the file to return (default 20000)
+    max_chars: Option<usize>,
+}
+
+#[derive(Deserialize, ToolInput)]
+#[serde(deny_unknown_fields)]
+struct ListPullsArgs {
+    /// The repository's owner, a user or organization
+    owner: String,
+    /// The repository's name
+    repo: String,
+    /// "open", "closed" or "all" (default open)
+    state: Option<String>,
+    /// Most pull requests to return, most recently updated first (default
+    /// 20, at most 100)
+    max_results: Option<usize>,
+}
+
+#[derive(Deserialize, ToolInput)]
+#[serde(deny_unknown_fields)]
+struct CreateIssueArgs {
+    /// The repository's owner, a user or organization
+    owner: String,
+    /// The repository's name
+    repo: String,
+    /// The issue's title
+    title: String,
+    /// The issue's description, in Markdown
+    body: Option<String>,
+    /// Names of labels to add; they must exist in the repository
+    #[serde(default)]
+    labels: Vec<String>,
+    /// Logins of users to assign
+    #[serde(default)]
+    assignees: Vec<String>,
+}
+
+/// The GitHub API, with the connected account's token.
+fn connect(scopes: &[&str]) -> Result<Api, String> {
+    let scopes: Vec<String> = scopes.iter().map(|s| s.to_string()).collect();
+    let token = oauth::access_token(&scopes)?;
+    Ok(Api::new("GitHub", "GitHub", token, fetch)
+        .base(API)
+        .header("Accept", "application/vnd.github+json")
+        .header("X-GitHub-Api-Version", "2022-11-28")
+        // GitHub refuses requests without one
+        .header("User-Agent", "harbor-mcp-github")
+        .message_at("/message")
+        .hint(hint))
+}
+
+/// What to do about a GitHub error, besides connecting the account again.
+fn hint(response: &HttpResponse) -> Option<&'static str> {
+    let limited = response
+        .headers
+        .iter()
+        .any(|(name, value)| name.eq_ignore_ascii_case("x-ratelimit-remaining") && value == "0");
+    match response.status {
+        403 | 429 if limited => Some("; the rate limit is used up, try again in a minute"),
+        404 => Some("; a private repository is only visible with the repo scope"),
+        _ => None,
+    }
+}
+
+harbor_mcp_sdk::http_fetch!();
+
+/// Check an option against the values an endpoint takes.
+fn check_choice(what: &str, given: &str, allowed: &[&str]) -> Result<(), String> {
+    if !allowed.contains(&given) {
+        return Err(format!("{} must be one of {}, not '{}'", what, allowed.join(", "), given));
+    }
+    Ok(())
+}
+
+/// The issue search query, limited to issues unless it says otherwise.
+fn issue_query(query: &str) -> String {
+    let query = query.trim();
+    let names_kind = query
+        .split_whitespace()
+        .any(|term| KIND_QUALIFIERS.iter().any(|q| term.eq_ignore_ascii_case(q)));
+    if names_kind {
+        query.to_string()
+    } else {
+        format!("{} is:issue", query)
+    }
+}
+
+fn search(path: &str, query: &str, sort: Option<&str>, max_results: Option<usize>) -> Result<Value, String> {
+    if query.trim().is_empty() {
+        return Err("Give a search query".to_string());
+    }
+    let per_page = max_results.unwrap_or(DEFAULT_RESULTS).clamp(1, MAX_RESULTS);
+    let mut url = format!("{}?q={}&per_page={}", path, encode_query(query.trim()), per_page);
+    if let Some(sort) = sort {
+        url.push_str(&format!("&sort={}", sort));
+    }
+    connect(&[REPO_SCOPE])?.json("GET", &url, None)
+}
+
+fn search_repositories(args: SearchReposArgs) -> Result<Value, String> {
+    if let Some(sort) = &args.sort {
+        check_choice("sort", sort, &["stars", "forks", "updated"])?;
+    }
+    let found = search("/search/repositories", &args.query, args.sort.as_deref(), args.max_results)?;
+    let repositories: Vec<Value> = found["items"].as_array().into_iter().flatten().map(view::repository).collect();
+    Ok(json!({ "total_count": found["total_count"], "repositories": repositories }))
+}
+
+fn search_issues(args: SearchIssuesArgs) -> Result<Value, String> {
+    if let Some(sort) = &args.sort {
+        check_choice("sort", sort, &["created", "updated", "comments"])?;
+    }
+    // Before is:issue is added, which would make an empty query match everything
+    if args.query.trim().is_empty() {
+        return Err("Give a search query".to_string());
+    }
+    let found = search("/search/issues", &issue_query(&args.query), args.sort.as_deref(), args.max_results)?;
+    let issues: Vec<Value> = found["items"].as_array().into_iter().flatten().map(view::issue).collect();
+    Ok(json!({ "total_count": found["total_count"], "issues": issues }))
+}
+
+fn read_file(args: ReadFileArgs) -> Result<Value, String> {
+    check_name("owner", &args.owner)?;
+    check_name("repository", &args.repo)?;
+    let mut url = format!("/repos/{}/{}/contents/{}", args.owner, args.repo, content::encode_path(&args.path)?);
+    if let Some(git_ref) = &args.git_ref {
+        url.push_str(&format!("?ref={}", encode_query(git_ref)));
+    }
+    let found = connect(&[REPO_SCOPE])?.json("GET", &url, None)?;
+    let repository = format!("{}/{}", args.owner, args.repo);
+    if let Some(entries) = found.as_array() {
+        return Ok(json!({
+            "repository": repository,
+            "path": args.path.trim_matches('/'),
+            "entries": entries.iter().map(view::entry).collect::<Vec<_>>(),
+        }));
+    }
+
+    let path = found["path"].as_str().unwrap_or_default();
+    match found["type"].as_str() {
+        Some("file") => {}
+        Some(kind) => return Err(format!("'{}' is a {}, not a file", path, kind)),
+        None => return Err("Unexpected answer from GitHub".to_string()),
+    }
+    let size = found["size"].as_u64().unwrap_or(0);
+    if size > MAX_READ_BYTES || found["encoding"] != "base64" {
+        return Err(format!(
+            "'{}' is {} KB; files over {} MB aren't read",
+            path,
+            size / 1024,
+            MAX_READ_BYTES / (1024 * 1024)
+        ));
+    }
+    let bytes = content::decode_base64(found["content"].as_str().unwrap_or_default())?;
+    let text = match String::from_utf8(bytes) {
+        Ok(text) if !text.contains('\0') => text,
+        _ => return Err(format!("'{}' isn't text and can't be shown", path)),
+    };
+
+    let mut shown = json!({
+        "repository": repository,
+        "path": path,
+        "size": size,
+        "sha": found["sha"],
+        "html_url": found["html_url"],
+    });
+    let max_chars = args.max_chars.unwrap_or(DEFAULT_MAX_CHARS).min(MAX_CHARS);
+    match text.char_indices().nth(max_chars) {
+        Some((end, _)) => {
+            shown["content"] = json!(&text[..end]);
+            shown["truncated"] = json!(true);
+            shown["content_chars"] = json!(text.chars().count());
+        }
+        None => shown["content"] = json!(text),
+    }
+    Ok(shown)
+}
+
+fn list_pulls(args: ListPullsArgs) -> Result<Value, String> {
+    check_name("owner", &args.owner)?;
+    check_name("repository", &args.repo)?;
+    let state = args.state.unwrap_or_else(|| "open".to_string());
+    check_choice("state", &state, &["open", "closed", "all"])?;
+    let per_page = args.max_results.unwrap_or(DEFAULT_PULLS).clamp(1, MAX_RESULTS);
+    let url = format!(
+        "/repos/{}/{}/pulls?state={}&sort=updated&direction=desc&per_page={}",
+        args.owner, args.repo, state, per_page
+    );
+    let pulls = connect(&[REPO_SCOPE])?.json("GET", &url, None)?;
+    let pulls: Vec<Value> = pulls.as_array().into_iter().flatten().map(view::pull_request).collect();
+    Ok(json!({ "repository": format!("{}/{}", args.owner, args.repo), "pull_requests": pulls }))
+}
+
+fn create_issue(args: CreateIssueArgs) -> Result<Value, String> {
+    check_name("owner", &args.owner)?;
+    check_name("repository", &args.repo)?;
+    if args.title.trim().is_empty() {
+        return Err("Give the issue a title".to_string());
+    }
+    let mut body = json!({ "title": args.title.trim() });
+    if let Some(text) = &args.body {
+        body["body"] = json!(text);
+    }
+    if !args.labels.is_empty() {
+        body["labels"] = json!(args.labels);
+    }
+    if !args.assignees.is_empty() {
+        body["assignees"] = json!(args.assignees);
+    }
+    let url = format!("/repos/{}/{}/issues", args.owner, args.repo);
+    let created = connect(&[REPO_SCOPE])?.json("POST", &url, Some(&body))?;
+    Ok(view::issue(&created))
+}
+
+fn reply(result: Result<Value, String>) -> Result<ToolResult, Error> {
+    Ok(match result {
+        Ok(value) => ToolResult::json(&value),
+        Err(e) => ToolResult::error(e),
+    })
+}
+
+/// Search GitHub repositories with GitHub's search syntax. Returns each
+/// repository's name, description, language, stars and link.
+#[harbor_tool(name = "github.searchRepositories")]
+fn search_repos(args: SearchReposArgs) -> Result<ToolResult, Error> {
+    reply(search_repositories(args))
+}
+
+/// Search GitHub issues, or pull requests with is:pr, with GitHub's
+/// search syntax, e.g. "repo:owner/name is:open label:bug".
+#[harbor_tool(name = "github.searchIssues")]
+fn find_issues(args: SearchIssuesArgs) -> Result<ToolResult, Error> {
+    reply(search_issues(args))
+}
+
+/// Read a file from a GitHub repository as text, or list a directory.
+/// Files must be text and at most 1 MB.
+#[harbor_tool(name = "github.readFile")]
+fn read(args: ReadFileArgs) -> Result<ToolResult, Error> {
+    reply(read_file(args))
+}
+
+/// List a repository's pull requests, most recently updated first, with
+/// their state, author and branches.
+#[harbor_tool(name = "github.listPullRequests")]
+fn pulls(args: ListPullsArgs) -> Result<ToolResult, Error> {
+    reply(list_pulls(args))
+}
+
+/// Open an issue in a GitHub repository as the connected account.
+/// Returns the new issue's number and link.
+#[harbor_tool(name = "github.createIssue")]
+fn open_issue(args: CreateIssueArgs) -> Result<ToolResult, Error> {
+    reply(create_issue(args))
+}
+
+fn server() -> Server {
+    Server::new("mcp-github", "1.0.0")
+        .register(search_repos_tool())
+        .register(find_issues_tool())
+        .register(read_tool())
+        .register(pulls_tool())
+        .register(open_issue_tool())
+}
+
+struct Component;
+
+impl exports::harbor::mcp::server::Guest for Component {
+    fn handle(request: String) -> String {
+        server().handle(&request)
+    }
+}
+
+export!(Component);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_issue_query() {
+        assert_eq!(issue_query(" repo:octo/harbor crash "), "repo:octo/harbor crash is:issue");
+        assert_eq!(issue_query("repo:octo/harbor is:PR is:open"), "repo:octo/harbor is:PR is:open");
+        assert_eq!(issue_query("type:issue author:ada"), "type:issue author:ada");
+    }
+
+    #[test]
+    fn test_check_choice() {
+        assert!(check_choice("state", "all", &["open", "closed", "all"]).is_ok());
+        let error = check_choice("state", "merged", &["open", "closed", "all"]).unwrap_err();
+        assert_eq!(error, "state must be one of open, closed, all, not 'merged'");
+    }
+}
@@ -0,0 +1,133 @@
+//! GitHub API objects as the tools show them: the fields worth reading,
+//! under plain names.
+
+use serde_json::{json, Value};
+
+/// Characters of an issue's body shown in search results.
+const MAX_BODY_CHARS: usize = 500;
+
+fn cut(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((end, _)) => format!("{}...", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+pub fn repository(repo: &Value) -> Value {
+    json!({
+        "full_name": repo["full_name"],
+        "description": repo["description"],
+        "html_url": repo["html_url"],
+        "private": repo["private"],
+        "language": repo["language"],
+        "stars": repo["stargazers_count"],
+        "forks": repo["forks_count"],
+        "open_issues": repo["open_issues_count"],
+        "default_branch": repo["default_branch"],
+        "updated_at": repo["updated_at"],
+    })
+}
+
+fn labels(item: &Value) -> Value {
+    item["labels"].as_array().into_iter().flatten().map(|label| label["name"].clone()).collect()
+}
+
+/// An issue or pull request from issue search, or a created issue.
+pub fn issue(item: &Value) -> Value {
+    // https://api.github.com/repos/{owner}/{repo}
+    let repository = item["repository_url"]
+        .as_str()
+        .and_then(|url| url.split("/repos/").nth(1))
+        .map_or(Value::Null, |name| json!(name));
+    let mut shown = json!({
+        "repository": repository,
+        "number": item["number"],
+        "title": item["title"],
+        "state": item["state"],
+        "pull_request": item.get("pull_request").is_some(),
+        "author": item["user"]["login"],
+        "labels": labels(item),
+        "comments": item["comments"],
+        "created_at": item["created_at"],
+        "updated_at": item["updated_at"],
+        "html_url": item["html_url"],
+    });
+    if let Some(body) = item["body"].as_str().filter(|body| !body.is_empty()) {
+        shown["body"] = json!(cut(body, MAX_BODY_CHARS));
+    }
+    shown
+}
+
+pub fn pull_request(pull: &Value) -> Value {
+    json!({
+        "number": pull["number"],
+        "title": pull["title"],
+        "state": pull["state"],
+        "draft": pull["draft"],
+        "merged": pull["merged_at"].is_string(),
+        "author": pull["user"]["login"],
+        "head": pull["head"]["label"],
+        "base": pull["base"]["ref"],
+        "labels": labels(pull),
+        "created_at": pull["created_at"],
+        "updated_at": pull["updated_at"],
+        "html_url": pull["html_url"],
+    })
+}
+
+/// An entry of a directory listing.
+pub fn entry(entry: &Value) -> Value {
+    json!({
+        "name": entry["name"],
+        "path": entry["path"],
+        "type": entry["type"],
+        "size": entry["size"],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_issue() {
+        let item = json!({
+            "repository_url": "https://api.github.com/repos/octo/harbor",
+            "number": 42,
+            "title": "Crash on start",
+            "state": "open",
+            "user": { "login": "ada" },
+            "labels": [{ "name": "bug" }],
+            "comments": 3,
+            "body": "x".repeat(600),
+            "html_url": "https://github.com/octo/harbor/issues/42"
+        });
+        let shown = issue(&item);
+        assert_eq!(shown["repository"], "octo/harbor");
+        assert_eq!(shown["pull_request"], false);
+        assert_eq!(shown["labels"], json!(["bug"]));
+        assert_eq!(shown["body"].as_str().unwrap().len(), 503);
+
+        let pull = issue(&json!({ "pull_request": { "url": "..." }, "body": "" }));
+        assert_eq!(pull["pull_request"], true);
+        assert!(pull.get("body").is_none());
+    }
+
+    #[test]
+    fn test_pull_request() {
+        let pull = json!({
+            "number": 7,
+            "state": "closed",
+            "draft": false,
+            "merged_at": "2024-06-03T09:00:00Z",
+            "user": { "login": "grace" },
+            "head": { "label": "grace:fix-crash", "ref": "fix-crash" },
+            "base": { "label": "octo:main", "ref": "main" }
+        });
+        let shown = pull_request(&pull);
+        assert_eq!(shown["merged"], true);
+        assert_eq!(shown["head"], "grace:fix-crash");
+        assert_eq!(shown["base"], "main");
+        assert_eq!(shown["labels"], json!([]));
+    }
+}
@@ -0,0 +1,195 @@
+//! Gmail MCP Server (WASM component)
+//!
+//! `gmail.search`, `gmail.read` and `gmail.send` tools over the Gmail API,
+//! for the Google account the user connected in Harbor. The server holds no
+//! credentials: before each call it asks the bridge for an access token
+//! through the `harbor:mcp/oauth` import, which refreshes it as needed and
+//! only hands it out for scopes the user granted, so reading needs
+//! `gmail.readonly` and sending needs `gmail.send`. Requests go through the
+//! `harbor:mcp/http` import to gmail.googleapis.com only.
+//!
+//! Build with:
+//!   cargo build --release --target wasm32-wasip2
+
+mod message;
+mod mime;
+
+use harbor_mcp_sdk::{encode_query, harbor_tool, Api, Error, Server, ToolInput, ToolResult};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+wit_bindgen::generate!({ path: "../../wit", world: "mcp-server" });
+
+use harbor::mcp::{http, oauth};
+
+const API: &str = "https://gmail.googleapis.com/gmail/v1/users/me";
+const READONLY_SCOPE: &str = "https://www.googleapis.com/auth/gmail.readonly";
+const SEND_SCOPE: &str = "https://www.googleapis.com/auth/gmail.send";
+
+const DEFAULT_RESULTS: usize = 10;
+const MAX_RESULTS: usize = 50;
+/// Characters of a body returned unless the call asks for more.
+const DEFAULT_MAX_CHARS: usize = 20_000;
+const MAX_CHARS: usize = 100_000;
+
+#[derive(Deserialize, ToolInput)]
+#[serde(deny_unknown_fields)]
+struct SearchArgs {
+    /// Gmail search syntax, as in the Gmail search box, e.g.
+    /// "from:ada@example.com is:unread newer_than:7d"
+    query: String,
+    /// Most messages to return (default 10, at most 50)
+    max_results: Option<usize>,
+    /// next_page_token from a previous search, for the next page
+    page_token: Option<String>,
+}
+
+#[derive(Deserialize, ToolInput)]
+#[serde(deny_unknown_fields)]
+struct ReadArgs {
+    /// The message's id, from gmail.search
+    id: String,
+    /// Most characters of the body to return (default 20000)
+    max_chars: Option<usize>,
+}
+
+#[derive(Deserialize, ToolInput)]
+#[serde(deny_unknown_fields)]
+struct SendArgs {
+    /// Recipients, e.g. "ada@example.com" or "Ada <ada@example.com>"
+    to: Vec<String>,
+    /// The subject line
+    subject: String,
+    /// The message, as plain text
+    body: String,
+    /// Recipients to copy
+    #[serde(default)]
+    cc: Vec<String>,
+    /// Recipients to copy without the others seeing
+    #[serde(default)]
+    bcc: Vec<String>,
+}
+
+/// The Gmail API, with a token for one scope.
+fn connect(scope: &str) -> Result<Api, String> {
+    let token = oauth::access_token(&[scope.to_string()])?;
+    Ok(Api::new("Gmail", "Google", token, fetch)
+        .base(API)
+        .header("Accept", "application/json"))
+}
+
+harbor_mcp_sdk::http_fetch!();
+
+/// Message ids are hex; refuse anything that could change the request path.
+fn check_id(id: &str) -> Result<(), String> {
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(format!("'{}' isn't a Gmail message id", id));
+    }
+    Ok(())
+}
+
+fn search_messages(args: SearchArgs) -> Result<Value, String> {
+    let gmail = connect(READONLY_SCOPE)?;
+    let max_results = args.max_results.unwrap_or(DEFAULT_RESULTS).clamp(1, MAX_RESULTS);
+    let mut path = format!("/messages?q={}&maxResults={}", encode_query(&args.query), max_results);
+    if let Some(page_token) = &args.page_token {
+        path.push_str(&format!("&pageToken={}", encode_query(page_token)));
+    }
+    let listed = gmail.json("GET", &path, None)?;
+    let mut messages = Vec::new();
+    for id in listed["messages"].as_array().into_iter().flatten().filter_map(|m| m["id"].as_str()) {
+        check_id(id)?;
+        let path = format!(
+            "/messages/{}?format=metadata&metadataHeaders=From&metadataHeaders=To\
+             &metadataHeaders=Subject&metadataHeaders=Date",
+            id
+        );
+        messages.push(message::summary(&gmail.json("GET", &path, None)?));
+    }
+    Ok(json!({
+        "messages": messages,
+        "next_page_token": listed["nextPageToken"],
+        "result_size_estimate": listed["resultSizeEstimate"],
+    }))
+}
+
+fn read_message(args: ReadArgs) -> Result<Value, String> {
+    check_id(&args.id)?;
+    let gmail = connect(READONLY_SCOPE)?;
+    let found = gmail.json("GET", &format!("/messages/{}?format=full", args.id), None)?;
+    let max_chars = args.max_chars.unwrap_or(DEFAULT_MAX_CHARS).min(MAX_CHARS);
+    Ok(message::full(&found, max_chars))
+}
+
+fn send_message(args: SendArgs) -> Result<Value, String> {
+    let raw = mime::build_message(&args.to, &args.cc, &args.bcc, &args.subject, &args.body)?;
+    let gmail = connect(SEND_SCOPE)?;
+    let sent = gmail.json(
+        "POST",
+        "/messages/send",
+        Some(&json!({ "raw": mime::encode_base64url(raw.as_bytes()) })),
+    )?;
+    Ok(json!({
+        "id": sent["id"],
+        "thread_id": sent["threadId"],
+        "labels": sent["labelIds"],
+    }))
+}
+
+fn reply(result: Result<Value, String>) -> Result<ToolResult, Error> {
+    Ok(match result {
+        Ok(value) => ToolResult::json(&value),
+        Err(e) => ToolResult::error(e),
+    })
+}
+
+/// Search the user's Gmail with Gmail search syntax. Returns each
+/// message's id, sender, recipients, subject, date and a snippet, newest
+/// first; read one in full with gmail.read.
+#[harbor_tool(name = "gmail.search")]
+fn search(args: SearchArgs) -> Result<ToolResult, Error> {
+    reply(search_messages(args))
+}
+
+/// Read a Gmail message: its headers, its body as text and the names of
+/// any attachments.
+#[harbor_tool(name = "gmail.read")]
+fn read(args: ReadArgs) -> Result<ToolResult, Error> {
+    reply(read_message(args))
+}
+
+/// Send a plain text email from the user's Gmail account. Confirm the
+/// recipients and text with the user first; sent mail can't be recalled.
+#[harbor_tool(name = "gmail.send")]
+fn send(args: SendArgs) -> Result<ToolResult, Error> {
+    reply(send_message(args))
+}
+
+fn server() -> Server {
+    Server::new("mcp-gmail", "1.0.0")
+        .register(search_tool())
+        .register(read_tool())
+        .register(send_tool())
+}
+
+struct Component;
+
+impl exports::harbor::mcp::server::Guest for Component {
+    fn handle(request: String) -> String {
+        server().handle(&request)
+    }
+}
+
+export!(Component);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_id() {
+        assert!(check_id("18f1c2a9e41d7b38").is_ok());
+        assert!(check_id("").is_err());
+        assert!(check_id("../labels").is_err());
+    }
+}
@@ -0,0 +1,156 @@
+//! Gmail API messages as the tools show them.
+//!
+//! The API returns a message as a tree of MIME parts with base64url
+//! bodies and headers as name/value pairs. Tools show the headers people
+//! read, the plain text body (or the HTML one as text) and the names of
+//! attachments.
+
+use serde_json::{json, Value};
+
+use crate::mime;
+
+/// The value of the first header called `name`.
+fn header<'a>(payload: &'a Value, name: &str) -> Option<&'a str> {
+    payload["headers"]
+        .as_array()?
+        .iter()
+        .find(|h| h["name"].as_str().is_some_and(|n| n.eq_ignore_ascii_case(name)))
+        .and_then(|h| h["value"].as_str())
+}
+
+/// A message listed by `gmail.search`: who, what and when, and Gmail's
+/// snippet of the body.
+pub fn summary(message: &Value) -> Value {
+    let payload = &message["payload"];
+    json!({
+        "id": message["id"],
+        "thread_id": message["threadId"],
+        "from": header(payload, "From"),
+        "to": header(payload, "To"),
+        "subject": header(payload, "Subject"),
+        "date": header(payload, "Date"),
+        "snippet": message["snippet"],
+        "labels": message["labelIds"],
+    })
+}
+
+/// The decoded data of a part's body, if it carries it inline.
+fn part_text(part: &Value) -> Option<String> {
+    let data = part["body"]["data"].as_str()?;
+    let bytes = mime::decode_base64url(data).ok()?;
+    Some(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// Walk the part tree for the first text/plain and text/html bodies and
+/// the attachments.
+fn walk(part: &Value, plain: &mut Option<String>, html: &mut Option<String>, attachments: &mut Vec<Value>) {
+    let mime_type = part["mimeType"].as_str().unwrap_or_default();
+    let filename = part["filename"].as_str().unwrap_or_default();
+    if !filename.is_empty() {
+        attachments.push(json!({
+            "filename": filename,
+            "mime_type": mime_type,
+            "size": part["body"]["size"],
+        }));
+    } else if mime_type.eq_ignore_ascii_case("text/plain") && plain.is_none() {
+        *plain = part_text(part);
+    } else if mime_type.eq_ignore_ascii_case("text/html") && html.is_none() {
+        *html = part_text(part);
+    }
+    for child in part["parts"].as_array().into_iter().flatten() {
+        walk(child, plain, html, attachments);
+    }
+}
+
+/// The body as text: the plain text part, or else the HTML part converted.
+pub fn body(payload: &Value) -> (String, Vec<Value>) {
+    let (mut plain, mut html, mut attachments) = (None, None, Vec::new());
+    walk(payload, &mut plain, &mut html, &mut attachments);
+    let text = match (plain, html) {
+        (Some(plain), _) => plain.replace("\r\n", "\n"),
+        (None, Some(html)) => mime::html_to_text(&html),
+        (None, None) => String::new(),
+    };
+    (text, attachments)
+}
+
+/// A message read in full by `gmail.read`, with its body cut to
+/// `max_chars` characters.
+pub fn full(message: &Value, max_chars: usize) -> Value {
+    let payload = &message["payload"];
+    let (text, attachments) = body(payload);
+    let mut shown = summary(message);
+    shown["cc"] = json!(header(payload, "Cc"));
+    match text.char_indices().nth(max_chars) {
+        Some((end, _)) => {
+            shown["body"] = json!(&text[..end]);
+            shown["truncated"] = json!(true);
+            shown["body_chars"] = json!(text.chars().count());
+        }
+        None => shown["body"] = json!(text),
+    }
+    if !attachments.is_empty() {
+        shown["attachments"] = json!(attachments);
+    }
+    shown
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message() -> Value {
+        json!({
+            "id": "18f1c2",
+            "threadId": "18f1c0",
+            "labelIds": ["INBOX", "UNREAD"],
+            "snippet": "Lunch on Friday?",
+            "payload": {
+                "mimeType": "multipart/mixed",
+                "headers": [
+                    { "name": "From", "value": "Ada <ada@example.com>" },
+                    { "name": "To", "value": "grace@example.com" },
+                    { "name": "Subject", "value": "Lunch" },
+                    { "name": "Date", "value": "Mon, 3 Jun 2024 09:00:00 +0000" }
+                ],
+                "parts": [
+                    {
+                        "mimeType": "multipart/alternative",
+                        "parts": [
+                            { "mimeType": "text/plain", "body": { "data": "THVuY2ggb24gRnJpZGF5Pw0K" } },
+                            { "mimeType": "text/html", "body": { "data": "PHA-THVuY2g8L3A-" } }
+                        ]
+                    },
+                    { "mimeType": "application/pdf", "filename": "menu.pdf",
+                      "body": { "attachmentId": "a1", "size": 5120 } }
+                ]
+            }
+        })
+    }
+
+    #[test]
+    fn test_summary() {
+        let shown = summary(&message());
+        assert_eq!(shown["from"], "Ada <ada@example.com>");
+        assert_eq!(shown["subject"], "Lunch");
+        assert_eq!(shown["thread_id"], "18f1c0");
+        assert_eq!(shown["labels"], json!(["INBOX", "UNREAD"]));
+    }
+
+    #[test]
+    fn test_full() {
+        let shown = full(&message(), 100);
+        assert_eq!(shown["body"], "Lunch on Friday?\n");
+        assert_eq!(shown["cc"], Value::Null);
+        assert_eq!(shown["attachments"][0]["filename"], "menu.pdf");
+        assert!(shown.get("truncated").is_none());
+
+        let cut = full(&message(), 5);
+        assert_eq!(cut["body"], "Lunch");
+        assert_eq!(cut["body_chars"], 17);
+
+        let mut html_only = message();
+        html_only["payload"]["parts"][0]["parts"].as_array_mut().unwrap().remove(0);
+        assert_eq!(full(&html_only, 100)["body"], "Lunch");
+    }
+}
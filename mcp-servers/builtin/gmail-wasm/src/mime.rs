@@ -0,0 +1,210 @@
+//! The parts of email the tools handle themselves: Gmail's URL-safe
+//! base64, plain text messages to send, and HTML bodies as text.
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Body lines are wrapped at this many base64 characters (RFC 2045).
+const LINE_LENGTH: usize = 76;
+
+fn base64(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk.iter().fold(0u32, |n, &b| n << 8 | u32::from(b)) << (8 * (3 - chunk.len()));
+        for i in 0..=chunk.len() {
+            out.push(BASE64[(n >> (18 - 6 * i) & 63) as usize] as char);
+        }
+        for _ in chunk.len()..3 {
+            out.push('=');
+        }
+    }
+    out
+}
+
+/// URL-safe base64 without padding, as Gmail takes `raw` messages.
+pub fn encode_base64url(bytes: &[u8]) -> String {
+    base64(bytes)
+        .trim_end_matches('=')
+        .chars()
+        .map(|c| match c {
+            '+' => '-',
+            '/' => '_',
+            c => c,
+        })
+        .collect()
+}
+
+/// Decode base64 in either alphabet, padded or not, as Gmail returns body
+/// data.
+pub fn decode_base64url(text: &str) -> Result<Vec<u8>, String> {
+    let mut out = Vec::with_capacity(text.len() * 3 / 4);
+    let (mut n, mut bits) = (0u32, 0);
+    for c in text.chars().filter(|c| !c.is_whitespace() && *c != '=') {
+        let value = match c {
+            'A'..='Z' => c as u32 - 'A' as u32,
+            'a'..='z' => c as u32 - 'a' as u32 + 26,
+            '0'..='9' => c as u32 - '0' as u32 + 52,
+            '+' | '-' => 62,
+            '/' | '_' => 63,
+            c => return Err(format!("'{}' isn't base64", c)),
+        };
+        n = n << 6 | value;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((n >> bits) as u8);
+        }
+    }
+    if bits >= 6 {
+        return Err("The base64 data is cut short".to_string());
+    }
+    Ok(out)
+}
+
+/// A header value as is if it's ASCII, otherwise as an RFC 2047 encoded
+/// word.
+fn header_text(text: &str) -> String {
+    if text.is_ascii() {
+        text.to_string()
+    } else {
+        format!("=?UTF-8?B?{}?=", base64(text.as_bytes()))
+    }
+}
+
+/// Refuse header values that would end the header early.
+fn check_header(name: &str, value: &str) -> Result<(), String> {
+    if value.contains(['\r', '\n']) {
+        return Err(format!("{} may not contain line breaks", name));
+    }
+    Ok(())
+}
+
+/// Check a list of addresses, e.g. "Ada <ada@example.com>".
+fn check_addresses(name: &str, addresses: &[String]) -> Result<(), String> {
+    for address in addresses {
+        check_header(name, address)?;
+        if !address.contains('@') {
+            return Err(format!("'{}' in {} isn't an email address", address, name));
+        }
+    }
+    Ok(())
+}
+
+/// A plain text message, as RFC 2822 text for Gmail's `messages.send`.
+/// Gmail fills in From, Date and Message-ID. Bcc recipients are taken
+/// from the header and dropped from what the others receive.
+pub fn build_message(
+    to: &[String],
+    cc: &[String],
+    bcc: &[String],
+    subject: &str,
+    body: &str,
+) -> Result<String, String> {
+    if to.is_empty() {
+        return Err("Give at least one recipient in 'to'".to_string());
+    }
+    check_addresses("to", to)?;
+    check_addresses("cc", cc)?;
+    check_addresses("bcc", bcc)?;
+    check_header("The subject", subject)?;
+
+    let mut message = format!("To: {}\r\n", to.join(", "));
+    if !cc.is_empty() {
+        message.push_str(&format!("Cc: {}\r\n", cc.join(", ")));
+    }
+    if !bcc.is_empty() {
+        message.push_str(&format!("Bcc: {}\r\n", bcc.join(", ")));
+    }
+    message.push_str(&format!("Subject: {}\r\n", header_text(subject)));
+    message.push_str("MIME-Version: 1.0\r\n");
+    message.push_str("Content-Type: text/plain; charset=UTF-8\r\n");
+    message.push_str("Content-Transfer-Encoding: base64\r\n\r\n");
+    let encoded = base64(body.replace("\r\n", "\n").replace('\n', "\r\n").as_bytes());
+    for line in encoded.as_bytes().chunks(LINE_LENGTH) {
+        message.push_str(std::str::from_utf8(line).expect("base64 is ASCII"));
+        message.push_str("\r\n");
+    }
+    Ok(message)
+}
+
+/// Readable text from an HTML body: tags dropped, block ends as line
+/// breaks, the common entities decoded and runs of blank lines collapsed.
+pub fn html_to_text(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        text.push_str(&rest[..start]);
+        let end = rest[start..].find('>').map_or(rest.len(), |end| start + end + 1);
+        let tag = rest[start + 1..end].trim_start_matches('/').to_ascii_lowercase();
+        let name: String = tag.chars().take_while(|c| c.is_ascii_alphanumeric()).collect();
+        if name == "style" || name == "script" {
+            // Skip to the closing tag, contents and all
+            let close = format!("</{}", name);
+            let skip = rest[end..].to_ascii_lowercase().find(&close).map_or(rest.len(), |i| end + i);
+            rest = &rest[skip..];
+            if let Some(gt) = rest.find('>') {
+                rest = &rest[gt + 1..];
+            }
+            continue;
+        }
+        if ["br", "p", "div", "tr", "li", "h1", "h2", "h3", "h4", "h5", "h6"].contains(&name.as_str()) {
+            text.push('\n');
+        }
+        rest = &rest[end..];
+    }
+    text.push_str(rest);
+
+    let text = text
+        .replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&");
+    let mut lines: Vec<&str> = Vec::new();
+    for line in text.lines().map(str::trim) {
+        if !line.is_empty() || lines.last().is_some_and(|last| !last.is_empty()) {
+            lines.push(line);
+        }
+    }
+    lines.join("\n").trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base64url() {
+        for (text, encoded) in [("", ""), ("f", "Zg"), ("fo", "Zm8"), ("foo", "Zm9v"), ("foob", "Zm9vYg")] {
+            assert_eq!(encode_base64url(text.as_bytes()), encoded);
+            assert_eq!(decode_base64url(encoded).unwrap(), text.as_bytes());
+        }
+        assert_eq!(encode_base64url(&[0xfb, 0xff]), "-_8");
+        assert_eq!(decode_base64url("-_8=").unwrap(), vec![0xfb, 0xff]);
+        assert_eq!(decode_base64url("+/8").unwrap(), vec![0xfb, 0xff]);
+        assert!(decode_base64url("Zm9v!").is_err());
+        assert!(decode_base64url("Z").is_err());
+    }
+
+    #[test]
+    fn test_build_message() {
+        let to = vec!["Ada <ada@example.com>".to_string()];
+        let cc = vec!["grace@example.com".to_string()];
+        let message = build_message(&to, &cc, &[], "Café at 3?", "Hi Ada,\nsee you there.").unwrap();
+        assert!(message.starts_with("To: Ada <ada@example.com>\r\nCc: grace@example.com\r\n"));
+        assert!(message.contains("Subject: =?UTF-8?B?Q2Fmw6kgYXQgMz8=?=\r\n"));
+        assert!(message.ends_with("\r\n\r\nSGkgQWRhLA0Kc2VlIHlvdSB0aGVyZS4=\r\n"));
+
+        assert!(build_message(&[], &[], &[], "Hi", "").is_err());
+        let injected = build_message(&to, &[], &[], "Hi\r\nBcc: eve@example.com", "");
+        assert!(injected.unwrap_err().contains("line breaks"));
+        assert!(build_message(&["ada".to_string()], &[], &[], "Hi", "").is_err());
+    }
+
+    #[test]
+    fn test_html_to_text() {
+        let html = "<html><head><style>p { color: red }</style></head><body>\
+                    <p>Hello&nbsp;<b>Ada</b>,</p><p>Fish &amp; chips?</p><br><br><div>Bye</div></body></html>";
+        assert_eq!(html_to_text(html), "Hello Ada,\n\nFish & chips?\n\nBye");
+    }
+}
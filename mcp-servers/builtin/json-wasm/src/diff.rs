@@ -0,0 +1,136 @@
+//! Structural differences between two JSON documents, as JSON Patch
+//! (RFC 6902) style operations: `add`, `remove` and `replace`, each with
+//! the JSON Pointer it applies to and the values before and after.
+//! Objects are compared key by key and arrays position by position, so an
+//! element inserted at the front of an array shows as every later element
+//! replaced, plus one added at the end.
+
+use serde_json::{json, Map, Value};
+
+/// Collects changes, keeping at most `limit` but counting all of them.
+pub struct Diff {
+    pub changes: Vec<Value>,
+    pub total: usize,
+    limit: usize,
+}
+
+impl Diff {
+    fn push(&mut self, change: Value) {
+        self.total += 1;
+        if self.changes.len() < self.limit {
+            self.changes.push(change);
+        }
+    }
+}
+
+/// The changes that turn `before` into `after`.
+pub fn diff(before: &Value, after: &Value, limit: usize) -> Diff {
+    let mut found = Diff {
+        changes: Vec::new(),
+        total: 0,
+        limit,
+    };
+    compare(before, after, &mut String::new(), &mut found);
+    found
+}
+
+/// `token` escaped for a JSON Pointer.
+fn escape(token: &str) -> String {
+    token.replace('~', "~0").replace('/', "~1")
+}
+
+fn compare(before: &Value, after: &Value, path: &mut String, found: &mut Diff) {
+    match (before, after) {
+        (Value::Object(a), Value::Object(b)) => compare_objects(a, b, path, found),
+        (Value::Array(a), Value::Array(b)) => {
+            let common = a.len().min(b.len());
+            for index in 0..common {
+                within(path, &index.to_string(), |path| compare(&a[index], &b[index], path, found));
+            }
+            for (index, value) in b.iter().enumerate().skip(common) {
+                let path = format!("{}/{}", path, index);
+                found.push(json!({ "op": "add", "path": path, "value": value }));
+            }
+            // From the end, so each index is still right when it's applied
+            for (index, value) in a.iter().enumerate().skip(common).rev() {
+                let path = format!("{}/{}", path, index);
+                found.push(json!({ "op": "remove", "path": path, "old": value }));
+            }
+        }
+        (a, b) if a == b => {}
+        (a, b) => {
+            found.push(json!({ "op": "replace", "path": path.clone(), "old": a, "value": b }));
+        }
+    }
+}
+
+fn compare_objects(
+    a: &Map<String, Value>,
+    b: &Map<String, Value>,
+    path: &mut String,
+    found: &mut Diff,
+) {
+    for (key, old) in a {
+        match b.get(key) {
+            Some(new) => within(path, &escape(key), |path| compare(old, new, path, found)),
+            None => {
+                let path = format!("{}/{}", path, escape(key));
+                found.push(json!({ "op": "remove", "path": path, "old": old }));
+            }
+        }
+    }
+    for (key, new) in b {
+        if !a.contains_key(key) {
+            let path = format!("{}/{}", path, escape(key));
+            found.push(json!({ "op": "add", "path": path, "value": new }));
+        }
+    }
+}
+
+/// Run `f` with `token` appended to `path`.
+fn within(path: &mut String, token: &str, f: impl FnOnce(&mut String)) {
+    let length = path.len();
+    path.push('/');
+    path.push_str(token);
+    f(path);
+    path.truncate(length);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_equal() {
+        let value = json!({ "a": [1, 2, { "b": null }] });
+        assert_eq!(diff(&value, &value.clone(), 10).total, 0);
+    }
+
+    #[test]
+    fn test_changes() {
+        let before = json!({ "name": "a", "tags": ["x", "y", "z"], "a/b": 1, "gone": true });
+        let after = json!({ "name": "b", "tags": ["x"], "a/b": 2, "new": { "n": 1 } });
+        let found = diff(&before, &after, 10);
+        assert_eq!(
+            found.changes,
+            vec![
+                json!({ "op": "replace", "path": "/a~1b", "old": 1, "value": 2 }),
+                json!({ "op": "remove", "path": "/gone", "old": true }),
+                json!({ "op": "replace", "path": "/name", "old": "a", "value": "b" }),
+                json!({ "op": "remove", "path": "/tags/2", "old": "z" }),
+                json!({ "op": "remove", "path": "/tags/1", "old": "y" }),
+                json!({ "op": "add", "path": "/new", "value": { "n": 1 } }),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_type_change_and_limit() {
+        let found = diff(&json!({ "a": [1] }), &json!({ "a": { "0": 1 } }), 10);
+        assert_eq!(found.changes[0]["op"], "replace");
+        assert_eq!(found.changes[0]["path"], "/a");
+
+        let found = diff(&json!([1, 2, 3]), &json!([4, 5, 6]), 2);
+        assert_eq!((found.changes.len(), found.total), (2, 3));
+    }
+}
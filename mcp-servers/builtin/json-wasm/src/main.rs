@@ -0,0 +1,196 @@
+//! JSON MCP Server (WASM)
+//!
+//! Lets agents work with large JSON without reading all of it:
+//! `json.query` picks values out with JSONPath or a jq-style path,
+//! `json.validate` checks a document against a JSON Schema and
+//! `json.diff` lists what changed between two documents.
+//!
+//! Build with:
+//!   cargo build --release --target wasm32-wasip1
+
+mod diff;
+mod path;
+
+use harbor_mcp_sdk::{harbor_tool, Error, Server, ToolInput, ToolResult};
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use serde_json_path::JsonPath;
+
+const DEFAULT_LIMIT: usize = 50;
+const MAX_LIMIT: usize = 1000;
+/// Most validation errors listed.
+const MAX_ERRORS: usize = 50;
+/// How many levels `shape` describes before summarizing.
+const SHAPE_DEPTH: usize = 3;
+
+#[derive(Deserialize, ToolInput)]
+#[serde(deny_unknown_fields)]
+struct QueryArgs {
+    /// The JSON document, as a value or as JSON text
+    json: Value,
+    /// JSONPath such as $.items[?@.price < 10].name, or a jq path such as .items[].name
+    query: String,
+    /// Describe each match's structure (keys, types, lengths) instead of returning it
+    #[serde(default)]
+    shape: bool,
+    /// Most matches to return (default 50)
+    limit: Option<usize>,
+}
+
+#[derive(Deserialize, ToolInput)]
+#[serde(deny_unknown_fields)]
+struct ValidateArgs {
+    /// The JSON document, as a value or as JSON text
+    json: Value,
+    /// The JSON Schema to check it against, as a value or as JSON text
+    schema: Value,
+}
+
+#[derive(Deserialize, ToolInput)]
+#[serde(deny_unknown_fields)]
+struct DiffArgs {
+    /// The original document, as a value or as JSON text
+    before: Value,
+    /// The changed document, as a value or as JSON text
+    after: Value,
+    /// Most changes to list (default 50)
+    limit: Option<usize>,
+}
+
+/// A document as passed in: JSON text given as a string is parsed, and
+/// anything else is the document itself.
+fn document(value: Value) -> Value {
+    match value {
+        Value::String(text) => serde_json::from_str(&text).unwrap_or(Value::String(text)),
+        value => value,
+    }
+}
+
+fn limit(requested: Option<usize>) -> usize {
+    requested.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT)
+}
+
+/// The structure of `value`: scalars as their type, objects by key and
+/// arrays by length and first element, summarized below `depth` levels.
+fn shape(value: &Value, depth: usize) -> Value {
+    match value {
+        Value::Null => json!("null"),
+        Value::Bool(_) => json!("boolean"),
+        Value::Number(_) => json!("number"),
+        Value::String(_) => json!("string"),
+        Value::Array(items) if depth == 0 => json!(format!("array({})", items.len())),
+        Value::Object(map) if depth == 0 => json!(format!("object({} keys)", map.len())),
+        Value::Array(items) => match items.first() {
+            Some(first) => json!({ "array": items.len(), "of": shape(first, depth - 1) }),
+            None => json!({ "array": 0 }),
+        },
+        Value::Object(map) => {
+            let shapes: Map<String, Value> = map
+                .iter()
+                .map(|(key, value)| (key.clone(), shape(value, depth - 1)))
+                .collect();
+            Value::Object(shapes)
+        }
+    }
+}
+
+/// Pick values out of a JSON document with a JSONPath or jq-style path,
+/// instead of reading all of it. Returns each match with its path.
+#[harbor_tool(name = "json.query")]
+fn query(args: QueryArgs) -> Result<ToolResult, Error> {
+    let json_path = match path::to_json_path(&args.query) {
+        Ok(json_path) => json_path,
+        Err(e) => return Ok(ToolResult::error(e)),
+    };
+    let parsed = match JsonPath::parse(&json_path) {
+        Ok(parsed) => parsed,
+        Err(e) => return Ok(ToolResult::error(format!("Invalid query {}: {}", json_path, e))),
+    };
+    let document = document(args.json);
+    let nodes = parsed.query_located(&document);
+    let matches: Vec<Value> = nodes
+        .iter()
+        .take(limit(args.limit))
+        .map(|node| {
+            let value = if args.shape {
+                shape(node.node(), SHAPE_DEPTH)
+            } else {
+                node.node().clone()
+            };
+            json!({ "path": node.location().to_string(), "value": value })
+        })
+        .collect();
+    let mut result = json!({ "matches": matches, "total": nodes.len() });
+    // Show the JSONPath a jq path became, so the caller can refine it
+    if json_path != args.query.trim() {
+        result["json_path"] = json!(json_path);
+    }
+    Ok(ToolResult::json(&result))
+}
+
+/// Check a JSON document against a JSON Schema. Returns whether it is
+/// valid and, if not, where and why.
+#[harbor_tool(name = "json.validate")]
+fn validate(args: ValidateArgs) -> Result<ToolResult, Error> {
+    let schema = document(args.schema);
+    let validator = match jsonschema::validator_for(&schema) {
+        Ok(validator) => validator,
+        Err(e) => return Ok(ToolResult::error(format!("Invalid schema: {}", e))),
+    };
+    let instance = document(args.json);
+    let mut errors = Vec::new();
+    let mut total = 0;
+    for error in validator.iter_errors(&instance) {
+        total += 1;
+        if errors.len() < MAX_ERRORS {
+            let path = error.instance_path.to_string();
+            errors.push(json!({ "path": path, "message": error.to_string() }));
+        }
+    }
+    let result = json!({ "valid": total == 0, "errors": errors, "total": total });
+    Ok(ToolResult::json(&result))
+}
+
+/// List the differences between two JSON documents as add, remove and
+/// replace operations on JSON Pointer paths, with the old and new values.
+#[harbor_tool(name = "json.diff")]
+fn compare(args: DiffArgs) -> Result<ToolResult, Error> {
+    let found = diff::diff(&document(args.before), &document(args.after), limit(args.limit));
+    let result = json!({
+        "equal": found.total == 0,
+        "changes": found.changes,
+        "total": found.total,
+    });
+    Ok(ToolResult::json(&result))
+}
+
+fn main() {
+    Server::new("mcp-json", "1.0.0")
+        .register(query_tool())
+        .register(validate_tool())
+        .register(compare_tool())
+        .run();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_document() {
+        assert_eq!(document(json!("{\"a\": 1}")), json!({ "a": 1 }));
+        assert_eq!(document(json!("not json")), json!("not json"));
+        assert_eq!(document(json!([1])), json!([1]));
+    }
+
+    #[test]
+    fn test_shape() {
+        let item = json!({ "id": 1, "tags": ["a"], "meta": { "x": {} } });
+        let value = json!({ "items": [item], "next": null });
+        let item_shape = json!({ "id": "number", "tags": "array(1)", "meta": "object(1 keys)" });
+        assert_eq!(
+            shape(&value, SHAPE_DEPTH),
+            json!({ "items": { "array": 1, "of": item_shape }, "next": "null" })
+        );
+    }
+}
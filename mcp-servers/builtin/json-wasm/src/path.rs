@@ -0,0 +1,146 @@
+//! Queries: JSONPath (RFC 9535) as it is, and jq-style paths turned into
+//! JSONPath. jq paths cover picking fields and elements, `.items[].name`,
+//! `.["a key"]`, `.a[1:3]` and `..name`; pipes and filters aren't jq paths,
+//! and JSONPath filters like `$.items[?@.price < 10]` do that job.
+
+/// The JSONPath for `query`, converting it if it is a jq path.
+pub fn to_json_path(query: &str) -> Result<String, String> {
+    let query = query.trim();
+    if query.starts_with('$') {
+        return Ok(query.to_string());
+    }
+    if !query.starts_with('.') {
+        return Err("Queries start with $ (JSONPath, e.g. $.items[*].name) \
+            or . (a jq path, e.g. .items[].name)"
+            .to_string());
+    }
+    let chars: Vec<char> = query.chars().collect();
+    let mut path = String::from("$");
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '.' => {
+                i += 1;
+                if chars.get(i) == Some(&'.') {
+                    path.push_str("..");
+                    i += 1;
+                    // `..` on its own is every value; `..[0]` needs no name
+                    if i == chars.len() {
+                        path.push_str("*");
+                    }
+                }
+                match chars.get(i) {
+                    Some('"') => {
+                        let (key, end) = quoted(&chars, i)?;
+                        path.push_str(&bracket(&key));
+                        i = end;
+                    }
+                    Some(c) if is_name(*c) => {
+                        let start = i;
+                        while i < chars.len() && is_name(chars[i]) {
+                            i += 1;
+                        }
+                        let name: String = chars[start..i].iter().collect();
+                        path.push_str(&bracket(&name));
+                    }
+                    Some('[') | None => {}
+                    Some(c) => return Err(unsupported(*c)),
+                }
+            }
+            '[' => {
+                let end = chars[i..]
+                    .iter()
+                    .position(|c| *c == ']')
+                    .map(|offset| i + offset)
+                    .ok_or("Unclosed '[' in the query")?;
+                let inside: String = chars[i + 1..end].iter().collect();
+                let inside = inside.trim();
+                if inside.is_empty() {
+                    path.push_str("[*]");
+                } else if inside.starts_with('"') {
+                    let inner: Vec<char> = inside.chars().collect();
+                    let (key, _) = quoted(&inner, 0)?;
+                    path.push_str(&bracket(&key));
+                } else {
+                    path.push_str(&format!("[{}]", inside));
+                }
+                i = end + 1;
+            }
+            // jq's optional marker: JSONPath never fails on a missing field
+            '?' => i += 1,
+            c if c.is_whitespace() => i += 1,
+            c => return Err(unsupported(c)),
+        }
+    }
+    Ok(path)
+}
+
+fn is_name(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-'
+}
+
+fn unsupported(c: char) -> String {
+    if c == '|' || c == '(' {
+        return "Only jq paths (like .items[].name) are supported, not pipes or functions; \
+            use a JSONPath filter such as $.items[?@.price < 10] instead"
+            .to_string();
+    }
+    format!("Unexpected '{}' in the query", c)
+}
+
+/// The string quoted at `chars[start]`, and the index after its closing
+/// quote.
+fn quoted(chars: &[char], start: usize) -> Result<(String, usize), String> {
+    let mut key = String::new();
+    let mut i = start + 1;
+    while i < chars.len() {
+        match chars[i] {
+            '"' => return Ok((key, i + 1)),
+            '\\' if i + 1 < chars.len() => {
+                key.push(chars[i + 1]);
+                i += 2;
+            }
+            c => {
+                key.push(c);
+                i += 1;
+            }
+        }
+    }
+    Err("Unclosed '\"' in the query".to_string())
+}
+
+/// `['key']`, escaped for JSONPath.
+fn bracket(key: &str) -> String {
+    format!("['{}']", key.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_path_is_kept() {
+        assert_eq!(to_json_path(" $.a[?@.b > 1] ").unwrap(), "$.a[?@.b > 1]");
+    }
+
+    #[test]
+    fn test_jq_paths() {
+        assert_eq!(to_json_path(".").unwrap(), "$");
+        assert_eq!(to_json_path(".items[].name").unwrap(), "$['items'][*]['name']");
+        assert_eq!(to_json_path(".items[0].tags[1:3]").unwrap(), "$['items'][0]['tags'][1:3]");
+        assert_eq!(to_json_path(".[-1]").unwrap(), "$[-1]");
+        assert_eq!(to_json_path(r#"."a key".b"#).unwrap(), "$['a key']['b']");
+        assert_eq!(to_json_path(r#".["it's"]"#).unwrap(), r"$['it\'s']");
+        assert_eq!(to_json_path("..id").unwrap(), "$..['id']");
+        assert_eq!(to_json_path("..").unwrap(), "$..*");
+        assert_eq!(to_json_path(".user.name?").unwrap(), "$['user']['name']");
+    }
+
+    #[test]
+    fn test_unsupported() {
+        assert!(to_json_path("items").unwrap_err().contains("start with"));
+        assert!(to_json_path(".items | length").unwrap_err().contains("pipes"));
+        assert!(to_json_path(".items[0").unwrap_err().contains("Unclosed"));
+        assert!(to_json_path(r#"."a"#).unwrap_err().contains("Unclosed"));
+    }
+}
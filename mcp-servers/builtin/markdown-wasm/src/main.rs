@@ -0,0 +1,215 @@
+//! Markdown MCP Server (WASM)
+//!
+//! Markdown for documentation work: `markdown.to_html` and
+//! `markdown.to_text` convert it, `markdown.headings` and `markdown.links`
+//! extract its outline and references, and `markdown.toc` writes a table
+//! of contents. Parsing is CommonMark with the GitHub extensions (tables,
+//! task lists, strikethrough, footnotes), by pulldown-cmark.
+//!
+//! Build with:
+//!   cargo build --release --target wasm32-wasip1
+
+mod outline;
+mod text;
+
+use harbor_mcp_sdk::{harbor_tool, Error, Server, ToolInput, ToolResult};
+use pulldown_cmark::{html, CowStr, Event, Options, Parser, Tag};
+use serde::Deserialize;
+use serde_json::json;
+
+/// Longest document taken, in bytes.
+const MAX_MARKDOWN_BYTES: usize = 1 << 20;
+const DEFAULT_TOC_DEPTH: u8 = 3;
+
+#[derive(Deserialize, ToolInput)]
+#[serde(deny_unknown_fields)]
+struct HtmlArgs {
+    /// The Markdown
+    markdown: String,
+    /// Pass raw HTML and javascript: links through instead of escaping and dropping them
+    #[serde(default)]
+    allow_html: bool,
+}
+
+#[derive(Deserialize, ToolInput)]
+#[serde(deny_unknown_fields)]
+struct MarkdownArgs {
+    /// The Markdown
+    markdown: String,
+}
+
+#[derive(Deserialize, ToolInput)]
+#[serde(deny_unknown_fields)]
+struct TocArgs {
+    /// The Markdown
+    markdown: String,
+    /// Shallowest heading level included (default 1; 2 leaves out a title)
+    min_level: Option<u8>,
+    /// Deepest heading level included (default 3)
+    max_level: Option<u8>,
+}
+
+/// The extensions every tool parses with.
+fn options() -> Options {
+    Options::ENABLE_TABLES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS
+        | Options::ENABLE_FOOTNOTES
+        | Options::ENABLE_HEADING_ATTRIBUTES
+}
+
+fn check_size(markdown: &str) -> Option<ToolResult> {
+    (markdown.len() > MAX_MARKDOWN_BYTES).then(|| {
+        ToolResult::error(format!("The Markdown is over {} bytes", MAX_MARKDOWN_BYTES))
+    })
+}
+
+/// Whether following a link to `url` could run script.
+fn is_script_url(url: &str) -> bool {
+    let scheme: String = url
+        .trim_start()
+        .chars()
+        .take_while(|&c| c != ':')
+        .filter(|c| !c.is_ascii_whitespace())
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+    url.contains(':') && matches!(scheme.as_str(), "javascript" | "vbscript" | "data")
+}
+
+/// Raw HTML as text, and links to script URLs as links to `#`.
+fn defuse(event: Event<'_>) -> Event<'_> {
+    match event {
+        Event::Html(html) | Event::InlineHtml(html) => Event::Text(html),
+        Event::Start(Tag::Link { link_type, dest_url, title, id }) if is_script_url(&dest_url) => {
+            Event::Start(Tag::Link { link_type, dest_url: CowStr::from("#"), title, id })
+        }
+        event => event,
+    }
+}
+
+fn to_html(markdown: &str, allow_html: bool) -> String {
+    let parser = Parser::new_ext(markdown, options());
+    let events: Vec<_> = if allow_html { parser.collect() } else { parser.map(defuse).collect() };
+    let mut out = String::with_capacity(markdown.len() * 3 / 2);
+    html::push_html(&mut out, outline::with_heading_ids(events).into_iter());
+    out
+}
+
+/// Convert Markdown to HTML. Headings get ids matching markdown.toc's
+/// links. Raw HTML is escaped and javascript: links dropped unless
+/// allow_html is set.
+#[harbor_tool(name = "markdown.to_html")]
+fn convert_to_html(args: HtmlArgs) -> Result<ToolResult, Error> {
+    if let Some(error) = check_size(&args.markdown) {
+        return Ok(error);
+    }
+    Ok(ToolResult::text(to_html(&args.markdown, args.allow_html)))
+}
+
+/// Convert Markdown to plain text, e.g. to count words or quote it
+/// somewhere without formatting. Links are followed by their URL.
+#[harbor_tool(name = "markdown.to_text")]
+fn convert_to_text(args: MarkdownArgs) -> Result<ToolResult, Error> {
+    if let Some(error) = check_size(&args.markdown) {
+        return Ok(error);
+    }
+    Ok(ToolResult::text(text::to_text(&args.markdown)))
+}
+
+/// List a Markdown document's headings with their level, anchor and line.
+#[harbor_tool(name = "markdown.headings")]
+fn list_headings(args: MarkdownArgs) -> Result<ToolResult, Error> {
+    if let Some(error) = check_size(&args.markdown) {
+        return Ok(error);
+    }
+    let headings: Vec<_> = outline::headings(&args.markdown)
+        .iter()
+        .map(|heading| {
+            json!({
+                "level": heading.level,
+                "text": heading.text,
+                "anchor": heading.anchor,
+                "line": heading.line,
+            })
+        })
+        .collect();
+    Ok(ToolResult::json(&json!({ "headings": headings })))
+}
+
+/// List the links and images in a Markdown document, with reference links
+/// resolved to their URL, e.g. to check them or collect sources.
+#[harbor_tool(name = "markdown.links")]
+fn list_links(args: MarkdownArgs) -> Result<ToolResult, Error> {
+    if let Some(error) = check_size(&args.markdown) {
+        return Ok(error);
+    }
+    let links: Vec<_> = outline::links(&args.markdown)
+        .iter()
+        .map(|link| {
+            let mut view = json!({ "text": link.text, "url": link.url, "line": link.line });
+            if !link.title.is_empty() {
+                view["title"] = json!(link.title);
+            }
+            if link.image {
+                view["image"] = json!(true);
+            }
+            view
+        })
+        .collect();
+    Ok(ToolResult::json(&json!({ "links": links })))
+}
+
+/// Write a table of contents for a Markdown document: a nested list
+/// linking to its headings by the anchors GitHub gives them.
+#[harbor_tool(name = "markdown.toc")]
+fn table_of_contents(args: TocArgs) -> Result<ToolResult, Error> {
+    if let Some(error) = check_size(&args.markdown) {
+        return Ok(error);
+    }
+    let min = args.min_level.unwrap_or(1);
+    let max = args.max_level.unwrap_or(DEFAULT_TOC_DEPTH);
+    if !(1..=6).contains(&min) || !(min..=6).contains(&max) {
+        return Ok(ToolResult::error("Levels go from 1 to 6, with min_level at most max_level"));
+    }
+    let toc = outline::toc(&outline::headings(&args.markdown), min, max);
+    if toc.is_empty() {
+        let message = format!("The document has no headings of levels {} to {}", min, max);
+        return Ok(ToolResult::error(message));
+    }
+    Ok(ToolResult::text(toc))
+}
+
+fn main() {
+    Server::new("mcp-markdown", "1.0.0")
+        .register(convert_to_html_tool())
+        .register(convert_to_text_tool())
+        .register(list_headings_tool())
+        .register(list_links_tool())
+        .register(table_of_contents_tool())
+        .run();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_html() {
+        let html = to_html("# Hi there\n\n<b>x</b> [y](javascript:alert(1))", false);
+        assert_eq!(
+            html,
+            "<h1 id=\"hi-there\">Hi there</h1>\n<p>&lt;b&gt;x&lt;/b&gt; <a href=\"#\">y</a></p>\n"
+        );
+        let html = to_html("<b>x</b> [y](https://example.com)", true);
+        assert_eq!(html, "<p><b>x</b> <a href=\"https://example.com\">y</a></p>\n");
+    }
+
+    #[test]
+    fn test_is_script_url() {
+        assert!(is_script_url("javascript:alert(1)"));
+        assert!(is_script_url(" JavaScript :void(0)"));
+        assert!(is_script_url("data:text/html,<script>"));
+        assert!(!is_script_url("https://example.com/javascript"));
+        assert!(!is_script_url("javascript"));
+    }
+}
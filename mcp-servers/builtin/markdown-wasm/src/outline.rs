@@ -0,0 +1,211 @@
+//! A document's headings and links, and a table of contents from them.
+//! Heading anchors are made the way GitHub makes them, so a table of
+//! contents links to the same places on GitHub as in `markdown.to_html`:
+//! lowercase, punctuation dropped, spaces as hyphens, and `-1`, `-2`...
+//! after repeats. A heading with an `{#id}` attribute keeps its id.
+
+use std::collections::HashMap;
+
+use pulldown_cmark::{CowStr, Event, Parser, Tag, TagEnd};
+
+pub struct Heading {
+    pub level: u8,
+    pub text: String,
+    pub anchor: String,
+    pub line: usize,
+}
+
+pub struct Link {
+    pub text: String,
+    pub url: String,
+    pub title: String,
+    pub image: bool,
+    pub line: usize,
+}
+
+/// Anchors for headings, each once.
+#[derive(Default)]
+pub struct Slugger {
+    seen: HashMap<String, usize>,
+}
+
+impl Slugger {
+    pub fn slug(&mut self, text: &str) -> String {
+        let base: String = text
+            .trim()
+            .to_lowercase()
+            .chars()
+            .filter(|c| c.is_alphanumeric() || matches!(c, ' ' | '-' | '_'))
+            .map(|c| if c == ' ' { '-' } else { c })
+            .collect();
+        let repeats = self.seen.entry(base.clone()).or_insert(0);
+        *repeats += 1;
+        match *repeats {
+            1 => base,
+            n => format!("{}-{}", base, n - 1),
+        }
+    }
+}
+
+/// 1-based line numbers of byte offsets.
+struct Lines(Vec<usize>);
+
+impl Lines {
+    fn new(text: &str) -> Self {
+        let starts = text.match_indices('\n').map(|(i, _)| i + 1);
+        Lines(std::iter::once(0).chain(starts).collect())
+    }
+
+    fn of(&self, offset: usize) -> usize {
+        self.0.partition_point(|&start| start <= offset)
+    }
+}
+
+/// The text of the element whose start event came just before `events`.
+fn inner_text(events: &[Event]) -> String {
+    let mut text = String::new();
+    let mut depth = 0;
+    for event in events {
+        match event {
+            Event::Start(_) => depth += 1,
+            Event::End(_) if depth == 0 => break,
+            Event::End(_) => depth -= 1,
+            Event::Text(t) | Event::Code(t) => text.push_str(t),
+            Event::SoftBreak | Event::HardBreak => text.push(' '),
+            _ => {}
+        }
+    }
+    text
+}
+
+/// Every heading, in order.
+pub fn headings(markdown: &str) -> Vec<Heading> {
+    let lines = Lines::new(markdown);
+    let (events, offsets): (Vec<_>, Vec<_>) =
+        Parser::new_ext(markdown, super::options()).into_offset_iter().unzip();
+    let mut slugger = Slugger::default();
+    let mut found = Vec::new();
+    for (i, event) in events.iter().enumerate() {
+        if let Event::Start(Tag::Heading { level, id, .. }) = event {
+            let text = inner_text(&events[i + 1..]);
+            let anchor = match id {
+                Some(id) => id.to_string(),
+                None => slugger.slug(&text),
+            };
+            let line = lines.of(offsets[i].start);
+            let text = text.trim().to_string();
+            found.push(Heading { level: *level as u8, text, anchor, line });
+        }
+    }
+    found
+}
+
+/// Give headings without an `{#id}` their anchor as id, for HTML.
+pub fn with_heading_ids(mut events: Vec<Event<'_>>) -> Vec<Event<'_>> {
+    let mut slugger = Slugger::default();
+    let anchors: Vec<(usize, String)> = events
+        .iter()
+        .enumerate()
+        .filter(|(_, event)| matches!(event, Event::Start(Tag::Heading { id: None, .. })))
+        .map(|(i, _)| (i, slugger.slug(&inner_text(&events[i + 1..]))))
+        .collect();
+    for (i, anchor) in anchors {
+        if let Event::Start(Tag::Heading { id, .. }) = &mut events[i] {
+            *id = Some(CowStr::from(anchor));
+        }
+    }
+    events
+}
+
+/// Every link and image, in order, with reference links resolved.
+pub fn links(markdown: &str) -> Vec<Link> {
+    let lines = Lines::new(markdown);
+    let (events, offsets): (Vec<_>, Vec<_>) =
+        Parser::new_ext(markdown, super::options()).into_offset_iter().unzip();
+    let mut found = Vec::new();
+    for (i, event) in events.iter().enumerate() {
+        let (url, title, image) = match event {
+            Event::Start(Tag::Link { dest_url, title, .. }) => (dest_url, title, false),
+            Event::Start(Tag::Image { dest_url, title, .. }) => (dest_url, title, true),
+            _ => continue,
+        };
+        found.push(Link {
+            text: inner_text(&events[i + 1..]).trim().to_string(),
+            url: url.to_string(),
+            title: title.to_string(),
+            image,
+            line: lines.of(offsets[i].start),
+        });
+    }
+    found
+}
+
+/// A nested Markdown list linking to the headings from level `min` to
+/// `max`, indented from the shallowest of them.
+pub fn toc(headings: &[Heading], min: u8, max: u8) -> String {
+    let included: Vec<_> = headings
+        .iter()
+        .filter(|heading| (min..=max).contains(&heading.level))
+        .collect();
+    let base = included.iter().map(|heading| heading.level).min().unwrap_or(min);
+    let mut toc = String::new();
+    for heading in included {
+        let indent = "  ".repeat(usize::from(heading.level - base));
+        let text = heading.text.replace('[', "\\[").replace(']', "\\]");
+        toc.push_str(&format!("{}- [{}](#{})\n", indent, text, heading.anchor));
+    }
+    toc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slug() {
+        let mut slugger = Slugger::default();
+        assert_eq!(slugger.slug("Getting Started"), "getting-started");
+        assert_eq!(slugger.slug("What's new in 2.0?"), "whats-new-in-20");
+        assert_eq!(slugger.slug("getting started"), "getting-started-1");
+        assert_eq!(slugger.slug("Getting  Started"), "getting--started");
+        assert_eq!(slugger.slug("Café & Crème"), "café--crème");
+    }
+
+    #[test]
+    fn test_headings_and_toc() {
+        let markdown = "# Guide\n\nIntro\n\n## Install `cargo`\n\n### On [Linux](#linux)\n\n\
+                        ## Usage {#use}\n\n## Usage\n";
+        let found = headings(markdown);
+        let summary: Vec<_> = found
+            .iter()
+            .map(|h| (h.level, h.text.as_str(), h.anchor.as_str(), h.line))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (1, "Guide", "guide", 1),
+                (2, "Install cargo", "install-cargo", 5),
+                (3, "On Linux", "on-linux", 7),
+                (2, "Usage", "use", 9),
+                (2, "Usage", "usage", 11),
+            ]
+        );
+        assert_eq!(
+            toc(&found, 2, 3),
+            "- [Install cargo](#install-cargo)\n  - [On Linux](#on-linux)\n\
+             - [Usage](#use)\n- [Usage](#usage)\n"
+        );
+    }
+
+    #[test]
+    fn test_links() {
+        let markdown = "See [the docs][docs] and ![logo](logo.png \"Logo\").\n\n\
+                        <https://example.com>\n\n[docs]: https://docs.rs\n";
+        let found = links(markdown);
+        assert_eq!(found.len(), 3);
+        assert_eq!(found[0].text, "the docs");
+        assert_eq!(found[0].url, "https://docs.rs");
+        assert!(found[1].image && found[1].title == "Logo" && found[1].text == "logo");
+        assert_eq!((found[2].url.as_str(), found[2].line), ("https://example.com", 3));
+    }
+}
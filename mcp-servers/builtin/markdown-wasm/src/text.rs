@@ -0,0 +1,131 @@
+//! Markdown as plain text: formatting dropped, paragraphs and blocks
+//! separated by a blank line, list items bulleted or numbered and
+//! indented by nesting, table cells separated by ` | ` and links
+//! followed by their URL. Raw HTML is left out.
+
+use pulldown_cmark::{Event, Parser, Tag, TagEnd};
+
+/// End `out` with a blank line, unless it is empty.
+fn block(out: &mut String) {
+    if out.is_empty() {
+        return;
+    }
+    while !out.ends_with("\n\n") {
+        out.push('\n');
+    }
+}
+
+fn line(out: &mut String) {
+    if !out.is_empty() && !out.ends_with('\n') {
+        out.push('\n');
+    }
+}
+
+pub fn to_text(markdown: &str) -> String {
+    let mut out = String::new();
+    // The next number of each list open, None for bulleted ones
+    let mut lists: Vec<Option<u64>> = Vec::new();
+    // Where each open link's text starts, and its URL
+    let mut links: Vec<(usize, String)> = Vec::new();
+    // Whether a list item has had no text yet, so a paragraph in it stays
+    // on the marker's line
+    let mut item_open = false;
+    let mut cells = 0;
+    for event in Parser::new_ext(markdown, super::options()) {
+        match event {
+            Event::Start(tag) => match tag {
+                Tag::Paragraph if item_open => {}
+                Tag::List(start) => {
+                    if lists.is_empty() {
+                        block(&mut out);
+                    } else {
+                        line(&mut out);
+                    }
+                    lists.push(start);
+                }
+                Tag::Item => {
+                    line(&mut out);
+                    out.push_str(&"  ".repeat(lists.len().saturating_sub(1)));
+                    match lists.last_mut() {
+                        Some(Some(number)) => {
+                            out.push_str(&format!("{}. ", number));
+                            *number += 1;
+                        }
+                        _ => out.push_str("- "),
+                    }
+                    item_open = true;
+                }
+                Tag::TableHead | Tag::TableRow => {
+                    line(&mut out);
+                    cells = 0;
+                }
+                Tag::TableCell => {
+                    if cells > 0 {
+                        out.push_str(" | ");
+                    }
+                    cells += 1;
+                }
+                Tag::Link { dest_url, .. } => links.push((out.len(), dest_url.to_string())),
+                Tag::Paragraph
+                | Tag::Heading { .. }
+                | Tag::BlockQuote(_)
+                | Tag::CodeBlock(_)
+                | Tag::Table(_)
+                | Tag::FootnoteDefinition(_) => {
+                    if lists.is_empty() {
+                        block(&mut out);
+                    } else {
+                        line(&mut out);
+                    }
+                }
+                _ => {}
+            },
+            Event::End(TagEnd::List(_)) => {
+                lists.pop();
+            }
+            Event::End(TagEnd::Link) => {
+                if let Some((start, url)) = links.pop() {
+                    let text = &out[start..];
+                    if !text.is_empty() && text != url && !url.starts_with('#') {
+                        out.push_str(&format!(" ({})", url));
+                    }
+                }
+            }
+            Event::Text(text) | Event::Code(text) => {
+                out.push_str(&text);
+                item_open = false;
+            }
+            Event::SoftBreak => out.push(' '),
+            Event::HardBreak => out.push('\n'),
+            Event::TaskListMarker(done) => out.push_str(if done { "[x] " } else { "[ ] " }),
+            Event::FootnoteReference(label) => out.push_str(&format!("[{}]", label)),
+            Event::Rule => block(&mut out),
+            _ => {}
+        }
+    }
+    out.trim_end().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_text() {
+        let markdown = "# Title\n\nSome *emphasis* and `code`,\nwrapped. \
+                        See [docs](https://docs.rs) or <https://example.com>.\n\n\
+                        - one\n- two\n  1. a\n  2. b\n\n| A | B |\n|---|---|\n| 1 | 2 |\n\n\
+                        <div>html</div>\n\n```\nlet x = 1;\n```\n";
+        assert_eq!(
+            to_text(markdown),
+            "Title\n\nSome emphasis and code, wrapped. See docs (https://docs.rs) or \
+             https://example.com.\n\n- one\n- two\n  1. a\n  2. b\n\nA | B\n1 | 2\n\nlet x = 1;"
+        );
+    }
+
+    #[test]
+    fn test_loose_list() {
+        assert_eq!(to_text("- [x] done\n\n- [ ] todo\n"), "- [x] done\n- [ ] todo");
+        assert_eq!(to_text("3. three\n4. four"), "3. three\n4. four");
+    }
+}
@@ -0,0 +1,191 @@
+//! Memory MCP Server (WASM component)
+//!
+//! Durable notes for chat agents: `memory.save` keeps a note with optional
+//! tags, `memory.search` finds notes by the words of a query and
+//! `memory.list` shows the latest. Notes live in the server's `kv`
+//! namespace, which the bridge keeps in `~/.harbor/state`, so they last
+//! across sessions and restarts without any outside service.
+//!
+//! Build with:
+//!   cargo build --release --target wasm32-wasip2
+
+mod notes;
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use harbor_mcp_sdk::{harbor_tool, rfc3339, Error, Server, ToolInput, ToolResult};
+use serde::Deserialize;
+use serde_json::json;
+
+use notes::Note;
+
+wit_bindgen::generate!({ path: "../../wit", world: "mcp-server" });
+
+use harbor::mcp::kv;
+
+/// Longest note saved, in characters.
+const MAX_NOTE_CHARS: usize = 10_000;
+const DEFAULT_LIMIT: usize = 10;
+const MAX_LIMIT: usize = 100;
+/// kv key of the id the next note gets.
+const NEXT_ID_KEY: &str = "next-id";
+
+#[derive(Deserialize, ToolInput)]
+#[serde(deny_unknown_fields)]
+struct SaveArgs {
+    /// What to remember
+    text: String,
+    /// Words to find the note by, e.g. "travel" or "project-x"
+    #[serde(default)]
+    tags: Vec<String>,
+    /// Id of a note to replace, instead of adding a new one
+    id: Option<String>,
+}
+
+#[derive(Deserialize, ToolInput)]
+#[serde(deny_unknown_fields)]
+struct SearchArgs {
+    /// Words to look for in notes' text and tags
+    query: String,
+    /// Most notes to return (default 10)
+    limit: Option<usize>,
+}
+
+#[derive(Deserialize, ToolInput)]
+#[serde(deny_unknown_fields)]
+struct ListArgs {
+    /// Only notes with this tag
+    tag: Option<String>,
+    /// Most notes to return (default 10)
+    limit: Option<usize>,
+}
+
+fn storage_error(e: String) -> Error {
+    Error::internal(format!("Memory storage failed: {}", e))
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn read_note(key: &str) -> Result<Option<Note>, Error> {
+    let stored = kv::get(key).map_err(storage_error)?;
+    Ok(stored.and_then(|bytes| serde_json::from_slice(&bytes).ok()))
+}
+
+/// Every note, oldest first.
+fn all_notes() -> Result<Vec<Note>, Error> {
+    let keys = kv::list(notes::KEY_PREFIX).map_err(storage_error)?;
+    let mut found = Vec::with_capacity(keys.len());
+    for key in keys {
+        found.extend(read_note(&key)?);
+    }
+    Ok(found)
+}
+
+fn next_id() -> Result<String, Error> {
+    let stored = kv::get(NEXT_ID_KEY).map_err(storage_error)?;
+    let id: u64 = stored
+        .and_then(|bytes| String::from_utf8(bytes).ok())
+        .and_then(|text| text.parse().ok())
+        .unwrap_or(1);
+    kv::set(NEXT_ID_KEY, (id + 1).to_string().as_bytes()).map_err(storage_error)?;
+    Ok(id.to_string())
+}
+
+/// A note as tools return it.
+fn view(note: &Note) -> serde_json::Value {
+    json!({
+        "id": note.id,
+        "text": note.text,
+        "tags": note.tags,
+        "saved": rfc3339(note.saved_at as i64),
+    })
+}
+
+fn limit(requested: Option<usize>) -> usize {
+    requested.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT)
+}
+
+/// Save a note that should be remembered across conversations, such as a
+/// user's preference or a fact to come back to. Pass the id of an existing
+/// note to replace it.
+#[harbor_tool(name = "memory.save")]
+fn save(args: SaveArgs) -> Result<ToolResult, Error> {
+    let text = args.text.trim();
+    if text.is_empty() {
+        return Ok(ToolResult::error("The note is empty"));
+    }
+    if text.chars().count() > MAX_NOTE_CHARS {
+        let message = format!("Notes can be at most {} characters; split this one up", MAX_NOTE_CHARS);
+        return Ok(ToolResult::error(message));
+    }
+    let (id, updated) = match args.id {
+        Some(id) => {
+            let exists = notes::key(&id).map(|key| read_note(&key)).transpose()?.flatten();
+            match exists {
+                Some(existing) => (existing.id, true),
+                None => return Ok(ToolResult::error(format!("There is no note with id '{}'", id))),
+            }
+        }
+        None => (next_id()?, false),
+    };
+    let note = Note {
+        text: text.to_string(),
+        tags: notes::normalize_tags(args.tags),
+        saved_at: now(),
+        id,
+    };
+    let key = notes::key(&note.id).ok_or_else(|| Error::internal("Invalid note id"))?;
+    let bytes = serde_json::to_vec(&note).map_err(|e| Error::internal(e.to_string()))?;
+    // Most likely over the storage quota, which the model can act on
+    if let Err(e) = kv::set(&key, &bytes) {
+        return Ok(ToolResult::error(format!("The note wasn't saved: {}", e)));
+    }
+    Ok(ToolResult::json(&json!({ "id": note.id, "updated": updated })))
+}
+
+/// Search saved notes by the words of a query, best matches first. Tags
+/// count more than text.
+#[harbor_tool(name = "memory.search")]
+fn search(args: SearchArgs) -> Result<ToolResult, Error> {
+    let found = notes::search(all_notes()?, &args.query, limit(args.limit));
+    let found: Vec<_> = found.iter().map(view).collect();
+    Ok(ToolResult::json(&json!({ "notes": found })))
+}
+
+/// List saved notes, newest first, optionally only those with a tag.
+#[harbor_tool(name = "memory.list")]
+fn list(args: ListArgs) -> Result<ToolResult, Error> {
+    let tag = args.tag.map(|tag| notes::normalize_tags(vec![tag])).unwrap_or_default();
+    let all = all_notes()?;
+    let total = all.len();
+    let listed: Vec<_> = all
+        .iter()
+        .rev()
+        .filter(|note| tag.iter().all(|tag| note.tags.contains(tag)))
+        .take(limit(args.limit))
+        .map(view)
+        .collect();
+    Ok(ToolResult::json(&json!({ "notes": listed, "total": total })))
+}
+
+fn server() -> Server {
+    Server::new("mcp-memory", "1.0.0")
+        .register(save_tool())
+        .register(search_tool())
+        .register(list_tool())
+}
+
+struct Component;
+
+impl exports::harbor::mcp::server::Guest for Component {
+    fn handle(request: String) -> String {
+        server().handle(&request)
+    }
+}
+
+export!(Component);
@@ -0,0 +1,118 @@
+//! Notes and how they are found. Each note is stored as JSON under
+//! `note:<id>`, its id zero-padded in the key so keys list oldest first.
+//! A search ranks notes by how many words of the query they contain,
+//! counting a tag match twice.
+
+use serde::{Deserialize, Serialize};
+
+pub const KEY_PREFIX: &str = "note:";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Note {
+    pub id: String,
+    pub text: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Seconds since the Unix epoch
+    pub saved_at: u64,
+}
+
+/// The kv key of note `id`, or `None` if `id` isn't a note id.
+pub fn key(id: &str) -> Option<String> {
+    let id = id.trim();
+    if id.is_empty() || id.len() > 12 || !id.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    Some(format!("{}{:0>12}", KEY_PREFIX, id.trim_start_matches('0')))
+}
+
+/// Tags as stored: trimmed, lowercase, without a leading `#`, each once.
+pub fn normalize_tags(tags: Vec<String>) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag = tag.trim().trim_start_matches('#').to_lowercase();
+        if !tag.is_empty() && !normalized.contains(&tag) {
+            normalized.push(tag);
+        }
+    }
+    normalized
+}
+
+/// The lowercase words of `text`.
+fn words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+fn score(note: &Note, terms: &[String]) -> usize {
+    let text = note.text.to_lowercase();
+    terms
+        .iter()
+        .map(|term| {
+            if note.tags.iter().any(|tag| tag.starts_with(term.as_str())) {
+                2
+            } else {
+                usize::from(text.contains(term.as_str()))
+            }
+        })
+        .sum()
+}
+
+/// The notes matching `query` best, at most `limit`, newer first among
+/// equal matches.
+pub fn search(notes: Vec<Note>, query: &str, limit: usize) -> Vec<Note> {
+    let terms = words(query);
+    let mut scored: Vec<(usize, Note)> = notes
+        .into_iter()
+        .map(|note| (score(&note, &terms), note))
+        .filter(|(score, _)| *score > 0)
+        .collect();
+    scored.sort_by(|(a, x), (b, y)| b.cmp(a).then(y.saved_at.cmp(&x.saved_at)));
+    scored.into_iter().take(limit).map(|(_, note)| note).collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(id: &str, text: &str, tags: &[&str], saved_at: u64) -> Note {
+        Note {
+            id: id.to_string(),
+            text: text.to_string(),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            saved_at,
+        }
+    }
+
+    #[test]
+    fn test_key() {
+        assert_eq!(key("42").as_deref(), Some("note:000000000042"));
+        assert_eq!(key("0042"), key("42"));
+        assert!(key("").is_none());
+        assert!(key("../x").is_none());
+    }
+
+    #[test]
+    fn test_normalize_tags() {
+        let tags = vec![" Work ".to_string(), "#work".to_string(), "".to_string(), "Travel".to_string()];
+        assert_eq!(normalize_tags(tags), vec!["work", "travel"]);
+    }
+
+    #[test]
+    fn test_search() {
+        let notes = vec![
+            note("1", "Alice prefers window seats", &["travel"], 100),
+            note("2", "Flight to Lisbon on May 3", &["travel"], 200),
+            note("3", "Project deadline is Friday", &["work"], 300),
+        ];
+        let found = search(notes.clone(), "travel seats", 10);
+        let ids: Vec<&str> = found.iter().map(|note| note.id.as_str()).collect();
+        assert_eq!(ids, vec!["1", "2"]);
+        assert_eq!(search(notes.clone(), "LISBON", 10)[0].id, "2");
+        assert_eq!(search(notes.clone(), "travel", 1)[0].id, "2");
+        assert!(search(notes, "  ", 10).is_empty());
+    }
+}
@@ -0,0 +1,187 @@
+//! Dice notation: `2d6`, `d20+5`, `4d6kh3` (keep the highest 3), `2d20kl1`
+//! (keep the lowest), `d%` for a d100, and sums of these and whole
+//! numbers, like `1d8+2d6-1`.
+
+use crate::ids::Rng;
+
+const MAX_TERMS: usize = 20;
+const MAX_DICE: u64 = 1000;
+const MAX_SIDES: u64 = 1_000_000;
+const MAX_MODIFIER: i64 = 1_000_000;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Keep {
+    All,
+    Highest(usize),
+    Lowest(usize),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Term {
+    Dice { count: u64, sides: u64, keep: Keep },
+    Number(i64),
+}
+
+/// One term of a roll: its notation, every die rolled, the dice kept and
+/// what it adds to the total.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rolled {
+    pub term: String,
+    pub rolls: Vec<u64>,
+    pub kept: Vec<u64>,
+    pub value: i64,
+}
+
+fn number(text: &str, what: &str) -> Result<u64, String> {
+    text.parse()
+        .map_err(|_| format!("Expected {} in dice notation, found '{}'", what, text))
+}
+
+fn parse_term(text: &str) -> Result<Term, String> {
+    let Some((count, rest)) = text.split_once('d') else {
+        let value = number(text, "a number")?;
+        return i64::try_from(value)
+            .ok()
+            .filter(|value| *value <= MAX_MODIFIER)
+            .map(Term::Number)
+            .ok_or_else(|| format!("Modifiers are limited to {}", MAX_MODIFIER));
+    };
+    let count = if count.is_empty() { 1 } else { number(count, "a number of dice")? };
+    let (sides, keep) = match rest.find('k') {
+        Some(at) => {
+            let (sides, keep) = rest.split_at(at);
+            let keep = match keep.get(..2) {
+                Some("kh") => Keep::Highest(number(&keep[2..], "how many dice to keep")? as usize),
+                Some("kl") => Keep::Lowest(number(&keep[2..], "how many dice to keep")? as usize),
+                _ => Keep::Highest(number(&keep[1..], "how many dice to keep")? as usize),
+            };
+            (sides, keep)
+        }
+        None => (rest, Keep::All),
+    };
+    let sides = if sides == "%" { 100 } else { number(sides, "a number of sides")? };
+    if count == 0 || count > MAX_DICE {
+        return Err(format!("Roll between 1 and {} dice at a time", MAX_DICE));
+    }
+    if sides == 0 || sides > MAX_SIDES {
+        return Err(format!("Dice have between 1 and {} sides", MAX_SIDES));
+    }
+    Ok(Term::Dice { count, sides, keep })
+}
+
+/// Parse dice notation into signed terms.
+fn parse(notation: &str) -> Result<Vec<(i64, String, Term)>, String> {
+    let compact: String = notation
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect();
+    if compact.is_empty() {
+        return Err("The dice notation is empty".to_string());
+    }
+    let mut terms = Vec::new();
+    let mut sign = 1;
+    let mut start = 0;
+    for (i, c) in compact.char_indices().chain([(compact.len(), '+')]) {
+        if c != '+' && c != '-' {
+            continue;
+        }
+        let text = &compact[start..i];
+        // A leading sign, as in `-1+d6`
+        if text.is_empty() && i == 0 {
+            sign = if c == '-' { -1 } else { 1 };
+            start = i + 1;
+            continue;
+        }
+        if text.is_empty() {
+            return Err(format!("Expected a term before '{}' in '{}'", c, notation.trim()));
+        }
+        terms.push((sign, text.to_string(), parse_term(text)?));
+        if terms.len() > MAX_TERMS {
+            return Err(format!("Rolls are limited to {} terms", MAX_TERMS));
+        }
+        sign = if c == '-' { -1 } else { 1 };
+        start = i + 1;
+    }
+    Ok(terms)
+}
+
+/// Roll `notation`, returning each term and the total.
+pub fn roll(notation: &str, rng: &mut impl Rng) -> Result<(Vec<Rolled>, i64), String> {
+    let mut rolled = Vec::new();
+    let mut total: i64 = 0;
+    for (sign, text, term) in parse(notation)? {
+        let (rolls, kept, value) = match term {
+            Term::Number(value) => (Vec::new(), Vec::new(), value),
+            Term::Dice { count, sides, keep } => {
+                let rolls: Vec<u64> = (0..count).map(|_| rng.below(sides) + 1).collect();
+                let mut sorted = rolls.clone();
+                sorted.sort_unstable();
+                let kept = match keep {
+                    Keep::All => rolls.clone(),
+                    Keep::Highest(n) => sorted[sorted.len() - n.min(sorted.len())..].to_vec(),
+                    Keep::Lowest(n) => sorted[..n.min(sorted.len())].to_vec(),
+                };
+                let value = kept.iter().sum::<u64>() as i64;
+                (rolls, kept, value)
+            }
+        };
+        total += sign * value;
+        let term = if sign < 0 { format!("-{}", text) } else { text };
+        rolled.push(Rolled {
+            term,
+            rolls,
+            kept,
+            value: sign * value,
+        });
+    }
+    Ok((rolled, total))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ids::tests::Counter;
+
+    #[test]
+    fn test_parse() {
+        let terms = parse("2d6 + d20 - 3").unwrap();
+        assert_eq!(terms[0].2, Term::Dice { count: 2, sides: 6, keep: Keep::All });
+        assert_eq!(terms[1].2, Term::Dice { count: 1, sides: 20, keep: Keep::All });
+        assert_eq!((terms[2].0, &terms[2].2), (-1, &Term::Number(3)));
+        let highest = Term::Dice { count: 4, sides: 6, keep: Keep::Highest(3) };
+        assert_eq!(parse("4d6kh3").unwrap()[0].2, highest);
+        let lowest = Term::Dice { count: 2, sides: 20, keep: Keep::Lowest(1) };
+        assert_eq!(parse("2D20KL1").unwrap()[0].2, lowest);
+        assert_eq!(parse("d%").unwrap()[0].2, Term::Dice { count: 1, sides: 100, keep: Keep::All });
+        assert_eq!(parse("-1+d4").unwrap()[0].0, -1);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(parse("").is_err());
+        assert!(parse("2d").unwrap_err().contains("sides"));
+        assert!(parse("xd6").unwrap_err().contains("number of dice"));
+        assert!(parse("1001d6").unwrap_err().contains("1000 dice"));
+        assert!(parse("d0").is_err());
+        assert!(parse("d6++1").unwrap_err().contains("before '+'"));
+        assert!(parse("d6+").unwrap_err().contains("before"));
+    }
+
+    #[test]
+    fn test_roll() {
+        let (rolled, total) = roll("4d6kh3+2", &mut Counter(0)).unwrap();
+        assert_eq!(rolled.len(), 2);
+        assert_eq!(rolled[0].rolls.len(), 4);
+        assert_eq!(rolled[0].kept.len(), 3);
+        assert!(rolled[0].rolls.iter().all(|r| (1..=6).contains(r)));
+        let lowest = *rolled[0].rolls.iter().min().unwrap() as i64;
+        let sum: i64 = rolled[0].rolls.iter().map(|r| *r as i64).sum();
+        assert_eq!(total, sum - lowest + 2);
+        assert_eq!((rolled[1].term.as_str(), rolled[1].value), ("2", 2));
+        assert!(rolled[1].rolls.is_empty());
+
+        let (rolled, total) = roll("d1-5", &mut Counter(0)).unwrap();
+        assert_eq!((rolled[1].term.as_str(), total), ("-5", -4));
+    }
+}
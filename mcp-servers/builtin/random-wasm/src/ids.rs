@@ -0,0 +1,179 @@
+//! Identifiers and strings from a source of random bytes: UUIDs (v4
+//! random, v7 time-ordered), ULIDs, nanoids and strings over an alphabet.
+//! Picks from a range reject the values that would make some results
+//! likelier than others, so every character and number is equally likely.
+
+/// A source of random bytes.
+pub trait Rng {
+    fn fill(&mut self, buffer: &mut [u8]);
+
+    fn next_u64(&mut self) -> u64 {
+        let mut bytes = [0u8; 8];
+        self.fill(&mut bytes);
+        u64::from_le_bytes(bytes)
+    }
+
+    /// A number in `0..n`, every value equally likely.
+    fn below(&mut self, n: u64) -> u64 {
+        assert!(n > 0, "empty range");
+        // 2^64 mod n: values under it would favour the low results
+        let threshold = n.wrapping_neg() % n;
+        loop {
+            let value = self.next_u64();
+            if value >= threshold {
+                return value % n;
+            }
+        }
+    }
+
+    /// A number in `min..=max`.
+    fn between(&mut self, min: i64, max: i64) -> i64 {
+        let span = (i128::from(max) - i128::from(min)) as u64;
+        let offset = match span.checked_add(1) {
+            Some(count) => self.below(count),
+            None => self.next_u64(),
+        };
+        (i128::from(min) + i128::from(offset)) as i64
+    }
+}
+
+/// Crockford's base 32, as ULIDs use.
+const CROCKFORD: &[u8] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+/// The URL-safe alphabet nanoids use by default.
+pub const NANOID_ALPHABET: &str =
+    "_-0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+fn format_uuid(bytes: &[u8; 16]) -> String {
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("{}-{}-{}-{}-{}", &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..])
+}
+
+/// Set the version nibble and the RFC 9562 variant bits.
+fn stamp(bytes: &mut [u8; 16], version: u8) {
+    bytes[6] = (bytes[6] & 0x0f) | (version << 4);
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+}
+
+/// A random (version 4) UUID.
+pub fn uuid_v4(rng: &mut impl Rng) -> String {
+    let mut bytes = [0u8; 16];
+    rng.fill(&mut bytes);
+    stamp(&mut bytes, 4);
+    format_uuid(&bytes)
+}
+
+/// A time-ordered (version 7) UUID: 48 bits of Unix milliseconds, then
+/// random bits.
+pub fn uuid_v7(rng: &mut impl Rng, millis: u64) -> String {
+    let mut bytes = [0u8; 16];
+    rng.fill(&mut bytes[6..]);
+    bytes[..6].copy_from_slice(&millis.to_be_bytes()[2..]);
+    stamp(&mut bytes, 7);
+    format_uuid(&bytes)
+}
+
+/// A ULID: 48 bits of Unix milliseconds and 80 random bits, as 26
+/// characters of Crockford base 32 that sort in time order.
+pub fn ulid(rng: &mut impl Rng, millis: u64) -> String {
+    let mut random = [0u8; 16];
+    rng.fill(&mut random[6..]);
+    let value = (u128::from(millis & 0xffff_ffff_ffff) << 80) | u128::from_be_bytes(random);
+    (0..26)
+        .map(|i| CROCKFORD[((value >> (125 - 5 * i)) & 31) as usize] as char)
+        .collect()
+}
+
+/// `length` characters picked from `alphabet`.
+pub fn string(rng: &mut impl Rng, alphabet: &[char], length: usize) -> String {
+    (0..length)
+        .map(|_| alphabet[rng.below(alphabet.len() as u64) as usize])
+        .collect()
+}
+
+/// The characters of a named character set, for `random.string`.
+pub fn charset(name: &str) -> Option<Vec<char>> {
+    let chars: Vec<char> = match name {
+        "alphanumeric" => ('0'..='9').chain('A'..='Z').chain('a'..='z').collect(),
+        "letters" => ('A'..='Z').chain('a'..='z').collect(),
+        "lowercase" => ('a'..='z').collect(),
+        "uppercase" => ('A'..='Z').collect(),
+        "digits" => ('0'..='9').collect(),
+        "hex" => ('0'..='9').chain('a'..='f').collect(),
+        "base64url" => NANOID_ALPHABET.chars().collect(),
+        // Printable ASCII without the space
+        "ascii" => ('!'..='~').collect(),
+        _ => return None,
+    };
+    Some(chars)
+}
+
+/// The distinct characters of a custom alphabet, in order.
+pub fn alphabet(text: &str) -> Vec<char> {
+    let mut chars: Vec<char> = Vec::new();
+    for c in text.chars() {
+        if !chars.contains(&c) {
+            chars.push(c);
+        }
+    }
+    chars
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Bytes from a simple counter, for repeatable tests.
+    pub struct Counter(pub u8);
+
+    impl Rng for Counter {
+        fn fill(&mut self, buffer: &mut [u8]) {
+            for byte in buffer {
+                *byte = self.0;
+                self.0 = self.0.wrapping_add(1);
+            }
+        }
+    }
+
+    #[test]
+    fn test_uuids() {
+        let v4 = uuid_v4(&mut Counter(0));
+        assert_eq!(v4, "00010203-0405-4607-8809-0a0b0c0d0e0f");
+        let v7 = uuid_v7(&mut Counter(0xff), 0x0189_abcd_ef01);
+        assert_eq!(v7, "0189abcd-ef01-7f00-8102-030405060708");
+    }
+
+    #[test]
+    fn test_ulid() {
+        let id = ulid(&mut Counter(0), 1_469_918_176_385);
+        assert_eq!(id.len(), 26);
+        // The time part of the ULID spec's example
+        assert_eq!(&id[..10], "01ARYZ6S41");
+        assert!(ulid(&mut Counter(0), 1) < ulid(&mut Counter(0xff), 2));
+    }
+
+    #[test]
+    fn test_below_and_between() {
+        let mut rng = Counter(0);
+        for n in [1, 2, 3, 7, 1000] {
+            assert!(rng.below(n) < n);
+        }
+        for _ in 0..20 {
+            let value = rng.between(-3, 3);
+            assert!((-3..=3).contains(&value));
+        }
+        rng.between(i64::MIN, i64::MAX);
+    }
+
+    #[test]
+    fn test_strings() {
+        let hex = charset("hex").unwrap();
+        let text = string(&mut Counter(0), &hex, 40);
+        assert_eq!(text.len(), 40);
+        assert!(text.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_eq!(charset("ascii").unwrap().len(), 94);
+        assert!(charset("emoji").is_none());
+        assert_eq!(alphabet("abca"), vec!['a', 'b', 'c']);
+        assert_eq!(NANOID_ALPHABET.len(), 64);
+    }
+}
@@ -0,0 +1,238 @@
+//! Random MCP Server (WASM)
+//!
+//! Identifiers and randomness that models can't produce themselves:
+//! UUIDs, ULIDs, nanoids, random strings, integers and dice rolls. Random
+//! bytes come from the host through WASI `random_get` (the `random`
+//! capability), which Harbor answers from `crypto.getRandomValues`.
+//!
+//! Build with:
+//!   cargo build --release --target wasm32-wasip1
+
+mod dice;
+mod ids;
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use harbor_mcp_sdk::{harbor_tool, Error, Server, ToolInput, ToolResult};
+use serde::Deserialize;
+use serde_json::json;
+
+use ids::Rng;
+
+/// Most values one call generates.
+const MAX_COUNT: usize = 100;
+const MAX_LENGTH: usize = 4096;
+const DEFAULT_NANOID_SIZE: usize = 21;
+const DEFAULT_STRING_LENGTH: usize = 32;
+
+struct HostRandom;
+
+impl Rng for HostRandom {
+    fn fill(&mut self, buffer: &mut [u8]) {
+        getrandom::getrandom(buffer).expect("the host provides random_get");
+    }
+}
+
+#[derive(Deserialize, ToolInput)]
+#[serde(deny_unknown_fields)]
+struct UuidArgs {
+    /// 4 for random UUIDs (default), 7 for time-ordered ones
+    version: Option<u8>,
+    /// How many to generate (default 1, at most 100)
+    count: Option<usize>,
+}
+
+#[derive(Deserialize, ToolInput)]
+#[serde(deny_unknown_fields)]
+struct UlidArgs {
+    /// How many to generate (default 1, at most 100)
+    count: Option<usize>,
+}
+
+#[derive(Deserialize, ToolInput)]
+#[serde(deny_unknown_fields)]
+struct NanoidArgs {
+    /// Length of each id (default 21)
+    size: Option<usize>,
+    /// Characters to use instead of A-Za-z0-9_-
+    alphabet: Option<String>,
+    /// How many to generate (default 1, at most 100)
+    count: Option<usize>,
+}
+
+#[derive(Deserialize, ToolInput)]
+#[serde(deny_unknown_fields)]
+struct StringArgs {
+    /// Length of each string (default 32)
+    length: Option<usize>,
+    /// alphanumeric (default), letters, lowercase, uppercase, digits, hex, base64url or ascii
+    charset: Option<String>,
+    /// Characters to use instead of a charset
+    alphabet: Option<String>,
+    /// How many to generate (default 1, at most 100)
+    count: Option<usize>,
+}
+
+#[derive(Deserialize, ToolInput)]
+#[serde(deny_unknown_fields)]
+struct IntegerArgs {
+    /// Smallest possible value
+    min: i64,
+    /// Largest possible value
+    max: i64,
+    /// How many to generate (default 1, at most 100)
+    count: Option<usize>,
+}
+
+#[derive(Deserialize, ToolInput)]
+#[serde(deny_unknown_fields)]
+struct DiceArgs {
+    /// Dice notation, e.g. "2d6+3", "d20", "4d6kh3" (keep highest 3) or "2d20kl1"
+    dice: String,
+}
+
+fn count(requested: Option<usize>) -> Result<usize, String> {
+    match requested.unwrap_or(1) {
+        count @ 1..=MAX_COUNT => Ok(count),
+        _ => Err(format!("count must be between 1 and {}", MAX_COUNT)),
+    }
+}
+
+fn length(requested: Option<usize>, default: usize) -> Result<usize, String> {
+    match requested.unwrap_or(default) {
+        length @ 1..=MAX_LENGTH => Ok(length),
+        _ => Err(format!("The length must be between 1 and {}", MAX_LENGTH)),
+    }
+}
+
+/// The characters of a custom alphabet, if one was given.
+fn custom_alphabet(text: Option<&str>) -> Result<Option<Vec<char>>, String> {
+    let Some(text) = text else { return Ok(None) };
+    let chars = ids::alphabet(text);
+    if chars.len() < 2 {
+        return Err("An alphabet needs at least 2 different characters".to_string());
+    }
+    Ok(Some(chars))
+}
+
+fn millis() -> Result<u64, Error> {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .map_err(|_| Error::new(-32000, "The host clock is unavailable"))
+}
+
+/// Generate `count` values with `f`, or return why the arguments are wrong.
+fn generate<T: serde::Serialize>(
+    key: &str,
+    count: Result<usize, String>,
+    mut f: impl FnMut(&mut HostRandom) -> T,
+) -> ToolResult {
+    match count {
+        Ok(count) => {
+            let values: Vec<T> = (0..count).map(|_| f(&mut HostRandom)).collect();
+            ToolResult::json(&json!({ key: values }))
+        }
+        Err(e) => ToolResult::error(e),
+    }
+}
+
+/// Generate UUIDs: version 4 (random, the default) or version 7 (ordered
+/// by creation time, good as database keys).
+#[harbor_tool(name = "random.uuid")]
+fn uuid(args: UuidArgs) -> Result<ToolResult, Error> {
+    let now = millis()?;
+    Ok(match args.version.unwrap_or(4) {
+        4 => generate("uuids", count(args.count), ids::uuid_v4),
+        7 => generate("uuids", count(args.count), |rng| ids::uuid_v7(rng, now)),
+        _ => ToolResult::error("version must be 4 or 7"),
+    })
+}
+
+/// Generate ULIDs: 26-character ids that sort in creation order.
+#[harbor_tool(name = "random.ulid")]
+fn ulid(args: UlidArgs) -> Result<ToolResult, Error> {
+    let now = millis()?;
+    Ok(generate("ulids", count(args.count), |rng| ids::ulid(rng, now)))
+}
+
+/// Generate nanoids: short URL-safe random ids, 21 characters by default.
+#[harbor_tool(name = "random.nanoid")]
+fn nanoid(args: NanoidArgs) -> Result<ToolResult, Error> {
+    let setup = custom_alphabet(args.alphabet.as_deref()).and_then(|alphabet| {
+        let alphabet = alphabet.unwrap_or_else(|| ids::NANOID_ALPHABET.chars().collect());
+        Ok((alphabet, length(args.size, DEFAULT_NANOID_SIZE)?))
+    });
+    let (alphabet, size) = match setup {
+        Ok(setup) => setup,
+        Err(e) => return Ok(ToolResult::error(e)),
+    };
+    Ok(generate("ids", count(args.count), |rng| ids::string(rng, &alphabet, size)))
+}
+
+/// Generate cryptographically secure random strings, such as passwords,
+/// tokens or test data, from a character set or a custom alphabet.
+#[harbor_tool(name = "random.string")]
+fn string(args: StringArgs) -> Result<ToolResult, Error> {
+    let setup = custom_alphabet(args.alphabet.as_deref()).and_then(|alphabet| {
+        let alphabet = match alphabet {
+            Some(alphabet) => alphabet,
+            None => {
+                let name = args.charset.as_deref().unwrap_or("alphanumeric");
+                ids::charset(name).ok_or_else(|| format!("Unknown charset '{}'", name))?
+            }
+        };
+        Ok((alphabet, length(args.length, DEFAULT_STRING_LENGTH)?))
+    });
+    let (alphabet, length) = match setup {
+        Ok(setup) => setup,
+        Err(e) => return Ok(ToolResult::error(e)),
+    };
+    Ok(generate("strings", count(args.count), |rng| ids::string(rng, &alphabet, length)))
+}
+
+/// Pick random integers between min and max, both included, every value
+/// equally likely.
+#[harbor_tool(name = "random.integer")]
+fn integer(args: IntegerArgs) -> Result<ToolResult, Error> {
+    if args.min > args.max {
+        return Ok(ToolResult::error("min must not be more than max"));
+    }
+    Ok(generate("values", count(args.count), |rng| rng.between(args.min, args.max)))
+}
+
+/// Roll dice in tabletop notation, such as 2d6+3, d20 or 4d6kh3 (roll
+/// four, keep the highest three). Returns every die and the total.
+#[harbor_tool(name = "random.dice")]
+fn roll(args: DiceArgs) -> Result<ToolResult, Error> {
+    let (rolled, total) = match dice::roll(&args.dice, &mut HostRandom) {
+        Ok(result) => result,
+        Err(e) => return Ok(ToolResult::error(e)),
+    };
+    let terms: Vec<_> = rolled
+        .iter()
+        .map(|term| {
+            let mut view = json!({ "term": term.term, "value": term.value });
+            if !term.rolls.is_empty() {
+                view["rolls"] = json!(term.rolls);
+            }
+            // Only worth showing when some dice were dropped
+            if term.kept.len() != term.rolls.len() {
+                view["kept"] = json!(term.kept);
+            }
+            view
+        })
+        .collect();
+    Ok(ToolResult::json(&json!({ "dice": args.dice.trim(), "total": total, "terms": terms })))
+}
+
+fn main() {
+    Server::new("mcp-random", "1.0.0")
+        .register(uuid_tool())
+        .register(ulid_tool())
+        .register(nanoid_tool())
+        .register(string_tool())
+        .register(integer_tool())
+        .register(roll_tool())
+        .run();
+}
@@ -0,0 +1,186 @@
+//! Regex MCP Server (WASM)
+//!
+//! Regular expressions for text wrangling: `regex.match` tests a pattern
+//! and returns the first match with its groups, `regex.extract` returns
+//! every match (or one group of each) and `regex.replace` substitutes
+//! them. Patterns use the Rust `regex` syntax, which matches in linear
+//! time; `patterns` has the limits on what is compiled and searched.
+//!
+//! Build with:
+//!   cargo build --release --target wasm32-wasip1
+
+mod patterns;
+
+use std::collections::HashSet;
+
+use harbor_mcp_sdk::{harbor_tool, Error, Server, ToolInput, ToolResult};
+use regex::{Captures, Regex};
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+
+use patterns::{Flags, Group, Offsets};
+
+const DEFAULT_LIMIT: usize = 100;
+const MAX_LIMIT: usize = 10_000;
+
+#[derive(Deserialize, ToolInput)]
+#[serde(deny_unknown_fields)]
+struct MatchArgs {
+    /// The regular expression, in Rust regex syntax (no look-around or backreferences)
+    pattern: String,
+    /// The text to search
+    text: String,
+    /// Flag letters: i (ignore case), m (^ and $ at line ends), s (. matches newlines), x (verbose)
+    #[serde(default)]
+    flags: String,
+    /// Only match the whole text, as if the pattern were anchored at both ends
+    #[serde(default)]
+    whole: bool,
+}
+
+#[derive(Deserialize, ToolInput)]
+#[serde(deny_unknown_fields)]
+struct ExtractArgs {
+    /// The regular expression, in Rust regex syntax (no look-around or backreferences)
+    pattern: String,
+    /// The text to search
+    text: String,
+    /// Flag letters: i (ignore case), m (^ and $ at line ends), s (. matches newlines), x (verbose)
+    #[serde(default)]
+    flags: String,
+    /// Capture group to return from each match, by number or name, instead of the whole match
+    group: Option<String>,
+    /// Return each distinct value once
+    #[serde(default)]
+    unique: bool,
+    /// Most values to return (default 100)
+    limit: Option<usize>,
+}
+
+#[derive(Deserialize, ToolInput)]
+#[serde(deny_unknown_fields)]
+struct ReplaceArgs {
+    /// The regular expression, in Rust regex syntax (no look-around or backreferences)
+    pattern: String,
+    /// The text to search
+    text: String,
+    /// What to put in place of each match; $1 or ${name} insert a group and $$ a dollar sign
+    replacement: String,
+    /// Flag letters: i (ignore case), m (^ and $ at line ends), s (. matches newlines), x (verbose)
+    #[serde(default)]
+    flags: String,
+    /// Insert the replacement as it is, without expanding $ references
+    #[serde(default)]
+    literal: bool,
+    /// Most matches to replace, from the start (default all)
+    limit: Option<usize>,
+}
+
+/// Check the text and compile the pattern. Errors are for the model to
+/// fix, so they come back as error results.
+fn prepare(pattern: &str, flags: &str, text: &str, whole: bool) -> Result<Regex, String> {
+    patterns::check_text(text)?;
+    patterns::compile(pattern, Flags::parse(flags)?, whole)
+}
+
+/// A match as `regex.match` returns it, with character offsets.
+fn describe(captures: &Captures, regex: &Regex, offsets: &mut Offsets) -> Value {
+    let found = captures.get(0).expect("group 0 always matches");
+    let groups: Vec<Option<&str>> = (1..captures.len())
+        .map(|index| captures.get(index).map(|group| group.as_str()))
+        .collect();
+    let mut described = json!({
+        "text": found.as_str(),
+        "start": offsets.at(found.start()),
+        "end": offsets.at(found.end()),
+        "groups": groups,
+    });
+    let named: Map<String, Value> = regex
+        .capture_names()
+        .flatten()
+        .map(|name| (name.to_string(), json!(captures.name(name).map(|m| m.as_str()))))
+        .collect();
+    if !named.is_empty() {
+        described["named"] = Value::Object(named);
+    }
+    described
+}
+
+/// Test whether a regular expression matches text, and return the first
+/// match with its position (in characters) and capture groups.
+#[harbor_tool(name = "regex.match")]
+fn match_text(args: MatchArgs) -> Result<ToolResult, Error> {
+    let regex = match prepare(&args.pattern, &args.flags, &args.text, args.whole) {
+        Ok(regex) => regex,
+        Err(e) => return Ok(ToolResult::error(e)),
+    };
+    let result = match regex.captures(&args.text) {
+        Some(captures) => {
+            let mut offsets = Offsets::new(&args.text);
+            json!({ "matched": true, "match": describe(&captures, &regex, &mut offsets) })
+        }
+        None => json!({ "matched": false }),
+    };
+    Ok(ToolResult::json(&result))
+}
+
+/// Extract every match of a regular expression from text, or one capture
+/// group of each match, in order.
+#[harbor_tool(name = "regex.extract")]
+fn extract(args: ExtractArgs) -> Result<ToolResult, Error> {
+    let regex = match prepare(&args.pattern, &args.flags, &args.text, false) {
+        Ok(regex) => regex,
+        Err(e) => return Ok(ToolResult::error(e)),
+    };
+    let group = match args.group.as_deref().map(|group| Group::parse(group, &regex)) {
+        Some(Err(e)) => return Ok(ToolResult::error(e)),
+        Some(Ok(group)) => group,
+        None => Group::Index(0),
+    };
+    let limit = args.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+    let mut values: Vec<&str> = Vec::new();
+    let mut seen = HashSet::new();
+    let mut total = 0;
+    let mut truncated = false;
+    for captures in regex.captures_iter(&args.text) {
+        total += 1;
+        // An optional group that didn't take part in this match
+        let Some(value) = group.get(&captures) else { continue };
+        if args.unique && !seen.insert(value.as_str()) {
+            continue;
+        }
+        if values.len() < limit {
+            values.push(value.as_str());
+        } else {
+            truncated = true;
+        }
+    }
+    let mut result = json!({ "matches": values, "total": total });
+    if truncated {
+        result["truncated"] = json!(true);
+    }
+    Ok(ToolResult::json(&result))
+}
+
+/// Replace matches of a regular expression in text. Returns the new text
+/// and how many matches were replaced.
+#[harbor_tool(name = "regex.replace")]
+fn replace(args: ReplaceArgs) -> Result<ToolResult, Error> {
+    let regex = match prepare(&args.pattern, &args.flags, &args.text, false) {
+        Ok(regex) => regex,
+        Err(e) => return Ok(ToolResult::error(e)),
+    };
+    let limit = args.limit.unwrap_or(usize::MAX);
+    match patterns::replace(&regex, &args.text, &args.replacement, args.literal, limit) {
+        Ok((text, count)) => Ok(ToolResult::json(&json!({ "text": text, "replacements": count }))),
+        Err(e) => Ok(ToolResult::error(e)),
+    }
+}
+
+fn main() {
+    Server::new("mcp-regex", "1.0.0")
+        .register(match_text_tool())
+        .register(extract_tool())
+        .register(replace_tool())
+        .run();
+}
@@ -0,0 +1,264 @@
+//! Compiling patterns within limits.
+//!
+//! The `regex` crate matches in time linear in the length of the text,
+//! with no backtracking, so a pattern like `(a+)+$` can't run away the way
+//! it can in a backtracking engine. The price is that look-around and
+//! backreferences aren't supported. What's limited here is how much gets
+//! compiled and searched: the pattern's length, nesting and compiled size,
+//! and the length of the text.
+
+use regex::{Regex, RegexBuilder};
+
+/// Longest pattern accepted, in characters.
+pub const MAX_PATTERN_CHARS: usize = 1000;
+/// Longest text searched, in bytes.
+pub const MAX_TEXT_BYTES: usize = 1 << 20;
+/// Longest text a replacement may produce, in bytes.
+pub const MAX_OUTPUT_BYTES: usize = 4 << 20;
+/// Most a pattern may compile to, in bytes. `\w{1000}` and similar
+/// repetitions of large classes are what reach it.
+const SIZE_LIMIT: usize = 1 << 20;
+/// Most memory the lazy DFA may use while searching.
+const DFA_SIZE_LIMIT: usize = 2 << 20;
+/// Deepest nesting of groups and repetitions.
+const NEST_LIMIT: u32 = 50;
+
+/// The flags a pattern is compiled with, from letters as in `/.../ims`.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Flags {
+    /// `i`: letters match either case
+    pub case_insensitive: bool,
+    /// `m`: `^` and `$` match at line boundaries
+    pub multi_line: bool,
+    /// `s`: `.` matches newlines too
+    pub dot_all: bool,
+    /// `x`: whitespace and `#` comments in the pattern are ignored
+    pub verbose: bool,
+}
+
+impl Flags {
+    /// Parse flag letters. `g` is accepted and ignored, since extracting
+    /// and replacing are global anyway.
+    pub fn parse(letters: &str) -> Result<Self, String> {
+        let mut flags = Flags::default();
+        for letter in letters.chars() {
+            match letter {
+                'i' => flags.case_insensitive = true,
+                'm' => flags.multi_line = true,
+                's' => flags.dot_all = true,
+                'x' => flags.verbose = true,
+                'g' => {}
+                c if c.is_whitespace() => {}
+                c => return Err(format!("Unknown flag '{}'; use i, m, s or x", c)),
+            }
+        }
+        Ok(flags)
+    }
+}
+
+/// Compile `pattern` with `flags`, anchored at both ends of the text when
+/// `whole` is set. Errors are messages to return to the caller.
+pub fn compile(pattern: &str, flags: Flags, whole: bool) -> Result<Regex, String> {
+    if pattern.chars().count() > MAX_PATTERN_CHARS {
+        return Err(format!("Patterns are limited to {} characters", MAX_PATTERN_CHARS));
+    }
+    let anchored;
+    let pattern = if whole {
+        anchored = format!(r"\A(?:{})\z", pattern);
+        &anchored
+    } else {
+        pattern
+    };
+    RegexBuilder::new(pattern)
+        .case_insensitive(flags.case_insensitive)
+        .multi_line(flags.multi_line)
+        .dot_matches_new_line(flags.dot_all)
+        .ignore_whitespace(flags.verbose)
+        .size_limit(SIZE_LIMIT)
+        .dfa_size_limit(DFA_SIZE_LIMIT)
+        .nest_limit(NEST_LIMIT)
+        .build()
+        .map_err(|e| match e {
+            regex::Error::CompiledTooBig(_) => "The pattern is too complex: it compiles to more \
+                than 1 MB. Use smaller repetition counts or narrower classes"
+                .to_string(),
+            e => format!("Invalid pattern: {}", e),
+        })
+}
+
+/// Check `text` is small enough to search.
+pub fn check_text(text: &str) -> Result<(), String> {
+    if text.len() > MAX_TEXT_BYTES {
+        return Err(format!(
+            "The text is {} bytes; at most {} can be searched",
+            text.len(),
+            MAX_TEXT_BYTES
+        ));
+    }
+    Ok(())
+}
+
+/// Replace the first `limit` matches of `regex` in `text`, expanding `$1`
+/// and `${name}` in `replacement` unless `literal`. Returns the new text
+/// and how many matches were replaced, or an error once the text grows
+/// past `MAX_OUTPUT_BYTES`.
+pub fn replace(
+    regex: &Regex,
+    text: &str,
+    replacement: &str,
+    literal: bool,
+    limit: usize,
+) -> Result<(String, usize), String> {
+    let mut output = String::with_capacity(text.len());
+    let mut last = 0;
+    let mut count = 0;
+    for captures in regex.captures_iter(text).take(limit) {
+        let found = captures.get(0).expect("group 0 always matches");
+        output.push_str(&text[last..found.start()]);
+        if literal {
+            output.push_str(replacement);
+        } else {
+            captures.expand(replacement, &mut output);
+        }
+        last = found.end();
+        count += 1;
+        if output.len() > MAX_OUTPUT_BYTES {
+            return Err(format!(
+                "The result would be more than {} bytes; replace fewer matches",
+                MAX_OUTPUT_BYTES
+            ));
+        }
+    }
+    output.push_str(&text[last..]);
+    Ok((output, count))
+}
+
+/// Which capture group a caller asked for: a number or a name.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Group {
+    Index(usize),
+    Name(String),
+}
+
+impl Group {
+    /// Parse a group reference, checking `regex` has it.
+    pub fn parse(group: &str, regex: &Regex) -> Result<Self, String> {
+        let group = group.trim().trim_start_matches('$');
+        let parsed = match group.parse::<usize>() {
+            Ok(index) if index < regex.captures_len() => Group::Index(index),
+            Ok(index) => {
+                let count = regex.captures_len() - 1;
+                return Err(format!("There is no group {}; the pattern has {}", index, count));
+            }
+            Err(_) if regex.capture_names().flatten().any(|name| name == group) => {
+                Group::Name(group.to_string())
+            }
+            Err(_) => return Err(format!("The pattern has no group named '{}'", group)),
+        };
+        Ok(parsed)
+    }
+
+    pub fn get<'t>(&self, captures: &regex::Captures<'t>) -> Option<regex::Match<'t>> {
+        match self {
+            Group::Index(index) => captures.get(*index),
+            Group::Name(name) => captures.name(name),
+        }
+    }
+}
+
+/// Turns byte offsets into character offsets, counting on from the last
+/// offset it was asked about when they come in increasing order.
+pub struct Offsets<'t> {
+    text: &'t str,
+    byte: usize,
+    chars: usize,
+}
+
+impl<'t> Offsets<'t> {
+    pub fn new(text: &'t str) -> Self {
+        Self {
+            text,
+            byte: 0,
+            chars: 0,
+        }
+    }
+
+    pub fn at(&mut self, byte: usize) -> usize {
+        if byte < self.byte {
+            self.byte = 0;
+            self.chars = 0;
+        }
+        self.chars += self.text[self.byte..byte].chars().count();
+        self.byte = byte;
+        self.chars
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flags() {
+        let flags = Flags::parse("gim").unwrap();
+        assert!(flags.case_insensitive && flags.multi_line && !flags.dot_all);
+        assert!(Flags::parse("q").unwrap_err().contains("'q'"));
+    }
+
+    #[test]
+    fn test_compile() {
+        let regex = compile("b+", Flags::parse("i").unwrap(), false).unwrap();
+        assert!(regex.is_match("aBBc"));
+        let whole = compile("b+", Flags::default(), true).unwrap();
+        assert!(!whole.is_match("abbc"));
+        assert!(whole.is_match("bbb"));
+        // Look-around isn't supported
+        assert!(compile("a(?=b)", Flags::default(), false).unwrap_err().starts_with("Invalid"));
+        let huge = compile(r"(?:\w{100}){100}", Flags::default(), false);
+        assert!(huge.unwrap_err().contains("complex"));
+        let nested = format!("{}a{}", "(".repeat(60), ")".repeat(60));
+        assert!(compile(&nested, Flags::default(), false).is_err());
+        assert!(compile(&"a".repeat(1001), Flags::default(), false).is_err());
+    }
+
+    #[test]
+    fn test_catastrophic_pattern_is_fast() {
+        let regex = compile("(a+)+$", Flags::default(), false).unwrap();
+        let text = format!("{}!", "a".repeat(100_000));
+        assert!(!regex.is_match(&text));
+    }
+
+    #[test]
+    fn test_replace() {
+        let regex = compile(r"(?P<y>\d{4})-(\d{2})", Flags::default(), false).unwrap();
+        let text = "2024-01 and 2025-06";
+        let (replaced, count) = replace(&regex, text, "$2/${y}", false, usize::MAX).unwrap();
+        assert_eq!((replaced.as_str(), count), ("01/2024 and 06/2025", 2));
+        let (replaced, count) = replace(&regex, text, "$2", true, 1).unwrap();
+        assert_eq!((replaced.as_str(), count), ("$2 and 2025-06", 1));
+        let doubling = compile(".", Flags::default(), false).unwrap();
+        let big = "a".repeat(MAX_TEXT_BYTES);
+        assert!(replace(&doubling, &big, "$0$0$0$0$0", false, usize::MAX).is_err());
+    }
+
+    #[test]
+    fn test_group() {
+        let regex = compile(r"(?P<year>\d{4})-(\d{2})", Flags::default(), false).unwrap();
+        assert_eq!(Group::parse("2", &regex), Ok(Group::Index(2)));
+        assert_eq!(Group::parse("year", &regex), Ok(Group::Name("year".to_string())));
+        assert!(Group::parse("3", &regex).is_err());
+        assert!(Group::parse("month", &regex).is_err());
+        let captures = regex.captures("on 2024-01").unwrap();
+        assert_eq!(Group::Name("year".to_string()).get(&captures).unwrap().as_str(), "2024");
+    }
+
+    #[test]
+    fn test_offsets() {
+        let text = "héllo wörld";
+        let mut offsets = Offsets::new(text);
+        assert_eq!(offsets.at(0), 0);
+        assert_eq!(offsets.at(7), 6);
+        assert_eq!(offsets.at(13), 11);
+        assert_eq!(offsets.at(3), 2);
+    }
+}
@@ -0,0 +1,145 @@
+//! Schedule MCP Server (WASM component)
+//!
+//! Lets chat agents set up tool calls that run on their own, such as
+//! checking a feed every hour: `schedule.create` has the bridge call a
+//! tool on a cron expression or a phrase like "weekdays at 9am",
+//! `schedule.list` shows the schedules with their latest results, and
+//! `schedule.delete` stops one. The bridge runs and keeps the schedules
+//! (see the `harbor:mcp/schedule` import), so they run whether or not a
+//! chat is open.
+//!
+//! Build with:
+//!   cargo build --release --target wasm32-wasip2
+
+mod view;
+
+use std::collections::BTreeMap;
+
+use harbor_mcp_sdk::{harbor_tool, Error, Locale, Server, ToolInput, ToolResult};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+wit_bindgen::generate!({ path: "../../wit", world: "mcp-server" });
+
+use harbor::mcp::schedule;
+
+const DEFAULT_RESULTS: usize = 1;
+/// As many as the bridge keeps.
+const MAX_RESULTS: usize = 10;
+
+#[derive(Deserialize, ToolInput)]
+#[serde(deny_unknown_fields)]
+struct CreateArgs {
+    /// What the schedule is for, e.g. "Check the Rust blog feed"
+    name: String,
+    /// ID of the server with the tool, e.g. "fetch-wasm"
+    server_id: String,
+    /// The tool to call, e.g. "http.get"
+    tool: String,
+    /// Arguments for the tool, the same on every run
+    #[serde(default)]
+    args: BTreeMap<String, Value>,
+    /// When to run: a phrase such as "every hour", "every 15 minutes" or
+    /// "weekdays at 9am", or a cron expression such as "0 * * * *"
+    schedule: String,
+    /// IANA time zone the schedule is in, e.g. "Europe/Paris"; defaults to
+    /// the user's
+    timezone: Option<String>,
+}
+
+#[derive(Deserialize, ToolInput)]
+#[serde(deny_unknown_fields)]
+struct ListArgs {
+    /// Latest results to show for each schedule (default 1, at most 10)
+    results: Option<usize>,
+}
+
+#[derive(Deserialize, ToolInput)]
+#[serde(deny_unknown_fields)]
+struct DeleteArgs {
+    /// The schedule's id, from schedule.create or schedule.list
+    id: String,
+}
+
+fn parse(text: &str) -> Result<Value, String> {
+    serde_json::from_str(text).map_err(|e| format!("Unexpected answer from the bridge: {}", e))
+}
+
+fn create_schedule(args: CreateArgs) -> Result<Value, String> {
+    let definition = json!({
+        "name": args.name,
+        "server_id": args.server_id,
+        "tool": args.tool,
+        "args": args.args,
+        "schedule": args.schedule,
+        "timezone": args.timezone.or_else(|| Locale::from_env().timezone),
+    });
+    let created = parse(&schedule::create(&definition.to_string())?)?;
+    Ok(view::view(&created, 0))
+}
+
+fn list_schedules(args: ListArgs) -> Result<Value, String> {
+    let results = args.results.unwrap_or(DEFAULT_RESULTS).min(MAX_RESULTS);
+    let listed = parse(&schedule::list()?)?;
+    let shown: Vec<Value> = listed
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|s| view::view(s, results))
+        .collect();
+    Ok(json!({ "schedules": shown }))
+}
+
+fn delete_schedule(args: DeleteArgs) -> Result<Value, String> {
+    if schedule::delete(&args.id)? {
+        Ok(json!({ "deleted": args.id }))
+    } else {
+        Err(format!("There is no schedule with id '{}'", args.id))
+    }
+}
+
+fn reply(result: Result<Value, String>) -> Result<ToolResult, Error> {
+    Ok(match result {
+        Ok(value) => ToolResult::json(&value),
+        Err(e) => ToolResult::error(e),
+    })
+}
+
+/// Have Harbor call a tool on a schedule, such as fetching a feed every
+/// hour, even when no chat is open. Runs can be at most every 5 minutes.
+/// Returns the schedule's id and when it first runs; results are kept and
+/// shown by schedule.list.
+#[harbor_tool(name = "schedule.create")]
+fn create(args: CreateArgs) -> Result<ToolResult, Error> {
+    reply(create_schedule(args))
+}
+
+/// List the schedules made here, each with its next run and its latest
+/// results, newest first.
+#[harbor_tool(name = "schedule.list")]
+fn list(args: ListArgs) -> Result<ToolResult, Error> {
+    reply(list_schedules(args))
+}
+
+/// Delete a schedule so it no longer runs.
+#[harbor_tool(name = "schedule.delete")]
+fn delete(args: DeleteArgs) -> Result<ToolResult, Error> {
+    reply(delete_schedule(args))
+}
+
+fn server() -> Server {
+    Server::new("mcp-schedule", "1.0.0")
+        .register(create_tool())
+        .register(list_tool())
+        .register(delete_tool())
+}
+
+struct Component;
+
+impl exports::harbor::mcp::server::Guest for Component {
+    fn handle(request: String) -> String {
+        server().handle(&request)
+    }
+}
+
+export!(Component);
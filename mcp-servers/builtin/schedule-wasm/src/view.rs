@@ -0,0 +1,95 @@
+//! Schedules as the tools show them.
+//!
+//! The bridge hands schedules over as it keeps them, with times in Unix
+//! milliseconds and the last ten results. Tools show ISO 8601 times and
+//! only as many results as were asked for.
+
+use harbor_mcp_sdk::rfc3339;
+use serde_json::{json, Value};
+
+/// A time in Unix milliseconds as an RFC 3339 UTC time.
+fn time(value: &Value) -> Value {
+    value.as_i64().map_or(Value::Null, |ms| Value::from(rfc3339(ms.div_euclid(1000))))
+}
+
+/// One run, with its start as an ISO time.
+fn run(run: &Value) -> Value {
+    let mut shown = json!({
+        "at": time(&run["started_at"]),
+        "ok": run["ok"],
+        "duration_ms": run["duration_ms"],
+    });
+    for key in ["result", "error"] {
+        if let Some(value) = run.get(key) {
+            shown[key] = value.clone();
+        }
+    }
+    if run["truncated"] == Value::Bool(true) {
+        shown["truncated"] = Value::Bool(true);
+    }
+    shown
+}
+
+/// A schedule with readable times and its `results` latest results.
+/// `next_run` is null for a schedule that won't run again.
+pub fn view(schedule: &Value, results: usize) -> Value {
+    let mut shown = json!({
+        "id": schedule["id"],
+        "name": schedule["name"],
+        "server_id": schedule["server_id"],
+        "tool": schedule["tool"],
+        "args": schedule["args"],
+        "schedule": schedule["schedule"],
+        "next_run": time(&schedule["next_run_at"]),
+    });
+    if let Some(timezone) = schedule.get("timezone") {
+        shown["timezone"] = timezone.clone();
+    }
+    if results > 0 {
+        let runs = schedule["results"].as_array().into_iter().flatten();
+        shown["latest_runs"] = runs.take(results).map(run).collect();
+    }
+    shown
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_view() {
+        let stored = json!({
+            "id": "schedule-1",
+            "name": "Rust blog",
+            "owner": "schedule-wasm",
+            "server_id": "fetch-wasm",
+            "tool": "http.get",
+            "args": { "url": "https://blog.rust-lang.org/feed.xml" },
+            "schedule": "every hour",
+            "timezone": "Europe/Paris",
+            "created_at": 1_717_400_000_000_i64,
+            "next_run_at": 1_717_405_200_000_i64,
+            "results": [
+                { "started_at": 1_717_401_600_000_i64, "duration_ms": 412, "ok": false,
+                  "error": "Request failed", "truncated": false },
+                { "started_at": 1_717_398_000_000_i64, "duration_ms": 380, "ok": true,
+                  "result": "<feed>", "truncated": true }
+            ]
+        });
+        let shown = view(&stored, 5);
+        assert_eq!(shown["next_run"], "2024-06-03T09:00:00Z");
+        assert_eq!(shown["timezone"], "Europe/Paris");
+        assert!(shown.get("owner").is_none());
+        assert_eq!(
+            shown["latest_runs"],
+            json!([
+                { "at": "2024-06-03T08:00:00Z", "ok": false, "duration_ms": 412, "error": "Request failed" },
+                { "at": "2024-06-03T07:00:00Z", "ok": true, "duration_ms": 380, "result": "<feed>", "truncated": true }
+            ])
+        );
+
+        let bare = view(&json!({ "id": "schedule-2", "next_run_at": null }), 0);
+        assert_eq!(bare["next_run"], Value::Null);
+        assert!(bare.get("latest_runs").is_none());
+    }
+}
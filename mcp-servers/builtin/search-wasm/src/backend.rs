@@ -0,0 +1,162 @@
+//! The search APIs: how each is asked and what its results look like.
+
+use harbor_mcp_sdk::encode_query;
+use serde_json::Value;
+
+use crate::text;
+
+/// The most results one request asks for; Brave's limit per page.
+pub const MAX_PER_REQUEST: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Backend {
+    Brave,
+    SerpApi,
+    Searxng,
+}
+
+/// One result, as every backend's is shown.
+#[derive(Debug, PartialEq)]
+pub struct Hit {
+    pub title: String,
+    pub url: String,
+    pub snippet: String,
+}
+
+impl Backend {
+    /// In the order one is picked when `SEARCH_BACKEND` isn't set.
+    pub const ALL: [Backend; 3] = [Backend::Brave, Backend::SerpApi, Backend::Searxng];
+
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "brave" => Ok(Backend::Brave),
+            "serpapi" => Ok(Backend::SerpApi),
+            "searxng" => Ok(Backend::Searxng),
+            other => Err(format!("Unknown search backend '{}'; use brave, serpapi or searxng", other)),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Backend::Brave => "brave",
+            Backend::SerpApi => "serpapi",
+            Backend::Searxng => "searxng",
+        }
+    }
+
+    /// The secret holding the backend's key, or the instance's URL.
+    pub fn secret(self) -> &'static str {
+        match self {
+            Backend::Brave => "BRAVE_API_KEY",
+            Backend::SerpApi => "SERPAPI_API_KEY",
+            Backend::Searxng => "SEARXNG_URL",
+        }
+    }
+
+    /// The URL and headers of a search for `query`, given the backend's
+    /// secret.
+    pub fn request(self, secret: &str, query: &str, count: usize) -> Result<(String, Vec<(String, String)>), String> {
+        let query = encode_query(query);
+        let accept = ("Accept".to_string(), "application/json".to_string());
+        Ok(match self {
+            Backend::Brave => (
+                format!("https://api.search.brave.com/res/v1/web/search?q={}&count={}", query, count),
+                vec![accept, ("X-Subscription-Token".to_string(), secret.to_string())],
+            ),
+            Backend::SerpApi => (
+                format!(
+                    "https://serpapi.com/search.json?engine=google&q={}&num={}&api_key={}",
+                    query,
+                    count,
+                    encode_query(secret)
+                ),
+                vec![accept],
+            ),
+            // SearXNG has no result count; results are cut afterwards
+            Backend::Searxng => (format!("{}/search?q={}&format=json", text::base_url(secret)?, query), vec![accept]),
+        })
+    }
+
+    /// The results in a response, in order.
+    pub fn hits(self, response: &Value) -> Vec<Hit> {
+        let (list, url, snippet) = match self {
+            Backend::Brave => (&response["web"]["results"], "url", "description"),
+            Backend::SerpApi => (&response["organic_results"], "link", "snippet"),
+            Backend::Searxng => (&response["results"], "url", "content"),
+        };
+        list.as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|item| {
+                Some(Hit {
+                    title: text::plain(item["title"].as_str().unwrap_or_default()),
+                    url: item[url].as_str()?.to_string(),
+                    snippet: text::plain(item[snippet].as_str().unwrap_or_default()),
+                })
+            })
+            .collect()
+    }
+
+    /// The error a backend reports in its body, if any.
+    pub fn error(self, response: &Value) -> Option<String> {
+        let message = match self {
+            Backend::Brave => response["error"]["detail"].as_str(),
+            Backend::SerpApi => response["error"].as_str(),
+            Backend::Searxng => None,
+        };
+        message.map(str::to_string)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Backend::parse(" Brave ").unwrap(), Backend::Brave);
+        assert_eq!(Backend::parse("searxng").unwrap().secret(), "SEARXNG_URL");
+        assert!(Backend::parse("bing").is_err());
+    }
+
+    #[test]
+    fn test_request() {
+        let (url, headers) = Backend::Brave.request("key", "rust wasm", 5).unwrap();
+        assert_eq!(url, "https://api.search.brave.com/res/v1/web/search?q=rust%20wasm&count=5");
+        assert_eq!(headers[1], ("X-Subscription-Token".to_string(), "key".to_string()));
+
+        let (url, _) = Backend::Searxng.request("http://localhost:8888/", "rust", 5).unwrap();
+        assert_eq!(url, "http://localhost:8888/search?q=rust&format=json");
+        assert!(Backend::Searxng.request("localhost:8888", "rust", 5).is_err());
+    }
+
+    #[test]
+    fn test_hits() {
+        let brave = json!({ "web": { "results": [{
+            "title": "The <strong>Rust</strong> Book",
+            "url": "https://doc.rust-lang.org/book/",
+            "description": "Learn <strong>Rust</strong>"
+        }] } });
+        assert_eq!(
+            Backend::Brave.hits(&brave),
+            vec![Hit {
+                title: "The Rust Book".to_string(),
+                url: "https://doc.rust-lang.org/book/".to_string(),
+                snippet: "Learn Rust".to_string(),
+            }]
+        );
+
+        let serpapi = json!({ "organic_results": [
+            { "title": "Rust", "link": "https://www.rust-lang.org/" },
+            { "title": "No link" }
+        ] });
+        let hits = Backend::SerpApi.hits(&serpapi);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].snippet, "");
+
+        assert!(Backend::Searxng.hits(&json!({})).is_empty());
+        let error = Backend::SerpApi.error(&json!({ "error": "Invalid API key." }));
+        assert_eq!(error.as_deref(), Some("Invalid API key."));
+    }
+}
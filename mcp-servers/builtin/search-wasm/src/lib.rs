@@ -0,0 +1,203 @@
+//! Web Search MCP Server (WASM component)
+//!
+//! A `search.web` tool over a choice of search APIs (see `backend`): Brave
+//! Search, SerpAPI (Google results) or a SearXNG instance. The user sets
+//! one up by storing its key, or the instance's URL, in the bridge's
+//! secrets store; the server reads it through the `harbor:mcp/secrets`
+//! import, so keys stay out of the environment and the module.
+//!
+//! Results come back as one text content block each, title, URL and
+//! snippet, at most `MAX_RESULTS` of them.
+//!
+//! Build with:
+//!   cargo build --release --target wasm32-wasip2
+
+mod backend;
+mod text;
+
+use harbor_mcp_sdk::{harbor_tool, Error, Server, ToolInput, ToolResult};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use backend::{Backend, Hit};
+
+wit_bindgen::generate!({ path: "../../wit", world: "mcp-server" });
+
+use harbor::mcp::{http, secrets};
+
+/// Names the backend to use when more than one is set up.
+const BACKEND_SECRET: &str = "SEARCH_BACKEND";
+
+const DEFAULT_RESULTS: usize = 5;
+const MAX_RESULTS: usize = backend::MAX_PER_REQUEST;
+/// Characters of a snippet shown.
+const MAX_SNIPPET_CHARS: usize = 300;
+
+#[derive(Deserialize, ToolInput)]
+#[serde(deny_unknown_fields)]
+struct SearchArgs {
+    /// What to search the web for
+    query: String,
+    /// Most results to return (default 5, at most 20)
+    max_results: Option<usize>,
+    /// "brave", "serpapi" or "searxng" (default the one set up, or
+    /// SEARCH_BACKEND)
+    backend: Option<String>,
+}
+
+#[derive(Deserialize, ToolInput)]
+#[serde(deny_unknown_fields)]
+struct BackendsArgs {}
+
+/// A secret's value, if the user set it.
+fn secret(name: &str) -> Option<String> {
+    secrets::get_secret(name).filter(|value| !value.trim().is_empty())
+}
+
+/// The backend to search with and its secret: the one asked for, else
+/// `SEARCH_BACKEND`'s, else the first set up.
+fn choose(asked: Option<&str>) -> Result<(Backend, String), String> {
+    let named = asked.map(str::to_string).or_else(|| secret(BACKEND_SECRET));
+    if let Some(name) = named {
+        let backend = Backend::parse(&name)?;
+        let value = secret(backend.secret()).ok_or_else(|| {
+            format!("{} isn't set up; store {} for 'search-wasm'", backend.name(), backend.secret())
+        })?;
+        return Ok((backend, value));
+    }
+    Backend::ALL
+        .into_iter()
+        .find_map(|backend| Some((backend, secret(backend.secret())?)))
+        .ok_or_else(|| {
+            "No search backend is set up; store BRAVE_API_KEY, SERPAPI_API_KEY or SEARXNG_URL \
+             for 'search-wasm' with the bridge's secrets.set"
+                .to_string()
+        })
+}
+
+/// Search, failing with the backend's message on an error status.
+fn search(backend: Backend, secret: &str, query: &str, count: usize) -> Result<Vec<Hit>, String> {
+    let (url, headers) = backend.request(secret, query, count)?;
+    let response = http::fetch(&http::Request {
+        method: "GET".to_string(),
+        url,
+        headers,
+        body: None,
+    })
+    .map_err(|e| match backend {
+        Backend::Searxng if e.contains("Network access denied") => {
+            format!("{}; add the SearXNG instance's host to the server's network hosts", e)
+        }
+        _ => e,
+    })?;
+    let value: Value = serde_json::from_slice(&response.body).unwrap_or(Value::Null);
+    if response.status >= 400 {
+        let message = backend
+            .error(&value)
+            .unwrap_or_else(|| text::cut(&String::from_utf8_lossy(&response.body), 200));
+        let hint = match (backend, response.status) {
+            (Backend::Searxng, 403) => "; allow the json format in the instance's search.formats".to_string(),
+            (_, 401 | 403) => format!("; check {}", backend.secret()),
+            (_, 429) => "; the plan's rate limit is used up, try again later".to_string(),
+            _ => String::new(),
+        };
+        return Err(format!("{} error ({}): {}{}", backend.name(), response.status, message, hint));
+    }
+    if let Some(message) = backend.error(&value) {
+        return Err(format!("{} error: {}", backend.name(), message));
+    }
+    Ok(backend.hits(&value))
+}
+
+/// A result as its own content block.
+fn block(n: usize, hit: &Hit) -> Value {
+    let mut shown = format!("{}. {}\n{}", n, hit.title, hit.url);
+    if !hit.snippet.is_empty() {
+        shown.push('\n');
+        shown.push_str(&text::cut(&hit.snippet, MAX_SNIPPET_CHARS));
+    }
+    json!({ "type": "text", "text": shown })
+}
+
+fn search_web(args: SearchArgs) -> Result<ToolResult, String> {
+    let query = args.query.trim();
+    if query.is_empty() {
+        return Err("Give a search query".to_string());
+    }
+    let max_results = args.max_results.unwrap_or(DEFAULT_RESULTS).clamp(1, MAX_RESULTS);
+    let (backend, secret) = choose(args.backend.as_deref())?;
+    let mut hits = search(backend, &secret, query, max_results)?;
+    hits.truncate(max_results);
+    if hits.is_empty() {
+        return Ok(ToolResult::text(format!("No results for '{}'", query)));
+    }
+    Ok(ToolResult {
+        content: hits.iter().enumerate().map(|(i, hit)| block(i + 1, hit)).collect(),
+        is_error: false,
+    })
+}
+
+fn list_backends() -> Value {
+    let chosen = choose(None).ok().map(|(backend, _)| backend.name());
+    let backends: Vec<Value> = Backend::ALL
+        .into_iter()
+        .map(|backend| {
+            json!({
+                "name": backend.name(),
+                "secret": backend.secret(),
+                "configured": secret(backend.secret()).is_some(),
+            })
+        })
+        .collect();
+    json!({ "backends": backends, "default": chosen })
+}
+
+/// Search the web. Returns each result (title, URL and snippet) as its
+/// own text block; fetch a page for more.
+#[harbor_tool(name = "search.web")]
+fn web(args: SearchArgs) -> Result<ToolResult, Error> {
+    Ok(search_web(args).unwrap_or_else(ToolResult::error))
+}
+
+/// List the search backends, which are set up, and which is used by
+/// default.
+#[harbor_tool(name = "search.backends")]
+fn backends(_args: BackendsArgs) -> Result<ToolResult, Error> {
+    Ok(ToolResult::json(&list_backends()))
+}
+
+fn server() -> Server {
+    Server::new("mcp-search", "1.0.0")
+        .register(web_tool())
+        .register(backends_tool())
+}
+
+struct Component;
+
+impl exports::harbor::mcp::server::Guest for Component {
+    fn handle(request: String) -> String {
+        server().handle(&request)
+    }
+}
+
+export!(Component);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_block() {
+        let hit = Hit {
+            title: "The Rust Book".to_string(),
+            url: "https://doc.rust-lang.org/book/".to_string(),
+            snippet: "x".repeat(400),
+        };
+        let text = block(2, &hit)["text"].as_str().unwrap().to_string();
+        assert!(text.starts_with("2. The Rust Book\nhttps://doc.rust-lang.org/book/\nxxx"));
+        assert!(text.ends_with("x..."));
+
+        let bare = Hit { snippet: String::new(), ..hit };
+        assert_eq!(block(1, &bare)["text"], "1. The Rust Book\nhttps://doc.rust-lang.org/book/");
+    }
+}
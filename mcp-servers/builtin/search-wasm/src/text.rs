@@ -0,0 +1,81 @@
+//! Text in and out of search APIs: snippets with markup removed and the
+//! SearXNG instance's URL.
+
+/// A snippet as plain text: tags such as Brave's `<strong>` dropped, the
+/// common entities decoded and whitespace collapsed.
+pub fn plain(snippet: &str) -> String {
+    let mut text = String::with_capacity(snippet.len());
+    let mut in_tag = false;
+    let mut chars = snippet.chars().peekable();
+    while let Some(c) = chars.next() {
+        // A tag starts with a name, '/' or '!'; "a < b" is text
+        let opens = chars.peek().is_some_and(|n| n.is_ascii_alphabetic() || *n == '/' || *n == '!');
+        match c {
+            '<' if !in_tag && opens => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            c if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    let text = text
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&#x27;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&");
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Cut `text` to `max_chars` characters, marking the cut.
+pub fn cut(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((end, _)) => format!("{}...", text[..end].trim_end()),
+        None => text.to_string(),
+    }
+}
+
+/// The SearXNG instance's base URL, without a trailing slash: http or
+/// https, with no query or fragment.
+pub fn base_url(url: &str) -> Result<String, String> {
+    let url = url.trim().trim_end_matches('/');
+    let rest = url
+        .strip_prefix("https://")
+        .or_else(|| url.strip_prefix("http://"))
+        .ok_or_else(|| format!("SEARXNG_URL must start with http:// or https://, not '{}'", url))?;
+    if rest.is_empty() || rest.starts_with('/') || rest.contains(['?', '#', ' ']) {
+        return Err(format!("SEARXNG_URL isn't an instance's address: '{}'", url));
+    }
+    Ok(url.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plain() {
+        assert_eq!(
+            plain("The <strong>Rust</strong> &amp; WebAssembly\n  book &#39;24"),
+            "The Rust & WebAssembly book '24"
+        );
+        assert_eq!(plain("a < b<br/>"), "a < b");
+        assert_eq!(plain("&lt;div&gt;"), "<div>");
+    }
+
+    #[test]
+    fn test_cut() {
+        assert_eq!(cut("hello world", 6), "hello...");
+        assert_eq!(cut("hello", 6), "hello");
+    }
+
+    #[test]
+    fn test_base_url() {
+        assert_eq!(base_url("http://localhost:8888/").unwrap(), "http://localhost:8888");
+        assert_eq!(base_url(" https://search.example.org/searx ").unwrap(), "https://search.example.org/searx");
+        assert!(base_url("search.example.org").is_err());
+        assert!(base_url("https://").is_err());
+        assert!(base_url("https://example.org/?q=").is_err());
+    }
+}
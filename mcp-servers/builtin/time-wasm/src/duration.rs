@@ -0,0 +1,236 @@
+//! Durations for `time.add`, either in words ("1d 2h", "3 weeks and 4
+//! days", "-90m") or in ISO 8601 ("P1Y2M", "PT30M"). Years and months
+//! move along the calendar and weeks and days keep the wall-clock time,
+//! so what they add depends on the zone; hours, minutes and seconds are
+//! exact. A leading `-` negates the whole duration.
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Duration {
+    pub months: i64,
+    pub days: i64,
+    pub seconds: i64,
+}
+
+#[derive(Clone, Copy)]
+enum Field {
+    Months,
+    Days,
+    Seconds,
+}
+
+/// Ten thousand years, in each field.
+const MAX_MONTHS: i64 = 12 * 10_000;
+const MAX_DAYS: i64 = 366 * 10_000;
+const MAX_SECONDS: i64 = MAX_DAYS * 86_400;
+
+fn too_long() -> String {
+    "Durations can be at most 10,000 years".to_string()
+}
+
+fn invalid(text: &str) -> String {
+    format!(
+        "Couldn't read '{}' as a duration; use e.g. \"1d 2h 30m\", \"-3 weeks\" or \"P1M\"",
+        text
+    )
+}
+
+/// The field a unit adds to and how many of that field's units it is.
+fn unit(name: &str) -> Option<(Field, i64)> {
+    Some(match name {
+        "y" | "yr" | "yrs" | "year" | "years" => (Field::Months, 12),
+        "mo" | "mos" | "mon" | "month" | "months" => (Field::Months, 1),
+        "w" | "wk" | "wks" | "week" | "weeks" => (Field::Days, 7),
+        "d" | "day" | "days" => (Field::Days, 1),
+        "h" | "hr" | "hrs" | "hour" | "hours" => (Field::Seconds, 3600),
+        "m" | "min" | "mins" | "minute" | "minutes" => (Field::Seconds, 60),
+        "s" | "sec" | "secs" | "second" | "seconds" => (Field::Seconds, 1),
+        _ => return None,
+    })
+}
+
+impl Duration {
+    fn add(&mut self, field: Field, amount: i64, factor: i64) -> Result<(), String> {
+        let slot = match field {
+            Field::Months => &mut self.months,
+            Field::Days => &mut self.days,
+            Field::Seconds => &mut self.seconds,
+        };
+        *slot = amount
+            .checked_mul(factor)
+            .and_then(|added| slot.checked_add(added))
+            .ok_or_else(too_long)?;
+        Ok(())
+    }
+
+    pub fn is_calendar(&self) -> bool {
+        self.months != 0 || self.days != 0
+    }
+}
+
+/// The number at the start of `text` and what follows it.
+fn number(text: &str) -> Option<(i64, &str)> {
+    let digits = text.find(|c: char| !c.is_ascii_digit()).unwrap_or(text.len());
+    if digits == 0 {
+        return None;
+    }
+    // Only too many digits fail to parse; the limits catch those
+    let amount = text[..digits].parse().unwrap_or(i64::MAX);
+    Some((amount, &text[digits..]))
+}
+
+fn words(text: &str, original: &str) -> Result<Duration, String> {
+    let mut duration = Duration::default();
+    let mut rest = text;
+    let mut terms = 0;
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_whitespace() || c == ',');
+        if let Some(after) = rest.strip_prefix("and ") {
+            rest = after;
+            continue;
+        }
+        if rest.is_empty() {
+            break;
+        }
+        let (amount, after) = number(rest).ok_or_else(|| invalid(original))?;
+        let after = after.trim_start();
+        let letters = after.find(|c: char| !c.is_alphabetic()).unwrap_or(after.len());
+        let name = &after[..letters];
+        if name.is_empty() {
+            return Err(invalid(original));
+        }
+        let (field, factor) = unit(&name.to_lowercase()).ok_or_else(|| {
+            format!("Unknown unit '{}' in the duration; use y, mo, w, d, h, m or s", name)
+        })?;
+        duration.add(field, amount, factor)?;
+        rest = &after[letters..];
+        terms += 1;
+    }
+    if terms == 0 {
+        return Err(invalid(original));
+    }
+    Ok(duration)
+}
+
+/// ISO 8601 `[nY][nM][nW][nD][T[nH][nM][nS]]`, after the `P`.
+fn iso(text: &str, original: &str) -> Result<Duration, String> {
+    let mut duration = Duration::default();
+    let mut rest = text;
+    let mut time = false;
+    let mut terms = 0;
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix(['T', 't']).filter(|_| !time) {
+            time = true;
+            rest = after;
+            continue;
+        }
+        let (amount, after) = number(rest).ok_or_else(|| invalid(original))?;
+        let designator = after.chars().next().map(|c| c.to_ascii_uppercase());
+        let (field, factor) = match (time, designator) {
+            (false, Some('Y')) => (Field::Months, 12),
+            (false, Some('M')) => (Field::Months, 1),
+            (false, Some('W')) => (Field::Days, 7),
+            (false, Some('D')) => (Field::Days, 1),
+            (true, Some('H')) => (Field::Seconds, 3600),
+            (true, Some('M')) => (Field::Seconds, 60),
+            (true, Some('S')) => (Field::Seconds, 1),
+            _ => return Err(invalid(original)),
+        };
+        duration.add(field, amount, factor)?;
+        rest = &after[1..];
+        terms += 1;
+    }
+    if terms == 0 {
+        return Err(invalid(original));
+    }
+    Ok(duration)
+}
+
+/// Read a duration in words or ISO 8601.
+pub fn parse(text: &str) -> Result<Duration, String> {
+    let trimmed = text.trim();
+    let (negative, rest) = match trimmed.strip_prefix('-') {
+        Some(rest) => (true, rest.trim_start()),
+        None => (false, trimmed.strip_prefix('+').unwrap_or(trimmed).trim_start()),
+    };
+    let duration = match rest.strip_prefix(['P', 'p']) {
+        Some(designators) => iso(designators, trimmed)?,
+        None => words(rest, trimmed)?,
+    };
+    if duration.months > MAX_MONTHS || duration.days > MAX_DAYS || duration.seconds > MAX_SECONDS {
+        return Err(too_long());
+    }
+    if negative {
+        return Ok(Duration {
+            months: -duration.months,
+            days: -duration.days,
+            seconds: -duration.seconds,
+        });
+    }
+    Ok(duration)
+}
+
+/// `seconds`, ignoring its sign, in words, largest unit first, e.g.
+/// "1 day, 2 hours, 5 seconds".
+pub fn describe(seconds: i64) -> String {
+    let mut rest = seconds.unsigned_abs();
+    let mut parts = Vec::new();
+    for (name, size) in [("day", 86_400), ("hour", 3600), ("minute", 60), ("second", 1)] {
+        let count = rest / size;
+        rest %= size;
+        if count > 0 {
+            parts.push(format!("{} {}{}", count, name, if count == 1 { "" } else { "s" }));
+        }
+    }
+    if parts.is_empty() {
+        return "0 seconds".to_string();
+    }
+    parts.join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn duration(months: i64, days: i64, seconds: i64) -> Duration {
+        Duration { months, days, seconds }
+    }
+
+    #[test]
+    fn test_parse_words() {
+        assert_eq!(parse("1d 2h 30m"), Ok(duration(0, 1, 9000)));
+        assert_eq!(parse("3 weeks and 4 days"), Ok(duration(0, 25, 0)));
+        assert_eq!(parse("1 year, 2 months"), Ok(duration(14, 0, 0)));
+        assert_eq!(parse("-90m"), Ok(duration(0, 0, -5400)));
+        assert_eq!(parse("- 1d 1s"), Ok(duration(0, -1, -1)));
+        assert_eq!(parse("45 Seconds"), Ok(duration(0, 0, 45)));
+        assert!(parse("").is_err());
+        assert_eq!(parse("5"), Err(invalid("5")));
+        assert!(parse("2 fortnights").unwrap_err().contains("fortnights"));
+        assert!(parse("1.5h").is_err());
+    }
+
+    #[test]
+    fn test_parse_iso() {
+        assert_eq!(parse("P1Y2M10DT2H30M"), Ok(duration(14, 10, 9000)));
+        assert_eq!(parse("PT30M"), Ok(duration(0, 0, 1800)));
+        assert_eq!(parse("-P1W"), Ok(duration(0, -7, 0)));
+        assert!(parse("P").is_err());
+        assert!(parse("P1H").is_err());
+        assert!(parse("PT1D").is_err());
+    }
+
+    #[test]
+    fn test_limits() {
+        assert!(parse("10000y").is_ok());
+        assert_eq!(parse("10001y"), Err(too_long()));
+        assert_eq!(parse("99999999999999999999999s"), Err(too_long()));
+        assert_eq!(parse("9223372036854775807h"), Err(too_long()));
+    }
+
+    #[test]
+    fn test_describe() {
+        assert_eq!(describe(0), "0 seconds");
+        assert_eq!(describe(90_061), "1 day, 1 hour, 1 minute, 1 second");
+        assert_eq!(describe(-7200), "2 hours");
+    }
+}
@@ -0,0 +1,161 @@
+//! Reading the times, zones and patterns tools are given. A time without
+//! an offset is a wall-clock time in the zone it is read in; when the
+//! clocks go back it means the earlier of the two instants, and one the
+//! clocks skip when they go forward is an error.
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+
+/// Longest pattern `time.parse` and `time.format` take.
+const MAX_PATTERN_LENGTH: usize = 200;
+
+/// ISO 8601 without an offset; `%.f` also matches no fraction.
+const LOCAL_FORMATS: &[&str] = &[
+    "%Y-%m-%dT%H:%M:%S%.f",
+    "%Y-%m-%d %H:%M:%S%.f",
+    "%Y-%m-%dT%H:%M",
+    "%Y-%m-%d %H:%M",
+];
+
+/// The IANA zone called `name`, in any case. `UTC`, `GMT` and `Z` are UTC.
+pub fn zone(name: &str) -> Result<Tz, String> {
+    let name = name.trim();
+    if ["UTC", "GMT", "Z"].iter().any(|utc| name.eq_ignore_ascii_case(utc)) {
+        return Ok(Tz::UTC);
+    }
+    Tz::from_str_insensitive(name).map_err(|_| {
+        format!(
+            "Unknown time zone '{}'; use an IANA name such as Europe/Paris or America/New_York",
+            name
+        )
+    })
+}
+
+/// The instant the clocks in `zone` show `local`.
+pub fn localize(local: NaiveDateTime, zone: Tz) -> Result<DateTime<Tz>, String> {
+    zone.from_local_datetime(&local).earliest().ok_or_else(|| {
+        format!(
+            "{} doesn't exist in {}: the clocks skip it for daylight saving",
+            local.format("%Y-%m-%d %H:%M"),
+            zone.name()
+        )
+    })
+}
+
+/// Read `text` as a time, in `zone`: "now", a Unix timestamp in seconds,
+/// RFC 3339 or RFC 2822 with an offset, or an ISO 8601 date, or date and
+/// time, without one.
+pub fn time(text: &str, zone: Tz, now: DateTime<Utc>) -> Result<DateTime<Tz>, String> {
+    let text = text.trim();
+    if text.eq_ignore_ascii_case("now") {
+        return Ok(now.with_timezone(&zone));
+    }
+    if let Ok(secs) = text.parse::<i64>() {
+        return DateTime::from_timestamp(secs, 0)
+            .map(|time| time.with_timezone(&zone))
+            .ok_or_else(|| format!("The timestamp {} is out of range", secs));
+    }
+    let with_offset = DateTime::parse_from_rfc3339(text);
+    if let Ok(time) = with_offset.or_else(|_| DateTime::parse_from_rfc2822(text)) {
+        return Ok(time.with_timezone(&zone));
+    }
+    if let Some(local) = LOCAL_FORMATS
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(text, format).ok())
+    {
+        return localize(local, zone);
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(text, "%Y-%m-%d") {
+        return localize(date.and_time(NaiveTime::MIN), zone);
+    }
+    Err(format!(
+        "Couldn't read '{}' as a time; use ISO 8601 such as 2024-01-15T10:30:00Z, \
+         or time.parse with a pattern",
+        text
+    ))
+}
+
+/// Make sure `pattern` is a strftime pattern chrono knows, as formatting
+/// with one it doesn't fails.
+pub fn check_pattern(pattern: &str) -> Result<(), String> {
+    if pattern.is_empty() {
+        return Err("The pattern is empty".to_string());
+    }
+    if pattern.len() > MAX_PATTERN_LENGTH {
+        return Err(format!("Patterns can be at most {} characters", MAX_PATTERN_LENGTH));
+    }
+    if StrftimeItems::new(pattern).any(|item| matches!(item, Item::Error)) {
+        return Err(format!(
+            "'{}' isn't a valid pattern; use strftime specifiers such as %Y-%m-%d %H:%M",
+            pattern
+        ));
+    }
+    Ok(())
+}
+
+/// Read `text` with a strftime `pattern`. A pattern with an offset (`%z`)
+/// gives that instant; without one the time is read in `zone`, and a
+/// pattern with only a date means its midnight.
+pub fn time_with_pattern(text: &str, pattern: &str, zone: Tz) -> Result<DateTime<Tz>, String> {
+    check_pattern(pattern)?;
+    let text = text.trim();
+    if let Ok(time) = DateTime::parse_from_str(text, pattern) {
+        return Ok(time.with_timezone(&zone));
+    }
+    match NaiveDateTime::parse_from_str(text, pattern) {
+        Ok(local) => localize(local, zone),
+        Err(e) => match NaiveDate::parse_from_str(text, pattern) {
+            Ok(date) => localize(date.and_time(NaiveTime::MIN), zone),
+            Err(_) => Err(format!("'{}' doesn't match the pattern '{}': {}", text, pattern, e)),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn now() -> DateTime<Utc> {
+        DateTime::from_timestamp(1_705_314_645, 0).unwrap()
+    }
+
+    #[test]
+    fn test_zone() {
+        assert_eq!(zone("Europe/Berlin"), Ok(Tz::Europe__Berlin));
+        assert_eq!(zone(" america/new_york "), Ok(Tz::America__New_York));
+        assert_eq!(zone("utc"), Ok(Tz::UTC));
+        assert!(zone("Mars/Olympus_Mons").is_err());
+    }
+
+    #[test]
+    fn test_time() {
+        let berlin = Tz::Europe__Berlin;
+        let read = |text: &str| time(text, berlin, now()).map(|time| time.to_rfc3339());
+        assert_eq!(read("now").unwrap(), "2024-01-15T11:30:45+01:00");
+        assert_eq!(read("1705314645").unwrap(), "2024-01-15T11:30:45+01:00");
+        assert_eq!(read("2024-01-15T10:30:45Z").unwrap(), "2024-01-15T11:30:45+01:00");
+        assert_eq!(read("Mon, 15 Jan 2024 10:30:45 +0000").unwrap(), "2024-01-15T11:30:45+01:00");
+        assert_eq!(read("2024-07-01 09:00").unwrap(), "2024-07-01T09:00:00+02:00");
+        assert_eq!(read("2024-07-01").unwrap(), "2024-07-01T00:00:00+02:00");
+        // The clocks went from 02:00 to 03:00, then back from 03:00 to 02:00
+        assert!(read("2024-03-31T02:30").unwrap_err().contains("daylight saving"));
+        assert_eq!(read("2024-10-27T02:30").unwrap(), "2024-10-27T02:30:00+02:00");
+        assert!(read("next tuesday").is_err());
+    }
+
+    #[test]
+    fn test_time_with_pattern() {
+        let utc = Tz::UTC;
+        let read = |text: &str, pattern: &str| {
+            time_with_pattern(text, pattern, utc).map(|time| time.to_rfc3339())
+        };
+        let read_ok = |text: &str, pattern: &str| read(text, pattern).unwrap();
+        assert_eq!(read_ok("15/01/2024 10:30", "%d/%m/%Y %H:%M"), "2024-01-15T10:30:00+00:00");
+        assert_eq!(read_ok("January 15, 2024", "%B %d, %Y"), "2024-01-15T00:00:00+00:00");
+        let with_offset = read_ok("2024-01-15 10:30 +0530", "%Y-%m-%d %H:%M %z");
+        assert_eq!(with_offset, "2024-01-15T05:00:00+00:00");
+        assert!(read("15/01/2024", "%Y-%m-%d").unwrap_err().contains("doesn't match"));
+        assert!(read("2024", "%Q").unwrap_err().contains("isn't a valid pattern"));
+    }
+}
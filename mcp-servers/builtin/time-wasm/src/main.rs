@@ -1,204 +1,383 @@
 //! Time MCP Server (WASM)
 //!
-//! A simple MCP server that returns the current time.
-//! Since WASM cannot access system clocks, the host injects
-//! the current time via the tool arguments.
+//! Returns the current time, read from the WASI clock the host provides,
+//! in UTC or in the user's time zone when the host shares it (the
+//! `locale` capability), and works with times: converting them between
+//! IANA zones, adding durations, measuring between two, and parsing and
+//! formatting with strftime patterns. Zone rules come from the IANA
+//! database built in through chrono-tz.
+
+mod duration;
+mod input;
 
-use serde::{Deserialize, Serialize};
-use std::io::{self, BufRead, Write};
 use std::time::{SystemTime, UNIX_EPOCH};
 
-#[derive(Debug, Deserialize)]
-struct RpcRequest {
-    id: serde_json::Value,
-    method: String,
-    #[serde(default)]
-    params: serde_json::Value,
+use chrono::{
+    DateTime, Datelike, Days, FixedOffset, Months, Offset, SecondsFormat, TimeDelta, TimeZone, Utc,
+};
+use chrono_tz::Tz;
+use harbor_mcp_sdk::{harbor_tool, Error, Locale, Server, ToolInput, ToolResult};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use duration::Duration;
+
+fn main() {
+    server().run();
 }
 
-#[derive(Debug, Serialize)]
-struct RpcResponse {
-    jsonrpc: &'static str,
-    id: serde_json::Value,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    result: Option<serde_json::Value>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    error: Option<RpcError>,
+fn server() -> Server {
+    Server::new("mcp-time", "1.0.0")
+        .tool(
+            "time.now",
+            json!({
+                "description": "Get the current date and time in ISO 8601 format (UTC)",
+                "type": "object",
+                "properties": {},
+                "required": []
+            }),
+            |_| now().map(|now| now.to_rfc3339_opts(SecondsFormat::Millis, true)),
+        )
+        .tool(
+            "time.local",
+            json!({
+                "description": "Get the current date and time in the user's time zone, with the zone and locale",
+                "type": "object",
+                "properties": {},
+                "required": []
+            }),
+            |_| local_time(Locale::from_env()),
+        )
+        .register(convert_time_tool())
+        .register(add_duration_tool())
+        .register(diff_times_tool())
+        .register(parse_time_tool())
+        .register(format_time_tool())
 }
 
-#[derive(Debug, Serialize)]
-struct RpcError {
-    code: i64,
-    message: String,
+#[derive(Deserialize, ToolInput)]
+#[serde(deny_unknown_fields)]
+struct ConvertArgs {
+    /// The time, e.g. "2024-01-15T10:30:00Z", "2024-01-15 10:30", a Unix timestamp or "now"
+    time: String,
+    /// The IANA zone to convert to, e.g. "Asia/Tokyo"
+    to: String,
+    /// The zone a time without an offset is in (default the user's, else UTC)
+    from: Option<String>,
 }
 
-fn write_response(response: RpcResponse) {
-    let mut out = io::stdout().lock();
-    let line = serde_json::to_string(&response).unwrap_or_else(|_| "{}".to_string());
-    let _ = out.write_all(line.as_bytes());
-    let _ = out.write_all(b"\n");
-    let _ = out.flush();
+#[derive(Deserialize, ToolInput)]
+#[serde(deny_unknown_fields)]
+struct AddArgs {
+    /// The duration, e.g. "1d 2h", "3 weeks", "-90m" or "P1M"; negative goes back
+    duration: String,
+    /// The time to start from (default now)
+    time: Option<String>,
+    /// The zone to work in (default the user's, else UTC)
+    timezone: Option<String>,
 }
 
-fn main() {
-    let stdin = io::stdin();
-    for line in stdin.lock().lines() {
-        if let Ok(raw) = line {
-            if raw.trim().is_empty() {
-                continue;
-            }
-            let request: Result<RpcRequest, _> = serde_json::from_str(&raw);
-            match request {
-                Ok(req) => handle_request(req),
-                Err(_) => {
-                    write_response(RpcResponse {
-                        jsonrpc: "2.0",
-                        id: serde_json::Value::Null,
-                        result: None,
-                        error: Some(RpcError {
-                            code: -32700,
-                            message: "Parse error".to_string(),
-                        }),
-                    });
-                }
-            }
-        }
-    }
+#[derive(Deserialize, ToolInput)]
+#[serde(deny_unknown_fields)]
+struct DiffArgs {
+    /// The earlier time
+    start: String,
+    /// The later time (default now)
+    end: Option<String>,
+    /// The zone of times without an offset, and to count days in (default the user's, else UTC)
+    timezone: Option<String>,
 }
 
-fn handle_request(request: RpcRequest) {
-    match request.method.as_str() {
-        "initialize" => {
-            let result = serde_json::json!({
-                "protocolVersion": "2024-11-05",
-                "capabilities": { "tools": {} },
-                "serverInfo": { "name": "mcp-time", "version": "1.0.0" }
-            });
-            write_response(RpcResponse {
-                jsonrpc: "2.0",
-                id: request.id,
-                result: Some(result),
-                error: None,
-            });
-        }
-        "tools/list" => {
-            let result = serde_json::json!({
-                "tools": [
-                    {
-                        "name": "time.now",
-                        "description": "Get the current date and time in ISO 8601 format (UTC)",
-                        "inputSchema": {
-                            "type": "object",
-                            "properties": {},
-                            "required": []
-                        }
-                    }
-                ]
-            });
-            write_response(RpcResponse {
-                jsonrpc: "2.0",
-                id: request.id,
-                result: Some(result),
-                error: None,
-            });
-        }
-        "tools/call" => {
-            // In WASM, we can't access system time directly.
-            // The host injects the current time via the "now" argument.
-            let now = request
-                .params
-                .get("arguments")
-                .and_then(|args| args.get("now"))
-                .and_then(|v| v.as_str())
-                .map(|s| s.to_string())
-                .unwrap_or_else(|| {
-                    // Fallback: try SystemTime (works in native, not in WASM)
-                    format_system_time()
-                });
-
-            let result = serde_json::json!({
-                "content": [
-                    {
-                        "type": "text",
-                        "text": now
-                    }
-                ]
-            });
-            write_response(RpcResponse {
-                jsonrpc: "2.0",
-                id: request.id,
-                result: Some(result),
-                error: None,
-            });
-        }
-        _ => {
-            write_response(RpcResponse {
-                jsonrpc: "2.0",
-                id: request.id,
-                result: None,
-                error: Some(RpcError {
-                    code: -32601,
-                    message: "Method not found".to_string(),
-                }),
-            });
-        }
-    }
+#[derive(Deserialize, ToolInput)]
+#[serde(deny_unknown_fields)]
+struct ParseArgs {
+    /// The text to read, e.g. "15/01/2024 10:30"
+    text: String,
+    /// strftime pattern it follows, e.g. "%d/%m/%Y %H:%M" (default ISO 8601 and RFC 2822)
+    pattern: Option<String>,
+    /// The zone of a time without an offset, and of the result (default the user's, else UTC)
+    timezone: Option<String>,
+}
+
+#[derive(Deserialize, ToolInput)]
+#[serde(deny_unknown_fields)]
+struct FormatArgs {
+    /// strftime pattern, e.g. "%A %-d %B %Y, %H:%M %Z"
+    pattern: String,
+    /// The time to format (default now)
+    time: Option<String>,
+    /// The zone to show it in (default the user's, else UTC)
+    timezone: Option<String>,
 }
 
-/// Format the current system time as ISO 8601.
-/// This works in native Rust but not in WASM (where SystemTime is unavailable).
-fn format_system_time() -> String {
+/// The current time from the host clock.
+fn now() -> Result<DateTime<Utc>, Error> {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
-        .map(|d| {
-            let secs = d.as_secs();
-            let millis = d.subsec_millis();
-            let days = secs / 86400;
-            let remaining = secs % 86400;
-            let hours = remaining / 3600;
-            let minutes = (remaining % 3600) / 60;
-            let seconds = remaining % 60;
-
-            let mut year = 1970i32;
-            let mut remaining_days = days as i32;
-
-            loop {
-                let days_in_year =
-                    if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) {
-                        366
-                    } else {
-                        365
-                    };
-                if remaining_days < days_in_year {
-                    break;
-                }
-                remaining_days -= days_in_year;
-                year += 1;
-            }
+        .ok()
+        .and_then(|d| DateTime::from_timestamp(d.as_secs() as i64, d.subsec_nanos()))
+        .ok_or_else(|| Error::new(-32000, "The host clock is unavailable"))
+}
+
+/// The local time as JSON: `{ time, timezone, locale }`. Falls back to UTC
+/// when the host didn't share the time zone.
+fn local_time(locale: Locale) -> Result<String, Error> {
+    let now = now()?;
+    // The zone's own rules when it is known, else the offset the host read
+    let offset = match locale.timezone.as_deref().map(input::zone) {
+        Some(Ok(zone)) => zone.offset_from_utc_datetime(&now.naive_utc()).fix(),
+        _ => locale
+            .utc_offset_minutes
+            .and_then(|minutes| FixedOffset::east_opt(minutes * 60))
+            .unwrap_or(Utc.fix()),
+    };
+    Ok(json!({
+        "time": now.with_timezone(&offset).to_rfc3339_opts(SecondsFormat::Millis, true),
+        "timezone": locale.timezone.as_deref().unwrap_or("UTC"),
+        "locale": locale.language,
+    })
+    .to_string())
+}
+
+/// The zone called `name`, or the user's when there is none, else UTC.
+fn zone_or_default(name: Option<&str>) -> Result<Tz, String> {
+    match name {
+        Some(name) => input::zone(name),
+        None => Ok(Locale::from_env()
+            .timezone
+            .and_then(|name| input::zone(&name).ok())
+            .unwrap_or(Tz::UTC)),
+    }
+}
+
+/// ISO 8601, to the millisecond only when there are milliseconds.
+fn iso(time: &DateTime<Tz>) -> String {
+    let precision = if time.timestamp_subsec_millis() == 0 {
+        SecondsFormat::Secs
+    } else {
+        SecondsFormat::Millis
+    };
+    time.to_rfc3339_opts(precision, true)
+}
+
+/// A time as tools return it.
+fn view(time: &DateTime<Tz>) -> Value {
+    json!({
+        "time": iso(time),
+        "timezone": time.timezone().name(),
+        "abbreviation": time.format("%Z").to_string(),
+        "offset": time.format("%:z").to_string(),
+        "weekday": time.format("%A").to_string(),
+        "unix": time.timestamp(),
+    })
+}
+
+fn respond(result: Result<Value, String>) -> Result<ToolResult, Error> {
+    Ok(match result {
+        Ok(value) => ToolResult::json(&value),
+        Err(e) => ToolResult::error(e),
+    })
+}
 
-            let is_leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
-            let days_in_months = if is_leap {
-                [31, 29, 31, 30, 31, 30, 31, 31, 30, 31, 30, 31]
+fn out_of_range() -> String {
+    "The result is out of the range of dates supported".to_string()
+}
+
+/// `time` moved by `duration`: months and days on the wall clock of its
+/// zone, then seconds exactly. A wall-clock time the clocks skip moves on
+/// by the length of the gap.
+fn shift(time: DateTime<Tz>, duration: Duration) -> Result<DateTime<Tz>, String> {
+    let mut shifted = time;
+    if duration.is_calendar() {
+        let months = u32::try_from(duration.months.unsigned_abs()).map_err(|_| out_of_range())?;
+        let months = Months::new(months);
+        let days = Days::new(duration.days.unsigned_abs());
+        let local = time.naive_local();
+        let local = if duration.months < 0 {
+            local.checked_sub_months(months)
+        } else {
+            local.checked_add_months(months)
+        };
+        let local = local.and_then(|local| {
+            if duration.days < 0 {
+                local.checked_sub_days(days)
             } else {
-                [31, 28, 31, 30, 31, 30, 31, 31, 30, 31, 30, 31]
-            };
-
-            let mut month = 1;
-            for &days_in_month in &days_in_months {
-                if remaining_days < days_in_month {
-                    break;
-                }
-                remaining_days -= days_in_month;
-                month += 1;
+                local.checked_add_days(days)
             }
-            let day = remaining_days + 1;
-
-            format!(
-                "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
-                year, month, day, hours, minutes, seconds, millis
-            )
-        })
-        .unwrap_or_else(|_| {
-            "Time unavailable - host did not provide 'now' argument".to_string()
-        })
+        });
+        let local = local.ok_or_else(out_of_range)?;
+        let zone = time.timezone();
+        shifted = match zone.from_local_datetime(&local).earliest() {
+            Some(shifted) => shifted,
+            // Gaps are at most an hour: read it an hour earlier, then add the hour
+            None => zone
+                .from_local_datetime(&(local - TimeDelta::hours(1)))
+                .earliest()
+                .map(|before| before + TimeDelta::hours(1))
+                .ok_or_else(out_of_range)?,
+        };
+    }
+    TimeDelta::try_seconds(duration.seconds)
+        .and_then(|seconds| shifted.checked_add_signed(seconds))
+        .ok_or_else(out_of_range)
+}
+
+/// Whole years, months and days from `start` to `end` on the calendar of
+/// their zone, counted as a person would; negative when `end` is earlier.
+fn calendar(start: &DateTime<Tz>, end: &DateTime<Tz>) -> (i64, i64, i64) {
+    let (mut from, mut to) = (start.naive_local(), end.naive_local());
+    let sign = if to < from {
+        std::mem::swap(&mut from, &mut to);
+        -1
+    } else {
+        1
+    };
+    let mut months = i64::from(to.year() - from.year()) * 12 + i64::from(to.month())
+        - i64::from(from.month());
+    let after = |months: i64| from.checked_add_months(Months::new(months as u32));
+    if months > 0 && !after(months).is_some_and(|anchor| anchor <= to) {
+        months -= 1;
+    }
+    let days = after(months).map_or(0, |anchor| (to - anchor).num_days());
+    (sign * (months / 12), sign * (months % 12), sign * days)
+}
+
+fn convert(args: ConvertArgs, now: DateTime<Utc>) -> Result<Value, String> {
+    let to = input::zone(&args.to)?;
+    let time = input::time(&args.time, zone_or_default(args.from.as_deref())?, now)?;
+    Ok(json!({ "from": view(&time), "to": view(&time.with_timezone(&to)) }))
+}
+
+fn add(args: AddArgs, now: DateTime<Utc>) -> Result<Value, String> {
+    let zone = zone_or_default(args.timezone.as_deref())?;
+    let time = input::time(args.time.as_deref().unwrap_or("now"), zone, now)?;
+    Ok(view(&shift(time, duration::parse(&args.duration)?)?))
+}
+
+fn diff(args: DiffArgs, now: DateTime<Utc>) -> Result<Value, String> {
+    let zone = zone_or_default(args.timezone.as_deref())?;
+    let start = input::time(&args.start, zone, now)?;
+    let end = input::time(args.end.as_deref().unwrap_or("now"), zone, now)?;
+    let seconds = end.signed_duration_since(start).num_seconds();
+    let (years, months, days) = calendar(&start, &end);
+    Ok(json!({
+        "start": iso(&start),
+        "end": iso(&end),
+        "seconds": seconds,
+        "text": duration::describe(seconds),
+        "calendar": { "years": years, "months": months, "days": days },
+    }))
+}
+
+fn parse(args: ParseArgs, now: DateTime<Utc>) -> Result<Value, String> {
+    let zone = zone_or_default(args.timezone.as_deref())?;
+    let time = match &args.pattern {
+        Some(pattern) => input::time_with_pattern(&args.text, pattern, zone)?,
+        None => input::time(&args.text, zone, now)?,
+    };
+    Ok(view(&time))
+}
+
+fn format(args: FormatArgs, now: DateTime<Utc>) -> Result<String, String> {
+    input::check_pattern(&args.pattern)?;
+    let zone = zone_or_default(args.timezone.as_deref())?;
+    let time = input::time(args.time.as_deref().unwrap_or("now"), zone, now)?;
+    Ok(time.format(&args.pattern).to_string())
+}
+
+/// Convert a time between time zones, e.g. "What time is 3pm in New York
+/// in Tokyo?". Daylight saving is taken into account.
+#[harbor_tool(name = "time.convert")]
+fn convert_time(args: ConvertArgs) -> Result<ToolResult, Error> {
+    respond(convert(args, now()?))
+}
+
+/// Add a duration to a time, or subtract a negative one: "1d 2h 30m",
+/// "2 weeks", "-3 months", or ISO 8601 such as "P1Y". Days, weeks, months
+/// and years follow the calendar, keeping the time of day across daylight
+/// saving changes; hours, minutes and seconds are exact.
+#[harbor_tool(name = "time.add")]
+fn add_duration(args: AddArgs) -> Result<ToolResult, Error> {
+    respond(add(args, now()?))
+}
+
+/// Measure the time between two times: the exact duration in seconds and
+/// words, and whole years, months and days on the calendar (as for an
+/// age). Both are negative when the end is earlier.
+#[harbor_tool(name = "time.diff")]
+fn diff_times(args: DiffArgs) -> Result<ToolResult, Error> {
+    respond(diff(args, now()?))
+}
+
+/// Read a date or time written in any layout, given its strftime pattern
+/// (e.g. "%d/%m/%Y %H:%M" or "%B %d, %Y"), or one in ISO 8601 or RFC 2822
+/// without a pattern.
+#[harbor_tool(name = "time.parse")]
+fn parse_time(args: ParseArgs) -> Result<ToolResult, Error> {
+    respond(parse(args, now()?))
+}
+
+/// Write a time with a strftime pattern, e.g. "%A %-d %B %Y" for "Monday
+/// 15 January 2024". %Z gives the zone's abbreviation and %z its offset.
+#[harbor_tool(name = "time.format")]
+fn format_time(args: FormatArgs) -> Result<ToolResult, Error> {
+    Ok(match format(args, now()?) {
+        Ok(text) => ToolResult::text(text),
+        Err(e) => ToolResult::error(e),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_york(text: &str) -> DateTime<Tz> {
+        input::time(text, Tz::America__New_York, DateTime::UNIX_EPOCH).unwrap()
+    }
+
+    fn added(time: &str, duration: &str) -> String {
+        iso(&shift(new_york(time), duration::parse(duration).unwrap()).unwrap())
+    }
+
+    #[test]
+    fn test_add() {
+        // New York's clocks went forward on 2024-03-10 at 02:00
+        assert_eq!(added("2024-03-09T12:00", "1d"), "2024-03-10T12:00:00-04:00");
+        assert_eq!(added("2024-03-09T12:00", "24h"), "2024-03-10T13:00:00-04:00");
+        assert_eq!(added("2024-03-09T02:30", "1d"), "2024-03-10T03:30:00-04:00");
+        assert_eq!(added("2024-01-31T09:00", "1mo"), "2024-02-29T09:00:00-05:00");
+        assert_eq!(added("2024-01-15T09:00", "-1y 2h"), "2023-01-15T07:00:00-05:00");
+    }
+
+    #[test]
+    fn test_calendar() {
+        let (born, today) = (new_york("1990-05-20"), new_york("2024-05-19"));
+        assert_eq!(calendar(&born, &today), (33, 11, 29));
+        assert_eq!(calendar(&today, &born), (-33, -11, -29));
+        assert_eq!(calendar(&born, &new_york("2024-05-20T08:00")), (34, 0, 0));
+        assert_eq!(calendar(&born, &born), (0, 0, 0));
+    }
+
+    #[test]
+    fn test_answers_the_manifest_tools() {
+        // The extension installs the server with the tools its manifest lists
+        let manifest: Value = serde_json::from_str(include_str!("../manifest.json")).unwrap();
+        let request = r#"{"jsonrpc":"2.0","id":1,"method":"tools/list"}"#;
+        let listed: Value = serde_json::from_str(&server().handle(request)).unwrap();
+        let names = |tools: &Value| -> Vec<String> {
+            let tools = tools.as_array().unwrap();
+            tools.iter().map(|tool| tool["name"].to_string()).collect()
+        };
+        assert_eq!(names(&listed["result"]["tools"]), names(&manifest["tools"]));
+    }
+
+    #[test]
+    fn test_view() {
+        let time = new_york("2024-07-04T09:00:00.250");
+        let view = view(&time);
+        assert_eq!(view["time"], "2024-07-04T09:00:00.250-04:00");
+        assert_eq!(view["abbreviation"], "EDT");
+        assert_eq!(view["offset"], "-04:00");
+        assert_eq!(view["weekday"], "Thursday");
+    }
 }
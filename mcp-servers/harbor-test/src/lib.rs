@@ -0,0 +1,304 @@
+//! Test harness for Harbor WASM MCP servers.
+//!
+//! Loads a compiled WASI preview 1 module in-process, on wasmtime, and
+//! talks MCP to it the way Harbor does: each request runs a fresh instance
+//! with the request on stdin, and the module's stdout is the response,
+//! after any notifications it sent. Server authors can then test with
+//! `cargo test`, without the browser or the bridge:
+//!
+//! ```no_run
+//! use harbor_test::TestServer;
+//! use serde_json::json;
+//!
+//! let server = TestServer::build("my_mcp_server").unwrap();
+//! assert!(server.tool_names().unwrap().contains(&"greet".to_string()));
+//! server
+//!     .call_tool("greet", json!({ "name": "Ada" }))
+//!     .unwrap()
+//!     .assert_text("Hello, Ada!");
+//! ```
+
+mod result;
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use serde_json::Value;
+use wasmtime::{Config, Engine, Linker, Module, Store};
+use wasmtime_wasi::pipe::{MemoryInputPipe, MemoryOutputPipe};
+use wasmtime_wasi::preview1::{self, WasiP1Ctx};
+use wasmtime_wasi::WasiCtxBuilder;
+
+pub use result::ToolResult;
+
+/// Fuel for each request, as the bridge's default: a module spinning the
+/// CPU fails the test rather than hanging it.
+const FUEL: u64 = 10_000_000_000;
+
+/// Most output kept from one request.
+const MAX_OUTPUT_BYTES: usize = 16 * 1024 * 1024;
+
+/// Why a request didn't get a response.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    /// The module couldn't be built, read or compiled
+    Load(String),
+    /// The module trapped, ran out of fuel or exited with an error
+    Trap { message: String, stderr: String },
+    /// The module's output isn't a JSON-RPC response
+    Protocol(String),
+    /// The server answered with a JSON-RPC error
+    Rpc {
+        code: i64,
+        message: String,
+        data: Option<Value>,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Load(message) | Error::Protocol(message) => f.write_str(message),
+            Error::Trap { message, stderr } if stderr.is_empty() => f.write_str(message),
+            Error::Trap { message, stderr } => write!(f, "{}\nstderr:\n{}", message, stderr),
+            Error::Rpc { code, message, .. } => write!(f, "JSON-RPC error {}: {}", code, message),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// What a module wrote for one request.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Response {
+    /// The JSON-RPC response
+    pub message: Value,
+    /// Notifications sent before it
+    pub notifications: Vec<Value>,
+    /// What the module wrote to stderr
+    pub stderr: String,
+}
+
+impl Response {
+    /// The result, or the error as `Error::Rpc`.
+    pub fn result(&self) -> Result<&Value, Error> {
+        match self.message.get("error") {
+            Some(error) => Err(Error::Rpc {
+                code: error.get("code").and_then(Value::as_i64).unwrap_or_default(),
+                message: error.get("message").and_then(Value::as_str).unwrap_or_default().to_string(),
+                data: error.get("data").cloned(),
+            }),
+            None => Ok(self.message.get("result").unwrap_or(&Value::Null)),
+        }
+    }
+}
+
+/// Split a module's stdout into notifications and the response, which is
+/// the last message with an `id`.
+fn parse_output(stdout: &str) -> Result<(Value, Vec<Value>), Error> {
+    let mut messages = Vec::new();
+    for line in stdout.lines().filter(|line| !line.trim().is_empty()) {
+        let message: Value = serde_json::from_str(line)
+            .map_err(|e| Error::Protocol(format!("Output is not JSON ({}): {}", e, line)))?;
+        messages.push(message);
+    }
+    let position = messages
+        .iter()
+        .rposition(|message| message.get("id").is_some())
+        .ok_or_else(|| Error::Protocol(format!("No response in output: {:?}", stdout)))?;
+    let response = messages.remove(position);
+    Ok((response, messages))
+}
+
+/// A compiled server module.
+pub struct TestServer {
+    engine: Engine,
+    module: Module,
+    env: Vec<(String, String)>,
+}
+
+impl TestServer {
+    /// Load a compiled module from a file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path).map_err(|e| Error::Load(format!("Failed to read {:?}: {}", path, e)))?;
+        Self::from_bytes(&bytes)
+    }
+
+    /// Load a module from its bytes.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let mut config = Config::new();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config).map_err(|e| Error::Load(e.to_string()))?;
+        let module = Module::new(&engine, bytes).map_err(|e| Error::Load(format!("Failed to compile module: {}", e)))?;
+        Ok(Self {
+            engine,
+            module,
+            env: Vec::new(),
+        })
+    }
+
+    /// Build the calling crate for `wasm32-wasip1` (release) and load the
+    /// module named `bin`, e.g. `my_mcp_server` for a package named
+    /// `my-mcp-server`. For use from the server's own integration tests.
+    pub fn build(bin: &str) -> Result<Self, Error> {
+        let dir = std::env::var_os("CARGO_MANIFEST_DIR")
+            .map(PathBuf::from)
+            .ok_or_else(|| Error::Load("CARGO_MANIFEST_DIR is not set; run under cargo".to_string()))?;
+        let cargo = std::env::var_os("CARGO").unwrap_or_else(|| "cargo".into());
+        let status = Command::new(cargo)
+            .args(["build", "--release", "--target", "wasm32-wasip1"])
+            .current_dir(&dir)
+            .status()
+            .map_err(|e| Error::Load(format!("Failed to run cargo: {}", e)))?;
+        if !status.success() {
+            return Err(Error::Load(format!("cargo build failed ({})", status)));
+        }
+        let target = std::env::var_os("CARGO_TARGET_DIR")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| dir.join("target"));
+        Self::load(target.join("wasm32-wasip1").join("release").join(format!("{}.wasm", bin)))
+    }
+
+    /// Set an environment variable for the module.
+    pub fn env(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.env.push((name.into(), value.into()));
+        self
+    }
+
+    /// Run one instance with `input` on stdin; returns stdout and stderr.
+    fn run(&self, input: &str) -> Result<(String, String), Error> {
+        let stdout = MemoryOutputPipe::new(MAX_OUTPUT_BYTES);
+        let stderr = MemoryOutputPipe::new(MAX_OUTPUT_BYTES);
+        let mut wasi = WasiCtxBuilder::new();
+        wasi.stdin(MemoryInputPipe::new(input.to_string()))
+            .stdout(stdout.clone())
+            .stderr(stderr.clone());
+        for (name, value) in &self.env {
+            wasi.env(name, value);
+        }
+        let mut store = Store::new(&self.engine, wasi.build_p1());
+        store.set_fuel(FUEL).map_err(|e| Error::Load(e.to_string()))?;
+        let mut linker: Linker<WasiP1Ctx> = Linker::new(&self.engine);
+        preview1::add_to_linker_sync(&mut linker, |ctx| ctx).map_err(|e| Error::Load(e.to_string()))?;
+
+        let outcome = linker
+            .instantiate(&mut store, &self.module)
+            .and_then(|instance| instance.get_typed_func::<(), ()>(&mut store, "_start"))
+            .and_then(|start| start.call(&mut store, ()));
+        let text = |pipe: &MemoryOutputPipe| String::from_utf8_lossy(&pipe.contents()).into_owned();
+        let (stdout, stderr) = (text(&stdout), text(&stderr));
+        if let Err(e) = outcome {
+            let exited_cleanly = e.downcast_ref::<wasmtime_wasi::I32Exit>().is_some_and(|exit| exit.0 == 0);
+            if !exited_cleanly {
+                return Err(Error::Trap {
+                    message: format!("{:#}", e),
+                    stderr,
+                });
+            }
+        }
+        Ok((stdout, stderr))
+    }
+
+    /// Send a JSON-RPC request and return what came back.
+    pub fn request(&self, method: &str, params: Value) -> Result<Response, Error> {
+        let mut request = serde_json::json!({ "jsonrpc": "2.0", "id": 1, "method": method });
+        if !params.is_null() {
+            request["params"] = params;
+        }
+        let (stdout, stderr) = self.run(&format!("{}\n", request))?;
+        let (message, notifications) = parse_output(&stdout)?;
+        Ok(Response {
+            message,
+            notifications,
+            stderr,
+        })
+    }
+
+    /// Send a request and return its result, or its error as `Error::Rpc`.
+    pub fn call(&self, method: &str, params: Value) -> Result<Value, Error> {
+        self.request(method, params)?.result().cloned()
+    }
+
+    /// `initialize`, returning the server's info and capabilities.
+    pub fn initialize(&self) -> Result<Value, Error> {
+        self.call(
+            "initialize",
+            serde_json::json!({
+                "protocolVersion": "2024-11-05",
+                "capabilities": {},
+                "clientInfo": { "name": "harbor-test", "version": env!("CARGO_PKG_VERSION") },
+            }),
+        )
+    }
+
+    /// `tools/list`, returning the tools.
+    pub fn list_tools(&self) -> Result<Vec<Value>, Error> {
+        let result = self.call("tools/list", Value::Null)?;
+        match result.get("tools") {
+            Some(Value::Array(tools)) => Ok(tools.clone()),
+            _ => Err(Error::Protocol(format!("tools/list result has no tools: {}", result))),
+        }
+    }
+
+    /// The names of the tools in `tools/list`.
+    pub fn tool_names(&self) -> Result<Vec<String>, Error> {
+        Ok(self
+            .list_tools()?
+            .iter()
+            .filter_map(|tool| tool.get("name").and_then(Value::as_str).map(String::from))
+            .collect())
+    }
+
+    /// `tools/call`. A tool that fails with `isError` still returns a
+    /// `ToolResult`; a JSON-RPC error, such as for invalid arguments, is
+    /// `Error::Rpc`.
+    pub fn call_tool(&self, name: &str, arguments: Value) -> Result<ToolResult, Error> {
+        let response = self.request("tools/call", serde_json::json!({ "name": name, "arguments": arguments }))?;
+        ToolResult::from_response(&response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_output() {
+        let stdout = concat!(
+            r#"{"jsonrpc":"2.0","method":"notifications/message","params":{"level":"debug","data":"hi"}}"#,
+            "\n",
+            r#"{"jsonrpc":"2.0","id":1,"result":{}}"#,
+            "\n"
+        );
+        let (response, notifications) = parse_output(stdout).unwrap();
+        assert_eq!(response["id"], 1);
+        assert_eq!(notifications.len(), 1);
+        assert!(matches!(parse_output(""), Err(Error::Protocol(_))));
+        assert!(matches!(parse_output("panicked\n"), Err(Error::Protocol(_))));
+    }
+
+    #[test]
+    fn test_response_error() {
+        let response = Response {
+            message: json!({ "jsonrpc": "2.0", "id": 1, "error": { "code": -32602, "message": "Invalid arguments: a: missing" } }),
+            notifications: Vec::new(),
+            stderr: String::new(),
+        };
+        match response.result() {
+            Err(Error::Rpc { code, message, data }) => {
+                assert_eq!(code, -32602);
+                assert_eq!(message, "Invalid arguments: a: missing");
+                assert_eq!(data, None);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_bad_module() {
+        assert!(matches!(TestServer::from_bytes(b"not wasm"), Err(Error::Load(_))));
+    }
+}
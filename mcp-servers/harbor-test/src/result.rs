@@ -0,0 +1,158 @@
+//! Tool results and assertions on their content blocks.
+
+use serde_json::Value;
+
+use crate::{Error, Response};
+
+/// The result of a `tools/call`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolResult {
+    /// Content blocks, e.g. `{ "type": "text", "text": "..." }`
+    pub content: Vec<Value>,
+    /// The tool reported failure (`isError`)
+    pub is_error: bool,
+    /// Notifications the server sent while handling the call
+    pub notifications: Vec<Value>,
+}
+
+impl ToolResult {
+    pub(crate) fn from_response(response: &Response) -> Result<Self, Error> {
+        let result = response.result()?;
+        let content = match result.get("content") {
+            Some(Value::Array(content)) => content.clone(),
+            _ => return Err(Error::Protocol(format!("tools/call result has no content: {}", result))),
+        };
+        Ok(Self {
+            content,
+            is_error: result.get("isError").and_then(Value::as_bool).unwrap_or(false),
+            notifications: response.notifications.clone(),
+        })
+    }
+
+    /// The text of the text blocks, joined by newlines.
+    pub fn text(&self) -> String {
+        self.content
+            .iter()
+            .filter(|block| block.get("type").and_then(Value::as_str) == Some("text"))
+            .filter_map(|block| block.get("text").and_then(Value::as_str))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// The text parsed as JSON, for tools that answer with JSON.
+    pub fn json(&self) -> Result<Value, Error> {
+        let text = self.text();
+        serde_json::from_str(&text).map_err(|e| Error::Protocol(format!("Tool output is not JSON ({}): {}", e, text)))
+    }
+
+    /// The `type` of each content block.
+    pub fn content_types(&self) -> Vec<&str> {
+        self.content
+            .iter()
+            .map(|block| block.get("type").and_then(Value::as_str).unwrap_or_default())
+            .collect()
+    }
+
+    /// The log messages (`notifications/message`) sent during the call.
+    pub fn logs(&self) -> Vec<&Value> {
+        self.notifications
+            .iter()
+            .filter(|n| n.get("method").and_then(Value::as_str) == Some("notifications/message"))
+            .filter_map(|n| n.pointer("/params/data"))
+            .collect()
+    }
+
+    /// Panic unless the tool succeeded.
+    #[track_caller]
+    pub fn assert_ok(&self) -> &Self {
+        assert!(!self.is_error, "tool failed: {}", self.text());
+        self
+    }
+
+    /// Panic unless the tool reported failure.
+    #[track_caller]
+    pub fn assert_error(&self) -> &Self {
+        assert!(self.is_error, "tool succeeded: {}", self.text());
+        self
+    }
+
+    /// Panic unless the tool succeeded with exactly this text.
+    #[track_caller]
+    pub fn assert_text(&self, expected: &str) -> &Self {
+        self.assert_ok();
+        assert_eq!(self.text(), expected, "tool text");
+        self
+    }
+
+    /// Panic unless the text contains `needle`.
+    #[track_caller]
+    pub fn assert_text_contains(&self, needle: &str) -> &Self {
+        let text = self.text();
+        assert!(text.contains(needle), "tool text {:?} does not contain {:?}", text, needle);
+        self
+    }
+
+    /// Panic unless the tool succeeded with this JSON as its text.
+    #[track_caller]
+    pub fn assert_json(&self, expected: &Value) -> &Self {
+        self.assert_ok();
+        match self.json() {
+            Ok(actual) => assert_eq!(&actual, expected, "tool JSON"),
+            Err(e) => panic!("{}", e),
+        }
+        self
+    }
+
+    /// Panic unless the content blocks have these types, in order.
+    #[track_caller]
+    pub fn assert_content_types(&self, expected: &[&str]) -> &Self {
+        assert_eq!(self.content_types(), expected, "content block types");
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn result(result: Value) -> ToolResult {
+        let response = Response {
+            message: json!({ "jsonrpc": "2.0", "id": 1, "result": result }),
+            notifications: vec![json!({
+                "jsonrpc": "2.0",
+                "method": "notifications/message",
+                "params": { "level": "debug", "data": "adding" }
+            })],
+            stderr: String::new(),
+        };
+        ToolResult::from_response(&response).unwrap()
+    }
+
+    #[test]
+    fn test_text_blocks() {
+        let r = result(json!({ "content": [
+            { "type": "text", "text": "{\"sum\":" },
+            { "type": "image", "data": "", "mimeType": "image/png" },
+            { "type": "text", "text": "3}" }
+        ] }));
+        r.assert_ok()
+            .assert_text("{\"sum\":\n3}")
+            .assert_text_contains("sum")
+            .assert_json(&json!({ "sum": 3 }))
+            .assert_content_types(&["text", "image", "text"]);
+        assert_eq!(r.logs(), vec![&json!("adding")]);
+    }
+
+    #[test]
+    fn test_tool_error() {
+        let r = result(json!({ "content": [{ "type": "text", "text": "boom" }], "isError": true }));
+        r.assert_error().assert_text_contains("boom");
+    }
+
+    #[test]
+    #[should_panic(expected = "tool failed")]
+    fn test_assert_ok_fails() {
+        result(json!({ "content": [], "isError": true })).assert_ok();
+    }
+}
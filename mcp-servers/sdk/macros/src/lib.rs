@@ -0,0 +1,334 @@
+//! Macros for `harbor-mcp-sdk`; use them through the SDK, which re-exports
+//! them.
+//!
+//! `#[derive(ToolInput)]` writes the JSON Schema of a tool's argument
+//! struct, and `#[harbor_tool]` turns a handler taking that struct into a
+//! `Tool` ready to register.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{
+    parse_macro_input, Attribute, Data, DeriveInput, Expr, Fields, FnArg, GenericArgument, ItemFn, Lit, LitStr, Meta,
+    PathArguments, Token, Type,
+};
+
+/// Derive `ToolInput` for a struct with named fields.
+///
+/// Field types map to JSON Schema types (strings, integers, numbers,
+/// booleans, arrays, maps), with a `minimum` of 0 for unsigned integers;
+/// other types must implement `ToolInput` themselves. Doc comments become descriptions. `Option` fields and
+/// fields with `#[serde(default)]` are optional, and serde's `rename`,
+/// `rename_all`, `skip` and `deny_unknown_fields` are followed so the schema
+/// matches what deserialization accepts.
+#[proc_macro_derive(ToolInput)]
+pub fn derive_tool_input(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match tool_input(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+/// Make a tool from a handler taking a `ToolInput` struct.
+///
+/// ```ignore
+/// /// Say hello to someone
+/// #[harbor_tool]
+/// fn greet(args: GreetArgs) -> Result<String, Error> { ... }
+///
+/// Server::new("my-server", "1.0.0").register(greet_tool()).run();
+/// ```
+///
+/// Generates `<name>_tool()`, returning the `Tool`. The tool is named after
+/// the function and described by its doc comment; `#[harbor_tool(name =
+/// "...", description = "...")]` overrides either.
+#[proc_macro_attribute]
+pub fn harbor_tool(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let mut name = None;
+    let mut description = None;
+    let parser = syn::meta::parser(|meta| {
+        if meta.path.is_ident("name") {
+            name = Some(meta.value()?.parse::<LitStr>()?.value());
+            Ok(())
+        } else if meta.path.is_ident("description") {
+            description = Some(meta.value()?.parse::<LitStr>()?.value());
+            Ok(())
+        } else {
+            Err(meta.error("expected `name` or `description`"))
+        }
+    });
+    parse_macro_input!(attr with parser);
+    let function = parse_macro_input!(item as ItemFn);
+    match harbor_tool_fn(function, name, description) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+fn harbor_tool_fn(function: ItemFn, name: Option<String>, description: Option<String>) -> syn::Result<TokenStream2> {
+    let inputs: Vec<&FnArg> = function.sig.inputs.iter().collect();
+    let arg_type = match inputs.as_slice() {
+        [FnArg::Typed(arg)] => &arg.ty,
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &function.sig,
+                "a tool handler takes exactly one argument, its `ToolInput` struct",
+            ))
+        }
+    };
+    let ident = &function.sig.ident;
+    let vis = &function.vis;
+    let tool_fn = format_ident!("{}_tool", ident);
+    let name = name.unwrap_or_else(|| ident.to_string());
+    let description = match description.or_else(|| doc_comment(&function.attrs)) {
+        Some(description) => quote!(::core::option::Option::Some(#description)),
+        None => quote!(::core::option::Option::None),
+    };
+    let doc = format!("The `{}` tool, handled by [`{}`].", name, ident);
+
+    Ok(quote! {
+        #function
+
+        #[doc = #doc]
+        #vis fn #tool_fn() -> ::harbor_mcp_sdk::Tool {
+            ::harbor_mcp_sdk::Tool::typed::<#arg_type, _, _>(#name, #description, #ident)
+        }
+    })
+}
+
+/// The doc comment on an item, as one paragraph.
+fn doc_comment(attrs: &[Attribute]) -> Option<String> {
+    let lines: Vec<String> = attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("doc"))
+        .filter_map(|attr| match &attr.meta {
+            Meta::NameValue(nv) => match &nv.value {
+                Expr::Lit(syn::ExprLit { lit: Lit::Str(s), .. }) => Some(s.value().trim().to_string()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .filter(|line| !line.is_empty())
+        .collect();
+    (!lines.is_empty()).then(|| lines.join(" "))
+}
+
+/// Serde attributes that change the schema.
+#[derive(Default)]
+struct SerdeAttrs {
+    rename: Option<String>,
+    rename_all: Option<String>,
+    default: bool,
+    skip: bool,
+    deny_unknown_fields: bool,
+}
+
+fn serde_attrs(attrs: &[Attribute]) -> syn::Result<SerdeAttrs> {
+    let mut found = SerdeAttrs::default();
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("serde")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                found.rename = Some(meta.value()?.parse::<LitStr>()?.value());
+            } else if meta.path.is_ident("rename_all") {
+                found.rename_all = Some(meta.value()?.parse::<LitStr>()?.value());
+            } else if meta.path.is_ident("default") {
+                found.default = true;
+                if meta.input.peek(Token![=]) {
+                    meta.value()?.parse::<LitStr>()?;
+                }
+            } else if meta.path.is_ident("skip") || meta.path.is_ident("skip_deserializing") {
+                found.skip = true;
+            } else if meta.path.is_ident("deny_unknown_fields") {
+                found.deny_unknown_fields = true;
+            } else if meta.input.peek(Token![=]) {
+                meta.value()?.parse::<Expr>()?;
+            } else if meta.input.peek(syn::token::Paren) {
+                let _nested;
+                syn::parenthesized!(_nested in meta.input);
+            }
+            Ok(())
+        })?;
+    }
+    Ok(found)
+}
+
+/// A snake_case field name under a serde `rename_all` rule.
+fn rename(field: &str, rule: &str) -> String {
+    let words: Vec<&str> = field.split('_').filter(|w| !w.is_empty()).collect();
+    let capitalize = |word: &str| {
+        let mut chars = word.chars();
+        chars
+            .next()
+            .map(|first| first.to_uppercase().chain(chars).collect::<String>())
+            .unwrap_or_default()
+    };
+    match rule {
+        "lowercase" => field.to_lowercase(),
+        "UPPERCASE" => field.to_uppercase(),
+        "camelCase" => words
+            .iter()
+            .enumerate()
+            .map(|(i, w)| if i == 0 { w.to_string() } else { capitalize(w) })
+            .collect(),
+        "PascalCase" => words.iter().map(|w| capitalize(w)).collect(),
+        "kebab-case" => words.join("-"),
+        "SCREAMING_SNAKE_CASE" => field.to_uppercase(),
+        "SCREAMING-KEBAB-CASE" => words.join("-").to_uppercase(),
+        _ => field.to_string(),
+    }
+}
+
+fn tool_input(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => fields.named.iter().collect(),
+            Fields::Unit => Vec::new(),
+            Fields::Unnamed(_) => {
+                return Err(syn::Error::new_spanned(input, "ToolInput needs named fields"));
+            }
+        },
+        _ => return Err(syn::Error::new_spanned(input, "ToolInput can only be derived for structs")),
+    };
+    let container = serde_attrs(&input.attrs)?;
+
+    let mut properties = Vec::new();
+    for field in fields {
+        let attrs = serde_attrs(&field.attrs)?;
+        if attrs.skip {
+            continue;
+        }
+        let ident = field.ident.as_ref().expect("named field").to_string();
+        let ident = ident.strip_prefix("r#").unwrap_or(&ident).to_string();
+        let key = match (&attrs.rename, &container.rename_all) {
+            (Some(rename), _) => rename.clone(),
+            (None, Some(rule)) => rename(&ident, rule),
+            (None, None) => ident,
+        };
+        let (ty, optional) = match option_inner(&field.ty) {
+            Some(inner) => (inner, true),
+            None => (&field.ty, false),
+        };
+        let schema = schema_for(ty);
+        let describe = doc_comment(&field.attrs).map(|description| {
+            quote! {
+                if let Value::Object(map) = &mut field {
+                    map.insert("description".to_string(), Value::from(#description));
+                }
+            }
+        });
+        let require = (!optional && !attrs.default && !container.default).then(|| {
+            quote! { required.push(Value::from(#key)); }
+        });
+        properties.push(quote! {
+            {
+                #[allow(unused_mut)]
+                let mut field = #schema;
+                #describe
+                properties.insert(#key.to_string(), field);
+                #require
+            }
+        });
+    }
+
+    let describe = doc_comment(&input.attrs).map(|description| {
+        quote! { schema.insert("description".to_string(), Value::from(#description)); }
+    });
+    let closed = container.deny_unknown_fields.then(|| {
+        quote! { schema.insert("additionalProperties".to_string(), Value::Bool(false)); }
+    });
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::harbor_mcp_sdk::ToolInput for #ident #ty_generics #where_clause {
+            fn schema() -> ::harbor_mcp_sdk::__private::serde_json::Value {
+                use ::harbor_mcp_sdk::__private::serde_json::{Map, Value};
+                let mut properties = Map::new();
+                let mut required: Vec<Value> = Vec::new();
+                #(#properties)*
+                let mut schema = Map::new();
+                schema.insert("type".to_string(), Value::from("object"));
+                schema.insert("properties".to_string(), Value::Object(properties));
+                schema.insert("required".to_string(), Value::Array(required));
+                #closed
+                #describe
+                Value::Object(schema)
+            }
+        }
+    })
+}
+
+/// The last path segment of a type, e.g. `Vec` for `std::vec::Vec<T>`.
+fn last_segment(ty: &Type) -> Option<&syn::PathSegment> {
+    match ty {
+        Type::Path(path) if path.qself.is_none() => path.path.segments.last(),
+        _ => None,
+    }
+}
+
+/// The `n`th type argument of a generic type.
+fn type_arg(ty: &Type, n: usize) -> Option<&Type> {
+    match &last_segment(ty)?.arguments {
+        PathArguments::AngleBracketed(args) => args
+            .args
+            .iter()
+            .filter_map(|arg| match arg {
+                GenericArgument::Type(ty) => Some(ty),
+                _ => None,
+            })
+            .nth(n),
+        _ => None,
+    }
+}
+
+fn option_inner(ty: &Type) -> Option<&Type> {
+    (last_segment(ty)?.ident == "Option").then(|| type_arg(ty, 0)).flatten()
+}
+
+/// An expression building the schema of `ty`.
+fn schema_for(ty: &Type) -> TokenStream2 {
+    let json = quote!(::harbor_mcp_sdk::__private::serde_json::json!);
+    let primitive = |name: &str| quote!(#json({ "type": #name }));
+    match ty {
+        Type::Reference(reference) => return schema_for(&reference.elem),
+        Type::Array(array) => {
+            let items = schema_for(&array.elem);
+            return quote!(#json({ "type": "array", "items": #items }));
+        }
+        Type::Slice(slice) => {
+            let items = schema_for(&slice.elem);
+            return quote!(#json({ "type": "array", "items": #items }));
+        }
+        _ => {}
+    }
+    let Some(segment) = last_segment(ty) else {
+        return quote!(<#ty as ::harbor_mcp_sdk::ToolInput>::schema());
+    };
+    match segment.ident.to_string().as_str() {
+        "String" | "str" | "char" | "PathBuf" => primitive("string"),
+        "i8" | "i16" | "i32" | "i64" | "i128" | "isize" => primitive("integer"),
+        "u8" | "u16" | "u32" | "u64" | "u128" | "usize" => quote!(#json({ "type": "integer", "minimum": 0 })),
+        "f32" | "f64" => primitive("number"),
+        "bool" => primitive("boolean"),
+        "Value" => quote!(#json({})),
+        "Vec" | "VecDeque" | "HashSet" | "BTreeSet" => match type_arg(ty, 0) {
+            Some(item) => {
+                let items = schema_for(item);
+                quote!(#json({ "type": "array", "items": #items }))
+            }
+            None => primitive("array"),
+        },
+        "HashMap" | "BTreeMap" => match type_arg(ty, 1) {
+            Some(value) => {
+                let values = schema_for(value);
+                quote!(#json({ "type": "object", "additionalProperties": #values }))
+            }
+            None => primitive("object"),
+        },
+        "Box" | "Option" => match type_arg(ty, 0) {
+            Some(inner) => schema_for(inner),
+            None => quote!(#json({})),
+        },
+        _ => quote!(<#ty as ::harbor_mcp_sdk::ToolInput>::schema()),
+    }
+}
@@ -0,0 +1,240 @@
+//! Web APIs that take a bearer token and answer JSON, such as Gmail's or
+//! GitHub's, for components with an account connected in Harbor.
+//!
+//! The SDK has no bindings of its own, so a component passes [`Api`] the
+//! token it got from the `harbor:mcp/oauth` import and a function sending
+//! an [`HttpRequest`] through its `harbor:mcp/http` import, which
+//! [`http_fetch!`](crate::http_fetch) writes for it.
+
+use serde_json::Value;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpRequest {
+    pub method: String,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Option<Vec<u8>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+/// Sends a request, failing only if it couldn't be made.
+pub type Fetch = fn(HttpRequest) -> Result<HttpResponse, String>;
+
+/// Define `fn fetch`, a [`Fetch`] sending requests through the component's
+/// `harbor:mcp/http` import. The component's bindings must be in scope as
+/// `http`, as after `use harbor::mcp::http;`.
+#[macro_export]
+macro_rules! http_fetch {
+    () => {
+        fn fetch(request: $crate::HttpRequest) -> Result<$crate::HttpResponse, String> {
+            let response = http::fetch(&http::Request {
+                method: request.method,
+                url: request.url,
+                headers: request.headers,
+                body: request.body,
+            })?;
+            Ok($crate::HttpResponse {
+                status: response.status,
+                headers: response.headers,
+                body: response.body,
+            })
+        }
+    };
+}
+
+/// An API, with a token for the connected account.
+pub struct Api {
+    name: String,
+    account: String,
+    base: String,
+    token: String,
+    headers: Vec<(String, String)>,
+    message_at: &'static str,
+    hint: fn(&HttpResponse) -> Option<&'static str>,
+    fetch: Fetch,
+}
+
+impl Api {
+    /// `name` is how errors call the API, e.g. `Gmail`, and `account` what
+    /// to connect again when the token is refused, e.g. `Google`.
+    pub fn new(name: impl Into<String>, account: impl Into<String>, token: impl Into<String>, fetch: Fetch) -> Self {
+        Self {
+            name: name.into(),
+            account: account.into(),
+            base: String::new(),
+            token: token.into(),
+            headers: Vec::new(),
+            message_at: "/error/message",
+            hint: |_| None,
+            fetch,
+        }
+    }
+
+    /// Prefix request paths with `base`, e.g. `https://api.github.com`.
+    pub fn base(mut self, base: impl Into<String>) -> Self {
+        self.base = base.into();
+        self
+    }
+
+    /// Send `name: value` with every request.
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Where the message is in an error answer, as a JSON pointer; Google's
+    /// APIs put it at `/error/message`, the default.
+    pub fn message_at(mut self, pointer: &'static str) -> Self {
+        self.message_at = pointer;
+        self
+    }
+
+    /// What to add to the message of an error answer, such as what to do
+    /// about a rate limit. A 401 says to connect the account again unless
+    /// this says otherwise.
+    pub fn hint(mut self, hint: fn(&HttpResponse) -> Option<&'static str>) -> Self {
+        self.hint = hint;
+        self
+    }
+
+    /// Make a request with a body of the given content type, failing with
+    /// the API's message on an error status.
+    pub fn request(&self, method: &str, path: &str, body: Option<(String, Vec<u8>)>) -> Result<HttpResponse, String> {
+        let mut headers = vec![("Authorization".to_string(), format!("Bearer {}", self.token))];
+        headers.extend(self.headers.iter().cloned());
+        let body = body.map(|(content_type, body)| {
+            headers.push(("Content-Type".to_string(), content_type));
+            body
+        });
+        let response = (self.fetch)(HttpRequest {
+            method: method.to_string(),
+            url: format!("{}{}", self.base, path),
+            headers,
+            body,
+        })?;
+        if response.status >= 400 {
+            return Err(self.error(&response));
+        }
+        Ok(response)
+    }
+
+    /// Make a request with a JSON body, if any, and return the JSON answer
+    /// (null if there is none).
+    pub fn json(&self, method: &str, path: &str, body: Option<&Value>) -> Result<Value, String> {
+        let body = body.map(|body| ("application/json".to_string(), body.to_string().into_bytes()));
+        let response = self.request(method, path, body)?;
+        Ok(serde_json::from_slice(&response.body).unwrap_or(Value::Null))
+    }
+
+    fn error(&self, response: &HttpResponse) -> String {
+        let value: Value = serde_json::from_slice(&response.body).unwrap_or(Value::Null);
+        let message = value
+            .pointer(self.message_at)
+            .and_then(Value::as_str)
+            .map(str::to_string)
+            .unwrap_or_else(|| String::from_utf8_lossy(&response.body).into_owned());
+        let hint = match (self.hint)(response) {
+            Some(hint) => hint.to_string(),
+            None if response.status == 401 => format!("; connect the {} account again", self.account),
+            None => String::new(),
+        };
+        format!("{} API error ({}): {}{}", self.name, response.status, message, hint)
+    }
+}
+
+/// `text` for a URL query or path segment, with everything but unreserved
+/// characters percent-encoded.
+pub fn encode_query(text: &str) -> String {
+    text.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+            b => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// Answers like a JSON API: the request back, or for `/missing`, a 404,
+    /// and for `/expired`, a 401.
+    fn fake(request: HttpRequest) -> Result<HttpResponse, String> {
+        let (status, body) = match request.url.as_str() {
+            "https://api.example.com/missing" => (404, json!({ "error": { "message": "Not found" } })),
+            "https://api.example.com/expired" => (401, json!({ "message": "Bad credentials" })),
+            _ => (
+                200,
+                json!({
+                    "method": request.method,
+                    "url": request.url,
+                    "headers": request.headers,
+                    "body": request.body.map(|body| String::from_utf8(body).unwrap()),
+                }),
+            ),
+        };
+        Ok(HttpResponse {
+            status,
+            headers: Vec::new(),
+            body: body.to_string().into_bytes(),
+        })
+    }
+
+    #[test]
+    fn test_requests() {
+        let api = Api::new("Example", "Example", "t0k", fake)
+            .base("https://api.example.com")
+            .header("Accept", "application/json");
+        let echoed = api.json("POST", "/items", Some(&json!({ "a": 1 }))).unwrap();
+        assert_eq!(echoed["url"], "https://api.example.com/items");
+        assert_eq!(
+            echoed["headers"],
+            json!([
+                ["Authorization", "Bearer t0k"],
+                ["Accept", "application/json"],
+                ["Content-Type", "application/json"]
+            ])
+        );
+        assert_eq!(echoed["body"], r#"{"a":1}"#);
+
+        assert_eq!(
+            api.json("GET", "/missing", None).unwrap_err(),
+            "Example API error (404): Not found"
+        );
+        assert_eq!(
+            api.json("GET", "/expired", None).unwrap_err(),
+            r#"Example API error (401): {"message":"Bad credentials"}; connect the Example account again"#
+        );
+        let api = api
+            .message_at("/message")
+            .hint(|response| (response.status == 404).then_some("; is it private?"));
+        assert_eq!(
+            api.json("GET", "/expired", None).unwrap_err(),
+            "Example API error (401): Bad credentials; connect the Example account again"
+        );
+        assert_eq!(
+            api.json("GET", "/missing", None).unwrap_err(),
+            r#"Example API error (404): {"error":{"message":"Not found"}}; is it private?"#
+        );
+    }
+
+    #[test]
+    fn test_encode_query() {
+        assert_eq!(
+            encode_query("from:ada@example.com is:unread"),
+            "from%3Aada%40example.com%20is%3Aunread"
+        );
+        assert_eq!(
+            encode_query("2024-06-03T09:00:00+02:00"),
+            "2024-06-03T09%3A00%3A00%2B02%3A00"
+        );
+        assert_eq!(encode_query("café"), "caf%C3%A9");
+    }
+}
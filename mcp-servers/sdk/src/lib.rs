@@ -0,0 +1,773 @@
+//! Harbor MCP SDK
+//!
+//! Everything a Rust MCP server needs besides its tools: the JSON-RPC
+//! types, `initialize`, `tools/list`, dispatch of `tools/call`, resources,
+//! prompts, notifications, the host's [`Locale`], an [`Api`] client for
+//! the web APIs of accounts the user connected, and the stdin loop of WASI
+//! preview 1 servers.
+//!
+//! ```no_run
+//! use harbor_mcp_sdk::Server;
+//! use serde_json::json;
+//!
+//! Server::new("my-server", "1.0.0")
+//!     .tool(
+//!         "greet",
+//!         json!({
+//!             "description": "Say hello to someone",
+//!             "type": "object",
+//!             "properties": { "name": { "type": "string" } },
+//!             "required": ["name"]
+//!         }),
+//!         |args| Ok(format!("Hello, {}!", args.str("name")?)),
+//!     )
+//!     .run();
+//! ```
+//!
+//! A tool's description is the `description` at the root of its input
+//! schema. Arguments are checked against the schema before the handler
+//! runs, and a call that doesn't match is answered with `-32602` and the
+//! fields that were wrong. Components (WASI preview 2) call [`Server::handle`] from their
+//! exported `handle` function instead of [`Server::run`].
+//!
+//! With the `macros` feature (on by default), the schema comes from the
+//! handler's argument struct instead, and arguments arrive deserialized:
+//!
+//! ```ignore
+//! use harbor_mcp_sdk::{harbor_tool, Error, Server, ToolInput};
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize, ToolInput)]
+//! struct GreetArgs {
+//!     /// Name of the person to greet
+//!     name: String,
+//! }
+//!
+//! /// Say hello to someone
+//! #[harbor_tool]
+//! fn greet(args: GreetArgs) -> Result<String, Error> {
+//!     Ok(format!("Hello, {}!", args.name))
+//! }
+//!
+//! Server::new("my-server", "1.0.0").register(greet_tool()).run();
+//! ```
+
+use std::io::{self, BufRead, Write};
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+mod api;
+mod date;
+mod locale;
+mod notify;
+mod prompt;
+mod resource;
+mod schema;
+
+pub use api::{encode_query, Api, Fetch, HttpRequest, HttpResponse};
+pub use date::{civil_from_days, days_from_civil, rfc3339};
+pub use locale::Locale;
+pub use notify::{log, notify, tools_changed, Level};
+pub use prompt::{Message, Prompt};
+pub use resource::Resource;
+pub use schema::{validate, FieldError};
+
+#[cfg(feature = "macros")]
+pub use harbor_mcp_macros::{harbor_tool, ToolInput};
+
+#[doc(hidden)]
+pub mod __private {
+    pub use serde_json;
+}
+
+/// The MCP protocol version servers answer `initialize` with.
+pub const PROTOCOL_VERSION: &str = "2024-11-05";
+
+/// A JSON-RPC request. Notifications have no `id`.
+#[derive(Debug, Deserialize)]
+pub struct Request {
+    #[serde(default)]
+    pub id: Option<Value>,
+    pub method: String,
+    #[serde(default)]
+    pub params: Value,
+}
+
+/// A JSON-RPC response.
+#[derive(Debug, Serialize)]
+pub struct Response {
+    jsonrpc: &'static str,
+    pub id: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<Error>,
+}
+
+impl Response {
+    pub fn result(id: Value, result: Value) -> Self {
+        Self {
+            jsonrpc: "2.0",
+            id,
+            result: Some(result),
+            error: None,
+        }
+    }
+
+    pub fn error(id: Value, error: Error) -> Self {
+        Self {
+            jsonrpc: "2.0",
+            id,
+            result: None,
+            error: Some(error),
+        }
+    }
+}
+
+/// A JSON-RPC error.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Error {
+    pub code: i64,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+}
+
+impl Error {
+    pub const PARSE_ERROR: i64 = -32700;
+    pub const METHOD_NOT_FOUND: i64 = -32601;
+    pub const INVALID_PARAMS: i64 = -32602;
+    pub const INTERNAL_ERROR: i64 = -32603;
+    /// MCP's code for `resources/read` of an unknown URI.
+    pub const RESOURCE_NOT_FOUND: i64 = -32002;
+
+    pub fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            data: None,
+        }
+    }
+
+    pub fn with_data(mut self, data: Value) -> Self {
+        self.data = Some(data);
+        self
+    }
+
+    pub fn parse_error() -> Self {
+        Self::new(Self::PARSE_ERROR, "Parse error")
+    }
+
+    pub fn method_not_found(method: &str) -> Self {
+        Self::new(Self::METHOD_NOT_FOUND, format!("Method not found: {}", method))
+    }
+
+    pub fn invalid_params(message: impl Into<String>) -> Self {
+        Self::new(Self::INVALID_PARAMS, message)
+    }
+
+    /// `-32602` for arguments that don't match the schema, with the wrong
+    /// fields in `data.fields`.
+    pub fn invalid_arguments(fields: Vec<FieldError>) -> Self {
+        let summary: Vec<String> = fields
+            .iter()
+            .map(|f| {
+                let field = if f.field.is_empty() { "arguments" } else { f.field.as_str() };
+                format!("{}: {}", field, f.problem)
+            })
+            .collect();
+        Self::invalid_params(format!("Invalid arguments: {}", summary.join("; ")))
+            .with_data(serde_json::json!({ "fields": fields }))
+    }
+
+    pub fn internal(message: impl Into<String>) -> Self {
+        Self::new(Self::INTERNAL_ERROR, message)
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({})", self.message, self.code)
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// A tool call's arguments.
+#[derive(Debug, Clone, Default)]
+pub struct Args(Value);
+
+impl Args {
+    pub fn new(value: Value) -> Self {
+        Self(value)
+    }
+
+    pub fn value(&self) -> &Value {
+        &self.0
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.0.get(name)
+    }
+
+    /// An argument that is missing or not of type `expected`.
+    fn wrong(&self, name: &str, expected: &str) -> Error {
+        let problem = match self.get(name) {
+            None => "missing".to_string(),
+            Some(value) => {
+                let errors = validate(&serde_json::json!({ "type": expected }), value);
+                errors.into_iter().next().map(|e| e.problem).unwrap_or_else(|| format!("expected {}", expected))
+            }
+        };
+        Error::invalid_arguments(vec![FieldError {
+            field: name.to_string(),
+            problem,
+        }])
+    }
+
+    /// A required string argument.
+    pub fn str(&self, name: &str) -> Result<&str, Error> {
+        self.opt_str(name).ok_or_else(|| self.wrong(name, "string"))
+    }
+
+    pub fn opt_str(&self, name: &str) -> Option<&str> {
+        self.get(name).and_then(Value::as_str)
+    }
+
+    /// A required number argument.
+    pub fn f64(&self, name: &str) -> Result<f64, Error> {
+        self.opt_f64(name).ok_or_else(|| self.wrong(name, "number"))
+    }
+
+    pub fn opt_f64(&self, name: &str) -> Option<f64> {
+        self.get(name).and_then(Value::as_f64)
+    }
+
+    /// A required integer argument.
+    pub fn i64(&self, name: &str) -> Result<i64, Error> {
+        self.get(name)
+            .and_then(Value::as_i64)
+            .ok_or_else(|| self.wrong(name, "integer"))
+    }
+
+    pub fn opt_bool(&self, name: &str) -> Option<bool> {
+        self.get(name).and_then(Value::as_bool)
+    }
+
+    /// All arguments as a struct.
+    pub fn parse<T: DeserializeOwned>(&self) -> Result<T, Error> {
+        serde_json::from_value(self.0.clone()).map_err(|e| Error::invalid_params(format!("Invalid arguments: {}", e)))
+    }
+}
+
+/// The result of a tool call. Strings convert to a single text item.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ToolResult {
+    pub content: Vec<Value>,
+    #[serde(rename = "isError", skip_serializing_if = "std::ops::Not::not")]
+    pub is_error: bool,
+}
+
+impl ToolResult {
+    pub fn text(text: impl Into<String>) -> Self {
+        Self {
+            content: vec![serde_json::json!({ "type": "text", "text": text.into() })],
+            is_error: false,
+        }
+    }
+
+    /// `value` as pretty-printed JSON text.
+    pub fn json(value: &Value) -> Self {
+        Self::text(serde_json::to_string_pretty(value).unwrap_or_default())
+    }
+
+    /// A failure the model should see, such as a rejected input, as opposed
+    /// to a protocol error.
+    pub fn error(message: impl Into<String>) -> Self {
+        Self {
+            is_error: true,
+            ..Self::text(message)
+        }
+    }
+}
+
+impl From<String> for ToolResult {
+    fn from(text: String) -> Self {
+        Self::text(text)
+    }
+}
+
+impl From<&str> for ToolResult {
+    fn from(text: &str) -> Self {
+        Self::text(text)
+    }
+}
+
+/// A type whose JSON Schema is known, usually a tool's arguments. Derive
+/// it with `#[derive(ToolInput)]`.
+pub trait ToolInput {
+    fn schema() -> Value;
+}
+
+type Handler = Box<dyn Fn(&Args) -> Result<ToolResult, Error>>;
+
+/// A tool: its name, input schema and handler.
+pub struct Tool {
+    name: String,
+    schema: Value,
+    handler: Handler,
+}
+
+impl Tool {
+    /// A tool taking its arguments as [`Args`].
+    pub fn new<R, F>(name: impl Into<String>, schema: Value, handler: F) -> Self
+    where
+        R: Into<ToolResult>,
+        F: Fn(&Args) -> Result<R, Error> + 'static,
+    {
+        Self {
+            name: name.into(),
+            schema,
+            handler: Box::new(move |args| handler(args).map(Into::into)),
+        }
+    }
+
+    /// A tool taking its arguments as `T`, with `T`'s schema. Arguments
+    /// that don't deserialize are rejected with `-32602`.
+    pub fn typed<T, R, F>(name: impl Into<String>, description: Option<&str>, handler: F) -> Self
+    where
+        T: ToolInput + DeserializeOwned,
+        R: Into<ToolResult>,
+        F: Fn(T) -> Result<R, Error> + 'static,
+    {
+        let mut schema = T::schema();
+        if let (Some(description), Value::Object(map)) = (description, &mut schema) {
+            map.insert("description".to_string(), Value::from(description));
+        }
+        Self::new(name, schema, move |args: &Args| handler(args.parse::<T>()?))
+    }
+}
+
+/// An MCP server: its identity, tools, resources and prompts.
+pub struct Server {
+    name: String,
+    version: String,
+    tools: Vec<Tool>,
+    resources: Vec<Resource>,
+    prompts: Vec<Prompt>,
+}
+
+impl Server {
+    pub fn new(name: impl Into<String>, version: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            version: version.into(),
+            tools: Vec::new(),
+            resources: Vec::new(),
+            prompts: Vec::new(),
+        }
+    }
+
+    /// Add a tool with its input schema and handler.
+    pub fn tool<R, F>(self, name: impl Into<String>, schema: Value, handler: F) -> Self
+    where
+        R: Into<ToolResult>,
+        F: Fn(&Args) -> Result<R, Error> + 'static,
+    {
+        self.register(Tool::new(name, schema, handler))
+    }
+
+    /// Add a tool, such as one made by `#[harbor_tool]`.
+    pub fn register(mut self, tool: Tool) -> Self {
+        self.tools.push(tool);
+        self
+    }
+
+    /// Add a resource, listed by `resources/list` and read by
+    /// `resources/read`.
+    pub fn resource(mut self, resource: Resource) -> Self {
+        self.resources.push(resource);
+        self
+    }
+
+    /// Add a prompt, listed by `prompts/list` and filled in by
+    /// `prompts/get`.
+    pub fn prompt(mut self, prompt: Prompt) -> Self {
+        self.prompts.push(prompt);
+        self
+    }
+
+    fn list_tools(&self) -> Value {
+        let tools: Vec<Value> = self
+            .tools
+            .iter()
+            .map(|tool| {
+                let mut entry = serde_json::json!({ "name": tool.name, "inputSchema": tool.schema });
+                if let Some(description) = tool.schema.get("description") {
+                    entry["description"] = description.clone();
+                }
+                entry
+            })
+            .collect();
+        serde_json::json!({ "tools": tools })
+    }
+
+    fn call_tool(&self, params: &Value) -> Result<Value, Error> {
+        let name = params.get("name").and_then(Value::as_str).unwrap_or_default();
+        let tool = self
+            .tools
+            .iter()
+            .find(|tool| tool.name == name)
+            .ok_or_else(|| Error::invalid_params(format!("Unknown tool: {}", name)))?;
+        let args = Args::new(params.get("arguments").cloned().unwrap_or_else(|| serde_json::json!({})));
+        let errors = validate(&tool.schema, args.value());
+        if !errors.is_empty() {
+            return Err(Error::invalid_arguments(errors));
+        }
+        let result = (tool.handler)(&args)?;
+        serde_json::to_value(result).map_err(|e| Error::internal(e.to_string()))
+    }
+
+    fn read_resource(&self, params: &Value) -> Result<Value, Error> {
+        let uri = params
+            .get("uri")
+            .and_then(Value::as_str)
+            .ok_or_else(|| Error::invalid_params("Missing 'uri' parameter"))?;
+        self.resources
+            .iter()
+            .find(|resource| resource.uri() == uri)
+            .ok_or_else(|| Error::new(Error::RESOURCE_NOT_FOUND, format!("Resource not found: {}", uri)))?
+            .read()
+    }
+
+    fn get_prompt(&self, params: &Value) -> Result<Value, Error> {
+        let name = params
+            .get("name")
+            .and_then(Value::as_str)
+            .ok_or_else(|| Error::invalid_params("Missing 'name' parameter"))?;
+        let prompt = self
+            .prompts
+            .iter()
+            .find(|prompt| prompt.name() == name)
+            .ok_or_else(|| Error::invalid_params(format!("Unknown prompt: {}", name)))?;
+        let args = Args::new(params.get("arguments").cloned().unwrap_or_else(|| serde_json::json!({})));
+        prompt.get(&args)
+    }
+
+    /// The capabilities answered to `initialize`: tools (whose list may
+    /// change, see [`tools_changed`]), logging, and resources and prompts if
+    /// there are any.
+    fn capabilities(&self) -> Value {
+        let mut capabilities = serde_json::json!({ "tools": { "listChanged": true }, "logging": {} });
+        if !self.resources.is_empty() {
+            capabilities["resources"] = serde_json::json!({});
+        }
+        if !self.prompts.is_empty() {
+            capabilities["prompts"] = serde_json::json!({});
+        }
+        capabilities
+    }
+
+    /// Answer one request; `None` for notifications.
+    pub fn handle_request(&self, request: Request) -> Option<Response> {
+        let result = match request.method.as_str() {
+            "initialize" => Ok(serde_json::json!({
+                "protocolVersion": PROTOCOL_VERSION,
+                "capabilities": self.capabilities(),
+                "serverInfo": { "name": self.name, "version": self.version }
+            })),
+            "ping" => Ok(serde_json::json!({})),
+            "logging/setLevel" => match request.params.get("level").cloned().map(serde_json::from_value::<Level>) {
+                Some(Ok(level)) => {
+                    notify::set_level(level);
+                    Ok(serde_json::json!({}))
+                }
+                _ => Err(Error::invalid_params("Missing or unknown 'level'")),
+            },
+            "tools/list" => Ok(self.list_tools()),
+            "tools/call" => self.call_tool(&request.params),
+            "resources/list" if !self.resources.is_empty() => {
+                let resources: Vec<Value> = self.resources.iter().map(Resource::entry).collect();
+                Ok(serde_json::json!({ "resources": resources }))
+            }
+            "resources/read" if !self.resources.is_empty() => self.read_resource(&request.params),
+            "prompts/list" if !self.prompts.is_empty() => {
+                let prompts: Vec<Value> = self.prompts.iter().map(Prompt::entry).collect();
+                Ok(serde_json::json!({ "prompts": prompts }))
+            }
+            "prompts/get" if !self.prompts.is_empty() => self.get_prompt(&request.params),
+            method => Err(Error::method_not_found(method)),
+        };
+        let id = request.id?;
+        Some(match result {
+            Ok(result) => Response::result(id, result),
+            Err(error) => Response::error(id, error),
+        })
+    }
+
+    /// Answer one JSON-RPC message. Returns the messages to send back, one
+    /// per line: the notifications raised while handling it, then the
+    /// response. Empty for a notification that raised none.
+    pub fn handle(&self, message: &str) -> String {
+        let response = match serde_json::from_str::<Request>(message) {
+            Ok(request) => self.handle_request(request),
+            Err(_) => Some(Response::error(Value::Null, Error::parse_error())),
+        };
+        let response = response.and_then(|response| serde_json::to_value(&response).ok());
+        let lines: Vec<String> = notify::drain()
+            .into_iter()
+            .chain(response)
+            .map(|message| message.to_string())
+            .collect();
+        lines.join("\n")
+    }
+
+    /// Answer requests from stdin, one per line, until it closes.
+    pub fn run(&self) {
+        let stdin = io::stdin();
+        for line in stdin.lock().lines() {
+            let Ok(line) = line else { break };
+            if line.trim().is_empty() {
+                continue;
+            }
+            let response = self.handle(&line);
+            if response.is_empty() {
+                continue;
+            }
+            let mut out = io::stdout().lock();
+            let _ = out.write_all(response.as_bytes());
+            let _ = out.write_all(b"\n");
+            let _ = out.flush();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn server() -> Server {
+        Server::new("test", "1.0.0")
+            .tool(
+                "greet",
+                json!({ "description": "Say hello", "type": "object" }),
+                |args| Ok(format!("Hello, {}!", args.str("name")?)),
+            )
+            .tool("fail", json!({ "type": "object" }), |_| Ok(ToolResult::error("nope")))
+    }
+
+    fn call(server: &Server, message: Value) -> Value {
+        serde_json::from_str(&server.handle(&message.to_string())).unwrap()
+    }
+
+    #[test]
+    fn test_initialize_and_list() {
+        let server = server();
+        let response = call(&server, json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize" }));
+        assert_eq!(response["result"]["serverInfo"]["name"], "test");
+        assert_eq!(response["result"]["protocolVersion"], PROTOCOL_VERSION);
+
+        let response = call(&server, json!({ "jsonrpc": "2.0", "id": 2, "method": "tools/list" }));
+        let tools = response["result"]["tools"].as_array().unwrap();
+        assert_eq!(tools[0]["name"], "greet");
+        assert_eq!(tools[0]["description"], "Say hello");
+        assert!(tools[1].get("description").is_none());
+    }
+
+    #[test]
+    fn test_call_tool() {
+        let server = server();
+        let response = call(
+            &server,
+            json!({ "id": 1, "method": "tools/call", "params": { "name": "greet", "arguments": { "name": "Ada" } } }),
+        );
+        assert_eq!(response["result"]["content"][0]["text"], "Hello, Ada!");
+        assert!(response["result"].get("isError").is_none());
+
+        let response = call(&server, json!({ "id": 2, "method": "tools/call", "params": { "name": "greet" } }));
+        assert_eq!(response["error"]["code"], Error::INVALID_PARAMS);
+        assert_eq!(response["error"]["data"]["fields"], json!([{ "field": "name", "problem": "missing" }]));
+
+        let response = call(&server, json!({ "id": 3, "method": "tools/call", "params": { "name": "fail" } }));
+        assert_eq!(response["result"]["isError"], true);
+
+        let response = call(&server, json!({ "id": 4, "method": "tools/call", "params": { "name": "missing" } }));
+        assert_eq!(response["error"]["message"], "Unknown tool: missing");
+    }
+
+    #[test]
+    fn test_errors_and_notifications() {
+        let server = server();
+        let response = call(&server, json!({ "id": 1, "method": "resources/list" }));
+        assert_eq!(response["error"]["code"], Error::METHOD_NOT_FOUND);
+
+        let response: Value = serde_json::from_str(&server.handle("not json")).unwrap();
+        assert_eq!(response["error"]["code"], Error::PARSE_ERROR);
+        assert!(response["id"].is_null());
+        assert_eq!(server.handle(r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#), "");
+    }
+
+    #[test]
+    fn test_resources() {
+        let server = server().resource(
+            Resource::new("file:///notes.md", "Notes", || Ok("# Notes".to_string()))
+                .description("Things to remember")
+                .mime_type("text/markdown"),
+        );
+        let response = call(&server, json!({ "id": 1, "method": "initialize" }));
+        assert_eq!(response["result"]["capabilities"]["resources"], json!({}));
+
+        let response = call(&server, json!({ "id": 2, "method": "resources/list" }));
+        assert_eq!(
+            response["result"]["resources"],
+            json!([{
+                "uri": "file:///notes.md",
+                "name": "Notes",
+                "description": "Things to remember",
+                "mimeType": "text/markdown"
+            }])
+        );
+
+        let response = call(
+            &server,
+            json!({ "id": 3, "method": "resources/read", "params": { "uri": "file:///notes.md" } }),
+        );
+        assert_eq!(
+            response["result"]["contents"],
+            json!([{ "uri": "file:///notes.md", "mimeType": "text/markdown", "text": "# Notes" }])
+        );
+
+        let response = call(
+            &server,
+            json!({ "id": 4, "method": "resources/read", "params": { "uri": "file:///other.md" } }),
+        );
+        assert_eq!(response["error"]["code"], Error::RESOURCE_NOT_FOUND);
+
+        // Without resources, the capability isn't advertised
+        let response = call(&self::server(), json!({ "id": 5, "method": "initialize" }));
+        assert!(response["result"]["capabilities"].get("resources").is_none());
+    }
+
+    #[test]
+    fn test_prompts() {
+        let server = server().prompt(
+            Prompt::new("review", |args| {
+                let language = args.opt_str("language").unwrap_or("code");
+                Ok(vec![Message::user(format!("Review this {}:\n{}", language, args.str("code")?))])
+            })
+            .description("Review code")
+            .argument("code", "The code to review", true)
+            .argument("language", "Its language", false),
+        );
+        let response = call(&server, json!({ "id": 1, "method": "initialize" }));
+        assert_eq!(response["result"]["capabilities"]["prompts"], json!({}));
+
+        let response = call(&server, json!({ "id": 2, "method": "prompts/list" }));
+        let prompt = &response["result"]["prompts"][0];
+        assert_eq!(prompt["name"], "review");
+        assert_eq!(prompt["arguments"][0], json!({ "name": "code", "description": "The code to review", "required": true }));
+
+        let response = call(
+            &server,
+            json!({ "id": 3, "method": "prompts/get", "params": { "name": "review", "arguments": { "code": "x = 1", "language": "Python" } } }),
+        );
+        assert_eq!(response["result"]["description"], "Review code");
+        assert_eq!(
+            response["result"]["messages"],
+            json!([{ "role": "user", "content": { "type": "text", "text": "Review this Python:\nx = 1" } }])
+        );
+
+        let response = call(
+            &server,
+            json!({ "id": 4, "method": "prompts/get", "params": { "name": "review", "arguments": { "language": 3 } } }),
+        );
+        assert_eq!(
+            response["error"]["data"]["fields"],
+            json!([
+                { "field": "code", "problem": "missing" },
+                { "field": "language", "problem": "expected string" }
+            ])
+        );
+
+        let response = call(&server, json!({ "id": 5, "method": "prompts/get", "params": { "name": "other" } }));
+        assert_eq!(response["error"]["message"], "Unknown prompt: other");
+    }
+
+    #[test]
+    fn test_notifications() {
+        let server = Server::new("test", "1.0.0").tool("reload", json!({ "type": "object" }), |_| {
+            log(Level::Debug, "reloading");
+            log(Level::Info, "reloaded");
+            tools_changed();
+            Ok("done")
+        });
+        let call_reload = r#"{"jsonrpc":"2.0","id":1,"method":"tools/call","params":{"name":"reload"}}"#;
+        let output = server.handle(call_reload);
+        let lines: Vec<Value> = output.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0], json!({ "jsonrpc": "2.0", "method": "notifications/message", "params": { "level": "debug", "data": "reloading" } }));
+        assert_eq!(lines[2], json!({ "jsonrpc": "2.0", "method": "notifications/tools/list_changed" }));
+        assert_eq!(lines[3]["id"], 1);
+
+        // Raised once, sent once
+        assert_eq!(server.handle(r#"{"jsonrpc":"2.0","id":2,"method":"ping"}"#).lines().count(), 1);
+
+        let response = server.handle(r#"{"jsonrpc":"2.0","id":3,"method":"logging/setLevel","params":{"level":"info"}}"#);
+        assert!(response.contains("\"result\""));
+        let output = server.handle(call_reload);
+        assert_eq!(output.lines().count(), 3);
+        assert!(!output.contains("reloading"));
+        notify::set_level(Level::Debug);
+    }
+
+    #[derive(Deserialize)]
+    struct AddArgs {
+        a: i64,
+        b: i64,
+    }
+
+    impl ToolInput for AddArgs {
+        fn schema() -> Value {
+            json!({
+                "type": "object",
+                "properties": { "a": { "type": "integer" }, "b": { "type": "integer" } },
+                "required": ["a", "b"],
+            })
+        }
+    }
+
+    #[test]
+    fn test_typed_tool() {
+        let server = Server::new("test", "1.0.0").register(Tool::typed(
+            "add",
+            Some("Add two integers"),
+            |args: AddArgs| Ok((args.a + args.b).to_string()),
+        ));
+        let tools = server.list_tools();
+        assert_eq!(tools["tools"][0]["description"], "Add two integers");
+        assert_eq!(tools["tools"][0]["inputSchema"]["required"], json!(["a", "b"]));
+
+        let response = call(
+            &server,
+            json!({ "id": 1, "method": "tools/call", "params": { "name": "add", "arguments": { "a": 2, "b": 3 } } }),
+        );
+        assert_eq!(response["result"]["content"][0]["text"], "5");
+
+        let response = call(
+            &server,
+            json!({ "id": 2, "method": "tools/call", "params": { "name": "add", "arguments": { "a": "2" } } }),
+        );
+        assert_eq!(response["error"]["code"], Error::INVALID_PARAMS);
+        assert_eq!(response["error"]["message"], "Invalid arguments: b: missing; a: expected integer, got string");
+        assert_eq!(
+            response["error"]["data"]["fields"],
+            json!([
+                { "field": "b", "problem": "missing" },
+                { "field": "a", "problem": "expected integer, got string" }
+            ])
+        );
+    }
+}
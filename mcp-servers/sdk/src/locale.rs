@@ -0,0 +1,75 @@
+//! The user's locale and time zone, for servers granted the `locale`
+//! capability.
+//!
+//! Harbor passes them in the environment: `HARBOR_LOCALE` (a BCP 47 tag
+//! such as `de-DE`), `TZ` (an IANA zone such as `Europe/Berlin`) and
+//! `HARBOR_UTC_OFFSET_MINUTES` (the zone's current offset, east positive),
+//! as well as `LANG` for libraries that read it. Modules run per request
+//! see the offset as of the request; components see it as of their start.
+
+/// What the host said about the user's locale. Fields are `None` when the
+/// server wasn't granted `locale`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Locale {
+    /// BCP 47 language tag, e.g. `en-US`
+    pub language: Option<String>,
+    /// IANA time zone, e.g. `America/New_York`
+    pub timezone: Option<String>,
+    /// Offset from UTC in minutes, e.g. `-300` for New York in winter
+    pub utc_offset_minutes: Option<i32>,
+}
+
+impl Locale {
+    /// Read the locale from the environment.
+    pub fn from_env() -> Self {
+        Self::from_vars(|name| std::env::var(name).ok())
+    }
+
+    fn from_vars(var: impl Fn(&str) -> Option<String>) -> Self {
+        let set = |name: &str| var(name).filter(|value| !value.is_empty());
+        Self {
+            language: set("HARBOR_LOCALE"),
+            timezone: set("TZ"),
+            utc_offset_minutes: set("HARBOR_UTC_OFFSET_MINUTES").and_then(|value| value.parse().ok()),
+        }
+    }
+
+    /// The UTC offset as in ISO 8601, e.g. `+05:30`; `Z` when unknown.
+    pub fn utc_offset(&self) -> String {
+        match self.utc_offset_minutes {
+            None | Some(0) => "Z".to_string(),
+            Some(minutes) => {
+                let sign = if minutes < 0 { '-' } else { '+' };
+                let minutes = minutes.unsigned_abs();
+                format!("{}{:02}:{:02}", sign, minutes / 60, minutes % 60)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_vars() {
+        let locale = Locale::from_vars(|name| match name {
+            "HARBOR_LOCALE" => Some("hi-IN".to_string()),
+            "TZ" => Some("Asia/Kolkata".to_string()),
+            "HARBOR_UTC_OFFSET_MINUTES" => Some("330".to_string()),
+            _ => None,
+        });
+        assert_eq!(locale.language.as_deref(), Some("hi-IN"));
+        assert_eq!(locale.timezone.as_deref(), Some("Asia/Kolkata"));
+        assert_eq!(locale.utc_offset(), "+05:30");
+
+        let unset = Locale::from_vars(|_| None);
+        assert_eq!(unset, Locale::default());
+        assert_eq!(unset.utc_offset(), "Z");
+        let west = Locale {
+            utc_offset_minutes: Some(-210),
+            ..Locale::default()
+        };
+        assert_eq!(west.utc_offset(), "-03:30");
+    }
+}
@@ -0,0 +1,66 @@
+//! Notifications: messages a server sends without being asked, such as log
+//! lines or word that its tool list changed.
+//!
+//! Servers answer one request at a time, so notifications raised while
+//! handling a request are queued and written out ahead of its response,
+//! each on its own line.
+
+use std::cell::{Cell, RefCell};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Log levels, as in syslog and MCP's `logging` capability.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    Debug,
+    Info,
+    Notice,
+    Warning,
+    Error,
+    Critical,
+    Alert,
+    Emergency,
+}
+
+thread_local! {
+    static OUTBOX: RefCell<Vec<Value>> = const { RefCell::new(Vec::new()) };
+    /// The least severe level sent, as set by the client with `logging/setLevel`
+    static MIN_LEVEL: Cell<Level> = const { Cell::new(Level::Debug) };
+}
+
+/// Send a notification: a JSON-RPC message with no `id`.
+pub fn notify(method: &str, params: Value) {
+    let mut message = serde_json::json!({ "jsonrpc": "2.0", "method": method });
+    if !params.is_null() {
+        message["params"] = params;
+    }
+    OUTBOX.with(|outbox| outbox.borrow_mut().push(message));
+}
+
+/// Tell the client the tool list changed, so it asks for `tools/list`
+/// again.
+pub fn tools_changed() {
+    notify("notifications/tools/list_changed", Value::Null);
+}
+
+/// Send a log message, unless the client asked for less at this level.
+pub fn log(level: Level, message: impl Into<String>) {
+    if level < MIN_LEVEL.with(Cell::get) {
+        return;
+    }
+    notify(
+        "notifications/message",
+        serde_json::json!({ "level": level, "data": message.into() }),
+    );
+}
+
+pub(crate) fn set_level(level: Level) {
+    MIN_LEVEL.with(|min| min.set(level));
+}
+
+/// The notifications queued since the last call.
+pub(crate) fn drain() -> Vec<Value> {
+    OUTBOX.with(|outbox| outbox.take())
+}
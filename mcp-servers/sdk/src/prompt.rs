@@ -0,0 +1,120 @@
+//! Prompts: message templates a server offers for the user to pick, filled
+//! in from string arguments.
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::{Args, Error, FieldError};
+
+/// One message of a filled-in prompt.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Message {
+    pub role: &'static str,
+    pub content: Value,
+}
+
+impl Message {
+    pub fn user(text: impl Into<String>) -> Self {
+        Self {
+            role: "user",
+            content: serde_json::json!({ "type": "text", "text": text.into() }),
+        }
+    }
+
+    pub fn assistant(text: impl Into<String>) -> Self {
+        Self {
+            role: "assistant",
+            ..Self::user(text)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct Argument {
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    required: bool,
+}
+
+type Render = Box<dyn Fn(&Args) -> Result<Vec<Message>, Error>>;
+
+/// A prompt: its name, arguments and how to fill it in.
+pub struct Prompt {
+    name: String,
+    description: Option<String>,
+    arguments: Vec<Argument>,
+    render: Render,
+}
+
+impl Prompt {
+    /// A prompt filled in by `render`, which gets the arguments of
+    /// `prompts/get`. Required arguments are checked before it runs.
+    pub fn new<F>(name: impl Into<String>, render: F) -> Self
+    where
+        F: Fn(&Args) -> Result<Vec<Message>, Error> + 'static,
+    {
+        Self {
+            name: name.into(),
+            description: None,
+            arguments: Vec::new(),
+            render: Box::new(render),
+        }
+    }
+
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// Declare an argument. All prompt arguments are strings.
+    pub fn argument(mut self, name: impl Into<String>, description: impl Into<String>, required: bool) -> Self {
+        self.arguments.push(Argument {
+            name: name.into(),
+            description: Some(description.into()),
+            required,
+        });
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The entry in `prompts/list`.
+    pub(crate) fn entry(&self) -> Value {
+        let mut entry = serde_json::json!({ "name": self.name, "arguments": self.arguments });
+        if let Some(description) = &self.description {
+            entry["description"] = Value::from(description.as_str());
+        }
+        entry
+    }
+
+    /// The result of `prompts/get`.
+    pub(crate) fn get(&self, args: &Args) -> Result<Value, Error> {
+        let wrong: Vec<FieldError> = self
+            .arguments
+            .iter()
+            .filter_map(|argument| {
+                let problem = match args.get(&argument.name) {
+                    None if argument.required => "missing",
+                    Some(value) if !value.is_string() => "expected string",
+                    _ => return None,
+                };
+                Some(FieldError {
+                    field: argument.name.clone(),
+                    problem: problem.to_string(),
+                })
+            })
+            .collect();
+        if !wrong.is_empty() {
+            return Err(Error::invalid_arguments(wrong));
+        }
+        let messages = (self.render)(args)?;
+        let mut result = serde_json::json!({ "messages": messages });
+        if let Some(description) = &self.description {
+            result["description"] = Value::from(description.as_str());
+        }
+        Ok(result)
+    }
+}
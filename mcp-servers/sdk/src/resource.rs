@@ -0,0 +1,68 @@
+//! Resources: documents a server exposes by URI for the client to read,
+//! as opposed to tools, which the model calls.
+
+use serde_json::Value;
+
+use crate::Error;
+
+type Reader = Box<dyn Fn() -> Result<String, Error>>;
+
+/// A resource: its URI, how it is listed and how to read it.
+pub struct Resource {
+    uri: String,
+    name: String,
+    description: Option<String>,
+    mime_type: Option<String>,
+    read: Reader,
+}
+
+impl Resource {
+    /// A text resource, read by calling `read` each time it is requested.
+    pub fn new<F>(uri: impl Into<String>, name: impl Into<String>, read: F) -> Self
+    where
+        F: Fn() -> Result<String, Error> + 'static,
+    {
+        Self {
+            uri: uri.into(),
+            name: name.into(),
+            description: None,
+            mime_type: None,
+            read: Box::new(read),
+        }
+    }
+
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    pub fn mime_type(mut self, mime_type: impl Into<String>) -> Self {
+        self.mime_type = Some(mime_type.into());
+        self
+    }
+
+    pub fn uri(&self) -> &str {
+        &self.uri
+    }
+
+    /// The entry in `resources/list`.
+    pub(crate) fn entry(&self) -> Value {
+        let mut entry = serde_json::json!({ "uri": self.uri, "name": self.name });
+        if let Some(description) = &self.description {
+            entry["description"] = Value::from(description.as_str());
+        }
+        if let Some(mime_type) = &self.mime_type {
+            entry["mimeType"] = Value::from(mime_type.as_str());
+        }
+        entry
+    }
+
+    /// The result of `resources/read`.
+    pub(crate) fn read(&self) -> Result<Value, Error> {
+        let mut contents = serde_json::json!({ "uri": self.uri, "text": (self.read)()? });
+        if let Some(mime_type) = &self.mime_type {
+            contents["mimeType"] = Value::from(mime_type.as_str());
+        }
+        Ok(serde_json::json!({ "contents": [contents] }))
+    }
+}
@@ -0,0 +1,216 @@
+//! Checking tool arguments against a tool's input schema.
+//!
+//! Covers the parts of JSON Schema that tool schemas use: `type`,
+//! `properties`, `required`, `additionalProperties`, `items`, `enum`,
+//! `minimum`/`maximum` and `minLength`/`maxLength`. Other keywords are
+//! accepted without being checked.
+
+use serde::Serialize;
+use serde_json::Value;
+
+/// An argument that doesn't match the schema.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FieldError {
+    /// Path to the argument, e.g. `name` or `items[2].id`; empty for the
+    /// arguments object itself
+    pub field: String,
+    pub problem: String,
+}
+
+/// Every way `value` breaks `schema`; empty if it matches.
+pub fn validate(schema: &Value, value: &Value) -> Vec<FieldError> {
+    let mut errors = Vec::new();
+    check(schema, value, "", &mut errors);
+    errors
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_i64() || n.is_u64() => "integer",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn is_type(value: &Value, ty: &str) -> bool {
+    match ty {
+        // Integers are numbers. A float isn't an integer even with no
+        // fraction, since serde won't read 2.0 into an integer field.
+        "number" => value.is_number(),
+        ty => type_name(value) == ty,
+    }
+}
+
+fn join(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
+
+fn check(schema: &Value, value: &Value, path: &str, errors: &mut Vec<FieldError>) {
+    let Some(schema) = schema.as_object() else {
+        return;
+    };
+    let mut fail = |problem: String| {
+        errors.push(FieldError {
+            field: path.to_string(),
+            problem,
+        })
+    };
+
+    let types: Vec<&str> = match schema.get("type") {
+        Some(Value::String(ty)) => vec![ty.as_str()],
+        Some(Value::Array(types)) => types.iter().filter_map(Value::as_str).collect(),
+        _ => Vec::new(),
+    };
+    if !types.is_empty() && !types.iter().any(|ty| is_type(value, ty)) {
+        fail(format!("expected {}, got {}", types.join(" or "), type_name(value)));
+        return;
+    }
+    if let Some(allowed) = schema.get("enum").and_then(Value::as_array) {
+        if !allowed.contains(value) {
+            let allowed: Vec<String> = allowed.iter().map(Value::to_string).collect();
+            fail(format!("must be one of {}", allowed.join(", ")));
+        }
+    }
+
+    match value {
+        Value::Number(n) => {
+            let n = n.as_f64().unwrap_or_default();
+            if let Some(min) = schema.get("minimum").and_then(Value::as_f64) {
+                if n < min {
+                    fail(format!("must be at least {}", min));
+                }
+            }
+            if let Some(max) = schema.get("maximum").and_then(Value::as_f64) {
+                if n > max {
+                    fail(format!("must be at most {}", max));
+                }
+            }
+        }
+        Value::String(s) => {
+            let len = s.chars().count() as u64;
+            if let Some(min) = schema.get("minLength").and_then(Value::as_u64) {
+                if len < min {
+                    fail(format!("must be at least {} characters", min));
+                }
+            }
+            if let Some(max) = schema.get("maxLength").and_then(Value::as_u64) {
+                if len > max {
+                    fail(format!("must be at most {} characters", max));
+                }
+            }
+        }
+        Value::Array(items) => {
+            if let Some(item_schema) = schema.get("items") {
+                for (i, item) in items.iter().enumerate() {
+                    check(item_schema, item, &format!("{}[{}]", path, i), errors);
+                }
+            }
+        }
+        Value::Object(map) => {
+            if let Some(required) = schema.get("required").and_then(Value::as_array) {
+                for key in required.iter().filter_map(Value::as_str) {
+                    if !map.contains_key(key) {
+                        errors.push(FieldError {
+                            field: join(path, key),
+                            problem: "missing".to_string(),
+                        });
+                    }
+                }
+            }
+            let properties = schema.get("properties").and_then(Value::as_object);
+            for (key, field) in map {
+                match properties.and_then(|properties| properties.get(key)) {
+                    Some(field_schema) => check(field_schema, field, &join(path, key), errors),
+                    None => match schema.get("additionalProperties") {
+                        Some(Value::Bool(false)) => errors.push(FieldError {
+                            field: join(path, key),
+                            problem: "unknown field".to_string(),
+                        }),
+                        Some(extra) if extra.is_object() => check(extra, field, &join(path, key), errors),
+                        _ => {}
+                    },
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn fields(errors: &[FieldError]) -> Vec<(&str, &str)> {
+        errors
+            .iter()
+            .map(|e| (e.field.as_str(), e.problem.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn test_objects() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "name": { "type": "string", "minLength": 1 },
+                "count": { "type": "integer", "minimum": 0 },
+                "mode": { "enum": ["fast", "slow"] }
+            },
+            "required": ["name", "count"],
+            "additionalProperties": false
+        });
+        assert!(validate(&schema, &json!({ "name": "a", "count": 2 })).is_empty());
+        assert_eq!(
+            fields(&validate(&schema, &json!({ "name": "a", "count": 2.0 }))),
+            vec![("count", "expected integer, got number")]
+        );
+
+        let errors = validate(&schema, &json!({ "count": "2", "mode": "medium", "extra": true }));
+        assert_eq!(
+            fields(&errors),
+            vec![
+                ("name", "missing"),
+                ("count", "expected integer, got string"),
+                ("extra", "unknown field"),
+                ("mode", "must be one of \"fast\", \"slow\""),
+            ]
+        );
+
+        let errors = validate(&schema, &json!({ "name": "", "count": -1 }));
+        assert_eq!(
+            fields(&errors),
+            vec![("count", "must be at least 0"), ("name", "must be at least 1 characters")]
+        );
+
+        let errors = validate(&schema, &json!([1]));
+        assert_eq!(fields(&errors), vec![("", "expected object, got array")]);
+    }
+
+    #[test]
+    fn test_nested() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "points": {
+                    "type": "array",
+                    "items": { "type": "object", "properties": { "x": { "type": "number" } }, "required": ["x"] }
+                },
+                "note": { "type": ["string", "null"] }
+            }
+        });
+        let errors = validate(&schema, &json!({ "points": [{ "x": 1 }, {}, { "x": "1" }], "note": null }));
+        assert_eq!(
+            fields(&errors),
+            vec![("points[1].x", "missing"), ("points[2].x", "expected number, got string")]
+        );
+    }
+}
@@ -0,0 +1,137 @@
+//! `#[derive(ToolInput)]` and `#[harbor_tool]`, used the way a server uses
+//! them, from outside the SDK.
+
+#![cfg(feature = "macros")]
+
+use std::collections::BTreeMap;
+
+use harbor_mcp_sdk::{harbor_tool, Error, Server, ToolInput};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+/// Where to send it
+#[derive(Deserialize, ToolInput)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct SendArgs {
+    /// Who gets the message
+    recipient_name: String,
+    /// How many times; once if not given
+    repeat: Option<u32>,
+    #[serde(rename = "body")]
+    text: String,
+    #[serde(default)]
+    urgent: bool,
+    #[serde(skip)]
+    #[allow(dead_code)]
+    attempt: u32,
+    tags: Vec<String>,
+    headers: BTreeMap<String, String>,
+    place: Option<Place>,
+}
+
+#[derive(Deserialize, ToolInput)]
+struct Place {
+    /// Degrees north
+    lat: f64,
+    lon: f64,
+}
+
+/// Send a message
+#[harbor_tool]
+fn send(args: SendArgs) -> Result<String, Error> {
+    let repeat = args.repeat.unwrap_or(1);
+    Ok(format!(
+        "{} x{} to {}: {}",
+        if args.urgent { "urgent" } else { "sent" },
+        repeat,
+        args.recipient_name,
+        args.text
+    ))
+}
+
+/// Not this description
+#[harbor_tool(name = "where", description = "Say where a place is")]
+fn locate(args: Place) -> Result<String, Error> {
+    Ok(format!("{}, {}", args.lat, args.lon))
+}
+
+fn call(server: &Server, name: &str, arguments: Value) -> Value {
+    let message = json!({ "id": 1, "method": "tools/call", "params": { "name": name, "arguments": arguments } });
+    serde_json::from_str(&server.handle(&message.to_string())).unwrap()
+}
+
+#[test]
+fn test_derived_schema() {
+    let schema = SendArgs::schema();
+    assert_eq!(schema["description"], "Where to send it");
+    assert_eq!(schema["additionalProperties"], false);
+    assert_eq!(schema["required"], json!(["recipientName", "body", "tags", "headers"]));
+
+    let properties = &schema["properties"];
+    assert_eq!(
+        properties["recipientName"],
+        json!({ "type": "string", "description": "Who gets the message" })
+    );
+    assert_eq!(
+        properties["repeat"],
+        json!({ "type": "integer", "minimum": 0, "description": "How many times; once if not given" })
+    );
+    assert_eq!(properties["body"], json!({ "type": "string" }));
+    assert_eq!(properties["urgent"], json!({ "type": "boolean" }));
+    assert_eq!(
+        properties["tags"],
+        json!({ "type": "array", "items": { "type": "string" } })
+    );
+    assert_eq!(
+        properties["headers"],
+        json!({ "type": "object", "additionalProperties": { "type": "string" } })
+    );
+    assert_eq!(properties["place"]["properties"]["lat"]["description"], "Degrees north");
+    assert_eq!(properties["place"]["required"], json!(["lat", "lon"]));
+    assert!(properties.get("text").is_none());
+    assert!(properties.get("attempt").is_none());
+}
+
+#[test]
+fn test_tools() {
+    let server = Server::new("test", "1.0.0")
+        .register(send_tool())
+        .register(locate_tool());
+    let response: Value =
+        serde_json::from_str(&server.handle(r#"{"jsonrpc":"2.0","id":1,"method":"tools/list"}"#)).unwrap();
+    let tools = response["result"]["tools"].as_array().unwrap();
+    assert_eq!(tools[0]["name"], "send");
+    assert_eq!(tools[0]["description"], "Send a message");
+    assert_eq!(tools[1]["name"], "where");
+    assert_eq!(tools[1]["description"], "Say where a place is");
+
+    let arguments = json!({ "recipientName": "Ada", "body": "hi", "tags": [], "headers": {} });
+    let response = call(&server, "send", arguments);
+    assert_eq!(response["result"]["content"][0]["text"], "sent x1 to Ada: hi");
+
+    let arguments =
+        json!({ "recipientName": "Ada", "body": "hi", "repeat": 2, "urgent": true, "tags": [], "headers": {} });
+    let response = call(&server, "send", arguments);
+    assert_eq!(response["result"]["content"][0]["text"], "urgent x2 to Ada: hi");
+
+    // Field names as serde reads them, not as they're spelled in Rust
+    let arguments = json!({ "recipient_name": "Ada", "text": "hi", "tags": [], "headers": {} });
+    let response = call(&server, "send", arguments);
+    assert_eq!(response["error"]["code"], Error::INVALID_PARAMS);
+    let fields: Vec<&str> = response["error"]["data"]["fields"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter_map(|field| field["field"].as_str())
+        .collect();
+    assert!(fields.contains(&"recipientName") && fields.contains(&"body"));
+    assert!(fields.contains(&"recipient_name") && fields.contains(&"text"));
+
+    // Unsigned fields take no negative numbers
+    let arguments = json!({ "recipientName": "Ada", "body": "hi", "repeat": -1, "tags": [], "headers": {} });
+    let response = call(&server, "send", arguments);
+    assert_eq!(response["error"]["code"], Error::INVALID_PARAMS);
+
+    let response = call(&server, "where", json!({ "lat": 51.5, "lon": -0.1 }));
+    assert_eq!(response["result"]["content"][0]["text"], "51.5, -0.1");
+}
@@ -1,227 +1,105 @@
 //! My MCP Server - WASM Template
 //!
 //! This is a starter template for building WASM MCP servers in Rust.
-//! Customize the tools and handlers below for your use case.
+//! Customize the tools and handlers below for your use case; the SDK takes
+//! care of JSON-RPC, `initialize`, `tools/list`, `resources/*`, `prompts/*`
+//! and argument parsing.
 //!
 //! Build with:
 //!   cargo build --release --target wasm32-wasip1
 
-use serde::{Deserialize, Serialize};
-use std::io::{self, BufRead, Write};
+use harbor_mcp_sdk::{harbor_tool, log, Error, Level, Message, Prompt, Resource, Server, ToolInput};
+use serde::Deserialize;
 
 // ============================================================================
-// JSON-RPC Types
+// Tool Handlers
 // ============================================================================
 
-#[derive(Debug, Deserialize)]
-struct RpcRequest {
-    id: serde_json::Value,
-    method: String,
-    #[serde(default)]
-    params: serde_json::Value,
-}
-
-#[derive(Debug, Serialize)]
-struct RpcResponse {
-    jsonrpc: &'static str,
-    id: serde_json::Value,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    result: Option<serde_json::Value>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    error: Option<RpcError>,
-}
-
-#[derive(Debug, Serialize)]
-struct RpcError {
-    code: i64,
-    message: String,
+// Each tool takes a struct of its arguments. `ToolInput` derives the input
+// schema from the fields and their doc comments, and calls whose arguments
+// don't match it are rejected with the wrong fields listed, so handlers
+// never see a missing or mistyped argument. Use `Option` (or
+// `#[serde(default)]`) only for arguments that really are optional.
+
+#[derive(Deserialize, ToolInput)]
+#[serde(deny_unknown_fields)]
+struct GreetArgs {
+    /// Name of the person to greet
+    name: String,
 }
 
-// ============================================================================
-// Tool Definitions
-// ============================================================================
-
-fn get_tools() -> serde_json::Value {
-    serde_json::json!({
-        "tools": [
-            {
-                "name": "greet",
-                "description": "Say hello to someone",
-                "inputSchema": {
-                    "type": "object",
-                    "properties": {
-                        "name": {
-                            "type": "string",
-                            "description": "Name of the person to greet"
-                        }
-                    },
-                    "required": ["name"]
-                }
-            },
-            {
-                "name": "add",
-                "description": "Add two numbers together",
-                "inputSchema": {
-                    "type": "object",
-                    "properties": {
-                        "a": { "type": "number", "description": "First number" },
-                        "b": { "type": "number", "description": "Second number" }
-                    },
-                    "required": ["a", "b"]
-                }
-            }
-        ]
-    })
+/// Say hello to someone
+#[harbor_tool]
+fn greet(args: GreetArgs) -> Result<String, Error> {
+    Ok(format!("Hello, {}!", args.name))
 }
 
-// ============================================================================
-// Tool Handlers
-// ============================================================================
-
-fn handle_greet(params: &serde_json::Value) -> String {
-    let name = params
-        .get("arguments")
-        .and_then(|a| a.get("name"))
-        .and_then(|n| n.as_str())
-        .unwrap_or("World");
-    
-    format!("Hello, {}!", name)
+#[derive(Deserialize, ToolInput)]
+#[serde(deny_unknown_fields)]
+struct AddArgs {
+    /// First number
+    a: f64,
+    /// Second number
+    b: f64,
 }
 
-fn handle_add(params: &serde_json::Value) -> String {
-    let args = params.get("arguments");
-    
-    let a = args
-        .and_then(|a| a.get("a"))
-        .and_then(|v| v.as_f64())
-        .unwrap_or(0.0);
-    
-    let b = args
-        .and_then(|a| a.get("b"))
-        .and_then(|v| v.as_f64())
-        .unwrap_or(0.0);
-    
-    let result = a + b;
-    format!("{} + {} = {}", a, b, result)
+/// Add two numbers together
+#[harbor_tool]
+fn add(args: AddArgs) -> Result<String, Error> {
+    // Log notifications go to the client ahead of the result. Call
+    // `harbor_mcp_sdk::tools_changed()` the same way when the tool list
+    // changes, and the client will ask for it again.
+    log(Level::Debug, format!("adding {} and {}", args.a, args.b));
+    Ok(format!("{} + {} = {}", args.a, args.b, args.a + args.b))
 }
 
 // ============================================================================
-// Response Writers
+// Resources
 // ============================================================================
 
-fn write_response(response: &RpcResponse) {
-    let mut out = io::stdout().lock();
-    if let Ok(json) = serde_json::to_string(response) {
-        let _ = out.write_all(json.as_bytes());
-        let _ = out.write_all(b"\n");
-        let _ = out.flush();
-    }
-}
+// Resources are documents the client can read by URI. This one is the file
+// `resources/usage.md`, built into the module: WASI preview 1 servers run
+// in the browser without a filesystem. A component with a `filesystem`
+// grant in its harbor.toml can read files with `std::fs` instead.
 
-fn write_result(id: serde_json::Value, result: serde_json::Value) {
-    write_response(&RpcResponse {
-        jsonrpc: "2.0",
-        id,
-        result: Some(result),
-        error: None,
-    });
-}
-
-fn write_tool_result(id: serde_json::Value, text: &str) {
-    let result = serde_json::json!({
-        "content": [{ "type": "text", "text": text }]
-    });
-    write_result(id, result);
-}
+const USAGE: &str = include_str!("../resources/usage.md");
 
-fn write_error(id: serde_json::Value, code: i64, message: &str) {
-    write_response(&RpcResponse {
-        jsonrpc: "2.0",
-        id,
-        result: None,
-        error: Some(RpcError {
-            code,
-            message: message.to_string(),
-        }),
-    });
+fn usage() -> Resource {
+    Resource::new("file:///usage.md", "Usage", || Ok(USAGE.to_string()))
+        .description("What this server's tools do")
+        .mime_type("text/markdown")
 }
 
 // ============================================================================
-// Request Handling
+// Prompts
 // ============================================================================
 
-fn handle_request(request: RpcRequest) {
-    match request.method.as_str() {
-        // Initialize (required by MCP)
-        "initialize" => {
-            let result = serde_json::json!({
-                "protocolVersion": "2024-11-05",
-                "capabilities": { "tools": {} },
-                "serverInfo": { "name": "my-wasm-server", "version": "1.0.0" }
-            });
-            write_result(request.id, result);
-        }
-
-        // List available tools
-        "tools/list" => {
-            write_result(request.id, get_tools());
-        }
-
-        // Execute a tool
-        "tools/call" => {
-            let tool_name = request.params
-                .get("name")
-                .and_then(|n| n.as_str())
-                .unwrap_or("");
-            
-            match tool_name {
-                "greet" => {
-                    let result = handle_greet(&request.params);
-                    write_tool_result(request.id, &result);
-                }
-                "add" => {
-                    let result = handle_add(&request.params);
-                    write_tool_result(request.id, &result);
-                }
-                _ => {
-                    write_error(
-                        request.id,
-                        -32601,
-                        &format!("Unknown tool: {}", tool_name),
-                    );
-                }
-            }
-        }
-
-        // Unknown method
-        _ => {
-            write_error(
-                request.id,
-                -32601,
-                &format!("Method not found: {}", request.method),
-            );
-        }
-    }
+// Prompts are message templates the user picks, filled in from string
+// arguments. Declared arguments that are required are checked by the SDK.
+
+fn explain() -> Prompt {
+    Prompt::new("explain", |args| {
+        let topic = args.str("topic")?;
+        let text = match args.opt_str("audience") {
+            Some(audience) => format!("Explain {} to {}.", topic, audience),
+            None => format!("Explain {}.", topic),
+        };
+        Ok(vec![Message::user(text)])
+    })
+    .description("Ask for an explanation of a topic")
+    .argument("topic", "What to explain", true)
+    .argument("audience", "Who the explanation is for", false)
 }
 
 // ============================================================================
-// Main Loop
+// Main
 // ============================================================================
 
 fn main() {
-    let stdin = io::stdin();
-    
-    for line in stdin.lock().lines() {
-        let Ok(raw) = line else { continue };
-        if raw.trim().is_empty() {
-            continue;
-        }
-        
-        match serde_json::from_str::<RpcRequest>(&raw) {
-            Ok(request) => handle_request(request),
-            Err(_) => {
-                write_error(serde_json::Value::Null, -32700, "Parse error");
-            }
-        }
-    }
+    Server::new("my-wasm-server", "1.0.0")
+        .register(greet_tool())
+        .register(add_tool())
+        .resource(usage())
+        .prompt(explain())
+        .run();
 }
@@ -0,0 +1,42 @@
+//! Runs the built module in-process with harbor-test, the way Harbor does.
+//! `cargo test` builds it for wasm32-wasip1 first.
+
+use std::sync::OnceLock;
+
+use harbor_test::{Error, TestServer};
+use serde_json::json;
+
+fn server() -> &'static TestServer {
+    static SERVER: OnceLock<TestServer> = OnceLock::new();
+    SERVER.get_or_init(|| TestServer::build("my_mcp_server").expect("server builds and loads"))
+}
+
+#[test]
+fn lists_tools() {
+    let mut names = server().tool_names().unwrap();
+    names.sort();
+    assert_eq!(names, ["add", "greet"]);
+}
+
+#[test]
+fn greets() {
+    server().call_tool("greet", json!({ "name": "Ada" })).unwrap().assert_text("Hello, Ada!");
+}
+
+#[test]
+fn adds_and_logs() {
+    let result = server().call_tool("add", json!({ "a": 2, "b": 3 })).unwrap();
+    result.assert_text("2 + 3 = 5");
+    assert_eq!(result.logs(), [&json!("adding 2 and 3")]);
+}
+
+#[test]
+fn rejects_bad_arguments() {
+    match server().call_tool("add", json!({ "a": "2" })) {
+        Err(Error::Rpc { code, data, .. }) => {
+            assert_eq!(code, -32602);
+            assert_eq!(data.unwrap()["fields"].as_array().unwrap().len(), 2);
+        }
+        other => panic!("expected invalid arguments, got {:?}", other),
+    }
+}
@@ -254,6 +254,23 @@ async function sendMessageToExtension(
   return Promise.race([messagePromise, timeoutPromise]);
 }
 
+let safariToken: string | null = null;
+
+/**
+ * Safari: The token harbor-bridge wants on /rpc from the extension, kept
+ * in ~/.harbor/extension-token. The extension can't read the file, so it
+ * asks the containing app, Harbor.app.
+ */
+async function safariBridgeToken(): Promise<string> {
+  if (safariToken) return safariToken;
+  const reply = (await browser!.runtime.sendNativeMessage('org.harbor', { type: 'extension_token' })) as
+    | { token?: string }
+    | undefined;
+  if (!reply?.token) throw new Error('Harbor.app did not give the bridge token');
+  safariToken = reply.token;
+  return safariToken;
+}
+
 /**
  * Safari: Send RPC request via HTTP to harbor-bridge.
  */
@@ -263,11 +280,13 @@ async function safariHttpRequest<T>(method: string, params: unknown = {}): Promi
   
   const response = await fetch(`${SAFARI_HTTP_BASE}/rpc`, {
     method: 'POST',
-    headers: { 'Content-Type': 'application/json' },
+    headers: { 'Content-Type': 'application/json', Authorization: `Bearer ${await safariBridgeToken()}` },
     body: JSON.stringify({ id, method, params }),
   });
   
   if (!response.ok) {
+    // A bridge started afresh may have made a new token
+    if (response.status === 401) safariToken = null;
     throw new Error(`HTTP ${response.status}: ${response.statusText}`);
   }
   